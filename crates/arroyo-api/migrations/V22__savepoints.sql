@@ -0,0 +1,29 @@
+CREATE TYPE savepoint_state as ENUM ('pending', 'ready', 'failed');
+
+CREATE TABLE savepoints (
+    id BIGSERIAL PRIMARY KEY,
+    pub_id VARCHAR NOT NULL UNIQUE,
+    organization_id VARCHAR NOT NULL,
+    created_by VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    finish_time TIMESTAMPTZ,
+
+    name TEXT NOT NULL,
+
+    -- savepoints outlive the job they were taken from, so this is not a foreign key
+    job_id VARCHAR NOT NULL,
+    pipeline_name TEXT NOT NULL,
+
+    state savepoint_state NOT NULL DEFAULT 'pending',
+    epoch INT,
+    operators JSONB DEFAULT '{}' NOT NULL,
+    failure_message TEXT,
+
+    UNIQUE(organization_id, name)
+);
+
+CREATE INDEX savepoints_job_id_idx ON savepoints (job_id);
+
+ALTER TABLE job_configs
+ADD COLUMN restore_savepoint_id BIGINT REFERENCES savepoints(id),
+ADD COLUMN allow_dropped_state BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,17 @@
+CREATE TYPE user_role as ENUM ('viewer', 'operator', 'admin');
+
+-- api keys are now stored as sha-256 hashes rather than in plaintext
+ALTER TABLE api_keys
+RENAME COLUMN api_key TO key_hash;
+
+UPDATE api_keys
+SET key_hash = encode(sha256(key_hash::bytea), 'hex');
+
+-- existing keys had full access to the api
+ALTER TABLE api_keys
+ADD COLUMN role user_role NOT NULL DEFAULT 'admin';
+
+ALTER TABLE api_keys
+ALTER COLUMN role DROP DEFAULT;
+
+CREATE UNIQUE INDEX api_keys_key_hash_idx ON api_keys (key_hash);
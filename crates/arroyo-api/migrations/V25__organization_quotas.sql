@@ -0,0 +1,10 @@
+-- quotas enforced on each organization's pipelines, as a JSON object with any of the fields
+-- can_create_programs, max_nexmark_qps, max_impulse_qps, max_parallelism, max_operators,
+-- max_running_jobs, and kafka_qps; fields that are not set take their default values, and
+-- organizations without a row here are unlimited
+CREATE TABLE organization_quotas (
+    organization_id VARCHAR PRIMARY KEY,
+    quotas JSONB DEFAULT '{}' NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
@@ -0,0 +1,12 @@
+-- secrets that connection configs can reference as {{ secret:name }}; values are encrypted with
+-- the SECRETS_KEY master key and are only decrypted by workers when constructing operators
+CREATE TABLE secrets (
+    pub_id VARCHAR PRIMARY KEY,
+    organization_id VARCHAR NOT NULL,
+    created_by VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    name TEXT NOT NULL,
+    encrypted_value BYTEA NOT NULL,
+
+    UNIQUE(organization_id, name)
+);
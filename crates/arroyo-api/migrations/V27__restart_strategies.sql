@@ -0,0 +1,5 @@
+ALTER TABLE job_configs
+ADD COLUMN restart_strategy JSONB;
+
+ALTER TABLE job_statuses
+ADD COLUMN next_retry_time TIMESTAMPTZ;
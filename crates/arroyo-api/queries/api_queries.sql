@@ -1,8 +1,45 @@
 ----------- api keys -------------------
 --! get_api_key
-SELECT user_id, organization_id
+SELECT user_id, organization_id, role
 FROM api_keys
-WHERE api_key = :api_key;
+WHERE key_hash = :key_hash;
+
+--! create_api_key
+INSERT INTO api_keys (pub_id, user_id, organization_id, created_by, name, role, key_hash)
+VALUES (:pub_id, :user_id, :organization_id, :created_by, :name, :role, :key_hash)
+RETURNING created_at;
+
+--! get_api_keys
+SELECT pub_id, name, role, created_by, created_at
+FROM api_keys
+WHERE organization_id = :organization_id
+ORDER BY created_at DESC;
+
+--! delete_api_key
+DELETE FROM api_keys
+WHERE organization_id = :organization_id AND pub_id = :pub_id;
+
+----------- organizations -------------------
+--! get_organization_quotas
+SELECT quotas
+FROM organization_quotas
+WHERE organization_id = :organization_id;
+
+----------- secrets -------------------
+--! create_secret
+INSERT INTO secrets (pub_id, organization_id, created_by, name, encrypted_value)
+VALUES (:pub_id, :organization_id, :created_by, :name, :encrypted_value)
+RETURNING created_at;
+
+--! get_secrets
+SELECT pub_id, name, created_by, created_at
+FROM secrets
+WHERE organization_id = :organization_id
+ORDER BY name;
+
+--! delete_secret
+DELETE FROM secrets
+WHERE organization_id = :organization_id AND pub_id = :pub_id;
 
 ----------- connection profiles ----------------
 --! create_connection_profile
@@ -128,7 +165,7 @@ WHERE organization_id = :organization_id AND pub_id = :pub_id;
 
 ----------- pipelines -------------------
 
---: DbPipeline (state?, ttl_micros?)
+--: DbPipeline (state?, ttl_micros?, autoscaling?, restart_strategy?)
 
 --! create_pipeline(udfs?, textual_repr?)
 INSERT INTO pipelines (pub_id, organization_id, created_by, name, type, textual_repr, udfs, program, proto_version)
@@ -136,7 +173,7 @@ VALUES (:pub_id, :organization_id, :created_by, :name, :type, :textual_repr, :ud
 RETURNING id;
 
 --! get_pipelines : DbPipeline
-SELECT pipelines.pub_id, name, type, textual_repr, udfs, program, checkpoint_interval_micros, stop, pipelines.created_at, state, parallelism_overrides, ttl_micros
+SELECT pipelines.pub_id, name, type, textual_repr, udfs, program, checkpoint_interval_micros, stop, pipelines.created_at, state, parallelism_overrides, ttl_micros, autoscaling, restart_strategy
 FROM pipelines
     INNER JOIN job_configs on pipelines.id = job_configs.pipeline_id
     LEFT JOIN job_statuses ON job_configs.id = job_statuses.id
@@ -151,7 +188,7 @@ ORDER BY pipelines.created_at DESC
 LIMIT :limit::integer;
 
 --! get_pipeline: DbPipeline
-SELECT pipelines.pub_id, name, type, textual_repr, udfs, program, checkpoint_interval_micros, stop, pipelines.created_at, state, parallelism_overrides, ttl_micros
+SELECT pipelines.pub_id, name, type, textual_repr, udfs, program, checkpoint_interval_micros, stop, pipelines.created_at, state, parallelism_overrides, ttl_micros, autoscaling, restart_strategy
 FROM pipelines
     INNER JOIN job_configs on pipelines.id = job_configs.pipeline_id
     LEFT JOIN job_statuses ON job_configs.id = job_statuses.id
@@ -168,7 +205,7 @@ WHERE pub_id = :pub_id AND organization_id = :organization_id;
 
 ----------- jobs -----------------------
 
---! update_job(checkpoint_interval_micros?, stop?, parallelism_overrides?)
+--! update_job(checkpoint_interval_micros?, stop?, parallelism_overrides?, autoscaling?, restart_strategy?)
 UPDATE job_configs
 SET
    updated_at = :updated_at,
@@ -176,7 +213,9 @@ SET
 
    stop = COALESCE(:stop, stop),
    checkpoint_interval_micros = COALESCE(:checkpoint_interval_micros, checkpoint_interval_micros),
-   parallelism_overrides = COALESCE(:parallelism_overrides, parallelism_overrides)
+   parallelism_overrides = COALESCE(:parallelism_overrides, parallelism_overrides),
+   autoscaling = COALESCE(:autoscaling, autoscaling),
+   restart_strategy = COALESCE(:restart_strategy, restart_strategy)
 WHERE id = :job_id AND organization_id = :organization_id;
 
 --! restart_job(mode)
@@ -188,10 +227,10 @@ SET
    restart_mode = :mode
 WHERE id = :job_id AND organization_id = :organization_id;
 
---! create_job(ttl_micros?)
+--! create_job(ttl_micros?, restore_savepoint_id?, restart_strategy?)
 INSERT INTO job_configs
-(id, organization_id, pipeline_name, created_by, pipeline_id, checkpoint_interval_micros, ttl_micros)
-VALUES (:id, :organization_id, :pipeline_name, :created_by, :pipeline_id, :checkpoint_interval_micros, :ttl_micros);
+(id, organization_id, pipeline_name, created_by, pipeline_id, checkpoint_interval_micros, ttl_micros, restore_savepoint_id, allow_dropped_state, parallelism_overrides, restart_strategy)
+VALUES (:id, :organization_id, :pipeline_name, :created_by, :pipeline_id, :checkpoint_interval_micros, :ttl_micros, :restore_savepoint_id, :allow_dropped_state, :parallelism_overrides, :restart_strategy);
 
 --! create_job_status
 INSERT INTO job_statuses (pub_id, id, organization_id) VALUES (:pub_id, :id, :organization_id);
@@ -204,24 +243,24 @@ FROM job_configs
 WHERE job_configs.organization_id = :organization_id AND ttl_micros IS NULL
 ORDER BY COALESCE(job_configs.updated_at, job_configs.created_at) DESC;
 
---! get_pipeline_jobs : DbPipelineJob(start_time?, finish_time?, state?, tasks?, failure_message?, run_id?)
-SELECT job_configs.id, stop, start_time, finish_time, state, tasks, failure_message, run_id, checkpoint_interval_micros, job_configs.created_at
+--! get_pipeline_jobs : DbPipelineJob(start_time?, finish_time?, state?, tasks?, failure_message?, run_id?, next_retry_time?)
+SELECT job_configs.id, stop, start_time, finish_time, state, tasks, failure_message, run_id, checkpoint_interval_micros, job_configs.created_at, next_retry_time
 FROM job_configs
          LEFT JOIN job_statuses ON job_configs.id = job_statuses.id
          INNER JOIN pipelines ON pipelines.id = job_configs.pipeline_id
 WHERE job_configs.organization_id = :organization_id AND pipelines.pub_id = :pub_id
 ORDER BY job_configs.created_at DESC;
 
---! get_all_jobs : DbPipelineJob(start_time?, finish_time?, state?, tasks?, failure_message?, run_id?)
-SELECT job_configs.id, stop, start_time, finish_time, state, tasks, failure_message, run_id, checkpoint_interval_micros, job_configs.created_at
+--! get_all_jobs : DbPipelineJob(start_time?, finish_time?, state?, tasks?, failure_message?, run_id?, next_retry_time?)
+SELECT job_configs.id, stop, start_time, finish_time, state, tasks, failure_message, run_id, checkpoint_interval_micros, job_configs.created_at, next_retry_time
 FROM job_configs
          LEFT JOIN job_statuses ON job_configs.id = job_statuses.id
          INNER JOIN pipelines ON pipelines.id = job_configs.pipeline_id
 WHERE job_configs.organization_id = :organization_id AND ttl_micros IS NULL
 ORDER BY job_configs.created_at DESC;
 
---! get_pipeline_job : DbPipelineJob(start_time?, finish_time?, state?, tasks?, failure_message?, run_id?)
-SELECT job_configs.id, stop, start_time, finish_time, state, tasks, failure_message, run_id, checkpoint_interval_micros, job_configs.created_at
+--! get_pipeline_job : DbPipelineJob(start_time?, finish_time?, state?, tasks?, failure_message?, run_id?, next_retry_time?)
+SELECT job_configs.id, stop, start_time, finish_time, state, tasks, failure_message, run_id, checkpoint_interval_micros, job_configs.created_at, next_retry_time
 FROM job_configs
          LEFT JOIN job_statuses ON job_configs.id = job_statuses.id
          INNER JOIN pipelines ON pipelines.id = job_configs.pipeline_id
@@ -284,6 +323,31 @@ WHERE job_configs.organization_id = :organization_id AND job_configs.id = :job_i
 ORDER BY jlm.created_at DESC
 LIMIT :limit::integer;
 
+----------- savepoints -----------------
+
+--: DbSavepoint (epoch?, finish_time?, failure_message?)
+
+--! create_savepoint
+INSERT INTO savepoints (pub_id, organization_id, created_by, name, job_id, pipeline_name)
+VALUES (:pub_id, :organization_id, :created_by, :name, :job_id, :pipeline_name);
+
+--! get_savepoints: DbSavepoint
+SELECT id, pub_id, name, job_id, pipeline_name, state, epoch, operators, created_at, finish_time, failure_message
+FROM savepoints
+WHERE organization_id = :organization_id
+ORDER BY created_at DESC;
+
+--! get_job_savepoints: DbSavepoint
+SELECT id, pub_id, name, job_id, pipeline_name, state, epoch, operators, created_at, finish_time, failure_message
+FROM savepoints
+WHERE organization_id = :organization_id AND job_id = :job_id
+ORDER BY created_at DESC;
+
+--! get_savepoint: DbSavepoint
+SELECT id, pub_id, name, job_id, pipeline_name, state, epoch, operators, created_at, finish_time, failure_message
+FROM savepoints
+WHERE organization_id = :organization_id AND pub_id = :pub_id;
+
 ----------- udfs -----------------------
 
 --: DbUdf (description?)
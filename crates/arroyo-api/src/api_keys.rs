@@ -0,0 +1,168 @@
+use crate::cloud::{hash_api_key, API_KEY_PREFIX};
+use crate::queries::api_queries;
+use crate::queries::api_queries::GetApiKeys;
+use crate::rest::AppState;
+use crate::rest_utils::{bad_request, client, log_and_map, not_found, ApiError, ErrorResp};
+use crate::types::public::UserRole;
+use crate::{to_micros, AuthData};
+use arroyo_rpc::api_types::auth::{ApiKey, ApiKeyCreated, ApiKeyPost, Role};
+use arroyo_rpc::api_types::ApiKeyCollection;
+use arroyo_rpc::public_ids::{generate_id, IdTypes};
+use axum::extract::{Path, State};
+use axum::{Extension, Json};
+use axum_extra::extract::WithRejection;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::Rng;
+
+impl From<UserRole> for Role {
+    fn from(role: UserRole) -> Self {
+        match role {
+            UserRole::viewer => Role::Viewer,
+            UserRole::operator => Role::Operator,
+            UserRole::admin => Role::Admin,
+        }
+    }
+}
+
+impl From<Role> for UserRole {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Viewer => UserRole::viewer,
+            Role::Operator => UserRole::operator,
+            Role::Admin => UserRole::admin,
+        }
+    }
+}
+
+impl Into<ApiKey> for GetApiKeys {
+    fn into(self) -> ApiKey {
+        ApiKey {
+            id: self.pub_id,
+            name: self.name,
+            role: self.role.into(),
+            created_by: self.created_by,
+            created_at: to_micros(self.created_at),
+        }
+    }
+}
+
+/// Create an API key; the key itself is only returned in this response
+#[utoipa::path(
+    post,
+    path = "/v1/api_keys",
+    tag = "api_keys",
+    request_body = ApiKeyPost,
+    responses(
+        (status = 200, description = "Created API key", body = ApiKeyCreated),
+    ),
+)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+    WithRejection(Json(req), _): WithRejection<Json<ApiKeyPost>, ApiError>,
+) -> Result<Json<ApiKeyCreated>, ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    if req.name.is_empty() {
+        return Err(bad_request("API key name must not be empty"));
+    }
+
+    if req.role > auth_data.role {
+        return Err(bad_request(
+            "API keys cannot be granted a higher role than their creator",
+        ));
+    }
+
+    let key = format!(
+        "{}{}",
+        API_KEY_PREFIX,
+        URL_SAFE_NO_PAD.encode(rand::thread_rng().gen::<[u8; 32]>())
+    );
+
+    let pub_id = generate_id(IdTypes::ApiKey);
+
+    let created_at = api_queries::create_api_key()
+        .bind(
+            &client,
+            &pub_id,
+            &auth_data.user_id,
+            &auth_data.organization_id,
+            &auth_data.user_id,
+            &req.name,
+            &req.role.into(),
+            &hash_api_key(&key),
+        )
+        .one()
+        .await
+        .map_err(log_and_map)?;
+
+    Ok(Json(ApiKeyCreated {
+        api_key: ApiKey {
+            id: pub_id,
+            name: req.name,
+            role: req.role,
+            created_by: auth_data.user_id,
+            created_at: to_micros(created_at),
+        },
+        key,
+    }))
+}
+
+/// List API keys
+#[utoipa::path(
+    get,
+    path = "/v1/api_keys",
+    tag = "api_keys",
+    responses(
+        (status = 200, description = "Got API keys", body = ApiKeyCollection),
+    ),
+)]
+pub async fn get_api_keys(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+) -> Result<Json<ApiKeyCollection>, ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    let keys = api_queries::get_api_keys()
+        .bind(&client, &auth_data.organization_id)
+        .all()
+        .await
+        .map_err(log_and_map)?
+        .into_iter()
+        .map(|k| k.into())
+        .collect();
+
+    Ok(Json(ApiKeyCollection { data: keys }))
+}
+
+/// Delete an API key, which immediately stops it from being accepted
+#[utoipa::path(
+    delete,
+    path = "/v1/api_keys/{id}",
+    tag = "api_keys",
+    params(
+        ("id" = String, Path, description = "API key id")
+    ),
+    responses(
+        (status = 200, description = "Deleted API key"),
+    ),
+)]
+pub async fn delete_api_key(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+    Path(pub_id): Path<String>,
+) -> Result<(), ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    let count = api_queries::delete_api_key()
+        .bind(&client, &auth_data.organization_id, &pub_id)
+        .await
+        .map_err(log_and_map)?;
+
+    if count != 1 {
+        return Err(not_found("API key"));
+    }
+
+    Ok(())
+}
@@ -1,16 +1,112 @@
-use crate::{rest_utils::ErrorResp, AuthData, OrgMetadata};
-use axum::headers::authorization::{Authorization, Bearer};
+use crate::queries::api_queries;
+use crate::rest_utils::{log_and_map, unauthorized, BearerAuth, ErrorResp};
+use crate::{AuthData, OrgMetadata};
+use anyhow::{anyhow, bail};
+use arroyo_rpc::api_types::auth::Role;
+use arroyo_types::{
+    ADMIN_API_KEY_ENV, AUTH_MODE_ENV, OIDC_AUDIENCE_ENV, OIDC_ISSUER_ENV, OIDC_JWKS_URL_ENV,
+    OIDC_ROLE_CLAIM_ENV,
+};
 use axum::TypedHeader;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use cornucopia_async::GenericClient;
+use jwt_simple::prelude::*;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
 
-pub(crate) async fn authenticate(
-    _client: impl GenericClient,
-    _bearer_auth: Option<TypedHeader<Authorization<Bearer>>>,
-) -> Result<AuthData, ErrorResp> {
-    Ok(AuthData {
-        user_id: "user".to_string(),
-        organization_id: "org".to_string(),
-        role: "admin".to_string(),
+// api keys are distinguished from JWTs by this prefix
+pub(crate) const API_KEY_PREFIX: &str = "arroyo_";
+
+// the open-source api has a single organization
+const DEFAULT_ORGANIZATION: &str = "org";
+
+// how long fetched signing keys are used before they are refreshed
+const JWKS_TTL: Duration = Duration::from_secs(60 * 60);
+
+// signing keys are refetched when a token uses an unknown key, but at most this often
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AuthMode {
+    None,
+    ApiKeys,
+    Oidc,
+}
+
+impl Display for AuthMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthMode::None => write!(f, "none"),
+            AuthMode::ApiKeys => write!(f, "api_keys"),
+            AuthMode::Oidc => write!(f, "oidc"),
+        }
+    }
+}
+
+struct OidcConfig {
+    jwks_url: String,
+    issuer: Option<String>,
+    audience: Option<String>,
+    role_claim: String,
+}
+
+static AUTH_MODE: Lazy<AuthMode> =
+    Lazy::new(|| match std::env::var(AUTH_MODE_ENV).ok().as_deref() {
+        None | Some("none") => AuthMode::None,
+        Some("api_keys") => AuthMode::ApiKeys,
+        Some("oidc") => AuthMode::Oidc,
+        Some(other) => panic!(
+            "Invalid {}: '{}'; expected one of none, api_keys, or oidc",
+            AUTH_MODE_ENV, other
+        ),
+    });
+
+static OIDC_CONFIG: Lazy<OidcConfig> = Lazy::new(|| OidcConfig {
+    jwks_url: std::env::var(OIDC_JWKS_URL_ENV).unwrap_or_else(|_| {
+        panic!(
+            "{} must be set when {} is oidc",
+            OIDC_JWKS_URL_ENV, AUTH_MODE_ENV
+        )
+    }),
+    issuer: std::env::var(OIDC_ISSUER_ENV).ok(),
+    audience: std::env::var(OIDC_AUDIENCE_ENV).ok(),
+    role_claim: std::env::var(OIDC_ROLE_CLAIM_ENV).unwrap_or_else(|_| "role".to_string()),
+});
+
+static ADMIN_API_KEY_HASH: Lazy<Option<String>> = Lazy::new(|| {
+    std::env::var(ADMIN_API_KEY_ENV)
+        .ok()
+        .map(|k| hash_api_key(&k))
+});
+
+/// Returns the configured authentication mode, panicking if the auth configuration is invalid
+pub(crate) fn auth_mode() -> AuthMode {
+    if *AUTH_MODE == AuthMode::Oidc {
+        Lazy::force(&OIDC_CONFIG);
+    }
+    *AUTH_MODE
+}
+
+pub(crate) fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+fn auth_data(
+    user_id: impl Into<String>,
+    organization_id: impl Into<String>,
+    role: Role,
+) -> AuthData {
+    AuthData {
+        user_id: user_id.into(),
+        organization_id: organization_id.into(),
+        role,
         org_metadata: OrgMetadata {
             can_create_programs: true,
             max_nexmark_qps: f64::MAX,
@@ -20,5 +116,429 @@ pub(crate) async fn authenticate(
             max_running_jobs: u32::MAX,
             kafka_qps: u32::MAX,
         },
+    }
+}
+
+pub(crate) async fn authenticate(
+    client: impl GenericClient,
+    bearer_auth: BearerAuth,
+) -> Result<AuthData, ErrorResp> {
+    let mut auth_data = identify(&client, bearer_auth).await?;
+
+    // organizations without stored quotas are unlimited
+    let quotas = api_queries::get_organization_quotas()
+        .bind(&client, &auth_data.organization_id)
+        .opt()
+        .await
+        .map_err(log_and_map)?;
+
+    if let Some(quotas) = quotas {
+        auth_data.org_metadata = serde_json::from_value(quotas).map_err(log_and_map)?;
+    }
+
+    Ok(auth_data)
+}
+
+async fn identify(
+    client: &impl GenericClient,
+    bearer_auth: BearerAuth,
+) -> Result<AuthData, ErrorResp> {
+    let mode = auth_mode();
+
+    if mode == AuthMode::None {
+        return Ok(auth_data("user", DEFAULT_ORGANIZATION, Role::Admin));
+    }
+
+    let Some(TypedHeader(authorization)) = bearer_auth else {
+        return Err(unauthorized("A bearer token is required"));
+    };
+
+    let token = authorization.token();
+
+    match credential_type(mode, token) {
+        CredentialType::ApiKey => authenticate_api_key(client, token).await,
+        CredentialType::Jwt => authenticate_jwt(token).await,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CredentialType {
+    ApiKey,
+    Jwt,
+}
+
+/// Determines how a bearer token is verified; in oidc mode, api keys can still be used alongside
+/// JWTs, and are recognized by their prefix
+fn credential_type(mode: AuthMode, token: &str) -> CredentialType {
+    if mode == AuthMode::ApiKeys || token.starts_with(API_KEY_PREFIX) {
+        CredentialType::ApiKey
+    } else {
+        CredentialType::Jwt
+    }
+}
+
+// the admin key configured in the environment isn't stored, and belongs to the default
+// organization
+fn admin_key_auth(key_hash: &str, admin_key_hash: Option<&str>) -> Option<AuthData> {
+    (admin_key_hash == Some(key_hash))
+        .then(|| auth_data("admin", DEFAULT_ORGANIZATION, Role::Admin))
+}
+
+async fn authenticate_api_key(
+    client: &impl GenericClient,
+    key: &str,
+) -> Result<AuthData, ErrorResp> {
+    let key_hash = hash_api_key(key);
+
+    if let Some(auth_data) = admin_key_auth(&key_hash, ADMIN_API_KEY_HASH.as_deref()) {
+        return Ok(auth_data);
+    }
+
+    let key = api_queries::get_api_key()
+        .bind(client, &key_hash)
+        .opt()
+        .await
+        .map_err(log_and_map)?
+        .ok_or_else(|| unauthorized("Invalid API key"))?;
+
+    Ok(auth_data(key.user_id, key.organization_id, key.role.into()))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Jwk {
+    kid: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+struct CachedJwks {
+    fetched_at: Instant,
+    keys: Vec<Jwk>,
+}
+
+static JWKS_CACHE: Lazy<tokio::sync::Mutex<Option<CachedJwks>>> =
+    Lazy::new(|| tokio::sync::Mutex::new(None));
+
+async fn fetch_jwks(url: &str) -> anyhow::Result<Vec<Jwk>> {
+    let jwks: Jwks = reqwest::get(url).await?.error_for_status()?.json().await?;
+    info!("Fetched {} signing keys from {}", jwks.keys.len(), url);
+    Ok(jwks.keys)
+}
+
+/// Finds the signing key with the given id, refreshing the cached keys from the provider if they
+/// are stale or don't contain it
+async fn signing_key(key_id: Option<&str>) -> anyhow::Result<Jwk> {
+    let find = |keys: &[Jwk]| match key_id {
+        Some(kid) => keys.iter().find(|k| k.kid.as_deref() == Some(kid)).cloned(),
+        None if keys.len() == 1 => Some(keys[0].clone()),
+        None => None,
+    };
+
+    let mut cache = JWKS_CACHE.lock().await;
+
+    let refresh = match &*cache {
+        None => true,
+        Some(cached) => {
+            let age = cached.fetched_at.elapsed();
+            age > JWKS_TTL || (find(&cached.keys).is_none() && age > JWKS_MIN_REFRESH)
+        }
+    };
+
+    if refresh {
+        *cache = Some(CachedJwks {
+            fetched_at: Instant::now(),
+            keys: fetch_jwks(&OIDC_CONFIG.jwks_url).await?,
+        });
+    }
+
+    find(&cache.as_ref().unwrap().keys)
+        .ok_or_else(|| anyhow!("no signing key found with id {:?}", key_id))
+}
+
+fn verify_jwt(
+    token: &str,
+    key: &Jwk,
+    algorithm: &str,
+    options: VerificationOptions,
+) -> anyhow::Result<JWTClaims<Map<String, Value>>> {
+    let decode = |field: &Option<String>| -> anyhow::Result<Vec<u8>> {
+        let field = field
+            .as_ref()
+            .ok_or_else(|| anyhow!("signing key is missing fields for {}", algorithm))?;
+        Ok(URL_SAFE_NO_PAD.decode(field)?)
+    };
+
+    Ok(match algorithm {
+        "RS256" => RS256PublicKey::from_components(&decode(&key.n)?, &decode(&key.e)?)?
+            .verify_token(token, Some(options))?,
+        "RS384" => RS384PublicKey::from_components(&decode(&key.n)?, &decode(&key.e)?)?
+            .verify_token(token, Some(options))?,
+        "RS512" => RS512PublicKey::from_components(&decode(&key.n)?, &decode(&key.e)?)?
+            .verify_token(token, Some(options))?,
+        "ES256" => {
+            // an uncompressed SEC1 point
+            let mut point = vec![0x04];
+            point.extend(decode(&key.x)?);
+            point.extend(decode(&key.y)?);
+            ES256PublicKey::from_bytes(&point)?.verify_token(token, Some(options))?
+        }
+        other => bail!("unsupported JWT algorithm {}", other),
     })
 }
+
+/// Determines a user's role from the configured claim, which may hold a single role or a list of
+/// them; users are granted the highest role they hold, or viewer if they hold none
+fn role_from_claims(claims: &Map<String, Value>, role_claim: &str) -> Role {
+    let mut path = role_claim.split('.');
+    let mut value = path.next().and_then(|first| claims.get(first));
+    for part in path {
+        value = value.and_then(|v| v.get(part));
+    }
+
+    let roles: Vec<&str> = match value {
+        Some(Value::String(s)) => vec![s.as_str()],
+        Some(Value::Array(values)) => values.iter().filter_map(|v| v.as_str()).collect(),
+        _ => vec![],
+    };
+
+    roles
+        .into_iter()
+        .filter_map(|r| r.parse().ok())
+        .max()
+        .unwrap_or(Role::Viewer)
+}
+
+async fn authenticate_jwt(token: &str) -> Result<AuthData, ErrorResp> {
+    let metadata =
+        Token::decode_metadata(token).map_err(|_| unauthorized("Invalid bearer token"))?;
+
+    let key = signing_key(metadata.key_id()).await.map_err(|e| {
+        warn!("Failed to find signing key for JWT: {:?}", e);
+        unauthorized("Invalid bearer token")
+    })?;
+
+    let config = &*OIDC_CONFIG;
+    let options = VerificationOptions {
+        allowed_issuers: config.issuer.as_ref().map(|i| HashSet::from([i.clone()])),
+        allowed_audiences: config.audience.as_ref().map(|a| HashSet::from([a.clone()])),
+        ..Default::default()
+    };
+
+    let claims = verify_jwt(token, &key, metadata.algorithm(), options).map_err(|e| {
+        info!("Rejected JWT: {:?}", e);
+        unauthorized("Invalid bearer token")
+    })?;
+
+    let Some(subject) = claims.subject else {
+        return Err(unauthorized("Bearer token has no subject"));
+    };
+
+    Ok(auth_data(
+        subject,
+        DEFAULT_ORGANIZATION,
+        role_from_claims(&claims.custom, &config.role_claim),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn claim_map(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_credential_type() {
+        assert_eq!(
+            credential_type(AuthMode::ApiKeys, "arroyo_abc"),
+            CredentialType::ApiKey
+        );
+        // in api_keys mode, every token is checked against the stored keys
+        assert_eq!(
+            credential_type(AuthMode::ApiKeys, "eyJhbGciOiJSUzI1NiJ9.e30.sig"),
+            CredentialType::ApiKey
+        );
+
+        assert_eq!(
+            credential_type(AuthMode::Oidc, "arroyo_abc"),
+            CredentialType::ApiKey
+        );
+        assert_eq!(
+            credential_type(AuthMode::Oidc, "eyJhbGciOiJSUzI1NiJ9.e30.sig"),
+            CredentialType::Jwt
+        );
+        assert_eq!(
+            credential_type(AuthMode::Oidc, "ARROYO_abc"),
+            CredentialType::Jwt
+        );
+    }
+
+    #[test]
+    fn test_admin_key_auth() {
+        let admin_hash = hash_api_key("arroyo_admin");
+
+        let auth =
+            admin_key_auth(&hash_api_key("arroyo_admin"), Some(admin_hash.as_str())).unwrap();
+        assert_eq!(auth.role, Role::Admin);
+        assert_eq!(auth.user_id, "admin");
+        assert_eq!(auth.organization_id, DEFAULT_ORGANIZATION);
+
+        assert!(admin_key_auth(&hash_api_key("arroyo_other"), Some(admin_hash.as_str())).is_none());
+        assert!(admin_key_auth(&hash_api_key("arroyo_admin"), None).is_none());
+    }
+
+    #[test]
+    fn test_hash_api_key() {
+        assert_eq!(
+            hash_api_key("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_role_from_claims() {
+        assert_eq!(
+            role_from_claims(&claim_map(json!({"role": "operator"})), "role"),
+            Role::Operator
+        );
+        assert_eq!(
+            role_from_claims(&claim_map(json!({"role": "Admin"})), "role"),
+            Role::Admin
+        );
+
+        // users with several roles get the highest one they hold
+        assert_eq!(
+            role_from_claims(
+                &claim_map(json!({"groups": ["viewer", "admin", "operator"]})),
+                "groups"
+            ),
+            Role::Admin
+        );
+
+        // nested claims are addressed with dotted paths
+        let nested = claim_map(json!({
+            "realm_access": {"roles": ["offline_access", "operator"]},
+            "role": "admin",
+        }));
+        assert_eq!(
+            role_from_claims(&nested, "realm_access.roles"),
+            Role::Operator
+        );
+        assert_eq!(
+            role_from_claims(&nested, "realm_access.missing"),
+            Role::Viewer
+        );
+
+        // users without a recognized role are viewers
+        assert_eq!(
+            role_from_claims(&claim_map(json!({"role": "superuser"})), "role"),
+            Role::Viewer
+        );
+        assert_eq!(
+            role_from_claims(&claim_map(json!({"role": 3})), "role"),
+            Role::Viewer
+        );
+        assert_eq!(
+            role_from_claims(&claim_map(json!({})), "role"),
+            Role::Viewer
+        );
+    }
+
+    fn jwk(key_pair: &RS256KeyPair, kid: &str) -> Jwk {
+        let components = key_pair.public_key().to_components();
+        Jwk {
+            kid: Some(kid.to_string()),
+            n: Some(URL_SAFE_NO_PAD.encode(components.n)),
+            e: Some(URL_SAFE_NO_PAD.encode(components.e)),
+            x: None,
+            y: None,
+        }
+    }
+
+    fn options(issuer: &str) -> VerificationOptions {
+        VerificationOptions {
+            allowed_issuers: Some(HashSet::from([issuer.to_string()])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_verify_jwt() {
+        let key_pair = RS256KeyPair::generate(2048).unwrap().with_key_id("k1");
+        let claims = Claims::with_custom_claims(
+            claim_map(json!({"realm_access": {"roles": ["operator"]}})),
+            jwt_simple::prelude::Duration::from_hours(1),
+        )
+        .with_subject("alice")
+        .with_issuer("https://issuer.example.com");
+        let token = key_pair.sign(claims).unwrap();
+
+        let metadata = Token::decode_metadata(&token).unwrap();
+        assert_eq!(metadata.key_id(), Some("k1"));
+        assert_eq!(metadata.algorithm(), "RS256");
+
+        let verified = verify_jwt(
+            &token,
+            &jwk(&key_pair, "k1"),
+            "RS256",
+            options("https://issuer.example.com"),
+        )
+        .unwrap();
+        assert_eq!(verified.subject.as_deref(), Some("alice"));
+        assert_eq!(
+            role_from_claims(&verified.custom, "realm_access.roles"),
+            Role::Operator
+        );
+
+        // tokens from other issuers are rejected
+        assert!(verify_jwt(
+            &token,
+            &jwk(&key_pair, "k1"),
+            "RS256",
+            options("https://other.example.com"),
+        )
+        .is_err());
+
+        // as are tokens that weren't signed by the provider's key
+        let other_key_pair = RS256KeyPair::generate(2048).unwrap();
+        assert!(verify_jwt(
+            &token,
+            &jwk(&other_key_pair, "k1"),
+            "RS256",
+            options("https://issuer.example.com"),
+        )
+        .is_err());
+
+        // and the key must have the fields that the algorithm needs
+        let mut incomplete = jwk(&key_pair, "k1");
+        incomplete.n = None;
+        assert!(verify_jwt(
+            &token,
+            &incomplete,
+            "RS256",
+            options("https://issuer.example.com")
+        )
+        .is_err());
+
+        assert_eq!(
+            verify_jwt(
+                &token,
+                &jwk(&key_pair, "k1"),
+                "HS256",
+                options("https://issuer.example.com")
+            )
+            .unwrap_err()
+            .to_string(),
+            "unsupported JWT algorithm HS256"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use axum::extract::{Path, State};
-use axum::Json;
+use axum::{Extension, Json};
 use axum_extra::extract::WithRejection;
 use std::collections::BTreeMap;
 
@@ -8,7 +8,9 @@ use arroyo_rpc::api_types::connections::{
     ConnectionAutocompleteResp, ConnectionProfile, ConnectionProfilePost, TestSourceMessage,
 };
 use arroyo_rpc::api_types::ConnectionProfileCollection;
+use arroyo_rpc::var_str::VarStr;
 use cornucopia_async::GenericClient;
+use serde_json::Value;
 use tracing::warn;
 
 use arroyo_rpc::public_ids::{generate_id, IdTypes};
@@ -16,9 +18,7 @@ use arroyo_rpc::public_ids::{generate_id, IdTypes};
 use crate::queries::api_queries;
 use crate::queries::api_queries::DbConnectionProfile;
 use crate::rest::AppState;
-use crate::rest_utils::{
-    authenticate, bad_request, client, log_and_map, not_found, ApiError, BearerAuth, ErrorResp,
-};
+use crate::rest_utils::{bad_request, client, log_and_map, not_found, ApiError, ErrorResp};
 use crate::{handle_db_error, handle_delete, AuthData};
 
 impl TryFrom<DbConnectionProfile> for ConnectionProfile {
@@ -46,6 +46,54 @@ impl TryFrom<DbConnectionProfile> for ConnectionProfile {
     }
 }
 
+// replaces sensitive values that are stored in plaintext when configs are returned from the api
+const REDACTED: &str = "********";
+
+/// Redacts the fields that a connector's config schema marks as sensitive, unless they only
+/// reference secrets or environment variables
+pub(crate) fn redact_sensitive(schema: &Value, config: &mut Value) {
+    for combinator in ["oneOf", "anyOf", "allOf"] {
+        if let Some(alternatives) = schema.get(combinator).and_then(|a| a.as_array()) {
+            for alternative in alternatives {
+                redact_sensitive(alternative, config);
+            }
+        }
+    }
+
+    let Some(config) = config.as_object_mut() else {
+        return;
+    };
+
+    if let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) {
+        for (key, property) in properties {
+            if let Some(value) = config.get_mut(key) {
+                redact_sensitive(property, value);
+            }
+        }
+    }
+
+    if let Some(sensitive) = schema.get("sensitive").and_then(|s| s.as_array()) {
+        for key in sensitive.iter().filter_map(|k| k.as_str()) {
+            if let Some(Value::String(value)) = config.get_mut(key) {
+                if !VarStr::new(value.clone()).only_placeholders() {
+                    *value = REDACTED.to_string();
+                }
+            }
+        }
+    }
+}
+
+/// Redacts sensitive values from a connection profile before it is returned from the api
+pub(crate) fn redact_profile(mut profile: ConnectionProfile) -> ConnectionProfile {
+    if let Some(schema) = connector_for_type(&profile.connector)
+        .and_then(|c| c.metadata().connection_config)
+        .and_then(|s| serde_json::from_str(&s).ok())
+    {
+        redact_sensitive(&schema, &mut profile.config);
+    }
+    profile
+}
+
 /// Test connection profile
 #[utoipa::path(
     post,
@@ -57,12 +105,8 @@ impl TryFrom<DbConnectionProfile> for ConnectionProfile {
     ),
 )]
 pub async fn test_connection_profile(
-    State(state): State<AppState>,
-    bearer_auth: BearerAuth,
     WithRejection(Json(req), _): WithRejection<Json<ConnectionProfilePost>, ApiError>,
 ) -> Result<Json<TestSourceMessage>, ErrorResp> {
-    let _auth_data = authenticate(&state.pool, bearer_auth).await.unwrap();
-
     let connector = connector_for_type(&req.connector)
         .ok_or_else(|| bad_request("Unknown connector type".to_string()))?;
 
@@ -92,11 +136,10 @@ pub async fn test_connection_profile(
 )]
 pub async fn create_connection_profile(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     WithRejection(Json(req), _): WithRejection<Json<ConnectionProfilePost>, ApiError>,
 ) -> Result<Json<ConnectionProfile>, ErrorResp> {
     let client = client(&state.pool).await.unwrap();
-    let auth_data = authenticate(&state.pool, bearer_auth).await.unwrap();
 
     connector_for_type(&req.connector)
         .ok_or_else(|| bad_request("Unknown connector type".to_string()))?
@@ -126,7 +169,7 @@ pub async fn create_connection_profile(
         .try_into()
         .map_err(log_and_map)?;
 
-    Ok(Json(connection_profile))
+    Ok(Json(redact_profile(connection_profile)))
 }
 
 /// List all connection profiles
@@ -140,12 +183,15 @@ pub async fn create_connection_profile(
 )]
 pub async fn get_connection_profiles(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
 ) -> Result<Json<ConnectionProfileCollection>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
-    let data = get_all_connection_profiles(&auth_data, &client).await?;
+    let data = get_all_connection_profiles(&auth_data, &client)
+        .await?
+        .into_iter()
+        .map(redact_profile)
+        .collect();
 
     Ok(Json(ConnectionProfileCollection { data }))
 }
@@ -164,11 +210,10 @@ pub async fn get_connection_profiles(
 )]
 pub(crate) async fn delete_connection_profile(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path(pub_id): Path<String>,
 ) -> Result<(), ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let deleted = api_queries::delete_connection_profile()
         .bind(&client, &auth_data.organization_id, &pub_id)
@@ -196,11 +241,10 @@ pub(crate) async fn delete_connection_profile(
 )]
 pub(crate) async fn get_connection_profile_autocomplete(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path(pub_id): Path<String>,
 ) -> Result<Json<ConnectionAutocompleteResp>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let connection_profile = api_queries::get_connection_profile_by_pub_id()
         .bind(&client, &auth_data.organization_id, &pub_id)
@@ -3,7 +3,7 @@ use anyhow::anyhow;
 use axum::extract::{Path, Query, State};
 use axum::response::sse::Event;
 use axum::response::Sse;
-use axum::Json;
+use axum::{Extension, Json};
 use axum_extra::extract::WithRejection;
 use cornucopia_async::GenericClient;
 use cornucopia_async::Params;
@@ -18,24 +18,25 @@ use tracing::warn;
 use arroyo_connectors::confluent::ConfluentProfile;
 use arroyo_connectors::connector_for_type;
 use arroyo_connectors::kafka::{KafkaConfig, KafkaTable, SchemaRegistry};
-use arroyo_formats::{avro, json};
+use arroyo_formats::{avro, json, proto};
 use arroyo_operator::connector::ErasedConnector;
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionTable, ConnectionTablePost, ConnectionType,
     SchemaDefinition,
 };
 use arroyo_rpc::api_types::{ConnectionTableCollection, PaginationQueryParams};
-use arroyo_rpc::formats::{AvroFormat, Format, JsonFormat};
+use arroyo_rpc::formats::{AvroFormat, Format, JsonFormat, ProtobufFormat};
 use arroyo_rpc::public_ids::{generate_id, IdTypes};
 use arroyo_rpc::schema_resolver::{
     ConfluentSchemaRegistry, ConfluentSchemaSubjectResponse, ConfluentSchemaType,
 };
 use arroyo_types::raw_schema;
 
+use crate::connection_profiles::{redact_profile, redact_sensitive};
 use crate::rest::AppState;
 use crate::rest_utils::{
-    authenticate, bad_request, client, log_and_map, not_found, paginate_results, required_field,
-    validate_pagination_params, ApiError, BearerAuth, ErrorResp,
+    bad_request, client, log_and_map, not_found, paginate_results, required_field,
+    validate_pagination_params, ApiError, ErrorResp,
 };
 use crate::{
     handle_db_error, handle_delete,
@@ -135,11 +136,10 @@ async fn get_and_validate_connector<E: GenericClient>(
 )]
 pub(crate) async fn delete_connection_table(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path(pub_id): Path<String>,
 ) -> Result<(), ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let deleted = api_queries::delete_connection_table()
         .bind(&client, &auth_data.organization_id, &pub_id)
@@ -166,11 +166,10 @@ pub(crate) async fn delete_connection_table(
 )]
 pub(crate) async fn test_connection_table(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     WithRejection(Json(req), _): WithRejection<Json<ConnectionTablePost>, ApiError>,
 ) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let (connector, _, profile, schema) =
         get_and_validate_connector(&req, &auth_data, &client).await?;
@@ -257,11 +256,10 @@ pub(crate) async fn get_all_connection_tables<C: GenericClient>(
 )]
 pub async fn create_connection_table(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     WithRejection(Json(req), _): WithRejection<Json<ConnectionTablePost>, ApiError>,
 ) -> Result<Json<ConnectionTable>, ErrorResp> {
     let mut client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
     let transaction = client.transaction().await.map_err(log_and_map)?;
     transaction
         .execute("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE", &[])
@@ -309,7 +307,19 @@ pub async fn create_connection_table(
         .try_into()
         .map_err(log_and_map)?;
 
-    Ok(Json(table))
+    Ok(Json(redact_table(table)))
+}
+
+/// Redacts sensitive values from a connection table and its profile before it is returned from
+/// the api
+fn redact_table(mut table: ConnectionTable) -> ConnectionTable {
+    if let Some(schema) = connector_for_type(&table.connector)
+        .and_then(|c| serde_json::from_str(&c.metadata().table_config).ok())
+    {
+        redact_sensitive(&schema, &mut table.config);
+    }
+    table.connection_profile = table.connection_profile.map(redact_profile);
+    table
 }
 
 impl TryInto<ConnectionTable> for DbConnectionTable {
@@ -388,11 +398,10 @@ impl TryInto<ConnectionTable> for DbConnectionTable {
 )]
 pub(crate) async fn get_connection_tables(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     query_params: Query<PaginationQueryParams>,
 ) -> Result<Json<ConnectionTableCollection>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let (starting_after, limit) =
         validate_pagination_params(query_params.starting_after.clone(), query_params.limit)?;
@@ -422,6 +431,7 @@ pub(crate) async fn get_connection_tables(
             result
         })
         .filter_map(Result::ok)
+        .map(redact_table)
         .collect();
 
     Ok(Json(ConnectionTableCollection {
@@ -467,11 +477,88 @@ pub(crate) async fn expand_schema(
             )
             .await
         }
+        Format::Protobuf(_) => {
+            expand_proto_schema(
+                name,
+                connector,
+                connection_type,
+                schema,
+                profile_config,
+                table_config,
+            )
+            .await
+        }
         Format::Parquet(_) => Ok(schema),
         Format::RawString(_) => Ok(schema),
     }
 }
 
+async fn expand_proto_schema(
+    _name: &str,
+    connector: &str,
+    connection_type: ConnectionType,
+    mut schema: ConnectionSchema,
+    profile_config: &Value,
+    table_config: &Value,
+) -> Result<ConnectionSchema, ErrorResp> {
+    if let Some(Format::Protobuf(ProtobufFormat {
+        confluent_schema_registry: true,
+        schema_id,
+        ..
+    })) = &mut schema.format
+    {
+        // unlike avro and json, protobuf sinks also need the registered schema in order
+        // to encode messages
+        let schema_response = get_schema(connector, table_config, profile_config)
+            .await?
+            .ok_or_else(|| bad_request(
+                format!("No schema was found; ensure that the topic exists and has a value schema configured in the schema registry")))?;
+
+        if schema_response.schema_type != ConfluentSchemaType::Protobuf {
+            return Err(bad_request(format!(
+                "Format configured is protobuf, but confluent schema repository returned a {:?} schema",
+                schema_response.schema_type
+            )));
+        }
+
+        schema_id.replace(schema_response.id);
+        schema.definition = Some(SchemaDefinition::ProtobufSchema(schema_response.schema));
+    }
+
+    let Some(SchemaDefinition::ProtobufSchema(definition)) = schema.definition.as_ref() else {
+        return Err(bad_request(format!(
+            "protobuf format requires a protobuf schema be set for {}s",
+            connection_type.to_string().to_lowercase()
+        )));
+    };
+
+    let compiled = proto::schema::schema_file_to_descriptor(definition)
+        .map_err(|e| bad_request(e.to_string()))?;
+
+    let Some(Format::Protobuf(format)) = &mut schema.format else {
+        unreachable!("format must be protobuf");
+    };
+    format.compiled_schema = Some(compiled);
+
+    let descriptor = proto::schema::get_message_descriptor(format)
+        .map_err(|e| bad_request(format!("Invalid protobuf schema: {}", e)))?;
+    format.message_name = Some(descriptor.full_name().to_string());
+
+    if !format.into_unstructured_json {
+        let fields: Result<_, String> = proto::schema::protobuf_to_arrow(&descriptor)
+            .map_err(|e| bad_request(format!("Invalid protobuf schema: {}", e)))?
+            .fields
+            .into_iter()
+            .map(|f| (**f).clone().try_into())
+            .collect();
+
+        schema.fields =
+            fields.map_err(|e| bad_request(format!("Failed to convert schema: {}", e)))?;
+    }
+
+    Ok(schema)
+}
+
 async fn expand_avro_schema(
     name: &str,
     connector: &str,
@@ -487,7 +574,7 @@ async fn expand_avro_schema(
     {
         let schema_response = get_schema(connector, table_config, profile_config).await?;
         match connection_type {
-            ConnectionType::Source => {
+            ConnectionType::Source | ConnectionType::Lookup => {
                 let schema_response = schema_response.ok_or_else(|| bad_request(
                         format!("No schema was found; ensure that the topic exists and has a value schema configured in the schema registry")))?;
 
@@ -508,7 +595,7 @@ async fn expand_avro_schema(
 
     let Some(SchemaDefinition::AvroSchema(definition)) = schema.definition.as_ref() else {
         return match connection_type {
-            ConnectionType::Source => Err(bad_request(
+            ConnectionType::Source | ConnectionType::Lookup => Err(bad_request(
                 "avro format requires an avro schema be set for sources",
             )),
             ConnectionType::Sink => {
@@ -554,7 +641,7 @@ async fn expand_json_schema(
         let schema_response = get_schema(connector, table_config, profile_config).await?;
 
         match connection_type {
-            ConnectionType::Source => {
+            ConnectionType::Source | ConnectionType::Lookup => {
                 let schema_response = schema_response.ok_or_else(|| bad_request(
                     format!("No schema was found; ensure that the topic exists and has a value schema configured in the schema registry")))?;
 
@@ -682,6 +769,13 @@ pub(crate) async fn test_schema(
                 Ok(())
             }
         }
+        SchemaDefinition::ProtobufSchema(schema) => {
+            if let Err(e) = proto::schema::schema_file_to_descriptor(&schema) {
+                Err(bad_request(e.to_string()))
+            } else {
+                Ok(())
+            }
+        }
         _ => {
             // TODO: add testing for other schema types
             Ok(())
@@ -2,10 +2,12 @@ use crate::queries::api_queries::{
     DbCheckpoint, DbLogMessage, DbPipelineJob, GetOperatorErrorsParams,
 };
 use arroyo_rpc::api_types::checkpoints::{
-    Checkpoint, CheckpointEventSpan, CheckpointSpanType, OperatorCheckpointGroup,
-    SubtaskCheckpointGroup,
+    Checkpoint, CheckpointEventSpan, CheckpointSpanType, CheckpointStateSummary,
+    OperatorCheckpointGroup, StateDumpFormat, StateDumpQueryParams, SubtaskCheckpointGroup,
+};
+use arroyo_rpc::api_types::pipelines::{
+    JobLogLevel, JobLogMessage, OutputData, RestartStrategy, StopType,
 };
-use arroyo_rpc::api_types::pipelines::{JobLogLevel, JobLogMessage, OutputData, StopType};
 use arroyo_rpc::api_types::{
     CheckpointCollection, JobCollection, JobLogMessageCollection,
     OperatorCheckpointGroupCollection, PaginationQueryParams,
@@ -16,12 +18,16 @@ use arroyo_rpc::grpc::api::{
 };
 use arroyo_rpc::grpc::controller_grpc_client::ControllerGrpcClient;
 use arroyo_rpc::public_ids::{generate_id, IdTypes};
+use arroyo_state::inspect::{self, StateFilter};
+use arroyo_types::from_micros;
 use axum::extract::{Path, Query, State};
 use axum::response::sse::{Event, Sse};
-use axum::Json;
-use cornucopia_async::Params;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use cornucopia_async::{GenericClient, Params};
 use deadpool_postgres::Transaction;
 use futures_util::stream::Stream;
+use http::header;
 use std::convert::Infallible;
 use std::{collections::HashMap, time::Duration};
 use tokio_stream::wrappers::ReceiverStream;
@@ -34,16 +40,55 @@ const PREVIEW_TTL: Duration = Duration::from_secs(60);
 use crate::pipelines::{query_job_by_pub_id, query_pipeline_by_pub_id};
 use crate::rest::AppState;
 use crate::rest_utils::{
-    authenticate, bad_request, client, log_and_map, not_found, paginate_results,
-    validate_pagination_params, BearerAuth, ErrorResp,
+    bad_request, client, log_and_map, not_found, paginate_results, validate_pagination_params,
+    ErrorResp,
 };
 use crate::types::public::LogLevel;
 use crate::{queries::api_queries, to_micros, types::public, AuthData};
 
+/// Checks that starting another job (or resuming `resuming_job_id`) would not exceed the
+/// organization's quota of running jobs
+pub(crate) async fn check_running_jobs_quota(
+    client: &impl GenericClient,
+    auth: &AuthData,
+    resuming_job_id: Option<&str>,
+) -> Result<(), ErrorResp> {
+    let running_jobs = api_queries::get_jobs()
+        .bind(client, &auth.organization_id)
+        .all()
+        .await
+        .map_err(log_and_map)?
+        .iter()
+        .filter(|j| {
+            Some(j.id.as_str()) != resuming_job_id
+                && j.stop == public::StopMode::none
+                && !j
+                    .state
+                    .as_ref()
+                    .map(|s| s == "Failed" || s == "Finished")
+                    .unwrap_or(false)
+        })
+        .count();
+
+    if running_jobs >= auth.org_metadata.max_running_jobs as usize {
+        return Err(bad_request(format!(
+            "Your organization's quota allows at most {} running jobs; stop an existing job \
+            before starting another",
+            auth.org_metadata.max_running_jobs
+        )));
+    }
+
+    Ok(())
+}
+
 pub(crate) async fn create_job<'a>(
     request: CreateJobReq,
     pipeline_name: &str,
     pipeline_id: &i64,
+    restore_savepoint_id: Option<i64>,
+    allow_dropped_state: bool,
+    parallelism_overrides: &HashMap<String, u64>,
+    restart_strategy: Option<&RestartStrategy>,
     auth: &AuthData,
     client: &Transaction<'a>,
 ) -> Result<String, ErrorResp> {
@@ -61,29 +106,7 @@ pub(crate) async fn create_job<'a>(
         ));
     }
 
-    let running_jobs = api_queries::get_jobs()
-        .bind(client, &auth.organization_id)
-        .all()
-        .await
-        .map_err(log_and_map)?
-        .iter()
-        .filter(|j| {
-            j.stop == public::StopMode::none
-                && !j
-                    .state
-                    .as_ref()
-                    .map(|s| s == "Failed" || s == "Finished")
-                    .unwrap_or(false)
-        })
-        .count();
-
-    if running_jobs > auth.org_metadata.max_running_jobs as usize {
-        let message = format!("You have exceeded the maximum number
-            of running jobs in your plan ({}). Stop an existing job or contact support@arroyo.systems for
-            an increase", auth.org_metadata.max_running_jobs);
-
-        return Err(bad_request(message));
-    }
+    check_running_jobs_quota(client, auth, None).await?;
 
     let job_id = generate_id(IdTypes::JobConfig);
 
@@ -102,6 +125,13 @@ pub(crate) async fn create_job<'a>(
             } else {
                 None
             }),
+            &restore_savepoint_id,
+            &allow_dropped_state,
+            &serde_json::to_value(parallelism_overrides).map_err(log_and_map)?,
+            &restart_strategy
+                .map(serde_json::to_value)
+                .transpose()
+                .map_err(log_and_map)?,
         )
         .await
         .map_err(log_and_map)?;
@@ -196,12 +226,11 @@ pub(crate) fn get_action(state: &str, running_desired: &bool) -> (String, Option
 )]
 pub async fn get_job_errors(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path((pipeline_pub_id, job_pub_id)): Path<(String, String)>,
     query_params: Query<PaginationQueryParams>,
 ) -> Result<Json<JobLogMessageCollection>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let (starting_after, limit) =
         validate_pagination_params(query_params.starting_after.clone(), query_params.limit)?;
@@ -268,11 +297,10 @@ impl Into<JobLogMessage> for DbLogMessage {
 )]
 pub async fn get_job_checkpoints(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path((pipeline_pub_id, job_pub_id)): Path<(String, String)>,
 ) -> Result<Json<CheckpointCollection>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &client, &auth_data).await?;
 
@@ -375,11 +403,10 @@ fn get_event_spans(subtask_details: &TaskCheckpointDetail) -> Vec<CheckpointEven
 )]
 pub async fn get_checkpoint_details(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path((pipeline_pub_id, job_pub_id, epoch)): Path<(String, String, u32)>,
 ) -> Result<Json<OperatorCheckpointGroupCollection>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &client, &auth_data).await?;
 
@@ -433,6 +460,118 @@ pub async fn get_checkpoint_details(
     Ok(Json(OperatorCheckpointGroupCollection { data: operators }))
 }
 
+async fn ensure_checkpoint_exists(
+    job_pub_id: &str,
+    epoch: u32,
+    client: &impl GenericClient,
+    auth_data: &AuthData,
+) -> Result<(), ErrorResp> {
+    api_queries::get_checkpoint_details()
+        .bind(
+            client,
+            &job_pub_id,
+            &auth_data.organization_id,
+            &(epoch as i32),
+        )
+        .opt()
+        .await
+        .map_err(log_and_map)?
+        .ok_or_else(|| {
+            not_found(&format!(
+                "Checkpoint with epoch {} for job '{}'",
+                epoch, job_pub_id
+            ))
+        })?;
+
+    Ok(())
+}
+
+/// Get the operators, tables and files stored in a checkpoint
+#[utoipa::path(
+    get,
+    path = "/v1/pipelines/{pipeline_id}/jobs/{job_id}/checkpoints/{epoch}/state",
+    tag = "jobs",
+    params(
+        ("pipeline_id" = String, Path, description = "Pipeline id"),
+        ("job_id" = String, Path, description = "Job id"),
+        ("epoch" = u32, Path, description = "Epoch")
+    ),
+    responses(
+        (status = 200, description = "Got checkpoint's state", body = CheckpointStateSummary),
+    ),
+)]
+pub async fn get_checkpoint_state(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+    Path((pipeline_pub_id, job_pub_id, epoch)): Path<(String, String, u32)>,
+) -> Result<Json<CheckpointStateSummary>, ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &client, &auth_data).await?;
+    ensure_checkpoint_exists(&job_pub_id, epoch, &client, &auth_data).await?;
+
+    let summary = inspect::describe_checkpoint(&job_pub_id, epoch)
+        .await
+        .map_err(log_and_map)?;
+
+    Ok(Json(summary))
+}
+
+/// Dump the contents of a table in a checkpoint
+#[utoipa::path(
+    get,
+    path = "/v1/pipelines/{pipeline_id}/jobs/{job_id}/checkpoints/{epoch}/state/{operator_id}/{table}",
+    tag = "jobs",
+    params(
+        ("pipeline_id" = String, Path, description = "Pipeline id"),
+        ("job_id" = String, Path, description = "Job id"),
+        ("epoch" = u32, Path, description = "Epoch"),
+        ("operator_id" = String, Path, description = "Operator id"),
+        ("table" = String, Path, description = "Table name"),
+        StateDumpQueryParams,
+    ),
+    responses(
+        (status = 200, description = "Table contents as newline-delimited JSON or an Arrow IPC stream"),
+    ),
+)]
+pub async fn get_checkpoint_table(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+    Path((pipeline_pub_id, job_pub_id, epoch, operator_id, table)): Path<(
+        String,
+        String,
+        u32,
+        String,
+        String,
+    )>,
+    query_params: Query<StateDumpQueryParams>,
+) -> Result<Response, ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &client, &auth_data).await?;
+    ensure_checkpoint_exists(&job_pub_id, epoch, &client, &auth_data).await?;
+
+    let filter = StateFilter {
+        key: query_params.key.clone(),
+        start_time: query_params.start_time.map(from_micros),
+        end_time: query_params.end_time.map(from_micros),
+    };
+
+    let (schema, batches) = inspect::read_table(&job_pub_id, epoch, &operator_id, &table, &filter)
+        .await
+        .map_err(|e| bad_request(format!("Failed to read table {}: {}", table, e)))?;
+
+    let format = query_params.format.unwrap_or_default();
+    let body = inspect::encode_batches(schema, &batches, format).map_err(log_and_map)?;
+
+    let content_type = match format {
+        StateDumpFormat::Json => "application/x-ndjson",
+        StateDumpFormat::Arrow => "application/vnd.apache.arrow.stream",
+    };
+
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
 /// Subscribe to a job's output
 #[utoipa::path(
     get,
@@ -448,11 +587,10 @@ pub async fn get_checkpoint_details(
 )]
 pub async fn get_job_output(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path((pipeline_pub_id, job_pub_id)): Path<(String, String)>,
 ) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     // validate that the job exists, the user has access, and the graph has a GrpcSink
     query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &client, &auth_data).await?;
@@ -526,10 +664,9 @@ pub async fn get_job_output(
 )]
 pub async fn get_jobs(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
 ) -> Result<Json<JobCollection>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let jobs: Vec<DbPipelineJob> = api_queries::get_all_jobs()
         .bind(&client, &auth_data.organization_id)
@@ -9,6 +9,7 @@ use tonic::transport::Channel;
 use tracing::{error, info, warn};
 use utoipa::OpenApi;
 
+use crate::api_keys::{__path_create_api_key, __path_delete_api_key, __path_get_api_keys};
 use crate::connection_profiles::{
     __path_create_connection_profile, __path_delete_connection_profile,
     __path_get_connection_profile_autocomplete, __path_get_connection_profiles,
@@ -20,8 +21,8 @@ use crate::connection_tables::{
 };
 use crate::connectors::__path_get_connectors;
 use crate::jobs::{
-    __path_get_checkpoint_details, __path_get_job_checkpoints, __path_get_job_errors,
-    __path_get_job_output, __path_get_jobs,
+    __path_get_checkpoint_details, __path_get_checkpoint_state, __path_get_checkpoint_table,
+    __path_get_job_checkpoints, __path_get_job_errors, __path_get_job_output, __path_get_jobs,
 };
 use crate::metrics::__path_get_operator_metric_groups;
 use crate::pipelines::__path_get_pipelines;
@@ -32,14 +33,21 @@ use crate::pipelines::{
 };
 use crate::rest::__path_ping;
 use crate::rest_utils::{bad_request, log_and_map, service_unavailable, ErrorResp};
+use crate::savepoints::{
+    __path_create_savepoint, __path_get_job_savepoints, __path_get_savepoint, __path_get_savepoints,
+};
+use crate::secrets::{__path_create_secret, __path_delete_secret, __path_get_secrets};
 use crate::udfs::{__path_create_udf, __path_delete_udf, __path_get_udfs, __path_validate_udf};
-use arroyo_rpc::api_types::{checkpoints::*, connections::*, metrics::*, pipelines::*, udfs::*, *};
+use arroyo_rpc::api_types::{
+    auth::*, checkpoints::*, connections::*, metrics::*, pipelines::*, secrets::*, udfs::*, *,
+};
 use arroyo_rpc::formats::*;
 use arroyo_rpc::grpc::compiler_grpc_client::CompilerGrpcClient;
 use arroyo_types::{
     ports, service_port, COMPILER_ADDR_ENV, COMPILER_PORT_ENV, CONTROLLER_ADDR_ENV, HTTP_PORT_ENV,
 };
 
+mod api_keys;
 mod cloud;
 mod connection_profiles;
 mod connection_tables;
@@ -49,6 +57,8 @@ mod metrics;
 mod pipelines;
 pub mod rest;
 mod rest_utils;
+mod savepoints;
+mod secrets;
 mod udfs;
 
 include!(concat!(env!("OUT_DIR"), "/api-sql.rs"));
@@ -100,7 +110,7 @@ pub struct OrgMetadata {
 pub struct AuthData {
     pub user_id: String,
     pub organization_id: String,
-    pub role: String,
+    pub role: Role,
     pub org_metadata: OrgMetadata,
 }
 
@@ -157,6 +167,8 @@ pub async fn start_server(pool: Pool) {
     let http_port = service_port("api", ports::API_HTTP, HTTP_PORT_ENV);
     let addr = format!("0.0.0.0:{}", http_port).parse().unwrap();
 
+    info!("Using '{}' authentication for the API", cloud::auth_mode());
+
     let app = rest::create_rest_app(pool, &controller_addr);
 
     info!("Starting API server on {:?}", addr);
@@ -260,13 +272,30 @@ impl IntoResponse for HttpError {
         test_connection_table,
         test_schema,
         get_checkpoint_details,
+        get_checkpoint_state,
+        get_checkpoint_table,
+        create_savepoint,
+        get_job_savepoints,
+        get_savepoints,
+        get_savepoint,
         create_udf,
         get_udfs,
-        delete_udf
+        delete_udf,
+        create_api_key,
+        get_api_keys,
+        delete_api_key,
+        create_secret,
+        get_secrets,
+        delete_secret
     ),
     components(schemas(
         PipelinePost,
         PipelinePatch,
+        AutoscalingConfig,
+        RestartStrategy,
+        FixedDelayRestart,
+        ExponentialBackoffRestart,
+        FailureRateRestart,
         PipelineRestart,
         Pipeline,
         PipelineGraph,
@@ -281,6 +310,17 @@ impl IntoResponse for HttpError {
         JobLogLevel,
         Checkpoint,
         CheckpointCollection,
+        CheckpointStateSummary,
+        OperatorState,
+        TableState,
+        SubtaskTableState,
+        StateFile,
+        StateDumpFormat,
+        StateDumpQueryParams,
+        SavepointPost,
+        Savepoint,
+        SavepointState,
+        SavepointCollection,
         OutputData,
         MetricNames,
         Metric,
@@ -303,14 +343,18 @@ impl IntoResponse for HttpError {
         SourceFieldType,
         FieldType,
         StructType,
+        ListType,
         PrimitiveType,
         SchemaDefinition,
         TestSourceMessage,
         JsonFormat,
         AvroFormat,
+        ProtobufFormat,
         ParquetFormat,
+        ParquetCompression,
         RawStringFormat,
         TimestampFormat,
+        DecimalEncoding,
         Framing,
         FramingMethod,
         NewlineDelimitedFraming,
@@ -329,6 +373,15 @@ impl IntoResponse for HttpError {
         GlobalUdf,
         GlobalUdfCollection,
         BadData,
+        DeadLetterTarget,
+        Role,
+        ApiKey,
+        ApiKeyPost,
+        ApiKeyCreated,
+        ApiKeyCollection,
+        Secret,
+        SecretPost,
+        SecretCollection,
     )),
     tags(
         (name = "ping", description = "Ping endpoint"),
@@ -336,7 +389,10 @@ impl IntoResponse for HttpError {
         (name = "connection_tables", description = "Connection tables management endpoints"),
         (name = "pipelines", description = "Pipeline management endpoints"),
         (name = "jobs", description = "Job management endpoints"),
+        (name = "savepoints", description = "Savepoint management endpoints"),
         (name = "connectors", description = "Connector management endpoints"),
+        (name = "api_keys", description = "API key management endpoints"),
+        (name = "secrets", description = "Secret management endpoints"),
     )
 )]
 pub struct ApiDoc;
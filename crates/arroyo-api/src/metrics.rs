@@ -1,5 +1,5 @@
 use axum::extract::{Path, State};
-use axum::Json;
+use axum::{Extension, Json};
 use base64::engine::general_purpose;
 use base64::Engine;
 use std::str::FromStr;
@@ -7,22 +7,46 @@ use std::{collections::HashMap, env, time::SystemTime};
 
 use crate::pipelines::query_job_by_pub_id;
 use crate::rest::AppState;
-use crate::rest_utils::{authenticate, client, BearerAuth, ErrorResp};
+use crate::rest_utils::{client, log_and_map, ErrorResp};
+use crate::AuthData;
 use arroyo_rpc::api_types::metrics::{
     Metric, MetricGroup, MetricNames, OperatorMetricGroup, SubtaskMetrics,
 };
 use arroyo_rpc::api_types::OperatorMetricGroupCollection;
+use arroyo_rpc::grpc::controller_grpc_client::ControllerGrpcClient;
+use arroyo_rpc::grpc::JobMetricsReq;
 use arroyo_types::{
     to_millis, API_METRICS_RATE_ENV, BYTES_RECV, BYTES_SENT, MESSAGES_RECV, MESSAGES_SENT,
-    TX_QUEUE_REM, TX_QUEUE_SIZE,
+    METRICS_BACKEND_ENV, TX_QUEUE_REM, TX_QUEUE_SIZE,
 };
 use http::StatusCode;
 use http::{header::AUTHORIZATION, HeaderMap, HeaderValue};
 use once_cell::sync::Lazy;
 use prometheus_http_query::Client;
+use tonic::Request;
+use tracing::warn;
 
 const METRICS_GRANULARITY_SECS: f64 = 5.0;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MetricsBackend {
+    Prometheus,
+    Controller,
+}
+
+static METRICS_BACKEND: Lazy<MetricsBackend> =
+    Lazy::new(|| match env::var(METRICS_BACKEND_ENV).ok().as_deref() {
+        Some("controller") => MetricsBackend::Controller,
+        None | Some("prometheus") => MetricsBackend::Prometheus,
+        Some(other) => {
+            warn!(
+                "Unknown metrics backend '{}'; falling back to prometheus",
+                other
+            );
+            MetricsBackend::Prometheus
+        }
+    });
+
 static METRICS_CLIENT: Lazy<Client> = Lazy::new(|| {
     let mut headers = HeaderMap::new();
     if let Ok(basic_auth) = std::env::var("PROM_AUTH") {
@@ -90,13 +114,91 @@ fn get_query(metric_name: MetricNames, job_id: &str, run_id: &u64, rate: &str) -
 )]
 pub async fn get_operator_metric_groups(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path((pipeline_pub_id, job_pub_id)): Path<(String, String)>,
 ) -> Result<Json<OperatorMetricGroupCollection>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let job = query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &client, &auth_data).await?;
+
+    let collection = match *METRICS_BACKEND {
+        MetricsBackend::Prometheus => prometheus_metric_groups(&job.id, &job.run_id).await?,
+        MetricsBackend::Controller => {
+            controller_metric_groups(&state.controller_addr, &job.id).await?
+        }
+    };
+
+    Ok(Json(collection))
+}
+
+fn metric_name(name: &str) -> Option<MetricNames> {
+    match name {
+        "bytes_recv" => Some(MetricNames::BytesRecv),
+        "bytes_sent" => Some(MetricNames::BytesSent),
+        "messages_recv" => Some(MetricNames::MessagesRecv),
+        "messages_sent" => Some(MetricNames::MessagesSent),
+        "backpressure" => Some(MetricNames::Backpressure),
+        _ => None,
+    }
+}
+
+/// Reads a job's metrics from the in-memory store that the controller builds from worker heartbeats
+async fn controller_metric_groups(
+    controller_addr: &str,
+    job_id: &str,
+) -> Result<OperatorMetricGroupCollection, ErrorResp> {
+    let mut controller = ControllerGrpcClient::connect(controller_addr.to_string())
+        .await
+        .map_err(log_and_map)?;
+
+    let operators = controller
+        .get_job_metrics(Request::new(JobMetricsReq {
+            job_id: job_id.to_string(),
+        }))
+        .await
+        .map_err(log_and_map)?
+        .into_inner()
+        .operators;
+
+    let data = operators
+        .into_iter()
+        .map(|op| OperatorMetricGroup {
+            operator_id: op.operator_id,
+            metric_groups: op
+                .metrics
+                .into_iter()
+                .filter_map(|m| {
+                    Some(MetricGroup {
+                        name: metric_name(&m.name)?,
+                        subtasks: m
+                            .subtasks
+                            .into_iter()
+                            .map(|s| SubtaskMetrics {
+                                index: s.index,
+                                metrics: s
+                                    .points
+                                    .into_iter()
+                                    .map(|p| Metric {
+                                        time: p.time,
+                                        value: p.value,
+                                    })
+                                    .collect(),
+                            })
+                            .collect(),
+                    })
+                })
+                .collect(),
+        })
+        .filter(|op| !op.metric_groups.is_empty())
+        .collect();
+
+    Ok(OperatorMetricGroupCollection { data })
+}
+
+async fn prometheus_metric_groups(
+    job_id: &str,
+    run_id: &u64,
+) -> Result<OperatorMetricGroupCollection, ErrorResp> {
     let rate = env::var(API_METRICS_RATE_ENV).unwrap_or_else(|_| "15s".to_string());
     let end = (to_millis(SystemTime::now()) / 1000) as i64;
     let start = end - 5 * 60;
@@ -104,7 +206,7 @@ pub async fn get_operator_metric_groups(
     let result = tokio::try_join!(
         METRICS_CLIENT
             .query_range(
-                get_query(MetricNames::BytesRecv, &job.id, &job.run_id, &rate),
+                get_query(MetricNames::BytesRecv, job_id, run_id, &rate),
                 start,
                 end,
                 METRICS_GRANULARITY_SECS
@@ -112,7 +214,7 @@ pub async fn get_operator_metric_groups(
             .get(),
         METRICS_CLIENT
             .query_range(
-                get_query(MetricNames::BytesSent, &job.id, &job.run_id, &rate),
+                get_query(MetricNames::BytesSent, job_id, run_id, &rate),
                 start,
                 end,
                 METRICS_GRANULARITY_SECS
@@ -120,7 +222,7 @@ pub async fn get_operator_metric_groups(
             .get(),
         METRICS_CLIENT
             .query_range(
-                get_query(MetricNames::MessagesRecv, &job.id, &job.run_id, &rate),
+                get_query(MetricNames::MessagesRecv, job_id, run_id, &rate),
                 start,
                 end,
                 METRICS_GRANULARITY_SECS
@@ -128,7 +230,7 @@ pub async fn get_operator_metric_groups(
             .get(),
         METRICS_CLIENT
             .query_range(
-                get_query(MetricNames::MessagesSent, &job.id, &job.run_id, &rate),
+                get_query(MetricNames::MessagesSent, job_id, run_id, &rate),
                 start,
                 end,
                 METRICS_GRANULARITY_SECS
@@ -136,7 +238,7 @@ pub async fn get_operator_metric_groups(
             .get(),
         METRICS_CLIENT
             .query_range(
-                get_query(MetricNames::Backpressure, &job.id, &job.run_id, &rate),
+                get_query(MetricNames::Backpressure, job_id, run_id, &rate),
                 start,
                 end,
                 METRICS_GRANULARITY_SECS
@@ -209,7 +311,7 @@ pub async fn get_operator_metric_groups(
                 }
             }
 
-            Ok(Json(collection))
+            Ok(collection)
         }
         Err(_) => Err(ErrorResp {
             status_code: StatusCode::INTERNAL_SERVER_ERROR,
@@ -2,7 +2,7 @@ use anyhow::{anyhow, bail, Context};
 use arrow_schema::SchemaRef;
 use arroyo_connectors::connector_for_type;
 use axum::extract::{Path, Query, State};
-use axum::Json;
+use axum::{Extension, Json};
 use axum_extra::extract::WithRejection;
 use cornucopia_async::{GenericClient, Params};
 use deadpool_postgres::{Object, Transaction};
@@ -15,11 +15,11 @@ use std::env;
 use petgraph::visit::NodeRef;
 use std::time::Duration;
 
-use crate::{compiler_service, connection_profiles, jobs, pipelines, types};
+use crate::{compiler_service, connection_profiles, jobs, pipelines, savepoints, types};
 use arroyo_datastream::preview_sink;
 use arroyo_rpc::api_types::pipelines::{
-    Job, Pipeline, PipelinePatch, PipelinePost, PipelineRestart, QueryValidationResult, StopType,
-    ValidateQueryPost,
+    Job, Pipeline, PipelinePatch, PipelinePost, PipelineRestart, QueryValidationResult,
+    RestartStrategy, StopType, ValidateQueryPost,
 };
 use arroyo_rpc::api_types::udfs::{GlobalUdf, Udf};
 use arroyo_rpc::api_types::{JobCollection, PaginationQueryParams, PipelineCollection};
@@ -36,7 +36,7 @@ use arroyo_rpc::formats::Format;
 use arroyo_rpc::grpc::compiler_grpc_client::CompilerGrpcClient;
 use arroyo_rpc::public_ids::{generate_id, IdTypes};
 use arroyo_rpc::schema_resolver::{ConfluentSchemaRegistry, ConfluentSchemaType};
-use arroyo_rpc::{error_chain, OperatorConfig};
+use arroyo_rpc::{error_chain, OperatorConfig, RateLimit};
 use arroyo_server_common::log_event;
 use prost::Message;
 use serde_json::json;
@@ -48,13 +48,13 @@ use crate::queries::api_queries;
 use crate::queries::api_queries::{DbPipeline, DbPipelineJob, GetPipelinesParams};
 use crate::rest::AppState;
 use crate::rest_utils::{
-    authenticate, bad_request, client, log_and_map, not_found, paginate_results, required_field,
-    unauthorized, validate_pagination_params, ApiError, BearerAuth, ErrorResp,
+    bad_request, client, log_and_map, not_found, paginate_results, required_field, unauthorized,
+    validate_pagination_params, ApiError, ErrorResp,
 };
 use crate::types::public::{PipelineType, RestartMode, StopMode};
 use crate::udfs::build_udf;
 use crate::{connection_tables, to_micros};
-use crate::{handle_db_error, AuthData};
+use crate::{handle_db_error, AuthData, OrgMetadata};
 use create_pipeline_req::Config::Sql;
 
 const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
@@ -182,6 +182,98 @@ fn set_parallelism(program: &mut LogicalProgram, parallelism: usize) {
     }
 }
 
+/// Checks that per-operator parallelism overrides refer to operators in the pipeline and are
+/// within the limits of the plan
+fn validate_operator_parallelism(
+    operator_parallelism: &HashMap<String, u64>,
+    operator_ids: &[String],
+    auth_data: &AuthData,
+) -> Result<(), ErrorResp> {
+    for (operator_id, parallelism) in operator_parallelism {
+        if !operator_ids.contains(operator_id) {
+            return Err(bad_request(format!(
+                "Pipeline does not have an operator '{}'",
+                operator_id
+            )));
+        }
+
+        if *parallelism == 0 || *parallelism > auth_data.org_metadata.max_parallelism as u64 {
+            return Err(bad_request(format!(
+                "Parallelism of operator '{}' must be between 1 and {}",
+                operator_id, auth_data.org_metadata.max_parallelism
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the pipeline's sources are within the organization's rate quotas, and sets rate
+/// limits on them so that the quotas are also enforced as the sources run
+fn apply_source_quotas(
+    program: &mut LogicalProgram,
+    quotas: &OrgMetadata,
+) -> Result<(), ErrorResp> {
+    for node in program.graph.node_weights_mut() {
+        if node.operator_name != OperatorName::ConnectorSource {
+            continue;
+        }
+
+        let mut op = ConnectorOp::decode(&node.operator_config[..]).map_err(log_and_map)?;
+        let mut config: OperatorConfig = serde_json::from_str(&op.config).map_err(log_and_map)?;
+
+        let quota = match op.connector.as_str() {
+            connector @ ("impulse" | "nexmark") => {
+                let quota = if connector == "impulse" {
+                    quotas.max_impulse_qps
+                } else {
+                    quotas.max_nexmark_qps
+                };
+
+                let event_rate = config
+                    .table
+                    .get("event_rate")
+                    .and_then(|r| r.as_f64())
+                    .unwrap_or_default();
+
+                if event_rate > quota {
+                    return Err(bad_request(format!(
+                        "The {} source '{}' has an event rate of {}, but your organization's quota \
+                        allows at most {} events per second",
+                        connector, op.description, event_rate, quota
+                    )));
+                }
+
+                quota
+            }
+            connector @ ("kafka" | "confluent") => {
+                if quotas.kafka_qps == 0 {
+                    return Err(bad_request(format!(
+                        "The {} source '{}' cannot be used, because your organization's quota \
+                        does not allow reading from Kafka",
+                        connector, op.description
+                    )));
+                }
+
+                quotas.kafka_qps as f64
+            }
+            _ => continue,
+        };
+
+        // the quota applies across all of the source's subtasks, separately from any rate limit
+        // that the source was configured with
+        if quota < u32::MAX as f64 {
+            config.quota = Some(RateLimit {
+                messages_per_second: quota as u32,
+            });
+            op.config = serde_json::to_string(&config).map_err(log_and_map)?;
+            node.operator_config = op.encode_to_vec();
+        }
+    }
+
+    Ok(())
+}
+
 #[allow(unused)]
 async fn try_register_confluent_schema(
     sink: &mut ConnectorOp,
@@ -224,7 +316,7 @@ async fn try_register_confluent_schema(
         }
         Some(Format::Json(mut json)) => {
             if json.confluent_schema_registry && json.schema_id.is_none() {
-                let json_schema = ArrowSerializer::json_schema(&*schema);
+                let json_schema = ArrowSerializer::json_schema(&*schema, &json);
 
                 let id = schema_registry
                     .write_schema(json_schema.to_string(), ConfluentSchemaType::Json)
@@ -358,7 +450,27 @@ pub(crate) async fn create_pipeline<'a>(
     //     )));
     // }
 
-    set_parallelism(&mut compiled.program, 1);
+    if is_preview {
+        set_parallelism(&mut compiled.program, 1);
+    }
+
+    let max_parallelism = compiled
+        .program
+        .graph
+        .node_weights()
+        .map(|n| n.parallelism)
+        .max()
+        .unwrap_or(0);
+
+    if max_parallelism > auth.org_metadata.max_parallelism as usize {
+        return Err(bad_request(format!(
+            "This pipeline has an operator with parallelism {}, but your plan allows you to run \
+            pipelines up to parallelism {}; contact support@arroyo.systems for an increase",
+            max_parallelism, auth.org_metadata.max_parallelism
+        )));
+    }
+
+    apply_source_quotas(&mut compiled.program, &auth.org_metadata)?;
 
     if is_preview && !env::var("PREVIEW_SINKS").is_ok_and(|s| s == "true") {
         for node in compiled.program.graph.node_weights_mut() {
@@ -467,6 +579,16 @@ impl TryInto<Pipeline> for DbPipeline {
             action_text,
             action_in_progress,
             preview: self.ttl_micros.is_some(),
+            autoscaling: self
+                .autoscaling
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(log_and_map)?,
+            restart_strategy: self
+                .restart_strategy
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(log_and_map)?,
         })
     }
 }
@@ -483,6 +605,7 @@ impl Into<Job> for DbPipelineJob {
             tasks: self.tasks.map(|t| t as u64),
             failure_message: self.failure_message,
             created_at: to_micros(self.created_at),
+            next_retry_time: self.next_retry_time.map(to_micros),
         }
     }
 }
@@ -499,11 +622,10 @@ impl Into<Job> for DbPipelineJob {
 )]
 pub async fn validate_query(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     WithRejection(Json(validate_query_post), _): WithRejection<Json<ValidateQueryPost>, ApiError>,
 ) -> Result<Json<QueryValidationResult>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let udfs = validate_query_post.udfs.unwrap_or(vec![]);
 
@@ -548,11 +670,10 @@ pub async fn validate_query(
 )]
 pub async fn post_pipeline(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     WithRejection(Json(pipeline_post), _): WithRejection<Json<PipelinePost>, ApiError>,
 ) -> Result<Json<Pipeline>, ErrorResp> {
     let mut client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let preview = pipeline_post.preview.unwrap_or(false);
 
@@ -580,7 +701,7 @@ pub async fn post_pipeline(
         .await
         .map_err(log_and_map)?;
 
-    let (pipeline_id, _program) = pipelines::create_pipeline(
+    let (pipeline_id, program) = pipelines::create_pipeline(
         &create_pipeline_req,
         &pipeline_pub_id,
         auth_data.clone(),
@@ -588,6 +709,31 @@ pub async fn post_pipeline(
     )
     .await?;
 
+    let operator_ids: Vec<_> = program.tasks_per_operator().into_keys().collect();
+
+    let operator_parallelism = pipeline_post.operator_parallelism.unwrap_or_default();
+    validate_operator_parallelism(&operator_parallelism, &operator_ids, &auth_data)?;
+
+    if let Some(restart_strategy) = &pipeline_post.restart_strategy {
+        validate_restart_strategy(restart_strategy)?;
+    }
+
+    let allow_dropped_state = pipeline_post.allow_dropped_state.unwrap_or(false);
+    let restore_savepoint_id = match &pipeline_post.savepoint_id {
+        Some(savepoint_id) => {
+            let savepoint = savepoints::savepoint_for_restore(
+                savepoint_id,
+                &operator_ids,
+                allow_dropped_state,
+                &auth_data,
+                &transaction,
+            )
+            .await?;
+            Some(savepoint.id)
+        }
+        None => None,
+    };
+
     let create_job = CreateJobReq {
         pipeline_id: format!("{}", pipeline_id),
         checkpoint_interval_micros: DEFAULT_CHECKPOINT_INTERVAL.as_micros() as u64,
@@ -598,6 +744,10 @@ pub async fn post_pipeline(
         create_job,
         &pipeline_post.name,
         &pipeline_id,
+        restore_savepoint_id,
+        allow_dropped_state,
+        &operator_parallelism,
+        pipeline_post.restart_strategy.as_ref(),
         &auth_data,
         &transaction,
     )
@@ -639,12 +789,11 @@ pub async fn post_pipeline(
 )]
 pub async fn patch_pipeline(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path(pipeline_pub_id): Path<String>,
     WithRejection(Json(pipeline_patch), _): WithRejection<Json<PipelinePatch>, ApiError>,
 ) -> Result<Json<Pipeline>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     // this assumes there is just one job for the pipeline
     let job_id = api_queries::get_pipeline_jobs()
@@ -674,25 +823,91 @@ pub async fn patch_pipeline(
         }
     }
 
-    let parallelism_overrides = if let Some(parallelism) = pipeline_patch.parallelism {
-        let res = api_queries::get_job_details()
-            .bind(&client, &auth_data.organization_id, &job_id)
-            .opt()
-            .await
-            .map_err(log_and_map)?
-            .ok_or_else(|| not_found("Job"))?;
+    if let Some(parallelism) = pipeline_patch.parallelism {
+        if parallelism == 0 || parallelism > auth_data.org_metadata.max_parallelism as u64 {
+            return Err(bad_request(format!(
+                "Parallelism must be between 1 and {}",
+                auth_data.org_metadata.max_parallelism
+            )));
+        }
+    }
 
-        let program = ArrowProgram::decode(&res.program[..]).map_err(log_and_map)?;
-        let map: HashMap<String, u32> = program
-            .nodes
-            .into_iter()
-            .map(|node| (node.node_id, parallelism as u32))
-            .collect();
+    if *stop == Some(types::public::StopMode::none) {
+        jobs::check_running_jobs_quota(&client, &auth_data, Some(&job_id)).await?;
+    }
 
-        Some(serde_json::to_value(map).map_err(log_and_map)?)
-    } else {
-        None
-    };
+    if let Some(autoscaling) = &pipeline_patch.autoscaling {
+        if autoscaling.max_parallelism > auth_data.org_metadata.max_parallelism {
+            return Err(bad_request(format!(
+                "autoscaling max_parallelism must not be greater than {}",
+                auth_data.org_metadata.max_parallelism
+            )));
+        }
+
+        if autoscaling.min_parallelism == 0 {
+            return Err(bad_request(
+                "autoscaling min_parallelism must be at least 1".to_string(),
+            ));
+        }
+
+        if autoscaling.min_parallelism > autoscaling.max_parallelism {
+            return Err(bad_request(
+                "autoscaling min_parallelism must not be greater than max_parallelism".to_string(),
+            ));
+        }
+    }
+
+    let autoscaling = pipeline_patch
+        .autoscaling
+        .as_ref()
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(log_and_map)?;
+
+    if let Some(restart_strategy) = &pipeline_patch.restart_strategy {
+        validate_restart_strategy(restart_strategy)?;
+    }
+
+    let restart_strategy = pipeline_patch
+        .restart_strategy
+        .as_ref()
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(log_and_map)?;
+
+    let parallelism_overrides =
+        if pipeline_patch.parallelism.is_some() || pipeline_patch.operator_parallelism.is_some() {
+            let res = api_queries::get_job_details()
+                .bind(&client, &auth_data.organization_id, &job_id)
+                .opt()
+                .await
+                .map_err(log_and_map)?
+                .ok_or_else(|| not_found("Job"))?;
+
+            let program = ArrowProgram::decode(&res.program[..]).map_err(log_and_map)?;
+            let operator_ids: Vec<String> = program.nodes.into_iter().map(|n| n.node_id).collect();
+
+            let mut map: HashMap<String, u32> = match pipeline_patch.parallelism {
+                Some(parallelism) => operator_ids
+                    .iter()
+                    .map(|id| (id.clone(), parallelism as u32))
+                    .collect(),
+                None => serde_json::from_value(res.parallelism_overrides).map_err(log_and_map)?,
+            };
+
+            if let Some(operator_parallelism) = &pipeline_patch.operator_parallelism {
+                validate_operator_parallelism(operator_parallelism, &operator_ids, &auth_data)?;
+                map.extend(
+                    operator_parallelism
+                        .iter()
+                        .map(|(id, p)| (id.clone(), *p as u32)),
+                );
+            }
+
+            Some(serde_json::to_value(map).map_err(log_and_map)?)
+        } else {
+            None
+        };
 
     let res = api_queries::update_job()
         .bind(
@@ -702,6 +917,8 @@ pub async fn patch_pipeline(
             &stop,
             &interval.map(|i| i.as_micros() as i64),
             &parallelism_overrides,
+            &autoscaling,
+            &restart_strategy,
             &job_id,
             &auth_data.organization_id,
         )
@@ -716,6 +933,40 @@ pub async fn patch_pipeline(
     Ok(Json(pipeline))
 }
 
+fn validate_restart_strategy(restart_strategy: &RestartStrategy) -> Result<(), ErrorResp> {
+    match restart_strategy {
+        RestartStrategy::ExponentialBackoff(backoff) => {
+            if backoff.initial_delay_millis == 0 {
+                return Err(bad_request(
+                    "initialDelayMillis must be greater than 0".to_string(),
+                ));
+            }
+
+            if backoff.max_delay_millis < backoff.initial_delay_millis {
+                return Err(bad_request(
+                    "maxDelayMillis must not be less than initialDelayMillis".to_string(),
+                ));
+            }
+
+            if !backoff.multiplier.is_finite() || backoff.multiplier < 1.0 {
+                return Err(bad_request("multiplier must be at least 1".to_string()));
+            }
+
+            if !(0.0..=1.0).contains(&backoff.jitter) {
+                return Err(bad_request("jitter must be between 0 and 1".to_string()));
+            }
+        }
+        RestartStrategy::FailureRate(failure_rate) => {
+            if failure_rate.window_secs == 0 {
+                return Err(bad_request("windowSecs must be greater than 0".to_string()));
+            }
+        }
+        RestartStrategy::FixedDelay(_) | RestartStrategy::Never => {}
+    }
+
+    Ok(())
+}
+
 /// Restart a pipeline
 #[utoipa::path(
     post,
@@ -730,12 +981,11 @@ pub async fn patch_pipeline(
 )]
 pub async fn restart_pipeline(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path(id): Path<String>,
     WithRejection(Json(req), _): WithRejection<Json<PipelineRestart>, ApiError>,
 ) -> Result<Json<Pipeline>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let job_id = api_queries::get_pipeline_jobs()
         .bind(&client, &auth_data.organization_id, &id)
@@ -784,11 +1034,10 @@ pub async fn restart_pipeline(
 )]
 pub async fn get_pipelines(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     query_params: Query<PaginationQueryParams>,
 ) -> Result<Json<PipelineCollection>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let (starting_after, limit) =
         validate_pagination_params(query_params.starting_after.clone(), query_params.limit)?;
@@ -840,11 +1089,10 @@ pub async fn get_pipelines(
 )]
 pub async fn get_pipeline(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path(pipeline_pub_id): Path<String>,
 ) -> Result<Json<Pipeline>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let pipeline = query_pipeline_by_pub_id(&pipeline_pub_id, &client, &auth_data).await?;
     Ok(Json(pipeline))
@@ -864,11 +1112,10 @@ pub async fn get_pipeline(
 )]
 pub async fn delete_pipeline(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path(pipeline_pub_id): Path<String>,
 ) -> Result<(), ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     let jobs: Vec<Job> = api_queries::get_pipeline_jobs()
         .bind(&client, &auth_data.organization_id, &pipeline_pub_id)
@@ -914,11 +1161,10 @@ pub async fn delete_pipeline(
 )]
 pub async fn get_pipeline_jobs(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path(pipeline_pub_id): Path<String>,
 ) -> Result<Json<JobCollection>, ErrorResp> {
     let client = client(&state.pool).await?;
-    let auth_data = authenticate(&state.pool, bearer_auth).await?;
 
     query_pipeline_by_pub_id(&pipeline_pub_id, &client, &auth_data).await?;
 
@@ -968,3 +1214,161 @@ pub async fn query_job_by_pub_id(
 
     Ok(res.into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arroyo_datastream::logical::{LogicalGraph, LogicalNode, ProgramConfig};
+
+    fn source(connector: &str, table: serde_json::Value) -> LogicalNode {
+        let config = OperatorConfig {
+            table,
+            ..Default::default()
+        };
+
+        LogicalNode {
+            operator_id: format!("source_{}", connector),
+            description: format!("{} source", connector),
+            operator_name: OperatorName::ConnectorSource,
+            operator_config: ConnectorOp {
+                connector: connector.to_string(),
+                config: serde_json::to_string(&config).unwrap(),
+                description: connector.to_string(),
+            }
+            .encode_to_vec(),
+            parallelism: 4,
+        }
+    }
+
+    fn program(nodes: Vec<LogicalNode>) -> LogicalProgram {
+        let mut graph = LogicalGraph::new();
+        for node in nodes {
+            graph.add_node(node);
+        }
+
+        LogicalProgram {
+            graph,
+            program_config: ProgramConfig {
+                udf_dylibs: HashMap::new(),
+            },
+        }
+    }
+
+    fn quotas(quotas: serde_json::Value) -> OrgMetadata {
+        serde_json::from_value(quotas).unwrap()
+    }
+
+    // the quota set on each source, by connector
+    fn applied_quotas(program: &LogicalProgram) -> HashMap<String, Option<u32>> {
+        program
+            .graph
+            .node_weights()
+            .map(|node| {
+                let op = ConnectorOp::decode(&node.operator_config[..]).unwrap();
+                let config: OperatorConfig = serde_json::from_str(&op.config).unwrap();
+                assert_eq!(config.rate_limit, None);
+                (op.connector, config.quota.map(|q| q.messages_per_second))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_source_quotas() {
+        let mut program = program(vec![
+            source("impulse", json!({"event_rate": 100.0})),
+            source("nexmark", json!({"event_rate": 500.0})),
+            source("kafka", json!({"topic": "events"})),
+            source("sse", json!({"endpoint": "http://localhost"})),
+        ]);
+
+        apply_source_quotas(
+            &mut program,
+            &quotas(json!({
+                "max_impulse_qps": 200.0,
+                "max_nexmark_qps": 1000.0,
+                "kafka_qps": 5000,
+            })),
+        )
+        .unwrap();
+
+        assert_eq!(
+            applied_quotas(&program),
+            HashMap::from([
+                ("impulse".to_string(), Some(200)),
+                ("nexmark".to_string(), Some(1000)),
+                ("kafka".to_string(), Some(5000)),
+                ("sse".to_string(), None),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unlimited_quotas_are_not_applied() {
+        let mut program = program(vec![
+            source("impulse", json!({"event_rate": 100.0})),
+            source("kafka", json!({"topic": "events"})),
+        ]);
+
+        apply_source_quotas(
+            &mut program,
+            &quotas(json!({
+                "max_impulse_qps": f64::MAX,
+                "kafka_qps": u32::MAX,
+            })),
+        )
+        .unwrap();
+
+        assert_eq!(
+            applied_quotas(&program),
+            HashMap::from([("impulse".to_string(), None), ("kafka".to_string(), None)])
+        );
+    }
+
+    #[test]
+    fn test_sources_over_quota_are_rejected() {
+        let mut over = program(vec![source("nexmark", json!({"event_rate": 2000.0}))]);
+        let err = apply_source_quotas(&mut over, &quotas(json!({"max_nexmark_qps": 1000.0})))
+            .unwrap_err();
+        assert_eq!(err.status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.message,
+            "The nexmark source 'nexmark' has an event rate of 2000, but your organization's \
+            quota allows at most 1000 events per second"
+        );
+
+        let mut kafka = program(vec![source("confluent", json!({"topic": "events"}))]);
+        let err = apply_source_quotas(&mut kafka, &quotas(json!({"kafka_qps": 0}))).unwrap_err();
+        assert_eq!(err.status_code, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_validate_restart_strategy() {
+        let strategy = |value: serde_json::Value| -> RestartStrategy {
+            serde_json::from_value(value).unwrap()
+        };
+
+        assert!(validate_restart_strategy(&RestartStrategy::default()).is_ok());
+
+        // errors refer to fields by the names they have in the API
+        let err = validate_restart_strategy(&strategy(json!({"exponentialBackoff": {
+            "initialDelayMillis": 1000,
+            "maxDelayMillis": 500,
+            "multiplier": 2.0,
+            "jitter": 0.1,
+        }})))
+        .unwrap_err();
+        assert_eq!(err.status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.message,
+            "maxDelayMillis must not be less than initialDelayMillis"
+        );
+
+        let err = validate_restart_strategy(&strategy(json!({"failureRate": {
+            "maxFailures": 3,
+            "windowSecs": 0,
+            "delayMillis": 0,
+        }})))
+        .unwrap_err();
+        assert_eq!(err.message, "windowSecs must be greater than 0");
+    }
+}
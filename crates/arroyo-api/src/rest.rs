@@ -1,10 +1,13 @@
 use axum::body::Body;
-use axum::response::IntoResponse;
+use axum::extract::State;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
 use axum::{
     routing::{delete, get, patch, post},
     Json, Router,
 };
 use deadpool_postgres::Pool;
+use http::{Method, Request};
 
 use once_cell::sync::Lazy;
 use std::env;
@@ -17,6 +20,7 @@ use tower_http::services::ServeDir;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
+use crate::api_keys::{create_api_key, delete_api_key, get_api_keys};
 use crate::connection_profiles::{
     create_connection_profile, delete_connection_profile, get_connection_profile_autocomplete,
     get_connection_profiles, test_connection_profile,
@@ -27,16 +31,20 @@ use crate::connection_tables::{
 };
 use crate::connectors::get_connectors;
 use crate::jobs::{
-    get_checkpoint_details, get_job_checkpoints, get_job_errors, get_job_output, get_jobs,
+    get_checkpoint_details, get_checkpoint_state, get_checkpoint_table, get_job_checkpoints,
+    get_job_errors, get_job_output, get_jobs,
 };
 use crate::metrics::get_operator_metric_groups;
 use crate::pipelines::{
     delete_pipeline, get_pipeline, get_pipeline_jobs, get_pipelines, patch_pipeline, post_pipeline,
     restart_pipeline, validate_query,
 };
-use crate::rest_utils::not_found;
+use crate::rest_utils::{authenticate, forbidden, not_found, BearerAuth, ErrorResp};
+use crate::savepoints::{create_savepoint, get_job_savepoints, get_savepoint, get_savepoints};
+use crate::secrets::{create_secret, delete_secret, get_secrets};
 use crate::udfs::{create_udf, delete_udf, get_udfs, validate_udf};
-use crate::ApiDoc;
+use crate::{ApiDoc, AuthData};
+use arroyo_rpc::api_types::auth::Role;
 use arroyo_types::{telemetry_enabled, API_ENDPOINT_ENV, ASSET_DIR_ENV};
 
 #[derive(Clone)]
@@ -62,6 +70,53 @@ pub async fn api_fallback() -> impl IntoResponse {
     not_found("Route")
 }
 
+/// The role needed to call an api route, given its path relative to /api/v1
+fn required_role(method: &Method, path: &str) -> Option<Role> {
+    if path == "/ping" || path.starts_with("/swagger-ui") || path.starts_with("/api-docs") {
+        return None;
+    }
+
+    if path == "/api_keys" || path.starts_with("/api_keys/") {
+        return Some(Role::Admin);
+    }
+
+    match *method {
+        Method::GET | Method::HEAD => Some(Role::Viewer),
+        _ => Some(Role::Operator),
+    }
+}
+
+fn check_role(auth_data: &AuthData, role: Role) -> Result<(), ErrorResp> {
+    if auth_data.role < role {
+        return Err(forbidden(format!(
+            "This endpoint requires the {:?} role",
+            role
+        )));
+    }
+
+    Ok(())
+}
+
+/// Authenticates requests to api routes and checks that the caller has the role the route
+/// requires. The caller's `AuthData` is added to the request's extensions, where handlers
+/// extract it from rather than authenticating again.
+async fn authorize<B>(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, ErrorResp> {
+    if let Some(path) = request.uri().path().strip_prefix("/api/v1") {
+        if let Some(role) = required_role(request.method(), path) {
+            let auth_data = authenticate(&state.pool, bearer_auth).await?;
+            check_role(&auth_data, role)?;
+            request.extensions_mut().insert(auth_data);
+        }
+    }
+
+    Ok(next.run(request).await)
+}
+
 pub fn create_rest_app(pool: Pool, controller_addr: &str) -> Router {
     let asset_dir = env::var(ASSET_DIR_ENV).unwrap_or_else(|_| "webui/dist".to_string());
 
@@ -100,7 +155,19 @@ pub fn create_rest_app(pool: Pool, controller_addr: &str) -> Router {
             "/:job_id/checkpoints/:checkpoint_id/operator_checkpoint_groups",
             get(get_checkpoint_details),
         )
+        .route(
+            "/:job_id/checkpoints/:checkpoint_id/state",
+            get(get_checkpoint_state),
+        )
+        .route(
+            "/:job_id/checkpoints/:checkpoint_id/state/:operator_id/:table",
+            get(get_checkpoint_table),
+        )
         .route("/:job_id/output", get(get_job_output))
+        .route(
+            "/:job_id/savepoints",
+            get(get_job_savepoints).post(create_savepoint),
+        )
         .route(
             "/:job_id/operator_metric_groups",
             get(get_operator_metric_groups),
@@ -129,9 +196,17 @@ pub fn create_rest_app(pool: Pool, controller_addr: &str) -> Router {
         .route("/udfs", get(get_udfs))
         .route("/udfs/validate", post(validate_udf))
         .route("/udfs/:id", delete(delete_udf))
+        .route("/api_keys", post(create_api_key))
+        .route("/api_keys", get(get_api_keys))
+        .route("/api_keys/:id", delete(delete_api_key))
+        .route("/secrets", post(create_secret))
+        .route("/secrets", get(get_secrets))
+        .route("/secrets/:id", delete(delete_secret))
         .route("/pipelines", post(post_pipeline))
         .route("/pipelines", get(get_pipelines))
         .route("/jobs", get(get_jobs))
+        .route("/savepoints", get(get_savepoints))
+        .route("/savepoints/:id", get(get_savepoint))
         .route("/pipelines/validate_query", post(validate_query))
         .route("/pipelines/:id", patch(patch_pipeline))
         .route("/pipelines/:id", get(get_pipeline))
@@ -140,6 +215,11 @@ pub fn create_rest_app(pool: Pool, controller_addr: &str) -> Router {
         .nest("/pipelines/:id/jobs", jobs_routes)
         .fallback(api_fallback);
 
+    let state = AppState {
+        controller_addr: controller_addr.to_string(),
+        pool,
+    };
+
     Router::new()
         .merge(
             SwaggerUi::new("/api/v1/swagger-ui")
@@ -148,9 +228,90 @@ pub fn create_rest_app(pool: Pool, controller_addr: &str) -> Router {
         .nest("/api/v1", api_routes)
         .route_service("/", fallback)
         .fallback_service(serve_dir)
-        .with_state(AppState {
-            controller_addr: controller_addr.to_string(),
-            pool,
-        })
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state)
         .layer(cors)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrgMetadata;
+    use http::StatusCode;
+
+    fn auth_data(role: Role) -> AuthData {
+        AuthData {
+            user_id: "user".to_string(),
+            organization_id: "org".to_string(),
+            role,
+            org_metadata: OrgMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_required_role() {
+        // the ping and documentation routes are public
+        for path in ["/ping", "/swagger-ui/index.html", "/api-docs/openapi.json"] {
+            assert_eq!(required_role(&Method::GET, path), None, "{}", path);
+        }
+
+        // reads need the viewer role and everything else the operator role
+        assert_eq!(
+            required_role(&Method::GET, "/pipelines"),
+            Some(Role::Viewer)
+        );
+        assert_eq!(
+            required_role(&Method::HEAD, "/pipelines/pl_1"),
+            Some(Role::Viewer)
+        );
+        for method in [Method::POST, Method::PATCH, Method::PUT, Method::DELETE] {
+            assert_eq!(
+                required_role(&method, "/pipelines/pl_1"),
+                Some(Role::Operator),
+                "{}",
+                method
+            );
+        }
+
+        // api keys can only be listed or managed by admins
+        for method in [Method::GET, Method::POST, Method::DELETE] {
+            assert_eq!(
+                required_role(&method, "/api_keys"),
+                Some(Role::Admin),
+                "{}",
+                method
+            );
+        }
+        assert_eq!(
+            required_role(&Method::DELETE, "/api_keys/ak_1"),
+            Some(Role::Admin)
+        );
+        assert_eq!(
+            required_role(&Method::GET, "/api_keys_other"),
+            Some(Role::Viewer)
+        );
+
+        // routes that don't exist still need to be authenticated
+        assert_eq!(required_role(&Method::GET, "/missing"), Some(Role::Viewer));
+    }
+
+    #[test]
+    fn test_check_role() {
+        let roles = [Role::Viewer, Role::Operator, Role::Admin];
+        for (i, held) in roles.iter().enumerate() {
+            for (j, required) in roles.iter().enumerate() {
+                let result = check_role(&auth_data(*held), *required);
+                if i >= j {
+                    assert!(result.is_ok(), "{:?} should satisfy {:?}", held, required);
+                } else {
+                    let err = result.unwrap_err();
+                    assert_eq!(err.status_code, StatusCode::FORBIDDEN);
+                    assert_eq!(
+                        err.message,
+                        format!("This endpoint requires the {:?} role", required)
+                    );
+                }
+            }
+        }
+    }
+}
@@ -104,6 +104,13 @@ pub(crate) fn unauthorized(message: impl Into<String>) -> ErrorResp {
     }
 }
 
+pub(crate) fn forbidden(message: impl Into<String>) -> ErrorResp {
+    ErrorResp {
+        status_code: StatusCode::FORBIDDEN,
+        message: message.into(),
+    }
+}
+
 pub(crate) fn not_found(object: &str) -> ErrorResp {
     ErrorResp {
         status_code: StatusCode::NOT_FOUND,
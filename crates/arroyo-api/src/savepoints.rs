@@ -0,0 +1,237 @@
+use crate::pipelines::query_job_by_pub_id;
+use crate::queries::api_queries;
+use crate::queries::api_queries::DbSavepoint;
+use crate::rest::AppState;
+use crate::rest_utils::{bad_request, client, log_and_map, not_found, ApiError, ErrorResp};
+use crate::types::public::SavepointState as DbSavepointState;
+use crate::{handle_db_error, to_micros, AuthData};
+use arroyo_rpc::api_types::checkpoints::{Savepoint, SavepointPost, SavepointState};
+use arroyo_rpc::api_types::SavepointCollection;
+use arroyo_rpc::public_ids::{generate_id, IdTypes};
+use axum::extract::{Path, State};
+use axum::{Extension, Json};
+use axum_extra::extract::WithRejection;
+use cornucopia_async::GenericClient;
+use std::collections::HashMap;
+
+impl Into<Savepoint> for DbSavepoint {
+    fn into(self) -> Savepoint {
+        Savepoint {
+            id: self.pub_id,
+            name: self.name,
+            job_id: self.job_id,
+            pipeline_name: self.pipeline_name,
+            state: match self.state {
+                DbSavepointState::pending => SavepointState::Pending,
+                DbSavepointState::ready => SavepointState::Ready,
+                DbSavepointState::failed => SavepointState::Failed,
+            },
+            epoch: self.epoch.map(|e| e as u32),
+            operators: serde_json::from_value(self.operators).unwrap_or_default(),
+            created_at: to_micros(self.created_at),
+            finish_time: self.finish_time.map(to_micros),
+            failure_message: self.failure_message,
+        }
+    }
+}
+
+/// Looks up a savepoint that a new pipeline can be restored from, and checks that every operator
+/// it holds state for is present in the new pipeline (unless dropping that state is allowed)
+pub(crate) async fn savepoint_for_restore(
+    savepoint_pub_id: &str,
+    operator_ids: &[String],
+    allow_dropped_state: bool,
+    auth_data: &AuthData,
+    client: &impl GenericClient,
+) -> Result<DbSavepoint, ErrorResp> {
+    let savepoint = api_queries::get_savepoint()
+        .bind(client, &auth_data.organization_id, &savepoint_pub_id)
+        .opt()
+        .await
+        .map_err(log_and_map)?
+        .ok_or_else(|| not_found("Savepoint"))?;
+
+    if savepoint.state != DbSavepointState::ready {
+        return Err(bad_request(format!(
+            "Savepoint '{}' is not ready to be restored from",
+            savepoint.name
+        )));
+    }
+
+    let operators: HashMap<String, Vec<String>> =
+        serde_json::from_value(savepoint.operators.clone()).map_err(log_and_map)?;
+
+    let mut missing: Vec<_> = operators
+        .keys()
+        .filter(|op| !operator_ids.contains(op))
+        .cloned()
+        .collect();
+    missing.sort();
+
+    if !missing.is_empty() && !allow_dropped_state {
+        return Err(bad_request(format!(
+            "Savepoint '{}' contains state for operators that are not in this pipeline: {}; \
+            set allow_dropped_state to start the pipeline without that state",
+            savepoint.name,
+            missing.join(", ")
+        )));
+    }
+
+    Ok(savepoint)
+}
+
+/// Trigger a savepoint for a running job
+#[utoipa::path(
+    post,
+    path = "/v1/pipelines/{pipeline_id}/jobs/{job_id}/savepoints",
+    tag = "jobs",
+    params(
+        ("pipeline_id" = String, Path, description = "Pipeline id"),
+        ("job_id" = String, Path, description = "Job id")
+    ),
+    request_body = SavepointPost,
+    responses(
+        (status = 200, description = "Created savepoint", body = Savepoint),
+    ),
+)]
+pub async fn create_savepoint(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+    Path((pipeline_pub_id, job_pub_id)): Path<(String, String)>,
+    WithRejection(Json(req), _): WithRejection<Json<SavepointPost>, ApiError>,
+) -> Result<Json<Savepoint>, ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    let job = query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &client, &auth_data).await?;
+
+    if job.state != "Running" {
+        return Err(bad_request(format!(
+            "Savepoints can only be taken of running jobs, but job is {}",
+            job.state
+        )));
+    }
+
+    if req.name.is_empty() {
+        return Err(bad_request("Savepoint name must not be empty"));
+    }
+
+    let details = api_queries::get_job_details()
+        .bind(&client, &auth_data.organization_id, &job_pub_id)
+        .opt()
+        .await
+        .map_err(log_and_map)?
+        .ok_or_else(|| not_found("Job"))?;
+
+    let pub_id = generate_id(IdTypes::Savepoint);
+
+    api_queries::create_savepoint()
+        .bind(
+            &client,
+            &pub_id,
+            &auth_data.organization_id,
+            &auth_data.user_id,
+            &req.name,
+            &job_pub_id,
+            &details.pipeline_name,
+        )
+        .await
+        .map_err(|e| handle_db_error("savepoint", e))?;
+
+    let savepoint = api_queries::get_savepoint()
+        .bind(&client, &auth_data.organization_id, &pub_id)
+        .one()
+        .await
+        .map_err(log_and_map)?;
+
+    Ok(Json(savepoint.into()))
+}
+
+/// List a job's savepoints
+#[utoipa::path(
+    get,
+    path = "/v1/pipelines/{pipeline_id}/jobs/{job_id}/savepoints",
+    tag = "jobs",
+    params(
+        ("pipeline_id" = String, Path, description = "Pipeline id"),
+        ("job_id" = String, Path, description = "Job id")
+    ),
+    responses(
+        (status = 200, description = "Got job's savepoints", body = SavepointCollection),
+    ),
+)]
+pub async fn get_job_savepoints(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+    Path((pipeline_pub_id, job_pub_id)): Path<(String, String)>,
+) -> Result<Json<SavepointCollection>, ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &client, &auth_data).await?;
+
+    let savepoints = api_queries::get_job_savepoints()
+        .bind(&client, &auth_data.organization_id, &job_pub_id)
+        .all()
+        .await
+        .map_err(log_and_map)?
+        .into_iter()
+        .map(|s| s.into())
+        .collect();
+
+    Ok(Json(SavepointCollection { data: savepoints }))
+}
+
+/// List all savepoints
+#[utoipa::path(
+    get,
+    path = "/v1/savepoints",
+    tag = "savepoints",
+    responses(
+        (status = 200, description = "Got savepoints", body = SavepointCollection),
+    ),
+)]
+pub async fn get_savepoints(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+) -> Result<Json<SavepointCollection>, ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    let savepoints = api_queries::get_savepoints()
+        .bind(&client, &auth_data.organization_id)
+        .all()
+        .await
+        .map_err(log_and_map)?
+        .into_iter()
+        .map(|s| s.into())
+        .collect();
+
+    Ok(Json(SavepointCollection { data: savepoints }))
+}
+
+/// Get a single savepoint
+#[utoipa::path(
+    get,
+    path = "/v1/savepoints/{id}",
+    tag = "savepoints",
+    params(
+        ("id" = String, Path, description = "Savepoint id")
+    ),
+    responses(
+        (status = 200, description = "Got savepoint", body = Savepoint),
+    ),
+)]
+pub async fn get_savepoint(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+    Path(savepoint_pub_id): Path<String>,
+) -> Result<Json<Savepoint>, ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    let savepoint = api_queries::get_savepoint()
+        .bind(&client, &auth_data.organization_id, &savepoint_pub_id)
+        .opt()
+        .await
+        .map_err(log_and_map)?
+        .ok_or_else(|| not_found("Savepoint"))?;
+
+    Ok(Json(savepoint.into()))
+}
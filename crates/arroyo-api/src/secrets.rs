@@ -0,0 +1,135 @@
+use crate::queries::api_queries;
+use crate::queries::api_queries::GetSecrets;
+use crate::rest::AppState;
+use crate::rest_utils::{
+    bad_request, client, internal_server_error, log_and_map, not_found, ApiError, ErrorResp,
+};
+use crate::{handle_db_error, to_micros, AuthData};
+use arroyo_rpc::api_types::secrets::{Secret, SecretPost};
+use arroyo_rpc::api_types::SecretCollection;
+use arroyo_rpc::public_ids::{generate_id, IdTypes};
+use arroyo_rpc::secrets;
+use axum::extract::{Path, State};
+use axum::{Extension, Json};
+use axum_extra::extract::WithRejection;
+use tracing::error;
+
+impl Into<Secret> for GetSecrets {
+    fn into(self) -> Secret {
+        Secret {
+            id: self.pub_id,
+            name: self.name,
+            created_by: self.created_by,
+            created_at: to_micros(self.created_at),
+        }
+    }
+}
+
+/// Create a secret, which can be referenced in connection configs as `{{ secret:name }}`
+#[utoipa::path(
+    post,
+    path = "/v1/secrets",
+    tag = "secrets",
+    request_body = SecretPost,
+    responses(
+        (status = 200, description = "Created secret", body = Secret),
+    ),
+)]
+pub async fn create_secret(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+    WithRejection(Json(req), _): WithRejection<Json<SecretPost>, ApiError>,
+) -> Result<Json<Secret>, ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    if !secrets::valid_name(&req.name) {
+        return Err(bad_request(
+            "Secret names must be non-empty and may only contain letters, numbers, and underscores",
+        ));
+    }
+
+    let encrypted_value = secrets::encrypt(&req.name, &req.value).map_err(|e| {
+        error!("Failed to encrypt secret: {:?}", e);
+        internal_server_error(format!("Failed to encrypt secret: {}", e))
+    })?;
+
+    let pub_id = generate_id(IdTypes::Secret);
+
+    let created_at = api_queries::create_secret()
+        .bind(
+            &client,
+            &pub_id,
+            &auth_data.organization_id,
+            &auth_data.user_id,
+            &req.name,
+            &encrypted_value,
+        )
+        .one()
+        .await
+        .map_err(|e| handle_db_error("secret", e))?;
+
+    Ok(Json(Secret {
+        id: pub_id,
+        name: req.name,
+        created_by: auth_data.user_id,
+        created_at: to_micros(created_at),
+    }))
+}
+
+/// List secrets; their values are never returned
+#[utoipa::path(
+    get,
+    path = "/v1/secrets",
+    tag = "secrets",
+    responses(
+        (status = 200, description = "Got secrets", body = SecretCollection),
+    ),
+)]
+pub async fn get_secrets(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+) -> Result<Json<SecretCollection>, ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    let secrets = api_queries::get_secrets()
+        .bind(&client, &auth_data.organization_id)
+        .all()
+        .await
+        .map_err(log_and_map)?
+        .into_iter()
+        .map(|s| s.into())
+        .collect();
+
+    Ok(Json(SecretCollection { data: secrets }))
+}
+
+/// Delete a secret; pipelines that reference it will fail the next time they are scheduled
+#[utoipa::path(
+    delete,
+    path = "/v1/secrets/{id}",
+    tag = "secrets",
+    params(
+        ("id" = String, Path, description = "Secret id")
+    ),
+    responses(
+        (status = 200, description = "Deleted secret"),
+    ),
+)]
+pub async fn delete_secret(
+    State(state): State<AppState>,
+    Extension(auth_data): Extension<AuthData>,
+    Path(pub_id): Path<String>,
+) -> Result<(), ErrorResp> {
+    let client = client(&state.pool).await?;
+
+    let count = api_queries::delete_secret()
+        .bind(&client, &auth_data.organization_id, &pub_id)
+        .await
+        .map_err(log_and_map)?;
+
+    if count != 1 {
+        return Err(not_found("Secret"));
+    }
+
+    Ok(())
+}
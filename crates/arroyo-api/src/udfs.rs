@@ -4,10 +4,9 @@ use crate::queries::api_queries::{
 };
 use crate::rest::AppState;
 use crate::rest_utils::{
-    authenticate, bad_request, client, internal_server_error, log_and_map, not_found, ApiError,
-    BearerAuth, ErrorResp,
+    bad_request, client, internal_server_error, log_and_map, not_found, ApiError, ErrorResp,
 };
-use crate::{compiler_service, to_micros};
+use crate::{compiler_service, to_micros, AuthData};
 use arroyo_df::{parse_dependencies, udfs, ParsedUdf};
 use arroyo_rpc::api_types::udfs::{GlobalUdf, UdfPost, UdfValidationResult, ValidateUdfPost};
 use arroyo_rpc::api_types::GlobalUdfCollection;
@@ -15,7 +14,7 @@ use arroyo_rpc::grpc::compiler_grpc_client::CompilerGrpcClient;
 use arroyo_rpc::grpc::{BuildUdfReq, UdfCrate};
 use arroyo_rpc::public_ids::{generate_id, IdTypes};
 use axum::extract::{Path, State};
-use axum::Json;
+use axum::{Extension, Json};
 use axum_extra::extract::WithRejection;
 use cornucopia_async::Params;
 use tonic::transport::Channel;
@@ -48,11 +47,10 @@ impl Into<GlobalUdf> for DbUdf {
 )]
 pub async fn create_udf(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     WithRejection(Json(req), _): WithRejection<Json<UdfPost>, ApiError>,
 ) -> Result<Json<GlobalUdf>, ErrorResp> {
     let mut client = client(&state.pool).await.unwrap();
-    let auth_data = authenticate(&state.pool, bearer_auth).await.unwrap();
 
     let transaction = client.transaction().await.map_err(log_and_map)?;
     transaction
@@ -137,10 +135,9 @@ pub async fn create_udf(
 )]
 pub async fn get_udfs(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
 ) -> Result<Json<GlobalUdfCollection>, ErrorResp> {
     let client = client(&state.pool).await.unwrap();
-    let auth_data = authenticate(&state.pool, bearer_auth).await.unwrap();
 
     let udfs = api_queries::get_udfs()
         .bind(&client, &auth_data.organization_id)
@@ -167,11 +164,10 @@ pub async fn get_udfs(
 )]
 pub async fn delete_udf(
     State(state): State<AppState>,
-    bearer_auth: BearerAuth,
+    Extension(auth_data): Extension<AuthData>,
     Path(udf_pub_id): Path<String>,
 ) -> Result<(), ErrorResp> {
     let client = client(&state.pool).await.unwrap();
-    let auth_data = authenticate(&state.pool, bearer_auth).await.unwrap();
 
     let count = api_queries::delete_udf()
         .params(
@@ -220,10 +216,11 @@ pub async fn build_udf(
     };
 
     // use the ArroyoSchemaProvider to do some validation and to get the function name
-    let function_name = match ParsedUdf::try_parse(udf_definition) {
-        Ok(function_name) => function_name.name,
+    let parsed = match ParsedUdf::try_parse(udf_definition) {
+        Ok(parsed) => parsed,
         Err(e) => return Ok(e.into()),
     };
+    let function_name = parsed.name;
 
     let cargo_toml = udfs::cargo_toml(&dependencies);
 
@@ -231,7 +228,9 @@ pub async fn build_udf(
         .build_udf(BuildUdfReq {
             udf_crate: Some(UdfCrate {
                 name: function_name.clone(),
-                definition: udf_definition.to_string(),
+                // the parsed definition has our #[udf] and #[udaf] attributes removed so that
+                // it compiles; the dependencies are kept so that changing them changes the dylib
+                definition: format!("/*\n{}*/\n\n{}", dependencies, parsed.definition),
                 cargo_toml,
                 lib_rs: match udfs::lib_rs(&udf_definition) {
                     Ok(lib_rs) => lib_rs,
@@ -1,12 +1,16 @@
 use anyhow::{anyhow, bail};
 use arroyo_compiler_service;
+use arroyo_rpc::api_types::checkpoints::StateDumpFormat;
 use arroyo_server_common::shutdown::Shutdown;
 use arroyo_server_common::{log_event, start_admin_server};
-use arroyo_types::{ports, DatabaseConfig};
+use arroyo_state::inspect::{self, StateFilter};
+use arroyo_types::{from_micros, ports, DatabaseConfig};
 use arroyo_worker::WorkerServer;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use deadpool_postgres::{ManagerConfig, Pool, RecyclingMethod};
 use serde_json::json;
+use std::io::Write;
+use std::path::PathBuf;
 use std::process::exit;
 use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
@@ -45,6 +49,65 @@ enum Commands {
         #[arg(long)]
         wait: Option<u32>,
     },
+
+    /// Inspects the state stored in a job's checkpoints
+    State {
+        #[command(subcommand)]
+        command: StateCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum StateCommands {
+    /// Lists the operators and tables in a checkpoint, with the sizes and row counts of their files
+    Describe {
+        /// Id of the job that wrote the checkpoint
+        job_id: String,
+
+        /// Epoch of the checkpoint
+        epoch: u32,
+    },
+
+    /// Dumps the contents of a table in a checkpoint
+    Dump {
+        /// Id of the job that wrote the checkpoint
+        job_id: String,
+
+        /// Epoch of the checkpoint
+        epoch: u32,
+
+        /// Operator that owns the table
+        operator_id: String,
+
+        /// Name of the table
+        table: String,
+
+        /// Format to write the table's rows in
+        #[arg(long, value_enum, default_value_t = DumpFormat::Json)]
+        format: DumpFormat,
+
+        /// Only include rows with this key
+        #[arg(long)]
+        key: Option<String>,
+
+        /// Only include rows at or after this time, in microseconds since the Unix epoch
+        #[arg(long)]
+        start_time: Option<u64>,
+
+        /// Only include rows before this time, in microseconds since the Unix epoch
+        #[arg(long)]
+        end_time: Option<u64>,
+
+        /// File to write the rows to; if not set they are written to stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum DumpFormat {
+    Json,
+    Arrow,
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -92,6 +155,13 @@ async fn main() {
                 exit(1);
             }
         }
+        Commands::State { command } => {
+            // output is written to stdout, so errors are reported without going through logging
+            if let Err(e) = inspect_state(command).await {
+                eprintln!("{:?}", e);
+                exit(1);
+            }
+        }
     };
 }
 
@@ -217,6 +287,48 @@ async fn migrate(wait: Option<u32>) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn inspect_state(command: &StateCommands) -> anyhow::Result<()> {
+    match command {
+        StateCommands::Describe { job_id, epoch } => {
+            let summary = inspect::describe_checkpoint(job_id, *epoch).await?;
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        }
+        StateCommands::Dump {
+            job_id,
+            epoch,
+            operator_id,
+            table,
+            format,
+            key,
+            start_time,
+            end_time,
+            output,
+        } => {
+            let filter = StateFilter {
+                key: key.clone(),
+                start_time: start_time.map(from_micros),
+                end_time: end_time.map(from_micros),
+            };
+
+            let (schema, batches) =
+                inspect::read_table(job_id, *epoch, operator_id, table, &filter).await?;
+
+            let format = match format {
+                DumpFormat::Json => StateDumpFormat::Json,
+                DumpFormat::Arrow => StateDumpFormat::Arrow,
+            };
+            let data = inspect::encode_batches(schema, &batches, format)?;
+
+            match output {
+                Some(path) => tokio::fs::write(path, data).await?,
+                None => std::io::stdout().write_all(&data)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
 async fn start_control_plane(service: CPService) {
     let _guard = arroyo_server_common::init_logging(service.name());
 
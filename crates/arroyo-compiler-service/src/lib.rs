@@ -110,6 +110,7 @@ edition = "2021"
 [dependencies]
 udf = {{ path = "../udf" }}
 arrow = {{ version = "50.0.0", features = ["ffi"] }}
+tokio = {{ version = "1", features = ["rt-multi-thread", "time"] }}
 
 
 [lib]
@@ -96,6 +96,7 @@ impl Connector for BlackholeConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: None,
             bad_data: None,
             framing: None,
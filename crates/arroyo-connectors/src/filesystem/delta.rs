@@ -6,12 +6,15 @@ use std::collections::HashMap;
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
 };
+use arroyo_rpc::formats::Format;
 use arroyo_rpc::OperatorConfig;
 
+use crate::filesystem::source::FileSystemSourceFunc;
 use crate::filesystem::{
-    file_system_sink_from_options, CommitStyle, FileSystemTable, FormatSettings, TableType,
+    file_system_sink_from_options, get_storage_url_and_options, CommitStyle, CompressionFormat,
+    DeltaSettings, FileSystemTable, FormatSettings, TableType,
 };
-use crate::EmptyConfig;
+use crate::{pull_option_to_i64, EmptyConfig};
 
 use arroyo_operator::connector::Connector;
 use arroyo_operator::operator::OperatorNode;
@@ -36,9 +39,9 @@ impl Connector for DeltaLakeConnector {
             id: "delta".to_string(),
             name: "Delta Lake".to_string(),
             icon: "".to_string(),
-            description: "Write to a Delta Lake table".to_string(),
+            description: "Read from and write to a Delta Lake table".to_string(),
             enabled: true,
-            source: false,
+            source: true,
             sink: true,
             testing: false,
             hidden: true,
@@ -66,8 +69,11 @@ impl Connector for DeltaLakeConnector {
         });
     }
 
-    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
-        return ConnectionType::Source;
+    fn table_type(&self, _: Self::ProfileT, table: Self::TableT) -> ConnectionType {
+        match table.table_type {
+            TableType::Source { .. } => ConnectionType::Source,
+            TableType::Sink { .. } => ConnectionType::Sink,
+        }
     }
 
     fn from_config(
@@ -78,40 +84,62 @@ impl Connector for DeltaLakeConnector {
         table: Self::TableT,
         schema: Option<&ConnectionSchema>,
     ) -> anyhow::Result<arroyo_operator::connector::Connection> {
-        let TableType::Sink {
-            write_path,
-            file_settings,
-            format_settings,
-            ..
-        } = &table.table_type
-        else {
-            bail!("Delta Lake connector only supports sink tables");
-        };
-        // confirm commit style is DeltaLake
-        if let Some(CommitStyle::DeltaLake) = file_settings
-            .as_ref()
-            .ok_or_else(|| anyhow!("no file_settings"))?
-            .commit_style
-        {
-            // ok
-        } else {
-            bail!("commit_style must be DeltaLake");
-        }
-
-        let backend_config = BackendConfig::parse_url(&write_path, true)?;
-        let is_local = match &backend_config {
-            BackendConfig::Local { .. } => true,
-            _ => false,
-        };
-        let description = match (&format_settings, is_local) {
-            (Some(FormatSettings::Parquet { .. }), true) => "LocalDeltaLake<Parquet>".to_string(),
-            (Some(FormatSettings::Parquet { .. }), false) => "DeltaLake<Parquet>".to_string(),
-            _ => bail!("Delta Lake sink only supports Parquet format"),
-        };
-
         let schema = schema
             .map(|s| s.to_owned())
-            .ok_or_else(|| anyhow!("no schema defined for Delta Lake sink"))?;
+            .ok_or_else(|| anyhow!("no schema defined for Delta Lake connection"))?;
+
+        let (description, connection_type) = match &table.table_type {
+            TableType::Source {
+                path,
+                delta_settings,
+                ..
+            } => {
+                if delta_settings.is_none() {
+                    bail!("Delta Lake sources must have delta settings");
+                }
+                if !matches!(schema.format, Some(Format::Parquet(_))) {
+                    bail!("Delta Lake source only supports Parquet format");
+                }
+                BackendConfig::parse_url(path, true)?;
+                (
+                    "DeltaLakeSource<Parquet>".to_string(),
+                    ConnectionType::Source,
+                )
+            }
+            TableType::Sink {
+                write_path,
+                file_settings,
+                format_settings,
+                ..
+            } => {
+                // confirm commit style is DeltaLake
+                if let Some(CommitStyle::DeltaLake) = file_settings
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("no file_settings"))?
+                    .commit_style
+                {
+                    // ok
+                } else {
+                    bail!("commit_style must be DeltaLake");
+                }
+
+                let backend_config = BackendConfig::parse_url(&write_path, true)?;
+                let is_local = match &backend_config {
+                    BackendConfig::Local { .. } => true,
+                    _ => false,
+                };
+                let description = match (&format_settings, is_local) {
+                    (Some(FormatSettings::Parquet { .. }), true) => {
+                        "LocalDeltaLake<Parquet>".to_string()
+                    }
+                    (Some(FormatSettings::Parquet { .. }), false) => {
+                        "DeltaLake<Parquet>".to_string()
+                    }
+                    _ => bail!("Delta Lake sink only supports Parquet format"),
+                };
+                (description, ConnectionType::Sink)
+            }
+        };
 
         let format = schema
             .format
@@ -123,6 +151,7 @@ impl Connector for DeltaLakeConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
@@ -132,7 +161,7 @@ impl Connector for DeltaLakeConnector {
             id,
             connector: self.name(),
             name: name.to_string(),
-            connection_type: ConnectionType::Sink,
+            connection_type,
             schema,
             config: serde_json::to_string(&config).unwrap(),
             description,
@@ -146,7 +175,39 @@ impl Connector for DeltaLakeConnector {
         schema: Option<&ConnectionSchema>,
         _profile: Option<&ConnectionProfile>,
     ) -> anyhow::Result<Connection> {
-        let table = file_system_sink_from_options(options, schema, CommitStyle::DeltaLake)?;
+        let table = match options.remove("type").as_deref() {
+            Some("source") => {
+                let (path, storage_options) = get_storage_url_and_options(options)?;
+                let tail = options
+                    .remove("source.tail")
+                    .map(|value| {
+                        value
+                            .parse::<bool>()
+                            .map_err(|_| anyhow!("invalid value '{}' for source.tail", value))
+                    })
+                    .transpose()?;
+                FileSystemTable {
+                    table_type: TableType::Source {
+                        path,
+                        storage_options,
+                        compression_format: Some(CompressionFormat::None),
+                        regex_pattern: None,
+                        delta_settings: Some(DeltaSettings {
+                            version: pull_option_to_i64("source.version", options)?,
+                            tail,
+                            poll_interval_seconds: pull_option_to_i64(
+                                "source.poll-interval-seconds",
+                                options,
+                            )?,
+                        }),
+                    },
+                }
+            }
+            Some("sink") | None => {
+                file_system_sink_from_options(options, schema, CommitStyle::DeltaLake)?
+            }
+            Some(t) => bail!("unknown type: {}", t),
+        };
 
         self.from_config(None, name, EmptyConfig {}, table, schema)
     }
@@ -157,14 +218,26 @@ impl Connector for DeltaLakeConnector {
         table: Self::TableT,
         config: OperatorConfig,
     ) -> anyhow::Result<OperatorNode> {
-        let TableType::Sink {
-            write_path,
-            file_settings,
-            format_settings,
-            ..
-        } = &table.table_type
-        else {
-            bail!("Delta Lake connector only supports sink tables");
+        let (write_path, file_settings, format_settings) = match &table.table_type {
+            TableType::Source { .. } => {
+                return Ok(OperatorNode::from_source(Box::new(FileSystemSourceFunc {
+                    table: table.table_type.clone(),
+                    format: config
+                        .format
+                        .ok_or_else(|| anyhow!("format required for Delta Lake source"))?,
+                    framing: config.framing.clone(),
+                    bad_data: config.bad_data.clone(),
+                    file_states: HashMap::new(),
+                    partition_values: HashMap::new(),
+                    delta_state: None,
+                })));
+            }
+            TableType::Sink {
+                write_path,
+                file_settings,
+                format_settings,
+                ..
+            } => (write_path, file_settings, format_settings),
         };
         // confirm commit style is DeltaLake
         if let Some(CommitStyle::DeltaLake) = file_settings
@@ -0,0 +1,246 @@
+use anyhow::{anyhow, bail};
+use arroyo_operator::connector::Connection;
+use arroyo_storage::BackendConfig;
+use std::collections::HashMap;
+
+use arroyo_rpc::api_types::connections::{
+    ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
+};
+use arroyo_rpc::OperatorConfig;
+
+use crate::filesystem::{
+    file_system_sink_from_options, CatalogType, CommitStyle, FileSystemTable, FormatSettings,
+    IcebergSettings, TableType,
+};
+use crate::{pull_opt, EmptyConfig};
+
+use arroyo_operator::connector::Connector;
+use arroyo_operator::operator::OperatorNode;
+
+use super::sink::{LocalParquetFileSystemSink, ParquetFileSystemSink};
+
+const TABLE_SCHEMA: &str = include_str!("./table.json");
+
+pub struct IcebergConnector {}
+
+impl IcebergConnector {
+    fn validate(table: &FileSystemTable) -> anyhow::Result<(&String, bool)> {
+        let TableType::Sink {
+            write_path,
+            file_settings,
+            format_settings,
+            iceberg_settings,
+            ..
+        } = &table.table_type
+        else {
+            bail!("Iceberg connector only supports sink tables");
+        };
+
+        // confirm commit style is Iceberg
+        let Some(CommitStyle::Iceberg) = file_settings
+            .as_ref()
+            .ok_or_else(|| anyhow!("no file_settings"))?
+            .commit_style
+        else {
+            bail!("commit_style must be Iceberg");
+        };
+
+        if !matches!(format_settings, Some(FormatSettings::Parquet { .. })) {
+            bail!("Iceberg sink only supports Parquet format");
+        }
+
+        if let Some(IcebergSettings {
+            catalog_type: Some(CatalogType::Rest),
+            rest_url,
+            namespace,
+            table_name,
+            ..
+        }) = iceberg_settings
+        {
+            if rest_url.is_none() || namespace.is_none() || table_name.is_none() {
+                bail!("the REST catalog requires a URL, namespace, and table name");
+            }
+        }
+
+        let backend_config = BackendConfig::parse_url(write_path, true)?;
+        let is_local = matches!(backend_config, BackendConfig::Local { .. });
+
+        // the hadoop catalog commits by creating the next metadata file only if it doesn't
+        // exist yet, which S3 can't do
+        let hadoop_catalog = !matches!(
+            iceberg_settings,
+            Some(IcebergSettings {
+                catalog_type: Some(CatalogType::Rest),
+                ..
+            })
+        );
+        if hadoop_catalog && matches!(backend_config, BackendConfig::S3(_)) {
+            bail!(
+                "the hadoop catalog can't safely commit to tables in S3, as it doesn't support \
+                conditional writes; use the REST catalog ('catalog.type' = 'rest') instead"
+            );
+        }
+
+        Ok((write_path, is_local))
+    }
+}
+
+impl Connector for IcebergConnector {
+    type ProfileT = EmptyConfig;
+
+    type TableT = FileSystemTable;
+
+    fn name(&self) -> &'static str {
+        "iceberg"
+    }
+
+    fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector {
+        arroyo_rpc::api_types::connections::Connector {
+            id: "iceberg".to_string(),
+            name: "Apache Iceberg".to_string(),
+            icon: "".to_string(),
+            description: "Write to an Apache Iceberg table".to_string(),
+            enabled: true,
+            source: false,
+            sink: true,
+            testing: false,
+            hidden: true,
+            custom_schemas: true,
+            connection_config: None,
+            table_config: TABLE_SCHEMA.to_owned(),
+        }
+    }
+
+    fn test(
+        &self,
+        _: &str,
+        _: Self::ProfileT,
+        _: Self::TableT,
+        _: Option<&ConnectionSchema>,
+        tx: tokio::sync::mpsc::Sender<TestSourceMessage>,
+    ) {
+        tokio::task::spawn(async move {
+            let message = TestSourceMessage {
+                error: false,
+                done: true,
+                message: "Successfully validated connection".to_string(),
+            };
+            tx.send(message).await.unwrap();
+        });
+    }
+
+    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
+        ConnectionType::Sink
+    }
+
+    fn from_config(
+        &self,
+        id: Option<i64>,
+        name: &str,
+        config: Self::ProfileT,
+        table: Self::TableT,
+        schema: Option<&ConnectionSchema>,
+    ) -> anyhow::Result<arroyo_operator::connector::Connection> {
+        let (_, is_local) = Self::validate(&table)?;
+        let description = if is_local {
+            "LocalIceberg<Parquet>".to_string()
+        } else {
+            "Iceberg<Parquet>".to_string()
+        };
+
+        let schema = schema
+            .map(|s| s.to_owned())
+            .ok_or_else(|| anyhow!("no schema defined for Iceberg sink"))?;
+
+        let format = schema
+            .format
+            .as_ref()
+            .map(|t| t.to_owned())
+            .ok_or_else(|| anyhow!("'format' must be set for Iceberg connection"))?;
+
+        let config = OperatorConfig {
+            connection: serde_json::to_value(config).unwrap(),
+            table: serde_json::to_value(table).unwrap(),
+            rate_limit: None,
+            quota: None,
+            format: Some(format),
+            bad_data: schema.bad_data.clone(),
+            framing: schema.framing.clone(),
+        };
+
+        Ok(Connection {
+            id,
+            connector: self.name(),
+            name: name.to_string(),
+            connection_type: ConnectionType::Sink,
+            schema,
+            config: serde_json::to_string(&config).unwrap(),
+            description,
+        })
+    }
+
+    fn from_options(
+        &self,
+        name: &str,
+        options: &mut HashMap<String, String>,
+        schema: Option<&ConnectionSchema>,
+        _profile: Option<&ConnectionProfile>,
+    ) -> anyhow::Result<Connection> {
+        let catalog_type = options
+            .remove("catalog.type")
+            .map(|value| {
+                CatalogType::try_from(&value)
+                    .map_err(|_err| anyhow!("{} is not a valid Iceberg catalog type", value))
+            })
+            .transpose()?;
+
+        let iceberg_settings = match catalog_type {
+            Some(CatalogType::Rest) => IcebergSettings {
+                catalog_type,
+                rest_url: Some(pull_opt("catalog.rest.url", options)?),
+                rest_token: options.remove("catalog.rest.token"),
+                warehouse: options.remove("catalog.warehouse"),
+                namespace: Some(pull_opt("namespace", options)?),
+                table_name: Some(pull_opt("table_name", options)?),
+            },
+            _ => IcebergSettings {
+                catalog_type,
+                rest_url: None,
+                rest_token: None,
+                warehouse: None,
+                namespace: None,
+                table_name: None,
+            },
+        };
+
+        let mut table = file_system_sink_from_options(options, schema, CommitStyle::Iceberg)?;
+        if let TableType::Sink {
+            iceberg_settings: settings,
+            ..
+        } = &mut table.table_type
+        {
+            *settings = Some(iceberg_settings);
+        }
+
+        self.from_config(None, name, EmptyConfig {}, table, schema)
+    }
+
+    fn make_operator(
+        &self,
+        _: Self::ProfileT,
+        table: Self::TableT,
+        config: OperatorConfig,
+    ) -> anyhow::Result<OperatorNode> {
+        let (write_path, is_local) = Self::validate(&table)?;
+
+        if is_local {
+            Ok(OperatorNode::from_operator(Box::new(
+                LocalParquetFileSystemSink::new(write_path.to_string(), table, config),
+            )))
+        } else {
+            Ok(OperatorNode::from_operator(Box::new(
+                ParquetFileSystemSink::new(table, config),
+            )))
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod delta;
+pub mod iceberg;
 mod sink;
 mod source;
 
@@ -145,6 +146,7 @@ impl Connector for FileSystemConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
@@ -187,6 +189,7 @@ impl Connector for FileSystemConnector {
                             storage_options,
                             compression_format: Some(compression_format),
                             regex_pattern: matching_pattern,
+                            delta_settings: None,
                         },
                     },
                     schema,
@@ -218,6 +221,8 @@ impl Connector for FileSystemConnector {
                     framing: config.framing.clone(),
                     bad_data: config.bad_data.clone(),
                     file_states: HashMap::new(),
+                    partition_values: HashMap::new(),
+                    delta_state: None,
                 })))
             }
             TableType::Sink {
@@ -225,6 +230,7 @@ impl Connector for FileSystemConnector {
                 format_settings,
                 storage_options: _,
                 write_path,
+                iceberg_settings: _,
             } => {
                 let backend_config = BackendConfig::parse_url(&write_path, true)?;
                 let is_local = match &backend_config {
@@ -361,6 +367,7 @@ pub fn file_system_sink_from_options(
             format_settings,
             write_path: storage_url,
             storage_options,
+            iceberg_settings: None,
         },
     })
 }
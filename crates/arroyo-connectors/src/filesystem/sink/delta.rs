@@ -22,6 +22,7 @@ use tracing::info;
 
 static INIT: Lazy<()> = Lazy::new(|| {
     deltalake::aws::register_handlers(None);
+    deltalake::azure::register_handlers(None);
 });
 
 pub(crate) async fn commit_files_to_delta(
@@ -37,7 +38,7 @@ pub(crate) async fn commit_files_to_delta(
 
     let add_actions = create_add_actions(&finished_files, &relative_table_path)?;
     let table_path = build_table_path(&storage_provider, &relative_table_path);
-    let storage_options = configure_storage_options(&table_path, storage_provider.clone()).await?;
+    let storage_options = configure_storage_options(&table_path, &storage_provider).await?;
     let mut table = load_or_create_table(&table_path, storage_options.clone(), &schema).await?;
 
     if let Some(new_version) = check_existing_files(
@@ -91,9 +92,24 @@ async fn create_new_table(
         .map_err(Into::into)
 }
 
+/// Loads the Delta table at `table_path`, either at `version` or the latest version.
+pub(crate) async fn load_table(
+    table_path: &str,
+    storage_provider: &StorageProvider,
+    version: Option<i64>,
+) -> Result<deltalake::DeltaTable> {
+    Lazy::force(&INIT);
+    let storage_options = configure_storage_options(table_path, storage_provider).await?;
+    let mut builder = DeltaTableBuilder::from_uri(table_path).with_storage_options(storage_options);
+    if let Some(version) = version {
+        builder = builder.with_version(version);
+    }
+    Ok(builder.load().await?)
+}
+
 async fn configure_storage_options(
     table_path: &str,
-    storage_provider: Arc<StorageProvider>,
+    storage_provider: &StorageProvider,
 ) -> Result<HashMap<String, String>> {
     let mut options = storage_provider.storage_options().clone();
     if table_path.starts_with("s3://") {
@@ -0,0 +1,1622 @@
+use super::FinishedFile;
+use crate::filesystem::{
+    CatalogType, CommitStyle, FileSettings, FileSystemTable, IcebergSettings, TableType,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use apache_avro::{types::Value as AvroValue, Schema as AvroSchema};
+use arrow::array::{
+    Array, ArrayRef, AsArray, LargeListArray, ListArray, MapArray, RecordBatch, StructArray,
+};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+use arroyo_storage::{BackendConfig, StorageProvider};
+use arroyo_types::to_millis;
+use bytes::Bytes;
+use object_store::{path::Path, ObjectStore, PutMode};
+use parquet::file::footer::{decode_footer, decode_metadata};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::SystemTime,
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const MAX_COMMIT_ATTEMPTS: usize = 5;
+const VERSION_HINT_FILE: &str = "version-hint.text";
+const NAME_MAPPING_PROPERTY: &str = "schema.name-mapping.default";
+// Recorded in the summary of every snapshot we commit. Data file names are unique, so finding
+// our first file in an existing snapshot means a previous attempt at this commit succeeded.
+const COMMITTED_FILE_PROPERTY: &str = "arroyo.first-data-file";
+
+const MANIFEST_SCHEMA: &str = r#"{
+  "type": "record",
+  "name": "manifest_entry",
+  "fields": [
+    {"name": "status", "type": "int", "field-id": 0},
+    {"name": "snapshot_id", "type": ["null", "long"], "default": null, "field-id": 1},
+    {"name": "sequence_number", "type": ["null", "long"], "default": null, "field-id": 3},
+    {"name": "file_sequence_number", "type": ["null", "long"], "default": null, "field-id": 4},
+    {"name": "data_file", "field-id": 2, "type": {
+      "type": "record",
+      "name": "r2",
+      "fields": [
+        {"name": "content", "type": "int", "field-id": 134},
+        {"name": "file_path", "type": "string", "field-id": 100},
+        {"name": "file_format", "type": "string", "field-id": 101},
+        {"name": "partition", "field-id": 102, "type": {"type": "record", "name": "r102", "fields": []}},
+        {"name": "record_count", "type": "long", "field-id": 103},
+        {"name": "file_size_in_bytes", "type": "long", "field-id": 104}
+      ]
+    }}
+  ]
+}"#;
+
+const MANIFEST_LIST_SCHEMA: &str = r#"{
+  "type": "record",
+  "name": "manifest_file",
+  "fields": [
+    {"name": "manifest_path", "type": "string", "field-id": 500},
+    {"name": "manifest_length", "type": "long", "field-id": 501},
+    {"name": "partition_spec_id", "type": "int", "field-id": 502},
+    {"name": "content", "type": "int", "default": 0, "field-id": 517},
+    {"name": "sequence_number", "type": "long", "default": 0, "field-id": 515},
+    {"name": "min_sequence_number", "type": "long", "default": 0, "field-id": 516},
+    {"name": "added_snapshot_id", "type": "long", "field-id": 503},
+    {"name": "added_files_count", "type": "int", "field-id": 504},
+    {"name": "existing_files_count", "type": "int", "field-id": 505},
+    {"name": "deleted_files_count", "type": "int", "field-id": 506},
+    {"name": "added_rows_count", "type": "long", "field-id": 512},
+    {"name": "existing_rows_count", "type": "long", "field-id": 513},
+    {"name": "deleted_rows_count", "type": "long", "field-id": 514},
+    {"name": "partitions", "default": null, "field-id": 507, "type": ["null", {
+      "type": "array",
+      "element-id": 508,
+      "items": {
+        "type": "record",
+        "name": "r508",
+        "fields": [
+          {"name": "contains_null", "type": "boolean", "field-id": 509},
+          {"name": "contains_nan", "type": ["null", "boolean"], "default": null, "field-id": 518},
+          {"name": "lower_bound", "type": ["null", "bytes"], "default": null, "field-id": 510},
+          {"name": "upper_bound", "type": ["null", "bytes"], "default": null, "field-id": 511}
+        ]
+      }
+    }]},
+    {"name": "key_metadata", "type": ["null", "bytes"], "default": null, "field-id": 519}
+  ]
+}"#;
+
+pub(crate) fn settings_for_table(table: &FileSystemTable) -> Option<IcebergSettings> {
+    match &table.table_type {
+        TableType::Sink {
+            iceberg_settings, ..
+        } => iceberg_settings.clone(),
+        TableType::Source { .. } => None,
+    }
+}
+
+/// Returns the schema that parquet files are written with for Iceberg tables, or `None` if the
+/// table isn't an Iceberg table
+pub(crate) fn write_schema_for_table(
+    table: &FileSystemTable,
+    schema: &Schema,
+) -> Option<SchemaRef> {
+    match &table.table_type {
+        TableType::Sink {
+            file_settings:
+                Some(FileSettings {
+                    commit_style: Some(CommitStyle::Iceberg),
+                    ..
+                }),
+            ..
+        } => Some(Arc::new(iceberg_write_schema(schema))),
+        _ => None,
+    }
+}
+
+fn iceberg_write_schema(schema: &Schema) -> Schema {
+    Schema::new(
+        schema
+            .fields()
+            .iter()
+            .map(|f| iceberg_field(f))
+            .collect::<Vec<_>>(),
+    )
+    .with_metadata(schema.metadata().clone())
+}
+
+fn iceberg_field(field: &Field) -> Field {
+    field
+        .clone()
+        .with_data_type(iceberg_data_type(field.data_type()))
+}
+
+/// Iceberg only supports microsecond timestamps and signed integers, so other timestamps and
+/// unsigned ints that don't fit in an int are converted before they're written
+fn iceberg_data_type(data_type: &DataType) -> DataType {
+    match data_type {
+        DataType::Timestamp(_, tz) => DataType::Timestamp(TimeUnit::Microsecond, tz.clone()),
+        DataType::UInt32 => DataType::Int64,
+        DataType::List(field) => DataType::List(Arc::new(iceberg_field(field))),
+        DataType::LargeList(field) => DataType::LargeList(Arc::new(iceberg_field(field))),
+        DataType::Struct(fields) => {
+            DataType::Struct(fields.iter().map(|f| iceberg_field(f)).collect())
+        }
+        DataType::Map(entries, sorted) => DataType::Map(Arc::new(iceberg_field(entries)), *sorted),
+        other => other.clone(),
+    }
+}
+
+/// Casts a batch into the schema returned by [`write_schema_for_table`]
+pub(crate) fn cast_for_iceberg(batch: &RecordBatch, schema: &SchemaRef) -> Result<RecordBatch> {
+    if batch.schema() == *schema {
+        return Ok(batch.clone());
+    }
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(schema.fields())
+        .map(|(column, field)| cast_array(column, field.data_type()))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+fn cast_array(array: &ArrayRef, to: &DataType) -> Result<ArrayRef> {
+    if array.data_type() == to {
+        return Ok(array.clone());
+    }
+    // nested arrays are rebuilt around their cast children, as not all nested casts are
+    // supported by arrow
+    Ok(match to {
+        DataType::List(field) => {
+            let list = array.as_list::<i32>();
+            Arc::new(ListArray::try_new(
+                field.clone(),
+                list.offsets().clone(),
+                cast_array(list.values(), field.data_type())?,
+                list.nulls().cloned(),
+            )?)
+        }
+        DataType::LargeList(field) => {
+            let list = array.as_list::<i64>();
+            Arc::new(LargeListArray::try_new(
+                field.clone(),
+                list.offsets().clone(),
+                cast_array(list.values(), field.data_type())?,
+                list.nulls().cloned(),
+            )?)
+        }
+        DataType::Struct(fields) => {
+            let array = array.as_struct();
+            Arc::new(StructArray::try_new(
+                fields.clone(),
+                array
+                    .columns()
+                    .iter()
+                    .zip(fields.iter())
+                    .map(|(column, field)| cast_array(column, field.data_type()))
+                    .collect::<Result<_>>()?,
+                array.nulls().cloned(),
+            )?)
+        }
+        DataType::Map(entries, sorted) => {
+            let map = array.as_map();
+            let entries_array: ArrayRef = Arc::new(map.entries().clone());
+            Arc::new(MapArray::try_new(
+                entries.clone(),
+                map.offsets().clone(),
+                cast_array(&entries_array, entries.data_type())?
+                    .as_struct()
+                    .clone(),
+                map.nulls().cloned(),
+                *sorted,
+            )?)
+        }
+        _ => cast(array, to)?,
+    })
+}
+
+/// Commits the finished files as a new snapshot of the Iceberg table at `relative_table_path`,
+/// creating the table if it doesn't exist. Returns the id of the snapshot containing the files.
+pub(crate) async fn commit_files_to_iceberg(
+    finished_files: Vec<FinishedFile>,
+    relative_table_path: Path,
+    storage_provider: Arc<StorageProvider>,
+    settings: Option<IcebergSettings>,
+    schema: SchemaRef,
+) -> Result<Option<i64>> {
+    if finished_files.is_empty() {
+        return Ok(None);
+    }
+
+    let catalog = Catalog::from_settings(settings)?;
+    let table = TableFiles::new(storage_provider, relative_table_path);
+    let data_files = table.data_files(&finished_files).await?;
+    let commit_marker = data_files
+        .iter()
+        .map(|file| file.file_path.as_str())
+        .min()
+        .unwrap()
+        .to_string();
+
+    for attempt in 1..=MAX_COMMIT_ATTEMPTS {
+        let base = match catalog.load(&table).await? {
+            Some(base) => base,
+            None => catalog.create(&table, &schema).await?,
+        };
+
+        if base.metadata.format_version != 2 {
+            bail!(
+                "Iceberg table at {} has format version {}, but only version 2 is supported",
+                table.location,
+                base.metadata.format_version
+            );
+        }
+
+        if let Some(snapshot) =
+            base.metadata.snapshots.iter().find(|snapshot| {
+                snapshot.summary.get(COMMITTED_FILE_PROPERTY) == Some(&commit_marker)
+            })
+        {
+            info!(
+                "files already committed to Iceberg table {} in snapshot {}",
+                table.location, snapshot.snapshot_id
+            );
+            return Ok(Some(snapshot.snapshot_id));
+        }
+
+        validate_schema(base.metadata.current_schema()?, &schema)?;
+
+        let snapshot = table
+            .write_snapshot(&base.metadata, &data_files, &commit_marker)
+            .await?;
+        let snapshot_id = snapshot.snapshot_id;
+
+        match catalog.commit(&table, base, snapshot).await? {
+            CommitResult::Committed => {
+                info!(
+                    "committed {} files to Iceberg table {} as snapshot {}",
+                    data_files.len(),
+                    table.location,
+                    snapshot_id
+                );
+                return Ok(Some(snapshot_id));
+            }
+            CommitResult::Conflict => {
+                warn!(
+                    "concurrent modification of Iceberg table {} (attempt {}/{}), retrying",
+                    table.location, attempt, MAX_COMMIT_ATTEMPTS
+                );
+            }
+        }
+    }
+
+    bail!(
+        "failed to commit to Iceberg table {} after {} attempts",
+        table.location,
+        MAX_COMMIT_ATTEMPTS
+    )
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct TableMetadata {
+    format_version: i32,
+    table_uuid: String,
+    location: String,
+    #[serde(default)]
+    last_sequence_number: i64,
+    last_updated_ms: i64,
+    last_column_id: i32,
+    current_schema_id: i32,
+    schemas: Vec<Value>,
+    default_spec_id: i32,
+    partition_specs: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    current_snapshot_id: Option<i64>,
+    #[serde(default)]
+    snapshots: Vec<Snapshot>,
+    #[serde(default)]
+    snapshot_log: Vec<Value>,
+    #[serde(default)]
+    metadata_log: Vec<Value>,
+    #[serde(default)]
+    refs: Map<String, Value>,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Snapshot {
+    snapshot_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent_snapshot_id: Option<i64>,
+    #[serde(default)]
+    sequence_number: i64,
+    timestamp_ms: i64,
+    manifest_list: String,
+    #[serde(default)]
+    summary: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schema_id: Option<i32>,
+}
+
+impl TableMetadata {
+    fn new(location: String, schema: &Schema) -> Result<Self> {
+        let (schema, last_column_id) = iceberg_schema(schema)?;
+        let Value::Object(other) = json!({
+            "last-partition-id": 999,
+            "default-sort-order-id": 0,
+            "sort-orders": [{"order-id": 0, "fields": []}],
+        }) else {
+            unreachable!()
+        };
+
+        Ok(Self {
+            format_version: 2,
+            table_uuid: Uuid::new_v4().to_string(),
+            location,
+            last_sequence_number: 0,
+            last_updated_ms: to_millis(SystemTime::now()) as i64,
+            last_column_id,
+            current_schema_id: 0,
+            schemas: vec![schema],
+            default_spec_id: 0,
+            partition_specs: vec![json!({"spec-id": 0, "fields": []})],
+            current_snapshot_id: None,
+            snapshots: vec![],
+            snapshot_log: vec![],
+            metadata_log: vec![],
+            refs: Map::new(),
+            properties: HashMap::new(),
+            other,
+        })
+    }
+
+    fn current_schema(&self) -> Result<&Value> {
+        self.schemas
+            .iter()
+            .find(|schema| schema["schema-id"].as_i64() == Some(self.current_schema_id as i64))
+            .ok_or_else(|| anyhow!("current schema {} not found", self.current_schema_id))
+    }
+
+    fn current_snapshot(&self) -> Option<&Snapshot> {
+        let id = self.current_snapshot_id?;
+        self.snapshots
+            .iter()
+            .find(|snapshot| snapshot.snapshot_id == id)
+    }
+
+    fn new_snapshot_id(&self) -> i64 {
+        loop {
+            let id = (rand::random::<u64>() >> 1) as i64;
+            if id != 0 && !self.snapshots.iter().any(|s| s.snapshot_id == id) {
+                return id;
+            }
+        }
+    }
+
+    /// Returns the name mapping to add to the table's properties, if it doesn't have one yet.
+    /// Our parquet files don't carry Iceberg field ids, so readers rely on the mapping to
+    /// resolve columns by name.
+    fn missing_name_mapping(&self) -> Result<Option<String>> {
+        if self.properties.contains_key(NAME_MAPPING_PROPERTY) {
+            return Ok(None);
+        }
+        let mapping = name_mapping(&self.current_schema()?["fields"]);
+        Ok(Some(serde_json::to_string(&mapping)?))
+    }
+
+    fn add_snapshot(&mut self, snapshot: Snapshot) {
+        self.last_sequence_number = snapshot.sequence_number;
+        self.last_updated_ms = snapshot.timestamp_ms;
+        self.current_snapshot_id = Some(snapshot.snapshot_id);
+        self.refs.insert(
+            "main".to_string(),
+            json!({"snapshot-id": snapshot.snapshot_id, "type": "branch"}),
+        );
+        self.snapshot_log.push(json!({
+            "timestamp-ms": snapshot.timestamp_ms,
+            "snapshot-id": snapshot.snapshot_id,
+        }));
+        self.snapshots.push(snapshot);
+    }
+}
+
+struct DataFile {
+    file_path: String,
+    record_count: i64,
+    file_size_in_bytes: i64,
+}
+
+/// The files of an Iceberg table that we manage directly: data files, manifests,
+/// manifest lists, and (for the hadoop catalog) the table metadata.
+struct TableFiles {
+    provider: Arc<StorageProvider>,
+    store: Arc<dyn ObjectStore>,
+    path: Path,
+    location: String,
+}
+
+impl TableFiles {
+    fn new(provider: Arc<StorageProvider>, path: Path) -> Self {
+        let location = uri_for_key(&provider, path.as_ref());
+        Self {
+            store: provider.get_backing_store(),
+            provider,
+            path,
+            location,
+        }
+    }
+
+    fn metadata_path(&self, name: &str) -> Path {
+        self.path.child("metadata").child(name)
+    }
+
+    fn metadata_uri(&self, name: &str) -> String {
+        format!("{}/metadata/{}", self.location, name)
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        match self.store.head(path).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn read_uri(&self, uri: &str) -> Result<Bytes> {
+        match uri.strip_prefix(&uri_for_key(&self.provider, "")) {
+            Some(key) => Ok(self.store.get(&Path::parse(key)?).await?.bytes().await?),
+            None => Ok(StorageProvider::get_url_with_options(
+                uri,
+                self.provider.storage_options().clone(),
+            )
+            .await?),
+        }
+    }
+
+    async fn data_files(&self, finished_files: &[FinishedFile]) -> Result<Vec<DataFile>> {
+        let mut data_files = Vec::with_capacity(finished_files.len());
+        for file in finished_files {
+            let path = Path::parse(&file.filename)?;
+            let size = self.store.head(&path).await?.size;
+            let record_count = parquet_record_count(self.store.as_ref(), &path, size)
+                .await
+                .with_context(|| format!("failed to read parquet footer of {}", file.filename))?;
+            data_files.push(DataFile {
+                file_path: uri_for_key(&self.provider, &file.filename),
+                record_count,
+                file_size_in_bytes: size as i64,
+            });
+        }
+        Ok(data_files)
+    }
+
+    /// Writes the manifest and manifest list for a snapshot appending `data_files` to the
+    /// table's current snapshot, and returns the (not yet committed) snapshot.
+    async fn write_snapshot(
+        &self,
+        metadata: &TableMetadata,
+        data_files: &[DataFile],
+        commit_marker: &str,
+    ) -> Result<Snapshot> {
+        let spec = metadata
+            .partition_specs
+            .iter()
+            .find(|spec| spec["spec-id"].as_i64() == Some(metadata.default_spec_id as i64))
+            .ok_or_else(|| anyhow!("default partition spec not found"))?;
+        if !spec["fields"].as_array().map_or(true, |f| f.is_empty()) {
+            bail!("writing to partitioned Iceberg tables is not supported");
+        }
+
+        let snapshot_id = metadata.new_snapshot_id();
+        let sequence_number = metadata.last_sequence_number + 1;
+        let parent = metadata.current_snapshot();
+        let commit_id = Uuid::new_v4();
+
+        let manifest_name = format!("{}-m0.avro", commit_id);
+        let manifest = write_manifest(
+            metadata.current_schema()?,
+            metadata.default_spec_id,
+            snapshot_id,
+            data_files,
+        )?;
+        let manifest_length = manifest.len() as i64;
+        self.store
+            .put(&self.metadata_path(&manifest_name), manifest.into())
+            .await?;
+
+        let list_schema = AvroSchema::parse_str(MANIFEST_LIST_SCHEMA)?;
+        let mut manifests = vec![];
+        if let Some(parent) = parent {
+            let bytes = self.read_uri(&parent.manifest_list).await?;
+            for manifest in apache_avro::Reader::with_schema(&list_schema, &bytes[..])? {
+                manifests.push(manifest?);
+            }
+        }
+        let added_records: i64 = data_files.iter().map(|f| f.record_count).sum();
+        let added_size: i64 = data_files.iter().map(|f| f.file_size_in_bytes).sum();
+        manifests.push(AvroValue::Record(vec![
+            (
+                "manifest_path".to_string(),
+                AvroValue::String(self.metadata_uri(&manifest_name)),
+            ),
+            (
+                "manifest_length".to_string(),
+                AvroValue::Long(manifest_length),
+            ),
+            (
+                "partition_spec_id".to_string(),
+                AvroValue::Int(metadata.default_spec_id),
+            ),
+            ("content".to_string(), AvroValue::Int(0)),
+            (
+                "sequence_number".to_string(),
+                AvroValue::Long(sequence_number),
+            ),
+            (
+                "min_sequence_number".to_string(),
+                AvroValue::Long(sequence_number),
+            ),
+            (
+                "added_snapshot_id".to_string(),
+                AvroValue::Long(snapshot_id),
+            ),
+            (
+                "added_files_count".to_string(),
+                AvroValue::Int(data_files.len() as i32),
+            ),
+            ("existing_files_count".to_string(), AvroValue::Int(0)),
+            ("deleted_files_count".to_string(), AvroValue::Int(0)),
+            (
+                "added_rows_count".to_string(),
+                AvroValue::Long(added_records),
+            ),
+            ("existing_rows_count".to_string(), AvroValue::Long(0)),
+            ("deleted_rows_count".to_string(), AvroValue::Long(0)),
+            (
+                "partitions".to_string(),
+                AvroValue::Union(1, Box::new(AvroValue::Array(vec![]))),
+            ),
+            (
+                "key_metadata".to_string(),
+                AvroValue::Union(0, Box::new(AvroValue::Null)),
+            ),
+        ]));
+
+        let mut writer = apache_avro::Writer::new(&list_schema, Vec::new());
+        writer.add_user_metadata("snapshot-id".to_string(), snapshot_id.to_string())?;
+        writer.add_user_metadata(
+            "parent-snapshot-id".to_string(),
+            parent
+                .map(|p| p.snapshot_id.to_string())
+                .unwrap_or_else(|| "null".to_string()),
+        )?;
+        writer.add_user_metadata("sequence-number".to_string(), sequence_number.to_string())?;
+        writer.add_user_metadata("format-version".to_string(), "2")?;
+        for manifest in manifests {
+            writer.append(manifest)?;
+        }
+        let manifest_list_name = format!("snap-{}-1-{}.avro", snapshot_id, commit_id);
+        self.store
+            .put(
+                &self.metadata_path(&manifest_list_name),
+                writer.into_inner()?.into(),
+            )
+            .await?;
+
+        let mut summary = HashMap::from([
+            ("operation".to_string(), "append".to_string()),
+            ("added-data-files".to_string(), data_files.len().to_string()),
+            ("added-records".to_string(), added_records.to_string()),
+            ("added-files-size".to_string(), added_size.to_string()),
+            (
+                COMMITTED_FILE_PROPERTY.to_string(),
+                commit_marker.to_string(),
+            ),
+        ]);
+        for (total, added) in [
+            ("total-data-files", data_files.len() as i64),
+            ("total-records", added_records),
+            ("total-files-size", added_size),
+        ] {
+            let previous = match parent {
+                Some(parent) => parent
+                    .summary
+                    .get(total)
+                    .and_then(|v| v.parse::<i64>().ok()),
+                None => Some(0),
+            };
+            if let Some(previous) = previous {
+                summary.insert(total.to_string(), (previous + added).to_string());
+            }
+        }
+
+        Ok(Snapshot {
+            snapshot_id,
+            parent_snapshot_id: parent.map(|p| p.snapshot_id),
+            sequence_number,
+            timestamp_ms: to_millis(SystemTime::now()) as i64,
+            manifest_list: self.metadata_uri(&manifest_list_name),
+            summary,
+            schema_id: Some(metadata.current_schema_id),
+        })
+    }
+}
+
+fn write_manifest(
+    schema: &Value,
+    spec_id: i32,
+    snapshot_id: i64,
+    data_files: &[DataFile],
+) -> Result<Vec<u8>> {
+    let avro_schema = AvroSchema::parse_str(MANIFEST_SCHEMA)?;
+    let mut writer = apache_avro::Writer::new(&avro_schema, Vec::new());
+    writer.add_user_metadata("schema".to_string(), serde_json::to_string(schema)?)?;
+    writer.add_user_metadata("schema-id".to_string(), schema["schema-id"].to_string())?;
+    writer.add_user_metadata("partition-spec".to_string(), "[]")?;
+    writer.add_user_metadata("partition-spec-id".to_string(), spec_id.to_string())?;
+    writer.add_user_metadata("format-version".to_string(), "2")?;
+    writer.add_user_metadata("content".to_string(), "data")?;
+
+    for file in data_files {
+        writer.append(AvroValue::Record(vec![
+            // status 1 = ADDED
+            ("status".to_string(), AvroValue::Int(1)),
+            (
+                "snapshot_id".to_string(),
+                AvroValue::Union(1, Box::new(AvroValue::Long(snapshot_id))),
+            ),
+            // added files inherit their sequence numbers from the manifest list
+            (
+                "sequence_number".to_string(),
+                AvroValue::Union(0, Box::new(AvroValue::Null)),
+            ),
+            (
+                "file_sequence_number".to_string(),
+                AvroValue::Union(0, Box::new(AvroValue::Null)),
+            ),
+            (
+                "data_file".to_string(),
+                AvroValue::Record(vec![
+                    ("content".to_string(), AvroValue::Int(0)),
+                    (
+                        "file_path".to_string(),
+                        AvroValue::String(file.file_path.clone()),
+                    ),
+                    (
+                        "file_format".to_string(),
+                        AvroValue::String("PARQUET".to_string()),
+                    ),
+                    ("partition".to_string(), AvroValue::Record(vec![])),
+                    (
+                        "record_count".to_string(),
+                        AvroValue::Long(file.record_count),
+                    ),
+                    (
+                        "file_size_in_bytes".to_string(),
+                        AvroValue::Long(file.file_size_in_bytes),
+                    ),
+                ]),
+            ),
+        ]))?;
+    }
+
+    Ok(writer.into_inner()?)
+}
+
+async fn parquet_record_count(store: &dyn ObjectStore, path: &Path, size: usize) -> Result<i64> {
+    if size < 8 {
+        bail!("file is too small to be a parquet file");
+    }
+    let footer = store.get_range(path, size - 8..size).await?;
+    let metadata_len = decode_footer(footer.as_ref().try_into()?)?;
+    if metadata_len + 8 > size {
+        bail!("invalid parquet footer");
+    }
+    let metadata = store
+        .get_range(path, size - 8 - metadata_len..size - 8)
+        .await?;
+    Ok(decode_metadata(&metadata)?.file_metadata().num_rows())
+}
+
+/// Returns a URI for the object at `key` in the provider's store, in the form expected by
+/// Iceberg's FileIO implementations.
+fn uri_for_key(provider: &StorageProvider, key: &str) -> String {
+    let base = provider.object_store_base_url();
+    let base = match provider.config() {
+        BackendConfig::GCS(_) => base
+            .strip_prefix("https://")
+            .and_then(|b| b.strip_suffix(".storage.googleapis.com"))
+            .map(|bucket| format!("gs://{}", bucket))
+            .unwrap_or_else(|| base.to_string()),
+        _ => base.to_string(),
+    };
+    if base.ends_with('/') {
+        format!("{}{}", base, key)
+    } else {
+        format!("{}/{}", base, key)
+    }
+}
+
+fn validate_schema(table_schema: &Value, schema: &Schema) -> Result<()> {
+    let table_fields: HashSet<_> = table_schema["fields"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|field| field["name"].as_str())
+        .collect();
+    for field in schema.fields() {
+        if !table_fields.contains(field.name().as_str()) {
+            bail!(
+                "field '{}' is not part of the Iceberg table's schema",
+                field.name()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Converts an Arrow schema into an Iceberg schema, returning it along with the highest
+/// field id assigned.
+fn iceberg_schema(schema: &Schema) -> Result<(Value, i32)> {
+    let mut last_id = 0;
+    let fields = iceberg_fields(schema.fields(), &mut last_id)?;
+    Ok((
+        json!({"type": "struct", "schema-id": 0, "fields": fields}),
+        last_id,
+    ))
+}
+
+fn iceberg_fields(fields: &Fields, last_id: &mut i32) -> Result<Vec<Value>> {
+    // like Iceberg itself, assign ids to all fields of a struct before those of nested types
+    let ids: Vec<_> = fields
+        .iter()
+        .map(|_| {
+            *last_id += 1;
+            *last_id
+        })
+        .collect();
+
+    fields
+        .iter()
+        .zip(ids)
+        .map(|(field, id)| {
+            Ok(json!({
+                "id": id,
+                "name": field.name(),
+                "required": !field.is_nullable(),
+                "type": iceberg_type(field.data_type(), last_id)?,
+            }))
+        })
+        .collect()
+}
+
+fn iceberg_element(field: &Field, last_id: &mut i32) -> Result<(i32, Value)> {
+    *last_id += 1;
+    let id = *last_id;
+    Ok((id, iceberg_type(field.data_type(), last_id)?))
+}
+
+fn iceberg_type(data_type: &DataType, last_id: &mut i32) -> Result<Value> {
+    Ok(match data_type {
+        DataType::Boolean => json!("boolean"),
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::UInt8 | DataType::UInt16 => {
+            json!("int")
+        }
+        // unsigned ints are written as Int64s
+        DataType::Int64 | DataType::UInt32 => json!("long"),
+        DataType::Float32 => json!("float"),
+        DataType::Float64 => json!("double"),
+        DataType::Decimal128(precision, scale) => {
+            json!(format!("decimal({}, {})", precision, scale))
+        }
+        DataType::Date32 => json!("date"),
+        DataType::Time64(TimeUnit::Microsecond) => json!("time"),
+        // timestamps of all units are written with microsecond precision
+        DataType::Timestamp(_, None) => json!("timestamp"),
+        DataType::Timestamp(_, Some(_)) => json!("timestamptz"),
+        DataType::Utf8 | DataType::LargeUtf8 => json!("string"),
+        DataType::Binary | DataType::LargeBinary => json!("binary"),
+        DataType::FixedSizeBinary(size) => json!(format!("fixed[{}]", size)),
+        DataType::List(field) | DataType::LargeList(field) => {
+            let (element_id, element) = iceberg_element(field, last_id)?;
+            json!({
+                "type": "list",
+                "element-id": element_id,
+                "element-required": !field.is_nullable(),
+                "element": element,
+            })
+        }
+        DataType::Struct(fields) => json!({
+            "type": "struct",
+            "fields": iceberg_fields(fields, last_id)?,
+        }),
+        DataType::Map(entries, _) => {
+            let DataType::Struct(key_value) = entries.data_type() else {
+                bail!("invalid map type {}", data_type);
+            };
+            let (key_id, key) = iceberg_element(&key_value[0], last_id)?;
+            let (value_id, value) = iceberg_element(&key_value[1], last_id)?;
+            json!({
+                "type": "map",
+                "key-id": key_id,
+                "key": key,
+                "value-id": value_id,
+                "value-required": !key_value[1].is_nullable(),
+                "value": value,
+            })
+        }
+        other => bail!("data type {} is not supported by the Iceberg sink", other),
+    })
+}
+
+fn name_mapping(fields: &Value) -> Value {
+    fields
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|field| {
+            mapped_field(
+                &field["id"],
+                Value::Array(vec![field["name"].clone()]),
+                &field["type"],
+            )
+        })
+        .collect()
+}
+
+fn mapped_field(id: &Value, names: Value, field_type: &Value) -> Value {
+    let mut mapped = json!({"field-id": id, "names": names});
+    // the arrow parquet writer names nested fields differently from other Iceberg writers
+    let nested = match field_type["type"].as_str() {
+        Some("struct") => Some(name_mapping(&field_type["fields"])),
+        Some("list") => Some(json!([mapped_field(
+            &field_type["element-id"],
+            json!(["element", "item"]),
+            &field_type["element"]
+        )])),
+        Some("map") => Some(json!([
+            mapped_field(
+                &field_type["key-id"],
+                json!(["key", "keys"]),
+                &field_type["key"]
+            ),
+            mapped_field(
+                &field_type["value-id"],
+                json!(["value", "values"]),
+                &field_type["value"]
+            ),
+        ])),
+        _ => None,
+    };
+    if let Some(nested) = nested {
+        mapped["fields"] = nested;
+    }
+    mapped
+}
+
+struct LoadedTable {
+    metadata: TableMetadata,
+    // the version of the metadata file for hadoop tables
+    version: i64,
+    metadata_location: Option<String>,
+}
+
+enum CommitResult {
+    Committed,
+    Conflict,
+}
+
+enum Catalog {
+    Hadoop,
+    Rest(RestCatalog),
+}
+
+impl Catalog {
+    fn from_settings(settings: Option<IcebergSettings>) -> Result<Self> {
+        let Some(settings) = settings else {
+            return Ok(Catalog::Hadoop);
+        };
+        match settings.catalog_type.unwrap_or(CatalogType::Hadoop) {
+            CatalogType::Hadoop => Ok(Catalog::Hadoop),
+            CatalogType::Rest => Ok(Catalog::Rest(RestCatalog {
+                client: reqwest::Client::new(),
+                url: settings
+                    .rest_url
+                    .ok_or_else(|| anyhow!("restUrl is required for the REST catalog"))?
+                    .trim_end_matches('/')
+                    .to_string(),
+                token: settings.rest_token,
+                warehouse: settings.warehouse,
+                namespace: settings
+                    .namespace
+                    .ok_or_else(|| anyhow!("namespace is required for the REST catalog"))?
+                    .split('.')
+                    .map(|s| s.to_string())
+                    .collect(),
+                table_name: settings
+                    .table_name
+                    .ok_or_else(|| anyhow!("tableName is required for the REST catalog"))?,
+            })),
+        }
+    }
+
+    async fn load(&self, table: &TableFiles) -> Result<Option<LoadedTable>> {
+        match self {
+            Catalog::Hadoop => {
+                let hint = match table
+                    .store
+                    .get(&table.metadata_path(VERSION_HINT_FILE))
+                    .await
+                {
+                    Ok(result) => result.bytes().await?,
+                    Err(object_store::Error::NotFound { .. }) => return Ok(None),
+                    Err(e) => return Err(e.into()),
+                };
+                let mut version: i64 = std::str::from_utf8(&hint)?
+                    .trim()
+                    .parse()
+                    .context("invalid Iceberg version hint")?;
+                // the hint is written after the metadata, so it may lag behind
+                while table
+                    .exists(&table.metadata_path(&hadoop_metadata_file(version + 1)))
+                    .await?
+                {
+                    version += 1;
+                }
+                let name = hadoop_metadata_file(version);
+                let bytes = table
+                    .store
+                    .get(&table.metadata_path(&name))
+                    .await?
+                    .bytes()
+                    .await?;
+                Ok(Some(LoadedTable {
+                    metadata: serde_json::from_slice(&bytes)?,
+                    version,
+                    metadata_location: Some(table.metadata_uri(&name)),
+                }))
+            }
+            Catalog::Rest(rest) => rest.load_table().await,
+        }
+    }
+
+    async fn create(&self, table: &TableFiles, schema: &Schema) -> Result<LoadedTable> {
+        match self {
+            // hadoop tables are created by the first commit
+            Catalog::Hadoop => Ok(LoadedTable {
+                metadata: TableMetadata::new(table.location.clone(), schema)?,
+                version: 0,
+                metadata_location: None,
+            }),
+            Catalog::Rest(rest) => rest.create_table(table, schema).await,
+        }
+    }
+
+    async fn commit(
+        &self,
+        table: &TableFiles,
+        base: LoadedTable,
+        snapshot: Snapshot,
+    ) -> Result<CommitResult> {
+        let name_mapping = base.metadata.missing_name_mapping()?;
+        match self {
+            Catalog::Hadoop => {
+                let mut metadata = base.metadata.clone();
+                if let Some(name_mapping) = name_mapping {
+                    metadata
+                        .properties
+                        .insert(NAME_MAPPING_PROPERTY.to_string(), name_mapping);
+                }
+                if let Some(location) = &base.metadata_location {
+                    metadata.metadata_log.push(json!({
+                        "timestamp-ms": base.metadata.last_updated_ms,
+                        "metadata-file": location,
+                    }));
+                }
+                metadata.add_snapshot(snapshot);
+
+                let version = base.version + 1;
+                let path = table.metadata_path(&hadoop_metadata_file(version));
+                let bytes = Bytes::from(serde_json::to_vec(&metadata)?);
+                match table
+                    .store
+                    .put_opts(&path, bytes, PutMode::Create.into())
+                    .await
+                {
+                    Ok(_) => {}
+                    Err(object_store::Error::AlreadyExists { .. }) => {
+                        return Ok(CommitResult::Conflict)
+                    }
+                    // without conditional puts, a concurrent commit could be overwritten
+                    Err(object_store::Error::NotImplemented) => {
+                        bail!(
+                            "the storage for Iceberg table {} doesn't support conditional writes, \
+                            which the hadoop catalog requires; use the REST catalog instead",
+                            table.location
+                        );
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+
+                table
+                    .store
+                    .put(
+                        &table.metadata_path(VERSION_HINT_FILE),
+                        version.to_string().into(),
+                    )
+                    .await?;
+                Ok(CommitResult::Committed)
+            }
+            Catalog::Rest(rest) => {
+                rest.commit_table(&base.metadata, snapshot, name_mapping)
+                    .await
+            }
+        }
+    }
+}
+
+fn hadoop_metadata_file(version: i64) -> String {
+    format!("v{}.metadata.json", version)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LoadTableResult {
+    #[serde(default)]
+    metadata_location: Option<String>,
+    metadata: TableMetadata,
+}
+
+impl From<LoadTableResult> for LoadedTable {
+    fn from(result: LoadTableResult) -> Self {
+        LoadedTable {
+            metadata: result.metadata,
+            version: 0,
+            metadata_location: result.metadata_location,
+        }
+    }
+}
+
+struct RestCatalog {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    warehouse: Option<String>,
+    namespace: Vec<String>,
+    table_name: String,
+}
+
+impl RestCatalog {
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Returns the base url for catalog requests, including any prefix the catalog
+    /// configures for our warehouse.
+    async fn base_url(&self) -> Result<String> {
+        let mut request = self.request(Method::GET, &format!("{}/v1/config", self.url));
+        if let Some(warehouse) = &self.warehouse {
+            request = request.query(&[("warehouse", warehouse)]);
+        }
+        let config: Value = check_response(request.send().await?).await?.json().await?;
+        Ok(match config["overrides"]["prefix"].as_str() {
+            Some(prefix) => format!("{}/v1/{}", self.url, prefix),
+            None => format!("{}/v1", self.url),
+        })
+    }
+
+    fn namespace_url(&self, base_url: &str) -> String {
+        // multi-level namespaces are separated by the unit separator
+        let namespace = self.namespace.join("\u{1f}");
+        format!(
+            "{}/namespaces/{}",
+            base_url,
+            utf8_percent_encode(&namespace, NON_ALPHANUMERIC)
+        )
+    }
+
+    fn table_url(&self, base_url: &str) -> String {
+        format!(
+            "{}/tables/{}",
+            self.namespace_url(base_url),
+            utf8_percent_encode(&self.table_name, NON_ALPHANUMERIC)
+        )
+    }
+
+    async fn load_table(&self) -> Result<Option<LoadedTable>> {
+        let url = self.table_url(&self.base_url().await?);
+        let response = self.request(Method::GET, &url).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let result: LoadTableResult = check_response(response).await?.json().await?;
+        Ok(Some(result.into()))
+    }
+
+    async fn create_table(&self, table: &TableFiles, schema: &Schema) -> Result<LoadedTable> {
+        let base_url = self.base_url().await?;
+        let (schema, _) = iceberg_schema(schema)?;
+        let response = self
+            .request(
+                Method::POST,
+                &format!("{}/tables", self.namespace_url(&base_url)),
+            )
+            .json(&json!({
+                "name": self.table_name,
+                "location": table.location,
+                "schema": schema,
+            }))
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::CONFLICT {
+            // created concurrently by someone else
+            return self
+                .load_table()
+                .await?
+                .ok_or_else(|| anyhow!("Iceberg table {} not found", self.table_name));
+        }
+
+        info!(
+            "created Iceberg table {}.{} at {}",
+            self.namespace.join("."),
+            self.table_name,
+            table.location
+        );
+        let result: LoadTableResult = check_response(response).await?.json().await?;
+        Ok(result.into())
+    }
+
+    async fn commit_table(
+        &self,
+        metadata: &TableMetadata,
+        snapshot: Snapshot,
+        name_mapping: Option<String>,
+    ) -> Result<CommitResult> {
+        let url = self.table_url(&self.base_url().await?);
+        let mut updates = vec![];
+        if let Some(name_mapping) = name_mapping {
+            updates.push(json!({
+                "action": "set-properties",
+                "updates": {NAME_MAPPING_PROPERTY: name_mapping},
+            }));
+        }
+        updates.push(json!({"action": "add-snapshot", "snapshot": snapshot}));
+        updates.push(json!({
+            "action": "set-snapshot-ref",
+            "ref-name": "main",
+            "type": "branch",
+            "snapshot-id": snapshot.snapshot_id,
+        }));
+
+        let response = self
+            .request(Method::POST, &url)
+            .json(&json!({
+                "identifier": {"namespace": self.namespace, "name": self.table_name},
+                "requirements": [
+                    {"type": "assert-table-uuid", "uuid": metadata.table_uuid},
+                    {
+                        "type": "assert-ref-snapshot-id",
+                        "ref": "main",
+                        "snapshot-id": snapshot.parent_snapshot_id,
+                    },
+                ],
+                "updates": updates,
+            }))
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::CONFLICT {
+            return Ok(CommitResult::Conflict);
+        }
+        check_response(response).await?;
+        Ok(CommitResult::Committed)
+    }
+}
+
+async fn check_response(response: Response) -> Result<Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    bail!("Iceberg REST catalog returned {}: {}", status, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{
+        Int64Array, ListBuilder, TimestampNanosecondArray, TimestampNanosecondBuilder, UInt32Array,
+    };
+    use arrow::datatypes::{Int64Type, TimestampMicrosecondType};
+    use parquet::arrow::ArrowWriter;
+
+    #[test]
+    fn test_iceberg_schema() {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new(
+                "tags",
+                DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+                true,
+            ),
+            Field::new(
+                "location",
+                DataType::Struct(
+                    vec![
+                        Field::new("lat", DataType::Float64, false),
+                        Field::new("lon", DataType::Float64, false),
+                    ]
+                    .into(),
+                ),
+                true,
+            ),
+            Field::new("ts", DataType::Timestamp(TimeUnit::Microsecond, None), true),
+        ]);
+
+        let (iceberg, last_id) = iceberg_schema(&schema).unwrap();
+        assert_eq!(last_id, 7);
+        assert_eq!(
+            iceberg,
+            json!({
+                "type": "struct",
+                "schema-id": 0,
+                "fields": [
+                    {"id": 1, "name": "id", "required": true, "type": "long"},
+                    {"id": 2, "name": "tags", "required": false, "type": {
+                        "type": "list",
+                        "element-id": 5,
+                        "element-required": false,
+                        "element": "string",
+                    }},
+                    {"id": 3, "name": "location", "required": false, "type": {
+                        "type": "struct",
+                        "fields": [
+                            {"id": 6, "name": "lat", "required": true, "type": "double"},
+                            {"id": 7, "name": "lon", "required": true, "type": "double"},
+                        ],
+                    }},
+                    {"id": 4, "name": "ts", "required": false, "type": "timestamp"},
+                ],
+            })
+        );
+
+        assert_eq!(
+            name_mapping(&iceberg["fields"]),
+            json!([
+                {"field-id": 1, "names": ["id"]},
+                {"field-id": 2, "names": ["tags"], "fields": [
+                    {"field-id": 5, "names": ["element", "item"]},
+                ]},
+                {"field-id": 3, "names": ["location"], "fields": [
+                    {"field-id": 6, "names": ["lat"]},
+                    {"field-id": 7, "names": ["lon"]},
+                ]},
+                {"field-id": 4, "names": ["ts"]},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_timestamps_and_unsigned_ints() {
+        let schema = Schema::new(vec![
+            Field::new("ts", DataType::Timestamp(TimeUnit::Nanosecond, None), false),
+            Field::new(
+                "times",
+                DataType::List(Arc::new(Field::new(
+                    "item",
+                    DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+                    true,
+                ))),
+                true,
+            ),
+            Field::new("count", DataType::UInt32, false),
+        ]);
+
+        let (iceberg, _) = iceberg_schema(&schema).unwrap();
+        assert_eq!(iceberg["fields"][0]["type"], json!("timestamp"));
+        assert_eq!(
+            iceberg["fields"][1]["type"]["element"],
+            json!("timestamptz")
+        );
+        assert_eq!(iceberg["fields"][2]["type"], json!("long"));
+
+        // the values are written in the types the Iceberg schema describes
+        let write_schema = Arc::new(iceberg_write_schema(&schema));
+        let mut times = ListBuilder::new(TimestampNanosecondBuilder::new().with_timezone("UTC"));
+        times.values().append_value(2_000_001_000);
+        times.values().append_null();
+        times.append(true);
+        times.append(false);
+
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(TimestampNanosecondArray::from(vec![1_000_000_999, 0])),
+                Arc::new(times.finish()),
+                Arc::new(UInt32Array::from(vec![u32::MAX, 1])),
+            ],
+        )
+        .unwrap();
+
+        let cast = cast_for_iceberg(&batch, &write_schema).unwrap();
+        assert_eq!(cast.schema(), write_schema);
+        assert_eq!(
+            cast.column(0)
+                .as_primitive::<TimestampMicrosecondType>()
+                .values()[..],
+            [1_000_000, 0]
+        );
+        let times = cast.column(1).as_list::<i32>();
+        assert!(times.is_null(1));
+        let first = times.value(0);
+        let first = first.as_primitive::<TimestampMicrosecondType>();
+        assert_eq!(first.value(0), 2_000_001);
+        assert!(first.is_null(1));
+        assert_eq!(
+            cast.column(2).as_primitive::<Int64Type>().values()[..],
+            [u32::MAX as i64, 1]
+        );
+    }
+
+    async fn local_table() -> (tempfile::TempDir, Arc<StorageProvider>) {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = StorageProvider::for_url(&format!("file://{}", dir.path().display()))
+            .await
+            .unwrap();
+        (dir, Arc::new(provider))
+    }
+
+    fn table_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("ts", DataType::Timestamp(TimeUnit::Nanosecond, None), false),
+        ]))
+    }
+
+    async fn write_data_file(provider: &StorageProvider, name: &str, rows: i64) -> FinishedFile {
+        let schema = table_schema();
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from_iter_values(0..rows)),
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    (0..rows).map(|i| i * 1_000_000_000),
+                )),
+            ],
+        )
+        .unwrap();
+        let write_schema = Arc::new(iceberg_write_schema(&schema));
+
+        let mut writer = ArrowWriter::try_new(vec![], write_schema.clone(), None).unwrap();
+        writer
+            .write(&cast_for_iceberg(&batch, &write_schema).unwrap())
+            .unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        let filename = format!("table/data/{}", name);
+        let size = bytes.len();
+        provider
+            .get_backing_store()
+            .put(&Path::from(filename.as_str()), bytes.into())
+            .await
+            .unwrap();
+
+        FinishedFile {
+            filename,
+            partition: None,
+            size,
+        }
+    }
+
+    async fn commit(provider: &Arc<StorageProvider>, files: Vec<FinishedFile>) -> Option<i64> {
+        commit_files_to_iceberg(
+            files,
+            Path::from("table"),
+            provider.clone(),
+            None,
+            table_schema(),
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn read_metadata(provider: &StorageProvider, version: i64) -> Option<TableMetadata> {
+        let path = Path::from(format!("table/metadata/{}", hadoop_metadata_file(version)));
+        match provider.get_backing_store().get(&path).await {
+            Ok(result) => Some(serde_json::from_slice(&result.bytes().await.unwrap()).unwrap()),
+            Err(object_store::Error::NotFound { .. }) => None,
+            Err(e) => panic!("failed to read metadata: {:?}", e),
+        }
+    }
+
+    async fn version_hint(provider: &StorageProvider) -> String {
+        let path = Path::from(format!("table/metadata/{}", VERSION_HINT_FILE));
+        let bytes = provider
+            .get_backing_store()
+            .get(&path)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_commit_to_hadoop_table() {
+        let (_dir, provider) = local_table().await;
+
+        let first = write_data_file(&provider, "a.parquet", 3).await;
+        let first_id = commit(&provider, vec![first]).await.unwrap();
+
+        assert_eq!(version_hint(&provider).await, "1");
+        let metadata = read_metadata(&provider, 1).await.unwrap();
+        assert_eq!(metadata.current_snapshot_id, Some(first_id));
+        assert_eq!(metadata.last_sequence_number, 1);
+        assert_eq!(
+            metadata.current_schema().unwrap()["fields"][1]["type"],
+            json!("timestamp")
+        );
+        assert!(metadata.properties.contains_key(NAME_MAPPING_PROPERTY));
+
+        let snapshot = metadata.current_snapshot().unwrap();
+        assert_eq!(snapshot.parent_snapshot_id, None);
+        assert_eq!(snapshot.summary["added-records"], "3");
+        assert_eq!(snapshot.summary["total-records"], "3");
+        assert_eq!(
+            snapshot.summary[COMMITTED_FILE_PROPERTY],
+            uri_for_key(&provider, "table/data/a.parquet")
+        );
+
+        let second = write_data_file(&provider, "b.parquet", 2).await;
+        let second_id = commit(&provider, vec![second]).await.unwrap();
+        assert_ne!(first_id, second_id);
+
+        assert_eq!(version_hint(&provider).await, "2");
+        let metadata = read_metadata(&provider, 2).await.unwrap();
+        assert_eq!(metadata.current_snapshot_id, Some(second_id));
+        assert_eq!(metadata.snapshots.len(), 2);
+        assert_eq!(metadata.metadata_log.len(), 1);
+
+        let snapshot = metadata.current_snapshot().unwrap();
+        assert_eq!(snapshot.parent_snapshot_id, Some(first_id));
+        assert_eq!(snapshot.sequence_number, 2);
+        assert_eq!(snapshot.summary["total-records"], "5");
+        assert_eq!(snapshot.summary["total-data-files"], "2");
+
+        // the new manifest list carries over the first snapshot's manifest
+        let table = TableFiles::new(provider.clone(), Path::from("table"));
+        let list_schema = AvroSchema::parse_str(MANIFEST_LIST_SCHEMA).unwrap();
+        let bytes = table.read_uri(&snapshot.manifest_list).await.unwrap();
+        let manifests = apache_avro::Reader::with_schema(&list_schema, &bytes[..])
+            .unwrap()
+            .count();
+        assert_eq!(manifests, 2);
+
+        // nothing is committed without files
+        assert_eq!(commit(&provider, vec![]).await, None);
+        assert!(read_metadata(&provider, 3).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recommit_after_restore() {
+        let (_dir, provider) = local_table().await;
+
+        let first = write_data_file(&provider, "a.parquet", 3).await;
+        let first_id = commit(&provider, vec![first.clone()]).await.unwrap();
+
+        // recommitting the same epoch's files, as happens when restoring from a checkpoint
+        // taken before the commit was acknowledged, finds the existing snapshot
+        assert_eq!(commit(&provider, vec![first.clone()]).await, Some(first_id));
+        assert!(read_metadata(&provider, 2).await.is_none());
+
+        // including once later commits have been made
+        let second = write_data_file(&provider, "b.parquet", 2).await;
+        let second_id = commit(&provider, vec![second]).await.unwrap();
+        assert_eq!(commit(&provider, vec![first]).await, Some(first_id));
+        assert!(read_metadata(&provider, 3).await.is_none());
+        assert_eq!(
+            read_metadata(&provider, 2)
+                .await
+                .unwrap()
+                .current_snapshot_id,
+            Some(second_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commit_conflict() {
+        let (_dir, provider) = local_table().await;
+        let table = TableFiles::new(provider.clone(), Path::from("table"));
+        let schema = table_schema();
+
+        let first = write_data_file(&provider, "a.parquet", 3).await;
+        commit(&provider, vec![first]).await.unwrap();
+
+        // another writer commits between our load and our commit
+        let base = Catalog::Hadoop.load(&table).await.unwrap().unwrap();
+        assert_eq!(base.version, 1);
+        let second = write_data_file(&provider, "b.parquet", 2).await;
+        let concurrent_id = commit(&provider, vec![second]).await.unwrap();
+
+        let third = write_data_file(&provider, "c.parquet", 1).await;
+        let data_files = table.data_files(&[third.clone()]).await.unwrap();
+        let snapshot = table
+            .write_snapshot(&base.metadata, &data_files, &data_files[0].file_path)
+            .await
+            .unwrap();
+        assert!(matches!(
+            Catalog::Hadoop
+                .commit(&table, base, snapshot)
+                .await
+                .unwrap(),
+            CommitResult::Conflict
+        ));
+
+        // the concurrent commit wasn't overwritten
+        let metadata = read_metadata(&provider, 2).await.unwrap();
+        assert_eq!(metadata.current_snapshot_id, Some(concurrent_id));
+
+        // retrying reloads the table and commits on top of the concurrent snapshot
+        let third_id = commit_files_to_iceberg(
+            vec![third],
+            Path::from("table"),
+            provider.clone(),
+            None,
+            schema,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let metadata = read_metadata(&provider, 3).await.unwrap();
+        assert_eq!(metadata.current_snapshot_id, Some(third_id));
+        assert_eq!(
+            metadata.current_snapshot().unwrap().parent_snapshot_id,
+            Some(concurrent_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_with_stale_version_hint() {
+        let (_dir, provider) = local_table().await;
+        let table = TableFiles::new(provider.clone(), Path::from("table"));
+
+        assert!(Catalog::Hadoop.load(&table).await.unwrap().is_none());
+
+        for name in ["a.parquet", "b.parquet"] {
+            let file = write_data_file(&provider, name, 1).await;
+            commit(&provider, vec![file]).await.unwrap();
+        }
+
+        // the hint is written after the metadata, so a failure in between leaves it behind
+        provider
+            .get_backing_store()
+            .put(
+                &table.metadata_path(VERSION_HINT_FILE),
+                Bytes::from_static(b"1"),
+            )
+            .await
+            .unwrap();
+
+        let loaded = Catalog::Hadoop.load(&table).await.unwrap().unwrap();
+        assert_eq!(loaded.version, 2);
+        assert_eq!(loaded.metadata.snapshots.len(), 2);
+    }
+}
@@ -37,7 +37,8 @@ impl BatchBufferingWriter for JsonWriter {
         };
         Self {
             current_buffer: Vec::new(),
-            serializer: ArrowSerializer::new(format.expect("should have format")),
+            serializer: ArrowSerializer::new(format.expect("should have format"))
+                .expect("filesystem sinks only serialize JSON, which needs no schema"),
             target_part_size,
         }
     }
@@ -109,7 +110,8 @@ impl LocalWriter for JsonLocalWriter {
         JsonLocalWriter {
             tmp_path,
             final_path,
-            serializer: ArrowSerializer::new(format.expect("should have format")),
+            serializer: ArrowSerializer::new(format.expect("should have format"))
+                .expect("filesystem sinks only serialize JSON, which needs no schema"),
             file,
             stats: None,
             schema,
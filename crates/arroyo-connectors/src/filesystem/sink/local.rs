@@ -20,10 +20,10 @@ use crate::filesystem::{sink::two_phase_committer::TwoPhaseCommitter, FileSettin
 use anyhow::{bail, Result};
 
 use super::{
-    add_suffix_prefix, delta, get_partitioner_from_file_settings, parquet::batches_by_partition,
-    two_phase_committer::TwoPhaseCommitterOperator, CommitState, CommitStyle, FileNaming,
-    FileSystemTable, FilenameStrategy, FinishedFile, MultiPartWriterStats, RollingPolicy,
-    TableType,
+    add_suffix_prefix, delta, get_partitioner_from_file_settings, iceberg,
+    parquet::batches_by_partition, two_phase_committer::TwoPhaseCommitterOperator, CommitState,
+    CommitStyle, FileNaming, FileSystemTable, FilenameStrategy, FinishedFile, MultiPartWriterStats,
+    RollingPolicy, TableType,
 };
 
 pub struct LocalFileSystemWriter<V: LocalWriter> {
@@ -63,6 +63,7 @@ impl<V: LocalWriter> LocalFileSystemWriter<V> {
         };
         let commit_state = match file_settings.as_ref().unwrap().commit_style.unwrap() {
             CommitStyle::DeltaLake => CommitState::DeltaLake { last_version: -1 },
+            CommitStyle::Iceberg => CommitState::Iceberg,
             CommitStyle::Direct => CommitState::VanillaParquet,
         };
 
@@ -310,21 +311,35 @@ impl<V: LocalWriter + Send + 'static> TwoPhaseCommitter for LocalFileSystemWrite
                 size: destination.metadata()?.len() as usize,
             });
         }
-        if let CommitState::DeltaLake { last_version } = self.commit_state {
-            let storage_provider = Arc::new(StorageProvider::for_url("/").await?);
-            if let Some(version) = delta::commit_files_to_delta(
-                finished_files,
-                object_store::path::Path::parse(&self.final_dir)?,
-                storage_provider,
-                last_version,
-                Arc::new(self.schema.as_ref().unwrap().schema_without_timestamp()),
-            )
-            .await?
-            {
-                self.commit_state = CommitState::DeltaLake {
-                    last_version: version,
-                };
+        match self.commit_state {
+            CommitState::DeltaLake { last_version } => {
+                let storage_provider = Arc::new(StorageProvider::for_url("/").await?);
+                if let Some(version) = delta::commit_files_to_delta(
+                    finished_files,
+                    object_store::path::Path::parse(&self.final_dir)?,
+                    storage_provider,
+                    last_version,
+                    Arc::new(self.schema.as_ref().unwrap().schema_without_timestamp()),
+                )
+                .await?
+                {
+                    self.commit_state = CommitState::DeltaLake {
+                        last_version: version,
+                    };
+                }
+            }
+            CommitState::Iceberg => {
+                let storage_provider = Arc::new(StorageProvider::for_url("/").await?);
+                iceberg::commit_files_to_iceberg(
+                    finished_files,
+                    object_store::path::Path::parse(&self.final_dir)?,
+                    storage_provider,
+                    iceberg::settings_for_table(&self.table_properties),
+                    Arc::new(self.schema.as_ref().unwrap().schema_without_timestamp()),
+                )
+                .await?;
             }
+            CommitState::VanillaParquet => {}
         }
         Ok(())
     }
@@ -44,7 +44,8 @@ use uuid::Uuid;
 
 use arroyo_types::*;
 pub mod arrow;
-mod delta;
+pub(crate) mod delta;
+mod iceberg;
 pub mod json;
 pub mod local;
 pub mod parquet;
@@ -93,6 +94,7 @@ impl<R: MultiPartWriter + Send + 'static> FileSystemSink<R> {
         let commit_strategy = match file_settings.as_ref().unwrap().commit_style.unwrap() {
             CommitStyle::Direct => CommitStrategy::PerSubtask,
             CommitStyle::DeltaLake => CommitStrategy::PerOperator,
+            CommitStyle::Iceberg => CommitStrategy::PerOperator,
         };
 
         TwoPhaseCommitterOperator::new(Self {
@@ -118,6 +120,7 @@ impl<R: MultiPartWriter + Send + 'static> FileSystemSink<R> {
             file_settings,
             format_settings: _,
             storage_options,
+            iceberg_settings: _,
         } = self.table.clone().table_type
         else {
             unreachable!("multi-part writer can only be used as sink");
@@ -452,6 +455,7 @@ struct AsyncMultipartFileSystemWriter<R: MultiPartWriter> {
 #[derive(Debug, Clone, Encode, Decode, PartialEq, Eq)]
 pub enum CommitState {
     DeltaLake { last_version: i64 },
+    Iceberg,
     VanillaParquet,
 }
 
@@ -733,6 +737,7 @@ where
 
         let commit_state = match file_settings.commit_style.unwrap() {
             CommitStyle::DeltaLake => CommitState::DeltaLake { last_version: -1 },
+            CommitStyle::Iceberg => CommitState::Iceberg,
             CommitStyle::Direct => CommitState::VanillaParquet,
         };
         let mut file_naming = file_settings
@@ -941,20 +946,33 @@ where
                 finished_files.push(file);
             }
         }
-        if let CommitState::DeltaLake { last_version } = self.commit_state {
-            if let Some(new_version) = delta::commit_files_to_delta(
-                finished_files,
-                self.path.clone(),
-                self.object_store.clone(),
-                last_version,
-                Arc::new(self.schema.schema_without_timestamp()),
-            )
-            .await?
-            {
-                self.commit_state = CommitState::DeltaLake {
-                    last_version: new_version,
-                };
+        match self.commit_state {
+            CommitState::DeltaLake { last_version } => {
+                if let Some(new_version) = delta::commit_files_to_delta(
+                    finished_files,
+                    self.path.clone(),
+                    self.object_store.clone(),
+                    last_version,
+                    Arc::new(self.schema.schema_without_timestamp()),
+                )
+                .await?
+                {
+                    self.commit_state = CommitState::DeltaLake {
+                        last_version: new_version,
+                    };
+                }
+            }
+            CommitState::Iceberg => {
+                iceberg::commit_files_to_iceberg(
+                    finished_files,
+                    self.path.clone(),
+                    self.object_store.clone(),
+                    iceberg::settings_for_table(&self.properties),
+                    Arc::new(self.schema.schema_without_timestamp()),
+                )
+                .await?;
             }
+            CommitState::VanillaParquet => {}
         }
         let finished_message = CheckpointData::Finished {
             max_file_index: self.max_file_index,
@@ -967,7 +985,7 @@ where
     fn delta_version(&mut self) -> i64 {
         match self.commit_state {
             CommitState::DeltaLake { last_version } => last_version,
-            CommitState::VanillaParquet => 0,
+            CommitState::Iceberg | CommitState::VanillaParquet => 0,
         }
     }
 
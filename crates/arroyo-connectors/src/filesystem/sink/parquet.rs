@@ -10,6 +10,7 @@ use anyhow::Result;
 use arrow::{
     array::{Array, RecordBatch, StringArray, TimestampNanosecondArray},
     compute::{sort_to_indices, take},
+    datatypes::SchemaRef,
 };
 use arroyo_rpc::{df::ArroyoSchemaRef, formats::Format};
 use arroyo_types::from_nanos;
@@ -21,6 +22,7 @@ use parquet::{
 };
 
 use super::{
+    iceberg,
     local::{CurrentFileRecovery, FilePreCommit, LocalWriter},
     BatchBufferingWriter, FileSettings, FileSystemTable, MultiPartWriterStats, TableType,
 };
@@ -55,6 +57,26 @@ fn writer_properties_from_table(table: &FileSystemTable) -> WriterProperties {
     parquet_writer_options.build()
 }
 
+/// Creates the writer for a file, along with the schema batches must be cast to first for tables
+/// (like Iceberg's) that don't support all of our types
+fn create_writer(
+    table: &FileSystemTable,
+    schema: &ArroyoSchemaRef,
+    buffer: SharedBuffer,
+) -> (ArrowWriter<SharedBuffer>, Option<SchemaRef>) {
+    let schema_without_timestamp = schema.schema_without_timestamp();
+    let cast_schema = iceberg::write_schema_for_table(table, &schema_without_timestamp);
+    let writer = ArrowWriter::try_new(
+        buffer,
+        cast_schema
+            .clone()
+            .unwrap_or_else(|| Arc::new(schema_without_timestamp)),
+        Some(writer_properties_from_table(table)),
+    )
+    .unwrap();
+    (writer, cast_schema)
+}
+
 /// A buffer with interior mutability shared by the [`ArrowWriter`] and
 /// [`AsyncArrowWriter`]. From Arrow. This lets us write data from the buffer to S3.
 #[derive(Clone)]
@@ -91,6 +113,7 @@ pub struct RecordBatchBufferingWriter {
     shared_buffer: SharedBuffer,
     target_part_size: usize,
     schema: ArroyoSchemaRef,
+    cast_schema: Option<SchemaRef>,
 }
 
 impl BatchBufferingWriter for RecordBatchBufferingWriter {
@@ -109,19 +132,14 @@ impl BatchBufferingWriter for RecordBatchBufferingWriter {
             5 * 1024 * 1024
         };
         let shared_buffer = SharedBuffer::new(target_part_size);
-        let writer_properties = writer_properties_from_table(config);
-        let writer = ArrowWriter::try_new(
-            shared_buffer.clone(),
-            Arc::new(schema.schema_without_timestamp()),
-            Some(writer_properties),
-        )
-        .unwrap();
+        let (writer, cast_schema) = create_writer(config, &schema, shared_buffer.clone());
 
         Self {
             writer: Some(writer),
             shared_buffer,
             target_part_size,
             schema,
+            cast_schema,
         }
     }
 
@@ -133,6 +151,9 @@ impl BatchBufferingWriter for RecordBatchBufferingWriter {
         let writer = self.writer.as_mut().unwrap();
         // remove timestamp column
         self.schema.remove_timestamp_column(&mut data);
+        if let Some(cast_schema) = &self.cast_schema {
+            data = iceberg::cast_for_iceberg(&data, cast_schema).unwrap();
+        }
         writer.write(&data).unwrap();
         if self.buffer_length() > self.target_part_size {
             Some(self.evict_current_buffer())
@@ -187,6 +208,7 @@ pub struct ParquetLocalWriter {
     shared_buffer: SharedBuffer,
     stats: Option<MultiPartWriterStats>,
     schema: ArroyoSchemaRef,
+    cast_schema: Option<SchemaRef>,
 }
 
 impl LocalWriter for ParquetLocalWriter {
@@ -198,13 +220,7 @@ impl LocalWriter for ParquetLocalWriter {
         schema: ArroyoSchemaRef,
     ) -> Self {
         let shared_buffer = SharedBuffer::new(0);
-        let writer_properties = writer_properties_from_table(table_properties);
-        let writer = ArrowWriter::try_new(
-            shared_buffer.clone(),
-            Arc::new(schema.schema_without_timestamp()),
-            Some(writer_properties),
-        )
-        .unwrap();
+        let (writer, cast_schema) = create_writer(table_properties, &schema, shared_buffer.clone());
         let file = File::create(tmp_path.clone()).unwrap();
         Self {
             writer: Some(writer),
@@ -214,6 +230,7 @@ impl LocalWriter for ParquetLocalWriter {
             shared_buffer,
             stats: None,
             schema,
+            cast_schema,
         }
     }
 
@@ -236,6 +253,9 @@ impl LocalWriter for ParquetLocalWriter {
             self.stats.as_mut().unwrap().last_write_at = Instant::now();
         }
         self.schema.remove_timestamp_column(&mut batch);
+        if let Some(cast_schema) = &self.cast_schema {
+            batch = iceberg::cast_for_iceberg(&batch, cast_schema)?;
+        }
         self.writer.as_mut().unwrap().write(&batch)?;
         Ok(())
     }
@@ -2,37 +2,43 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::future::ready;
 use std::hash::{Hash, Hasher};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use anyhow::Result;
-use arrow::array::RecordBatch;
+use arrow::array::{ArrayRef, RecordBatch};
 
-use arrow::datatypes::SchemaRef;
+use arrow::datatypes::{Schema, SchemaRef};
 use arroyo_state::global_table_config;
 use arroyo_state::tables::global_keyed_map::GlobalKeyedView;
 use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
 use async_trait::async_trait;
 use bincode::{Decode, Encode};
 use datafusion::common::ScalarValue;
+use deltalake::kernel::{Action, Add};
+use deltalake::table::PeekCommit;
 use futures::StreamExt;
+use object_store::path::Path;
 use parquet::arrow::async_reader::ParquetObjectReader;
 use parquet::arrow::ParquetRecordBatchStreamBuilder;
 
 use arroyo_operator::context::ArrowContext;
+use arroyo_operator::dead_letter::SourcePosition;
 use regex::Regex;
 use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::select;
-use tokio_stream::wrappers::LinesStream;
+use tokio_stream::wrappers::{LinesStream, ReceiverStream};
 use tokio_stream::Stream;
+use tokio_util::io::SyncIoBridge;
 use tracing::info;
 
-use crate::filesystem::{CompressionFormat, TableType};
+use crate::filesystem::sink::delta;
+use crate::filesystem::{CompressionFormat, DeltaSettings, TableType};
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
 use arroyo_rpc::formats::{BadData, Format, Framing};
 use arroyo_rpc::grpc::TableConfig;
 use arroyo_rpc::{grpc::StopMode, ControlMessage};
-use arroyo_storage::StorageProvider;
+use arroyo_storage::{BackendConfig, StorageProvider};
 use arroyo_types::{to_nanos, UserError};
 
 #[allow(unused)]
@@ -42,6 +48,9 @@ pub struct FileSystemSourceFunc {
     pub framing: Option<Framing>,
     pub bad_data: Option<BadData>,
     pub file_states: HashMap<String, FileReadState>,
+    // values of the partition columns of Delta files, which aren't stored in the files themselves
+    pub partition_values: HashMap<String, HashMap<String, Option<String>>>,
+    pub delta_state: Option<DeltaReadState>,
 }
 
 #[derive(Encode, Decode, Debug, Clone, PartialEq, PartialOrd)]
@@ -50,10 +59,56 @@ pub enum FileReadState {
     RecordsRead(usize),
 }
 
+/// Position of a subtask in a Delta table's log: all of its files up to `version` have been
+/// read, except for those of the initial snapshot if `snapshot_finished` is false. Files covered
+/// by this position are dropped from the subtask's file states, so that they don't grow with
+/// the length of the log.
+#[derive(Encode, Decode, Debug, Clone, PartialEq)]
+pub struct DeltaReadState {
+    version: i64,
+    snapshot_finished: bool,
+    // the parallelism the source was running at, which determines the files the subtask owned
+    parallelism: usize,
+}
+
+impl DeltaReadState {
+    /// Whether a file added by `commit` (or by the initial snapshot, if `None`) had been read by
+    /// the subtask that owned it, according to the restored positions of every subtask
+    fn covers(restored: &HashMap<usize, DeltaReadState>, path: &str, commit: Option<i64>) -> bool {
+        let Some(parallelism) = restored.values().next().map(|state| state.parallelism) else {
+            return false;
+        };
+
+        restored
+            .get(&assigned_task(path, parallelism))
+            .is_some_and(|state| {
+                state.snapshot_finished && commit.map_or(true, |commit| commit <= state.version)
+            })
+    }
+}
+
+const DEFAULT_DELTA_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+// number of decoded Avro values buffered between the decoding thread and the source
+const AVRO_READ_BUFFER: usize = 1024;
+
+fn assigned_task(path: &str, parallelism: usize) -> usize {
+    // hash the path and modulo by the number of tasks
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    (hasher.finish() as usize) % parallelism
+}
+
+fn is_assigned(path: &str, parallelism: usize, task_index: usize) -> bool {
+    assigned_task(path, parallelism) == task_index
+}
+
 #[async_trait]
 impl SourceOperator for FileSystemSourceFunc {
     fn tables(&self) -> HashMap<String, TableConfig> {
-        global_table_config("a", "fs")
+        let mut tables = global_table_config("a", "fs");
+        tables.extend(global_table_config("v", "delta versions"));
+        tables
     }
 
     fn name(&self) -> String {
@@ -86,12 +141,13 @@ impl FileSystemSourceFunc {
     }
 
     async fn run_int(&mut self, ctx: &mut ArrowContext) -> Result<SourceFinishType, UserError> {
-        let (storage_provider, regex_pattern) = match &self.table {
+        let (storage_provider, regex_pattern, delta) = match &self.table {
             TableType::Source {
                 path,
                 storage_options,
                 compression_format: _,
                 regex_pattern,
+                delta_settings,
             } => {
                 let storage_provider =
                     StorageProvider::for_url_with_options(&path, storage_options.clone())
@@ -109,7 +165,10 @@ impl FileSystemSourceFunc {
                             err.to_string(),
                         )
                     })?;
-                (storage_provider, matcher)
+                let delta = delta_settings
+                    .clone()
+                    .map(|settings| (path.clone(), settings));
+                (storage_provider, matcher, delta)
             }
             TableType::Sink { .. } => {
                 return Err(UserError::new(
@@ -122,10 +181,23 @@ impl FileSystemSourceFunc {
             self.format.clone(),
             self.framing.clone(),
             self.bad_data.clone(),
-        );
+        )?;
         let parallelism = ctx.task_info.parallelism;
         let task_index = ctx.task_info.task_index;
 
+        let state: &mut GlobalKeyedView<String, (String, FileReadState)> = ctx
+            .table_manager
+            .get_global_keyed_state("a")
+            .await
+            .expect("should have table");
+        self.file_states = state.get_all().clone().into_values().collect();
+
+        if let Some((path, settings)) = delta {
+            return self
+                .run_delta(ctx, &storage_provider, &path, &settings)
+                .await;
+        }
+
         // TODO: sort by creation time
         let mut file_paths = storage_provider
             .list(regex_pattern.is_some())
@@ -135,10 +207,7 @@ impl FileSystemSourceFunc {
                 let Ok(path) = path else {
                     return ready(true);
                 };
-                // hash the path and modulo by the number of tasks
-                let mut hasher = DefaultHasher::new();
-                path.hash(&mut hasher);
-                if (hasher.finish() as usize) % parallelism != task_index {
+                if !is_assigned(path.as_ref(), parallelism, task_index) {
                     info!("filtering out path {}", path.to_string());
                     return ready(false);
                 }
@@ -150,13 +219,6 @@ impl FileSystemSourceFunc {
                 }
             });
 
-        let state: &mut GlobalKeyedView<String, (String, FileReadState)> = ctx
-            .table_manager
-            .get_global_keyed_state("a")
-            .await
-            .expect("should have table");
-        self.file_states = state.get_all().clone().into_values().collect();
-
         while let Some(path) = file_paths.next().await {
             let obj_key = path
                 .map_err(|err| UserError::new("could not get next path", err.to_string()))?
@@ -176,25 +238,257 @@ impl FileSystemSourceFunc {
         Ok(SourceFinishType::Final)
     }
 
+    async fn run_delta(
+        &mut self,
+        ctx: &mut ArrowContext,
+        storage_provider: &StorageProvider,
+        path: &str,
+        settings: &DeltaSettings,
+    ) -> Result<SourceFinishType, UserError> {
+        // local providers are rooted at the table itself, while object store providers are
+        // rooted at the bucket
+        let table_key = match storage_provider.config() {
+            BackendConfig::Local(_) => None,
+            _ => Some(
+                StorageProvider::get_key(path)
+                    .map_err(|err| UserError::new("invalid Delta table path", err.to_string()))?,
+            ),
+        };
+        let table_uri = match &table_key {
+            Some(key) => format!("{}/{}", storage_provider.object_store_base_url(), key),
+            None => storage_provider.object_store_base_url().to_string(),
+        };
+
+        // when restoring, start from the earliest version any subtask had reached; files that
+        // were already read are skipped if they're covered by the position of the subtask that
+        // owned them, or otherwise based on their read state
+        let versions: &mut GlobalKeyedView<usize, DeltaReadState> = ctx
+            .table_manager
+            .get_global_keyed_state("v")
+            .await
+            .expect("should have table");
+        let restored = versions.get_all().clone();
+        let (version, snapshot_finished) = if restored.is_empty() {
+            (settings.version, false)
+        } else {
+            (
+                restored.values().map(|state| state.version).min(),
+                restored.values().all(|state| state.snapshot_finished),
+            )
+        };
+        let parallelism = ctx.task_info.parallelism;
+        let task_index = ctx.task_info.task_index;
+        self.file_states
+            .retain(|path, _| is_assigned(path, parallelism, task_index));
+
+        let table = delta::load_table(&table_uri, storage_provider, version)
+            .await
+            .map_err(|err| {
+                UserError::new(
+                    "could not load Delta table",
+                    format!("table:{}, err:{:?}", table_uri, err),
+                )
+            })?;
+        let mut version = table.version();
+        self.delta_state = Some(DeltaReadState {
+            version,
+            snapshot_finished,
+            parallelism,
+        });
+
+        if !snapshot_finished {
+            let files = table
+                .snapshot()
+                .and_then(|snapshot| snapshot.file_actions())
+                .map_err(|err| {
+                    UserError::new("could not read Delta table files", err.to_string())
+                })?;
+            info!(
+                "reading {} files from version {} of Delta table {}",
+                files.len(),
+                version,
+                table_uri
+            );
+            let mut read = vec![];
+            for add in files {
+                let obj_key = delta_file_key(&table_key, &add)?;
+                if let Some(finish_type) = self
+                    .read_delta_file(ctx, storage_provider, &obj_key, add, None, &restored)
+                    .await?
+                {
+                    return Ok(finish_type);
+                }
+                read.push(obj_key);
+            }
+            self.delta_state = Some(DeltaReadState {
+                version,
+                snapshot_finished: true,
+                parallelism,
+            });
+            self.forget_files(read);
+        }
+
+        if !settings.tail.unwrap_or(false) {
+            info!("Delta source finished reading version {}", version);
+            return Ok(SourceFinishType::Final);
+        }
+
+        let poll_interval = settings
+            .poll_interval_seconds
+            .map(|secs| Duration::from_secs(secs as u64))
+            .unwrap_or(DEFAULT_DELTA_POLL_INTERVAL);
+
+        loop {
+            let commit = table.peek_next_commit(version).await.map_err(|err| {
+                UserError::new(
+                    "could not read Delta transaction log",
+                    format!(
+                        "table:{}, version:{}, err:{:?}",
+                        table_uri,
+                        version + 1,
+                        err
+                    ),
+                )
+            })?;
+
+            match commit {
+                PeekCommit::New(next_version, actions) => {
+                    let mut files = vec![];
+                    for action in actions {
+                        match action {
+                            Action::Add(add) if add.data_change => files.push(add),
+                            Action::Remove(remove) if remove.data_change => {
+                                return Err(UserError::new(
+                                    "unsupported Delta commit",
+                                    format!(
+                                        "version {} of Delta table {} removes {}; only append-only tables can be tailed",
+                                        next_version, table_uri, remove.path
+                                    ),
+                                ));
+                            }
+                            // compactions and other rewrites don't change the table's data
+                            _ => {}
+                        }
+                    }
+                    let mut read = vec![];
+                    for add in files {
+                        let obj_key = delta_file_key(&table_key, &add)?;
+                        if let Some(finish_type) = self
+                            .read_delta_file(
+                                ctx,
+                                storage_provider,
+                                &obj_key,
+                                add,
+                                Some(next_version),
+                                &restored,
+                            )
+                            .await?
+                        {
+                            return Ok(finish_type);
+                        }
+                        read.push(obj_key);
+                    }
+                    version = next_version;
+                    self.delta_state = Some(DeltaReadState {
+                        version,
+                        snapshot_finished: true,
+                        parallelism,
+                    });
+                    self.forget_files(read);
+                }
+                PeekCommit::UpToDate => {
+                    select! {
+                        _ = tokio::time::sleep(poll_interval) => {},
+                        msg_res = ctx.control_rx.recv() => {
+                            if let Some(control_message) = msg_res {
+                                if let Some(finish_type) = self.process_control_message(ctx, control_message).await {
+                                    return Ok(finish_type);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn read_delta_file(
+        &mut self,
+        ctx: &mut ArrowContext,
+        storage_provider: &StorageProvider,
+        obj_key: &String,
+        add: Add,
+        commit: Option<i64>,
+        restored: &HashMap<usize, DeltaReadState>,
+    ) -> Result<Option<SourceFinishType>, UserError> {
+        // rows removed through deletion vectors are still in the data files, so reading these
+        // tables would emit deleted rows
+        if add.deletion_vector.is_some() {
+            return Err(UserError::new(
+                "unsupported Delta table",
+                format!(
+                    "file {} has a deletion vector; tables with deletion vectors are not supported",
+                    add.path
+                ),
+            ));
+        }
+
+        if !is_assigned(obj_key, ctx.task_info.parallelism, ctx.task_info.task_index) {
+            return Ok(None);
+        }
+        if DeltaReadState::covers(restored, obj_key, commit) {
+            return Ok(None);
+        }
+        if let Some(FileReadState::Finished) = self.file_states.get(obj_key) {
+            return Ok(None);
+        }
+
+        if !add.partition_values.is_empty() {
+            self.partition_values
+                .insert(obj_key.clone(), add.partition_values);
+        }
+        let result = self.read_file(ctx, storage_provider, obj_key).await;
+        if let Ok(None) = result {
+            self.partition_values.remove(obj_key);
+        }
+        result
+    }
+
+    /// Drops the read states of Delta files that are covered by the source's position in the log
+    fn forget_files(&mut self, paths: Vec<String>) {
+        for path in paths {
+            self.file_states.remove(&path);
+        }
+    }
+
+    async fn get_compressed_reader(
+        &self,
+        storage_provider: &StorageProvider,
+        path: String,
+    ) -> Result<Box<dyn AsyncRead + Unpin + Send>, UserError> {
+        let stream_reader = storage_provider
+            .get_as_stream(path.clone())
+            .await
+            .map_err(|err| {
+                UserError::new("could not read file", format!("path:{}, err:{}", path, err))
+            })?;
+
+        Ok(match self.get_compression_format() {
+            CompressionFormat::Zstd => Box::new(ZstdDecoder::new(BufReader::new(stream_reader))),
+            CompressionFormat::Gzip => Box::new(GzipDecoder::new(BufReader::new(stream_reader))),
+            CompressionFormat::None => Box::new(BufReader::new(stream_reader)),
+        })
+    }
+
     async fn get_newline_separated_stream(
         &mut self,
         storage_provider: &StorageProvider,
         path: String,
     ) -> Result<Box<dyn Stream<Item = Result<String, UserError>> + Unpin + Send>, UserError> {
         match &self.format {
-            Format::Json(_) => {
-                let stream_reader = storage_provider.get_as_stream(path).await.unwrap();
+            Format::Json(_) | Format::RawString(_) => {
+                let compression_reader = self.get_compressed_reader(storage_provider, path).await?;
 
-                let compression_reader: Box<dyn AsyncRead + Unpin + Send> =
-                    match self.get_compression_format() {
-                        CompressionFormat::Zstd => {
-                            Box::new(ZstdDecoder::new(BufReader::new(stream_reader)))
-                        }
-                        CompressionFormat::Gzip => {
-                            Box::new(GzipDecoder::new(BufReader::new(stream_reader)))
-                        }
-                        CompressionFormat::None => Box::new(BufReader::new(stream_reader)),
-                    };
                 // use line iterators
                 let lines = LinesStream::new(BufReader::new(compression_reader).lines());
                 Ok(Box::new(lines.map(|string_result| {
@@ -215,6 +509,7 @@ impl FileSystemSourceFunc {
         storage_provider: &StorageProvider,
         path: String,
         out_schema: SchemaRef,
+        partition_values: HashMap<String, Option<String>>,
     ) -> Result<Box<dyn Stream<Item = Result<RecordBatch, UserError>> + Unpin + Send>, UserError>
     {
         match &self.format {
@@ -246,7 +541,11 @@ impl FileSystemSourceFunc {
                 let result = Box::new(stream.map(move |res| match res {
                     Ok(record_batch) => {
                         // add timestamp
-                        let mut columns = record_batch.columns().to_vec();
+                        let mut columns = if partition_values.is_empty() {
+                            record_batch.columns().to_vec()
+                        } else {
+                            with_partition_columns(&record_batch, &out_schema, &partition_values)?
+                        };
                             let current_time = to_nanos(SystemTime::now());
                             let current_time_scalar =
                                 ScalarValue::TimestampNanosecond(Some(current_time as i64), None);
@@ -297,7 +596,7 @@ impl FileSystemSourceFunc {
         };
 
         match self.format {
-            Format::Json(_) => {
+            Format::Json(_) | Format::RawString(_) => {
                 let line_reader = self
                     .get_newline_separated_stream(storage_provider, obj_key.to_string())
                     .await?
@@ -305,13 +604,20 @@ impl FileSystemSourceFunc {
                 self.read_line_file(ctx, line_reader, obj_key, records_read)
                     .await
             }
-            Format::Avro(_) => todo!(),
+            Format::Avro(_) => {
+                self.read_avro_file(ctx, storage_provider, obj_key, records_read)
+                    .await
+            }
             Format::Parquet(_) => {
                 let record_batch_stream = self
                     .get_record_batch_stream(
                         storage_provider,
                         obj_key.to_string(),
                         ctx.out_schema.as_ref().unwrap().schema.clone(),
+                        self.partition_values
+                            .get(obj_key)
+                            .cloned()
+                            .unwrap_or_default(),
                     )
                     .await?
                     .skip(records_read);
@@ -319,7 +625,10 @@ impl FileSystemSourceFunc {
                 self.read_parquet_file(ctx, record_batch_stream, obj_key, records_read)
                     .await
             }
-            Format::RawString(_) => todo!(),
+            Format::Protobuf(_) => Err(UserError::new(
+                "unsupported format",
+                "protobuf is not supported for filesystem sources",
+            )),
         }
     }
 
@@ -358,6 +667,92 @@ impl FileSystemSourceFunc {
         }
     }
 
+    async fn read_avro_file(
+        &mut self,
+        ctx: &mut ArrowContext,
+        storage_provider: &StorageProvider,
+        obj_key: &String,
+        mut records_read: usize,
+    ) -> Result<Option<SourceFinishType>, UserError> {
+        let Format::Avro(format) = &self.format else {
+            unreachable!("code path only for Avro");
+        };
+        let reader_schema = format.reader_schema.clone();
+
+        // the avro reader works over a synchronous reader, so the file is decoded on a blocking
+        // thread that reads from the (decompressed) object stream as it goes; values are handed
+        // back over a bounded channel so that large files are never held in memory
+        let reader = SyncIoBridge::new(
+            self.get_compressed_reader(storage_provider, obj_key.to_string())
+                .await?,
+        );
+        let (tx, rx) = tokio::sync::mpsc::channel(AVRO_READ_BUFFER);
+        let path = obj_key.clone();
+        let decode = tokio::task::spawn_blocking(move || {
+            // values are resolved from the writer schema embedded in the file to the table's
+            // reader schema, if one was configured
+            let reader = match &reader_schema {
+                Some(schema) => apache_avro::Reader::with_schema(&schema.0, reader),
+                None => apache_avro::Reader::new(reader),
+            }
+            .map_err(|err| {
+                UserError::new("invalid Avro file", format!("path:{}, err:{:?}", path, err))
+            })?;
+
+            for value in reader.skip(records_read) {
+                if tx.blocking_send(value).is_err() {
+                    // the source has stopped reading this file
+                    break;
+                }
+            }
+            Ok::<_, UserError>(())
+        });
+
+        let mut values = ReceiverStream::new(rx);
+
+        loop {
+            select! {
+                value = values.next() => {
+                    match value {
+                        Some(value) => {
+                            ctx.deserialize_avro_value(
+                                value,
+                                SystemTime::now(),
+                                &SourcePosition::new(obj_key, records_read),
+                            ).await?;
+                            records_read += 1;
+                            if ctx.should_flush() {
+                                ctx.flush_buffer().await?;
+                            }
+                        }
+                        None => {
+                            // the channel closes once the file has been decoded, or decoding failed
+                            decode.await.map_err(|err| {
+                                UserError::new(
+                                    "could not read file",
+                                    format!("path:{}, err:{}", obj_key, err),
+                                )
+                            })??;
+                            info!("finished reading file {}", obj_key);
+                            ctx.flush_buffer().await?;
+                            self.file_states.insert(obj_key.to_string(), FileReadState::Finished);
+                            return Ok(None);
+                        }
+                    }
+                },
+                msg_res = ctx.control_rx.recv() => {
+                    if let Some(control_message) = msg_res {
+                        self.file_states.insert(obj_key.to_string(), FileReadState::RecordsRead(records_read));
+                        match self.process_control_message(ctx, control_message).await {
+                            Some(finish_type) => return Ok(Some(finish_type)),
+                            None => ()
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     async fn read_line_file(
         &mut self,
         ctx: &mut ArrowContext,
@@ -370,7 +765,11 @@ impl FileSystemSourceFunc {
                 line = line_reader.next() => {
                     match line.transpose()? {
                         Some(line) => {
-                            ctx.deserialize_slice(line.as_bytes(), SystemTime::now()).await?;
+                            ctx.deserialize_slice_at(
+                                line.as_bytes(),
+                                SystemTime::now(),
+                                &SourcePosition::new(obj_key, records_read),
+                            ).await?;
                             records_read += 1;
                             if ctx.should_flush() {
                                 ctx.flush_buffer().await?;
@@ -412,6 +811,14 @@ impl FileSystemSourceFunc {
                         .insert(file.clone(), (file.clone(), read_state.clone()))
                         .await;
                 }
+                if let Some(delta_state) = &self.delta_state {
+                    ctx.table_manager
+                        .get_global_keyed_state("v")
+                        .await
+                        .unwrap()
+                        .insert(ctx.task_info.task_index, delta_state.clone())
+                        .await;
+                }
                 // checkpoint our state
                 if self.start_checkpoint(c, ctx).await {
                     Some(SourceFinishType::Immediate)
@@ -437,3 +844,765 @@ impl FileSystemSourceFunc {
         }
     }
 }
+
+/// The object key of a file added to a Delta table, whose path in the log is relative to the
+/// table and url-encoded
+fn delta_file_key(table_key: &Option<String>, add: &Add) -> Result<String, UserError> {
+    let file_path = Path::from_url_path(&add.path).map_err(|err| {
+        UserError::new(
+            "invalid path in Delta log",
+            format!("path:{}, err:{}", add.path, err),
+        )
+    })?;
+    Ok(match table_key {
+        Some(key) => format!("{}/{}", key, file_path),
+        None => file_path.to_string(),
+    })
+}
+
+/// Builds the columns of `out_schema` (without the timestamp) by name from a Delta file's batch,
+/// filling in partition columns from the file's partition values.
+fn with_partition_columns(
+    batch: &RecordBatch,
+    out_schema: &Schema,
+    partition_values: &HashMap<String, Option<String>>,
+) -> Result<Vec<ArrayRef>, UserError> {
+    let fields = out_schema.fields();
+    fields[..fields.len() - 1]
+        .iter()
+        .map(|field| {
+            if let Some(column) = batch.column_by_name(field.name()) {
+                return Ok(column.clone());
+            }
+            let value = match partition_values.get(field.name()) {
+                Some(Some(value)) => ScalarValue::try_from_string(value.clone(), field.data_type()),
+                Some(None) => ScalarValue::try_from(field.data_type()),
+                None => {
+                    return Err(UserError::new(
+                        "data does not match schema",
+                        format!(
+                            "column {} is not in the parquet file or its partition values",
+                            field.name()
+                        ),
+                    ))
+                }
+            }
+            .and_then(|value| value.to_array_of_size(batch.num_rows()))
+            .map_err(|err| {
+                UserError::new(
+                    "invalid partition value",
+                    format!("column:{}, err:{}", field.name(), err),
+                )
+            })?;
+            Ok(value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Range;
+    use std::path::Path as FsPath;
+    use std::sync::Arc;
+
+    use apache_avro::types::Record;
+    use arrow::array::{Float64Array, Int64Array, StringArray};
+    use arrow::datatypes::{DataType, Field, TimeUnit};
+    use arroyo_operator::context::{batch_bounded, BatchReceiver};
+    use arroyo_rpc::df::ArroyoSchema;
+    use arroyo_rpc::formats::{AvroFormat, ParquetFormat, RawStringFormat, SerializableAvroSchema};
+    use arroyo_rpc::ControlResp;
+    use arroyo_types::{get_test_task_info, ArrowMessage};
+    use async_compression::tokio::write::GzipEncoder;
+    use parquet::arrow::ArrowWriter;
+    use serde_json::json;
+    use tokio::io::AsyncWriteExt;
+    use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+    use super::*;
+
+    const WRITER_SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "Event",
+        "fields": [
+            {"name": "id", "type": "long"},
+            {"name": "name", "type": "string"}
+        ]
+    }"#;
+
+    fn out_schema(fields: Vec<Field>) -> SchemaRef {
+        let mut fields = fields;
+        fields.push(Field::new(
+            "_timestamp",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        ));
+        Arc::new(Schema::new(fields))
+    }
+
+    fn avro_file(codec: apache_avro::Codec, count: i64) -> Vec<u8> {
+        let schema = apache_avro::Schema::parse_str(WRITER_SCHEMA).unwrap();
+        let mut writer = apache_avro::Writer::with_codec(&schema, vec![], codec);
+        for id in 0..count {
+            let mut record = Record::new(&schema).unwrap();
+            record.put("id", id);
+            record.put("name", format!("event-{}", id));
+            writer.append(record).unwrap();
+        }
+        writer.into_inner().unwrap()
+    }
+
+    async fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzipEncoder::new(vec![]);
+        encoder.write_all(data).await.unwrap();
+        encoder.shutdown().await.unwrap();
+        encoder.into_inner()
+    }
+
+    fn source(
+        dir: &FsPath,
+        format: Format,
+        compression_format: CompressionFormat,
+    ) -> FileSystemSourceFunc {
+        FileSystemSourceFunc {
+            table: TableType::Source {
+                path: format!("file://{}", dir.display()),
+                storage_options: Default::default(),
+                compression_format: Some(compression_format),
+                regex_pattern: None,
+                delta_settings: None,
+            },
+            format,
+            framing: None,
+            bad_data: None,
+            file_states: HashMap::new(),
+            partition_values: HashMap::new(),
+            delta_state: None,
+        }
+    }
+
+    struct TestContext {
+        ctx: ArrowContext,
+        data_rx: BatchReceiver,
+        // also held so that the source's control channels stay open
+        control_tx: Sender<ControlMessage>,
+        _command_rx: Receiver<ControlResp>,
+    }
+
+    async fn test_context(source: &FileSystemSourceFunc, schema: SchemaRef) -> TestContext {
+        let mut task_info = get_test_task_info();
+        task_info.job_id = format!("fs-source-{}", rand::random::<u64>());
+
+        let (control_tx, control_rx) = channel(128);
+        let (command_tx, command_rx) = channel(128);
+        let (data_tx, data_rx) = batch_bounded(1024);
+        let timestamp_index = schema.fields().len() - 1;
+
+        let ctx = ArrowContext::new(
+            task_info,
+            None,
+            control_rx,
+            command_tx,
+            1,
+            vec![],
+            Some(ArroyoSchema::new_unkeyed(schema, timestamp_index)),
+            None,
+            vec![vec![data_tx]],
+            source.tables(),
+        )
+        .await;
+
+        TestContext {
+            ctx,
+            data_rx,
+            control_tx,
+            _command_rx: command_rx,
+        }
+    }
+
+    async fn read_files(
+        dir: &FsPath,
+        format: Format,
+        compression_format: CompressionFormat,
+        schema: SchemaRef,
+    ) -> Vec<RecordBatch> {
+        read_all(&mut source(dir, format, compression_format), schema).await
+    }
+
+    async fn read_all(source: &mut FileSystemSourceFunc, schema: SchemaRef) -> Vec<RecordBatch> {
+        let mut test = test_context(source, schema).await;
+
+        assert!(matches!(
+            source.run_int(&mut test.ctx).await.unwrap(),
+            SourceFinishType::Final
+        ));
+        drop(test.ctx);
+
+        let mut batches = vec![];
+        while let Some(message) = test.data_rx.recv().await {
+            if let ArrowMessage::Data(batch) = message {
+                batches.push(batch);
+            }
+        }
+        batches
+    }
+
+    fn column<'a, T: 'static>(batches: &'a [RecordBatch], name: &str) -> Vec<&'a T> {
+        batches
+            .iter()
+            .map(|batch| {
+                batch
+                    .column_by_name(name)
+                    .unwrap()
+                    .as_any()
+                    .downcast_ref::<T>()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn ids(batches: &[RecordBatch]) -> Vec<i64> {
+        let mut ids: Vec<_> = column::<Int64Array>(batches, "id")
+            .into_iter()
+            .flat_map(|array| array.values().iter().copied())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_read_avro_with_writer_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        tokio::fs::write(
+            dir.path().join("events.avro"),
+            avro_file(apache_avro::Codec::Null, 5000),
+        )
+        .await
+        .unwrap();
+
+        // without a reader schema, values are decoded with the schema embedded in the file
+        let batches = read_files(
+            dir.path(),
+            Format::Avro(AvroFormat::new(false, false, false)),
+            CompressionFormat::None,
+            out_schema(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("name", DataType::Utf8, false),
+            ]),
+        )
+        .await;
+
+        assert_eq!(ids(&batches), (0..5000).collect::<Vec<_>>());
+        let names: Vec<_> = column::<StringArray>(&batches, "name")
+            .into_iter()
+            .flat_map(|array| array.iter().map(|name| name.unwrap().to_string()))
+            .collect();
+        assert!(names.contains(&"event-4999".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_read_avro_resolves_to_reader_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        tokio::fs::write(
+            dir.path().join("events.avro"),
+            avro_file(apache_avro::Codec::Null, 10),
+        )
+        .await
+        .unwrap();
+
+        // the reader schema drops `name` and adds `score` with a default
+        let reader_schema = apache_avro::Schema::parse_str(
+            r#"{
+                "type": "record",
+                "name": "Event",
+                "fields": [
+                    {"name": "id", "type": "long"},
+                    {"name": "score", "type": "double", "default": 1.5}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut format = AvroFormat::new(false, false, false);
+        format.reader_schema = Some(SerializableAvroSchema(reader_schema));
+
+        let batches = read_files(
+            dir.path(),
+            Format::Avro(format),
+            CompressionFormat::None,
+            out_schema(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("score", DataType::Float64, false),
+            ]),
+        )
+        .await;
+
+        assert_eq!(ids(&batches), (0..10).collect::<Vec<_>>());
+        assert!(column::<Float64Array>(&batches, "score")
+            .into_iter()
+            .flat_map(|array| array.values().iter())
+            .all(|score| *score == 1.5));
+    }
+
+    #[tokio::test]
+    async fn test_read_compressed_avro() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // blocks compressed by the avro codec, within a gzipped file
+        let data = gzip(&avro_file(apache_avro::Codec::Deflate, 1000)).await;
+        tokio::fs::write(dir.path().join("events.avro.gz"), data)
+            .await
+            .unwrap();
+
+        let batches = read_files(
+            dir.path(),
+            Format::Avro(AvroFormat::new(false, false, false)),
+            CompressionFormat::Gzip,
+            out_schema(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("name", DataType::Utf8, false),
+            ]),
+        )
+        .await;
+
+        assert_eq!(ids(&batches), (0..1000).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_read_invalid_avro_file() {
+        let dir = tempfile::tempdir().unwrap();
+        tokio::fs::write(dir.path().join("events.avro"), b"not an avro file")
+            .await
+            .unwrap();
+
+        let mut source = source(
+            dir.path(),
+            Format::Avro(AvroFormat::new(false, false, false)),
+            CompressionFormat::None,
+        );
+        let mut test = test_context(
+            &source,
+            out_schema(vec![Field::new("id", DataType::Int64, false)]),
+        )
+        .await;
+
+        let Err(err) = source.run_int(&mut test.ctx).await else {
+            panic!("reading an invalid file should fail");
+        };
+        assert_eq!(err.name, "invalid Avro file");
+    }
+
+    #[tokio::test]
+    async fn test_read_raw_string() {
+        let dir = tempfile::tempdir().unwrap();
+        let lines: Vec<_> = (0..100).map(|i| format!("line {}", i)).collect();
+        tokio::fs::write(
+            dir.path().join("lines.txt.gz"),
+            gzip(lines.join("\n").as_bytes()).await,
+        )
+        .await
+        .unwrap();
+
+        let batches = read_files(
+            dir.path(),
+            Format::RawString(RawStringFormat {}),
+            CompressionFormat::Gzip,
+            out_schema(vec![Field::new("value", DataType::Utf8, false)]),
+        )
+        .await;
+
+        let mut values: Vec<_> = column::<StringArray>(&batches, "value")
+            .into_iter()
+            .flat_map(|array| array.iter().map(|value| value.unwrap().to_string()))
+            .collect();
+        values.sort();
+        let mut expected = lines;
+        expected.sort();
+        assert_eq!(values, expected);
+    }
+
+    /// Writes a commit to the log of a Delta table. The commit is written elsewhere and renamed
+    /// into the log, so that a tailing source never sees a partial commit.
+    fn write_delta_commit(dir: &FsPath, version: i64, actions: Vec<serde_json::Value>) {
+        let log = dir.join("_delta_log");
+        std::fs::create_dir_all(&log).unwrap();
+
+        let commit: Vec<_> = actions.iter().map(|action| action.to_string()).collect();
+        let tmp = dir.join(format!("commit-{}.tmp", version));
+        std::fs::write(&tmp, commit.join("\n")).unwrap();
+        std::fs::rename(tmp, log.join(format!("{:020}.json", version))).unwrap();
+    }
+
+    fn create_delta_table(dir: &FsPath, protocol: serde_json::Value) {
+        let schema = json!({
+            "type": "struct",
+            "fields": [
+                {"name": "id", "type": "long", "nullable": false, "metadata": {}},
+                {"name": "region", "type": "string", "nullable": true, "metadata": {}}
+            ]
+        });
+
+        write_delta_commit(
+            dir,
+            0,
+            vec![
+                json!({ "protocol": protocol }),
+                json!({
+                    "metaData": {
+                        "id": "5e8e3a6b-5d0c-4c8e-9e1f-0d2b9a7c4f10",
+                        "format": {"provider": "parquet", "options": {}},
+                        "schemaString": schema.to_string(),
+                        "partitionColumns": ["region"],
+                        "configuration": {},
+                        "createdTime": 0
+                    }
+                }),
+            ],
+        );
+    }
+
+    /// Writes a data file holding `ids` to the `region` partition, returning its add action
+    fn delta_file(dir: &FsPath, region: &str, ids: Range<i64>) -> serde_json::Value {
+        let path = format!("region={}/part-{}.parquet", region, ids.start);
+
+        // partition columns aren't stored in the files themselves
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)])),
+            vec![Arc::new(Int64Array::from_iter_values(ids))],
+        )
+        .unwrap();
+        let mut data = vec![];
+        let mut writer = ArrowWriter::try_new(&mut data, batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        std::fs::create_dir_all(dir.join(format!("region={}", region))).unwrap();
+        std::fs::write(dir.join(&path), &data).unwrap();
+
+        json!({
+            "add": {
+                "path": path,
+                "partitionValues": {"region": region},
+                "size": data.len(),
+                "modificationTime": 0,
+                "dataChange": true
+            }
+        })
+    }
+
+    fn append_only_protocol() -> serde_json::Value {
+        json!({"minReaderVersion": 1, "minWriterVersion": 2})
+    }
+
+    fn delta_source(dir: &FsPath, version: Option<i64>, tail: bool) -> FileSystemSourceFunc {
+        let mut source = source(
+            dir,
+            Format::Parquet(ParquetFormat {
+                compression: Default::default(),
+            }),
+            CompressionFormat::None,
+        );
+        let TableType::Source { delta_settings, .. } = &mut source.table else {
+            unreachable!();
+        };
+        *delta_settings = Some(DeltaSettings {
+            version,
+            tail: Some(tail),
+            poll_interval_seconds: Some(1),
+        });
+        source
+    }
+
+    fn delta_schema() -> SchemaRef {
+        out_schema(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("region", DataType::Utf8, true),
+        ])
+    }
+
+    fn delta_rows(batches: &[RecordBatch]) -> Vec<(i64, String)> {
+        let mut rows: Vec<_> = column::<Int64Array>(batches, "id")
+            .into_iter()
+            .zip(column::<StringArray>(batches, "region"))
+            .flat_map(|(ids, regions)| {
+                ids.values()
+                    .iter()
+                    .copied()
+                    .zip(regions.iter().map(|region| region.unwrap().to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        rows.sort();
+        rows
+    }
+
+    fn expected_rows(files: &[(&str, Range<i64>)]) -> Vec<(i64, String)> {
+        let mut rows: Vec<_> = files
+            .iter()
+            .flat_map(|(region, ids)| ids.clone().map(|id| (id, region.to_string())))
+            .collect();
+        rows.sort();
+        rows
+    }
+
+    async fn recv_rows(data_rx: &mut BatchReceiver, count: usize) -> Vec<(i64, String)> {
+        let mut batches = vec![];
+        while batches.iter().map(RecordBatch::num_rows).sum::<usize>() < count {
+            let message = tokio::time::timeout(Duration::from_secs(30), data_rx.recv())
+                .await
+                .expect("timed out waiting for data")
+                .expect("source stopped before sending all data");
+            if let ArrowMessage::Data(batch) = message {
+                batches.push(batch);
+            }
+        }
+        delta_rows(&batches)
+    }
+
+    /// Starts a tailing source in the background, returning a handle that yields the source once
+    /// it has been stopped
+    fn spawn_source(
+        mut source: FileSystemSourceFunc,
+        mut ctx: ArrowContext,
+    ) -> tokio::task::JoinHandle<FileSystemSourceFunc> {
+        tokio::spawn(async move {
+            let finish_type = source.run_int(&mut ctx).await.unwrap();
+            assert!(matches!(finish_type, SourceFinishType::Immediate));
+            source
+        })
+    }
+
+    async fn stop_source(
+        control_tx: &Sender<ControlMessage>,
+        handle: tokio::task::JoinHandle<FileSystemSourceFunc>,
+    ) -> FileSystemSourceFunc {
+        // let the source finish up the commit it was reading before stopping it
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        control_tx
+            .send(ControlMessage::Stop {
+                mode: StopMode::Immediate,
+            })
+            .await
+            .unwrap();
+        handle.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_read_delta_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        create_delta_table(dir.path(), append_only_protocol());
+        write_delta_commit(
+            dir.path(),
+            1,
+            vec![
+                delta_file(dir.path(), "us", 0..10),
+                delta_file(dir.path(), "eu", 10..15),
+            ],
+        );
+        write_delta_commit(dir.path(), 2, vec![delta_file(dir.path(), "us", 15..20)]);
+
+        // partition columns are filled in from the partition values in the log
+        let mut source = delta_source(dir.path(), None, false);
+        let batches = read_all(&mut source, delta_schema()).await;
+        assert_eq!(
+            delta_rows(&batches),
+            expected_rows(&[("us", 0..10), ("eu", 10..15), ("us", 15..20)])
+        );
+
+        // files covered by the source's position are no longer tracked individually
+        assert_eq!(
+            source.delta_state,
+            Some(DeltaReadState {
+                version: 2,
+                snapshot_finished: true,
+                parallelism: 1,
+            })
+        );
+        assert!(source.file_states.is_empty());
+        assert!(source.partition_values.is_empty());
+
+        let mut source = delta_source(dir.path(), Some(1), false);
+        let batches = read_all(&mut source, delta_schema()).await;
+        assert_eq!(
+            delta_rows(&batches),
+            expected_rows(&[("us", 0..10), ("eu", 10..15)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tail_delta_table() {
+        let dir = tempfile::tempdir().unwrap();
+        create_delta_table(dir.path(), append_only_protocol());
+        write_delta_commit(dir.path(), 1, vec![delta_file(dir.path(), "us", 0..10)]);
+
+        let source = delta_source(dir.path(), None, true);
+        let mut test = test_context(&source, delta_schema()).await;
+        let handle = spawn_source(source, test.ctx);
+
+        assert_eq!(
+            recv_rows(&mut test.data_rx, 10).await,
+            expected_rows(&[("us", 0..10)])
+        );
+
+        write_delta_commit(
+            dir.path(),
+            2,
+            vec![
+                delta_file(dir.path(), "eu", 10..20),
+                delta_file(dir.path(), "us", 20..25),
+            ],
+        );
+        assert_eq!(
+            recv_rows(&mut test.data_rx, 15).await,
+            expected_rows(&[("eu", 10..20), ("us", 20..25)])
+        );
+
+        let source = stop_source(&test.control_tx, handle).await;
+        assert_eq!(
+            source.delta_state,
+            Some(DeltaReadState {
+                version: 2,
+                snapshot_finished: true,
+                parallelism: 1,
+            })
+        );
+        assert!(source.file_states.is_empty());
+    }
+
+    async fn restore_delta_states(ctx: &mut ArrowContext, states: Vec<DeltaReadState>) {
+        let versions: &mut GlobalKeyedView<usize, DeltaReadState> =
+            ctx.table_manager.get_global_keyed_state("v").await.unwrap();
+        for (task_index, state) in states.into_iter().enumerate() {
+            versions.insert(task_index, state).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restore_delta_position() {
+        let dir = tempfile::tempdir().unwrap();
+        create_delta_table(dir.path(), append_only_protocol());
+        write_delta_commit(dir.path(), 1, vec![delta_file(dir.path(), "us", 0..10)]);
+        write_delta_commit(dir.path(), 2, vec![delta_file(dir.path(), "eu", 10..20)]);
+        write_delta_commit(dir.path(), 3, vec![delta_file(dir.path(), "us", 20..30)]);
+
+        // the source had read the snapshot and tailed up to version 2
+        let source = delta_source(dir.path(), None, true);
+        let mut test = test_context(&source, delta_schema()).await;
+        restore_delta_states(
+            &mut test.ctx,
+            vec![DeltaReadState {
+                version: 2,
+                snapshot_finished: true,
+                parallelism: 1,
+            }],
+        )
+        .await;
+        let handle = spawn_source(source, test.ctx);
+
+        assert_eq!(
+            recv_rows(&mut test.data_rx, 10).await,
+            expected_rows(&[("us", 20..30)])
+        );
+
+        stop_source(&test.control_tx, handle).await;
+        while let Some(message) = test.data_rx.recv().await {
+            assert!(
+                !matches!(message, ArrowMessage::Data(_)),
+                "only files after the restored version should be read"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restore_delta_position_after_rescale() {
+        let dir = tempfile::tempdir().unwrap();
+        create_delta_table(dir.path(), append_only_protocol());
+        let files = [("us", 0..10), ("eu", 10..20), ("us", 20..30)];
+        for (version, (region, ids)) in files.iter().enumerate() {
+            write_delta_commit(
+                dir.path(),
+                version as i64 + 1,
+                vec![delta_file(dir.path(), region, ids.clone())],
+            );
+        }
+
+        // the source ran with two subtasks: the first had read the snapshot at version 1 and
+        // tailed up to version 2, while the second was still reading the snapshot
+        let states = vec![
+            DeltaReadState {
+                version: 2,
+                snapshot_finished: true,
+                parallelism: 2,
+            },
+            DeltaReadState {
+                version: 1,
+                snapshot_finished: false,
+                parallelism: 2,
+            },
+        ];
+
+        // so on restore, files owned by the first subtask are only read if they came after
+        // version 2, and every file owned by the second subtask is read
+        let expected: Vec<_> = files
+            .iter()
+            .enumerate()
+            .filter(|(version, (region, ids))| {
+                let path = format!("region={}/part-{}.parquet", region, ids.start);
+                assigned_task(&path, 2) == 1 || *version as i64 + 1 > 2
+            })
+            .map(|(_, file)| file.clone())
+            .collect();
+        let count = expected.iter().map(|(_, ids)| ids.clone().count()).sum();
+
+        // the restored source runs with a single subtask
+        let source = delta_source(dir.path(), None, true);
+        let mut test = test_context(&source, delta_schema()).await;
+        restore_delta_states(&mut test.ctx, states).await;
+        let handle = spawn_source(source, test.ctx);
+
+        assert_eq!(
+            recv_rows(&mut test.data_rx, count).await,
+            expected_rows(&expected)
+        );
+
+        let source = stop_source(&test.control_tx, handle).await;
+        assert_eq!(
+            source.delta_state,
+            Some(DeltaReadState {
+                version: 3,
+                snapshot_finished: true,
+                parallelism: 1,
+            })
+        );
+        assert!(source.file_states.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delta_deletion_vectors_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        create_delta_table(
+            dir.path(),
+            json!({
+                "minReaderVersion": 3,
+                "minWriterVersion": 7,
+                "readerFeatures": ["deletionVectors"],
+                "writerFeatures": ["deletionVectors"]
+            }),
+        );
+        let mut add = delta_file(dir.path(), "us", 0..10);
+        add["add"]["deletionVector"] = json!({
+            "storageType": "i",
+            "pathOrInlineDv": "wi5b=000010000siXQKl0rr91000f55c8Xg0@@D72lkbi5=-{L",
+            "sizeInBytes": 40,
+            "cardinality": 6
+        });
+        write_delta_commit(dir.path(), 1, vec![add]);
+
+        let mut source = delta_source(dir.path(), None, false);
+        let mut test = test_context(&source, delta_schema()).await;
+
+        let Err(err) = source.run_int(&mut test.ctx).await else {
+            panic!("reading a table with deletion vectors should fail");
+        };
+        assert_eq!(err.name, "unsupported Delta table");
+    }
+}
@@ -151,6 +151,7 @@ impl Connector for FluvioConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
@@ -194,7 +195,7 @@ impl Connector for FluvioConnector {
                     config
                         .format
                         .ok_or_else(|| anyhow!("format required for fluvio sink"))?,
-                ),
+                )?,
             }))),
         }
     }
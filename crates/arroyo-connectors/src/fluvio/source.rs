@@ -1,5 +1,6 @@
 use anyhow::anyhow;
 use arroyo_operator::context::ArrowContext;
+use arroyo_operator::dead_letter::SourcePosition;
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
 use arroyo_rpc::formats::{BadData, Format, Framing};
@@ -46,14 +47,6 @@ impl SourceOperator for FluvioSourceFunc {
         global_table_config("f", "fluvio source state")
     }
 
-    async fn on_start(&mut self, ctx: &mut ArrowContext) {
-        ctx.initialize_deserializer(
-            self.format.clone(),
-            self.framing.clone(),
-            self.bad_data.clone(),
-        );
-    }
-
     async fn run(&mut self, ctx: &mut ArrowContext) -> SourceFinishType {
         match self.run_int(ctx).await {
             Ok(r) => r,
@@ -141,6 +134,12 @@ impl FluvioSourceFunc {
     }
 
     async fn run_int(&mut self, ctx: &mut ArrowContext) -> Result<SourceFinishType, UserError> {
+        ctx.initialize_deserializer(
+            self.format.clone(),
+            self.framing.clone(),
+            self.bad_data.clone(),
+        )?;
+
         let mut streams = self
             .get_consumer(ctx)
             .await
@@ -162,7 +161,11 @@ impl FluvioSourceFunc {
                     match message {
                         Some((_, Ok(msg))) => {
                             let timestamp = from_millis(msg.timestamp().max(0) as u64);
-                            ctx.deserialize_slice(msg.value(), timestamp).await?;
+                            ctx.deserialize_slice_at(
+                                msg.value(),
+                                timestamp,
+                                &SourcePosition::new(msg.partition(), msg.offset()),
+                            ).await?;
 
                             if ctx.should_flush() {
                                 ctx.flush_buffer().await?;
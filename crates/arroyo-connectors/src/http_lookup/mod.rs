@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use arroyo_operator::connector::{Connection, Connector, LookupConnector};
+use arroyo_operator::operator::OperatorNode;
+use arroyo_rpc::api_types::connections::{
+    ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
+};
+use arroyo_rpc::{var_str::VarStr, OperatorConfig};
+use arroyo_types::string_to_map;
+use async_trait::async_trait;
+use futures::{stream, StreamExt, TryStreamExt};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+use typify::import_types;
+
+use crate::{construct_http_client, pull_opt, pull_option_to_i64, EmptyConfig};
+
+const TABLE_SCHEMA: &str = include_str!("./table.json");
+const ICON: &str = include_str!("../polling_http/http.svg");
+const KEY_PLACEHOLDER: &str = "{key}";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_CONCURRENCY: usize = 16;
+
+import_types!(
+    schema = "src/http_lookup/table.json",
+    convert = { {type = "string", format = "var-str"} = VarStr }
+);
+
+pub struct HttpLookupConnector {}
+
+impl HttpLookupConnector {
+    fn client(table: &HttpLookupTable) -> anyhow::Result<Client> {
+        let headers = table
+            .headers
+            .as_ref()
+            .map(|s| s.sub_env_vars())
+            .transpose()?;
+
+        construct_http_client(&table.endpoint, headers)
+    }
+}
+
+impl Connector for HttpLookupConnector {
+    type ProfileT = EmptyConfig;
+    type TableT = HttpLookupTable;
+
+    fn name(&self) -> &'static str {
+        "http_lookup"
+    }
+
+    fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector {
+        arroyo_rpc::api_types::connections::Connector {
+            id: "http_lookup".to_string(),
+            name: "HTTP Lookup".to_string(),
+            icon: ICON.to_string(),
+            description: "Enrich events by looking up values from an HTTP server".to_string(),
+            enabled: true,
+            source: false,
+            sink: false,
+            testing: false,
+            hidden: false,
+            custom_schemas: true,
+            connection_config: None,
+            table_config: TABLE_SCHEMA.to_owned(),
+        }
+    }
+
+    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
+        ConnectionType::Lookup
+    }
+
+    fn test(
+        &self,
+        _: &str,
+        _: Self::ProfileT,
+        table: Self::TableT,
+        _: Option<&ConnectionSchema>,
+        tx: Sender<TestSourceMessage>,
+    ) {
+        tokio::task::spawn(async move {
+            let message = match Self::client(&table) {
+                Ok(_) => TestSourceMessage::done("Successfully validated lookup table"),
+                Err(e) => TestSourceMessage::fail(format!("{}", e.root_cause())),
+            };
+
+            tx.send(message).await.unwrap();
+        });
+    }
+
+    fn from_options(
+        &self,
+        name: &str,
+        options: &mut HashMap<String, String>,
+        schema: Option<&ConnectionSchema>,
+        _profile: Option<&ConnectionProfile>,
+    ) -> anyhow::Result<Connection> {
+        let endpoint = pull_opt("endpoint", options)?;
+        let headers = options.remove("headers");
+        let timeout_ms = pull_option_to_i64("timeout_ms", options)?;
+        let max_concurrency = pull_option_to_i64("max_concurrency", options)?;
+
+        self.from_config(
+            None,
+            name,
+            EmptyConfig {},
+            HttpLookupTable {
+                endpoint,
+                headers: headers.map(VarStr::new),
+                timeout_ms,
+                max_concurrency,
+            },
+            schema,
+        )
+    }
+
+    fn from_config(
+        &self,
+        id: Option<i64>,
+        name: &str,
+        config: Self::ProfileT,
+        table: Self::TableT,
+        schema: Option<&ConnectionSchema>,
+    ) -> anyhow::Result<Connection> {
+        if !table.endpoint.contains(KEY_PLACEHOLDER) {
+            bail!(
+                "endpoint for HTTP lookup table must contain the placeholder {}",
+                KEY_PLACEHOLDER
+            );
+        }
+
+        if let Some(headers) = &table.headers {
+            string_to_map(&headers.sub_env_vars()?, ':').ok_or_else(|| {
+                anyhow!(
+                    "Invalid format for headers; should be a \
+                    comma-separated list of colon-separated key value pairs"
+                )
+            })?;
+        }
+
+        if matches!(table.timeout_ms, Some(t) if t <= 0) {
+            bail!("timeout_ms must be greater than 0");
+        }
+
+        if matches!(table.max_concurrency, Some(c) if c <= 0) {
+            bail!("max_concurrency must be greater than 0");
+        }
+
+        let description = format!("HttpLookup<{}>", table.endpoint);
+
+        let schema = schema
+            .map(|s| s.to_owned())
+            .ok_or_else(|| anyhow!("no schema defined for HTTP lookup table"))?;
+
+        let format = schema
+            .format
+            .as_ref()
+            .map(|t| t.to_owned())
+            .ok_or_else(|| anyhow!("'format' must be set for HTTP lookup table"))?;
+
+        let config = OperatorConfig {
+            connection: serde_json::to_value(config).unwrap(),
+            table: serde_json::to_value(table).unwrap(),
+            rate_limit: None,
+            quota: None,
+            format: Some(format),
+            bad_data: schema.bad_data.clone(),
+            framing: schema.framing.clone(),
+        };
+
+        Ok(Connection {
+            id,
+            connector: self.name(),
+            name: name.to_string(),
+            connection_type: ConnectionType::Lookup,
+            schema,
+            config: serde_json::to_string(&config).unwrap(),
+            description,
+        })
+    }
+
+    fn make_operator(
+        &self,
+        _: Self::ProfileT,
+        _: Self::TableT,
+        _: OperatorConfig,
+    ) -> anyhow::Result<OperatorNode> {
+        bail!("HTTP lookup tables can only be used in lookup joins");
+    }
+
+    fn make_lookup(
+        &self,
+        _: Self::ProfileT,
+        table: Self::TableT,
+        _: OperatorConfig,
+    ) -> anyhow::Result<Box<dyn LookupConnector>> {
+        Ok(Box::new(HttpLookup {
+            client: Self::client(&table)?,
+            timeout: table
+                .timeout_ms
+                .map(|t| Duration::from_millis(t as u64))
+                .unwrap_or(DEFAULT_TIMEOUT),
+            max_concurrency: table
+                .max_concurrency
+                .map(|c| c as usize)
+                .unwrap_or(DEFAULT_MAX_CONCURRENCY),
+            endpoint: table.endpoint,
+        }))
+    }
+}
+
+pub struct HttpLookup {
+    client: Client,
+    endpoint: String,
+    timeout: Duration,
+    max_concurrency: usize,
+}
+
+impl HttpLookup {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let url = self.endpoint.replace(
+            KEY_PLACEHOLDER,
+            &utf8_percent_encode(key, NON_ALPHANUMERIC).to_string(),
+        );
+
+        let resp = self
+            .client
+            .get(&url)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(|e| anyhow!("request to {} failed: {:?}", url, e))?;
+
+        match resp.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(
+                resp.bytes()
+                    .await
+                    .map_err(|e| anyhow!("failed to read response from {}: {:?}", url, e))?
+                    .to_vec(),
+            )),
+            status => bail!("request to {} failed with status {}", url, status),
+        }
+    }
+}
+
+#[async_trait]
+impl LookupConnector for HttpLookup {
+    fn name(&self) -> String {
+        "HttpLookup".to_string()
+    }
+
+    async fn lookup(&mut self, keys: &[String]) -> anyhow::Result<Vec<Option<Vec<u8>>>> {
+        let this = &*self;
+        stream::iter(keys)
+            .map(|k| this.get(k))
+            .buffered(this.max_concurrency)
+            .try_collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use axum::http::Uri;
+    use axum::Router;
+
+    use super::*;
+
+    // serves the request path as the response, except for keys named "missing"
+    fn serve(in_flight: Arc<AtomicUsize>, max_in_flight: Arc<AtomicUsize>) -> SocketAddr {
+        let app = Router::new().fallback(move |uri: Uri| async move {
+            let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            if uri.path().ends_with("/missing") {
+                (axum::http::StatusCode::NOT_FOUND, String::new())
+            } else {
+                (axum::http::StatusCode::OK, uri.path().to_string())
+            }
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+        addr
+    }
+
+    fn lookup(addr: SocketAddr, max_concurrency: usize) -> HttpLookup {
+        HttpLookup {
+            client: Client::new(),
+            endpoint: format!("http://{}/users/{}", addr, KEY_PLACEHOLDER),
+            timeout: DEFAULT_TIMEOUT,
+            max_concurrency,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lookup() {
+        let addr = serve(Default::default(), Default::default());
+        let mut lookup = lookup(addr, 4);
+
+        let keys = ["alice", "missing", "a b/c?d", "ü"].map(|k| k.to_string());
+        let values = lookup.lookup(&keys).await.unwrap();
+
+        assert_eq!(
+            values,
+            vec![
+                Some(b"/users/alice".to_vec()),
+                None,
+                Some(b"/users/a%20b%2Fc%3Fd".to_vec()),
+                Some(b"/users/%C3%BC".to_vec()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_error() {
+        let app = Router::new().fallback(|| async { axum::http::StatusCode::BAD_GATEWAY });
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        let err = lookup(addr, 4)
+            .lookup(&["alice".to_string()])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("502"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_concurrency() {
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let addr = serve(Default::default(), max_in_flight.clone());
+        let mut lookup = lookup(addr, 3);
+
+        let keys: Vec<_> = (0..50).map(|i| i.to_string()).collect();
+        let values = lookup.lookup(&keys).await.unwrap();
+
+        assert_eq!(values.len(), 50);
+        assert_eq!(values[7], Some(b"/users/7".to_vec()));
+        assert!(max_in_flight.load(Ordering::SeqCst) <= 3);
+    }
+}
@@ -163,6 +163,7 @@ impl Connector for ImpulseConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: None,
             bad_data: None,
             framing: None,
@@ -183,7 +184,7 @@ impl Connector for ImpulseConnector {
         &self,
         _: Self::ProfileT,
         table: Self::TableT,
-        _: OperatorConfig,
+        config: OperatorConfig,
     ) -> anyhow::Result<OperatorNode> {
         Ok(OperatorNode::from_source(Box::new(ImpulseSourceFunc {
             interval: table
@@ -198,6 +199,7 @@ impl Connector for ImpulseConnector {
                 counter: 0,
                 start_time: SystemTime::now(),
             },
+            quota: config.quota,
         })))
     }
 }
@@ -5,7 +5,7 @@ use std::sync::Arc;
 use arrow::array::builder::{TimestampNanosecondBuilder, UInt64Builder};
 use arrow::array::RecordBatch;
 use arroyo_rpc::grpc::{StopMode, TableConfig};
-use arroyo_rpc::ControlMessage;
+use arroyo_rpc::{ControlMessage, RateLimit};
 use async_trait::async_trait;
 use bincode::{Decode, Encode};
 use datafusion::common::ScalarValue;
@@ -15,6 +15,8 @@ use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
 use arroyo_types::{from_millis, print_time, to_millis, to_nanos};
+
+use crate::source_rate_limiter;
 use tracing::{debug, info};
 
 #[derive(Encode, Decode, Debug, Copy, Clone, Eq, PartialEq)]
@@ -36,6 +38,7 @@ pub struct ImpulseSourceFunc {
     pub spec: ImpulseSpec,
     pub limit: usize,
     pub state: ImpulseSourceState,
+    pub quota: Option<RateLimit>,
 }
 
 impl ImpulseSourceFunc {
@@ -71,6 +74,7 @@ impl ImpulseSourceFunc {
                 counter: 0,
                 start_time,
             },
+            quota: None,
         }
     }
 
@@ -119,6 +123,7 @@ impl ImpulseSourceFunc {
         let schema = ctx.out_schema.as_ref().unwrap().schema.clone();
 
         let batch_size = self.batch_size(ctx);
+        let rate_limiter = source_rate_limiter(&self.quota, ctx.task_info.parallelism);
 
         let mut items = 0;
         let mut counter_builder = UInt64Builder::with_capacity(batch_size);
@@ -155,6 +160,10 @@ impl ImpulseSourceFunc {
 
             self.state.counter += 1;
 
+            if let Some(rate_limiter) = &rate_limiter {
+                rate_limiter.until_ready().await;
+            }
+
             match ctx.control_rx.try_recv() {
                 Ok(ControlMessage::Checkpoint(c)) => {
                     // checkpoint our state
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use arroyo_operator::dead_letter::DeadLetterSink;
+use arroyo_rpc::var_str::VarStr;
+use arroyo_types::TaskInfo;
+use async_trait::async_trait;
+use futures::future::try_join_all;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::ClientConfig;
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Writes dead letters to a Kafka topic, keyed by the subtask they came from
+pub struct KafkaDeadLetterSink {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaDeadLetterSink {
+    pub fn new(
+        bootstrap_servers: &str,
+        topic: &str,
+        client_configs: &BTreeMap<String, String>,
+    ) -> Result<Self> {
+        let producer: FutureProducer = producer_config(bootstrap_servers, client_configs)?
+            .create()
+            .map_err(|e| anyhow!("failed to create dead letter producer: {:?}", e))?;
+
+        Ok(Self {
+            producer,
+            topic: topic.to_string(),
+        })
+    }
+}
+
+fn producer_config(
+    bootstrap_servers: &str,
+    client_configs: &BTreeMap<String, String>,
+) -> Result<ClientConfig> {
+    let mut config = ClientConfig::new();
+    config.set("bootstrap.servers", bootstrap_servers);
+
+    for (key, value) in client_configs {
+        let value = VarStr::new(value.clone())
+            .sub_env_vars()
+            .with_context(|| format!("invalid value for dead letter client config '{}'", key))?;
+        config.set(key, value);
+    }
+
+    Ok(config)
+}
+
+#[async_trait]
+impl DeadLetterSink for KafkaDeadLetterSink {
+    async fn write(&mut self, task_info: &TaskInfo, letters: Vec<Vec<u8>>) -> Result<()> {
+        let key = format!("{}-{}", task_info.operator_id, task_info.task_index);
+
+        // all of the letters are enqueued before waiting for any of them to be delivered
+        try_join_all(letters.iter().map(|letter| {
+            self.producer.send(
+                FutureRecord::to(&self.topic).key(&key).payload(letter),
+                DELIVERY_TIMEOUT,
+            )
+        }))
+        .await
+        .map_err(|(e, _)| anyhow!("failed to write dead letters: {:?}", e))?;
+
+        Ok(())
+    }
+
+    async fn flush(&mut self, _: &TaskInfo) -> Result<()> {
+        // writes wait for delivery, so this only has work to do if one was interrupted
+        self.producer
+            .flush(DELIVERY_TIMEOUT)
+            .map_err(|e| anyhow!("failed to flush dead letters: {:?}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_producer_config() {
+        std::env::set_var("DEAD_LETTER_TEST_PASSWORD", "hunter2");
+
+        let config = producer_config(
+            "broker-1:9093,broker-2:9093",
+            &[
+                ("security.protocol", "SASL_SSL"),
+                ("sasl.mechanism", "SCRAM-SHA-512"),
+                ("sasl.username", "arroyo"),
+                ("sasl.password", "{{ DEAD_LETTER_TEST_PASSWORD }}"),
+                ("ssl.ca.location", "/etc/ssl/ca.pem"),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.get("bootstrap.servers"),
+            Some("broker-1:9093,broker-2:9093")
+        );
+        assert_eq!(config.get("security.protocol"), Some("SASL_SSL"));
+        assert_eq!(config.get("sasl.mechanism"), Some("SCRAM-SHA-512"));
+        assert_eq!(config.get("sasl.password"), Some("hunter2"));
+        assert_eq!(config.get("ssl.ca.location"), Some("/etc/ssl/ca.pem"));
+    }
+
+    #[test]
+    fn test_producer_config_missing_env_var() {
+        let result = producer_config(
+            "localhost:9092",
+            &[(
+                "sasl.password".to_string(),
+                "{{ DEAD_LETTER_TEST_MISSING_VAR }}".to_string(),
+            )]
+            .into_iter()
+            .collect(),
+        );
+
+        assert!(result.is_err());
+    }
+}
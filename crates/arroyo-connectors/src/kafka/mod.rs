@@ -36,6 +36,7 @@ use crate::kafka::source::KafkaSourceFunc;
 use arroyo_operator::connector::Connector;
 use arroyo_operator::operator::OperatorNode;
 
+pub mod dead_letter;
 mod sink;
 mod source;
 
@@ -211,6 +212,7 @@ impl Connector for KafkaConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
@@ -381,6 +383,7 @@ impl Connector for KafkaConnector {
                             .unwrap_or(u32::MAX),
                     )
                     .unwrap(),
+                    quota: config.quota,
                 })))
             }
             TableType::Sink { commit_mode } => {
@@ -393,7 +396,7 @@ impl Connector for KafkaConnector {
                     topic: table.topic,
                     serializer: ArrowSerializer::new(
                         config.format.expect("Format must be defined for KafkaSink"),
-                    ),
+                    )?,
                 })))
             }
         }
@@ -600,7 +603,7 @@ impl KafkaTester {
                         aschema.clone(),
                         BadData::Fail {},
                         Arc::new(schema_resolver),
-                    );
+                    )?;
                     let mut builders = aschema.builders();
 
                     let mut error = deserializer
@@ -622,7 +625,7 @@ impl KafkaTester {
                         aschema.clone(),
                         None,
                         BadData::Fail {},
-                    );
+                    )?;
                     let mut builders = aschema.builders();
 
                     let mut error = deserializer
@@ -643,6 +646,33 @@ impl KafkaTester {
                     }
                 }
             }
+            Format::Protobuf(proto) => {
+                if proto.confluent_schema_registry && msg[0] != 0 {
+                    bail!("Message appears to be encoded as normal Protobuf, rather than SR-Protobuf, but the schema registry is enabled. Ensure that the format and schema type are correct.");
+                }
+
+                let aschema: ArroyoSchema = schema.clone().into();
+                let mut deserializer = ArrowDeserializer::new(
+                    format.clone(),
+                    aschema.clone(),
+                    None,
+                    BadData::Fail {},
+                )?;
+                let mut builders = aschema.builders();
+
+                let mut error = deserializer
+                    .deserialize_slice(&mut builders, &msg, SystemTime::now())
+                    .await
+                    .into_iter()
+                    .next();
+                if let Some(Err(e)) = deserializer.flush_buffer() {
+                    error.replace(e);
+                }
+
+                if let Some(error) = error {
+                    bail!("Failed to parse message as Protobuf: {:?}. Ensure that the format and schema are correct.", error.details());
+                }
+            }
             Format::Parquet(_) => {
                 unreachable!()
             }
@@ -1,12 +1,16 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
-use arroyo_rpc::grpc::TableConfig;
+use arroyo_rpc::grpc::{GlobalKeyedTableConfig, TableConfig, TableEnum};
 use arroyo_rpc::{CheckpointEvent, ControlMessage, ControlResp};
+use arroyo_state::tables::global_keyed_map::GlobalKeyedView;
 use arroyo_types::*;
+use bincode::{Decode, Encode};
+use prost::Message;
 use std::collections::HashMap;
 
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
+use rdkafka::config::RDKafkaLogLevel;
 use rdkafka::producer::{DeliveryFuture, FutureProducer, FutureRecord, Producer};
 use rdkafka::util::Timeout;
 
@@ -22,15 +26,19 @@ use rdkafka::error::{KafkaError, RDKafkaErrorCode};
 use std::time::{Duration, SystemTime};
 
 use super::SinkCommitMode;
+use transactions::{commit_transaction, ConnectionConfig, ProducerIdentity, TransactionalContext};
 
 #[cfg(test)]
 mod test;
+mod transactions;
+
+pub type KafkaProducer = FutureProducer<TransactionalContext>;
 
 pub struct KafkaSinkFunc {
     pub topic: String,
     pub bootstrap_servers: String,
     pub consistency_mode: ConsistencyMode,
-    pub producer: Option<FutureProducer>,
+    pub producer: Option<KafkaProducer>,
     pub write_futures: Vec<DeliveryFuture>,
     pub client_config: HashMap<String, String>,
     pub serializer: ArrowSerializer,
@@ -40,7 +48,16 @@ pub enum ConsistencyMode {
     AtLeastOnce,
     ExactlyOnce {
         next_transaction_index: usize,
-        producer_to_complete: Option<FutureProducer>,
+        producer_to_complete: Option<KafkaProducer>,
+        /// records the producer identity assigned to the current transactional producer
+        producer_context: TransactionalContext,
+        /// whether any records have been written in the current transaction
+        transaction_has_records: bool,
+        /// the pre-commit state of the transaction awaiting commit, if any
+        pre_commit: Option<KafkaPreCommit>,
+        /// the connection used to commit transactions on behalf of a failed producer, which is
+        /// built on start so that unsupported configurations fail before they're needed
+        recovery_config: Option<ConnectionConfig>,
     },
 }
 
@@ -51,51 +68,109 @@ impl From<SinkCommitMode> for ConsistencyMode {
             SinkCommitMode::ExactlyOnce => ConsistencyMode::ExactlyOnce {
                 next_transaction_index: 0,
                 producer_to_complete: None,
+                producer_context: TransactionalContext::default(),
+                transaction_has_records: false,
+                pre_commit: None,
+                recovery_config: None,
             },
         }
     }
 }
 
+/// The state of a transaction that has been flushed as part of a checkpoint but not yet
+/// committed. It's stored in the checkpoint so that, if we fail during the commit phase, the
+/// restored sink can commit the transaction on behalf of the producer that started it.
+#[derive(Debug, Clone, Encode, Decode, PartialEq)]
+pub struct KafkaPreCommit {
+    transactional_id: String,
+    epoch: u32,
+    /// the producer that owns the transaction, or None if no records were written in it, in
+    /// which case there is nothing to commit
+    producer: Option<ProducerIdentity>,
+}
+
 impl KafkaSinkFunc {
     fn is_committing(&self) -> bool {
         matches!(self.consistency_mode, ConsistencyMode::ExactlyOnce { .. })
     }
 
     fn init_producer(&mut self, task_info: &TaskInfo) -> Result<()> {
-        let mut client_config = ClientConfig::new();
-        client_config.set("bootstrap.servers", &self.bootstrap_servers);
-        for (key, value) in &self.client_config {
-            client_config.set(key, value);
-        }
+        let client_config = self.client_config();
 
         match &mut self.consistency_mode {
             ConsistencyMode::AtLeastOnce => {
-                self.producer = Some(client_config.create()?);
+                self.producer =
+                    Some(client_config.create_with_context(TransactionalContext::default())?);
             }
             ConsistencyMode::ExactlyOnce {
                 next_transaction_index,
+                producer_context,
+                transaction_has_records,
                 ..
             } => {
-                client_config.set("enable.idempotence", "true");
-                let transactional_id = format!(
-                    "arroyo-id-{}-{}-{}-{}-{}",
-                    task_info.job_id,
-                    task_info.operator_id,
-                    self.topic,
-                    task_info.task_index,
-                    next_transaction_index
-                );
-                client_config.set("transactional.id", transactional_id);
-                let producer: FutureProducer = client_config.create()?;
-                producer.init_transactions(Timeout::After(Duration::from_secs(30)))?;
-                producer.begin_transaction()?;
+                let transactional_id =
+                    Self::transactional_id(&self.topic, task_info, *next_transaction_index);
+                let (producer, context) =
+                    Self::transactional_producer(client_config, transactional_id)?;
                 *next_transaction_index += 1;
+                *producer_context = context;
+                *transaction_has_records = false;
                 self.producer = Some(producer);
             }
         }
         Ok(())
     }
 
+    fn transactional_id(topic: &str, task_info: &TaskInfo, index: usize) -> String {
+        format!(
+            "arroyo-id-{}-{}-{}-{}-{}",
+            task_info.job_id, task_info.operator_id, topic, task_info.task_index, index
+        )
+    }
+
+    /// Creates a producer for the transactional id and begins a transaction. Initializing
+    /// transactions fences any previous producer with the same id; Kafka will abort a
+    /// transaction that producer left open (or finish one it had started committing).
+    fn transactional_producer(
+        mut client_config: ClientConfig,
+        transactional_id: String,
+    ) -> Result<(KafkaProducer, TransactionalContext)> {
+        client_config.set("enable.idempotence", "true");
+        client_config.set("transactional.id", transactional_id);
+
+        // the producer id is only reported in librdkafka's eos debug logs
+        let debug = match client_config.get("debug") {
+            Some(debug) => format!("{},eos", debug),
+            None => "eos".to_string(),
+        };
+        client_config.set("debug", debug);
+        client_config.set_log_level(RDKafkaLogLevel::Debug);
+
+        let context = TransactionalContext::default();
+        let producer: KafkaProducer = client_config.create_with_context(context.clone())?;
+        producer.init_transactions(Timeout::After(Duration::from_secs(30)))?;
+        producer.begin_transaction()?;
+        Ok((producer, context))
+    }
+
+    fn client_config(&self) -> ClientConfig {
+        let mut client_config = ClientConfig::new();
+        client_config.set("bootstrap.servers", &self.bootstrap_servers);
+        for (key, value) in &self.client_config {
+            client_config.set(key, value);
+        }
+        client_config
+    }
+
+    fn recovery_config(&self) -> Result<ConnectionConfig> {
+        let mut client_config = self.client_config.clone();
+        client_config.insert(
+            "bootstrap.servers".to_string(),
+            self.bootstrap_servers.clone(),
+        );
+        ConnectionConfig::new(&client_config)
+    }
+
     async fn flush(&mut self, ctx: &mut ArrowContext) {
         self.producer
             .as_ref()
@@ -117,19 +192,37 @@ impl KafkaSinkFunc {
     }
 
     async fn publish(&mut self, k: Option<Vec<u8>>, v: Vec<u8>, ctx: &mut ArrowContext) {
+        let future = Self::send(self.producer.as_ref().unwrap(), &self.topic, &k, &v, ctx).await;
+        self.write_futures.push(future);
+
+        if let ConsistencyMode::ExactlyOnce {
+            transaction_has_records,
+            ..
+        } = &mut self.consistency_mode
+        {
+            *transaction_has_records = true;
+        }
+    }
+
+    async fn send(
+        producer: &KafkaProducer,
+        topic: &str,
+        k: &Option<Vec<u8>>,
+        v: &Vec<u8>,
+        ctx: &mut ArrowContext,
+    ) -> DeliveryFuture {
         let mut rec = {
             if let Some(k) = k.as_ref() {
-                FutureRecord::to(&self.topic).key(k).payload(&v)
+                FutureRecord::to(topic).key(k).payload(v)
             } else {
-                FutureRecord::to(&self.topic).payload(&v)
+                FutureRecord::to(topic).payload(v)
             }
         };
 
         loop {
-            match self.producer.as_mut().unwrap().send_result(rec) {
+            match producer.send_result(rec) {
                 Ok(future) => {
-                    self.write_futures.push(future);
-                    return;
+                    return future;
                 }
                 Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), f)) => {
                     rec = f;
@@ -157,13 +250,66 @@ impl ArrowOperator for KafkaSinkFunc {
 
     fn tables(&self) -> HashMap<String, TableConfig> {
         if self.is_committing() {
-            todo!("implement committing state")
+            let mut tables = arroyo_state::global_table_config("i", "transactional id index");
+            tables.insert(
+                "p".into(),
+                TableConfig {
+                    table_type: TableEnum::GlobalKeyValue.into(),
+                    config: GlobalKeyedTableConfig {
+                        table_name: "p".into(),
+                        description: "pre-commit transactions".into(),
+                        uses_two_phase_commit: true,
+                    }
+                    .encode_to_vec(),
+                },
+            );
+            tables
         } else {
             HashMap::new()
         }
     }
 
     async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let config = self.is_committing().then(|| self.recovery_config());
+
+        if let ConsistencyMode::ExactlyOnce {
+            next_transaction_index,
+            pre_commit,
+            recovery_config,
+            ..
+        } = &mut self.consistency_mode
+        {
+            match config {
+                Some(Ok(config)) => *recovery_config = Some(config),
+                Some(Err(e)) => {
+                    ctx.fail_task(UserError::new(
+                        "Unsupported Kafka configuration for exactly-once",
+                        format!("transactions can't be recovered after a failure: {}", e),
+                    ))
+                    .await;
+                }
+                None => {}
+            }
+
+            let task_index = ctx.task_info.task_index;
+
+            // continue from the transaction index we were using when the checkpoint was taken,
+            // which fences the producer for any transaction that was started after it
+            let index_state: &mut GlobalKeyedView<usize, usize> = ctx
+                .table_manager
+                .get_global_keyed_state("i")
+                .await
+                .expect("should be able to get table");
+            *next_transaction_index = index_state.get(&task_index).copied().unwrap_or_default();
+
+            let pre_commit_state: &mut GlobalKeyedView<usize, KafkaPreCommit> = ctx
+                .table_manager
+                .get_global_keyed_state("p")
+                .await
+                .expect("should be able to get table");
+            *pre_commit = pre_commit_state.get(&task_index).cloned();
+        }
+
         self.init_producer(&ctx.task_info)
             .expect("Producer creation failed");
     }
@@ -185,21 +331,68 @@ impl ArrowOperator for KafkaSinkFunc {
         };
     }
 
-    async fn handle_checkpoint(&mut self, _: CheckpointBarrier, ctx: &mut ArrowContext) {
+    async fn handle_checkpoint(&mut self, barrier: CheckpointBarrier, ctx: &mut ArrowContext) {
         self.flush(ctx).await;
         if let ConsistencyMode::ExactlyOnce {
             next_transaction_index,
             producer_to_complete,
+            producer_context,
+            transaction_has_records,
+            pre_commit,
+            ..
         } = &mut self.consistency_mode
         {
+            let transactional_id =
+                Self::transactional_id(&self.topic, &ctx.task_info, *next_transaction_index - 1);
+
+            let producer = match (*transaction_has_records, producer_context.identity()) {
+                (false, _) => None,
+                (true, Some(identity)) => Some(identity),
+                (true, None) => {
+                    ctx.fail_task(UserError::new(
+                        "Failed to pre-commit Kafka transaction",
+                        format!(
+                            "could not determine the producer id for transaction {}",
+                            transactional_id
+                        ),
+                    ))
+                    .await;
+                    return;
+                }
+            };
+
             *producer_to_complete = self.producer.take();
+            let task_index = ctx.task_info.task_index;
+
+            let committing = KafkaPreCommit {
+                transactional_id,
+                epoch: barrier.epoch,
+                producer,
+            };
+
             ctx.table_manager
                 .get_global_keyed_state("i")
                 .await
                 .as_mut()
                 .unwrap()
-                .insert(ctx.task_info.task_index, *next_transaction_index)
+                .insert(task_index, *next_transaction_index)
+                .await;
+
+            ctx.table_manager
+                .get_global_keyed_state("p")
+                .await
+                .as_mut()
+                .unwrap()
+                .insert(task_index, committing.clone())
                 .await;
+
+            ctx.table_manager
+                .insert_committing_data("p", vec![])
+                .await
+                .expect("should be able to send committing data");
+
+            *pre_commit = Some(committing);
+
             self.init_producer(&ctx.task_info)
                 .expect("creating new producer during checkpointing");
         }
@@ -211,32 +404,102 @@ impl ArrowOperator for KafkaSinkFunc {
         _commit_data: &HashMap<String, HashMap<u32, Vec<u8>>>,
         ctx: &mut ArrowContext,
     ) {
+        if !self.is_committing() {
+            warn!("received commit but consistency mode is not exactly once");
+            return;
+        }
+
+        if let Err(e) = self.commit(epoch).await {
+            ctx.fail_task(UserError::new(
+                "Failed to commit Kafka transaction",
+                format!("{:?}", e),
+            ))
+            .await;
+            return;
+        }
+
+        self.send_commit_event(epoch, ctx).await;
+    }
+
+    async fn on_close(&mut self, _: &Option<SignalMessage>, ctx: &mut ArrowContext) {
+        if !self.is_committing() {
+            return;
+        }
+        if let Some(ControlMessage::Commit { epoch, commit_data }) = ctx.control_rx.recv().await {
+            self.handle_commit(epoch, &commit_data, ctx).await;
+        } else {
+            warn!("no commit message received, not committing")
+        }
+    }
+}
+
+impl KafkaSinkFunc {
+    async fn commit(&mut self, epoch: u32) -> Result<()> {
         let ConsistencyMode::ExactlyOnce {
-            next_transaction_index: _,
             producer_to_complete,
+            pre_commit,
+            recovery_config,
+            ..
         } = &mut self.consistency_mode
         else {
-            warn!("received commit but consistency mode is not exactly once");
-            return;
+            return Ok(());
         };
 
-        let Some(committing_producer) = producer_to_complete.take() else {
-            unimplemented!("received a commit message without a producer ready to commit. Restoring from commit phase not yet implemented");
-        };
-        let mut commits_attempted = 0;
-        loop {
-            if committing_producer
-                .commit_transaction(Timeout::After(Duration::from_secs(10)))
-                .is_ok()
-            {
-                break;
-            } else if commits_attempted == 5 {
-                panic!("failed to commit 5 times, giving up");
-            } else {
-                error!("failed to commit {} times, retrying", commits_attempted);
-                commits_attempted += 1;
+        let pre_commit = pre_commit.take();
+        if let Some(producer) = producer_to_complete.take() {
+            let mut commits_attempted = 0;
+            loop {
+                match producer.commit_transaction(Timeout::After(Duration::from_secs(10))) {
+                    Ok(()) => return Ok(()),
+                    Err(e) if commits_attempted < 5 => {
+                        commits_attempted += 1;
+                        error!(
+                            "failed to commit {} times, retrying: {:?}",
+                            commits_attempted, e
+                        );
+                    }
+                    Err(e) => {
+                        return Err(anyhow!(
+                            "failed to commit {} times, giving up: {:?}",
+                            commits_attempted + 1,
+                            e
+                        ));
+                    }
+                }
             }
         }
+
+        // we were restored from a checkpoint whose commit phase did not finish, so we need to
+        // commit the transaction that was pre-committed before the failure on behalf of the
+        // producer that wrote it
+        let Some(pre_commit) = pre_commit else {
+            warn!(
+                "received commit for epoch {} with no pending transaction",
+                epoch
+            );
+            return Ok(());
+        };
+
+        let Some(producer) = pre_commit.producer else {
+            info!(
+                "transaction {} for epoch {} has no records, nothing to recover",
+                pre_commit.transactional_id, pre_commit.epoch
+            );
+            return Ok(());
+        };
+
+        info!(
+            "recovering transaction {} for epoch {} from producer {:?}",
+            pre_commit.transactional_id, pre_commit.epoch, producer
+        );
+
+        let config = recovery_config
+            .as_ref()
+            .ok_or_else(|| anyhow!("the transaction recovery configuration is not valid"))?;
+        commit_transaction(config, &pre_commit.transactional_id, producer).await
+    }
+
+    async fn send_commit_event(&self, epoch: u32, ctx: &mut ArrowContext) {
         let checkpoint_event = ControlResp::CheckpointEvent(CheckpointEvent {
             checkpoint_epoch: epoch,
             operator_id: ctx.task_info.operator_id.clone(),
@@ -249,15 +512,4 @@ impl ArrowOperator for KafkaSinkFunc {
             .await
             .expect("sent commit event");
     }
-
-    async fn on_close(&mut self, _: &Option<SignalMessage>, ctx: &mut ArrowContext) {
-        if !self.is_committing() {
-            return;
-        }
-        if let Some(ControlMessage::Commit { epoch, commit_data }) = ctx.control_rx.recv().await {
-            self.handle_commit(epoch, &commit_data, ctx).await;
-        } else {
-            warn!("no commit message received, not committing")
-        }
-    }
 }
@@ -12,17 +12,20 @@ use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::ArrowOperator;
 use arroyo_rpc::df::ArroyoSchema;
 use arroyo_rpc::formats::{Format, JsonFormat};
+use arroyo_rpc::grpc::TaskCheckpointEventType;
+use arroyo_rpc::ControlResp;
 use arroyo_types::CheckpointBarrier;
 use arroyo_types::*;
 use itertools::Itertools;
 use rdkafka::admin::{AdminClient, AdminOptions, NewTopic};
 use rdkafka::consumer::{Consumer, StreamConsumer};
-use rdkafka::producer::Producer;
+use rdkafka::producer::{FutureRecord, Producer};
 use rdkafka::{ClientConfig, Message};
 use serde::Deserialize;
-use tokio::sync::mpsc::channel;
+use tokio::sync::mpsc::{channel, Receiver};
 
-use super::{ConsistencyMode, KafkaSinkFunc};
+use super::transactions::{ConnectionConfig, TransactionalContext};
+use super::{ConsistencyMode, KafkaPreCommit, KafkaSinkFunc};
 
 pub struct KafkaTopicTester {
     topic: String,
@@ -77,33 +80,30 @@ impl KafkaTopicTester {
             consistency_mode: ConsistencyMode::AtLeastOnce,
             write_futures: vec![],
             client_config: HashMap::new(),
-            serializer: ArrowSerializer::new(Format::Json(JsonFormat::default())),
+            serializer: ArrowSerializer::new(Format::Json(JsonFormat::default())).unwrap(),
         };
 
-        let (_, control_rx) = channel(128);
-        let (command_tx, _) = channel(128);
-
-        let task_info = get_test_task_info();
-
-        let mut ctx = ArrowContext::new(
-            task_info,
-            None,
-            control_rx,
-            command_tx,
-            1,
-            vec![ArroyoSchema::new_unkeyed(schema(), 0)],
-            None,
-            None,
-            vec![vec![]],
-            HashMap::new(),
-        )
-        .await;
+        let (mut ctx, _) = test_context().await;
 
         kafka.on_start(&mut ctx).await;
 
         KafkaSinkWithWrites { sink: kafka, ctx }
     }
 
+    fn get_consumer_with_config(&mut self, job_id: &str, isolation_level: &str) -> StreamConsumer {
+        let base_consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", self.server.to_string())
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest")
+            .set("isolation.level", isolation_level)
+            .set("group.id", format!("{}-{}-consumer", job_id, "operator_id"))
+            .create()
+            .expect("Consumer creation failed");
+
+        base_consumer.subscribe(&[&self.topic]).expect("success");
+        base_consumer
+    }
+
     fn get_consumer(&mut self, job_id: &str) -> StreamConsumer {
         let base_consumer: StreamConsumer = ClientConfig::new()
             .set("bootstrap.servers", self.server.to_string())
@@ -130,6 +130,29 @@ async fn get_data(consumer: &mut StreamConsumer) -> String {
     String::from_utf8(payload.to_vec()).unwrap()
 }
 
+async fn test_context() -> (ArrowContext, Receiver<ControlResp>) {
+    let (_, control_rx) = channel(128);
+    let (command_tx, command_rx) = channel(128);
+
+    let task_info = get_test_task_info();
+
+    let ctx = ArrowContext::new(
+        task_info,
+        None,
+        control_rx,
+        command_tx,
+        1,
+        vec![ArroyoSchema::new_unkeyed(schema(), 0)],
+        None,
+        None,
+        vec![vec![]],
+        HashMap::new(),
+    )
+    .await;
+
+    (ctx, command_rx)
+}
+
 struct KafkaSinkWithWrites {
     sink: KafkaSinkFunc,
     ctx: ArrowContext,
@@ -204,3 +227,95 @@ async fn test_kafka() {
         assert_eq!(message, result.value);
     }
 }
+
+#[tokio::test]
+async fn test_kafka_recovers_pre_committed_transaction() {
+    let mut kafka_topic_tester = KafkaTopicTester {
+        topic: "arroyo-sink-recovery".to_string(),
+        server: "0.0.0.0:9092".to_string(),
+    };
+
+    kafka_topic_tester.create_topic("recovery", 1).await;
+
+    // write and flush a transaction, then fail before committing it
+    let transactional_id = "arroyo-sink-recovery-test".to_string();
+    let mut client_config = ClientConfig::new();
+    client_config.set("bootstrap.servers", &kafka_topic_tester.server);
+    let (producer, context) =
+        KafkaSinkFunc::transactional_producer(client_config, transactional_id.clone()).unwrap();
+
+    for message in 1u32..50 {
+        let payload = format!("{{\"value\":{}}}", message);
+        producer
+            .send(
+                FutureRecord::<(), _>::to(&kafka_topic_tester.topic).payload(&payload),
+                Duration::from_secs(5),
+            )
+            .await
+            .expect("record should be written");
+    }
+    producer.flush(Duration::from_secs(5)).unwrap();
+
+    let pre_commit = KafkaPreCommit {
+        transactional_id,
+        epoch: 1,
+        producer: Some(
+            context
+                .identity()
+                .expect("should have captured the producer id"),
+        ),
+    };
+    // dropping the producer would abort the transaction, as opposed to a crashed worker
+    std::mem::forget(producer);
+
+    // restore a sink with only the pre-commit state and run the commit phase
+    let mut sink = KafkaSinkFunc {
+        topic: kafka_topic_tester.topic.to_string(),
+        bootstrap_servers: kafka_topic_tester.server.to_string(),
+        producer: None,
+        consistency_mode: ConsistencyMode::ExactlyOnce {
+            next_transaction_index: 1,
+            producer_to_complete: None,
+            producer_context: TransactionalContext::default(),
+            transaction_has_records: false,
+            pre_commit: Some(pre_commit),
+            recovery_config: Some(
+                ConnectionConfig::new(&HashMap::from([(
+                    "bootstrap.servers".to_string(),
+                    kafka_topic_tester.server.to_string(),
+                )]))
+                .unwrap(),
+            ),
+        },
+        write_futures: vec![],
+        client_config: HashMap::new(),
+        serializer: ArrowSerializer::new(Format::Json(JsonFormat::default())).unwrap(),
+    };
+
+    let (mut ctx, mut command_rx) = test_context().await;
+    sink.handle_commit(1, &HashMap::new(), &mut ctx).await;
+
+    match command_rx.recv().await {
+        Some(ControlResp::CheckpointEvent(event)) => {
+            assert_eq!(
+                event.event_type,
+                TaskCheckpointEventType::FinishedCommit as i32
+            );
+        }
+        other => panic!("expected commit to finish, got {:?}", other),
+    }
+
+    // the original records are now visible to transactional readers, exactly once
+    let mut consumer = kafka_topic_tester.get_consumer_with_config("recovery", "read_committed");
+    for message in 1u32..50 {
+        let result: TestData = serde_json::from_str(&get_data(&mut consumer).await).unwrap();
+        assert_eq!(message, result.value);
+    }
+
+    assert!(
+        tokio::time::timeout(Duration::from_secs(2), consumer.recv())
+            .await
+            .is_err(),
+        "no records should have been re-sent"
+    );
+}
@@ -0,0 +1,919 @@
+//! Support for committing a transaction that a previous producer pre-committed before failing.
+//!
+//! Neither librdkafka nor the Java client can resume a transaction started by another producer
+//! instance. Like Flink's `resumeTransaction`, we instead record the producer id and epoch the
+//! coordinator assigned to each transactional producer, store them in the checkpoint, and on
+//! recovery send the `EndTxn` request for that producer to the transaction coordinator ourselves.
+//! librdkafka doesn't expose the producer id, so it's captured from its `eos` debug logs.
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use bincode::{Decode, Encode};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use hmac::{Hmac, Mac};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rdkafka::config::RDKafkaLogLevel;
+use rdkafka::ClientContext;
+use sha2::{Digest, Sha256, Sha512};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::{self, Certificate, PrivateKey, RootCertStore, ServerName};
+use tokio_rustls::TlsConnector;
+use tracing::{debug, error, info, warn};
+
+const FIND_COORDINATOR: i16 = 10;
+const SASL_HANDSHAKE: i16 = 17;
+const END_TXN: i16 = 26;
+const SASL_AUTHENTICATE: i16 = 36;
+
+const COORDINATOR_TYPE_TRANSACTION: i8 = 1;
+
+const MAX_ATTEMPTS: usize = 10;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The producer id and epoch that the transaction coordinator assigned to a transactional
+/// producer. Together with the transactional id, these identify its open transaction.
+#[derive(Debug, Copy, Clone, Encode, Decode, PartialEq, Eq)]
+pub struct ProducerIdentity {
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+}
+
+/// Client context for transactional producers which records the producer identity assigned
+/// when transactions are initialized (or when the epoch is bumped after an abortable error)
+#[derive(Clone, Default)]
+pub struct TransactionalContext {
+    identity: Arc<Mutex<Option<ProducerIdentity>>>,
+}
+
+impl TransactionalContext {
+    pub fn identity(&self) -> Option<ProducerIdentity> {
+        *self.identity.lock().unwrap()
+    }
+}
+
+impl ClientContext for TransactionalContext {
+    fn log(&self, level: RDKafkaLogLevel, fac: &str, log_message: &str) {
+        if let Some(identity) = parse_acquired_pid(log_message) {
+            *self.identity.lock().unwrap() = Some(identity);
+        }
+
+        match level {
+            RDKafkaLogLevel::Emerg
+            | RDKafkaLogLevel::Alert
+            | RDKafkaLogLevel::Critical
+            | RDKafkaLogLevel::Error => {
+                error!(target: "librdkafka", "librdkafka: {} {}", fac, log_message)
+            }
+            RDKafkaLogLevel::Warning => {
+                warn!(target: "librdkafka", "librdkafka: {} {}", fac, log_message)
+            }
+            RDKafkaLogLevel::Notice | RDKafkaLogLevel::Info => {
+                info!(target: "librdkafka", "librdkafka: {} {}", fac, log_message)
+            }
+            RDKafkaLogLevel::Debug => {
+                debug!(target: "librdkafka", "librdkafka: {} {}", fac, log_message)
+            }
+        }
+    }
+}
+
+/// Parses the identity out of librdkafka's `IDEMPPID` log line, which looks like
+/// `Acquired PID{Id:1000,Epoch:0} (previous PID{Id:-1,Epoch:-1})`
+fn parse_acquired_pid(message: &str) -> Option<ProducerIdentity> {
+    const PREFIX: &str = "Acquired PID{Id:";
+    let rest = &message[message.find(PREFIX)? + PREFIX.len()..];
+    let (producer_id, rest) = rest.split_once(",Epoch:")?;
+    let (producer_epoch, _) = rest.split_once('}')?;
+
+    let identity = ProducerIdentity {
+        producer_id: producer_id.parse().ok()?,
+        producer_epoch: producer_epoch.parse().ok()?,
+    };
+
+    (identity.producer_id >= 0 && identity.producer_epoch >= 0).then_some(identity)
+}
+
+/// Commits the open transaction of the producer with the given identity. The producer must have
+/// flushed all of its records before it failed, which is guaranteed for pre-committed
+/// transactions. Committing a transaction that has already been committed succeeds.
+pub async fn commit_transaction(
+    config: &ConnectionConfig,
+    transactional_id: &str,
+    identity: ProducerIdentity,
+) -> Result<()> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let result = tokio::time::timeout(
+            REQUEST_TIMEOUT,
+            try_commit(config, transactional_id, identity),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            Err(io::Error::new(io::ErrorKind::TimedOut, "timed out committing transaction").into())
+        });
+
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < MAX_ATTEMPTS && is_retriable(&e) => {
+                warn!(
+                    "failed to commit transaction {} (attempt {}), retrying: {:?}",
+                    transactional_id, attempt, e
+                );
+                tokio::time::sleep(Duration::from_millis(100 * (1 << attempt.min(6)))).await;
+            }
+            Err(e) => {
+                return Err(e.context(format!(
+                    "failed to commit transaction {} for producer {:?}",
+                    transactional_id, identity
+                )));
+            }
+        }
+    }
+}
+
+async fn try_commit(
+    config: &ConnectionConfig,
+    transactional_id: &str,
+    identity: ProducerIdentity,
+) -> Result<()> {
+    let (host, port) = find_coordinator(config, transactional_id).await?;
+    let mut coordinator = Connection::connect(config, &host, port).await?;
+
+    let mut request = BytesMut::new();
+    put_string(&mut request, transactional_id);
+    request.put_i64(identity.producer_id);
+    request.put_i16(identity.producer_epoch);
+    request.put_u8(1); // committed = true
+
+    let mut response = coordinator.request(END_TXN, 0, &request).await?;
+    let _throttle_time_ms = get_i32(&mut response)?;
+    check_error(END_TXN, get_i16(&mut response)?, None)
+}
+
+async fn find_coordinator(
+    config: &ConnectionConfig,
+    transactional_id: &str,
+) -> Result<(String, u16)> {
+    let mut last_error = anyhow!("no bootstrap servers configured");
+    for (host, port) in &config.bootstrap_servers {
+        let mut connection = match Connection::connect(config, host, *port).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                last_error = e;
+                continue;
+            }
+        };
+
+        let mut request = BytesMut::new();
+        put_string(&mut request, transactional_id);
+        request.put_i8(COORDINATOR_TYPE_TRANSACTION);
+
+        let mut response = connection.request(FIND_COORDINATOR, 1, &request).await?;
+        let _throttle_time_ms = get_i32(&mut response)?;
+        let error_code = get_i16(&mut response)?;
+        let error_message = get_nullable_string(&mut response)?;
+        check_error(FIND_COORDINATOR, error_code, error_message)?;
+        let _node_id = get_i32(&mut response)?;
+        let host = get_nullable_string(&mut response)?
+            .ok_or_else(|| anyhow!("coordinator has no host"))?;
+        let port = get_i32(&mut response)?;
+
+        return Ok((host, port.try_into().context("invalid coordinator port")?));
+    }
+
+    Err(last_error)
+}
+
+/// An error code returned by the broker in a response
+#[derive(Debug)]
+struct BrokerError {
+    api_key: i16,
+    code: i16,
+    message: Option<String>,
+}
+
+impl std::fmt::Display for BrokerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.code {
+            7 => "REQUEST_TIMED_OUT",
+            14 => "COORDINATOR_LOAD_IN_PROGRESS",
+            15 => "COORDINATOR_NOT_AVAILABLE",
+            16 => "NOT_COORDINATOR",
+            33 => "UNSUPPORTED_SASL_MECHANISM",
+            34 => "ILLEGAL_SASL_STATE",
+            47 => "INVALID_PRODUCER_EPOCH",
+            48 => "INVALID_TXN_STATE",
+            49 => "INVALID_PRODUCER_ID_MAPPING",
+            51 => "CONCURRENT_TRANSACTIONS",
+            53 => "TRANSACTIONAL_ID_AUTHORIZATION_FAILED",
+            58 => "SASL_AUTHENTICATION_FAILED",
+            90 => "PRODUCER_FENCED",
+            _ => "UNKNOWN",
+        };
+        write!(
+            f,
+            "request {} failed with error {} ({})",
+            self.api_key, name, self.code
+        )?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BrokerError {}
+
+fn check_error(api_key: i16, code: i16, message: Option<String>) -> Result<()> {
+    if code == 0 {
+        return Ok(());
+    }
+
+    let error = BrokerError {
+        api_key,
+        code,
+        message,
+    };
+    Err(match code {
+        // the transaction was aborted (for example because it exceeded transaction.timeout.ms)
+        // or the producer was fenced, so its records can't be committed
+        47 | 48 | 49 | 90 => anyhow!(error).context(
+            "the pre-committed transaction can no longer be committed; its data has been lost",
+        ),
+        _ => error.into(),
+    })
+}
+
+fn is_retriable(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<BrokerError>() {
+        // the coordinator moved or is still loading, or a previous commit is still completing
+        Some(error) => matches!(error.code, 7 | 14 | 15 | 16 | 51),
+        // connection failures and timeouts
+        None => e.downcast_ref::<io::Error>().is_some(),
+    }
+}
+
+/// An invalid or unsupported client configuration
+#[derive(Debug)]
+struct ConfigError(String);
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SaslMechanism {
+    Plain,
+    ScramSha256,
+    ScramSha512,
+}
+
+struct SaslConfig {
+    mechanism: SaslMechanism,
+    username: String,
+    password: String,
+}
+
+// TLS settings that librdkafka supports but the recovery client can't honor
+const UNSUPPORTED_TLS_SETTINGS: [&str; 5] = [
+    "ssl.key.password",
+    "ssl.keystore.location",
+    "ssl.engine.location",
+    "ssl.certificate.verify_cb",
+    "ssl.ca.certificate.stores",
+];
+
+/// The subset of the librdkafka client configuration needed to connect to the brokers. It's
+/// built when an exactly-once sink starts, so that configurations that can't be used to recover
+/// transactions are rejected up front rather than when a recovery is needed.
+pub struct ConnectionConfig {
+    bootstrap_servers: Vec<(String, u16)>,
+    client_id: String,
+    tls: Option<TlsConnector>,
+    sasl: Option<SaslConfig>,
+}
+
+impl ConnectionConfig {
+    pub fn new(client_config: &HashMap<String, String>) -> Result<Self> {
+        let bootstrap_servers = client_config
+            .get("bootstrap.servers")
+            .ok_or_else(|| ConfigError("bootstrap.servers is not set".to_string()))?
+            .split(',')
+            .map(|server| {
+                let server = server.trim();
+                let server = server
+                    .split_once("://")
+                    .map(|(_, server)| server)
+                    .unwrap_or(server);
+                let (host, port) = server.rsplit_once(':').unwrap_or((server, "9092"));
+                let port = port
+                    .parse()
+                    .map_err(|_| ConfigError(format!("invalid bootstrap server '{}'", server)))?;
+                Ok((host.to_string(), port))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let protocol = client_config
+            .get("security.protocol")
+            .map(|p| p.to_lowercase())
+            .unwrap_or_else(|| "plaintext".to_string());
+
+        let (tls, sasl) = match protocol.as_str() {
+            "plaintext" => (false, false),
+            "ssl" => (true, false),
+            "sasl_plaintext" => (false, true),
+            "sasl_ssl" => (true, true),
+            other => {
+                return Err(
+                    ConfigError(format!("unsupported security.protocol '{}'", other)).into(),
+                )
+            }
+        };
+
+        let tls = tls.then(|| tls_connector(client_config)).transpose()?;
+
+        let sasl = sasl
+            .then(|| {
+                let mechanism = match client_config
+                    .get("sasl.mechanism")
+                    .or_else(|| client_config.get("sasl.mechanisms"))
+                    .map(|m| m.to_uppercase())
+                    .as_deref()
+                    .unwrap_or("GSSAPI")
+                {
+                    "PLAIN" => SaslMechanism::Plain,
+                    "SCRAM-SHA-256" => SaslMechanism::ScramSha256,
+                    "SCRAM-SHA-512" => SaslMechanism::ScramSha512,
+                    other => {
+                        return Err(ConfigError(format!(
+                            "SASL mechanism {} is not supported for recovering transactions",
+                            other
+                        )))
+                    }
+                };
+
+                let get = |key: &str| {
+                    client_config
+                        .get(key)
+                        .cloned()
+                        .ok_or_else(|| ConfigError(format!("{} is not set", key)))
+                };
+
+                Ok(SaslConfig {
+                    mechanism,
+                    username: get("sasl.username")?,
+                    password: get("sasl.password")?,
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            bootstrap_servers,
+            client_id: client_config
+                .get("client.id")
+                .cloned()
+                .unwrap_or_else(|| "arroyo-transaction-recovery".to_string()),
+            tls,
+            sasl,
+        })
+    }
+}
+
+/// Reads the PEM data for a TLS setting, which librdkafka accepts either as a path in
+/// `<setting>.location` or inline in `<setting>.pem`
+fn read_pem(client_config: &HashMap<String, String>, setting: &str) -> Result<Option<Vec<u8>>> {
+    if let Some(pem) = client_config.get(&format!("{}.pem", setting)) {
+        return Ok(Some(pem.as_bytes().to_vec()));
+    }
+
+    client_config
+        .get(&format!("{}.location", setting))
+        .map(|location| {
+            std::fs::read(location)
+                .map_err(|e| ConfigError(format!("could not read {}: {}", location, e)).into())
+        })
+        .transpose()
+}
+
+fn tls_connector(client_config: &HashMap<String, String>) -> Result<TlsConnector> {
+    if let Some(setting) = UNSUPPORTED_TLS_SETTINGS
+        .iter()
+        .find(|s| client_config.contains_key(**s))
+    {
+        bail!(ConfigError(format!(
+            "{} is not supported for recovering transactions",
+            setting
+        )));
+    }
+
+    if client_config
+        .get("enable.ssl.certificate.verification")
+        .is_some_and(|v| v == "false")
+    {
+        bail!(ConfigError(
+            "disabling certificate verification is not supported for recovering transactions"
+                .to_string()
+        ));
+    }
+
+    let mut roots = RootCertStore::empty();
+    match read_pem(client_config, "ssl.ca")? {
+        Some(pem) => {
+            for cert in rustls_pemfile::certs(&mut pem.as_slice())? {
+                roots.add(&Certificate(cert))?;
+            }
+        }
+        None => {
+            for cert in rustls_native_certs::load_native_certs()? {
+                roots.add(&Certificate(cert.0))?;
+            }
+        }
+    }
+
+    let builder = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots);
+
+    let config = match (
+        read_pem(client_config, "ssl.certificate")?,
+        read_pem(client_config, "ssl.key")?,
+    ) {
+        (Some(certs), Some(key)) => {
+            let certs = rustls_pemfile::certs(&mut certs.as_slice())?
+                .into_iter()
+                .map(Certificate)
+                .collect();
+            builder
+                .with_client_auth_cert(certs, private_key(&key)?)
+                .map_err(|e| ConfigError(format!("invalid client certificate: {}", e)))?
+        }
+        (None, None) => builder.with_no_client_auth(),
+        _ => bail!(ConfigError(
+            "client authentication requires both a certificate and a key".to_string()
+        )),
+    };
+
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+fn private_key(mut pem: &[u8]) -> Result<PrivateKey> {
+    for item in rustls_pemfile::read_all(&mut pem)? {
+        match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+
+    bail!(ConfigError(
+        "no unencrypted private key found in ssl.key".to_string()
+    ))
+}
+
+trait KafkaStream: AsyncRead + AsyncWrite + Send + Unpin {}
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> KafkaStream for T {}
+
+struct Connection {
+    stream: Box<dyn KafkaStream>,
+    client_id: String,
+    correlation_id: i32,
+}
+
+impl Connection {
+    async fn connect(config: &ConnectionConfig, host: &str, port: u16) -> Result<Self> {
+        let tcp = TcpStream::connect((host, port))
+            .await
+            .with_context(|| format!("failed to connect to {}:{}", host, port))?;
+
+        let stream: Box<dyn KafkaStream> = match &config.tls {
+            Some(connector) => {
+                let server_name = ServerName::try_from(host)
+                    .map_err(|_| ConfigError(format!("invalid server name '{}'", host)))?;
+                Box::new(connector.connect(server_name, tcp).await?)
+            }
+            None => Box::new(tcp),
+        };
+
+        let mut connection = Self {
+            stream,
+            client_id: config.client_id.clone(),
+            correlation_id: 0,
+        };
+
+        if let Some(sasl) = &config.sasl {
+            connection.authenticate(sasl).await?;
+        }
+
+        Ok(connection)
+    }
+
+    /// Sends a request with a v1 request header, and returns the response body
+    async fn request(&mut self, api_key: i16, api_version: i16, body: &[u8]) -> Result<Bytes> {
+        self.correlation_id += 1;
+
+        let mut request = BytesMut::new();
+        request.put_i16(api_key);
+        request.put_i16(api_version);
+        request.put_i32(self.correlation_id);
+        put_string(&mut request, &self.client_id);
+        request.put_slice(body);
+
+        self.stream.write_i32(request.len() as i32).await?;
+        self.stream.write_all(&request).await?;
+        self.stream.flush().await?;
+
+        let len = self.stream.read_i32().await?;
+        ensure!(len >= 4, "invalid response length {}", len);
+        let mut response = vec![0; len as usize];
+        self.stream.read_exact(&mut response).await?;
+
+        let mut response = Bytes::from(response);
+        let correlation_id = get_i32(&mut response)?;
+        ensure!(
+            correlation_id == self.correlation_id,
+            "expected response to request {}, got {}",
+            self.correlation_id,
+            correlation_id
+        );
+
+        Ok(response)
+    }
+
+    async fn sasl_authenticate(&mut self, auth_bytes: &[u8]) -> Result<Bytes> {
+        let mut request = BytesMut::new();
+        put_bytes(&mut request, auth_bytes);
+
+        let mut response = self.request(SASL_AUTHENTICATE, 0, &request).await?;
+        let error_code = get_i16(&mut response)?;
+        let error_message = get_nullable_string(&mut response)?;
+        check_error(SASL_AUTHENTICATE, error_code, error_message)?;
+        get_bytes(&mut response)
+    }
+
+    async fn authenticate(&mut self, sasl: &SaslConfig) -> Result<()> {
+        let mechanism = match sasl.mechanism {
+            SaslMechanism::Plain => "PLAIN",
+            SaslMechanism::ScramSha256 => "SCRAM-SHA-256",
+            SaslMechanism::ScramSha512 => "SCRAM-SHA-512",
+        };
+
+        let mut request = BytesMut::new();
+        put_string(&mut request, mechanism);
+        let mut response = self.request(SASL_HANDSHAKE, 1, &request).await?;
+        check_error(SASL_HANDSHAKE, get_i16(&mut response)?, None)?;
+
+        match sasl.mechanism {
+            SaslMechanism::Plain => {
+                let token = format!("\0{}\0{}", sasl.username, sasl.password);
+                self.sasl_authenticate(token.as_bytes()).await?;
+            }
+            SaslMechanism::ScramSha256 | SaslMechanism::ScramSha512 => {
+                let mut scram = Scram::new(sasl);
+                let server_first = self
+                    .sasl_authenticate(scram.client_first().as_bytes())
+                    .await?;
+                let client_final = scram.client_final(&server_first)?;
+                let server_final = self.sasl_authenticate(client_final.as_bytes()).await?;
+                scram.verify_server_final(&server_final)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Client side of the SCRAM exchange (RFC 5802) used by Kafka's SCRAM-SHA-256/512 mechanisms
+struct Scram<'a> {
+    sasl: &'a SaslConfig,
+    client_nonce: String,
+    client_first_bare: String,
+    server_signature: Option<Vec<u8>>,
+}
+
+impl<'a> Scram<'a> {
+    fn new(sasl: &'a SaslConfig) -> Self {
+        let nonce: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        Self::with_nonce(sasl, &nonce)
+    }
+
+    fn with_nonce(sasl: &'a SaslConfig, nonce: &str) -> Self {
+        let username = sasl.username.replace('=', "=3D").replace(',', "=2C");
+        Self {
+            sasl,
+            client_nonce: nonce.to_string(),
+            client_first_bare: format!("n={},r={}", username, nonce),
+            server_signature: None,
+        }
+    }
+
+    fn client_first(&self) -> String {
+        format!("n,,{}", self.client_first_bare)
+    }
+
+    fn client_final(&mut self, server_first: &[u8]) -> Result<String> {
+        let server_first =
+            std::str::from_utf8(server_first).context("invalid SCRAM server message")?;
+
+        let mut nonce = None;
+        let mut salt = None;
+        let mut iterations = None;
+        for attribute in server_first.split(',') {
+            match attribute.split_once('=') {
+                Some(("r", value)) => nonce = Some(value),
+                Some(("s", value)) => salt = Some(base64::decode(value)?),
+                Some(("i", value)) => iterations = Some(value.parse::<u32>()?),
+                _ => {}
+            }
+        }
+
+        let (Some(nonce), Some(salt), Some(iterations)) = (nonce, salt, iterations) else {
+            bail!("invalid SCRAM server message '{}'", server_first);
+        };
+        ensure!(
+            nonce.starts_with(&self.client_nonce),
+            "SCRAM server nonce does not extend the client nonce"
+        );
+
+        let salted_password = self.salted_password(&salt, iterations);
+        let client_key = self.hmac(&salted_password, b"Client Key");
+        let stored_key = self.hash(&client_key);
+
+        let client_final_without_proof = format!("c=biws,r={}", nonce);
+        let auth_message = format!(
+            "{},{},{}",
+            self.client_first_bare, server_first, client_final_without_proof
+        );
+
+        let client_signature = self.hmac(&stored_key, auth_message.as_bytes());
+        let proof: Vec<u8> = client_key
+            .iter()
+            .zip(client_signature)
+            .map(|(k, s)| k ^ s)
+            .collect();
+
+        let server_key = self.hmac(&salted_password, b"Server Key");
+        self.server_signature = Some(self.hmac(&server_key, auth_message.as_bytes()));
+
+        Ok(format!(
+            "{},p={}",
+            client_final_without_proof,
+            base64::encode(proof)
+        ))
+    }
+
+    fn verify_server_final(&self, server_final: &[u8]) -> Result<()> {
+        let server_final =
+            std::str::from_utf8(server_final).context("invalid SCRAM server message")?;
+        let Some(signature) = server_final.strip_prefix("v=") else {
+            bail!("SCRAM authentication failed: {}", server_final);
+        };
+        ensure!(
+            Some(base64::decode(signature)?) == self.server_signature,
+            "SCRAM server signature did not match"
+        );
+        Ok(())
+    }
+
+    fn salted_password(&self, salt: &[u8], iterations: u32) -> Vec<u8> {
+        let mut u = self.hmac(
+            self.sasl.password.as_bytes(),
+            &[salt, &1u32.to_be_bytes()].concat(),
+        );
+        let mut result = u.clone();
+        for _ in 1..iterations {
+            u = self.hmac(self.sasl.password.as_bytes(), &u);
+            result.iter_mut().zip(&u).for_each(|(r, u)| *r ^= u);
+        }
+        result
+    }
+
+    fn hmac(&self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self.sasl.mechanism {
+            SaslMechanism::ScramSha512 => {
+                let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("hmac accepts any key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            _ => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self.sasl.mechanism {
+            SaslMechanism::ScramSha512 => Sha512::digest(data).to_vec(),
+            _ => Sha256::digest(data).to_vec(),
+        }
+    }
+}
+
+fn put_string(buf: &mut BytesMut, s: &str) {
+    buf.put_i16(s.len() as i16);
+    buf.put_slice(s.as_bytes());
+}
+
+fn put_bytes(buf: &mut BytesMut, b: &[u8]) {
+    buf.put_i32(b.len() as i32);
+    buf.put_slice(b);
+}
+
+fn get_i16(buf: &mut Bytes) -> Result<i16> {
+    ensure!(buf.remaining() >= 2, "truncated response");
+    Ok(buf.get_i16())
+}
+
+fn get_i32(buf: &mut Bytes) -> Result<i32> {
+    ensure!(buf.remaining() >= 4, "truncated response");
+    Ok(buf.get_i32())
+}
+
+fn get_nullable_string(buf: &mut Bytes) -> Result<Option<String>> {
+    let len = get_i16(buf)?;
+    if len < 0 {
+        return Ok(None);
+    }
+    ensure!(buf.remaining() >= len as usize, "truncated response");
+    Ok(Some(String::from_utf8(
+        buf.split_to(len as usize).to_vec(),
+    )?))
+}
+
+fn get_bytes(buf: &mut Bytes) -> Result<Bytes> {
+    let len = get_i32(buf)?;
+    if len < 0 {
+        return Ok(Bytes::new());
+    }
+    ensure!(buf.remaining() >= len as usize, "truncated response");
+    Ok(buf.split_to(len as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_acquired_pid() {
+        assert_eq!(
+            parse_acquired_pid("Acquired PID{Id:1003,Epoch:2} (previous PID{Id:1002,Epoch:0})"),
+            Some(ProducerIdentity {
+                producer_id: 1003,
+                producer_epoch: 2
+            })
+        );
+        assert_eq!(
+            parse_acquired_pid("[thrd:main]: Acquired PID{Id:7,Epoch:0}"),
+            Some(ProducerIdentity {
+                producer_id: 7,
+                producer_epoch: 0
+            })
+        );
+        assert_eq!(
+            parse_acquired_pid("Acquired invalid PID{id:-1,epoch:-1}: ignoring"),
+            None
+        );
+        assert_eq!(parse_acquired_pid("Transaction commit succeeded"), None);
+    }
+
+    #[test]
+    fn test_connection_config() {
+        let config = ConnectionConfig::new(&HashMap::from([
+            (
+                "bootstrap.servers".to_string(),
+                "broker-1:9093, broker-2".to_string(),
+            ),
+            (
+                "security.protocol".to_string(),
+                "SASL_PLAINTEXT".to_string(),
+            ),
+            ("sasl.mechanism".to_string(), "SCRAM-SHA-512".to_string()),
+            ("sasl.username".to_string(), "user".to_string()),
+            ("sasl.password".to_string(), "pass".to_string()),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            config.bootstrap_servers,
+            vec![
+                ("broker-1".to_string(), 9093),
+                ("broker-2".to_string(), 9092)
+            ]
+        );
+        assert!(config.tls.is_none());
+        assert_eq!(
+            config.sasl.as_ref().unwrap().mechanism,
+            SaslMechanism::ScramSha512
+        );
+
+        let Err(err) = ConnectionConfig::new(&HashMap::from([
+            (
+                "bootstrap.servers".to_string(),
+                "localhost:9092".to_string(),
+            ),
+            (
+                "security.protocol".to_string(),
+                "sasl_plaintext".to_string(),
+            ),
+            ("sasl.mechanism".to_string(), "GSSAPI".to_string()),
+        ])) else {
+            panic!("GSSAPI should not be supported");
+        };
+        assert!(err.downcast_ref::<ConfigError>().is_some());
+        assert!(!is_retriable(&err));
+    }
+
+    #[test]
+    fn test_client_certificates() {
+        let config = |settings: &[(&str, &str)]| {
+            let mut config = HashMap::from([
+                (
+                    "bootstrap.servers".to_string(),
+                    "localhost:9093".to_string(),
+                ),
+                ("security.protocol".to_string(), "ssl".to_string()),
+                ("ssl.ca.pem".to_string(), "".to_string()),
+            ]);
+            config.extend(settings.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+            ConnectionConfig::new(&config)
+        };
+
+        assert!(config(&[]).unwrap().tls.is_some());
+
+        for settings in [
+            &[("ssl.certificate.pem", "")][..],
+            &[("ssl.key.location", "/tmp/client.key")][..],
+            &[("ssl.certificate.pem", ""), ("ssl.key.pem", "not a key")][..],
+            &[("ssl.key.password", "secret")][..],
+            &[("ssl.keystore.location", "/tmp/client.p12")][..],
+            &[("enable.ssl.certificate.verification", "false")][..],
+        ] {
+            let Err(err) = config(settings) else {
+                panic!("{:?} should not be supported", settings);
+            };
+            assert!(err.downcast_ref::<ConfigError>().is_some(), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn test_error_classification() {
+        let err = check_error(END_TXN, 51, None).unwrap_err();
+        assert!(is_retriable(&err));
+
+        let err = check_error(END_TXN, 16, None).unwrap_err();
+        assert!(is_retriable(&err));
+
+        // fenced or aborted transactions can't be retried
+        for code in [47, 48, 90] {
+            let err = check_error(END_TXN, code, None).unwrap_err();
+            assert!(!is_retriable(&err), "{}", code);
+        }
+
+        assert!(check_error(END_TXN, 0, None).is_ok());
+    }
+
+    #[test]
+    fn test_scram_sha256() {
+        // test vector from RFC 7677
+        let sasl = SaslConfig {
+            mechanism: SaslMechanism::ScramSha256,
+            username: "user".to_string(),
+            password: "pencil".to_string(),
+        };
+
+        let mut scram = Scram::with_nonce(&sasl, "rOprNGfwEbeRWgbNEkqO");
+        assert_eq!(scram.client_first(), "n,,n=user,r=rOprNGfwEbeRWgbNEkqO");
+
+        let client_final = scram
+            .client_final(
+                b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+            )
+            .unwrap();
+        assert_eq!(
+            client_final,
+            "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+        );
+
+        scram
+            .verify_server_final(b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=")
+            .unwrap();
+        assert!(scram.verify_server_final(b"e=invalid-proof").is_err());
+    }
+}
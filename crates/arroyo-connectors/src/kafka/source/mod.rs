@@ -1,9 +1,11 @@
 use arroyo_rpc::formats::{BadData, Format, Framing};
 use arroyo_rpc::grpc::TableConfig;
 use arroyo_rpc::schema_resolver::SchemaResolver;
-use arroyo_rpc::{grpc::StopMode, ControlMessage, ControlResp};
+use arroyo_rpc::{grpc::StopMode, ControlMessage, ControlResp, RateLimit};
 
+use crate::source_rate_limiter;
 use arroyo_operator::context::ArrowContext;
+use arroyo_operator::dead_letter::SourcePosition;
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
 use arroyo_types::*;
@@ -34,6 +36,8 @@ pub struct KafkaSourceFunc {
     pub schema_resolver: Arc<dyn SchemaResolver + Sync>,
     pub client_configs: HashMap<String, String>,
     pub messages_per_second: NonZeroU32,
+    // the organization's quota, which applies across all of the source's subtasks
+    pub quota: Option<RateLimit>,
 }
 
 #[derive(Copy, Clone, Debug, Encode, Decode, PartialEq, PartialOrd)]
@@ -125,6 +129,7 @@ impl KafkaSourceFunc {
             .map_err(|e| UserError::new("Could not create Kafka consumer", format!("{:?}", e)))?;
 
         let rate_limiter = GovernorRateLimiter::direct(Quota::per_second(self.messages_per_second));
+        let quota_limiter = source_rate_limiter(&self.quota, ctx.task_info.parallelism);
         let mut offsets = HashMap::new();
 
         if consumer.assignment().unwrap().count() == 0 {
@@ -141,7 +146,7 @@ impl KafkaSourceFunc {
             self.framing.clone(),
             self.bad_data.clone(),
             self.schema_resolver.clone(),
-        );
+        )?;
 
         let mut flush_ticker = tokio::time::interval(Duration::from_millis(50));
         flush_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
@@ -156,7 +161,11 @@ impl KafkaSourceFunc {
                                     .ok_or_else(|| UserError::new("Failed to read timestamp from Kafka record",
                                         "The message read from Kafka did not contain a message timestamp"))?;
 
-                                ctx.deserialize_slice(&v, from_millis(timestamp as u64)).await?;
+                                ctx.deserialize_slice_at(
+                                    &v,
+                                    from_millis(timestamp as u64),
+                                    &SourcePosition::new(msg.partition(), msg.offset()),
+                                ).await?;
 
                                 if ctx.should_flush() {
                                     ctx.flush_buffer().await?;
@@ -164,6 +173,9 @@ impl KafkaSourceFunc {
 
                                 offsets.insert(msg.partition(), msg.offset());
                                 rate_limiter.until_ready().await;
+                                if let Some(quota_limiter) = &quota_limiter {
+                                    quota_limiter.until_ready().await;
+                                }
                             }
                         },
                         Err(err) => {
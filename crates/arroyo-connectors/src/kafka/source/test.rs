@@ -87,6 +87,7 @@ impl KafkaTopicTester {
             schema_resolver: Arc::new(FailingSchemaResolver::new()),
             client_configs: HashMap::new(),
             messages_per_second: NonZeroU32::new(100).unwrap(),
+            quota: None,
         });
 
         let (to_control_tx, control_rx) = channel(128);
@@ -287,6 +288,7 @@ async fn test_kafka() {
         finish_time: 0,
         table_checkpoint_metadata: single_item_hash_map("k", table_metadata),
         table_configs: subtask_metadata.table_configs,
+        allow_dropped_tables: false,
         operator_metadata: Some(OperatorMetadata {
             job_id: task_info.job_id.clone(),
             operator_id: task_info.operator_id.clone(),
@@ -108,6 +108,7 @@ impl Connector for KinesisConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
@@ -209,7 +210,7 @@ impl Connector for KinesisConnector {
                         config
                             .format
                             .ok_or_else(|| anyhow!("Format must be defined for KinesisSink"))?,
-                    ),
+                    )?,
                     flush_config,
                 })))
             }
@@ -8,6 +8,7 @@ use std::{
 
 use anyhow::{anyhow, bail, Context as AnyhowContext, Result};
 use arroyo_operator::context::ArrowContext;
+use arroyo_operator::dead_letter::SourcePosition;
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
 use arroyo_rpc::formats::{BadData, Format, Framing};
@@ -168,14 +169,6 @@ impl SourceOperator for KinesisSourceFunc {
         global_table_config("k", "kinesis source state")
     }
 
-    async fn on_start(&mut self, ctx: &mut ArrowContext) {
-        ctx.initialize_deserializer(
-            self.format.clone(),
-            self.framing.clone(),
-            self.bad_data.clone(),
-        );
-    }
-
     async fn run(&mut self, ctx: &mut ArrowContext) -> SourceFinishType {
         match self.run_int(ctx).await {
             Ok(r) => r,
@@ -357,6 +350,12 @@ impl KinesisSourceFunc {
     /// * An interval that periodically polls for new shards, initializing their futures.
     /// * Polling off of the control queue, to perform checkpointing and stop the operator.
     async fn run_int(&mut self, ctx: &mut ArrowContext) -> Result<SourceFinishType, UserError> {
+        ctx.initialize_deserializer(
+            self.format.clone(),
+            self.framing.clone(),
+            self.bad_data.clone(),
+        )?;
+
         self.init_client().await;
         let starting_futures = self
             .init_shards(ctx)
@@ -440,7 +439,12 @@ impl KinesisSourceFunc {
             let data = record.data.unwrap().into_inner();
             let timestamp = record.approximate_arrival_timestamp.unwrap();
 
-            ctx.deserialize_slice(&data, from_nanos(timestamp.as_nanos() as u128))
+            let position = SourcePosition {
+                partition: None,
+                offset: record.sequence_number,
+            };
+
+            ctx.deserialize_slice_at(&data, from_nanos(timestamp.as_nanos() as u128), &position)
                 .await?;
 
             if ctx.should_flush() {
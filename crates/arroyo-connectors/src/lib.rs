@@ -1,6 +1,8 @@
 use crate::confluent::ConfluentConnector;
 use crate::filesystem::delta::DeltaLakeConnector;
+use crate::filesystem::iceberg::IcebergConnector;
 use crate::filesystem::FileSystemConnector;
+use crate::http_lookup::HttpLookupConnector;
 use crate::kinesis::KinesisConnector;
 use crate::mqtt::MqttConnector;
 use crate::polling_http::PollingHTTPConnector;
@@ -10,14 +12,21 @@ use crate::single_file::SingleFileConnector;
 use crate::webhook::WebhookConnector;
 use anyhow::{anyhow, bail, Context};
 use arroyo_operator::connector::ErasedConnector;
+use arroyo_operator::dead_letter::{
+    set_dead_letter_sink_factory, DeadLetterSink, DeadLetterSinkFactory,
+};
 use arroyo_rpc::api_types::connections::{
     ConnectionSchema, ConnectionType, FieldType, SourceField, SourceFieldType, TestSourceMessage,
 };
+use arroyo_rpc::formats::DeadLetterTarget;
 use arroyo_rpc::primitive_to_sql;
 use arroyo_rpc::var_str::VarStr;
+use arroyo_rpc::RateLimit;
 use arroyo_types::string_to_map;
+use async_trait::async_trait;
 use blackhole::BlackholeConnector;
 use fluvio::FluvioConnector;
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
 use impulse::ImpulseConnector;
 use nexmark::NexmarkConnector;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
@@ -25,17 +34,20 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sse::SSEConnector;
 use std::collections::HashMap;
+use std::num::NonZeroU32;
 use std::time::Duration;
 use tokio::sync::mpsc::Sender;
 use tracing::warn;
 use websocket::WebsocketConnector;
 
+use self::kafka::dead_letter::KafkaDeadLetterSink;
 use self::kafka::KafkaConnector;
 
 pub mod blackhole;
 pub mod confluent;
 pub mod filesystem;
 pub mod fluvio;
+pub mod http_lookup;
 pub mod impulse;
 pub mod kafka;
 pub mod kinesis;
@@ -49,6 +61,36 @@ pub mod sse;
 pub mod webhook;
 pub mod websocket;
 
+/// The rate at which a single subtask of a source may read, given a rate limit that applies
+/// across all of the source's subtasks.
+///
+/// The limit is split evenly between the subtasks, rounding down to a whole number of messages
+/// per second so that together they never exceed it. When the limit is lower than the
+/// parallelism, each subtask instead reads one message every `parallelism / messages_per_second`
+/// seconds. A limit of zero is treated as a limit of one.
+pub(crate) fn subtask_quota(messages_per_second: u32, parallelism: usize) -> Quota {
+    let parallelism = parallelism.max(1) as u32;
+    let messages_per_second = messages_per_second.max(1);
+
+    match NonZeroU32::new(messages_per_second / parallelism) {
+        Some(rate) => Quota::per_second(rate),
+        None => Quota::with_period(Duration::from_secs(parallelism as u64) / messages_per_second)
+            .expect("period is at least one second"),
+    }
+}
+
+/// Creates a token bucket that holds a source subtask to its share of the source's rate limit
+pub(crate) fn source_rate_limiter(
+    rate_limit: &Option<RateLimit>,
+    parallelism: usize,
+) -> Option<DefaultDirectRateLimiter> {
+    let rate_limit = rate_limit.as_ref()?;
+    Some(RateLimiter::direct(subtask_quota(
+        rate_limit.messages_per_second,
+        parallelism,
+    )))
+}
+
 pub fn connectors() -> HashMap<&'static str, Box<dyn ErasedConnector>> {
     let connectors: Vec<Box<dyn ErasedConnector>> = vec![
         Box::new(BlackholeConnector {}),
@@ -56,6 +98,8 @@ pub fn connectors() -> HashMap<&'static str, Box<dyn ErasedConnector>> {
         Box::new(DeltaLakeConnector {}),
         Box::new(FileSystemConnector {}),
         Box::new(FluvioConnector {}),
+        Box::new(HttpLookupConnector {}),
+        Box::new(IcebergConnector {}),
         Box::new(ImpulseConnector {}),
         Box::new(KafkaConnector {}),
         Box::new(KinesisConnector {}),
@@ -73,6 +117,35 @@ pub fn connectors() -> HashMap<&'static str, Box<dyn ErasedConnector>> {
     connectors.into_iter().map(|c| (c.name(), c)).collect()
 }
 
+/// Creates the sinks for dead-letter targets that are implemented by connectors
+struct ConnectorDeadLetterSinks;
+
+#[async_trait]
+impl DeadLetterSinkFactory for ConnectorDeadLetterSinks {
+    async fn create(&self, target: &DeadLetterTarget) -> anyhow::Result<Box<dyn DeadLetterSink>> {
+        match target {
+            DeadLetterTarget::Kafka {
+                bootstrap_servers,
+                topic,
+                client_configs,
+            } => Ok(Box::new(KafkaDeadLetterSink::new(
+                bootstrap_servers,
+                topic,
+                client_configs,
+            )?)),
+            DeadLetterTarget::Filesystem { .. } => {
+                bail!("filesystem dead letter targets are created by the operator")
+            }
+        }
+    }
+}
+
+/// Makes the connectors' dead-letter targets available to sources; called before running any
+/// operators
+pub fn register_dead_letter_sinks() {
+    set_dead_letter_sink_factory(Box::new(ConnectorDeadLetterSinks));
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct EmptyConfig {}
 
@@ -126,7 +199,7 @@ pub(crate) fn source_field(name: &str, field_type: FieldType) -> SourceField {
         field_type: SourceFieldType {
             sql_name: match field_type.clone() {
                 FieldType::Primitive(p) => Some(primitive_to_sql(p).to_string()),
-                FieldType::Struct(_) => None,
+                FieldType::Struct(_) | FieldType::List(_) => None,
             },
             r#type: field_type,
         },
@@ -171,3 +244,73 @@ pub fn header_map(headers: Option<VarStr>) -> HashMap<String, String> {
     )
     .expect("Invalid header map")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subtask_quota() {
+        // the limit is split evenly between subtasks
+        let quota = subtask_quota(100, 4);
+        assert_eq!(quota.burst_size().get(), 25);
+        assert_eq!(quota.replenish_interval(), Duration::from_millis(40));
+
+        // shares are rounded down, so the subtasks read at most 9 messages per second in total
+        let quota = subtask_quota(10, 3);
+        assert_eq!(quota.burst_size().get(), 3);
+
+        // with fewer messages per second than subtasks, each one reads a message every
+        // parallelism / limit seconds rather than one every second
+        let quota = subtask_quota(3, 4);
+        assert_eq!(quota.burst_size().get(), 1);
+        assert_eq!(quota.replenish_interval(), Duration::from_secs(4) / 3);
+
+        let quota = subtask_quota(1, 8);
+        assert_eq!(quota.burst_size().get(), 1);
+        assert_eq!(quota.replenish_interval(), Duration::from_secs(8));
+
+        assert_eq!(subtask_quota(10, 0).burst_size().get(), 10);
+        assert_eq!(
+            subtask_quota(0, 2).replenish_interval(),
+            Duration::from_secs(2)
+        );
+    }
+
+    #[test]
+    fn test_subtask_quotas_never_exceed_the_limit() {
+        for messages_per_second in [1, 2, 3, 7, 10, 99, 100, 1000, 10_000] {
+            for parallelism in 1..=64usize {
+                let quota = subtask_quota(messages_per_second, parallelism);
+                let subtask_rate = 1.0 / quota.replenish_interval().as_secs_f64();
+                let total = subtask_rate * parallelism as f64;
+                // replenish intervals are truncated to whole nanoseconds
+                assert!(
+                    total <= messages_per_second as f64 * (1.0 + 1e-6),
+                    "{} subtasks read {} messages per second with a limit of {}",
+                    parallelism,
+                    total,
+                    messages_per_second
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_source_rate_limiter() {
+        assert!(source_rate_limiter(&None, 4).is_none());
+
+        let limiter = source_rate_limiter(
+            &Some(RateLimit {
+                messages_per_second: 8,
+            }),
+            4,
+        )
+        .unwrap();
+
+        // each subtask can read a burst of its share of the limit, and then has to wait
+        assert!(limiter.check().is_ok());
+        assert!(limiter.check().is_ok());
+        assert!(limiter.check().is_err());
+    }
+}
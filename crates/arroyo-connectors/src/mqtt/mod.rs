@@ -178,6 +178,7 @@ impl Connector for MqttConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
@@ -290,7 +291,7 @@ impl Connector for MqttConnector {
                     config
                         .format
                         .ok_or_else(|| anyhow!("format is required for mqtt sink"))?,
-                ),
+                )?,
                 stopped: Arc::new(AtomicBool::new(false)),
                 client: None,
             })),
@@ -28,16 +28,22 @@ pub struct MqttSinkFunc {
 }
 
 impl MqttSinkFunc {
-    pub fn new(config: MqttConfig, qos: QoS, topic: String, retain: bool, format: Format) -> Self {
-        Self {
+    pub fn new(
+        config: MqttConfig,
+        qos: QoS,
+        topic: String,
+        retain: bool,
+        format: Format,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
             config,
             qos,
             topic,
             retain,
-            serializer: ArrowSerializer::new(format),
+            serializer: ArrowSerializer::new(format)?,
             client: None,
             stopped: Arc::new(AtomicBool::new(false)),
-        }
+        })
     }
 }
 
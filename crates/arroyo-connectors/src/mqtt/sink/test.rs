@@ -73,7 +73,8 @@ impl MqttTopicTester {
             self.topic.clone(),
             false,
             Format::Json(JsonFormat::default()),
-        );
+        )
+        .unwrap();
 
         let (_, control_rx) = channel(128);
         let (command_tx, _) = channel(128);
@@ -96,7 +96,7 @@ impl MqttSourceFunc {
             self.format.clone(),
             self.framing.clone(),
             self.bad_data.clone(),
-        );
+        )?;
 
         if ctx.task_info.task_index > 0 {
             tracing::warn!(
@@ -208,6 +208,7 @@ impl Connector for NexmarkConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: None,
             bad_data: None,
             framing: None,
@@ -228,10 +229,10 @@ impl Connector for NexmarkConnector {
         &self,
         _: Self::ProfileT,
         table: Self::TableT,
-        _: OperatorConfig,
+        config: OperatorConfig,
     ) -> anyhow::Result<OperatorNode> {
         Ok(OperatorNode::from_source(Box::new(
-            NexmarkSourceFunc::from_config(&table),
+            NexmarkSourceFunc::from_config(&table, config.quota),
         )))
     }
 }
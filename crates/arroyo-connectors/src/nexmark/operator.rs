@@ -1,4 +1,5 @@
 use crate::nexmark::{auction_fields, bid_fields, person_fields, NexmarkTable};
+use crate::source_rate_limiter;
 use arrow::array::{
     Int64Builder, RecordBatch, StringBuilder, StructBuilder, TimestampNanosecondBuilder,
 };
@@ -6,7 +7,7 @@ use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
 use arroyo_rpc::grpc::{StopMode, TableConfig};
-use arroyo_rpc::ControlMessage;
+use arroyo_rpc::{ControlMessage, RateLimit};
 use arroyo_types::{should_flush, to_millis, to_nanos};
 use async_trait::async_trait;
 use bincode::{Decode, Encode};
@@ -169,6 +170,7 @@ impl ArrowWriter for Option<&Bid> {
 pub struct NexmarkSourceFunc {
     first_event_rate: f64,
     num_events: Option<u64>,
+    quota: Option<RateLimit>,
     state: Option<NexmarkSourceState>,
 }
 
@@ -184,16 +186,18 @@ impl NexmarkSourceFunc {
         Self {
             first_event_rate: first_event_rate as f64,
             num_events,
+            quota: None,
             state: None,
         }
     }
 
-    pub fn from_config(table: &NexmarkTable) -> Self {
+    pub fn from_config(table: &NexmarkTable, quota: Option<RateLimit>) -> Self {
         Self {
             first_event_rate: table.event_rate,
             num_events: table
                 .runtime
                 .map(|time| (table.event_rate * time).floor() as u64),
+            quota,
             state: None,
         }
     }
@@ -255,6 +259,7 @@ impl SourceOperator for NexmarkSourceFunc {
         let mut auction_builder = StructBuilder::from_fields(auction_fields(), 128);
         let mut bid_builder = StructBuilder::from_fields(bid_fields(), 128);
         let mut timestamp_builder = TimestampNanosecondBuilder::with_capacity(128);
+        let rate_limiter = source_rate_limiter(&self.quota, ctx.task_info.parallelism);
 
         while generator.has_next() {
             let now = SystemTime::now();
@@ -263,6 +268,10 @@ impl SourceOperator for NexmarkSourceFunc {
                 sleep(next_event.wallclock_timestamp.duration_since(now).unwrap()).await;
             }
 
+            if let Some(rate_limiter) = &rate_limiter {
+                rate_limiter.until_ready().await;
+            }
+
             records += 1;
             next_event.person.as_ref().write_into(&mut person_builder);
             next_event.auction.as_ref().write_into(&mut auction_builder);
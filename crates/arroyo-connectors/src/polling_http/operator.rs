@@ -82,12 +82,6 @@ impl PollingHttpSourceFunc {
         ctx: &mut ArrowContext,
         msg: Option<ControlMessage>,
     ) -> Option<SourceFinishType> {
-        ctx.initialize_deserializer(
-            self.format.clone(),
-            self.framing.clone(),
-            self.bad_data.clone(),
-        );
-
         match msg? {
             ControlMessage::Checkpoint(c) => {
                 debug!("starting checkpointing {}", ctx.task_info.task_index);
@@ -205,7 +199,7 @@ impl PollingHttpSourceFunc {
             self.format.clone(),
             self.framing.clone(),
             self.bad_data.clone(),
-        );
+        )?;
 
         // since there's no way to partition across an http source, only read on the first task
         let mut timer = tokio::time::interval(self.polling_interval);
@@ -93,6 +93,7 @@ impl Connector for PreviewConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: None,
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
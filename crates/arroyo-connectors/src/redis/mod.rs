@@ -4,8 +4,9 @@ use std::collections::HashMap;
 
 use anyhow::{anyhow, bail};
 use arroyo_formats::ser::ArrowSerializer;
-use arroyo_operator::connector::{Connection, Connector};
+use arroyo_operator::connector::{Connection, Connector, LookupConnector};
 use arroyo_operator::operator::OperatorNode;
+use arroyo_rpc::formats::Format;
 use arroyo_rpc::var_str::VarStr;
 use redis::aio::ConnectionManager;
 use redis::cluster::ClusterClient;
@@ -20,6 +21,7 @@ use arroyo_rpc::api_types::connections::{
 };
 use arroyo_rpc::OperatorConfig;
 
+use crate::redis::operator::lookup::RedisLookup;
 use crate::redis::operator::sink::{GeneralConnection, RedisSinkFunc};
 use crate::{pull_opt, pull_option_to_u64};
 
@@ -161,7 +163,7 @@ impl Connector for RedisConnector {
             id: "redis".to_string(),
             name: "Redis".to_string(),
             icon: ICON.to_string(),
-            description: "Write results to Redis".to_string(),
+            description: "Write results to Redis, or look up values in lookup joins".to_string(),
             enabled: true,
             source: false,
             sink: true,
@@ -173,8 +175,11 @@ impl Connector for RedisConnector {
         }
     }
 
-    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
-        return ConnectionType::Source;
+    fn table_type(&self, _: Self::ProfileT, table: Self::TableT) -> ConnectionType {
+        match table.connector_type {
+            TableType::Target(_) => ConnectionType::Sink,
+            TableType::Lookup(_) => ConnectionType::Lookup,
+        }
     }
 
     fn get_schema(
@@ -292,7 +297,7 @@ impl Connector for RedisConnector {
             Ok(column)
         }
 
-        let sink = match typ.as_str() {
+        let connector_type = match typ.as_str() {
             "sink" => TableType::Target(match pull_opt("target", options)?.as_str() {
                 "string" => Target::StringTable {
                     key_prefix: pull_opt("target.key_prefix", options)?,
@@ -343,8 +348,11 @@ impl Connector for RedisConnector {
                     bail!("'{}' is not a valid redis target", s);
                 }
             }),
+            "lookup" => TableType::Lookup(Lookup {
+                key_prefix: options.remove("lookup.key_prefix"),
+            }),
             s => {
-                bail!("'{}' is not a valid type; must be `sink`", s);
+                bail!("'{}' is not a valid type; must be `sink` or `lookup`", s);
             }
         };
 
@@ -352,9 +360,7 @@ impl Connector for RedisConnector {
             None,
             name,
             connection_config,
-            RedisTable {
-                connector_type: sink,
-            },
+            RedisTable { connector_type },
             s,
         )
     }
@@ -379,10 +385,22 @@ impl Connector for RedisConnector {
 
         let _ = RedisClient::new(&config)?;
 
+        if format.is_updating() {
+            if let TableType::Target(Target::ListTable { .. }) = &table.connector_type {
+                bail!("Redis list targets do not support updating formats like 'debezium_json'");
+            }
+        }
+
+        let (connection_type, description) = match &table.connector_type {
+            TableType::Target(_) => (ConnectionType::Sink, "RedisSink"),
+            TableType::Lookup(_) => (ConnectionType::Lookup, "RedisLookup"),
+        };
+
         let config = OperatorConfig {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
@@ -392,10 +410,10 @@ impl Connector for RedisConnector {
             id,
             connector: self.name(),
             name: name.to_string(),
-            connection_type: ConnectionType::Sink,
+            connection_type,
             schema,
             config: serde_json::to_string(&config).unwrap(),
-            description: "RedisSink".to_string(),
+            description: description.to_string(),
         })
     }
 
@@ -405,16 +423,25 @@ impl Connector for RedisConnector {
         table: Self::TableT,
         config: OperatorConfig,
     ) -> anyhow::Result<OperatorNode> {
+        let TableType::Target(target) = table.connector_type else {
+            bail!("redis lookup tables can only be used in lookup joins");
+        };
+
         let client = RedisClient::new(&profile)?;
 
         let (tx, cmd_rx) = tokio::sync::mpsc::channel(128);
         let (cmd_tx, rx) = tokio::sync::mpsc::channel(128);
 
+        // updating data is upserted into Redis, with retractions becoming deletes, so values are
+        // written as plain rows rather than as Debezium envelopes
+        let mut format = config.format.expect("redis table must have a format");
+        if let Format::Json(json) = &mut format {
+            json.debezium = false;
+        }
+
         Ok(OperatorNode::from_operator(Box::new(RedisSinkFunc {
-            serializer: ArrowSerializer::new(
-                config.format.expect("redis table must have a format"),
-            ),
-            table,
+            serializer: ArrowSerializer::new(format)?,
+            target,
             client,
             cmd_q: Some((cmd_tx, cmd_rx)),
             tx,
@@ -423,4 +450,21 @@ impl Connector for RedisConnector {
             hash_index: None,
         })))
     }
+
+    fn make_lookup(
+        &self,
+        profile: Self::ProfileT,
+        table: Self::TableT,
+        _: OperatorConfig,
+    ) -> anyhow::Result<Box<dyn LookupConnector>> {
+        let TableType::Lookup(Lookup { key_prefix }) = table.connector_type else {
+            bail!("redis sink tables cannot be used in lookup joins");
+        };
+
+        Ok(Box::new(RedisLookup {
+            client: RedisClient::new(&profile)?,
+            connection: None,
+            key_prefix: key_prefix.unwrap_or_default(),
+        }))
+    }
 }
@@ -0,0 +1,65 @@
+use crate::redis::operator::sink::GeneralConnection;
+use crate::redis::RedisClient;
+use anyhow::anyhow;
+use arroyo_operator::connector::LookupConnector;
+use async_trait::async_trait;
+
+pub struct RedisLookup {
+    pub client: RedisClient,
+    pub connection: Option<GeneralConnection>,
+    pub key_prefix: String,
+}
+
+#[async_trait]
+impl LookupConnector for RedisLookup {
+    fn name(&self) -> String {
+        "RedisLookup".to_string()
+    }
+
+    async fn lookup(&mut self, keys: &[String]) -> anyhow::Result<Vec<Option<Vec<u8>>>> {
+        if self.connection.is_none() {
+            self.connection = Some(
+                self.client
+                    .get_connection()
+                    .await
+                    .map_err(|e| anyhow!("failed to connect to redis: {:?}", e))?,
+            );
+        }
+
+        let connection = self.connection.as_mut().unwrap();
+
+        let keys: Vec<String> = keys
+            .iter()
+            .map(|k| format!("{}{}", self.key_prefix, k))
+            .collect();
+
+        let result: redis::RedisResult<Vec<Option<Vec<u8>>>> =
+            if let GeneralConnection::Clustered(_) = connection {
+                // keys in a batch may live on different nodes, so they are fetched one at a
+                // time rather than with a cross-slot MGET
+                let mut values = Vec::with_capacity(keys.len());
+                for key in &keys {
+                    match redis::cmd("GET").arg(key).query_async(connection).await {
+                        Ok(v) => values.push(v),
+                        Err(e) => {
+                            self.connection = None;
+                            return Err(anyhow!("failed to look up keys in redis: {:?}", e));
+                        }
+                    }
+                }
+                Ok(values)
+            } else {
+                let mut pipeline = redis::pipe();
+                for key in &keys {
+                    pipeline.get(key);
+                }
+                pipeline.query_async(connection).await
+            };
+
+        result.map_err(|e| {
+            // drop the connection so that it's re-established on the next attempt
+            self.connection = None;
+            anyhow!("failed to look up keys in redis: {:?}", e)
+        })
+    }
+}
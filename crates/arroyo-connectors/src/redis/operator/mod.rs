@@ -1 +1,2 @@
+pub mod lookup;
 pub mod sink;
@@ -1,8 +1,10 @@
-use crate::redis::{ListOperation, RedisClient, RedisTable, TableType, Target};
+use crate::redis::{ListOperation, RedisClient, Target};
 use arrow::array::{AsArray, RecordBatch};
+use arrow::datatypes::UInt64Type;
 use arroyo_formats::ser::ArrowSerializer;
 use arroyo_operator::context::{ArrowContext, ErrorReporter};
 use arroyo_operator::operator::ArrowOperator;
+use arroyo_rpc::UPDATING_META_FIELD;
 use arroyo_types::CheckpointBarrier;
 use async_trait::async_trait;
 use redis::aio::{ConnectionLike, ConnectionManager};
@@ -19,7 +21,7 @@ const FLUSH_BYTES: usize = 10 * 1024 * 1024;
 
 pub struct RedisSinkFunc {
     pub serializer: ArrowSerializer,
-    pub table: RedisTable,
+    pub target: Target,
     pub client: RedisClient,
     pub cmd_q: Option<(Sender<u32>, Receiver<RedisCmd>)>,
 
@@ -40,6 +42,29 @@ impl RedisSinkFunc {
 
         key
     }
+
+    async fn delete(&mut self, batch: &RecordBatch, idx: usize) {
+        let cmd = match &self.target {
+            Target::StringTable { key_prefix, .. } => RedisCmd::Del {
+                key: self.make_key(key_prefix, batch, idx),
+            },
+            Target::HashTable {
+                hash_key_prefix, ..
+            } => RedisCmd::HDel {
+                key: self.make_key(hash_key_prefix, batch, idx),
+                field: batch
+                    .column(self.hash_index.expect("no hash index"))
+                    .as_string::<i32>()
+                    .value(idx)
+                    .to_string(),
+            },
+            Target::ListTable { .. } => {
+                unreachable!("list targets do not support updating data")
+            }
+        };
+
+        self.tx.send(cmd).await.expect("Redis writer panicked");
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -61,6 +86,15 @@ pub enum RedisCmd {
         value: Vec<u8>,
     },
 
+    Del {
+        key: String,
+    },
+
+    HDel {
+        key: String,
+        field: String,
+    },
+
     Flush(u32),
 }
 
@@ -164,6 +198,16 @@ impl RedisWriter {
 
                                 self.pipeline.hset(key, field, value);
                             }
+                            Some(RedisCmd::Del { key }) => {
+                                self.size_estimate += key.len();
+
+                                self.pipeline.del(key);
+                            }
+                            Some(RedisCmd::HDel { key, field }) => {
+                                self.size_estimate += key.len() + field.len();
+
+                                self.pipeline.hdel(key, field);
+                            }
                             Some(RedisCmd::Flush(i)) => {
                                 self.flush().await;
                                 if self.tx.send(i).await.is_err() {
@@ -237,19 +281,19 @@ impl ArrowOperator for RedisSinkFunc {
     }
 
     async fn on_start(&mut self, ctx: &mut ArrowContext) {
-        match &self.table.connector_type {
-            TableType::Target(Target::ListTable {
+        match &self.target {
+            Target::ListTable {
                 list_key_column: Some(key),
                 ..
-            })
-            | TableType::Target(Target::StringTable {
+            }
+            | Target::StringTable {
                 key_column: Some(key),
                 ..
-            })
-            | TableType::Target(Target::HashTable {
+            }
+            | Target::HashTable {
                 hash_key_column: Some(key),
                 ..
-            }) => {
+            } => {
                 self.key_index = Some(
                     ctx.in_schemas
                         .get(0)
@@ -266,9 +310,9 @@ impl ArrowOperator for RedisSinkFunc {
             _ => {}
         }
 
-        if let TableType::Target(Target::HashTable {
+        if let Target::HashTable {
             hash_field_column, ..
-        }) = &self.table.connector_type
+        } = &self.target
         {
             self.hash_index = Some(ctx.in_schemas.get(0).expect("no in-schema for redis sink!")
                 .schema
@@ -290,17 +334,15 @@ impl ArrowOperator for RedisSinkFunc {
                         size_estimate: 0,
                         last_flushed: Instant::now(),
                         max_push_keys: HashSet::new(),
-                        behavior: match self.table.connector_type {
-                            TableType::Target(Target::StringTable { ttl_secs, .. }) => {
-                                RedisBehavior::Set {
-                                    ttl: ttl_secs.map(|t| t.get() as usize),
-                                }
-                            }
-                            TableType::Target(Target::ListTable {
+                        behavior: match self.target {
+                            Target::StringTable { ttl_secs, .. } => RedisBehavior::Set {
+                                ttl: ttl_secs.map(|t| t.get() as usize),
+                            },
+                            Target::ListTable {
                                 max_length,
                                 operation,
                                 ..
-                            }) => {
+                            } => {
                                 let max = max_length.map(|x| x.get() as usize);
                                 match operation {
                                     ListOperation::Append => {
@@ -311,7 +353,7 @@ impl ArrowOperator for RedisSinkFunc {
                                     }
                                 }
                             }
-                            TableType::Target(Target::HashTable { .. }) => RedisBehavior::Hash,
+                            Target::HashTable { .. } => RedisBehavior::Hash,
                         },
                     }
                     .start();
@@ -330,40 +372,61 @@ impl ArrowOperator for RedisSinkFunc {
     }
 
     async fn process_batch(&mut self, batch: RecordBatch, _: &mut ArrowContext) {
+        let updating_meta = batch
+            .schema()
+            .index_of(UPDATING_META_FIELD)
+            .ok()
+            .map(|i| batch.column(i).as_struct().clone());
+
         for (i, value) in self.serializer.serialize(&batch).enumerate() {
-            match &self.table.connector_type {
-                TableType::Target(target) => match &target {
-                    Target::StringTable { key_prefix, .. } => {
-                        let key = self.make_key(key_prefix, &batch, i);
-                        self.tx
-                            .send(RedisCmd::Data { key, value })
-                            .await
-                            .expect("Redis writer panicked");
+            if let Some(meta) = &updating_meta {
+                let is_retract = meta.column(0).as_boolean();
+                if is_retract.value(i) {
+                    // a retraction that's immediately followed by an append for the same key is
+                    // an update, which is handled by overwriting the value
+                    let ids = meta.column(1).as_primitive::<UInt64Type>();
+                    let updated = i + 1 < batch.num_rows()
+                        && !is_retract.value(i + 1)
+                        && ids.value(i + 1) == ids.value(i);
+
+                    if !updated {
+                        self.delete(&batch, i).await;
                     }
-                    Target::ListTable { list_prefix, .. } => {
-                        let key = self.make_key(list_prefix, &batch, i);
+                    continue;
+                }
+            }
 
-                        self.tx
-                            .send(RedisCmd::Data { key, value })
-                            .await
-                            .expect("Redis writer panicked");
-                    }
-                    Target::HashTable {
-                        hash_key_prefix, ..
-                    } => {
-                        let key = self.make_key(hash_key_prefix, &batch, i);
-                        let field = batch
-                            .column(self.hash_index.expect("no hash index"))
-                            .as_string::<i32>()
-                            .value(i)
-                            .to_string();
-
-                        self.tx
-                            .send(RedisCmd::HData { key, field, value })
-                            .await
-                            .expect("Redis writer panicked");
-                    }
-                },
+            match &self.target {
+                Target::StringTable { key_prefix, .. } => {
+                    let key = self.make_key(key_prefix, &batch, i);
+                    self.tx
+                        .send(RedisCmd::Data { key, value })
+                        .await
+                        .expect("Redis writer panicked");
+                }
+                Target::ListTable { list_prefix, .. } => {
+                    let key = self.make_key(list_prefix, &batch, i);
+
+                    self.tx
+                        .send(RedisCmd::Data { key, value })
+                        .await
+                        .expect("Redis writer panicked");
+                }
+                Target::HashTable {
+                    hash_key_prefix, ..
+                } => {
+                    let key = self.make_key(hash_key_prefix, &batch, i);
+                    let field = batch
+                        .column(self.hash_index.expect("no hash index"))
+                        .as_string::<i32>()
+                        .value(i)
+                        .to_string();
+
+                    self.tx
+                        .send(RedisCmd::HData { key, field, value })
+                        .await
+                        .expect("Redis writer panicked");
+                }
             };
         }
     }
@@ -137,7 +137,7 @@ impl SSESourceFunc {
             self.format.clone(),
             self.framing.clone(),
             self.bad_data.clone(),
-        );
+        )?;
 
         let mut client = eventsource_client::ClientBuilder::for_url(&self.url).unwrap();
 
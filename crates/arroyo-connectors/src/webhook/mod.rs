@@ -159,6 +159,7 @@ impl Connector for WebhookConnector {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
             rate_limit: None,
+            quota: None,
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
@@ -226,7 +227,7 @@ impl Connector for WebhookConnector {
                 config
                     .format
                     .expect("No format configured for webhook sink"),
-            ),
+            )?,
             last_reported_error_at: Arc::new(Mutex::new(SystemTime::UNIX_EPOCH)),
         })))
     }
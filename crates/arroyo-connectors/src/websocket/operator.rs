@@ -54,12 +54,6 @@ impl SourceOperator for WebsocketSourceFunc {
         if let Some(state) = s.get(&()) {
             self.state = state.clone();
         }
-
-        ctx.initialize_deserializer(
-            self.format.clone(),
-            self.framing.clone(),
-            self.bad_data.clone(),
-        );
     }
 
     async fn run(&mut self, ctx: &mut ArrowContext) -> SourceFinishType {
@@ -132,6 +126,12 @@ impl WebsocketSourceFunc {
     }
 
     async fn run_int(&mut self, ctx: &mut ArrowContext) -> Result<SourceFinishType, UserError> {
+        ctx.initialize_deserializer(
+            self.format.clone(),
+            self.framing.clone(),
+            self.bad_data.clone(),
+        )?;
+
         let uri = match Uri::from_str(&self.url.to_string()) {
             Ok(uri) => uri,
             Err(e) => {
@@ -1,4 +1,4 @@
---! all_jobs : Job(ttl_micros?, state?, start_time?, finish_time?, tasks?, failure_message?, run_id?, pipeline_path?, wasm_path?)
+--! all_jobs : Job(ttl_micros?, state?, start_time?, finish_time?, tasks?, failure_message?, run_id?, pipeline_path?, wasm_path?, restore_savepoint?, pending_savepoint?, autoscaling?, restart_strategy?, next_retry_time?)
 SELECT
     job_configs.id as id,
     job_configs.organization_id as org_id,
@@ -19,11 +19,20 @@ SELECT
     wasm_path,
     job_configs.restart_nonce as config_restart_nonce,
     job_statuses.restart_nonce as status_restart_nonce,
-    restart_mode
+    restart_mode,
+    (SELECT pub_id FROM savepoints WHERE savepoints.id = job_configs.restore_savepoint_id) as restore_savepoint,
+    allow_dropped_state,
+    (SELECT pub_id FROM savepoints
+        WHERE savepoints.job_id = job_configs.id AND savepoints.state = 'pending'
+        ORDER BY savepoints.id
+        LIMIT 1) as pending_savepoint,
+    autoscaling,
+    restart_strategy,
+    next_retry_time
 FROM job_configs
 LEFT JOIN job_statuses ON job_configs.id = job_statuses.id;
 
---! update_job_status (start_time?, finish_time?, tasks?, failure_message?, pipeline_path?, wasm_path?)
+--! update_job_status (start_time?, finish_time?, tasks?, failure_message?, pipeline_path?, wasm_path?, next_retry_time?)
 UPDATE job_statuses
 SET state = :state,
     start_time = :start_time,
@@ -34,7 +43,8 @@ SET state = :state,
     pipeline_path = :pipeline_path,
     wasm_path = :wasm_path,
     run_id = :run_id,
-    restart_nonce = :restart_nonce
+    restart_nonce = :restart_nonce,
+    next_retry_time = :next_retry_time
 WHERE id = :job_id;
 
 --! get_program
@@ -89,3 +99,37 @@ LIMIT 1;
 INSERT INTO job_log_messages (pub_id, job_id, operator_id, task_index, log_level, message, details)
 VALUES (:pub_id, :job_id, :operator_id, :task_index, :log_level, :message, :details)
 RETURNING id;
+
+--! create_controller_log_message
+INSERT INTO job_log_messages (pub_id, job_id, log_level, message, details)
+VALUES (:pub_id, :job_id, :log_level, :message, :details)
+RETURNING id;
+
+--! mark_savepoint_ready
+UPDATE savepoints
+SET
+    state = 'ready',
+    epoch = :epoch,
+    operators = :operators,
+    finish_time = :finish_time
+WHERE pub_id = :pub_id;
+
+--! mark_savepoint_failed
+UPDATE savepoints
+SET
+    state = 'failed',
+    failure_message = :failure_message,
+    finish_time = :finish_time
+WHERE pub_id = :pub_id;
+
+--! update_parallelism_overrides
+UPDATE job_configs
+SET
+    parallelism_overrides = :parallelism_overrides,
+    updated_at = :updated_at
+WHERE id = :job_id;
+
+--! get_secrets
+SELECT name, encrypted_value
+FROM secrets
+WHERE organization_id = :organization_id;
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use arroyo_rpc::api_types::pipelines::AutoscalingConfig;
+use arroyo_rpc::public_ids::{generate_id, IdTypes};
+use arroyo_types::{BUSY_TIME, METRICS_BACKEND_ENV, TX_QUEUE_REM, TX_QUEUE_SIZE};
+use base64::engine::general_purpose;
+use base64::Engine;
+use deadpool_postgres::Pool;
+use lazy_static::lazy_static;
+use prometheus_http_query::Client;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::metrics::MetricsStore;
+use crate::queries::controller_queries;
+use crate::types::public::LogLevel;
+
+// the fraction of time we want the busiest operator to spend processing after rescaling
+const TARGET_BUSY: f64 = 0.7;
+
+// scale up once an operator that isn't waiting on its outputs is busier than this
+const SCALE_UP_BUSY: f64 = 0.85;
+
+// scale down once every operator is less busy than this
+const SCALE_DOWN_BUSY: f64 = 0.3;
+
+// operators whose output queues are fuller than this are blocked by a slower operator downstream,
+// so their busy time (which includes time spent waiting to send) doesn't tell us anything
+const BACKPRESSURE_THRESHOLD: f64 = 0.5;
+
+const METRICS_WINDOW: &str = "1m";
+const METRICS_WINDOW_DURATION: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref METRICS_CLIENT: Client = {
+        let mut headers = HeaderMap::new();
+        if let Ok(basic_auth) = std::env::var("PROM_AUTH") {
+            headers.append(
+                AUTHORIZATION,
+                HeaderValue::from_str(
+                    &("Basic ".to_owned() + &general_purpose::STANDARD.encode(basic_auth)),
+                )
+                .unwrap(),
+            );
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap();
+
+        let prometheus_endpoint =
+            std::env::var("PROM_ENDPOINT").unwrap_or_else(|_| "http://localhost:9090".to_string());
+        Client::from(client, &prometheus_endpoint).unwrap()
+    };
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct OperatorLoad {
+    // average fraction of time the operator's subtasks spend processing batches
+    pub busy: f64,
+    // the fullest of the operator's output queues, from 0 (empty) to 1 (full)
+    pub backpressure: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScalingDecision {
+    pub parallelism: usize,
+    pub reason: String,
+}
+
+fn busy_query(job_id: &str, run_id: i64) -> String {
+    format!(
+        "rate({}{{job_id=\"{}\",run_id=\"{}\"}}[{}]) / 1000000",
+        BUSY_TIME, job_id, run_id, METRICS_WINDOW
+    )
+}
+
+fn backpressure_query(job_id: &str, run_id: i64) -> String {
+    let tx_queue_size = format!(
+        "{}{{job_id=\"{}\",run_id=\"{}\"}}",
+        TX_QUEUE_SIZE, job_id, run_id
+    );
+    let tx_queue_rem = format!(
+        "{}{{job_id=\"{}\",run_id=\"{}\"}}",
+        TX_QUEUE_REM, job_id, run_id
+    );
+    format!("1 - (({} + 1) / ({} + 1))", tx_queue_rem, tx_queue_size)
+}
+
+/// Fetches the current busy time and backpressure of each operator in the job from prometheus
+pub async fn operator_loads(job_id: &str, run_id: i64) -> Result<HashMap<String, OperatorLoad>> {
+    let (busy, backpressure) = tokio::try_join!(
+        METRICS_CLIENT.query(busy_query(job_id, run_id)).get(),
+        METRICS_CLIENT
+            .query(backpressure_query(job_id, run_id))
+            .get(),
+    )?;
+
+    let mut busy_by_operator: HashMap<String, Vec<f64>> = HashMap::new();
+    for v in busy
+        .data()
+        .as_vector()
+        .ok_or_else(|| anyhow!("busy time query did not return a vector"))?
+    {
+        if let Some(operator_id) = v.metric().get("operator_id") {
+            busy_by_operator
+                .entry(operator_id.clone())
+                .or_default()
+                .push(v.sample().value());
+        }
+    }
+
+    let mut loads: HashMap<String, OperatorLoad> = busy_by_operator
+        .into_iter()
+        .map(|(operator_id, values)| {
+            let busy = values.iter().sum::<f64>() / values.len() as f64;
+            (
+                operator_id,
+                OperatorLoad {
+                    busy,
+                    backpressure: 0.0,
+                },
+            )
+        })
+        .collect();
+
+    for v in backpressure
+        .data()
+        .as_vector()
+        .ok_or_else(|| anyhow!("backpressure query did not return a vector"))?
+    {
+        if let Some(operator_id) = v.metric().get("operator_id") {
+            let load = loads.entry(operator_id.clone()).or_default();
+            load.backpressure = load.backpressure.max(v.sample().value());
+        }
+    }
+
+    Ok(loads)
+}
+
+/// Decides whether a job whose largest operator runs at `current` parallelism should be rescaled,
+/// given the load on each of its operators
+pub fn decide(
+    current: usize,
+    loads: &HashMap<String, OperatorLoad>,
+    config: &AutoscalingConfig,
+) -> Option<ScalingDecision> {
+    let min = (config.min_parallelism as usize).max(1);
+    let max = (config.max_parallelism as usize).max(min);
+
+    let target =
+        |busy: f64| ((current as f64 * busy / TARGET_BUSY).ceil() as usize).clamp(min, max);
+
+    // the bottleneck is the busiest operator that is not itself blocked on a downstream operator
+    let bottleneck = loads
+        .iter()
+        .filter(|(_, l)| l.backpressure < BACKPRESSURE_THRESHOLD)
+        .max_by(|(_, a), (_, b)| a.busy.total_cmp(&b.busy));
+
+    let busiest = loads.values().map(|l| l.busy).fold(0.0, f64::max);
+
+    let (parallelism, reason) = match bottleneck {
+        Some((operator_id, load)) if load.busy > SCALE_UP_BUSY => (
+            target(load.busy),
+            format!(
+                "operator {} was busy {:.0}% of the time with {:.0}% backpressure",
+                operator_id,
+                load.busy * 100.0,
+                load.backpressure * 100.0
+            ),
+        ),
+        _ if !loads.is_empty() && busiest < SCALE_DOWN_BUSY => (
+            target(busiest),
+            format!(
+                "the busiest operator was only busy {:.0}% of the time",
+                busiest * 100.0
+            ),
+        ),
+        _ if current < min || current > max => (
+            current.clamp(min, max),
+            format!(
+                "parallelism {} is outside of the configured bounds of {} to {}",
+                current, min, max
+            ),
+        ),
+        _ => return None,
+    };
+
+    (parallelism != current).then_some(ScalingDecision {
+        parallelism,
+        reason,
+    })
+}
+
+/// Checks the load on a running job and, if it should be rescaled, updates its parallelism
+/// overrides; the rescale itself is performed by the controller once it sees the config change.
+/// Loads are read from prometheus unless the controller metrics backend is configured, in which
+/// case they come from the metrics the controller collects from worker heartbeats.
+pub async fn autoscale(
+    job_id: &str,
+    run_id: i64,
+    operator_parallelism: &HashMap<String, usize>,
+    config: &AutoscalingConfig,
+    metrics: &Mutex<MetricsStore>,
+    pool: &Pool,
+) -> Result<()> {
+    let Some(current) = operator_parallelism.values().max().cloned() else {
+        bail!("job has no operators");
+    };
+
+    let loads = if std::env::var(METRICS_BACKEND_ENV).as_deref() == Ok("controller") {
+        metrics
+            .lock()
+            .unwrap()
+            .operator_loads(job_id, METRICS_WINDOW_DURATION)
+    } else {
+        operator_loads(job_id, run_id).await?
+    };
+
+    let Some(decision) = decide(current, &loads, config) else {
+        return Ok(());
+    };
+
+    info!(
+        message = "autoscaling job",
+        job_id,
+        from = current,
+        to = decision.parallelism,
+        reason = decision.reason
+    );
+
+    // operators are scaled in proportion to the largest, so that operators that were configured
+    // with a lower parallelism (like sources limited by their partitions) stay smaller than the rest
+    // of the job
+    let overrides: HashMap<&String, usize> = operator_parallelism
+        .iter()
+        .map(|(op, p)| (op, (p * decision.parallelism).div_ceil(current)))
+        .collect();
+
+    let c = pool.get().await?;
+    controller_queries::update_parallelism_overrides()
+        .bind(
+            &c,
+            &serde_json::to_value(overrides)?,
+            &OffsetDateTime::now_utc(),
+            &job_id,
+        )
+        .await?;
+
+    controller_queries::create_controller_log_message()
+        .bind(
+            &c,
+            &generate_id(IdTypes::JobLogMessage),
+            &job_id,
+            &LogLevel::info,
+            &format!(
+                "Autoscaler rescaling job from parallelism {} to {}",
+                current, decision.parallelism
+            ),
+            &format!("Rescaling because {}", decision.reason),
+        )
+        .one()
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config() -> AutoscalingConfig {
+        AutoscalingConfig {
+            enabled: true,
+            min_parallelism: 1,
+            max_parallelism: 8,
+            cooldown_secs: 300,
+        }
+    }
+
+    fn loads(loads: &[(&str, f64, f64)]) -> HashMap<String, OperatorLoad> {
+        loads
+            .iter()
+            .map(|(op, busy, backpressure)| {
+                (
+                    op.to_string(),
+                    OperatorLoad {
+                        busy: *busy,
+                        backpressure: *backpressure,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scales_up_bottleneck() {
+        // the source is busy because it's blocked on the slow window
+        let loads = loads(&[
+            ("source", 0.95, 0.9),
+            ("window", 0.95, 0.0),
+            ("sink", 0.2, 0.0),
+        ]);
+
+        let decision = decide(2, &loads, &config()).unwrap();
+        assert_eq!(decision.parallelism, 3);
+        assert!(decision.reason.contains("window"));
+    }
+
+    #[test]
+    fn test_ignores_backpressured_operators() {
+        let loads = loads(&[("source", 0.95, 0.9), ("sink", 0.5, 0.0)]);
+        assert_eq!(decide(2, &loads, &config()), None);
+    }
+
+    #[test]
+    fn test_scales_down_idle_job() {
+        let loads = loads(&[("source", 0.1, 0.0), ("sink", 0.2, 0.0)]);
+        assert_eq!(decide(4, &loads, &config()).unwrap().parallelism, 2);
+    }
+
+    #[test]
+    fn test_respects_bounds() {
+        let mut config = config();
+        config.max_parallelism = 4;
+
+        let busy = loads(&[("window", 1.0, 0.0)]);
+        assert_eq!(decide(4, &busy, &config), None);
+
+        config.min_parallelism = 3;
+        let idle = loads(&[("window", 0.05, 0.0)]);
+        assert_eq!(decide(4, &idle, &config).unwrap().parallelism, 3);
+
+        let moderate = loads(&[("window", 0.5, 0.0)]);
+        assert_eq!(decide(2, &moderate, &config).unwrap().parallelism, 3);
+    }
+}
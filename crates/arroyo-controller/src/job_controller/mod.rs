@@ -25,6 +25,7 @@ use tonic::{transport::Channel, Request};
 use tracing::{error, info, warn};
 
 use crate::types::public::CheckpointState as DbCheckpointState;
+use crate::types::public::LogLevel;
 use crate::{queries::controller_queries, JobConfig, JobMessage, RunningMessage};
 use arroyo_state::committing_state::CommittingState;
 
@@ -522,11 +523,22 @@ impl RunningJobModel {
     }
 }
 
+#[derive(Debug, Clone)]
+struct PendingSavepoint {
+    id: String,
+    // the epoch of the checkpoint the savepoint will be taken from, once it has been started
+    epoch: Option<u32>,
+}
+
 pub struct JobController {
     pool: Pool,
     config: JobConfig,
     model: RunningJobModel,
     cleanup_task: Option<JoinHandle<anyhow::Result<u32>>>,
+    requested_savepoint: Option<String>,
+    savepoint: Option<PendingSavepoint>,
+    savepoint_task: Option<JoinHandle<anyhow::Result<()>>>,
+    last_savepoint: Option<String>,
 }
 
 impl std::fmt::Debug for JobController {
@@ -535,6 +547,7 @@ impl std::fmt::Debug for JobController {
             .field("config", &self.config)
             .field("model", &self.model)
             .field("cleaning", &self.cleanup_task.is_some())
+            .field("savepoint", &self.savepoint)
             .finish()
     }
 }
@@ -595,8 +608,12 @@ impl JobController {
                 operator_parallelism: program.tasks_per_operator(),
                 program,
             },
+            requested_savepoint: config.pending_savepoint.clone(),
             config,
             cleanup_task: None,
+            savepoint: None,
+            savepoint_task: None,
+            last_savepoint: None,
         }
     }
 
@@ -652,11 +669,69 @@ impl JobController {
         }
 
         if let Some(new_epoch) = self.model.cleanup_needed() {
-            if self.cleanup_task.is_none() && self.model.checkpoint_state.is_none() {
+            if self.cleanup_task.is_none()
+                && self.savepoint_task.is_none()
+                && self.model.checkpoint_state.is_none()
+            {
                 self.cleanup_task = Some(self.start_cleanup(new_epoch));
             }
         }
 
+        // check on savepoints, which are taken from the first checkpoint started after they're
+        // requested
+        if self.savepoint_task.is_some() && self.savepoint_task.as_ref().unwrap().is_finished() {
+            let task = self.savepoint_task.take().unwrap();
+            let savepoint = self.savepoint.take().unwrap();
+
+            match task.await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    error!(
+                        message = "failed to record savepoint",
+                        job_id = self.config.id,
+                        savepoint_id = savepoint.id,
+                        error = format!("{:?}", e)
+                    );
+                }
+                Err(e) => {
+                    error!(
+                        message = "savepoint panicked",
+                        job_id = self.config.id,
+                        savepoint_id = savepoint.id,
+                        error = format!("{:?}", e)
+                    );
+                }
+            }
+
+            self.last_savepoint = Some(savepoint.id);
+        }
+
+        if self.savepoint.is_none() {
+            if let Some(id) = self.requested_savepoint.take() {
+                info!(
+                    message = "starting savepoint",
+                    job_id = self.config.id,
+                    savepoint_id = id
+                );
+                self.savepoint = Some(PendingSavepoint { id, epoch: None });
+            }
+        }
+
+        if let Some(savepoint) = self.savepoint.clone() {
+            if self.savepoint_task.is_none() && self.model.checkpoint_state.is_none() {
+                match savepoint.epoch {
+                    Some(epoch) => {
+                        self.savepoint_task = Some(self.start_savepoint(savepoint.id, epoch));
+                    }
+                    None if self.cleanup_task.is_none() => {
+                        self.checkpoint(false).await?;
+                        self.savepoint.as_mut().unwrap().epoch = Some(self.model.epoch);
+                    }
+                    None => {}
+                }
+            }
+        }
+
         // check on checkpointing
         if self.model.checkpoint_state.is_some() {
             self.model.finish_checkpoint_if_done(&self.pool).await?;
@@ -697,6 +772,53 @@ impl JobController {
         self.model.all_tasks_finished()
     }
 
+    /// Gives up on an in-progress checkpoint, marking it as failed so that the job will be
+    /// restored from the previous completed epoch. Checkpoints that have been written and are
+    /// only waiting on commits are left alone, as those commits are replayed on restore.
+    ///
+    /// Returns the epoch of the abandoned checkpoint, if there was one.
+    pub async fn abandon_checkpoint(&mut self) -> anyhow::Result<Option<u32>> {
+        let Some(CheckpointingOrCommittingState::Checkpointing(checkpointing)) =
+            &self.model.checkpoint_state
+        else {
+            return Ok(None);
+        };
+
+        let epoch = self.model.epoch;
+        warn!(
+            message = "abandoning in-progress checkpoint",
+            job_id = self.config.id,
+            epoch
+        );
+
+        RunningJobModel::update_checkpoint_in_db(
+            checkpointing,
+            &self.pool,
+            DbCheckpointState::failed,
+        )
+        .await?;
+
+        let c = self.pool.get().await?;
+        controller_queries::create_controller_log_message()
+            .bind(
+                &c,
+                &generate_id(IdTypes::JobLogMessage),
+                &self.config.id,
+                &LogLevel::warn,
+                &format!("Checkpoint {} was abandoned", epoch),
+                &format!(
+                    "The job was force-stopped before checkpoint {} completed; it will be restored from the previous completed checkpoint",
+                    epoch
+                ),
+            )
+            .one()
+            .await?;
+
+        self.model.checkpoint_state = None;
+
+        Ok(Some(epoch))
+    }
+
     pub async fn checkpoint_finished(&mut self) -> anyhow::Result<bool> {
         if self.model.checkpoint_state.is_some() {
             self.model.finish_checkpoint_if_done(&self.pool).await?;
@@ -752,6 +874,22 @@ impl JobController {
         }
     }
 
+    /// Requests a savepoint of the job; this is a no-op if the savepoint has already been taken
+    /// or is in progress
+    pub fn request_savepoint(&mut self, savepoint_id: &str) {
+        let in_progress = self
+            .savepoint
+            .as_ref()
+            .map(|s| s.id == savepoint_id)
+            .unwrap_or(false);
+
+        if in_progress || self.last_savepoint.as_deref() == Some(savepoint_id) {
+            return;
+        }
+
+        self.requested_savepoint = Some(savepoint_id.to_string());
+    }
+
     pub fn operator_parallelism(&self, op: &str) -> Option<usize> {
         self.model.operator_parallelism.get(op).cloned()
     }
@@ -790,4 +928,63 @@ impl JobController {
             Ok(new_min)
         })
     }
+
+    fn start_savepoint(
+        &mut self,
+        savepoint_id: String,
+        epoch: u32,
+    ) -> JoinHandle<anyhow::Result<()>> {
+        let job_id = self.config.id.clone();
+        let pool = self.pool.clone();
+
+        info!(message = "Writing savepoint", job_id, savepoint_id, epoch);
+        let start = Instant::now();
+
+        tokio::spawn(async move {
+            let result = StateBackend::write_savepoint(&job_id, epoch, &savepoint_id).await;
+
+            let c = pool.get().await?;
+            match result {
+                Ok(operators) => {
+                    controller_queries::mark_savepoint_ready()
+                        .bind(
+                            &c,
+                            &(epoch as i32),
+                            &serde_json::to_value(&operators).unwrap(),
+                            &OffsetDateTime::now_utc(),
+                            &savepoint_id,
+                        )
+                        .await?;
+
+                    info!(
+                        message = "Finished savepoint",
+                        job_id,
+                        savepoint_id,
+                        epoch,
+                        duration = start.elapsed().as_secs_f32()
+                    );
+                }
+                Err(e) => {
+                    error!(
+                        message = "Failed to write savepoint",
+                        job_id,
+                        savepoint_id,
+                        epoch,
+                        error = format!("{:?}", e)
+                    );
+
+                    controller_queries::mark_savepoint_failed()
+                        .bind(
+                            &c,
+                            &format!("{:?}", e),
+                            &OffsetDateTime::now_utc(),
+                            &savepoint_id,
+                        )
+                        .await?;
+                }
+            }
+
+            Ok(())
+        })
+    }
 }
@@ -3,13 +3,14 @@
 #![allow(clippy::type_complexity)]
 
 use anyhow::Result;
+use arroyo_rpc::api_types::pipelines::{AutoscalingConfig, RestartStrategy};
 use arroyo_rpc::grpc::controller_grpc_server::{ControllerGrpc, ControllerGrpcServer};
 use arroyo_rpc::grpc::{
     GrpcOutputSubscription, HeartbeatNodeReq, HeartbeatNodeResp, HeartbeatReq, HeartbeatResp,
-    OutputData, RegisterNodeReq, RegisterNodeResp, RegisterWorkerReq, RegisterWorkerResp,
-    TaskCheckpointCompletedReq, TaskCheckpointCompletedResp, TaskFailedReq, TaskFailedResp,
-    TaskFinishedReq, TaskFinishedResp, TaskStartedReq, TaskStartedResp, WorkerFinishedReq,
-    WorkerFinishedResp,
+    JobMetricsReq, JobMetricsResp, OutputData, RegisterNodeReq, RegisterNodeResp,
+    RegisterWorkerReq, RegisterWorkerResp, TaskCheckpointCompletedReq, TaskCheckpointCompletedResp,
+    TaskFailedReq, TaskFailedResp, TaskFinishedReq, TaskFinishedResp, TaskStartedReq,
+    TaskStartedResp, WorkerFinishedReq, WorkerFinishedResp,
 };
 use arroyo_rpc::grpc::{
     SinkDataReq, SinkDataResp, TaskCheckpointEventReq, TaskCheckpointEventResp, WorkerErrorReq,
@@ -33,13 +34,17 @@ use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 use tracing::{debug, info, warn};
 
+mod autoscaler;
 //pub mod compiler;
 pub mod job_controller;
+mod metrics;
+mod restarts;
 pub mod schedulers;
 mod states;
 
 include!(concat!(env!("OUT_DIR"), "/controller-sql.rs"));
 
+use crate::metrics::MetricsStore;
 use crate::schedulers::{NodeScheduler, ProcessScheduler, Scheduler};
 use types::public::LogLevel;
 use types::public::{RestartMode, StopMode};
@@ -64,7 +69,7 @@ lazy_static! {
     .unwrap();
 }
 
-#[derive(Eq, PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct JobConfig {
     id: String,
     organization_id: String,
@@ -76,6 +81,11 @@ pub struct JobConfig {
     parallelism_overrides: HashMap<String, usize>,
     restart_nonce: i32,
     restart_mode: RestartMode,
+    restore_savepoint: Option<String>,
+    allow_dropped_state: bool,
+    pending_savepoint: Option<String>,
+    autoscaling: Option<AutoscalingConfig>,
+    restart_strategy: RestartStrategy,
 }
 
 #[derive(Clone, Debug)]
@@ -91,6 +101,7 @@ pub struct JobStatus {
     pipeline_path: Option<String>,
     wasm_path: Option<String>,
     restart_nonce: i32,
+    next_retry_time: Option<OffsetDateTime>,
 }
 
 impl JobStatus {
@@ -109,6 +120,7 @@ impl JobStatus {
                 &self.wasm_path,
                 &self.run_id,
                 &self.restart_nonce,
+                &self.next_retry_time,
                 &self.id,
             )
             .await
@@ -170,6 +182,7 @@ pub struct ControllerServer {
     job_state: Arc<tokio::sync::Mutex<HashMap<String, StateMachine>>>,
     data_txs: Arc<tokio::sync::Mutex<HashMap<String, Vec<Sender<Result<OutputData, Status>>>>>>,
     scheduler: Arc<dyn Scheduler>,
+    metrics: Arc<std::sync::Mutex<MetricsStore>>,
     db: Pool,
 }
 
@@ -208,6 +221,11 @@ impl ControllerGrpc for ControllerServer {
     ) -> Result<Response<HeartbeatResp>, Status> {
         let req = request.into_inner();
 
+        self.metrics
+            .lock()
+            .unwrap()
+            .record(&req.job_id, SystemTime::now(), req.metrics);
+
         self.send_to_job_queue(
             &req.job_id,
             JobMessage::RunningMessage(RunningMessage::WorkerHeartbeat {
@@ -424,6 +442,16 @@ impl ControllerGrpc for ControllerServer {
             Err(err) => Err(Status::from_error(Box::new(err))),
         }
     }
+
+    async fn get_job_metrics(
+        &self,
+        request: Request<JobMetricsReq>,
+    ) -> Result<Response<JobMetricsResp>, Status> {
+        let req = request.into_inner();
+        let operators = self.metrics.lock().unwrap().job_metrics(&req.job_id);
+
+        Ok(Response::new(JobMetricsResp { operators }))
+    }
 }
 
 impl ControllerServer {
@@ -451,6 +479,7 @@ impl ControllerServer {
             scheduler,
             data_txs: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             job_state: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            metrics: Arc::new(std::sync::Mutex::new(MetricsStore::default())),
             db: pool,
         }
     }
@@ -480,6 +509,7 @@ impl ControllerServer {
         let db = self.db.clone();
         let jobs = Arc::clone(&self.job_state);
         let scheduler = Arc::clone(&self.scheduler);
+        let metrics = Arc::clone(&self.metrics);
 
         let token = guard.token();
 
@@ -512,6 +542,14 @@ impl ControllerServer {
                             .collect(),
                         restart_nonce: p.config_restart_nonce,
                         restart_mode: p.restart_mode,
+                        restore_savepoint: p.restore_savepoint,
+                        allow_dropped_state: p.allow_dropped_state,
+                        pending_savepoint: p.pending_savepoint,
+                        autoscaling: p.autoscaling.and_then(|a| serde_json::from_value(a).ok()),
+                        restart_strategy: p
+                            .restart_strategy
+                            .and_then(|r| serde_json::from_value(r).ok())
+                            .unwrap_or_default(),
                     };
 
                     let mut jobs = jobs.lock().await;
@@ -528,6 +566,7 @@ impl ControllerServer {
                         pipeline_path: p.pipeline_path,
                         wasm_path: p.wasm_path,
                         restart_nonce: p.status_restart_nonce,
+                        next_retry_time: p.next_retry_time,
                     };
 
                     if let Some(sm) = jobs.get_mut(&config.id) {
@@ -540,6 +579,7 @@ impl ControllerServer {
                                 status,
                                 db.clone(),
                                 scheduler.clone(),
+                                metrics.clone(),
                                 guard.clone_temporary(),
                             )
                             .await,
@@ -0,0 +1,363 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+use arroyo_rpc::grpc::{
+    MetricPoint, MetricSeries, OperatorMetricSeries, SubtaskMetricSeries, SubtaskMetricValues,
+};
+use arroyo_types::{
+    to_micros, BUSY_TIME, BYTES_RECV, BYTES_SENT, MESSAGES_RECV, MESSAGES_SENT, TX_QUEUE_REM,
+    TX_QUEUE_SIZE,
+};
+
+use crate::autoscaler::OperatorLoad;
+
+// how long snapshots are kept, which matches the window of metrics shown in the web ui
+const RETENTION: Duration = Duration::from_secs(5 * 60);
+
+// bounds the number of snapshots kept per subtask, in case workers heartbeat more often than usual
+const MAX_SNAPSHOTS: usize = 5 * 60;
+
+// series that are computed as per-second rates of counters reported by the workers
+const RATE_SERIES: [(&str, &str); 4] = [
+    ("bytes_recv", BYTES_RECV),
+    ("bytes_sent", BYTES_SENT),
+    ("messages_recv", MESSAGES_RECV),
+    ("messages_sent", MESSAGES_SENT),
+];
+
+const BACKPRESSURE_SERIES: &str = "backpressure";
+
+#[derive(Debug)]
+struct Snapshot {
+    time: SystemTime,
+    values: HashMap<String, f64>,
+}
+
+impl Snapshot {
+    fn backpressure(&self) -> Option<f64> {
+        let rem = self.values.get(TX_QUEUE_REM)?;
+        let size = self.values.get(TX_QUEUE_SIZE)?;
+        // add 1 to each value to account for uninitialized queues, which report 0
+        Some(1.0 - ((rem + 1.0) / (size + 1.0)))
+    }
+}
+
+/// A bounded in-memory time-series store for the task metrics that workers report with their
+/// heartbeats, which allows operator metrics to be served without an external Prometheus
+#[derive(Debug, Default)]
+pub struct MetricsStore {
+    jobs: HashMap<String, HashMap<(String, u32), VecDeque<Snapshot>>>,
+}
+
+impl MetricsStore {
+    pub fn record(&mut self, job_id: &str, time: SystemTime, metrics: Vec<SubtaskMetricValues>) {
+        let job = self.jobs.entry(job_id.to_string()).or_default();
+
+        for m in metrics {
+            let snapshots = job.entry((m.operator_id, m.subtask_index)).or_default();
+
+            if snapshots.back().is_some_and(|s| s.time >= time) {
+                continue;
+            }
+
+            snapshots.push_back(Snapshot {
+                time,
+                values: m.values,
+            });
+
+            if snapshots.len() > MAX_SNAPSHOTS {
+                snapshots.pop_front();
+            }
+        }
+
+        self.expire(time);
+    }
+
+    fn expire(&mut self, now: SystemTime) {
+        let cutoff = now.checked_sub(RETENTION).unwrap_or(SystemTime::UNIX_EPOCH);
+
+        self.jobs.retain(|_, subtasks| {
+            subtasks.retain(|_, snapshots| {
+                while snapshots.front().is_some_and(|s| s.time < cutoff) {
+                    snapshots.pop_front();
+                }
+                !snapshots.is_empty()
+            });
+            !subtasks.is_empty()
+        });
+    }
+
+    /// Returns the metric series for each of the job's operators over the retention window
+    pub fn job_metrics(&mut self, job_id: &str) -> Vec<OperatorMetricSeries> {
+        self.expire(SystemTime::now());
+
+        let Some(subtasks) = self.jobs.get(job_id) else {
+            return vec![];
+        };
+
+        let mut operators: BTreeMap<&str, BTreeMap<&str, Vec<SubtaskMetricSeries>>> =
+            BTreeMap::new();
+
+        for ((operator_id, index), snapshots) in subtasks {
+            let series = operators.entry(operator_id).or_default();
+
+            for (name, metric) in RATE_SERIES {
+                let points: Vec<_> = snapshots
+                    .iter()
+                    .zip(snapshots.iter().skip(1))
+                    .filter_map(|(prev, next)| {
+                        let elapsed = next.time.duration_since(prev.time).ok()?.as_secs_f64();
+                        let delta = next.values.get(metric)? - prev.values.get(metric)?;
+                        // counters are reset when the job restarts
+                        (elapsed > 0.0 && delta >= 0.0).then(|| MetricPoint {
+                            time: to_micros(next.time),
+                            value: delta / elapsed,
+                        })
+                    })
+                    .collect();
+
+                if !points.is_empty() {
+                    series.entry(name).or_default().push(SubtaskMetricSeries {
+                        index: *index,
+                        points,
+                    });
+                }
+            }
+
+            let points: Vec<_> = snapshots
+                .iter()
+                .filter_map(|s| {
+                    Some(MetricPoint {
+                        time: to_micros(s.time),
+                        value: s.backpressure()?,
+                    })
+                })
+                .collect();
+
+            if !points.is_empty() {
+                series
+                    .entry(BACKPRESSURE_SERIES)
+                    .or_default()
+                    .push(SubtaskMetricSeries {
+                        index: *index,
+                        points,
+                    });
+            }
+        }
+
+        operators
+            .into_iter()
+            .map(|(operator_id, series)| OperatorMetricSeries {
+                operator_id: operator_id.to_string(),
+                metrics: series
+                    .into_iter()
+                    .map(|(name, mut subtasks)| {
+                        subtasks.sort_by_key(|s| s.index);
+                        MetricSeries {
+                            name: name.to_string(),
+                            subtasks,
+                        }
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Returns the busy time and backpressure of each of the job's operators over the most recent
+    /// `window`, matching what the autoscaler otherwise queries from prometheus
+    pub fn operator_loads(
+        &mut self,
+        job_id: &str,
+        window: Duration,
+    ) -> HashMap<String, OperatorLoad> {
+        let now = SystemTime::now();
+        self.expire(now);
+
+        let Some(subtasks) = self.jobs.get(job_id) else {
+            return HashMap::new();
+        };
+
+        let start = now.checked_sub(window).unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let mut busy_by_operator: HashMap<&str, Vec<f64>> = HashMap::new();
+        let mut loads: HashMap<String, OperatorLoad> = HashMap::new();
+
+        for ((operator_id, _), snapshots) in subtasks {
+            let recent: Vec<_> = snapshots.iter().filter(|s| s.time >= start).collect();
+            let Some(latest) = recent.last() else {
+                // subtasks that have stopped reporting, like those of a previous run
+                continue;
+            };
+
+            let (busy_micros, elapsed) = recent
+                .iter()
+                .zip(recent.iter().skip(1))
+                .filter_map(|(prev, next)| {
+                    let elapsed = next.time.duration_since(prev.time).ok()?.as_secs_f64();
+                    let delta = next.values.get(BUSY_TIME)? - prev.values.get(BUSY_TIME)?;
+                    // counters are reset when the job restarts
+                    (delta >= 0.0).then_some((delta, elapsed))
+                })
+                .fold((0.0, 0.0), |(d, e), (delta, elapsed)| {
+                    (d + delta, e + elapsed)
+                });
+
+            if elapsed > 0.0 {
+                busy_by_operator
+                    .entry(operator_id)
+                    .or_default()
+                    .push(busy_micros / 1_000_000.0 / elapsed);
+            }
+
+            if let Some(backpressure) = latest.backpressure() {
+                let load = loads.entry(operator_id.clone()).or_default();
+                load.backpressure = load.backpressure.max(backpressure);
+            }
+        }
+
+        for (operator_id, values) in busy_by_operator {
+            loads.entry(operator_id.to_string()).or_default().busy =
+                values.iter().sum::<f64>() / values.len() as f64;
+        }
+
+        loads
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn values(operator_id: &str, messages_recv: f64, rem: f64) -> SubtaskMetricValues {
+        SubtaskMetricValues {
+            operator_id: operator_id.to_string(),
+            subtask_index: 0,
+            values: [
+                (MESSAGES_RECV.to_string(), messages_recv),
+                (TX_QUEUE_REM.to_string(), rem),
+                (TX_QUEUE_SIZE.to_string(), 99.0),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn test_rates_and_backpressure() {
+        let mut store = MetricsStore::default();
+        let start = SystemTime::now() - Duration::from_secs(20);
+
+        store.record("job", start, vec![values("op", 100.0, 99.0)]);
+        store.record(
+            "job",
+            start + Duration::from_secs(5),
+            vec![values("op", 600.0, 49.0)],
+        );
+        // the counter was reset by a restart
+        store.record(
+            "job",
+            start + Duration::from_secs(10),
+            vec![values("op", 50.0, 49.0)],
+        );
+
+        let metrics = store.job_metrics("job");
+        assert_eq!(metrics.len(), 1);
+
+        let series: HashMap<_, _> = metrics[0]
+            .metrics
+            .iter()
+            .map(|m| (m.name.as_str(), &m.subtasks[0].points))
+            .collect();
+
+        let recv = series.get("messages_recv").unwrap();
+        assert_eq!(recv.len(), 1);
+        assert_eq!(recv[0].value, 100.0);
+
+        let backpressure = series.get("backpressure").unwrap();
+        assert_eq!(backpressure.len(), 3);
+        assert_eq!(backpressure[0].value, 0.0);
+        assert_eq!(backpressure[1].value, 0.5);
+
+        assert!(store.job_metrics("other").is_empty());
+    }
+
+    fn load_values(subtask_index: u32, busy_micros: f64, rem: f64) -> SubtaskMetricValues {
+        SubtaskMetricValues {
+            operator_id: "op".to_string(),
+            subtask_index,
+            values: [
+                (BUSY_TIME.to_string(), busy_micros),
+                (TX_QUEUE_REM.to_string(), rem),
+                (TX_QUEUE_SIZE.to_string(), 99.0),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn test_operator_loads() {
+        let mut store = MetricsStore::default();
+        let window = Duration::from_secs(60);
+        let start = SystemTime::now() - Duration::from_secs(30);
+
+        // this snapshot is outside of the window, so doesn't contribute
+        store.record(
+            "job",
+            start - Duration::from_secs(60),
+            vec![load_values(0, 0.0, 99.0), load_values(1, 0.0, 99.0)],
+        );
+        store.record(
+            "job",
+            start,
+            vec![
+                load_values(0, 50_000_000.0, 99.0),
+                load_values(1, 0.0, 99.0),
+            ],
+        );
+        // subtask 0 is busy the whole time, and subtask 1 half of the time
+        store.record(
+            "job",
+            start + Duration::from_secs(10),
+            vec![
+                load_values(0, 60_000_000.0, 49.0),
+                load_values(1, 5_000_000.0, 99.0),
+            ],
+        );
+        // subtask 1's counter was reset by a restart
+        store.record(
+            "job",
+            start + Duration::from_secs(20),
+            vec![
+                load_values(0, 70_000_000.0, 74.0),
+                load_values(1, 1_000_000.0, 99.0),
+            ],
+        );
+
+        let loads = store.operator_loads("job", window);
+        assert_eq!(loads.len(), 1);
+
+        let load = loads.get("op").unwrap();
+        assert!((load.busy - 0.75).abs() < 1e-9, "busy was {}", load.busy);
+        assert_eq!(load.backpressure, 0.25);
+
+        assert!(store.operator_loads("other", window).is_empty());
+    }
+
+    #[test]
+    fn test_expires_old_snapshots() {
+        let mut store = MetricsStore::default();
+        let now = SystemTime::now();
+
+        store.record(
+            "old",
+            now - RETENTION - Duration::from_secs(1),
+            vec![values("op", 1.0, 0.0)],
+        );
+        store.record("new", now, vec![values("op", 1.0, 0.0)]);
+
+        assert!(store.job_metrics("old").is_empty());
+        assert_eq!(store.job_metrics("new").len(), 1);
+        assert_eq!(store.jobs.len(), 1);
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use arroyo_rpc::api_types::pipelines::{ExponentialBackoffRestart, RestartStrategy};
+use rand::Rng;
+
+/// Decides how long to wait before restarting a failed job, or returns the reason it should be
+/// failed instead. `restarts` is the number of consecutive restarts before this failure, and
+/// `recent_failures` the number of failures (including this one) within the failure-rate window.
+pub(crate) fn restart_delay(
+    strategy: &RestartStrategy,
+    restarts: u32,
+    recent_failures: usize,
+) -> Result<Duration, String> {
+    match strategy {
+        RestartStrategy::FixedDelay(fixed) => {
+            if restarts >= fixed.max_restarts {
+                return Err("Job has restarted too many times".to_string());
+            }
+            Ok(Duration::from_millis(fixed.delay_millis))
+        }
+        RestartStrategy::ExponentialBackoff(backoff) => {
+            if backoff
+                .max_restarts
+                .is_some_and(|max_restarts| restarts >= max_restarts)
+            {
+                return Err("Job has restarted too many times".to_string());
+            }
+            Ok(backoff_delay(
+                backoff,
+                restarts,
+                rand::thread_rng().gen_range(-1.0..=1.0),
+            ))
+        }
+        RestartStrategy::FailureRate(failure_rate) => {
+            if recent_failures > failure_rate.max_failures as usize {
+                return Err(format!(
+                    "Job failed more than {} times in {} seconds",
+                    failure_rate.max_failures, failure_rate.window_secs
+                ));
+            }
+            Ok(Duration::from_millis(failure_rate.delay_millis))
+        }
+        RestartStrategy::Never => Err("Job failed and its restart strategy is never".to_string()),
+    }
+}
+
+// `sample` is uniformly distributed in [-1, 1], and determines how much jitter is applied
+fn backoff_delay(backoff: &ExponentialBackoffRestart, restarts: u32, sample: f64) -> Duration {
+    let max_delay = backoff.max_delay_millis as f64;
+    let delay = (backoff.initial_delay_millis as f64
+        * backoff
+            .multiplier
+            .powi(restarts.min(i32::MAX as u32) as i32))
+    .min(max_delay);
+
+    let jittered = delay * (1.0 + backoff.jitter.clamp(0.0, 1.0) * sample);
+    Duration::from_millis(jittered.clamp(0.0, max_delay) as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arroyo_rpc::api_types::pipelines::{FailureRateRestart, FixedDelayRestart};
+
+    fn backoff() -> ExponentialBackoffRestart {
+        ExponentialBackoffRestart {
+            initial_delay_millis: 1000,
+            max_delay_millis: 60_000,
+            multiplier: 2.0,
+            jitter: 0.0,
+            max_restarts: None,
+        }
+    }
+
+    #[test]
+    fn test_fixed_delay() {
+        let strategy = RestartStrategy::FixedDelay(FixedDelayRestart {
+            delay_millis: 500,
+            max_restarts: 3,
+        });
+
+        assert_eq!(
+            restart_delay(&strategy, 2, 1),
+            Ok(Duration::from_millis(500))
+        );
+        assert!(restart_delay(&strategy, 3, 1).is_err());
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let backoff = backoff();
+        assert_eq!(backoff_delay(&backoff, 0, 0.0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(&backoff, 3, 0.0), Duration::from_secs(8));
+        assert_eq!(backoff_delay(&backoff, 20, 0.0), Duration::from_secs(60));
+
+        let strategy = RestartStrategy::ExponentialBackoff(ExponentialBackoffRestart {
+            max_restarts: Some(5),
+            ..backoff
+        });
+        assert!(restart_delay(&strategy, 4, 1).is_ok());
+        assert!(restart_delay(&strategy, 5, 1).is_err());
+    }
+
+    #[test]
+    fn test_backoff_jitter() {
+        let backoff = ExponentialBackoffRestart {
+            jitter: 0.5,
+            ..backoff()
+        };
+
+        assert_eq!(backoff_delay(&backoff, 1, -1.0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(&backoff, 1, 1.0), Duration::from_secs(3));
+
+        // jitter never takes the delay past the maximum
+        assert_eq!(backoff_delay(&backoff, 10, 1.0), Duration::from_secs(60));
+
+        for _ in 0..100 {
+            let delay =
+                restart_delay(&RestartStrategy::ExponentialBackoff(backoff.clone()), 2, 1).unwrap();
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(6));
+        }
+    }
+
+    #[test]
+    fn test_failure_rate() {
+        let strategy = RestartStrategy::FailureRate(FailureRateRestart {
+            max_failures: 3,
+            window_secs: 60,
+            delay_millis: 100,
+        });
+
+        // consecutive restarts don't matter, only how many failures are in the window
+        assert_eq!(
+            restart_delay(&strategy, 50, 3),
+            Ok(Duration::from_millis(100))
+        );
+        assert!(restart_delay(&strategy, 0, 4).is_err());
+    }
+
+    #[test]
+    fn test_never() {
+        assert!(restart_delay(&RestartStrategy::Never, 0, 1).is_err());
+    }
+}
@@ -3,8 +3,9 @@ use arroyo_datastream::logical::LogicalProgram;
 use arroyo_rpc::grpc::node_grpc_client::NodeGrpcClient;
 use arroyo_rpc::grpc::{
     api, HeartbeatNodeReq, RegisterNodeReq, StartWorkerData, StartWorkerHeader, StartWorkerReq,
-    StopWorkerReq, StopWorkerStatus, WorkerFinishedReq,
+    StartWorkerUdf, StopWorkerReq, StopWorkerStatus, WorkerFinishedReq,
 };
+use arroyo_storage::StorageProvider;
 use arroyo_types::{
     NodeId, WorkerId, ARROYO_PROGRAM_ENV, JOB_ID_ENV, NODE_ID_ENV, RUN_ID_ENV, SLOTS_PER_NODE,
     TASK_SLOTS_ENV, WORKER_ID_ENV,
@@ -13,7 +14,7 @@ use base64::{engine::general_purpose, Engine as _};
 use lazy_static::lazy_static;
 use prometheus::{register_gauge, Gauge};
 use prost::Message;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env::current_exe;
 use std::fs;
 use std::path::PathBuf;
@@ -22,7 +23,7 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::process::Command;
-use tokio::sync::{oneshot, Mutex};
+use tokio::sync::{oneshot, Mutex, OnceCell};
 use tonic::{Request, Status};
 use tracing::{info, warn};
 pub mod embedded;
@@ -45,6 +46,10 @@ lazy_static! {
 
 const NODE_PART_SIZE: usize = 2 * 1024 * 1024;
 
+// nodes that haven't heartbeated within this period are considered lost, along with their workers
+const NODE_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+const NODE_EXPIRATION_INTERVAL: Duration = Duration::from_secs(5);
+
 #[async_trait::async_trait]
 pub trait Scheduler: Send + Sync {
     async fn start_workers(
@@ -238,6 +243,8 @@ impl Scheduler for ProcessScheduler {
 struct NodeStatus {
     id: NodeId,
     free_slots: usize,
+    // slots set aside for workers that are still being started
+    reserved_slots: usize,
     scheduled_slots: HashMap<WorkerId, usize>,
     addr: String,
     last_heartbeat: Instant,
@@ -247,21 +254,27 @@ impl NodeStatus {
     fn new(id: NodeId, slots: usize, addr: String) -> NodeStatus {
         FREE_SLOTS.add(slots as f64);
         REGISTERED_SLOTS.add(slots as f64);
+        REGISTERED_NODES.inc();
 
         NodeStatus {
             id,
             free_slots: slots,
+            reserved_slots: 0,
             scheduled_slots: HashMap::new(),
             addr,
             last_heartbeat: Instant::now(),
         }
     }
 
-    fn take_slots(&mut self, worker: WorkerId, slots: usize) {
+    fn total_slots(&self) -> usize {
+        self.free_slots + self.reserved_slots + self.scheduled_slots.values().sum::<usize>()
+    }
+
+    fn reserve_slots(&mut self, slots: usize) {
         if let Some(v) = self.free_slots.checked_sub(slots) {
             FREE_SLOTS.sub(slots as f64);
             self.free_slots = v;
-            self.scheduled_slots.insert(worker, slots);
+            self.reserved_slots += slots;
         } else {
             panic!(
                 "Attempted to schedule more slots than are available on node {} ({} < {})",
@@ -270,6 +283,17 @@ impl NodeStatus {
         }
     }
 
+    fn unreserve_slots(&mut self, slots: usize) {
+        self.reserved_slots -= slots;
+        self.free_slots += slots;
+        FREE_SLOTS.add(slots as f64);
+    }
+
+    fn take_reserved_slots(&mut self, worker: WorkerId, slots: usize) {
+        self.reserved_slots -= slots;
+        self.scheduled_slots.insert(worker, slots);
+    }
+
     fn release_slots(&mut self, worker_id: WorkerId, slots: usize) {
         if let Some(freed) = self.scheduled_slots.remove(&worker_id) {
             assert_eq!(freed, slots,
@@ -317,13 +341,36 @@ impl NodeSchedulerState {
             .collect();
         for node_id in expired_nodes {
             warn!("expiring node {:?} from scheduler state", node_id);
-            self.nodes.remove(&node_id);
+            let node = self.nodes.remove(&node_id).unwrap();
+            FREE_SLOTS.sub(node.free_slots as f64);
+            REGISTERED_SLOTS.sub(node.total_slots() as f64);
+            REGISTERED_NODES.dec();
+
+            // the workers on a lost node are gone as well; once the job controller sees them stop
+            // heartbeating it will recover the job, which reschedules it on the remaining nodes
+            self.workers.retain(|worker_id, worker| {
+                if worker.node_id == node_id {
+                    warn!(
+                        message = "worker lost with expired node",
+                        job_id = worker.job_id,
+                        worker_id = worker_id.0,
+                        node_id = node_id.0
+                    );
+                    false
+                } else {
+                    true
+                }
+            });
         }
     }
 }
 
+/// This Scheduler runs workers on a cluster of arroyo-node daemons, which register themselves
+/// with the controller along with the number of task slots they provide. The worker binary and
+/// any UDF dylibs the pipeline needs are streamed to the nodes when the workers are started.
 pub struct NodeScheduler {
     state: Arc<Mutex<NodeSchedulerState>>,
+    binary: OnceCell<Arc<Vec<u8>>>,
 }
 
 pub enum SchedulerError {
@@ -334,9 +381,132 @@ pub enum SchedulerError {
 
 impl NodeScheduler {
     pub fn new() -> Self {
+        let state = Arc::new(Mutex::new(NodeSchedulerState::default()));
+
+        let expiration_state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(NODE_EXPIRATION_INTERVAL);
+            loop {
+                interval.tick().await;
+                expiration_state
+                    .lock()
+                    .await
+                    .expire_nodes(Instant::now() - NODE_HEARTBEAT_TIMEOUT);
+            }
+        });
+
         Self {
-            state: Arc::new(Mutex::new(NodeSchedulerState::default())),
+            state,
+            binary: OnceCell::new(),
+        }
+    }
+
+    /// Workers run the same binary as the controller, so that's what we send to the nodes
+    async fn worker_binary(&self) -> Result<Arc<Vec<u8>>, SchedulerError> {
+        self.binary
+            .get_or_try_init(|| async {
+                let path = current_exe().map_err(|e| {
+                    SchedulerError::Other(format!("Could not get path of worker binary: {:?}", e))
+                })?;
+
+                tokio::fs::read(&path).await.map(Arc::new).map_err(|e| {
+                    SchedulerError::Other(format!(
+                        "Failed to read worker binary {:?}: {:?}",
+                        path, e
+                    ))
+                })
+            })
+            .await
+            .cloned()
+    }
+
+    async fn udf_dylibs(
+        program: &LogicalProgram,
+    ) -> Result<Vec<(String, Vec<u8>)>, SchedulerError> {
+        let mut dylibs = vec![];
+        for (name, config) in &program.program_config.udf_dylibs {
+            let data = StorageProvider::get_url(&config.dylib_path)
+                .await
+                .map_err(|e| {
+                    SchedulerError::Other(format!(
+                        "Failed to fetch UDF dylib for '{}' from '{}': {:?}",
+                        name, config.dylib_path, e
+                    ))
+                })?;
+            dylibs.push((name.clone(), data.to_vec()));
         }
+
+        Ok(dylibs)
+    }
+
+    async fn start_worker_on_node(
+        node: &NodeStatus,
+        req: &StartPipelineReq,
+        slots: usize,
+        program: Vec<u8>,
+        binary: Arc<Vec<u8>>,
+        udfs: Arc<Vec<(String, Vec<u8>)>>,
+    ) -> Result<WorkerId, String> {
+        let mut client = NodeGrpcClient::connect(format!("http://{}", node.addr))
+            .await
+            .map_err(|e| format!("Failed to connect to node {}: {:?}", node.addr, e))?;
+
+        let header = StartWorkerReq {
+            msg: Some(arroyo_rpc::grpc::start_worker_req::Msg::Header(
+                StartWorkerHeader {
+                    name: req.name.clone(),
+                    job_id: req.job_id.clone(),
+                    wasm: vec![],
+                    slots: slots as u64,
+                    node_id: node.id.0,
+                    run_id: req.run_id as u64,
+                    env_vars: req.env_vars.clone(),
+                    binary_size: binary.len() as u64,
+                    program,
+                    udfs: udfs
+                        .iter()
+                        .map(|(name, data)| StartWorkerUdf {
+                            name: name.clone(),
+                            size: data.len() as u64,
+                        })
+                        .collect(),
+                },
+            )),
+        };
+
+        let outbound = async_stream::stream! {
+            yield header;
+
+            // the binary and the UDF dylibs are sent as a single sequence of parts, which the node
+            // splits back up using the sizes from the header
+            let total = binary.len() + udfs.iter().map(|(_, data)| data.len()).sum::<usize>();
+            let mut part = 0;
+            let mut sent = 0;
+
+            for artifact in std::iter::once(&binary[..]).chain(udfs.iter().map(|(_, data)| &data[..])) {
+                for chunk in artifact.chunks(NODE_PART_SIZE) {
+                    sent += chunk.len();
+
+                    yield StartWorkerReq {
+                        msg: Some(arroyo_rpc::grpc::start_worker_req::Msg::Data(StartWorkerData {
+                            part,
+                            data: chunk.to_vec(),
+                            has_more: sent < total,
+                        }))
+                    };
+
+                    part += 1;
+                }
+            }
+        };
+
+        let res = client
+            .start_worker(Request::new(outbound))
+            .await
+            .map_err(|e| format!("Failed to start worker on node {}: {:?}", node.addr, e))?
+            .into_inner();
+
+        Ok(WorkerId(res.worker_id))
     }
 
     async fn stop_worker(
@@ -467,143 +637,98 @@ impl Scheduler for NodeScheduler {
             .collect())
     }
 
-    #[allow(unreachable_code, unused)]
     async fn start_workers(
         &self,
         start_pipeline_req: StartPipelineReq,
     ) -> Result<(), SchedulerError> {
-        todo!("node scheduler");
-        // let (binary, wasm) = get_binaries(&start_pipeline_req)
-        //     .await
-        //     .map_err(|_| SchedulerError::CompilationNeeded)?;
-        //
-        // let binary = Arc::new(binary);
-
-        // TODO: make this locking more fine-grained
-        let mut state = self.state.lock().await;
-
-        state.expire_nodes(Instant::now() - Duration::from_secs(30));
+        let binary = self.worker_binary().await?;
+        let udfs = Arc::new(Self::udf_dylibs(&start_pipeline_req.program).await?);
+        let program = api::ArrowProgram::from(start_pipeline_req.program.clone()).encode_to_vec();
 
-        let free_slots = state.nodes.values().map(|n| n.free_slots).sum::<usize>();
-        let slots = start_pipeline_req.slots;
-        if slots > free_slots {
-            return Err(SchedulerError::NotEnoughSlots {
-                slots_needed: slots - free_slots,
-            });
+        {
+            let mut state = self.state.lock().await;
+            state.expire_nodes(Instant::now() - NODE_HEARTBEAT_TIMEOUT);
+
+            let free_slots = state.nodes.values().map(|n| n.free_slots).sum::<usize>();
+            if start_pipeline_req.slots > free_slots {
+                return Err(SchedulerError::NotEnoughSlots {
+                    slots_needed: start_pipeline_req.slots - free_slots,
+                });
+            }
         }
 
-        let mut to_schedule = slots;
-        let mut slots_assigned = vec![];
+        let mut to_schedule = start_pipeline_req.slots;
+        let mut failed_nodes = HashSet::new();
         while to_schedule > 0 {
-            // find the node with the most free slots and fill it
-            let node = {
-                if let Some(status) = state
+            // find the node with the most free slots and reserve them. The lock is released while
+            // the worker starts, as streaming the binary can take a long time and heartbeats need
+            // the state to keep nodes from expiring.
+            let (node, slots_for_this_one) = {
+                let mut state = self.state.lock().await;
+                let Some(node) = state
                     .nodes
-                    .values()
-                    .filter(|n| {
-                        n.free_slots > 0 && n.last_heartbeat.elapsed() < Duration::from_secs(30)
-                    })
+                    .values_mut()
+                    .filter(|n| n.free_slots > 0 && !failed_nodes.contains(&n.id))
                     .max_by_key(|n| n.free_slots)
-                    .cloned()
-                {
-                    status
-                } else {
-                    unreachable!();
-                }
+                else {
+                    // any workers we did start are cleaned up by the retry, which stops all
+                    // workers for the job before scheduling again
+                    return Err(SchedulerError::Other(format!(
+                        "Failed to start workers for {} slots on the available nodes",
+                        to_schedule
+                    )));
+                };
+
+                let slots_for_this_one = node.free_slots.min(to_schedule);
+                node.reserve_slots(slots_for_this_one);
+                (node.clone(), slots_for_this_one)
             };
 
-            let slots_for_this_one = node.free_slots.min(to_schedule);
             info!(
                 "Scheduling {} slots on node {}",
                 slots_for_this_one, node.addr
             );
 
-            let mut client = NodeGrpcClient::connect(format!("http://{}", node.addr))
-                .await
-                // TODO: handle this issue more gracefully by moving trying other nodes
-                .map_err(|e| {
-                    // release back slots already scheduled.
-                    slots_assigned
-                        .iter()
-                        .for_each(|(node_id, worker_id, slots)| {
-                            state
-                                .nodes
-                                .get_mut(node_id)
-                                .unwrap()
-                                .release_slots(*worker_id, *slots);
-                        });
-                    SchedulerError::Other(format!(
-                        "Failed to connect to node {}: {:?}",
-                        node.addr, e
-                    ))
-                })?;
-
-            let header = StartWorkerReq {
-                msg: Some(arroyo_rpc::grpc::start_worker_req::Msg::Header(
-                    StartWorkerHeader {
-                        name: start_pipeline_req.name.clone(),
-                        job_id: start_pipeline_req.job_id.clone(),
-                        wasm: todo!(),
-                        slots: slots_for_this_one as u64,
-                        node_id: node.id.0,
-                        run_id: start_pipeline_req.run_id as u64,
-                        env_vars: start_pipeline_req.env_vars.clone(),
-                        binary_size: todo!(),
-                    },
-                )),
-            };
-
-            let binary: Vec<u8> = todo!();
-            let outbound = async_stream::stream! {
-                yield header;
-
-                let mut part = 0;
-                let mut sent = 0;
-
-                for chunk in binary.chunks(NODE_PART_SIZE) {
-                    sent += chunk.len();
-
-                    yield StartWorkerReq {
-                        msg: Some(arroyo_rpc::grpc::start_worker_req::Msg::Data(StartWorkerData {
-                            part,
-                            data: chunk.to_vec(),
-                            has_more: sent < binary.len(),
-                        }))
-                    };
-
-                    part += 1;
+            let result = Self::start_worker_on_node(
+                &node,
+                &start_pipeline_req,
+                slots_for_this_one,
+                program.clone(),
+                binary.clone(),
+                udfs.clone(),
+            )
+            .await;
+
+            let mut state = self.state.lock().await;
+            let worker_id = match result {
+                Ok(worker_id) => worker_id,
+                Err(e) => {
+                    warn!(
+                        message = "failed to start worker on node",
+                        job_id = start_pipeline_req.job_id,
+                        node_id = node.id.0,
+                        node_addr = node.addr,
+                        error = e
+                    );
+                    if let Some(node) = state.nodes.get_mut(&node.id) {
+                        node.unreserve_slots(slots_for_this_one);
+                    }
+                    failed_nodes.insert(node.id);
+                    continue;
                 }
             };
 
-            let res = client
-                .start_worker(Request::new(outbound))
-                .await
-                .map_err(|e| {
-                    // release back slots already scheduled.
-                    slots_assigned
-                        .iter()
-                        .for_each(|(node_id, worker_id, slots)| {
-                            state
-                                .nodes
-                                .get_mut(node_id)
-                                .unwrap()
-                                .release_slots(*worker_id, *slots);
-                        });
-                    SchedulerError::Other(format!(
-                        "Failed to start worker on node {}: {:?}",
-                        node.addr, e
-                    ))
-                })?
-                .into_inner();
-
-            state
-                .nodes
-                .get_mut(&node.id)
-                .unwrap()
-                .take_slots(WorkerId(res.worker_id), slots_for_this_one);
+            let Some(node_status) = state.nodes.get_mut(&node.id) else {
+                // the node expired while the worker was starting, so the worker is lost with it
+                return Err(SchedulerError::Other(format!(
+                    "Node {} expired while starting worker {}",
+                    node.addr, worker_id.0
+                )));
+            };
+            node_status.take_reserved_slots(worker_id, slots_for_this_one);
 
             state.workers.insert(
-                WorkerId(res.worker_id),
+                worker_id,
                 NodeWorker {
                     job_id: start_pipeline_req.job_id.clone(),
                     run_id: start_pipeline_req.run_id,
@@ -612,8 +737,6 @@ impl Scheduler for NodeScheduler {
                 },
             );
 
-            slots_assigned.push((node.id, WorkerId(res.worker_id), slots_for_this_one));
-
             to_schedule -= slots_for_this_one;
         }
         Ok(())
@@ -1,5 +1,8 @@
+use std::time::Duration;
+
 use arroyo_rpc::grpc;
-use tracing::debug;
+use tokio::time::Instant;
+use tracing::{debug, warn};
 
 use crate::{states::StateError, JobMessage};
 
@@ -8,6 +11,9 @@ use super::{
     JobContext, State, Stopped, Transition,
 };
 
+// how long a force stop waits for the in-flight checkpoint before abandoning it
+const FORCE_STOP_CHECKPOINT_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Debug)]
 pub struct CheckpointStopping {}
 
@@ -21,6 +27,7 @@ impl State for CheckpointStopping {
         let job_controller = ctx.job_controller.as_mut().unwrap();
 
         let mut final_checkpoint_started = false;
+        let mut force_deadline: Option<Instant> = None;
 
         loop {
             match job_controller.checkpoint_finished().await {
@@ -55,7 +62,23 @@ impl State for CheckpointStopping {
                 }
             }
 
-            match ctx.rx.recv().await.expect("channel closed while receiving") {
+            let msg = tokio::select! {
+                msg = ctx.rx.recv() => msg.expect("channel closed while receiving"),
+                _ = tokio::time::sleep_until(force_deadline.unwrap_or_else(far_future)) => {
+                    warn!(
+                        message = "final checkpoint did not complete before the force-stop timeout",
+                        job_id = ctx.config.id
+                    );
+                    return Ok(Transition::next(
+                        *self,
+                        Stopping {
+                            stop_mode: StopBehavior::StopWorkers,
+                        },
+                    ));
+                }
+            };
+
+            match msg {
                 JobMessage::RunningMessage(msg) => {
                     if let Err(e) = job_controller.handle_message(msg).await {
                         return Err(ctx.retryable(
@@ -77,7 +100,11 @@ impl State for CheckpointStopping {
                             ));
                         }
                         crate::types::public::StopMode::force => {
-                            todo!("implement force stop mode");
+                            // give the in-flight checkpoint a chance to complete, after which
+                            // it's abandoned and the workers are torn down
+                            force_deadline.get_or_insert_with(|| {
+                                Instant::now() + FORCE_STOP_CHECKPOINT_TIMEOUT
+                            });
                         }
                         _ => {
                             // do nothing
@@ -91,3 +118,8 @@ impl State for CheckpointStopping {
         }
     }
 }
+
+fn far_future() -> Instant {
+    // there's no deadline until a force stop is requested
+    Instant::now() + Duration::from_secs(60 * 60 * 24 * 365)
+}
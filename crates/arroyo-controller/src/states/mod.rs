@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::sync::RwLock;
 use std::time::{Duration, Instant};
 use std::{fmt::Debug, sync::Arc};
@@ -16,10 +17,13 @@ use tracing::{error, info, warn};
 use anyhow::{anyhow, Result};
 
 use crate::job_controller::JobController;
+use crate::metrics::MetricsStore;
 use crate::queries::controller_queries;
+use crate::restarts;
 use crate::types::public::StopMode;
 use crate::{schedulers::Scheduler, JobConfig, JobMessage, JobStatus};
 use arroyo_datastream::logical::LogicalProgram;
+use arroyo_rpc::api_types::pipelines::RestartStrategy;
 use arroyo_server_common::shutdown::ShutdownGuard;
 use prost::Message;
 
@@ -167,7 +171,13 @@ impl State for Stopped {
 // State transitions
 impl TransitionTo<Compiling> for Created {}
 
-impl TransitionTo<Compiling> for Stopped {}
+impl TransitionTo<Compiling> for Stopped {
+    fn update_status(&self) -> TransitionFn {
+        Box::new(|ctx| {
+            ctx.status.failure_message = None;
+        })
+    }
+}
 
 impl TransitionTo<Compiling> for Scheduling {}
 
@@ -205,6 +215,13 @@ impl TransitionTo<Recovering> for Running {
         })
     }
 }
+impl TransitionTo<Recovering> for Restarting {
+    fn update_status(&self) -> TransitionFn {
+        Box::new(|ctx| {
+            ctx.status.restarts += 1;
+        })
+    }
+}
 impl TransitionTo<Rescaling> for Running {}
 
 impl TransitionTo<Scheduling> for Rescaling {
@@ -215,7 +232,20 @@ impl TransitionTo<Scheduling> for Rescaling {
     }
 }
 
-impl TransitionTo<Compiling> for Recovering {}
+impl TransitionTo<Compiling> for Recovering {
+    fn update_status(&self) -> TransitionFn {
+        Box::new(|ctx| {
+            ctx.status.next_retry_time = None;
+        })
+    }
+}
+impl TransitionTo<Stopping> for Recovering {
+    fn update_status(&self) -> TransitionFn {
+        Box::new(|ctx| {
+            ctx.status.next_retry_time = None;
+        })
+    }
+}
 impl TransitionTo<Compiling> for Failed {
     fn update_status(&self) -> TransitionFn {
         Box::new(|ctx| {
@@ -355,10 +385,13 @@ pub struct JobContext<'a> {
     program: &'a mut LogicalProgram,
     pool: Pool,
     scheduler: Arc<dyn Scheduler>,
+    metrics: Arc<std::sync::Mutex<MetricsStore>>,
     rx: &'a mut Receiver<JobMessage>,
     retries_attempted: usize,
     job_controller: Option<JobController>,
     last_transitioned_at: Instant,
+    // times of recent failures, for failure-rate restart strategies
+    failures: VecDeque<Instant>,
 }
 
 impl<'a> JobContext<'a> {
@@ -381,6 +414,33 @@ impl<'a> JobContext<'a> {
             retries: retries.saturating_sub(self.retries_attempted),
         }
     }
+
+    /// Applies the job's restart strategy after it fails, setting the time at which it should be
+    /// restarted, or returning a fatal error if it should not be
+    pub fn schedule_restart(&mut self, source: anyhow::Error) -> Result<(), StateError> {
+        let now = Instant::now();
+        if let RestartStrategy::FailureRate(failure_rate) = &self.config.restart_strategy {
+            let window = Duration::from_secs(failure_rate.window_secs);
+            self.failures.push_back(now);
+            while self
+                .failures
+                .front()
+                .is_some_and(|t| now.duration_since(*t) > window)
+            {
+                self.failures.pop_front();
+            }
+        }
+
+        let delay = restarts::restart_delay(
+            &self.config.restart_strategy,
+            self.status.restarts.max(0) as u32,
+            self.failures.len(),
+        )
+        .map_err(|message| fatal(message, source))?;
+
+        self.status.next_retry_time = Some(OffsetDateTime::now_utc() + delay);
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -487,6 +547,7 @@ async fn execute_state<'a>(
             );
             ctx.status.failure_message = Some(message);
             ctx.status.finish_time = Some(OffsetDateTime::now_utc());
+            ctx.status.next_retry_time = None;
             let s: Box<dyn State> = Box::new(Failed {});
             Some(s)
         }
@@ -542,6 +603,7 @@ pub async fn run_to_completion(
     pool: Pool,
     mut rx: Receiver<JobMessage>,
     scheduler: Arc<dyn Scheduler>,
+    metrics: Arc<std::sync::Mutex<MetricsStore>>,
 ) {
     let mut ctx = JobContext {
         config: config.read().unwrap().clone(),
@@ -549,10 +611,12 @@ pub async fn run_to_completion(
         program: &mut program,
         pool: pool.clone(),
         scheduler,
+        metrics,
         rx: &mut rx,
         retries_attempted: 0,
         job_controller: None,
         last_transitioned_at: Instant::now(),
+        failures: VecDeque::new(),
     };
 
     loop {
@@ -573,6 +637,7 @@ pub struct StateMachine {
     config: Arc<RwLock<JobConfig>>,
     pool: Pool,
     scheduler: Arc<dyn Scheduler>,
+    metrics: Arc<std::sync::Mutex<MetricsStore>>,
 }
 
 impl StateMachine {
@@ -581,6 +646,7 @@ impl StateMachine {
         status: JobStatus,
         pool: Pool,
         scheduler: Arc<dyn Scheduler>,
+        metrics: Arc<std::sync::Mutex<MetricsStore>>,
         shutdown_guard: ShutdownGuard,
     ) -> Self {
         let mut this = Self {
@@ -588,6 +654,7 @@ impl StateMachine {
             config: Arc::new(RwLock::new(config)),
             pool,
             scheduler,
+            metrics,
         };
 
         this.start(status, shutdown_guard).await;
@@ -662,12 +729,15 @@ impl StateMachine {
 
         if let Some(initial_state) = initial_state {
             status.state = initial_state.name().to_string();
+            // a pending restart is not carried over into a new state machine
+            status.next_retry_time = None;
             status.update_db(&self.pool).await.unwrap();
             let (tx, rx) = channel(1024);
             {
                 let config = self.config.clone();
                 let pool = self.pool.clone();
                 let scheduler = self.scheduler.clone();
+                let metrics = self.metrics.clone();
 
                 let pipeline_id = config.read().unwrap().pipeline_id;
                 match Self::get_program(&pool, &status.id, pipeline_id).await {
@@ -683,6 +753,7 @@ impl StateMachine {
                                 pool,
                                 rx,
                                 scheduler,
+                                metrics,
                             )
                             .await;
                             info!(message = "finished state machine", job_id = id);
@@ -2,9 +2,13 @@ use std::time::{Duration, Instant};
 
 use anyhow::bail;
 use arroyo_rpc::grpc::StopMode;
+use time::OffsetDateTime;
 use tokio::time::timeout;
 use tracing::{info, warn};
 
+use crate::states::stop_if_desired_non_running;
+use crate::JobMessage;
+
 use super::{compiling::Compiling, JobContext, State, StateError, Transition};
 
 #[derive(Debug)]
@@ -96,6 +100,39 @@ impl State for Recovering {
             return Err(ctx.retryable(self, "failed to tear down existing cluster", e, 10));
         }
 
+        // wait until the restart strategy allows the job to be restarted, unless it's stopped
+        // in the meantime
+        if let Some(retry_at) = ctx.status.next_retry_time {
+            let delay = (retry_at - OffsetDateTime::now_utc())
+                .try_into()
+                .unwrap_or(Duration::ZERO);
+
+            info!(
+                message = "waiting to restart job",
+                job_id = ctx.config.id,
+                delay_ms = delay.as_millis() as u64
+            );
+
+            let sleep = tokio::time::sleep(delay);
+            tokio::pin!(sleep);
+
+            loop {
+                tokio::select! {
+                    _ = &mut sleep => break,
+                    msg = ctx.rx.recv() => {
+                        match msg.expect("channel closed while receiving") {
+                            JobMessage::ConfigUpdate(c) => {
+                                stop_if_desired_non_running!(self, &c);
+                            }
+                            _ => {
+                                // the cluster has been torn down, so there's nothing to handle
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
         Ok(Transition::next(*self, Compiling))
     }
 }
@@ -34,24 +34,18 @@ impl State for Restarting {
                             }
                         }
                         Err(e) => {
-                            return Err(ctx.retryable(
-                                self,
-                                "failed while monitoring final checkpoint",
-                                e,
-                                10,
-                            ));
+                            // the job failed before it could finish, so it's recovered like any
+                            // other failure
+                            ctx.schedule_restart(e)?;
+                            return Ok(Transition::next(*self, Recovering {}));
                         }
                     }
 
                     match ctx.rx.recv().await.expect("channel closed while receiving") {
                         JobMessage::RunningMessage(msg) => {
                             if let Err(e) = job_controller.handle_message(msg).await {
-                                return Err(ctx.retryable(
-                                    self,
-                                    "failed while waiting for job finish",
-                                    e,
-                                    10,
-                                ));
+                                ctx.schedule_restart(e)?;
+                                return Ok(Transition::next(*self, Recovering {}));
                             }
                         }
                         JobMessage::ConfigUpdate(c) => {
@@ -3,13 +3,14 @@ use std::time::{Duration, Instant};
 use time::OffsetDateTime;
 use tokio::time::MissedTickBehavior;
 
-use tracing::error;
+use tracing::{error, warn};
 
+use crate::autoscaler;
 use crate::states::finishing::Finishing;
 use crate::states::recovering::Recovering;
 use crate::states::rescaling::Rescaling;
 use crate::states::restarting::Restarting;
-use crate::states::{fatal, stop_if_desired_running};
+use crate::states::stop_if_desired_running;
 use crate::JobMessage;
 use crate::{job_controller::ControllerProgress, states::StateError};
 use arroyo_server_common::log_event;
@@ -20,8 +21,8 @@ use super::{JobContext, State, Transition};
 // after this amount of time, we consider the job to be healthy and reset the restarts counter
 const HEALTHY_DURATION: Duration = Duration::from_secs(2 * 60);
 
-// how many times we allow the job to restart before moving it to failed
-const RESTARTS_ALLOWED: usize = 10;
+// how often we check whether a job with autoscaling enabled should be rescaled
+const AUTOSCALE_INTERVAL: Duration = Duration::from_secs(30);
 
 #[derive(Debug)]
 pub struct Running {}
@@ -40,6 +41,9 @@ impl State for Running {
         let mut log_interval = tokio::time::interval(Duration::from_secs(60));
         log_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
+        let mut autoscale_interval = tokio::time::interval(AUTOSCALE_INTERVAL);
+        autoscale_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
         loop {
             let ttl_end: Option<Duration> = ctx.config.ttl.map(|t| {
                 let elapsed = Duration::from_micros(
@@ -62,6 +66,10 @@ impl State for Running {
                                 }));
                             }
 
+                            if let Some(savepoint) = &c.pending_savepoint {
+                                ctx.job_controller.as_mut().unwrap().request_savepoint(savepoint);
+                            }
+
                             let job_controller = ctx.job_controller.as_ref().unwrap();
                             for (op, p) in &c.parallelism_overrides {
                                 if let Some(actual) = job_controller.operator_parallelism(op){
@@ -116,12 +124,7 @@ impl State for Running {
                                 "job_id": ctx.config.id,
                                 "error": format!("{:?}", err),
                             }));
-                            if ctx.status.restarts >= RESTARTS_ALLOWED as i32 {
-                                return Err(fatal(
-                                    "Job has restarted too many times",
-                                    err
-                                ));
-                            }
+                            ctx.schedule_restart(err)?;
                             return Ok(Transition::next(
                                 *self,
                                 Recovering {}
@@ -140,6 +143,29 @@ impl State for Running {
                         }),
                     );
                 }
+                _ = autoscale_interval.tick() => {
+                    let Some(autoscaling) = ctx.config.autoscaling.as_ref().filter(|a| a.enabled) else {
+                        continue;
+                    };
+
+                    // every rescale restarts the job, so the cooldown is measured from when it
+                    // started running
+                    if running_start.elapsed() < Duration::from_secs(autoscaling.cooldown_secs) {
+                        continue;
+                    }
+
+                    if let Err(e) = autoscaler::autoscale(
+                        &ctx.config.id,
+                        ctx.status.run_id,
+                        &ctx.program.tasks_per_operator(),
+                        autoscaling,
+                        &ctx.metrics,
+                        &ctx.pool,
+                    ).await {
+                        warn!(message = "failed to autoscale job", error = format!("{:?}", e),
+                            job_id = ctx.config.id);
+                    }
+                }
                 _ = tokio::time::sleep(ttl_end.unwrap_or(Duration::MAX)) => {
                     // TTL has expired, stop the job
                     return Ok(Transition::next(
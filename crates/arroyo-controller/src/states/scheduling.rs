@@ -6,12 +6,15 @@ use std::{
 
 use arroyo_rpc::grpc::{worker_grpc_client::WorkerGrpcClient, StartExecutionReq, TaskAssignment};
 use arroyo_types::WorkerId;
+use cornucopia_async::GenericClient;
+use time::OffsetDateTime;
 use tokio::{sync::Mutex, task::JoinHandle};
 use tonic::{transport::Channel, Request};
 use tracing::{error, info, warn};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use arroyo_datastream::logical::LogicalProgram;
+use arroyo_rpc::public_ids::{generate_id, IdTypes};
 use arroyo_state::{
     committing_state::CommittingState,
     parquet::get_storage_env_vars,
@@ -23,6 +26,8 @@ use crate::{
     job_controller::JobController,
     queries::controller_queries,
     states::{compiling::Compiling, stop_if_desired_non_running},
+    types::public::LogLevel,
+    JobConfig,
 };
 use crate::{schedulers::SchedulerError, JobMessage};
 use crate::{
@@ -34,6 +39,100 @@ use super::{running::Running, JobContext, State, Transition};
 
 const STARTUP_TIME: Duration = Duration::from_secs(10 * 60);
 
+/// Splits the operators a savepoint has state for into those that are in the pipeline and those
+/// that aren't, which can only be dropped if `allow_dropped_state` is set
+fn savepoint_operators(
+    savepoint_id: &str,
+    operator_ids: Vec<String>,
+    operators: &HashMap<String, usize>,
+    allow_dropped_state: bool,
+) -> anyhow::Result<(Vec<String>, Vec<String>)> {
+    let (retained, dropped): (Vec<_>, Vec<_>) = operator_ids
+        .into_iter()
+        .partition(|op| operators.contains_key(op));
+
+    if !dropped.is_empty() && !allow_dropped_state {
+        bail!(
+            "savepoint {} contains state for operators that are not in the pipeline: {}",
+            savepoint_id,
+            dropped.join(", ")
+        );
+    }
+
+    Ok((retained, dropped))
+}
+
+/// Writes the state of a savepoint as the first checkpoint of a job that has not yet checkpointed,
+/// returning the epoch and id of that checkpoint
+async fn restore_from_savepoint(
+    savepoint_id: &str,
+    config: &JobConfig,
+    program: &LogicalProgram,
+    c: &impl GenericClient,
+) -> anyhow::Result<(u32, i64)> {
+    let metadata = StateBackend::load_savepoint_metadata(savepoint_id).await?;
+
+    let (retained, dropped) = savepoint_operators(
+        savepoint_id,
+        metadata.operator_ids,
+        &program.tasks_per_operator(),
+        config.allow_dropped_state,
+    )?;
+
+    if !dropped.is_empty() {
+        warn!(
+            message = "dropping savepoint state for operators that are not in the pipeline",
+            job_id = config.id,
+            savepoint_id,
+            operators = dropped.join(", ")
+        );
+
+        controller_queries::create_controller_log_message()
+            .bind(
+                c,
+                &generate_id(IdTypes::JobLogMessage),
+                &config.id,
+                &LogLevel::warn,
+                &"Dropped state from savepoint".to_string(),
+                &format!(
+                    "Savepoint {} contains state for operators that are not in this pipeline, which was not restored: {}",
+                    savepoint_id,
+                    dropped.join(", ")
+                ),
+            )
+            .one()
+            .await?;
+    }
+
+    let metadata = StateBackend::restore_savepoint(
+        savepoint_id,
+        &config.id,
+        retained,
+        config.allow_dropped_state,
+    )
+    .await?;
+
+    let checkpoint_id = controller_queries::create_checkpoint()
+        .bind(
+            c,
+            &generate_id(IdTypes::Checkpoint),
+            &config.organization_id,
+            &config.id,
+            &StateBackend::name().to_string(),
+            &(metadata.epoch as i32),
+            &(metadata.min_epoch as i32),
+            &OffsetDateTime::now_utc(),
+        )
+        .one()
+        .await?;
+
+    controller_queries::commit_checkpoint()
+        .bind(c, &OffsetDateTime::now_utc(), &checkpoint_id)
+        .await?;
+
+    Ok((metadata.epoch, checkpoint_id))
+}
+
 #[derive(Debug, Clone)]
 struct WorkerStatus {
     id: WorkerId,
@@ -41,6 +140,35 @@ struct WorkerStatus {
     slots: usize,
 }
 
+/// Fetches the encrypted secrets that are referenced by the program's operators; these are sent to
+/// the workers, which decrypt them when constructing the operators
+async fn referenced_secrets(
+    program: &LogicalProgram,
+    organization_id: &str,
+    c: &impl GenericClient,
+) -> anyhow::Result<HashMap<String, Vec<u8>>> {
+    let referenced: HashSet<String> = program
+        .graph
+        .node_weights()
+        .flat_map(|node| {
+            arroyo_rpc::secrets::references(&String::from_utf8_lossy(&node.operator_config))
+        })
+        .collect();
+
+    if referenced.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    Ok(controller_queries::get_secrets()
+        .bind(c, &organization_id)
+        .all()
+        .await?
+        .into_iter()
+        .filter(|secret| referenced.contains(&secret.name))
+        .map(|secret| (secret.name, secret.encrypted_value))
+        .collect())
+}
+
 #[derive(Debug)]
 pub struct Scheduling {}
 
@@ -334,8 +462,44 @@ impl State for Scheduling {
                 }
             });
 
+        // a new job that was created from a savepoint starts from the savepoint's state
+        let checkpoint_info = match (checkpoint_info, &ctx.config.restore_savepoint) {
+            (None, Some(savepoint_id)) => {
+                info!(
+                    message = "restoring savepoint",
+                    job_id = ctx.config.id,
+                    savepoint_id
+                );
+
+                let (epoch, id) =
+                    restore_from_savepoint(savepoint_id, &ctx.config, ctx.program, &c)
+                        .await
+                        .map_err(|err| {
+                            fatal(
+                                format!("Failed to restore job from savepoint {}", savepoint_id),
+                                err,
+                            )
+                        })?;
+
+                Some(CheckpointInfo {
+                    epoch,
+                    min_epoch: epoch,
+                    id,
+                    needs_commits: false,
+                })
+            }
+            (checkpoint_info, _) => checkpoint_info,
+        };
+
         info!("Restoring from {:?}", checkpoint_info);
 
+        let secrets = match referenced_secrets(ctx.program, &ctx.config.organization_id, &c).await {
+            Ok(secrets) => secrets,
+            Err(e) => {
+                return Err(ctx.retryable(self, "failed to load secrets for pipeline", e, 10));
+            }
+        };
+
         {
             // mark in-progress checkpoints as failed
             let last_epoch = checkpoint_info
@@ -462,6 +626,7 @@ impl State for Scheduling {
 
                 let job_id = ctx.config.id.clone();
                 let restore_epoch = checkpoint_info.as_ref().map(|info| info.epoch);
+                let secrets = secrets.clone();
                 tokio::spawn(async move {
                     info!(
                         message = "starting execution on worker",
@@ -473,6 +638,7 @@ impl State for Scheduling {
                             .start_execution(Request::new(StartExecutionReq {
                                 restore_epoch,
                                 tasks: assignments.clone(),
+                                secrets: secrets.clone(),
                             }))
                             .await
                         {
@@ -560,3 +726,33 @@ impl State for Scheduling {
         Ok(Transition::next(*self, Running {}))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_savepoint_operators() {
+        let operators: HashMap<_, _> = [("source".to_string(), 1), ("sink".to_string(), 1)]
+            .into_iter()
+            .collect();
+        let operator_ids = || vec!["source".to_string(), "old_window".to_string()];
+
+        let err = savepoint_operators("sp_1", operator_ids(), &operators, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "savepoint sp_1 contains state for operators that are not in the pipeline: old_window"
+        );
+
+        assert_eq!(
+            savepoint_operators("sp_1", operator_ids(), &operators, true).unwrap(),
+            (vec!["source".to_string()], vec!["old_window".to_string()])
+        );
+
+        // operators without state in the savepoint start empty
+        assert_eq!(
+            savepoint_operators("sp_1", vec!["sink".to_string()], &operators, false).unwrap(),
+            (vec!["sink".to_string()], vec![])
+        );
+    }
+}
@@ -60,6 +60,26 @@ impl State for Stopping {
                 }
             }
             (_, StopBehavior::StopWorkers) | (None, _) => {
+                if let Some(job_controller) = ctx.job_controller.as_mut() {
+                    match job_controller.abandon_checkpoint().await {
+                        Ok(Some(epoch)) => {
+                            ctx.status.failure_message = Some(format!(
+                                "Checkpoint {} was abandoned when the job was force-stopped",
+                                epoch
+                            ));
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            return Err(ctx.retryable(
+                                self,
+                                "failed to abandon in-progress checkpoint",
+                                e,
+                                10,
+                            ));
+                        }
+                    }
+                }
+
                 if let Err(e) = ctx
                     .scheduler
                     .stop_workers(&ctx.config.id, Some(ctx.status.run_id), true)
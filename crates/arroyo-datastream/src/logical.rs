@@ -28,10 +28,14 @@ pub enum OperatorName {
     ArrowAggregate,
     Join,
     InstantJoin,
+    IntervalJoin,
+    LookupJoin,
+    AsyncUdf,
     WindowFunction,
     TumblingWindowAggregate,
     SlidingWindowAggregate,
     SessionWindowAggregate,
+    UpdatingAggregate,
     ConnectorSource,
     ConnectorSink,
 }
@@ -179,6 +183,10 @@ pub struct DylibUdfConfig {
     pub dylib_path: String,
     pub arg_types: Vec<ArrowType>,
     pub return_type: ArrowType,
+    pub is_async: bool,
+    pub is_aggregate: bool,
+    /// for UDAFs, the types of the fields that make up the accumulator state
+    pub state_types: Vec<ArrowType>,
 }
 
 #[derive(Clone, Debug)]
@@ -300,6 +308,9 @@ impl From<DylibUdfConfig> for ArrowDylibUdfConfig {
             dylib_path: from.dylib_path,
             arg_types: from.arg_types.iter().map(|t| t.encode_to_vec()).collect(),
             return_type: from.return_type.encode_to_vec(),
+            is_async: from.is_async,
+            is_aggregate: from.is_aggregate,
+            state_types: from.state_types.iter().map(|t| t.encode_to_vec()).collect(),
         }
     }
 }
@@ -315,6 +326,13 @@ impl From<ArrowDylibUdfConfig> for DylibUdfConfig {
                 .collect(),
             return_type: ArrowType::decode(&mut from.return_type.as_slice())
                 .expect("invalid arrow type"),
+            is_async: from.is_async,
+            is_aggregate: from.is_aggregate,
+            state_types: from
+                .state_types
+                .iter()
+                .map(|t| ArrowType::decode(&mut t.as_slice()).expect("invalid arrow type"))
+                .collect(),
         }
     }
 }
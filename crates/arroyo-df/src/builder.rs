@@ -5,7 +5,7 @@ use std::time::Duration;
 
 use arrow::datatypes::IntervalMonthDayNanoType;
 
-use arroyo_datastream::logical::{LogicalEdge, LogicalGraph, LogicalNode};
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalGraph, LogicalNode};
 use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
 
 use async_trait::async_trait;
@@ -24,7 +24,10 @@ use datafusion_expr::{
 };
 use datafusion_physical_expr::PhysicalExpr;
 use datafusion_proto::protobuf::{PhysicalExprNode, PhysicalPlanNode};
+use petgraph::algo::toposort;
 use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
 use tokio::runtime::Runtime;
 use tokio::sync::oneshot;
 
@@ -32,16 +35,18 @@ use crate::extension::key_calculation::KeyCalculationExtension;
 use crate::extension::{ArroyoExtension, NodeWithIncomingEdges};
 use crate::physical::{new_registry, ArroyoMemExec, ArroyoPhysicalExtensionCodec, DecodingContext};
 use crate::schemas::add_timestamp_field_arrow;
+use crate::ArroyoSchemaProvider;
+use arroyo_operator::operator::Registry;
 use datafusion_proto::{
     physical_plan::AsExecutionPlan,
     protobuf::{physical_plan_node::PhysicalPlanType, AggregateMode},
 };
 
-#[derive(Default)]
 pub(crate) struct PlanToGraphVisitor {
     graph: DiGraph<LogicalNode, LogicalEdge>,
     output_schemas: HashMap<NodeIndex, ArroyoSchemaRef>,
     named_nodes: HashMap<NamedNode, NodeIndex>,
+    explicit_parallelism: HashMap<NodeIndex, usize>,
     // each node that needs to know its inputs should push an empty vec in pre_visit.
     // In post_visit each node should cleanup its vec and push its index to the last vec, if present.
     traversal: Vec<Vec<NodeIndex>>,
@@ -51,10 +56,12 @@ pub(crate) struct PlanToGraphVisitor {
 pub(crate) struct Planner {
     planner: DefaultPhysicalPlanner,
     session_state: SessionState,
+    // used to decode the plans we've encoded, so it needs to know about UDAFs
+    registry: Arc<Registry>,
 }
 
-impl Default for Planner {
-    fn default() -> Self {
+impl Planner {
+    pub(crate) fn new(schema_provider: &ArroyoSchemaProvider) -> Self {
         let planner = DefaultPhysicalPlanner::with_extension_planners(vec![Arc::new(
             ArroyoExtensionPlanner {},
         )]);
@@ -69,14 +76,18 @@ impl Default for Planner {
         let session_state =
             SessionState::new_with_config_rt(config, Arc::new(RuntimeEnv::default()))
                 .with_physical_optimizer_rules(vec![]);
+        let mut registry = new_registry();
+        for udaf in schema_provider.aggregate_functions.values() {
+            registry.add_udaf(udaf.clone());
+        }
+
         Self {
             planner,
             session_state,
+            registry: Arc::new(registry),
         }
     }
-}
 
-impl Planner {
     pub(crate) fn sync_plan(&self, plan: &LogicalPlan) -> DFResult<Arc<dyn ExecutionPlan>> {
         let fut = self.planner.create_physical_plan(plan, &self.session_state);
         let (tx, mut rx) = oneshot::channel();
@@ -137,7 +148,7 @@ impl Planner {
 
         // need to convert to ExecutionPlan to get the partial schema.
         let partial_aggregation_exec_plan = partial_aggregation_plan.try_into_physical_plan(
-            &new_registry(),
+            self.registry.as_ref(),
             &RuntimeEnv::new(RuntimeConfig::new()).unwrap(),
             &codec,
         )?;
@@ -229,6 +240,17 @@ impl ExtensionPlanner for ArroyoExtensionPlanner {
 }
 
 impl PlanToGraphVisitor {
+    pub(crate) fn new(schema_provider: &ArroyoSchemaProvider) -> Self {
+        Self {
+            graph: Default::default(),
+            output_schemas: Default::default(),
+            named_nodes: Default::default(),
+            explicit_parallelism: Default::default(),
+            traversal: vec![],
+            planner: Planner::new(schema_provider),
+        }
+    }
+
     fn add_index_to_traversal(&mut self, index: NodeIndex) {
         if let Some(last) = self.traversal.last_mut() {
             last.push(index);
@@ -241,7 +263,52 @@ impl PlanToGraphVisitor {
         Ok(())
     }
 
-    pub fn into_graph(self) -> LogicalGraph {
+    /// Returns the planned graph, with each node's parallelism set. Nodes that were not configured
+    /// with their own parallelism take that of their inputs if they are all connected by forward
+    /// edges at the same parallelism (so that, for example, the operators that follow a source stay
+    /// chained to it), and otherwise run at the default parallelism. The inputs of a join always run
+    /// at the default parallelism, as join operators rely on both sides having the same number of
+    /// partitions to tell them apart.
+    pub fn into_graph(mut self, default_parallelism: usize) -> LogicalGraph {
+        let order = toposort(&self.graph, None).expect("planned graph contains a cycle");
+
+        for idx in order {
+            let parallelism = self
+                .explicit_parallelism
+                .get(&idx)
+                .copied()
+                .unwrap_or_else(|| {
+                    if self
+                        .graph
+                        .edges_directed(idx, Direction::Outgoing)
+                        .any(|e| {
+                            matches!(
+                                e.weight().edge_type,
+                                LogicalEdgeType::LeftJoin | LogicalEdgeType::RightJoin
+                            )
+                        })
+                    {
+                        return default_parallelism;
+                    }
+
+                    let mut inputs = self
+                        .graph
+                        .edges_directed(idx, Direction::Incoming)
+                        .map(|e| (e.weight().edge_type, self.graph[e.source()].parallelism));
+
+                    match inputs.next() {
+                        Some((LogicalEdgeType::Forward, p))
+                            if inputs.all(|input| input == (LogicalEdgeType::Forward, p)) =>
+                        {
+                            p
+                        }
+                        _ => default_parallelism,
+                    }
+                });
+
+            self.graph[idx].parallelism = parallelism;
+        }
+
         self.graph
     }
 
@@ -274,6 +341,9 @@ impl PlanToGraphVisitor {
             .map_err(|e| DataFusionError::Plan(format!("error planning extension: {}", e)))?;
         let node_index = self.graph.add_node(node);
         self.add_index_to_traversal(node_index);
+        if let Some(parallelism) = extension.parallelism() {
+            self.explicit_parallelism.insert(node_index, parallelism);
+        }
         for (source, edge) in input_nodes.into_iter().zip(edges.into_iter()) {
             self.graph.add_edge(source, node_index, edge);
         }
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::fmt::Formatter;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use arrow_schema::DataType;
+use arroyo_datastream::logical::{
+    DylibUdfConfig, LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName,
+};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api::AsyncUdfOperator;
+use datafusion_common::{DFField, DFSchema, DFSchemaRef, OwnedTableReference, Result as DFResult};
+use datafusion_expr::{Expr, Extension, LogicalPlan, UserDefinedLogicalNodeCore};
+use datafusion_proto::protobuf::{ArrowType, PhysicalExprNode};
+use prost::Message;
+
+use crate::builder::{NamedNode, Planner};
+use crate::AsyncUdfOptions;
+
+use super::remote_table::RemoteTableExtension;
+use super::{ArroyoExtension, NodeWithIncomingEdges};
+
+pub(crate) const ASYNC_UDF_EXTENSION_NAME: &'static str = "AsyncUdfExtension";
+pub(crate) const ASYNC_RESULT_FIELD: &str = "__async_result";
+
+/// Calls an async UDF for each row of its input. The output has all of the fields of the input
+/// followed by `result_field`, which holds the value returned by the UDF.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct AsyncUdfExtension {
+    pub(crate) input: LogicalPlan,
+    pub(crate) udf_name: String,
+    pub(crate) dylib_path: String,
+    pub(crate) arg_types: Vec<DataType>,
+    pub(crate) return_type: DataType,
+    pub(crate) options: AsyncUdfOptions,
+    pub(crate) args: Vec<Expr>,
+    pub(crate) result_field: String,
+    pub(crate) schema: DFSchemaRef,
+}
+
+impl AsyncUdfExtension {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        input: LogicalPlan,
+        udf_name: String,
+        dylib_path: String,
+        arg_types: Vec<DataType>,
+        return_type: DataType,
+        options: AsyncUdfOptions,
+        args: Vec<Expr>,
+        result_field: String,
+    ) -> DFResult<Self> {
+        let input = match input {
+            // we only segment operators at extension points, so anything between the UDF and
+            // the prior extension needs to be computed by its own node
+            LogicalPlan::Extension(_) => input,
+            _ => LogicalPlan::Extension(Extension {
+                node: Arc::new(RemoteTableExtension {
+                    schema: input.schema().clone(),
+                    input,
+                    name: OwnedTableReference::bare(format!("{}_input", result_field)),
+                    materialize: false,
+                }),
+            }),
+        };
+
+        let schema = Arc::new(input.schema().join(&DFSchema::new_with_metadata(
+            vec![DFField::new_unqualified(
+                &result_field,
+                return_type.clone(),
+                true,
+            )],
+            HashMap::new(),
+        )?)?);
+
+        Ok(Self {
+            input,
+            udf_name,
+            dylib_path,
+            arg_types,
+            return_type,
+            options,
+            args,
+            result_field,
+            schema,
+        })
+    }
+}
+
+impl UserDefinedLogicalNodeCore for AsyncUdfExtension {
+    fn name(&self) -> &str {
+        ASYNC_UDF_EXTENSION_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        self.args.clone()
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "AsyncUdfExtension: {}({}) as {}",
+            self.udf_name,
+            self.args
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.result_field
+        )
+    }
+
+    fn from_template(&self, exprs: &[Expr], inputs: &[LogicalPlan]) -> Self {
+        Self {
+            input: inputs[0].clone(),
+            args: exprs.to_vec(),
+            ..self.clone()
+        }
+    }
+}
+
+impl ArroyoExtension for AsyncUdfExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            bail!("AsyncUdfExtension should have exactly one input");
+        }
+        let input_schema = input_schemas[0].clone();
+
+        let arg_exprs = self
+            .args
+            .iter()
+            .map(|arg| {
+                let expr = planner.create_physical_expr(arg, self.input.schema())?;
+                Ok(PhysicalExprNode::try_from(expr)?.encode_to_vec())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let udf = DylibUdfConfig {
+            dylib_path: self.dylib_path.clone(),
+            arg_types: self
+                .arg_types
+                .iter()
+                .map(ArrowType::try_from)
+                .collect::<std::result::Result<_, _>>()?,
+            return_type: ArrowType::try_from(&self.return_type)?,
+            is_async: true,
+            is_aggregate: false,
+            state_types: vec![],
+        };
+
+        let config = AsyncUdfOperator {
+            name: format!("async_udf_{}", index),
+            udf_name: self.udf_name.clone(),
+            udf: Some(udf.into()),
+            input_schema: Some(input_schema.as_ref().clone().try_into()?),
+            output_schema: Some(self.output_schema().try_into()?),
+            arg_exprs,
+            ordered: self.options.ordered,
+            max_concurrency: self.options.max_concurrency,
+            timeout_micros: self.options.timeout.as_micros() as u64,
+            retries: self.options.retries,
+        };
+
+        let node = LogicalNode {
+            operator_id: config.name.clone(),
+            description: format!("AsyncUdf<{}>", self.udf_name),
+            operator_name: OperatorName::AsyncUdf,
+            operator_config: config.encode_to_vec(),
+            parallelism: 1,
+        };
+
+        let edge = LogicalEdge::project_all(LogicalEdgeType::Forward, (*input_schema).clone());
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_keys(Arc::new(self.schema.as_ref().into()), vec![]).unwrap()
+    }
+}
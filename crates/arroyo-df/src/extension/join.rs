@@ -4,7 +4,7 @@ use crate::physical::ArroyoPhysicalExtensionCodec;
 use anyhow::bail;
 use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
 use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
-use arroyo_rpc::grpc::api::JoinOperator;
+use arroyo_rpc::grpc::api::{IntervalJoinOperator, JoinOperator};
 use datafusion_common::DFSchemaRef;
 use datafusion_expr::expr::Expr;
 use datafusion_expr::{LogicalPlan, UserDefinedLogicalNodeCore};
@@ -15,10 +15,19 @@ use std::sync::Arc;
 
 pub(crate) const JOIN_NODE_NAME: &'static str = "JoinNode";
 
+/// Bounds on the difference between the event times of the rows in an interval join; a left and
+/// right row can only be joined if `lower <= right time - left time <= upper`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct JoinTimeBounds {
+    pub(crate) lower_nanos: i64,
+    pub(crate) upper_nanos: i64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct JoinExtension {
     pub(crate) rewritten_join: LogicalPlan,
     pub(crate) is_instant: bool,
+    pub(crate) time_bounds: Option<JoinTimeBounds>,
 }
 
 impl ArroyoExtension for JoinExtension {
@@ -43,23 +52,47 @@ impl ArroyoExtension for JoinExtension {
             join_plan.clone(),
             &ArroyoPhysicalExtensionCodec::default(),
         )?;
-        let operator_name = if self.is_instant {
-            OperatorName::InstantJoin
-        } else {
-            OperatorName::Join
-        };
-        let config = JoinOperator {
-            name: format!("join_{}", index),
-            left_schema: Some(left_schema.as_ref().clone().try_into()?),
-            right_schema: Some(right_schema.as_ref().clone().try_into()?),
-            output_schema: Some(self.output_schema().try_into()?),
-            join_plan: physical_plan_node.encode_to_vec(),
+        let left_schema_proto = Some(left_schema.as_ref().clone().try_into()?);
+        let right_schema_proto = Some(right_schema.as_ref().clone().try_into()?);
+        let output_schema_proto = Some(self.output_schema().try_into()?);
+
+        let (operator_name, description, operator_config) = match self.time_bounds {
+            Some(bounds) => (
+                OperatorName::IntervalJoin,
+                "interval join",
+                IntervalJoinOperator {
+                    name: format!("interval_join_{}", index),
+                    left_schema: left_schema_proto,
+                    right_schema: right_schema_proto,
+                    output_schema: output_schema_proto,
+                    join_plan: physical_plan_node.encode_to_vec(),
+                    lower_bound_micros: bounds.lower_nanos / 1_000,
+                    upper_bound_micros: bounds.upper_nanos / 1_000,
+                }
+                .encode_to_vec(),
+            ),
+            None => (
+                if self.is_instant {
+                    OperatorName::InstantJoin
+                } else {
+                    OperatorName::Join
+                },
+                "join",
+                JoinOperator {
+                    name: format!("join_{}", index),
+                    left_schema: left_schema_proto,
+                    right_schema: right_schema_proto,
+                    output_schema: output_schema_proto,
+                    join_plan: physical_plan_node.encode_to_vec(),
+                }
+                .encode_to_vec(),
+            ),
         };
         let logical_node = LogicalNode {
             operator_id: format!("join_{}", index),
-            description: "join".to_string(),
+            description: description.to_string(),
             operator_name,
-            operator_config: config.encode_to_vec(),
+            operator_config,
             parallelism: 1,
         };
         let left_edge =
@@ -111,6 +144,7 @@ impl UserDefinedLogicalNodeCore for JoinExtension {
         Self {
             rewritten_join: inputs[0].clone(),
             is_instant: self.is_instant,
+            time_bounds: self.time_bounds,
         }
     }
 }
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fmt::Formatter;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use arrow_schema::Schema;
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api::{self, LookupJoinOperator};
+use datafusion_common::{DFField, DFSchema, DFSchemaRef, JoinType, OwnedTableReference};
+use datafusion_expr::{Expr, Extension, LogicalPlan, UserDefinedLogicalNodeCore};
+use datafusion_proto::protobuf::PhysicalExprNode;
+use prost::Message;
+
+use crate::builder::{NamedNode, Planner};
+use crate::schemas::{add_timestamp_field, add_timestamp_field_arrow};
+use crate::tables::ConnectorTable;
+
+use super::remote_table::RemoteTableExtension;
+use super::{ArroyoExtension, NodeWithIncomingEdges};
+
+pub(crate) const LOOKUP_SOURCE_NAME: &'static str = "LookupSource";
+pub(crate) const LOOKUP_JOIN_NAME: &'static str = "LookupJoinExtension";
+
+/// A scan of a lookup table. This does not become an operator on its own; it's consumed by the
+/// JoinRewriter, which turns a join against it into a [LookupJoinExtension].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct LookupSource {
+    pub(crate) table: ConnectorTable,
+    pub(crate) schema: DFSchemaRef,
+}
+
+impl LookupSource {
+    pub fn new(name: OwnedTableReference, table: ConnectorTable) -> Self {
+        let fields = table
+            .physical_schema()
+            .fields()
+            .iter()
+            .map(|f| DFField::from_qualified(&name, f.clone()))
+            .collect();
+
+        let schema = Arc::new(DFSchema::new_with_metadata(fields, HashMap::new()).unwrap());
+        let schema = add_timestamp_field(schema, Some(name)).unwrap();
+
+        Self { table, schema }
+    }
+}
+
+impl UserDefinedLogicalNodeCore for LookupSource {
+    fn name(&self) -> &str {
+        LOOKUP_SOURCE_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "LookupSource: {}", self.table.name)
+    }
+
+    fn from_template(&self, _exprs: &[Expr], _inputs: &[LogicalPlan]) -> Self {
+        self.clone()
+    }
+}
+
+/// Joins each row of its input against a lookup table, by querying the table's connector with
+/// the value of `key_expr`. The output has all of the fields of the input followed by the
+/// physical fields of the lookup table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct LookupJoinExtension {
+    pub(crate) input: LogicalPlan,
+    pub(crate) schema: DFSchemaRef,
+    pub(crate) connector: ConnectorTable,
+    pub(crate) key_expr: Expr,
+    pub(crate) join_type: JoinType,
+}
+
+impl LookupJoinExtension {
+    pub fn new(
+        input: LogicalPlan,
+        schema: DFSchemaRef,
+        connector: ConnectorTable,
+        key_expr: Expr,
+        join_type: JoinType,
+    ) -> Self {
+        let input = match input {
+            // we only segment operators at extension points, so anything between the join and
+            // the prior extension needs to be computed by its own node
+            LogicalPlan::Extension(_) => input,
+            _ => LogicalPlan::Extension(Extension {
+                node: Arc::new(RemoteTableExtension {
+                    schema: input.schema().clone(),
+                    input,
+                    name: OwnedTableReference::bare(format!("{}_input", connector.name)),
+                    materialize: false,
+                }),
+            }),
+        };
+
+        Self {
+            input,
+            schema,
+            connector,
+            key_expr,
+            join_type,
+        }
+    }
+
+    fn key_field(&self) -> Result<&String> {
+        self.connector
+            .lookup_key()
+            .ok_or_else(|| anyhow!("lookup table {} has no key", self.connector.name))
+    }
+
+    /// The schema of the values returned by the lookup connector, which is the physical schema of
+    /// the table without the key
+    fn lookup_schema(&self) -> Result<ArroyoSchema> {
+        let key = self.key_field()?;
+        let physical = self.connector.physical_schema();
+        let fields: Vec<_> = physical
+            .fields()
+            .iter()
+            .filter(|f| f.name() != key)
+            .cloned()
+            .collect();
+
+        ArroyoSchema::from_schema_keys(
+            add_timestamp_field_arrow(Arc::new(Schema::new(fields))),
+            vec![],
+        )
+    }
+}
+
+impl UserDefinedLogicalNodeCore for LookupJoinExtension {
+    fn name(&self) -> &str {
+        LOOKUP_JOIN_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![self.key_expr.clone()]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "LookupJoinExtension({}): {} = {}",
+            self.join_type, self.key_expr, self.connector.name
+        )
+    }
+
+    fn from_template(&self, exprs: &[Expr], inputs: &[LogicalPlan]) -> Self {
+        Self {
+            input: inputs[0].clone(),
+            schema: self.schema.clone(),
+            connector: self.connector.clone(),
+            key_expr: exprs[0].clone(),
+            join_type: self.join_type,
+        }
+    }
+}
+
+impl ArroyoExtension for LookupJoinExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            bail!("lookup join should have exactly one input");
+        }
+        let input_schema = input_schemas[0].clone();
+
+        let key_expression = planner.create_physical_expr(&self.key_expr, self.input.schema())?;
+        let key_expression = PhysicalExprNode::try_from(key_expression)?;
+
+        let join_type = match self.join_type {
+            JoinType::Inner => api::JoinType::Inner,
+            JoinType::Left => api::JoinType::Left,
+            j => bail!("unsupported join type for lookup join: {}", j),
+        };
+
+        let config = LookupJoinOperator {
+            name: format!("lookup_join_{}", index),
+            input_schema: Some(input_schema.as_ref().clone().try_into()?),
+            lookup_schema: Some(self.lookup_schema()?.try_into()?),
+            output_schema: Some(self.output_schema().try_into()?),
+            connector: Some(self.connector.connector_op()),
+            key_expression: key_expression.encode_to_vec(),
+            key_field: self.key_field()?.clone(),
+            join_type: join_type as i32,
+            ttl_micros: self
+                .connector
+                .lookup_cache_ttl
+                .map(|t| t.as_micros() as u64),
+            max_cache_entries: self.connector.lookup_cache_max_entries.map(|n| n as u64),
+        };
+
+        let node = LogicalNode {
+            operator_id: format!("lookup_join_{}", index),
+            description: format!("LookupJoin<{}>", self.connector.name),
+            operator_name: OperatorName::LookupJoin,
+            operator_config: config.encode_to_vec(),
+            parallelism: 1,
+        };
+
+        let edge = LogicalEdge::project_all(LogicalEdgeType::Forward, (*input_schema).clone());
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_keys(Arc::new(self.schema.as_ref().into()), vec![]).unwrap()
+    }
+}
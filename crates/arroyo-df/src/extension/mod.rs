@@ -13,21 +13,27 @@ use join::JoinExtension;
 
 use self::{
     aggregate::{AggregateExtension, AGGREGATE_EXTENSION_NAME},
+    async_udf::{AsyncUdfExtension, ASYNC_UDF_EXTENSION_NAME},
     join::JOIN_NODE_NAME,
     key_calculation::{KeyCalculationExtension, KEY_CALCULATION_NAME},
+    lookup::{LookupJoinExtension, LOOKUP_JOIN_NAME, LOOKUP_SOURCE_NAME},
     remote_table::{RemoteTableExtension, REMOTE_TABLE_NAME},
     sink::{SinkExtension, SINK_NODE_NAME},
     table_source::{TableSourceExtension, TABLE_SOURCE_NAME},
+    updating_aggregate::{UpdatingAggregateExtension, UPDATING_AGGREGATE_EXTENSION_NAME},
     watermark_node::WATERMARK_NODE_NAME,
     window_fn::{WindowFunctionExtension, WINDOW_FUNCTION_EXTENSION_NAME},
 };
 
 pub(crate) mod aggregate;
+pub(crate) mod async_udf;
 pub(crate) mod join;
 pub(crate) mod key_calculation;
+pub(crate) mod lookup;
 pub(crate) mod remote_table;
 pub(crate) mod sink;
 pub(crate) mod table_source;
+pub(crate) mod updating_aggregate;
 pub(crate) mod watermark_node;
 pub(crate) mod window_fn;
 pub(crate) trait ArroyoExtension {
@@ -40,6 +46,10 @@ pub(crate) trait ArroyoExtension {
         input_schemas: Vec<ArroyoSchemaRef>,
     ) -> Result<NodeWithIncomingEdges>;
     fn output_schema(&self) -> ArroyoSchema;
+    // the parallelism the node was configured to run at, if it doesn't use the default
+    fn parallelism(&self) -> Option<usize> {
+        None
+    }
 }
 
 pub(crate) struct NodeWithIncomingEdges {
@@ -79,6 +89,13 @@ impl<'a> TryFrom<&'a Arc<dyn UserDefinedLogicalNode>> for &'a dyn ArroyoExtensio
                     node.as_any().downcast_ref::<AggregateExtension>().unwrap();
                 Ok(aggregate_extension as &dyn ArroyoExtension)
             }
+            UPDATING_AGGREGATE_EXTENSION_NAME => {
+                let updating_aggregate_extension = node
+                    .as_any()
+                    .downcast_ref::<UpdatingAggregateExtension>()
+                    .unwrap();
+                Ok(updating_aggregate_extension as &dyn ArroyoExtension)
+            }
             REMOTE_TABLE_NAME => {
                 let remote_table_extension = node
                     .as_any()
@@ -97,6 +114,19 @@ impl<'a> TryFrom<&'a Arc<dyn UserDefinedLogicalNode>> for &'a dyn ArroyoExtensio
                     .unwrap();
                 Ok(window_function_extension as &dyn ArroyoExtension)
             }
+            LOOKUP_JOIN_NAME => {
+                let lookup_join_extension =
+                    node.as_any().downcast_ref::<LookupJoinExtension>().unwrap();
+                Ok(lookup_join_extension as &dyn ArroyoExtension)
+            }
+            ASYNC_UDF_EXTENSION_NAME => {
+                let async_udf_extension =
+                    node.as_any().downcast_ref::<AsyncUdfExtension>().unwrap();
+                Ok(async_udf_extension as &dyn ArroyoExtension)
+            }
+            LOOKUP_SOURCE_NAME => Err(DataFusionError::Plan(
+                "lookup tables can only be used on the right side of a join".to_string(),
+            )),
             other => Err(DataFusionError::Plan(format!("unexpected node: {}", other))),
         }
     }
@@ -143,4 +143,11 @@ impl ArroyoExtension for SinkExtension {
         ArroyoSchema::from_schema_keys(Arc::new(self.input.schema().as_ref().into()), vec![])
             .unwrap()
     }
+
+    fn parallelism(&self) -> Option<usize> {
+        match &self.table {
+            Table::ConnectorTable(table) => table.parallelism,
+            _ => None,
+        }
+    }
 }
@@ -121,4 +121,8 @@ impl ArroyoExtension for TableSourceExtension {
     fn output_schema(&self) -> ArroyoSchema {
         ArroyoSchema::from_schema_keys(Arc::new(self.schema.as_ref().into()), vec![]).unwrap()
     }
+
+    fn parallelism(&self) -> Option<usize> {
+        self.table.parallelism
+    }
 }
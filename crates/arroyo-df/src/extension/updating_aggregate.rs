@@ -0,0 +1,143 @@
+use std::{fmt::Formatter, sync::Arc, time::Duration};
+
+use anyhow::{bail, Result};
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::{
+    df::{ArroyoSchema, ArroyoSchemaRef},
+    grpc::api::UpdatingAggregateOperator,
+    TIMESTAMP_FIELD,
+};
+use datafusion_common::{DFSchemaRef, Result as DFResult};
+use datafusion_expr::{Expr, LogicalPlan, UserDefinedLogicalNodeCore};
+use prost::Message;
+
+use crate::{
+    builder::{NamedNode, Planner, SplitPlanOutput},
+    schemas::{add_timestamp_field, add_updating_meta_field},
+};
+
+use super::{ArroyoExtension, NodeWithIncomingEdges};
+
+pub(crate) const UPDATING_AGGREGATE_EXTENSION_NAME: &'static str = "UpdatingAggregateExtension";
+
+/// An aggregate without a window, which continuously emits the current value of each group. The
+/// output is an updating stream: every time a group's value changes the previously emitted row is
+/// retracted and the new one is appended, and groups that haven't been updated for `ttl` are
+/// retracted and dropped from state.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct UpdatingAggregateExtension {
+    pub(crate) aggregate: LogicalPlan,
+    pub(crate) key_fields: Vec<usize>,
+    pub(crate) schema: DFSchemaRef,
+    pub(crate) ttl: Duration,
+}
+
+impl UpdatingAggregateExtension {
+    pub fn new(aggregate: LogicalPlan, key_fields: Vec<usize>, ttl: Duration) -> DFResult<Self> {
+        let timestamp_qualifier = aggregate.inputs()[0]
+            .schema()
+            .field_with_unqualified_name(TIMESTAMP_FIELD)?
+            .qualifier()
+            .cloned();
+
+        let schema = add_updating_meta_field(aggregate.schema().clone(), None)?;
+        let schema = add_timestamp_field(schema, timestamp_qualifier)?;
+
+        Ok(Self {
+            aggregate,
+            key_fields,
+            schema,
+            ttl,
+        })
+    }
+}
+
+impl UserDefinedLogicalNodeCore for UpdatingAggregateExtension {
+    fn name(&self) -> &str {
+        UPDATING_AGGREGATE_EXTENSION_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.aggregate]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "UpdatingAggregateExtension: {} | ttl: {:?}",
+            self.schema
+                .fields()
+                .iter()
+                .map(|f| f.qualified_name())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.ttl
+        )
+    }
+
+    fn from_template(&self, _exprs: &[Expr], inputs: &[LogicalPlan]) -> Self {
+        assert_eq!(inputs.len(), 1, "input size inconsistent");
+        Self::new(inputs[0].clone(), self.key_fields.clone(), self.ttl).unwrap()
+    }
+}
+
+impl ArroyoExtension for UpdatingAggregateExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            bail!("UpdatingAggregateExtension should have exactly one input");
+        }
+        let input_schema = input_schemas[0].clone();
+
+        // the partial aggregation holds the aggregate expressions against the input, and its
+        // output (the keys followed by the accumulator states) is what we checkpoint
+        let SplitPlanOutput {
+            partial_aggregation_plan,
+            partial_schema,
+            ..
+        } = planner.split_physical_plan(self.key_fields.clone(), &self.aggregate)?;
+
+        let config = UpdatingAggregateOperator {
+            name: format!("updating_aggregate_{}", index),
+            input_schema: Some(input_schema.as_ref().clone().try_into()?),
+            state_schema: Some(partial_schema.try_into()?),
+            output_schema: Some(self.output_schema().try_into()?),
+            aggregate_plan: partial_aggregation_plan.encode_to_vec(),
+            ttl_micros: self.ttl.as_micros() as u64,
+        };
+
+        let node = LogicalNode {
+            operator_id: config.name.clone(),
+            description: "UpdatingAggregate".to_string(),
+            operator_name: OperatorName::UpdatingAggregate,
+            operator_config: config.encode_to_vec(),
+            parallelism: 1,
+        };
+
+        let edge = LogicalEdge::project_all(LogicalEdgeType::Shuffle, (*input_schema).clone());
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_keys(Arc::new(self.schema.as_ref().into()), vec![]).unwrap()
+    }
+}
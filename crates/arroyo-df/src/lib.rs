@@ -12,7 +12,7 @@ use arroyo_datastream::WindowType;
 use datafusion::datasource::DefaultTableSource;
 #[allow(deprecated)]
 use datafusion::physical_plan::functions::make_scalar_function;
-use datafusion_common::{DFField, OwnedTableReference, ScalarValue};
+use datafusion_common::{DFField, OwnedTableReference, Result as DFResult, ScalarValue};
 pub mod builder;
 pub(crate) mod extension;
 pub mod external;
@@ -24,8 +24,10 @@ pub mod schemas;
 mod tables;
 pub mod types;
 
-use datafusion::prelude::create_udf;
+use datafusion::prelude::{create_udaf, create_udf};
 
+use arrow::compute::kernels::cast_utils::parse_interval_month_day_nano;
+use datafusion::sql::sqlparser::ast::{Expr as SqlExpr, Statement, Value as SqlValue};
 use datafusion::sql::sqlparser::dialect::PostgreSqlDialect;
 use datafusion::sql::sqlparser::parser::Parser;
 use datafusion::sql::{planner::ContextProvider, TableReference};
@@ -37,10 +39,10 @@ use datafusion_expr::{
     Signature, Volatility, WindowUDF,
 };
 
-use datafusion_expr::{AggregateUDF, TableSource};
+use datafusion_expr::{Accumulator, AccumulatorFactoryFunction, AggregateUDF, TableSource};
 use logical::LogicalBatchInput;
 
-use schemas::window_arrow_struct;
+use schemas::{is_updating, window_arrow_struct};
 use tables::{Insert, Table};
 
 use crate::builder::PlanToGraphVisitor;
@@ -53,6 +55,7 @@ use prettyplease::unparse;
 use regex::Regex;
 use std::collections::HashSet;
 use std::fmt::Debug;
+use std::str::FromStr;
 
 use crate::json::get_json_functions;
 use crate::rewriters::{SourceMetadataVisitor, UnnestRewriter};
@@ -69,6 +72,10 @@ use tracing::{info, warn};
 use unicase::UniCase;
 
 const DEFAULT_IDLE_TIME: Option<Duration> = Some(Duration::from_secs(5 * 60));
+const DEFAULT_UPDATING_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_ASYNC_UDF_MAX_CONCURRENCY: u64 = 1000;
+const DEFAULT_ASYNC_UDF_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_ASYNC_UDF_RETRIES: u64 = 5;
 
 mod json;
 pub mod udfs;
@@ -81,6 +88,7 @@ mod test;
 pub struct UdfDef {
     pub args: Vec<NullableType>,
     pub ret: NullableType,
+    pub async_options: Option<AsyncUdfOptions>,
     def: String,
     dependencies: String,
 }
@@ -102,6 +110,131 @@ pub struct ArroyoSchemaProvider {
     pub udf_defs: HashMap<String, UdfDef>,
     config_options: datafusion::config::ConfigOptions,
     pub dylib_udfs: HashMap<String, DylibUdfConfig>,
+    pub planning_options: PlanningOptions,
+}
+
+/// Options that affect how queries are planned, which may be set from SQL via `SET`
+#[derive(Debug, Clone)]
+pub struct PlanningOptions {
+    /// how long a non-windowed aggregate retains the state for a key that hasn't been updated
+    pub updating_ttl: Duration,
+    /// the parallelism of operators that don't have their own, overriding the pipeline's default
+    pub parallelism: Option<usize>,
+}
+
+impl Default for PlanningOptions {
+    fn default() -> Self {
+        Self {
+            updating_ttl: DEFAULT_UPDATING_TTL,
+            parallelism: None,
+        }
+    }
+}
+
+impl PlanningOptions {
+    fn set(&mut self, variable: &str, value: &[SqlExpr]) -> Result<()> {
+        let [SqlExpr::Value(SqlValue::SingleQuotedString(value))] = value else {
+            bail!("SET {} expects a single string value", variable);
+        };
+
+        match variable {
+            "updating_ttl" => {
+                let interval = parse_interval_month_day_nano(value)
+                    .map_err(|e| anyhow!("invalid interval '{}' for updating_ttl: {}", value, e))?;
+                self.updating_ttl = interval_month_day_nanos_to_duration(interval);
+                if self.updating_ttl.is_zero() {
+                    bail!("updating_ttl must be greater than zero");
+                }
+            }
+            "parallelism" => {
+                let parallelism = usize::from_str(value)
+                    .map_err(|_| anyhow!("invalid parallelism '{}', expected a number", value))?;
+                if parallelism == 0 {
+                    bail!("parallelism must be greater than zero");
+                }
+                self.parallelism = Some(parallelism);
+            }
+            _ => bail!("unknown variable '{}'", variable),
+        }
+
+        Ok(())
+    }
+}
+
+/// How an async UDF is executed, configured with a `#[udf(...)]` attribute on the function, like
+/// `#[udf(ordered, max_concurrency = 100, timeout = "5 seconds", retries = 3)]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AsyncUdfOptions {
+    /// whether results are emitted in the order of their inputs
+    pub ordered: bool,
+    /// the maximum number of calls that may be in flight at once, per subtask
+    pub max_concurrency: u64,
+    /// how long a call may take before it's retried
+    pub timeout: Duration,
+    /// how many times a call that times out is retried before the job fails
+    pub retries: u64,
+}
+
+impl Default for AsyncUdfOptions {
+    fn default() -> Self {
+        Self {
+            ordered: false,
+            max_concurrency: DEFAULT_ASYNC_UDF_MAX_CONCURRENCY,
+            timeout: DEFAULT_ASYNC_UDF_TIMEOUT,
+            retries: DEFAULT_ASYNC_UDF_RETRIES,
+        }
+    }
+}
+
+impl AsyncUdfOptions {
+    fn parse(attr: &syn::Attribute) -> Result<Self> {
+        let mut options = Self::default();
+        if let syn::Meta::Path(_) = attr.meta {
+            return Ok(options);
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("ordered") {
+                options.ordered = true;
+            } else if meta.path.is_ident("unordered") {
+                options.ordered = false;
+            } else if meta.path.is_ident("max_concurrency") {
+                let value: syn::LitInt = meta.value()?.parse()?;
+                options.max_concurrency = value.base10_parse()?;
+            } else if meta.path.is_ident("timeout") {
+                let value: syn::LitStr = meta.value()?.parse()?;
+                let interval = parse_interval_month_day_nano(&value.value())
+                    .map_err(|e| meta.error(format!("invalid timeout: {}", e)))?;
+                options.timeout = interval_month_day_nanos_to_duration(interval);
+            } else if meta.path.is_ident("retries") {
+                let value: syn::LitInt = meta.value()?.parse()?;
+                options.retries = value.base10_parse()?;
+            } else {
+                return Err(meta.error(
+                    "unknown udf option; expected one of ordered, unordered, max_concurrency, timeout, retries",
+                ));
+            }
+            Ok(())
+        })?;
+
+        if options.max_concurrency == 0 {
+            bail!("max_concurrency must be greater than zero");
+        }
+        if options.timeout.is_zero() {
+            bail!("timeout must be greater than zero");
+        }
+
+        Ok(options)
+    }
+}
+
+/// The accumulator of a UDAF, which is a struct marked with `#[udaf]` that implements `init`,
+/// `update`, `merge` and `finish`. The fields of the struct make up the state of the aggregate,
+/// which is what gets checkpointed and merged across partial aggregates.
+#[derive(Clone, Debug)]
+pub struct UdafAccumulator {
+    pub struct_name: String,
+    pub state_fields: Vec<(String, NullableType)>,
 }
 
 pub struct ParsedUdf {
@@ -111,6 +244,10 @@ pub struct ParsedUdf {
     pub ret_type: NullableType,
     pub definition: String,
     pub dependencies: String,
+    /// set if the UDF is an `async fn`
+    pub async_options: Option<AsyncUdfOptions>,
+    /// set if this is a UDAF rather than a function
+    pub aggregate: Option<UdafAccumulator>,
 }
 
 impl ParsedUdf {
@@ -131,9 +268,214 @@ impl ParsedUdf {
         None
     }
 
+    fn receiver(sig: &syn::Signature) -> Option<&syn::Receiver> {
+        match sig.inputs.first() {
+            Some(FnArg::Receiver(receiver)) => Some(receiver),
+            _ => None,
+        }
+    }
+
+    /// Parses the accumulator struct and its methods if the definition contains a UDAF, returning
+    /// the name of the aggregate, its argument types and its return type
+    fn try_parse_udaf(
+        file: &mut syn::File,
+    ) -> Result<Option<(String, Vec<NullableType>, NullableType, UdafAccumulator)>> {
+        let Some((struct_index, attr_index)) =
+            file.items
+                .iter()
+                .enumerate()
+                .find_map(|(i, item)| match item {
+                    Item::Struct(s) => s
+                        .attrs
+                        .iter()
+                        .position(|attr| attr.path().is_ident("udaf"))
+                        .map(|a| (i, a)),
+                    _ => None,
+                })
+        else {
+            return Ok(None);
+        };
+
+        let Item::Struct(item_struct) = &mut file.items[struct_index] else {
+            unreachable!("udaf attribute was found on a struct");
+        };
+
+        // like #[udf], the udaf attribute isn't a real macro
+        let attr = item_struct.attrs.remove(attr_index);
+        let struct_name = item_struct.ident.to_string();
+
+        let mut name = to_snake_case(&struct_name);
+        if let syn::Meta::List(_) = attr.meta {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    name = value.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown udaf option; expected name"))
+                }
+            })
+            .map_err(|e| anyhow!("Invalid udaf options for {}: {}", struct_name, e))?;
+        }
+
+        item_struct.vis = Visibility::Public(Default::default());
+        let syn::Fields::Named(fields) = &mut item_struct.fields else {
+            bail!("UDAF {} must be a struct with named fields", struct_name);
+        };
+
+        let mut state_fields = vec![];
+        for field in fields.named.iter_mut() {
+            field.vis = Visibility::Public(Default::default());
+            let field_name = field.ident.as_ref().unwrap().to_string();
+            let field_type = rust_to_arrow(&field.ty)
+                .filter(|t| !matches!(t.data_type, DataType::List(_)))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Could not convert field {} of UDAF {} into a SQL data type",
+                        field_name,
+                        struct_name
+                    )
+                })?;
+            state_fields.push((field_name, field_type));
+        }
+
+        if state_fields.is_empty() {
+            bail!(
+                "UDAF {} must have at least one field to hold its state",
+                struct_name
+            );
+        }
+
+        let mut has_init = false;
+        let mut has_merge = false;
+        let mut args = None;
+        let mut ret = None;
+
+        for item in file.items.iter_mut() {
+            let Item::Impl(item_impl) = item else {
+                continue;
+            };
+            let syn::Type::Path(self_ty) = item_impl.self_ty.as_ref() else {
+                continue;
+            };
+            if item_impl.trait_.is_some() || !self_ty.path.is_ident(&struct_name) {
+                continue;
+            }
+
+            for impl_item in item_impl.items.iter_mut() {
+                let syn::ImplItem::Fn(method) = impl_item else {
+                    continue;
+                };
+                method.vis = Visibility::Public(Default::default());
+
+                let receiver = Self::receiver(&method.sig);
+                let mutable_receiver =
+                    receiver.is_some_and(|r| r.reference.is_some() && r.mutability.is_some());
+
+                match method.sig.ident.to_string().as_str() {
+                    "init" => {
+                        if !method.sig.inputs.is_empty() {
+                            bail!("{}::init must not take any arguments", struct_name);
+                        }
+                        has_init = true;
+                    }
+                    "update" => {
+                        if !mutable_receiver {
+                            bail!("{}::update must take &mut self", struct_name);
+                        }
+
+                        let mut update_args = vec![];
+                        for (i, arg) in method.sig.inputs.iter().skip(1).enumerate() {
+                            let FnArg::Typed(t) = arg else {
+                                unreachable!("only the first argument can be a receiver");
+                            };
+                            update_args.push(
+                                rust_to_arrow(&t.ty)
+                                    .filter(|t| !matches!(t.data_type, DataType::List(_)))
+                                    .ok_or_else(|| {
+                                        anyhow!(
+                                            "Could not convert {}::update arg {} into a SQL data type",
+                                            struct_name,
+                                            i
+                                        )
+                                    })?,
+                            );
+                        }
+
+                        if update_args.is_empty() {
+                            bail!("{}::update must take at least one argument", struct_name);
+                        }
+                        args = Some(update_args);
+                    }
+                    "merge" => {
+                        if !mutable_receiver || method.sig.inputs.len() != 2 {
+                            bail!(
+                                "{}::merge must take &mut self and another {}",
+                                struct_name,
+                                struct_name
+                            );
+                        }
+                        has_merge = true;
+                    }
+                    "finish" => {
+                        if !receiver
+                            .is_some_and(|r| r.reference.is_some() && r.mutability.is_none())
+                            || method.sig.inputs.len() != 1
+                        {
+                            bail!("{}::finish must only take &self", struct_name);
+                        }
+
+                        ret = Some(match &method.sig.output {
+                            ReturnType::Default => {
+                                bail!("{}::finish return type must be specified", struct_name)
+                            }
+                            ReturnType::Type(_, t) => rust_to_arrow(t).ok_or_else(|| {
+                                anyhow!(
+                                    "Could not convert {}::finish return type into a SQL data type",
+                                    struct_name
+                                )
+                            })?,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let (true, true, Some(args), Some(ret)) = (has_init, has_merge, args, ret) else {
+            bail!(
+                "UDAF {} must implement init, update, merge and finish",
+                struct_name
+            );
+        };
+
+        Ok(Some((
+            name,
+            args,
+            ret,
+            UdafAccumulator {
+                struct_name,
+                state_fields,
+            },
+        )))
+    }
+
     pub fn try_parse(def: &str) -> anyhow::Result<ParsedUdf> {
         let mut file = parse_file(def)?;
 
+        if let Some((name, args, ret_type, accumulator)) = Self::try_parse_udaf(&mut file)? {
+            return Ok(ParsedUdf {
+                name,
+                args,
+                vec_arguments: 0,
+                ret_type,
+                definition: unparse(&file),
+                dependencies: parse_dependencies(def)?,
+                async_options: None,
+                aggregate: Some(accumulator),
+            });
+        }
+
         let mut functions = file.items.iter_mut().filter_map(|item| match item {
             Item::Fn(function) => Some(function),
             _ => None,
@@ -192,6 +534,32 @@ impl ParsedUdf {
             })?,
         };
 
+        // the udf attribute configures how we call the function, and isn't a real macro
+        let udf_attr = function
+            .attrs
+            .iter()
+            .position(|attr| attr.path().is_ident("udf"))
+            .map(|i| function.attrs.remove(i));
+
+        let async_options = if function.sig.asyncness.is_some() {
+            if vec_arguments > 0 {
+                bail!("Async function {} can't take vector arguments", name);
+            }
+            Some(match &udf_attr {
+                Some(attr) => AsyncUdfOptions::parse(attr)
+                    .map_err(|e| anyhow!("Invalid udf options for function {}: {}", name, e))?,
+                None => AsyncUdfOptions::default(),
+            })
+        } else {
+            if udf_attr.is_some() {
+                bail!(
+                    "Function {} has a #[udf] attribute, which is only supported on async functions",
+                    name
+                );
+            }
+            None
+        };
+
         function.vis = Visibility::Public(Default::default());
 
         Ok(ParsedUdf {
@@ -201,10 +569,27 @@ impl ParsedUdf {
             ret_type: ret,
             definition: unparse(&file),
             dependencies: parse_dependencies(def)?,
+            async_options,
+            aggregate: None,
         })
     }
 }
 
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
 impl ArroyoSchemaProvider {
     pub fn new() -> Self {
         let tables = HashMap::new();
@@ -286,6 +671,7 @@ impl ArroyoSchemaProvider {
             udf_defs: HashMap::new(),
             config_options: datafusion::config::ConfigOptions::new(),
             dylib_udfs: HashMap::new(),
+            planning_options: PlanningOptions::default(),
         }
     }
 
@@ -332,16 +718,62 @@ impl ArroyoSchemaProvider {
             Err(e) => bail!("Error converting arg types: {}", e),
         };
 
+        let state_types = match &parsed.aggregate {
+            Some(accumulator) => accumulator
+                .state_fields
+                .iter()
+                .map(|(_, t)| ArrowType::try_from(&t.data_type))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![],
+        };
+
         self.dylib_udfs.insert(
             parsed.name.clone(),
             DylibUdfConfig {
                 dylib_path: url.to_string(),
                 arg_types,
                 return_type: ArrowType::try_from(&parsed.ret_type.data_type)?,
+                is_async: parsed.async_options.is_some(),
+                is_aggregate: parsed.aggregate.is_some(),
+                state_types,
             },
         );
 
-        if self
+        if let Some(accumulator) = &parsed.aggregate {
+            let name = parsed.name.clone();
+            // accumulators are only created by the workers, which load the UDAF from its dylib
+            let accumulator_factory: AccumulatorFactoryFunction =
+                Arc::new(move |_: &DataType| -> DFResult<Box<dyn Accumulator>> {
+                    Err(DataFusionError::Plan(format!(
+                        "UDAF {} can't be evaluated during planning",
+                        name
+                    )))
+                });
+
+            if self
+                .aggregate_functions
+                .insert(
+                    parsed.name.clone(),
+                    Arc::new(create_udaf(
+                        &parsed.name,
+                        parsed.args.iter().map(|t| t.data_type.clone()).collect(),
+                        Arc::new(parsed.ret_type.data_type.clone()),
+                        Volatility::Volatile,
+                        accumulator_factory,
+                        Arc::new(
+                            accumulator
+                                .state_fields
+                                .iter()
+                                .map(|(_, t)| t.data_type.clone())
+                                .collect(),
+                        ),
+                    )),
+                )
+                .is_some()
+            {
+                warn!("Global UDAF '{}' is being overwritten", parsed.name);
+            };
+        } else if self
             .functions
             .insert(
                 parsed.name.clone(),
@@ -364,6 +796,7 @@ impl ArroyoSchemaProvider {
             UdfDef {
                 args: parsed.args,
                 ret: parsed.ret_type,
+                async_options: parsed.async_options,
                 def: parsed.definition,
                 dependencies: parsed.dependencies,
             },
@@ -533,12 +966,21 @@ fn find_window(expression: &Expr) -> Result<Option<WindowType>> {
 pub async fn parse_and_get_arrow_program(
     query: String,
     mut schema_provider: ArroyoSchemaProvider,
-    // TODO: use config
-    _config: SqlConfig,
+    config: SqlConfig,
 ) -> Result<CompiledSql> {
     let dialect = PostgreSqlDialect {};
     let mut inserts = vec![];
     for statement in Parser::parse_sql(&dialect, &query)? {
+        if let Statement::SetVariable {
+            variable, value, ..
+        } = &statement
+        {
+            schema_provider
+                .planning_options
+                .set(&variable.to_string().to_lowercase(), value)?;
+            continue;
+        }
+
         if let Some(table) = Table::try_from_statement(&statement, &schema_provider)
             .context("failed in try_from statement")?
         {
@@ -556,7 +998,7 @@ pub async fn parse_and_get_arrow_program(
     }
 
     let mut used_connections = HashSet::new();
-    let mut plan_to_graph_visitor = PlanToGraphVisitor::default();
+    let mut plan_to_graph_visitor = PlanToGraphVisitor::new(&schema_provider);
 
     for insert in inserts {
         let (plan, sink_name) = match insert {
@@ -584,9 +1026,16 @@ pub async fn parse_and_get_arrow_program(
                 let table = schema_provider
                     .get_table(&sink_name)
                     .ok_or_else(|| anyhow!("Connection {} not found", sink_name))?;
-                let Table::ConnectorTable(_connector_table) = table else {
+                let Table::ConnectorTable(connector_table) = table else {
                     bail!("expected connector table");
                 };
+                if is_updating(plan_rewrite.schema()) && !connector_table.is_update() {
+                    bail!(
+                        "the query writes updating data (from a non-windowed aggregate) to {}, which requires an updating format like 'debezium_json'",
+                        sink_name
+                    );
+                }
+                connector_table.validate_sink_fields(plan_rewrite.schema().fields())?;
                 SinkExtension::new(
                     OwnedTableReference::bare(sink_name),
                     table.clone(),
@@ -607,7 +1056,12 @@ pub async fn parse_and_get_arrow_program(
             node: Arc::new(sink),
         }))?;
     }
-    let graph = plan_to_graph_visitor.into_graph();
+    let graph = plan_to_graph_visitor.into_graph(
+        schema_provider
+            .planning_options
+            .parallelism
+            .unwrap_or(config.default_parallelism),
+    );
     let program = LogicalProgram {
         graph,
         program_config: ProgramConfig {
@@ -671,6 +1125,19 @@ pub fn has_duplicate_udf_names<'a>(definitions: impl Iterator<Item = &'a String>
             continue;
         };
 
+        // the name of a UDAF comes from its accumulator struct rather than a function
+        if let Ok(ParsedUdf {
+            name,
+            aggregate: Some(_),
+            ..
+        }) = ParsedUdf::try_parse(definition)
+        {
+            if !udf_names.insert(name) {
+                return true;
+            }
+            continue;
+        }
+
         for item in file.items {
             let Item::Fn(function) = item else {
                 continue;
@@ -746,4 +1213,149 @@ pub fn my_udf() -> i64 {
         "#;
         assert!(parse_dependencies(definition).is_err());
     }
+
+    #[test]
+    fn test_parse_async_udf() {
+        let definition = r#"
+#[udf(ordered, max_concurrency = 50, timeout = "10 seconds", retries = 0)]
+pub async fn score(x: i64) -> i64 {
+    x
+}
+        "#;
+
+        let parsed = ParsedUdf::try_parse(definition).unwrap();
+        assert_eq!(
+            parsed.async_options,
+            Some(AsyncUdfOptions {
+                ordered: true,
+                max_concurrency: 50,
+                timeout: Duration::from_secs(10),
+                retries: 0,
+            })
+        );
+        assert!(!parsed.definition.contains("#[udf"));
+
+        let definition = r#"
+pub async fn score(x: i64) -> i64 {
+    x
+}
+        "#;
+        assert_eq!(
+            ParsedUdf::try_parse(definition).unwrap().async_options,
+            Some(AsyncUdfOptions::default())
+        );
+
+        let definition = r#"
+#[udf(ordered)]
+pub fn score(x: i64) -> i64 {
+    x
+}
+        "#;
+        assert!(ParsedUdf::try_parse(definition).is_err());
+    }
+
+    #[test]
+    fn test_parse_udaf() {
+        let definition = r#"
+#[udaf]
+struct WeightedAverage {
+    sum: f64,
+    weight: Option<f64>,
+}
+
+impl WeightedAverage {
+    fn init() -> Self {
+        Self { sum: 0.0, weight: None }
+    }
+
+    fn update(&mut self, value: f64, weight: f64) {
+        self.sum += value * weight;
+        self.weight = Some(self.weight.unwrap_or_default() + weight);
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.sum += other.sum;
+        self.weight = Some(self.weight.unwrap_or_default() + other.weight.unwrap_or_default());
+    }
+
+    fn finish(&self) -> Option<f64> {
+        Some(self.sum / self.weight?)
+    }
+}
+        "#;
+
+        let parsed = ParsedUdf::try_parse(definition).unwrap();
+        assert_eq!(parsed.name, "weighted_average");
+        assert_eq!(
+            parsed.args,
+            vec![
+                NullableType::not_null(DataType::Float64),
+                NullableType::not_null(DataType::Float64)
+            ]
+        );
+        assert_eq!(parsed.ret_type, NullableType::null(DataType::Float64));
+        let accumulator = parsed.aggregate.unwrap();
+        assert_eq!(accumulator.struct_name, "WeightedAverage");
+        assert_eq!(
+            accumulator.state_fields,
+            vec![
+                ("sum".to_string(), NullableType::not_null(DataType::Float64)),
+                ("weight".to_string(), NullableType::null(DataType::Float64)),
+            ]
+        );
+        assert!(!parsed.definition.contains("#[udaf"));
+        assert!(parsed.definition.contains("pub fn update"));
+
+        let definition = r#"
+#[udaf(name = "my_mean")]
+struct Mean {
+    sum: f64,
+}
+
+impl Mean {
+    fn init() -> Self {
+        Self { sum: 0.0 }
+    }
+
+    fn update(&mut self, value: f64) {
+        self.sum += value;
+    }
+
+    fn finish(&self) -> f64 {
+        self.sum
+    }
+}
+        "#;
+        // missing merge
+        assert!(ParsedUdf::try_parse(definition).is_err());
+
+        let definition = r#"
+#[udaf]
+struct Count {
+    count: i64,
+}
+
+impl Count {
+    fn init() -> Self {
+        Self { count: 0 }
+    }
+
+    fn update(&mut self) {
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.count += other.count;
+    }
+
+    fn finish(&self) -> i64 {
+        self.count
+    }
+}
+        "#;
+        // updates are driven by their arguments, so a UDAF must take at least one
+        let err = ParsedUdf::try_parse(definition).unwrap_err();
+        assert!(err.to_string().contains("at least one argument"), "{}", err);
+        assert!(udfs::lib_rs(definition).is_err());
+    }
 }
@@ -2,12 +2,15 @@ use arrow::ffi::{from_ffi, to_ffi, FFI_ArrowArray};
 use dlopen2::wrapper::WrapperApi;
 use std::{
     any::Any,
+    ffi::c_void,
     mem,
+    path::PathBuf,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
 use crate::types::array_to_columnar_value;
-use arrow_array::{Array, ArrayRef, RecordBatch, StructArray};
+use arrow_array::{make_array, Array, ArrayRef, RecordBatch, StructArray};
 use arrow_schema::ffi::FFI_ArrowSchema;
 use arrow_schema::{DataType, Schema, SchemaRef, TimeUnit};
 use arroyo_datastream::logical::DylibUdfConfig;
@@ -32,16 +35,19 @@ use arroyo_rpc::grpc::api::{arroyo_exec_node, ArroyoExecNode, MemExecNode, Unnes
 use arroyo_storage::StorageProvider;
 use datafusion::physical_plan::unnest::UnnestExec;
 use datafusion_expr::{
-    ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature, TypeSignature, Volatility,
+    Accumulator, AggregateUDFImpl, ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature,
+    TypeSignature, Volatility,
 };
 use datafusion_physical_expr::expressions::Column;
 use datafusion_proto::physical_plan::PhysicalExtensionCodec;
+use datafusion_proto::protobuf::ArrowType;
 use dlopen2::wrapper::Container;
 use prost::Message;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use std::path::Path;
 use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::oneshot;
 use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
 
 pub fn window_function(columns: &[ColumnarValue]) -> DFResult<ColumnarValue> {
@@ -191,34 +197,325 @@ impl UdfDylib {
             Volatility::Volatile,
         );
 
-        let udf = StorageProvider::get_url(&config.dylib_path)
-            .await
-            .unwrap_or_else(|e| {
-                panic!(
-                    "Unable to fetch UDF dylib from '{}': {:?}",
-                    config.dylib_path, e
-                )
-            });
+        Self {
+            name: name.to_string(),
+            signature,
+            udf: unsafe { Container::load(fetch_dylib(config).await).unwrap() },
+            return_type: DataType::try_from(&config.return_type)
+                .expect("Failed to convert ArrowType to DataType"),
+        }
+    }
+}
 
-        // write the dylib to a local file
-        let local_udfs_dir = "/tmp/arroyo/local_udfs";
-        std::fs::create_dir_all(local_udfs_dir).expect("unable to create local udfs dir");
+/// Downloads a UDF dylib from the object store to a local file, returning its path
+async fn fetch_dylib(config: &DylibUdfConfig) -> PathBuf {
+    let udf = StorageProvider::get_url(&config.dylib_path)
+        .await
+        .unwrap_or_else(|e| {
+            panic!(
+                "Unable to fetch UDF dylib from '{}': {:?}",
+                config.dylib_path, e
+            )
+        });
+
+    // write the dylib to a local file
+    let local_udfs_dir = "/tmp/arroyo/local_udfs";
+    std::fs::create_dir_all(local_udfs_dir).expect("unable to create local udfs dir");
 
-        let dylib_file_name = Path::new(&config.dylib_path)
-            .file_name()
-            .expect("Invalid dylib path");
-        let local_dylib_path = Path::new(local_udfs_dir).join(dylib_file_name);
+    let dylib_file_name = Path::new(&config.dylib_path)
+        .file_name()
+        .expect("Invalid dylib path");
+    let local_dylib_path = Path::new(local_udfs_dir).join(dylib_file_name);
 
-        std::fs::write(&local_dylib_path, udf).expect("unable to write dylib to file");
+    // the same dylib may be loaded concurrently by multiple subtasks, so we write it to a
+    // temporary file and move it into place to avoid replacing a library that's already mapped
+    let tmp_path = local_dylib_path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&tmp_path, udf).expect("unable to write dylib to file");
+    std::fs::rename(&tmp_path, &local_dylib_path).expect("unable to move dylib into place");
 
+    local_dylib_path
+}
+
+#[derive(WrapperApi)]
+struct AsyncUdfDylibInterface {
+    run_async: unsafe extern "C" fn(
+        args_ptr: *mut FfiArraySchemaPair,
+        args_len: usize,
+        args_capacity: usize,
+        timeout_micros: u64,
+        callback: extern "C" fn(*mut c_void, *mut FfiArraySchemaPair),
+        context: *mut c_void,
+    ),
+}
+
+/// An async UDF, which is executed by its own operator rather than as part of a DataFusion plan
+pub struct AsyncUdfDylib {
+    name: String,
+    return_type: DataType,
+    udf: Container<AsyncUdfDylibInterface>,
+}
+
+impl Debug for AsyncUdfDylib {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncUdfDylib")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl AsyncUdfDylib {
+    /// Download an async UDF dylib from the object store
+    pub async fn init(name: &str, config: &DylibUdfConfig) -> Self {
         Self {
             name: name.to_string(),
-            signature,
-            udf: unsafe { Container::load(local_dylib_path).unwrap() },
+            udf: unsafe { Container::load(fetch_dylib(config).await).unwrap() },
             return_type: DataType::try_from(&config.return_type)
                 .expect("Failed to convert ArrowType to DataType"),
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn return_type(&self) -> &DataType {
+        &self.return_type
+    }
+
+    /// Starts a call of the UDF with a single row of arguments. The receiver resolves to the
+    /// single-row result, or None if the call timed out.
+    pub fn call(
+        &self,
+        args: &[ArrayRef],
+        timeout: Duration,
+    ) -> oneshot::Receiver<Option<ArrayRef>> {
+        let (tx, rx) = oneshot::channel();
+
+        let mut args = args
+            .iter()
+            .map(|arg| {
+                let (array, schema) = to_ffi(&arg.to_data()).unwrap();
+                FfiArraySchemaPair(array, schema)
+            })
+            .collect::<Vec<_>>();
+
+        let context = Box::into_raw(Box::new(tx)) as *mut c_void;
+
+        unsafe {
+            (self.udf.run_async)(
+                args.as_mut_ptr(),
+                args.len(),
+                args.capacity(),
+                timeout.as_micros() as u64,
+                async_udf_callback,
+                context,
+            )
+        };
+
+        // the UDF dylib is responsible for freeing the memory of the args
+        mem::forget(args);
+
+        rx
+    }
+}
+
+/// Called by an async UDF dylib (from its own runtime) when a call completes
+extern "C" fn async_udf_callback(context: *mut c_void, result: *mut FfiArraySchemaPair) {
+    let tx = unsafe { Box::from_raw(context as *mut oneshot::Sender<Option<ArrayRef>>) };
+
+    let result = (!result.is_null()).then(|| {
+        let FfiArraySchemaPair(array, schema) = unsafe { std::ptr::read(result) };
+        make_array(unsafe { from_ffi(array, &schema) }.unwrap())
+    });
+
+    // the receiver will have been dropped if the operator has shut down
+    let _ = tx.send(result);
+}
+
+#[derive(WrapperApi)]
+struct UdafDylibInterface {
+    udaf_new: unsafe extern "C" fn() -> *mut c_void,
+    udaf_update: unsafe extern "C" fn(
+        accumulator: *mut c_void,
+        args_ptr: *mut FfiArraySchemaPair,
+        args_len: usize,
+        args_capacity: usize,
+    ),
+    udaf_merge: unsafe extern "C" fn(
+        accumulator: *mut c_void,
+        states_ptr: *mut FfiArraySchemaPair,
+        states_len: usize,
+        states_capacity: usize,
+    ),
+    udaf_state: unsafe extern "C" fn(accumulator: *mut c_void) -> FfiArraySchemaPair,
+    udaf_evaluate: unsafe extern "C" fn(accumulator: *mut c_void) -> FfiArraySchemaPair,
+    udaf_drop: unsafe extern "C" fn(accumulator: *mut c_void),
+}
+
+/// A UDAF, whose accumulators live in the dylib and are driven through [DylibAccumulator]
+pub struct UdafDylib {
+    name: String,
+    signature: Signature,
+    return_type: DataType,
+    state_types: Vec<DataType>,
+    udaf: Arc<Container<UdafDylibInterface>>,
+}
+
+impl Debug for UdafDylib {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UdafDylib")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl UdafDylib {
+    /// Download a UDAF dylib from the object store
+    pub async fn init(name: &str, config: &DylibUdfConfig) -> Self {
+        let to_data_type =
+            |t: &ArrowType| DataType::try_from(t).expect("Failed to convert ArrowType to DataType");
+
+        Self {
+            name: name.to_string(),
+            signature: Signature::exact(
+                config.arg_types.iter().map(to_data_type).collect(),
+                Volatility::Volatile,
+            ),
+            return_type: to_data_type(&config.return_type),
+            state_types: config.state_types.iter().map(to_data_type).collect(),
+            udaf: Arc::new(unsafe { Container::load(fetch_dylib(config).await).unwrap() }),
+        }
+    }
+}
+
+impl AggregateUDFImpl for UdafDylib {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> DFResult<DataType> {
+        Ok(self.return_type.clone())
+    }
+
+    fn accumulator(&self, _arg: &DataType) -> DFResult<Box<dyn Accumulator>> {
+        Ok(Box::new(DylibAccumulator {
+            name: self.name.clone(),
+            accumulator: unsafe { (self.udaf.udaf_new)() },
+            udaf: self.udaf.clone(),
+        }))
+    }
+
+    fn state_type(&self, _return_type: &DataType) -> DFResult<Vec<DataType>> {
+        Ok(self.state_types.clone())
+    }
+}
+
+/// An accumulator owned by a UDAF dylib. Its state is the fields of the UDAF's struct, which
+/// means it's checkpointed along with the rest of the partial aggregate.
+struct DylibAccumulator {
+    name: String,
+    accumulator: *mut c_void,
+    udaf: Arc<Container<UdafDylibInterface>>,
+}
+
+// the accumulator is only ever accessed through the &mut or & of its owner
+unsafe impl Send for DylibAccumulator {}
+unsafe impl Sync for DylibAccumulator {}
+
+impl Debug for DylibAccumulator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DylibAccumulator")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl DylibAccumulator {
+    fn to_ffi_args(values: &[ArrayRef]) -> Vec<FfiArraySchemaPair> {
+        values
+            .iter()
+            .map(|value| {
+                let (array, schema) = to_ffi(&value.to_data()).unwrap();
+                FfiArraySchemaPair(array, schema)
+            })
+            .collect()
+    }
+
+    fn from_ffi_result(result: FfiArraySchemaPair) -> ArrayRef {
+        let FfiArraySchemaPair(array, schema) = result;
+        make_array(unsafe { from_ffi(array, &schema) }.unwrap())
+    }
+}
+
+impl Accumulator for DylibAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DFResult<()> {
+        let mut args = Self::to_ffi_args(values);
+        unsafe {
+            (self.udaf.udaf_update)(
+                self.accumulator,
+                args.as_mut_ptr(),
+                args.len(),
+                args.capacity(),
+            )
+        };
+        // the UDAF dylib is responsible for freeing the memory of the args
+        mem::forget(args);
+        Ok(())
+    }
+
+    fn evaluate(&self) -> DFResult<ScalarValue> {
+        let result = Self::from_ffi_result(unsafe { (self.udaf.udaf_evaluate)(self.accumulator) });
+        ScalarValue::try_from_array(&result, 0)
+    }
+
+    fn size(&self) -> usize {
+        mem::size_of_val(self)
+    }
+
+    fn state(&self) -> DFResult<Vec<ScalarValue>> {
+        // the state is returned as a single-row struct with a column per field
+        let state = Self::from_ffi_result(unsafe { (self.udaf.udaf_state)(self.accumulator) });
+        let state = state
+            .as_any()
+            .downcast_ref::<StructArray>()
+            .ok_or_else(|| {
+                DataFusionError::Execution(format!("state of UDAF {} is not a struct", self.name))
+            })?;
+
+        state
+            .columns()
+            .iter()
+            .map(|column| ScalarValue::try_from_array(column, 0))
+            .collect()
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DFResult<()> {
+        let mut states = Self::to_ffi_args(states);
+        unsafe {
+            (self.udaf.udaf_merge)(
+                self.accumulator,
+                states.as_mut_ptr(),
+                states.len(),
+                states.capacity(),
+            )
+        };
+        mem::forget(states);
+        Ok(())
+    }
+}
+
+impl Drop for DylibAccumulator {
+    fn drop(&mut self) {
+        unsafe { (self.udaf.udaf_drop)(self.accumulator) };
+    }
 }
 
 #[repr(C)]
@@ -1,16 +1,20 @@
 use crate::extension::aggregate::AggregateExtension;
 use crate::extension::key_calculation::KeyCalculationExtension;
+use crate::extension::updating_aggregate::UpdatingAggregateExtension;
 use crate::plan::WindowDetectingVisitor;
-use crate::{find_window, WindowBehavior};
+use crate::schemas::is_updating;
+use crate::{find_window, ArroyoSchemaProvider, WindowBehavior};
 use datafusion_common::tree_node::{TreeNode, TreeNodeRewriter};
-use datafusion_common::{DFField, DFSchema, DataFusionError, Result as DFResult};
+use datafusion_common::{plan_err, DFField, DFSchema, DataFusionError, Result as DFResult};
 use datafusion_expr::{Aggregate, Expr, Extension, LogicalPlan};
 use std::sync::Arc;
 
-#[derive(Debug, Default)]
-pub struct AggregateRewriter {}
+#[derive(Debug)]
+pub struct AggregateRewriter<'a> {
+    pub(crate) schema_provider: &'a ArroyoSchemaProvider,
+}
 
-impl TreeNodeRewriter for AggregateRewriter {
+impl<'a> TreeNodeRewriter for AggregateRewriter<'a> {
     type N = LogicalPlan;
 
     fn mutate(&mut self, node: Self::N) -> DFResult<Self::N> {
@@ -24,6 +28,13 @@ impl TreeNodeRewriter for AggregateRewriter {
         else {
             return Ok(node);
         };
+
+        if is_updating(input.schema()) {
+            return plan_err!(
+                "aggregating over the updating output of a non-windowed aggregate is not supported"
+            );
+        }
+
         let mut window_group_expr: Vec<_> = group_expr
             .iter()
             .enumerate()
@@ -70,27 +81,31 @@ impl TreeNodeRewriter for AggregateRewriter {
                         "window in group by does not match input window".to_string(),
                     ));
                 }
-                group_expr[window_index] = Expr::Column(window_detecting_visitor.fields.iter().next().unwrap().qualified_column());
-                WindowBehavior::InData
+                group_expr[window_index] = Expr::Column(
+                    window_detecting_visitor
+                        .fields
+                        .iter()
+                        .next()
+                        .unwrap()
+                        .qualified_column(),
+                );
+                Some(WindowBehavior::InData)
             }
-            (true, false) => WindowBehavior::InData,
+            (true, false) => Some(WindowBehavior::InData),
             (false, true) => {
                 // strip out window from group by, will be handled by operator.
                 let (window_index, window_type) = window_group_expr.pop().unwrap();
                 group_expr.remove(window_index);
                 key_fields.remove(window_index);
                 let window_field = schema.field(window_index).clone();
-                WindowBehavior::FromOperator {
+                Some(WindowBehavior::FromOperator {
                     window: window_type,
                     window_field,
                     window_index,
-                }
-            }
-            (false, false) => {
-                return Err(DataFusionError::NotImplemented(
-                    format!("must have window in aggregate. Make sure you are calling one of the windowing functions (hop, tumble, session) or using the window field of the input"),
-                ))
+                })
             }
+            // without a window, this becomes an updating aggregate
+            (false, false) => None,
         };
 
         let key_count = key_fields.len();
@@ -124,11 +139,11 @@ impl TreeNodeRewriter for AggregateRewriter {
             )),
         });
         let mut aggregate_schema_fields = schema.fields().clone();
-        if let WindowBehavior::FromOperator {
+        if let Some(WindowBehavior::FromOperator {
             window: _,
             window_field: _,
             window_index,
-        } = &window_behavior
+        }) = &window_behavior
         {
             aggregate_schema_fields.remove(*window_index);
         }
@@ -144,6 +159,17 @@ impl TreeNodeRewriter for AggregateRewriter {
             internal_schema,
         )?;
 
+        let Some(window_behavior) = window_behavior else {
+            let updating_extension = UpdatingAggregateExtension::new(
+                LogicalPlan::Aggregate(rewritten_aggregate),
+                (0..key_count).collect(),
+                self.schema_provider.planning_options.updating_ttl,
+            )?;
+            return Ok(LogicalPlan::Extension(Extension {
+                node: Arc::new(updating_extension),
+            }));
+        };
+
         let aggregate_extension = AggregateExtension::new(
             window_behavior,
             LogicalPlan::Aggregate(rewritten_aggregate),
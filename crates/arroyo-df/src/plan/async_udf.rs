@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use datafusion_common::{
+    plan_err,
+    tree_node::{Transformed, TreeNode, VisitRecursion},
+    Column, DataFusionError, Result as DFResult,
+};
+use datafusion_expr::{
+    expr::ScalarFunction, Expr, Extension, LogicalPlan, Projection, ScalarFunctionDefinition,
+};
+
+use crate::{
+    extension::async_udf::{AsyncUdfExtension, ASYNC_RESULT_FIELD},
+    schemas::is_updating,
+    ArroyoSchemaProvider,
+};
+
+/// Lifts calls to async UDFs out of projections. Each call is computed by an [AsyncUdfExtension]
+/// below the projection, which appends the result to its input rows, and the projection then
+/// refers to that result column.
+pub(crate) struct AsyncUdfRewriter<'a> {
+    pub(crate) schema_provider: &'a ArroyoSchemaProvider,
+}
+
+impl<'a> AsyncUdfRewriter<'a> {
+    fn async_udf_name<'b>(&self, expr: &'b Expr) -> Option<&'b str> {
+        let Expr::ScalarFunction(ScalarFunction {
+            func_def: ScalarFunctionDefinition::UDF(udf),
+            ..
+        }) = expr
+        else {
+            return None;
+        };
+
+        self.schema_provider
+            .udf_defs
+            .get(udf.name())
+            .filter(|def| def.async_options.is_some())
+            .map(|_| udf.name())
+    }
+
+    /// Finds a call to an async UDF whose arguments don't themselves call async UDFs
+    fn find_call(&self, exprs: &[Expr]) -> DFResult<Option<Expr>> {
+        let mut call = None;
+        for expr in exprs {
+            expr.apply(&mut |e| {
+                // parents are visited before their children, so the last call we find can't
+                // have another call in its arguments
+                if self.async_udf_name(e).is_some() {
+                    call = Some(e.clone());
+                }
+                Ok(VisitRecursion::Continue)
+            })?;
+        }
+        Ok(call)
+    }
+
+    /// Async UDFs are only supported in projections; this fails if any other node calls one
+    pub(crate) fn check_unsupported(&self, node: &LogicalPlan) -> DFResult<()> {
+        if let Some(call) = self.find_call(&node.expressions())? {
+            return plan_err!(
+                "async UDF {} can only be called in SELECT expressions",
+                self.async_udf_name(&call).unwrap()
+            );
+        }
+        Ok(())
+    }
+
+    pub(crate) fn rewrite_projection(&self, projection: &mut Projection) -> DFResult<()> {
+        let names = projection
+            .expr
+            .iter()
+            .map(|e| e.display_name())
+            .collect::<DFResult<Vec<_>>>()?;
+
+        let mut rewritten = false;
+        while let Some(call) = self.find_call(&projection.expr)? {
+            let Expr::ScalarFunction(ScalarFunction { args, .. }) = &call else {
+                unreachable!("found a call that isn't a function");
+            };
+            let name = self.async_udf_name(&call).unwrap();
+
+            if is_updating(projection.input.schema()) {
+                return plan_err!(
+                    "async UDF {} can't be called on the updating output of a non-windowed aggregate",
+                    name
+                );
+            }
+
+            let def = &self.schema_provider.udf_defs[name];
+            let dylib = self.schema_provider.dylib_udfs.get(name).ok_or_else(|| {
+                DataFusionError::Plan(format!("no dylib found for async UDF {}", name))
+            })?;
+
+            let result_field = format!(
+                "{}_{}",
+                ASYNC_RESULT_FIELD,
+                projection
+                    .input
+                    .schema()
+                    .fields()
+                    .iter()
+                    .filter(|f| f.name().starts_with(ASYNC_RESULT_FIELD))
+                    .count()
+            );
+
+            let extension = AsyncUdfExtension::new(
+                projection.input.as_ref().clone(),
+                name.to_string(),
+                dylib.dylib_path.clone(),
+                def.args.iter().map(|arg| arg.data_type.clone()).collect(),
+                def.ret.data_type.clone(),
+                def.async_options.unwrap(),
+                args.clone(),
+                result_field.clone(),
+            )?;
+
+            let result_column = Expr::Column(Column::new_unqualified(result_field));
+            projection.expr = projection
+                .expr
+                .drain(..)
+                .map(|expr| {
+                    expr.transform_up(&|e| {
+                        Ok(if e == call {
+                            Transformed::Yes(result_column.clone())
+                        } else {
+                            Transformed::No(e)
+                        })
+                    })
+                })
+                .collect::<DFResult<_>>()?;
+
+            projection.input = Arc::new(LogicalPlan::Extension(Extension {
+                node: Arc::new(extension),
+            }));
+            rewritten = true;
+        }
+
+        if rewritten {
+            // alias rewritten expressions to their original names so the schema is unchanged
+            for (expr, name) in projection.expr.iter_mut().zip(names) {
+                if expr.display_name()? != name {
+                    *expr = expr.clone().alias(name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,21 +1,187 @@
-use crate::extension::join::JoinExtension;
+use crate::extension::join::{JoinExtension, JoinTimeBounds};
 use crate::extension::key_calculation::KeyCalculationExtension;
+use crate::extension::lookup::{LookupJoinExtension, LookupSource, LOOKUP_SOURCE_NAME};
+use crate::extension::remote_table::REMOTE_TABLE_NAME;
+use crate::extension::watermark_node::WATERMARK_NODE_NAME;
 use crate::plan::WindowDetectingVisitor;
+use crate::schemas::is_updating;
+use arrow::datatypes::{IntervalDayTimeType, IntervalMonthDayNanoType};
 use arroyo_datastream::WindowType;
-use datafusion_common::tree_node::TreeNodeRewriter;
+use arroyo_rpc::TIMESTAMP_FIELD;
+use datafusion_common::tree_node::{Transformed, TreeNode, TreeNodeRewriter, VisitRecursion};
 use datafusion_common::{
     Column, DFField, DFSchema, DataFusionError, JoinConstraint, JoinType, Result as DFResult,
     ScalarValue,
 };
 use datafusion_expr::expr::{Alias, ScalarFunction};
+use datafusion_expr::utils::split_conjunction;
 use datafusion_expr::{
-    BinaryExpr, BuiltinScalarFunction, Case, Expr, Extension, Join, LogicalPlan, Projection,
+    Between, BinaryExpr, BuiltinScalarFunction, Case, Expr, Extension, Filter, Join, LogicalPlan,
+    Operator, Projection, SubqueryAlias,
 };
 use std::sync::Arc;
 
+const NANOS_PER_DAY: i64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Converts an interval literal into signed nanoseconds; intervals with months are not supported,
+/// as they don't have a fixed length
+fn interval_nanos(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Literal(ScalarValue::IntervalMonthDayNano(Some(val))) => {
+            let (months, days, nanos) = IntervalMonthDayNanoType::to_parts(*val);
+            (months == 0).then(|| days as i64 * NANOS_PER_DAY + nanos)
+        }
+        Expr::Literal(ScalarValue::IntervalDayTime(Some(val))) => {
+            let (days, millis) = IntervalDayTimeType::to_parts(*val);
+            Some(days as i64 * NANOS_PER_DAY + millis as i64 * 1_000_000)
+        }
+        _ => None,
+    }
+}
+
 pub(crate) struct JoinRewriter {}
 
 impl JoinRewriter {
+    /// Finds a lookup table scanned by this side of a join, along with any filters that have
+    /// been pushed down onto it
+    fn find_lookup(plan: &LogicalPlan) -> DFResult<Option<(LookupSource, Vec<Expr>)>> {
+        let mut filters = vec![];
+        let mut plan = plan;
+        loop {
+            match plan {
+                LogicalPlan::SubqueryAlias(alias) => {
+                    plan = &alias.input;
+                }
+                LogicalPlan::Filter(filter) => {
+                    filters.push(filter.predicate.clone());
+                    plan = &filter.input;
+                }
+                LogicalPlan::Extension(Extension { node }) if node.name() == LOOKUP_SOURCE_NAME => {
+                    let lookup = node.as_any().downcast_ref::<LookupSource>().unwrap();
+                    return Ok(Some((lookup.clone(), filters)));
+                }
+                _ => {
+                    let mut found = false;
+                    plan.apply(&mut |p| {
+                        if let LogicalPlan::Extension(Extension { node }) = p {
+                            found |= node.name() == LOOKUP_SOURCE_NAME;
+                        }
+                        Ok(VisitRecursion::Continue)
+                    })?;
+
+                    return if found {
+                        Err(DataFusionError::Plan(
+                            "lookup tables must be joined directly, without a subquery".into(),
+                        ))
+                    } else {
+                        Ok(None)
+                    };
+                }
+            }
+        }
+    }
+
+    fn lookup_join(join: Join, lookup: LookupSource, filters: Vec<Expr>) -> DFResult<LogicalPlan> {
+        let Join {
+            left,
+            right,
+            on,
+            filter,
+            join_type,
+            join_constraint: JoinConstraint::On,
+            schema,
+            null_equals_null: false,
+        } = join
+        else {
+            return Err(DataFusionError::NotImplemented(
+                "can't handle join constraint other than ON".into(),
+            ));
+        };
+
+        if !matches!(join_type, JoinType::Inner | JoinType::Left) {
+            return Err(DataFusionError::NotImplemented(
+                "lookup joins must be INNER or LEFT joins".into(),
+            ));
+        }
+
+        if filter.is_some() {
+            return Err(DataFusionError::NotImplemented(
+                "lookup joins may only have a single equality condition on the primary key of the lookup table".into(),
+            ));
+        }
+
+        if !filters.is_empty() && join_type != JoinType::Inner {
+            return Err(DataFusionError::NotImplemented(
+                "conditions on a lookup table are only supported for INNER joins".into(),
+            ));
+        }
+
+        let key = lookup.table.lookup_key().ok_or_else(|| {
+            DataFusionError::Plan(format!("lookup table {} has no key", lookup.table.name))
+        })?;
+
+        let [(left_expr, Expr::Column(right_column))] = on.as_slice() else {
+            return Err(DataFusionError::NotImplemented(format!(
+                "lookup joins must join on the primary key of the lookup table ({}) with a single equality condition",
+                key
+            )));
+        };
+
+        if &right_column.name != key {
+            return Err(DataFusionError::Plan(format!(
+                "lookup joins must join on the primary key of the lookup table ({}), not {}",
+                key, right_column.name
+            )));
+        }
+
+        // remove the timestamp of the lookup side, so that the output has the timestamp of the
+        // input row
+        let left_fields = left.schema().fields().len();
+        let fields = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(i, f)| *i < left_fields || f.name() != TIMESTAMP_FIELD)
+            .map(|(_, f)| f.clone())
+            .collect();
+        let output_schema = Arc::new(DFSchema::new_with_metadata(
+            fields,
+            schema.metadata().clone(),
+        )?);
+
+        let qualifier = right
+            .schema()
+            .fields()
+            .first()
+            .and_then(|f| f.qualifier().cloned());
+
+        let mut plan = LogicalPlan::Extension(Extension {
+            node: Arc::new(LookupJoinExtension::new(
+                left.as_ref().clone(),
+                output_schema,
+                lookup.table,
+                left_expr.clone(),
+                join_type,
+            )),
+        });
+
+        // filters that were pushed down onto the lookup table are applied to the joined rows
+        for predicate in filters {
+            let predicate = predicate.transform_up(&|e| {
+                Ok(match e {
+                    Expr::Column(c) => {
+                        Transformed::Yes(Expr::Column(Column::new(qualifier.clone(), c.name)))
+                    }
+                    e => Transformed::No(e),
+                })
+            })?;
+
+            plan = LogicalPlan::Filter(Filter::try_new(predicate, Arc::new(plan))?);
+        }
+
+        Ok(plan)
+    }
+
     fn check_join_windowing(join: &Join) -> DFResult<bool> {
         let left_window = WindowDetectingVisitor::get_window(&join.left)?;
         let right_window = WindowDetectingVisitor::get_window(&join.right)?;
@@ -55,6 +221,145 @@ impl JoinRewriter {
         }
     }
 
+    /// Whether the named output column of the plan always holds its event time, either because it
+    /// is the timestamp column itself or because it is the column the timestamp was taken from
+    /// (like a source's event_time_field)
+    fn is_event_time(plan: &LogicalPlan, name: &str) -> bool {
+        if name == TIMESTAMP_FIELD {
+            return true;
+        }
+
+        match plan {
+            LogicalPlan::Projection(projection) => {
+                let position = |name: &str| {
+                    projection
+                        .schema
+                        .fields()
+                        .iter()
+                        .position(|f| f.name() == name)
+                };
+                let (Some(index), Some(timestamp_index)) =
+                    (position(name), position(TIMESTAMP_FIELD))
+                else {
+                    return false;
+                };
+
+                match (
+                    projection.expr[index].clone().unalias(),
+                    projection.expr[timestamp_index].clone().unalias(),
+                ) {
+                    (expr, timestamp) if expr == timestamp => true,
+                    // both columns are passed through from the input
+                    (Expr::Column(column), Expr::Column(timestamp))
+                        if timestamp.name == TIMESTAMP_FIELD =>
+                    {
+                        Self::is_event_time(&projection.input, &column.name)
+                    }
+                    _ => false,
+                }
+            }
+            LogicalPlan::SubqueryAlias(SubqueryAlias { input, .. })
+            | LogicalPlan::Filter(Filter { input, .. }) => Self::is_event_time(input, name),
+            LogicalPlan::Extension(Extension { node })
+                if node.name() == WATERMARK_NODE_NAME || node.name() == REMOTE_TABLE_NAME =>
+            {
+                Self::is_event_time(node.inputs()[0], name)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns Some(true) if the column is the event time of the left side of the join,
+    /// Some(false) if it's the event time of the right side, and None otherwise
+    fn event_time_side(join: &Join, column: &Column) -> Option<bool> {
+        for (input, is_left) in [(&join.left, true), (&join.right, false)] {
+            if input.schema().index_of_column(column).is_ok() {
+                return Self::is_event_time(input, &column.name).then_some(is_left);
+            }
+        }
+        None
+    }
+
+    /// Splits an expression like `a.ts - INTERVAL '5' MINUTE` into its column and signed offset
+    fn time_term(expr: &Expr) -> Option<(&Column, i64)> {
+        match expr {
+            Expr::Column(column) => Some((column, 0)),
+            Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                let (column, offset) = Self::time_term(left)?;
+                let interval = interval_nanos(right)?;
+                match op {
+                    Operator::Plus => Some((column, offset + interval)),
+                    Operator::Minus => Some((column, offset - interval)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Normalizes a comparison between the event times of the two sides into the form
+    /// `right time - left time <op> bound`
+    fn time_bound(join: &Join, left: &Expr, op: Operator, right: &Expr) -> Option<(Operator, i64)> {
+        let (left_column, left_offset) = Self::time_term(left)?;
+        let (right_column, right_offset) = Self::time_term(right)?;
+
+        match (
+            Self::event_time_side(join, left_column)?,
+            Self::event_time_side(join, right_column)?,
+        ) {
+            // r + lo <op> l + ro => r - l <op> ro - lo
+            (false, true) => Some((op, right_offset - left_offset)),
+            // l + lo <op> r + ro => r - l <swapped op> lo - ro
+            (true, false) => Some((op.swap()?, left_offset - right_offset)),
+            _ => None,
+        }
+    }
+
+    /// Finds bounds on the difference between the event times of the two sides in the join
+    /// condition, as in `b.ts BETWEEN a.ts - INTERVAL '5' MINUTE AND a.ts + INTERVAL '10' MINUTE`.
+    /// Returns None unless the difference is bounded in both directions.
+    fn time_bounds(join: &Join) -> Option<JoinTimeBounds> {
+        let filter = join.filter.as_ref()?;
+
+        let mut comparisons = vec![];
+        for conjunct in split_conjunction(filter) {
+            match conjunct {
+                Expr::Between(Between {
+                    expr,
+                    negated: false,
+                    low,
+                    high,
+                }) => {
+                    comparisons.push((expr.as_ref(), Operator::GtEq, low.as_ref()));
+                    comparisons.push((expr.as_ref(), Operator::LtEq, high.as_ref()));
+                }
+                Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                    comparisons.push((left.as_ref(), *op, right.as_ref()));
+                }
+                _ => {}
+            }
+        }
+
+        let mut lower: Option<i64> = None;
+        let mut upper: Option<i64> = None;
+        for (left, op, right) in comparisons {
+            match Self::time_bound(join, left, op, right) {
+                Some((Operator::Gt | Operator::GtEq, bound)) => {
+                    lower = Some(lower.map_or(bound, |l| l.max(bound)));
+                }
+                Some((Operator::Lt | Operator::LtEq, bound)) => {
+                    upper = Some(upper.map_or(bound, |u| u.min(bound)));
+                }
+                _ => {}
+            }
+        }
+
+        Some(JoinTimeBounds {
+            lower_nanos: lower?,
+            upper_nanos: upper?,
+        })
+    }
+
     fn create_join_key_plan(
         &self,
         input: Arc<LogicalPlan>,
@@ -187,8 +492,33 @@ impl TreeNodeRewriter for JoinRewriter {
         let LogicalPlan::Join(join) = node else {
             return Ok(node);
         };
+
+        if Self::find_lookup(&join.left)?.is_some() {
+            return Err(DataFusionError::Plan(
+                "lookup tables must be on the right side of a join".into(),
+            ));
+        }
+
+        if let Some((lookup, filters)) = Self::find_lookup(&join.right)? {
+            return Self::lookup_join(join, lookup, filters);
+        }
+
+        if is_updating(join.left.schema()) || is_updating(join.right.schema()) {
+            return Err(DataFusionError::NotImplemented(
+                "joining the updating output of a non-windowed aggregate is not supported".into(),
+            ));
+        }
+
         let is_instant = Self::check_join_windowing(&join)?;
 
+        // non-windowed inner joins whose conditions bound the difference between the two sides'
+        // event times only need to keep each row for as long as it can still be matched
+        let time_bounds = if !is_instant && join.join_type == JoinType::Inner {
+            Self::time_bounds(&join)
+        } else {
+            None
+        };
+
         let Join {
             left,
             right,
@@ -225,6 +555,7 @@ impl TreeNodeRewriter for JoinRewriter {
         let join_extension = JoinExtension {
             rewritten_join: final_logical_plan,
             is_instant,
+            time_bounds,
         };
 
         Ok(LogicalPlan::Extension(Extension {
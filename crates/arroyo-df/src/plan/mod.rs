@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 
 use arroyo_datastream::WindowType;
-use arroyo_rpc::TIMESTAMP_FIELD;
+use arroyo_rpc::{TIMESTAMP_FIELD, UPDATING_META_FIELD};
 use datafusion_common::{
     plan_err,
     tree_node::{TreeNode, TreeNodeRewriter, TreeNodeVisitor, VisitRecursion},
@@ -9,6 +9,7 @@ use datafusion_common::{
 };
 
 use aggregate::AggregateRewriter;
+use async_udf::AsyncUdfRewriter;
 use datafusion_expr::{expr::Alias, Aggregate, Expr, Extension, LogicalPlan};
 use join::JoinRewriter;
 
@@ -19,13 +20,14 @@ use crate::{
     },
     find_window,
     rewriters::SourceRewriter,
-    schemas::{add_timestamp_field, has_timestamp_field},
+    schemas::{add_timestamp_field, add_updating_meta_field, has_timestamp_field, is_updating},
     ArroyoSchemaProvider, WindowBehavior,
 };
 
 use self::window_fn::WindowFunctionRewriter;
 
 mod aggregate;
+mod async_udf;
 mod join;
 mod window_fn;
 
@@ -253,8 +255,32 @@ impl<'a> TreeNodeRewriter for ArroyoRewriter<'a> {
     type N = LogicalPlan;
 
     fn mutate(&mut self, mut node: Self::N) -> DFResult<Self::N> {
+        let async_udf_rewriter = AsyncUdfRewriter {
+            schema_provider: self.schema_provider,
+        };
+        if !matches!(node, LogicalPlan::Projection(_)) {
+            async_udf_rewriter.check_unsupported(&node)?;
+        }
+
         match node {
             LogicalPlan::Projection(ref mut projection) => {
+                async_udf_rewriter.rewrite_projection(projection)?;
+
+                // updating data needs to carry its retraction metadata through to the sink
+                if is_updating(projection.input.schema()) && !is_updating(&projection.schema) {
+                    let meta_field = projection
+                        .input
+                        .schema()
+                        .field_with_unqualified_name(UPDATING_META_FIELD)?
+                        .clone();
+                    projection.schema = add_updating_meta_field(
+                        projection.schema.clone(),
+                        meta_field.qualifier().cloned(),
+                    )?;
+                    projection
+                        .expr
+                        .push(Expr::Column(meta_field.qualified_column()));
+                }
                 if !has_timestamp_field(projection.schema.clone()) {
                     let timestamp_field = projection
                         .input
@@ -274,7 +300,10 @@ impl<'a> TreeNodeRewriter for ArroyoRewriter<'a> {
                 }
             }
             LogicalPlan::Aggregate(aggregate) => {
-                return AggregateRewriter {}.mutate(LogicalPlan::Aggregate(aggregate));
+                return AggregateRewriter {
+                    schema_provider: self.schema_provider,
+                }
+                .mutate(LogicalPlan::Aggregate(aggregate));
             }
             LogicalPlan::Join(join) => {
                 return JoinRewriter {}.mutate(LogicalPlan::Join(join));
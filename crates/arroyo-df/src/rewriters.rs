@@ -1,3 +1,4 @@
+use crate::extension::lookup::{LookupJoinExtension, LookupSource};
 use crate::extension::remote_table::RemoteTableExtension;
 use crate::extension::sink::SinkExtension;
 use crate::extension::table_source::TableSourceExtension;
@@ -8,6 +9,7 @@ use crate::tables::Table;
 use crate::ArroyoSchemaProvider;
 
 use arrow_schema::DataType;
+use arroyo_rpc::api_types::connections::ConnectionType;
 use arroyo_rpc::TIMESTAMP_FIELD;
 
 use datafusion_common::tree_node::{
@@ -233,6 +235,14 @@ impl<'a> TreeNodeRewriter for SourceRewriter<'a> {
             .ok_or_else(|| DataFusionError::Plan(format!("Table {} not found", table_name)))?;
 
         match table {
+            Table::ConnectorTable(table) if table.connection_type == ConnectionType::Lookup => {
+                Ok(LogicalPlan::Extension(Extension {
+                    node: Arc::new(LookupSource::new(
+                        table_scan.table_name.clone(),
+                        table.clone(),
+                    )),
+                }))
+            }
             Table::ConnectorTable(table) => self.mutate_connector_table(&table_scan, table),
             Table::MemoryTable { .. } => Err(DataFusionError::Plan(
                 "Memory tables are not supported yet".to_string(),
@@ -438,6 +448,11 @@ impl<'a> SourceMetadataVisitor<'a> {
                 let SinkExtension { name, .. } = node.as_any().downcast_ref::<SinkExtension>()?;
                 name.to_string()
             }
+            "LookupJoinExtension" => {
+                let LookupJoinExtension { connector, .. } =
+                    node.as_any().downcast_ref::<LookupJoinExtension>()?;
+                return connector.id;
+            }
             _ => return None,
         };
         let table = self.schema_provider.get_table(&table_name)?;
@@ -1,5 +1,6 @@
 use arrow::datatypes::{DataType, TimeUnit};
 use arrow_schema::{Field, Schema, SchemaRef};
+use arroyo_rpc::{updating_meta_field, UPDATING_META_FIELD};
 use datafusion_common::{DFField, DFSchema, DFSchemaRef, OwnedTableReference, Result as DFResult};
 use std::{collections::HashMap, sync::Arc};
 
@@ -48,6 +49,35 @@ pub(crate) fn has_timestamp_field(schema: DFSchemaRef) -> bool {
         .any(|field| field.name() == "_timestamp")
 }
 
+pub(crate) fn add_updating_meta_field(
+    schema: DFSchemaRef,
+    qualifier: Option<OwnedTableReference>,
+) -> DFResult<DFSchemaRef> {
+    if is_updating(&schema) {
+        return Ok(schema);
+    }
+
+    let field = updating_meta_field();
+    let meta_field = DFField::new(
+        qualifier,
+        UPDATING_META_FIELD,
+        field.data_type().clone(),
+        false,
+    );
+    Ok(Arc::new(schema.join(&DFSchema::new_with_metadata(
+        vec![meta_field],
+        HashMap::new(),
+    )?)?))
+}
+
+/// Whether the schema belongs to an updating (changelog) stream, which carries retractions
+pub(crate) fn is_updating(schema: &DFSchemaRef) -> bool {
+    schema
+        .fields()
+        .iter()
+        .any(|field| field.name() == UPDATING_META_FIELD)
+}
+
 pub fn add_timestamp_field_arrow(schema: SchemaRef) -> SchemaRef {
     let mut fields = schema.fields().to_vec();
     fields.push(Arc::new(Field::new(
@@ -11,7 +11,7 @@ use arroyo_operator::connector::Connection;
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionType, SourceField,
 };
-use arroyo_rpc::formats::{BadData, Format, Framing};
+use arroyo_rpc::formats::{BadData, Format, Framing, ProtobufFormat};
 use arroyo_rpc::grpc::api::ConnectorOp;
 use arroyo_types::ArroyoExtensionType;
 use datafusion::sql::planner::PlannerContext;
@@ -55,6 +55,10 @@ pub struct ConnectorTable {
     pub event_time_field: Option<String>,
     pub watermark_field: Option<String>,
     pub idle_time: Option<Duration>,
+    pub primary_keys: Vec<String>,
+    pub lookup_cache_max_entries: Option<usize>,
+    pub lookup_cache_ttl: Option<Duration>,
+    pub parallelism: Option<usize>,
 
     pub inferred_fields: Option<Vec<DFField>>,
 }
@@ -129,6 +133,10 @@ impl From<Connection> for ConnectorTable {
             event_time_field: None,
             watermark_field: None,
             idle_time: DEFAULT_IDLE_TIME,
+            primary_keys: vec![],
+            lookup_cache_max_entries: None,
+            lookup_cache_ttl: None,
+            parallelism: None,
             inferred_fields: None,
         }
     }
@@ -139,11 +147,12 @@ impl ConnectorTable {
         name: &str,
         connector: &str,
         mut fields: Vec<FieldSpec>,
+        primary_keys: Vec<String>,
         options: &mut HashMap<String, String>,
         connection_profile: Option<&ConnectionProfile>,
     ) -> Result<Self> {
         // TODO: a more principled way of letting connectors dictate types to use
-        if "delta" == connector {
+        if "delta" == connector || "iceberg" == connector {
             fields = fields
                 .into_iter()
                 .map(|field_spec| match &field_spec {
@@ -155,9 +164,7 @@ impl ConnectorTable {
                         }
                         _ => field_spec,
                     },
-                    FieldSpec::VirtualField { .. } => {
-                        unreachable!("delta lake is only a sink, can't have virtual fields")
-                    }
+                    FieldSpec::VirtualField { .. } => field_spec,
                 })
                 .collect();
         }
@@ -166,6 +173,14 @@ impl ConnectorTable {
 
         let format = Format::from_opts(options).map_err(|e| anyhow!("invalid format: '{e}'"))?;
 
+        if let Some(Format::Protobuf(ProtobufFormat {
+            compiled_schema: None,
+            ..
+        })) = &format
+        {
+            bail!("protobuf tables must be created as connection tables with a protobuf schema; the format can't be used with a schema defined in SQL");
+        }
+
         let framing = Framing::from_opts(options).map_err(|e| anyhow!("invalid framing: '{e}'"))?;
 
         let schema_fields: Result<Vec<SourceField>> = fields
@@ -195,6 +210,29 @@ impl ConnectorTable {
             Some(fields.is_empty()),
         )?;
 
+        let lookup_cache_max_entries = options
+            .remove("lookup.cache.max_entries")
+            .map(|t| usize::from_str(&t))
+            .transpose()
+            .map_err(|_| anyhow!("lookup.cache.max_entries must be set to a number"))?;
+
+        let lookup_cache_ttl = options
+            .remove("lookup.cache.ttl_secs")
+            .map(|t| u64::from_str(&t))
+            .transpose()
+            .map_err(|_| anyhow!("lookup.cache.ttl_secs must be set to a number"))?
+            .map(Duration::from_secs);
+
+        let parallelism = options
+            .remove("parallelism")
+            .map(|t| usize::from_str(&t))
+            .transpose()
+            .map_err(|_| anyhow!("parallelism must be set to a number"))?;
+
+        if parallelism == Some(0) {
+            bail!("parallelism must be greater than 0");
+        }
+
         let connection =
             connector.from_options(name, options, Some(&schema), connection_profile)?;
 
@@ -205,6 +243,10 @@ impl ConnectorTable {
 
         table.event_time_field = options.remove("event_time_field");
         table.watermark_field = options.remove("watermark_field");
+        table.primary_keys = primary_keys;
+        table.lookup_cache_max_entries = lookup_cache_max_entries;
+        table.lookup_cache_ttl = lookup_cache_ttl;
+        table.parallelism = parallelism;
 
         table.idle_time = options
             .remove("idle_micros")
@@ -223,14 +265,86 @@ impl ConnectorTable {
             );
         }
 
+        if table.connection_type == ConnectionType::Lookup {
+            table.validate_lookup()?;
+        } else if table.lookup_cache_max_entries.is_some() || table.lookup_cache_ttl.is_some() {
+            bail!("lookup.cache options can only be set on lookup tables");
+        }
+
         Ok(table)
     }
 
+    fn validate_lookup(&self) -> Result<()> {
+        if self.has_virtual_fields() {
+            bail!("lookup tables cannot have virtual fields");
+        }
+
+        if self.event_time_field.is_some() || self.watermark_field.is_some() {
+            bail!("event_time_field and watermark_field cannot be set on lookup tables");
+        }
+
+        if self.parallelism.is_some() {
+            bail!("parallelism cannot be set on lookup tables");
+        }
+
+        if self.is_update() {
+            bail!("lookup tables cannot use an updating format");
+        }
+
+        let [key] = self.primary_keys.as_slice() else {
+            bail!("lookup tables must have a single PRIMARY KEY column, which is used as the lookup key");
+        };
+
+        let field = self
+            .fields
+            .iter()
+            .find(|f| f.field().name() == key)
+            .ok_or_else(|| anyhow!("primary key column '{}' not found", key))?;
+
+        if field.field().data_type() != &DataType::Utf8 {
+            bail!(
+                "primary key column '{}' of a lookup table must be TEXT",
+                key
+            );
+        }
+
+        if matches!(self.lookup_cache_max_entries, Some(0)) {
+            bail!("lookup.cache.max_entries must be greater than 0");
+        }
+
+        Ok(())
+    }
+
+    /// The key column of a lookup table
+    pub fn lookup_key(&self) -> Option<&String> {
+        (self.connection_type == ConnectionType::Lookup)
+            .then(|| self.primary_keys.first())
+            .flatten()
+    }
+
     fn has_virtual_fields(&self) -> bool {
         self.fields.iter().any(|f| f.is_virtual())
     }
 
-    fn is_update(&self) -> bool {
+    /// Checks that rows with the given fields can be written to this table by its sink
+    pub(crate) fn validate_sink_fields(&self, fields: &[DFField]) -> Result<()> {
+        if let Some(Format::Json(_)) = &self.format {
+            for field in fields {
+                arroyo_formats::json::ser::validate_data_type(field.data_type()).map_err(|e| {
+                    anyhow!(
+                        "field '{}' cannot be written to {} as JSON: {}",
+                        field.name(),
+                        self.name,
+                        e
+                    )
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn is_update(&self) -> bool {
         self.format
             .as_ref()
             .map(|f| f.is_updating())
@@ -297,7 +411,7 @@ impl ConnectorTable {
         )
     }
 
-    fn connector_op(&self) -> ConnectorOp {
+    pub(crate) fn connector_op(&self) -> ConnectorOp {
         ConnectorOp {
             connector: self.connector.clone(),
             config: self.config.clone(),
@@ -319,6 +433,9 @@ impl ConnectorTable {
             ConnectionType::Sink => {
                 bail!("cannot read from sink")
             }
+            ConnectionType::Lookup => {
+                bail!("lookup tables can only be read in a lookup join")
+            }
         };
 
         if self.is_update() && self.has_virtual_fields() {
@@ -491,6 +608,21 @@ impl Table {
 
             let connector = with_map.remove("connector");
             let fields = Self::schema_from_columns(columns, schema_provider)?;
+            let primary_keys = columns
+                .iter()
+                .filter(|c| {
+                    c.options.iter().any(|o| {
+                        matches!(
+                            o.option,
+                            ColumnOption::Unique {
+                                is_primary: true,
+                                ..
+                            }
+                        )
+                    })
+                })
+                .map(|c| c.name.value.to_string())
+                .collect();
 
             match connector.as_deref() {
                 Some("memory") | None => {
@@ -534,6 +666,7 @@ impl Table {
                             &name,
                             connector,
                             fields,
+                            primary_keys,
                             &mut with_map,
                             connection_profile,
                         )
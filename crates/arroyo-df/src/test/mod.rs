@@ -1,20 +1,26 @@
 mod plan_tests;
 
-use arrow_schema::DataType;
+use std::sync::Arc;
+
+use arrow_schema::{DataType, Field, Fields};
 use arroyo_connectors::{
     nexmark::{NexmarkConnector, NexmarkTable},
     EmptyConfig,
 };
-use arroyo_operator::connector::Connector;
+use arroyo_datastream::logical::{LogicalEdgeType, OperatorName};
+use arroyo_operator::connector::{Connection, Connector};
+use arroyo_rpc::formats::{Format, JsonFormat};
 use arroyo_types::NullableType;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
 use test_log::test;
 
+use crate::tables::ConnectorTable;
 use crate::{parse_and_get_program, ArroyoSchemaProvider, SqlConfig};
+use datafusion_common::DFField;
 
-fn get_test_schema_provider() -> ArroyoSchemaProvider {
-    let mut schema_provider = ArroyoSchemaProvider::new();
-
-    let nexmark = (NexmarkConnector {})
+fn nexmark_connection() -> Connection {
+    (NexmarkConnector {})
         .from_config(
             Some(1),
             "nexmark",
@@ -25,9 +31,13 @@ fn get_test_schema_provider() -> ArroyoSchemaProvider {
             },
             None,
         )
-        .unwrap();
+        .unwrap()
+}
 
-    schema_provider.add_connector_table(nexmark);
+fn get_test_schema_provider() -> ArroyoSchemaProvider {
+    let mut schema_provider = ArroyoSchemaProvider::new();
+
+    schema_provider.add_connector_table(nexmark_connection());
 
     schema_provider
 }
@@ -55,3 +65,209 @@ async fn test_udf() {
         .await
         .unwrap();
 }
+
+#[test(tokio::test)]
+async fn test_mixed_parallelism() {
+    let sql = "
+    SET parallelism = '8';
+
+    CREATE TABLE events WITH (
+        connector = 'nexmark',
+        event_rate = '10',
+        parallelism = '2'
+    );
+
+    SELECT bid.auction, count(*) as count
+    FROM events
+    WHERE bid is not null
+    GROUP BY 1, tumble(interval '1 second');
+    ";
+
+    let program = parse_and_get_program(sql, get_test_schema_provider(), SqlConfig::default())
+        .await
+        .unwrap()
+        .program;
+
+    let graph = &program.graph;
+    for node in graph.node_weights() {
+        match node.operator_name {
+            OperatorName::ConnectorSource => assert_eq!(node.parallelism, 2),
+            OperatorName::TumblingWindowAggregate => assert_eq!(node.parallelism, 8),
+            _ => {}
+        }
+    }
+
+    // operators chained to the source by forward edges run at its parallelism
+    for edge in graph.edge_indices() {
+        let (from, to) = graph.edge_endpoints(edge).unwrap();
+        if graph[edge].edge_type == LogicalEdgeType::Forward {
+            assert_eq!(graph[from].parallelism, graph[to].parallelism);
+        }
+    }
+}
+
+#[test(tokio::test)]
+async fn test_join_with_mixed_parallelism() {
+    let sql = "
+    SET parallelism = '32';
+
+    CREATE TABLE left_events WITH (
+        connector = 'nexmark',
+        event_rate = '10',
+        parallelism = '6'
+    );
+
+    CREATE TABLE right_events WITH (
+        connector = 'nexmark',
+        event_rate = '10'
+    );
+
+    SELECT l.bid.auction, r.bid.price
+    FROM left_events l
+    JOIN right_events r ON l.bid.auction = r.bid.auction
+    WHERE l.bid is not null AND r.bid is not null;
+    ";
+
+    let program = parse_and_get_program(sql, get_test_schema_provider(), SqlConfig::default())
+        .await
+        .unwrap()
+        .program;
+
+    let graph = &program.graph;
+    let join = graph
+        .node_indices()
+        .find(|idx| graph[*idx].operator_name == OperatorName::Join)
+        .expect("should plan a join");
+
+    // both sides of the join must arrive with the same number of partitions, even though one of
+    // the sources runs at a lower parallelism
+    let input_parallelism: Vec<_> = graph
+        .edges_directed(join, Direction::Incoming)
+        .map(|e| (e.weight().edge_type, graph[e.source()].parallelism))
+        .collect();
+    assert_eq!(input_parallelism.len(), 2);
+    for (edge_type, parallelism) in input_parallelism {
+        assert!(matches!(
+            edge_type,
+            LogicalEdgeType::LeftJoin | LogicalEdgeType::RightJoin
+        ));
+        assert_eq!(parallelism, 32);
+    }
+
+    assert!(graph
+        .node_weights()
+        .any(|node| node.operator_name == OperatorName::ConnectorSource && node.parallelism == 6));
+}
+
+#[test(tokio::test)]
+async fn test_interval_join() {
+    let tables = "
+    CREATE TABLE orders (
+        id BIGINT,
+        ts TIMESTAMP NOT NULL
+    ) WITH (
+        connector = 'single_file',
+        path = '/tmp/orders.json',
+        format = 'json',
+        type = 'source',
+        event_time_field = 'ts'
+    );
+
+    CREATE TABLE shipments (
+        order_id BIGINT,
+        ts TIMESTAMP NOT NULL
+    ) WITH (
+        connector = 'single_file',
+        path = '/tmp/shipments.json',
+        format = 'json',
+        type = 'source',
+        event_time_field = 'ts'
+    );
+    ";
+
+    let join_operators = |query: &'static str| async move {
+        let program = parse_and_get_program(
+            &format!("{}{}", tables, query),
+            get_test_schema_provider(),
+            SqlConfig::default(),
+        )
+        .await
+        .unwrap()
+        .program;
+
+        program
+            .graph
+            .node_weights()
+            .filter(|node| {
+                matches!(
+                    node.operator_name,
+                    OperatorName::Join | OperatorName::IntervalJoin
+                )
+            })
+            .map(|node| node.operator_name)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        join_operators(
+            "SELECT o.id FROM orders o JOIN shipments s ON o.id = s.order_id
+            AND s.ts BETWEEN o.ts - INTERVAL '5' MINUTE AND o.ts + INTERVAL '10' MINUTE;"
+        )
+        .await,
+        vec![OperatorName::IntervalJoin]
+    );
+
+    assert_eq!(
+        join_operators(
+            "SELECT o.id FROM orders o JOIN shipments s ON o.id = s.order_id
+            AND o.ts < s.ts + INTERVAL '1' HOUR AND s.ts <= o.ts;"
+        )
+        .await,
+        vec![OperatorName::IntervalJoin]
+    );
+
+    // bounded in only one direction, so rows may need to be kept forever
+    assert_eq!(
+        join_operators(
+            "SELECT o.id FROM orders o JOIN shipments s ON o.id = s.order_id
+            AND s.ts >= o.ts;"
+        )
+        .await,
+        vec![OperatorName::Join]
+    );
+}
+
+#[test]
+fn test_json_sink_fields_are_validated() {
+    let mut table = ConnectorTable::from(nexmark_connection());
+    table.format = Some(Format::Json(JsonFormat::default()));
+
+    let decimal = DFField::new_unqualified("price", DataType::Decimal128(38, 10), true);
+    table.validate_sink_fields(&[decimal.clone()]).unwrap();
+
+    // JSON object keys can't be built from lists
+    let map = DataType::Map(
+        Arc::new(Field::new(
+            "entries",
+            DataType::Struct(Fields::from(vec![
+                Field::new(
+                    "keys",
+                    DataType::List(Arc::new(Field::new("item", DataType::Int32, true))),
+                    false,
+                ),
+                Field::new("values", DataType::Utf8, true),
+            ])),
+            false,
+        )),
+        false,
+    );
+    let err = table
+        .validate_sink_fields(&[decimal, DFField::new_unqualified("tags", map, true)])
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("field 'tags' cannot be written to nexmark as JSON"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,8 @@
+CREATE TABLE nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT bid.auction, concat(enrich_auction(bid.auction), '-', bid.bidder) AS name
+FROM nexmark
+WHERE bid IS NOT NULL;
\ No newline at end of file
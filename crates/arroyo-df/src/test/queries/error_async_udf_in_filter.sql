@@ -0,0 +1,9 @@
+--fail=async UDF enrich_auction can only be called in SELECT expressions
+CREATE TABLE nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT bid.auction
+FROM nexmark
+WHERE enrich_auction(bid.auction) = 'auction-1';
\ No newline at end of file
@@ -0,0 +1,18 @@
+--fail=lookup joins must join on the primary key of the lookup table (id), not name
+CREATE TABLE impulse WITH (
+    connector = 'impulse',
+    event_rate = '10'
+);
+
+CREATE TABLE users (
+    id TEXT PRIMARY KEY,
+    name TEXT
+) WITH (
+    connector = 'http_lookup',
+    endpoint = 'http://localhost:8080/users/{key}',
+    format = 'json'
+);
+
+SELECT impulse.counter, users.id
+FROM impulse
+JOIN users ON CAST(impulse.counter AS TEXT) = users.name;
@@ -0,0 +1,19 @@
+--fail=protobuf tables must be created as connection tables with a protobuf schema
+CREATE TABLE impulse WITH (
+    connector = 'impulse',
+    event_rate = '10'
+);
+
+CREATE TABLE events (
+    id TEXT,
+    count BIGINT
+) WITH (
+    connector = 'single_file',
+    path = '/tmp/events.bin',
+    format = 'protobuf',
+    type = 'sink'
+);
+
+INSERT INTO events
+SELECT CAST(counter AS TEXT), counter
+FROM impulse;
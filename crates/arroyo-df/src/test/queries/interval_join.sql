@@ -0,0 +1,28 @@
+CREATE TABLE orders (
+  id BIGINT,
+  customer_id BIGINT,
+  ts TIMESTAMP NOT NULL
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/orders.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'ts'
+);
+
+CREATE TABLE shipments (
+  order_id BIGINT,
+  carrier TEXT,
+  ts TIMESTAMP NOT NULL
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/shipments.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'ts'
+);
+
+SELECT o.id, o.customer_id, s.carrier
+FROM orders o
+JOIN shipments s ON o.id = s.order_id
+  AND s.ts BETWEEN o.ts - INTERVAL '5' MINUTE AND o.ts + INTERVAL '10' MINUTE;
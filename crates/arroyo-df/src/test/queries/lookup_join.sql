@@ -0,0 +1,22 @@
+CREATE TABLE impulse WITH (
+    connector = 'impulse',
+    event_rate = '10'
+);
+
+CREATE TABLE users (
+    id TEXT PRIMARY KEY,
+    name TEXT,
+    country TEXT
+) WITH (
+    connector = 'redis',
+    address = 'redis://localhost:6379',
+    format = 'json',
+    type = 'lookup',
+    'lookup.key_prefix' = 'users:',
+    'lookup.cache.max_entries' = '1000',
+    'lookup.cache.ttl_secs' = '60'
+);
+
+SELECT impulse.counter, u.name
+FROM impulse
+LEFT JOIN users u ON CAST(impulse.counter AS TEXT) = u.id;
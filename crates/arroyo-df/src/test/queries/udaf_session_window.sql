@@ -0,0 +1,12 @@
+CREATE TABLE nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT
+    bid.auction as auction,
+    session(INTERVAL '30' second) as window,
+    geometric_mean(CAST(bid.price AS DOUBLE)) as price
+FROM nexmark
+WHERE bid IS NOT NULL
+GROUP BY 1, 2
@@ -0,0 +1,23 @@
+SET updating_ttl = '1 hour';
+
+CREATE TABLE Nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+CREATE TABLE auction_counts (
+    auction BIGINT,
+    bids BIGINT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'sink',
+    topic = 'auction_counts',
+    format = 'debezium_json'
+);
+
+INSERT INTO auction_counts
+SELECT bid.auction, count(*) as bids
+FROM nexmark
+WHERE bid is not null
+GROUP BY 1
\ No newline at end of file
@@ -0,0 +1,5 @@
+#[udf(ordered, max_concurrency = 100, timeout = "5 seconds")]
+async fn enrich_auction(auction: i64) -> Option<String> {
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    Some(format!("auction-{}", auction))
+}
@@ -0,0 +1,28 @@
+#[udaf]
+struct GeometricMean {
+    log_sum: f64,
+    count: i64,
+}
+
+impl GeometricMean {
+    fn init() -> Self {
+        Self {
+            log_sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn update(&mut self, value: f64) {
+        self.log_sum += value.ln();
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.log_sum += other.log_sum;
+        self.count += other.count;
+    }
+
+    fn finish(&self) -> Option<f64> {
+        (self.count > 0).then(|| (self.log_sum / self.count as f64).exp())
+    }
+}
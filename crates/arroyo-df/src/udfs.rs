@@ -22,6 +22,14 @@ edition = "2021"
 pub fn lib_rs(definition: &str) -> anyhow::Result<String> {
     let parsed = ParsedUdf::try_parse(definition)?;
 
+    if parsed.async_options.is_some() {
+        return async_lib_rs(&parsed);
+    }
+
+    if parsed.aggregate.is_some() {
+        return udaf_lib_rs(&parsed);
+    }
+
     let udf_name = format_ident!("{}", parsed.name);
 
     let results_builder = if matches!(parsed.ret_type.data_type, DataType::Utf8) {
@@ -176,6 +184,316 @@ pub fn lib_rs(definition: &str) -> anyhow::Result<String> {
     }))
 }
 
+/// Async UDFs are called one row at a time; each call is spawned onto a runtime owned by the
+/// dylib, and its result (or null, if it timed out) is passed back through `callback`
+fn async_lib_rs(parsed: &ParsedUdf) -> anyhow::Result<String> {
+    let udf_name = format_ident!("{}", parsed.name);
+
+    let results_builder = if matches!(parsed.ret_type.data_type, DataType::Utf8) {
+        quote!(let mut results_builder = array::StringBuilder::with_capacity(1, 64);)
+    } else {
+        let return_type = data_type_to_arrow_type_token(parsed.ret_type.data_type.clone())?;
+        quote!(let mut results_builder = array::PrimitiveBuilder::<datatypes::#return_type>::with_capacity(1);)
+    };
+
+    let mut defs = vec![];
+    let mut unwrapping = vec![];
+    let mut args = vec![];
+    for (i, arg_type) in parsed.args.iter().enumerate() {
+        let id = format_ident!("arg_{}", i);
+        defs.push(match &arg_type.data_type {
+            DataType::Utf8 => quote! {
+                let #id = array::StringArray::from(args[#i].clone());
+                let #id = (!#id.is_null(0)).then(|| #id.value(0).to_string());
+            },
+            data_type => {
+                let arrow_type = data_type_to_arrow_type_token(data_type.clone())?;
+                quote! {
+                    let #id = array::PrimitiveArray::<datatypes::#arrow_type>::from(args[#i].clone());
+                    let #id = (!#id.is_null(0)).then(|| #id.value(0));
+                }
+            }
+        });
+
+        if !arg_type.nullable {
+            unwrapping.push(quote! {
+                let Some(#id) = #id else {
+                    return Some(None);
+                };
+            });
+        }
+
+        args.push(quote!(#id));
+    }
+
+    let value = if parsed.ret_type.nullable {
+        quote!(value)
+    } else {
+        quote!(Some(value))
+    };
+
+    Ok(prettyplease::unparse(&parse_quote! {
+        use arrow::array;
+        use arrow::array::Array;
+        use arrow::datatypes;
+        use arrow::ffi::{FFI_ArrowArray, FFI_ArrowSchema, from_ffi, to_ffi};
+        use std::ffi::c_void;
+        use std::sync::OnceLock;
+        use std::time::Duration;
+        use udf;
+
+        #[repr(C)]
+        pub struct FfiArraySchemaPair(FFI_ArrowArray, FFI_ArrowSchema);
+
+        struct CallbackContext(*mut c_void);
+
+        unsafe impl Send for CallbackContext {}
+
+        static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+
+        #[no_mangle]
+        pub extern "C" fn run_async(
+            args_ptr: *mut FfiArraySchemaPair,
+            args_len: usize,
+            args_capacity: usize,
+            timeout_micros: u64,
+            callback: extern "C" fn(*mut c_void, *mut FfiArraySchemaPair),
+            context: *mut c_void,
+        ) {
+            let args = unsafe {
+                Vec::from_raw_parts(args_ptr, args_len, args_capacity)
+            };
+
+            let args = args
+                .into_iter()
+                .map(|pair| {
+                    let FfiArraySchemaPair(array, schema) = pair;
+                    unsafe { from_ffi(array, &schema).unwrap() }
+                })
+                .collect::<Vec<_>>();
+
+            #(#defs)*
+
+            let context = CallbackContext(context);
+            let timeout = Duration::from_micros(timeout_micros);
+
+            let runtime = RUNTIME.get_or_init(|| {
+                tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()
+                    .expect("failed to start the UDF runtime")
+            });
+
+            runtime.spawn(async move {
+                let context = context;
+
+                let result = async move {
+                    #(#unwrapping)*
+                    let value = tokio::time::timeout(timeout, udf::#udf_name(#(#args),*)).await.ok()?;
+                    Some(#value)
+                }.await;
+
+                match result {
+                    Some(value) => {
+                        #results_builder
+                        results_builder.append_option(value);
+
+                        let (array, schema) = to_ffi(&results_builder.finish().to_data()).unwrap();
+                        let mut result = FfiArraySchemaPair(array, schema);
+
+                        // the callback takes ownership of the result
+                        callback(context.0, &mut result);
+                        std::mem::forget(result);
+                    }
+                    None => callback(context.0, std::ptr::null_mut()),
+                }
+            });
+        }
+    }))
+}
+
+/// UDAF accumulators are created, updated and merged through the dylib, and are passed back
+/// and forth as opaque pointers. Their state is exported as a single-row struct array with a
+/// column per field of the accumulator struct.
+fn udaf_lib_rs(parsed: &ParsedUdf) -> anyhow::Result<String> {
+    let accumulator = parsed
+        .aggregate
+        .as_ref()
+        .expect("udaf_lib_rs called for a scalar UDF");
+    let struct_name = format_ident!("{}", accumulator.struct_name);
+
+    // the number of rows in each update is taken from the first argument
+    if parsed.args.is_empty() {
+        bail!(
+            "{}::update must take at least one argument",
+            accumulator.struct_name
+        );
+    }
+
+    let mut arg_defs = vec![];
+    let mut arg_values = vec![];
+    let mut args = vec![];
+    for (i, arg_type) in parsed.args.iter().enumerate() {
+        let array_id = format_ident!("array_{}", i);
+        let id = format_ident!("arg_{}", i);
+        let array_type = array_type_token(&arg_type.data_type)?;
+        arg_defs.push(quote!(let #array_id = #array_type::from(args[#i].clone());));
+
+        let value = value_token(&array_id, &arg_type.data_type);
+        arg_values.push(quote!(let #id = (!#array_id.is_null(row)).then(|| #value);));
+
+        // like SQL aggregates, rows with nulls are skipped unless the UDAF accepts them
+        if !arg_type.nullable {
+            arg_values.push(quote! {
+                let Some(#id) = #id else {
+                    continue;
+                };
+            });
+        }
+        args.push(quote!(#id));
+    }
+
+    let mut state_defs = vec![];
+    let mut state_values = vec![];
+    let mut state_fields = vec![];
+    let mut state_columns = vec![];
+    for (i, (field_name, field_type)) in accumulator.state_fields.iter().enumerate() {
+        let array_id = format_ident!("state_{}", i);
+        let id = format_ident!("field_{}", i);
+        let field = format_ident!("{}", field_name);
+        let array_type = array_type_token(&field_type.data_type)?;
+        state_defs.push(quote!(let #array_id = #array_type::from(states[#i].clone());));
+
+        let value = value_token(&array_id, &field_type.data_type);
+        state_values.push(quote!(let #id = (!#array_id.is_null(row)).then(|| #value);));
+        if !field_type.nullable {
+            state_values.push(quote! {
+                let Some(#id) = #id else {
+                    continue;
+                };
+            });
+        }
+        state_fields.push(quote!(#field: #id));
+
+        let column = if field_type.nullable {
+            quote!(accumulator.#field.clone())
+        } else {
+            quote!(Some(accumulator.#field.clone()))
+        };
+        state_columns.push(
+            quote!((#field_name, Arc::new(#array_type::from(vec![#column])) as array::ArrayRef)),
+        );
+    }
+
+    let result_type = array_type_token(&parsed.ret_type.data_type)?;
+    let result = if parsed.ret_type.nullable {
+        quote!(accumulator.finish())
+    } else {
+        quote!(Some(accumulator.finish()))
+    };
+
+    Ok(prettyplease::unparse(&parse_quote! {
+        use arrow::array;
+        use arrow::array::Array;
+        use arrow::datatypes;
+        use arrow::ffi::{FFI_ArrowArray, FFI_ArrowSchema, from_ffi, to_ffi};
+        use std::ffi::c_void;
+        use std::sync::Arc;
+        use udf;
+
+        #[repr(C)]
+        pub struct FfiArraySchemaPair(FFI_ArrowArray, FFI_ArrowSchema);
+
+        fn read_arrays(ptr: *mut FfiArraySchemaPair, len: usize, capacity: usize) -> Vec<array::ArrayData> {
+            let arrays = unsafe {
+                Vec::from_raw_parts(ptr, len, capacity)
+            };
+
+            arrays
+                .into_iter()
+                .map(|pair| {
+                    let FfiArraySchemaPair(array, schema) = pair;
+                    unsafe { from_ffi(array, &schema).unwrap() }
+                })
+                .collect()
+        }
+
+        fn export(array: impl Array) -> FfiArraySchemaPair {
+            let (array, schema) = to_ffi(&array.to_data()).unwrap();
+            FfiArraySchemaPair(array, schema)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn udaf_new() -> *mut c_void {
+            Box::into_raw(Box::new(udf::#struct_name::init())) as *mut c_void
+        }
+
+        #[no_mangle]
+        pub extern "C" fn udaf_update(accumulator: *mut c_void, args_ptr: *mut FfiArraySchemaPair, args_len: usize, args_capacity: usize) {
+            let accumulator = unsafe { &mut *(accumulator as *mut udf::#struct_name) };
+            let args = read_arrays(args_ptr, args_len, args_capacity);
+
+            #(#arg_defs)*
+
+            for row in 0..args[0].len() {
+                #(#arg_values)*
+                accumulator.update(#(#args),*);
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn udaf_merge(accumulator: *mut c_void, states_ptr: *mut FfiArraySchemaPair, states_len: usize, states_capacity: usize) {
+            let accumulator = unsafe { &mut *(accumulator as *mut udf::#struct_name) };
+            let states = read_arrays(states_ptr, states_len, states_capacity);
+
+            #(#state_defs)*
+
+            for row in 0..states[0].len() {
+                #(#state_values)*
+                accumulator.merge(udf::#struct_name {
+                    #(#state_fields),*
+                });
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn udaf_state(accumulator: *mut c_void) -> FfiArraySchemaPair {
+            let accumulator = unsafe { &*(accumulator as *const udf::#struct_name) };
+            export(array::StructArray::try_from(vec![#(#state_columns),*]).unwrap())
+        }
+
+        #[no_mangle]
+        pub extern "C" fn udaf_evaluate(accumulator: *mut c_void) -> FfiArraySchemaPair {
+            let accumulator = unsafe { &*(accumulator as *const udf::#struct_name) };
+            export(#result_type::from(vec![#result]))
+        }
+
+        #[no_mangle]
+        pub extern "C" fn udaf_drop(accumulator: *mut c_void) {
+            drop(unsafe { Box::from_raw(accumulator as *mut udf::#struct_name) });
+        }
+    }))
+}
+
+fn array_type_token(data_type: &DataType) -> anyhow::Result<TokenStream> {
+    Ok(match data_type {
+        DataType::Utf8 => quote!(array::StringArray),
+        DataType::Boolean => quote!(array::BooleanArray),
+        _ => {
+            let arrow_type = data_type_to_arrow_type_token(data_type.clone())?;
+            quote!(array::PrimitiveArray::<datatypes::#arrow_type>)
+        }
+    })
+}
+
+fn value_token(array: &proc_macro2::Ident, data_type: &DataType) -> TokenStream {
+    if matches!(data_type, DataType::Utf8) {
+        quote!(#array.value(row).to_string())
+    } else {
+        quote!(#array.value(row))
+    }
+}
+
 fn data_type_to_arrow_type_token(data_type: DataType) -> anyhow::Result<TokenStream> {
     match data_type {
         DataType::Utf8 => Ok(quote!(GenericStringType<i32>)),
@@ -281,7 +281,8 @@ mod tests {
                 arroyo_schema.clone(),
                 BadData::Fail {},
                 resolver,
-            ),
+            )
+            .unwrap(),
             builders,
             arroyo_schema,
         )
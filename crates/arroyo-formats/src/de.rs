@@ -1,10 +1,16 @@
 use crate::avro::de;
+use crate::proto;
+use apache_avro::types::Value as AvroValue;
+use apache_avro::AvroResult;
 use arrow_array::builder::{ArrayBuilder, StringBuilder, TimestampNanosecondBuilder};
 use arrow_array::{RecordBatch, StringArray};
 use arroyo_rpc::df::ArroyoSchema;
-use arroyo_rpc::formats::{AvroFormat, BadData, Format, Framing, FramingMethod, JsonFormat};
+use arroyo_rpc::formats::{
+    AvroFormat, BadData, Format, Framing, FramingMethod, JsonFormat, ProtobufFormat,
+};
 use arroyo_rpc::schema_resolver::{FailingSchemaResolver, FixedSchemaResolver, SchemaResolver};
 use arroyo_types::{should_flush, to_nanos, RawJson, SourceError};
+use prost_reflect::MessageDescriptor;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Instant, SystemTime};
@@ -92,6 +98,7 @@ pub struct ArrowDeserializer {
     buffered_since: Instant,
     schema_registry: Arc<Mutex<HashMap<u32, apache_avro::schema::Schema>>>,
     schema_resolver: Arc<dyn SchemaResolver + Sync>,
+    proto_descriptor: Option<MessageDescriptor>,
 }
 
 impl ArrowDeserializer {
@@ -100,7 +107,7 @@ impl ArrowDeserializer {
         schema: ArroyoSchema,
         framing: Option<Framing>,
         bad_data: BadData,
-    ) -> Self {
+    ) -> anyhow::Result<Self> {
         let resolver = if let Format::Avro(AvroFormat {
             reader_schema: Some(schema),
             ..
@@ -121,8 +128,13 @@ impl ArrowDeserializer {
         schema: ArroyoSchema,
         bad_data: BadData,
         schema_resolver: Arc<dyn SchemaResolver + Sync>,
-    ) -> Self {
-        Self {
+    ) -> anyhow::Result<Self> {
+        let proto_descriptor = match &format {
+            Format::Protobuf(proto) => Some(proto::schema::get_message_descriptor(proto)?),
+            _ => None,
+        };
+
+        Ok(Self {
             json_decoder: matches!(
                 format,
                 Format::Json(..)
@@ -130,6 +142,10 @@ impl ArrowDeserializer {
                         into_unstructured_json: false,
                         ..
                     })
+                    | Format::Protobuf(ProtobufFormat {
+                        into_unstructured_json: false,
+                        ..
+                    })
             )
             .then(|| {
                 // exclude the timestamp field
@@ -144,6 +160,7 @@ impl ArrowDeserializer {
                     TimestampNanosecondBuilder::new(),
                 )
             }),
+            proto_descriptor,
             format: Arc::new(format),
             framing: framing.map(|f| Arc::new(f)),
             schema,
@@ -152,7 +169,7 @@ impl ArrowDeserializer {
             schema_resolver,
             buffered_count: 0,
             buffered_since: Instant::now(),
-        }
+        })
     }
 
     pub async fn deserialize_slice(
@@ -222,6 +239,37 @@ impl ArrowDeserializer {
                 timestamp_builder.append_value(to_nanos(timestamp) as i64);
                 self.buffered_count += 1;
             }
+            Format::Protobuf(proto) => {
+                let Some(descriptor) = &self.proto_descriptor else {
+                    panic!("protobuf descriptor not initialized");
+                };
+
+                let json = proto::de::deserialize_proto(proto, descriptor, msg)?;
+
+                if proto.into_unstructured_json {
+                    let (idx, _) = self
+                        .schema
+                        .schema
+                        .column_with_name("value")
+                        .expect("no 'value' column for unstructured protobuf");
+                    buffer[idx]
+                        .as_any_mut()
+                        .downcast_mut::<StringBuilder>()
+                        .expect("'value' column has incorrect type")
+                        .append_value(json.to_string());
+                    add_timestamp(buffer, self.schema.timestamp_index, timestamp);
+                } else {
+                    let Some((decoder, timestamp_builder)) = &mut self.json_decoder else {
+                        panic!("json decoder not initialized");
+                    };
+
+                    decoder
+                        .decode(json.to_string().as_bytes())
+                        .map_err(|e| SourceError::bad_data(format!("invalid JSON: {:?}", e)))?;
+                    timestamp_builder.append_value(to_nanos(timestamp) as i64);
+                    self.buffered_count += 1;
+                }
+            }
             Format::Avro(_) => unreachable!("this should not be called for avro"),
             Format::Parquet(_) => todo!("parquet is not supported as an input format"),
         }
@@ -253,50 +301,60 @@ impl ArrowDeserializer {
             }
         };
 
-        let into_json = format.into_unstructured_json;
-        let errors = messages
+        messages
             .into_iter()
-            .map(|record| {
-                let value = record.map_err(|e| {
-                    SourceError::bad_data(format!("failed to deserialize from avro: {:?}", e))
-                })?;
-
-                if into_json {
-                    let (idx, _) = self
-                        .schema
-                        .schema
-                        .column_with_name("value")
-                        .expect("no 'value' column for unstructed avro");
-                    let array = builders[idx]
-                        .as_any_mut()
-                        .downcast_mut::<StringBuilder>()
-                        .expect("'value' column has incorrect type");
+            .map(|record| self.deserialize_avro_value(builders, record, timestamp))
+            .filter_map(|r: Result<(), SourceError>| r.err())
+            .collect()
+    }
 
-                    array.append_value(de::avro_to_json(value).to_string());
-                    add_timestamp(builders, self.schema.timestamp_index, timestamp);
-                    self.buffered_count += 1;
-                } else {
-                    // for now round-trip through json in order to handle unsupported avro features
-                    // as that allows us to rely on raw json deserialization
-                    let json = de::avro_to_json(value).to_string();
+    /// Deserializes a single decoded avro value, for example one read from an Avro object
+    /// container file
+    pub fn deserialize_avro_value(
+        &mut self,
+        builders: &mut Vec<Box<dyn ArrayBuilder>>,
+        record: AvroResult<AvroValue>,
+        timestamp: SystemTime,
+    ) -> Result<(), SourceError> {
+        let Format::Avro(format) = &*self.format else {
+            unreachable!("not avro");
+        };
 
-                    let Some((decoder, timestamp_builder)) = &mut self.json_decoder else {
-                        panic!("json decoder not initialized");
-                    };
+        let value = record.map_err(|e| {
+            SourceError::bad_data(format!("failed to deserialize from avro: {:?}", e))
+        })?;
+
+        if format.into_unstructured_json {
+            let (idx, _) = self
+                .schema
+                .schema
+                .column_with_name("value")
+                .expect("no 'value' column for unstructed avro");
+            let array = builders[idx]
+                .as_any_mut()
+                .downcast_mut::<StringBuilder>()
+                .expect("'value' column has incorrect type");
+
+            array.append_value(de::avro_to_json(value).to_string());
+            add_timestamp(builders, self.schema.timestamp_index, timestamp);
+            self.buffered_count += 1;
+        } else {
+            // for now round-trip through json in order to handle unsupported avro features
+            // as that allows us to rely on raw json deserialization
+            let json = de::avro_to_json(value).to_string();
 
-                    decoder
-                        .decode(json.as_bytes())
-                        .map_err(|e| SourceError::bad_data(format!("invalid JSON: {:?}", e)))?;
-                    self.buffered_count += 1;
-                    timestamp_builder.append_value(to_nanos(timestamp) as i64);
-                }
+            let Some((decoder, timestamp_builder)) = &mut self.json_decoder else {
+                panic!("json decoder not initialized");
+            };
 
-                Ok(())
-            })
-            .filter_map(|r: Result<(), SourceError>| r.err())
-            .collect();
+            decoder
+                .decode(json.as_bytes())
+                .map_err(|e| SourceError::bad_data(format!("invalid JSON: {:?}", e)))?;
+            self.buffered_count += 1;
+            timestamp_builder.append_value(to_nanos(timestamp) as i64);
+        }
 
-        errors
+        Ok(())
     }
 
     fn deserialize_raw_string(&mut self, buffer: &mut Vec<Box<dyn ArrayBuilder>>, msg: &[u8]) {
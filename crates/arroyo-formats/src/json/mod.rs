@@ -1,10 +1,11 @@
-use arrow::datatypes::{Field, Fields, SchemaRef};
+use arrow::datatypes::{DataType, Field, Fields, SchemaRef};
 use arrow_array::builder::{ArrayBuilder, StringBuilder};
-use arroyo_rpc::formats::JsonFormat;
+use arroyo_rpc::formats::{DecimalEncoding, JsonFormat};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 
 pub mod schema;
+pub mod ser;
 
 pub fn deserialize_slice_json(
     schema: &SchemaRef,
@@ -160,68 +161,96 @@ pub mod opt_timestamp_as_millis {
     }
 }
 
-pub fn field_to_json_schema(field: &Field) -> Value {
-    match field.data_type() {
-        arrow::datatypes::DataType::Null => {
+pub fn field_to_json_schema(field: &Field, decimal_encoding: &DecimalEncoding) -> Value {
+    data_type_to_json_schema(field.data_type(), decimal_encoding)
+}
+
+fn data_type_to_json_schema(data_type: &DataType, decimal_encoding: &DecimalEncoding) -> Value {
+    match data_type {
+        DataType::Null => {
             json! {{ "type": "null" }}
         }
-        arrow::datatypes::DataType::Boolean => {
+        DataType::Boolean => {
             json! {{ "type": "boolean" }}
         }
-        arrow::datatypes::DataType::Int8
-        | arrow::datatypes::DataType::Int16
-        | arrow::datatypes::DataType::Int32
-        | arrow::datatypes::DataType::Int64
-        | arrow::datatypes::DataType::UInt8
-        | arrow::datatypes::DataType::UInt16
-        | arrow::datatypes::DataType::UInt32
-        | arrow::datatypes::DataType::UInt64 => {
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64 => {
             // TODO: integer bounds
             json! {{ "type": "integer" }}
         }
-        arrow::datatypes::DataType::Float16
-        | arrow::datatypes::DataType::Float32
-        | arrow::datatypes::DataType::Float64 => {
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => {
             json! {{ "type": "number" }}
         }
-        arrow::datatypes::DataType::Timestamp(_, _) => {
+        DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => match decimal_encoding {
+            DecimalEncoding::Number => json! {{ "type": "number" }},
+            DecimalEncoding::String => json! {{ "type": "string" }},
+        },
+        DataType::Timestamp(_, _) => {
             json! {{ "type": "string", "format": "date-time" }}
         }
-        arrow::datatypes::DataType::Date32
-        | arrow::datatypes::DataType::Date64
-        | arrow::datatypes::DataType::Time32(_)
-        | arrow::datatypes::DataType::Time64(_) => {
-            todo!()
+        DataType::Date32 | DataType::Date64 => {
+            json! {{ "type": "string", "format": "date" }}
+        }
+        DataType::Time32(_) | DataType::Time64(_) => {
+            json! {{ "type": "string", "format": "time" }}
+        }
+        // durations are written as an integer count of their unit
+        DataType::Duration(_) => {
+            json! {{ "type": "integer" }}
+        }
+        DataType::Interval(_) => {
+            json! {{ "type": "string" }}
         }
-        arrow::datatypes::DataType::Duration(_) => todo!(),
-        arrow::datatypes::DataType::Interval(_) => todo!(),
-        arrow::datatypes::DataType::Binary
-        | arrow::datatypes::DataType::FixedSizeBinary(_)
-        | arrow::datatypes::DataType::LargeBinary => {
+        DataType::Binary | DataType::FixedSizeBinary(_) | DataType::LargeBinary => {
             json! {{ "type": "array", "items": { "type": "integer" }}}
         }
-        arrow::datatypes::DataType::Utf8 | arrow::datatypes::DataType::LargeUtf8 => {
+        DataType::Utf8 | DataType::LargeUtf8 => {
             json! {{ "type": "string" }}
         }
-        arrow::datatypes::DataType::List(t)
-        | arrow::datatypes::DataType::FixedSizeList(t, _)
-        | arrow::datatypes::DataType::LargeList(t) => {
-            json! {{"type": "array", "items": field_to_json_schema(&*t) }}
+        DataType::List(t) | DataType::FixedSizeList(t, _) | DataType::LargeList(t) => {
+            json! {{"type": "array", "items": field_to_json_schema(t, decimal_encoding) }}
+        }
+        DataType::Struct(s) => arrow_to_json_schema(s, decimal_encoding),
+        // unions are written as an object with a single property set, for whichever variant
+        // the row holds
+        DataType::Union(fields, _) => {
+            let props: HashMap<String, Value> = fields
+                .iter()
+                .map(|(_, f)| (f.name().clone(), field_to_json_schema(f, decimal_encoding)))
+                .collect();
+
+            json! {{
+                "type": "object",
+                "properties": props,
+                "maxProperties": 1,
+            }}
+        }
+        DataType::Dictionary(_, t) => data_type_to_json_schema(t, decimal_encoding),
+        DataType::RunEndEncoded(_, values) => field_to_json_schema(values, decimal_encoding),
+        // map keys are always written as strings
+        DataType::Map(entries, _) => {
+            let DataType::Struct(kv) = entries.data_type() else {
+                unreachable!("map entries must be a struct");
+            };
+
+            json! {{
+                "type": "object",
+                "additionalProperties": field_to_json_schema(&kv[1], decimal_encoding),
+            }}
         }
-        arrow::datatypes::DataType::Struct(s) => arrow_to_json_schema(s),
-        arrow::datatypes::DataType::Union(_, _) => todo!(),
-        arrow::datatypes::DataType::Dictionary(_, _) => todo!(),
-        arrow::datatypes::DataType::Decimal128(_, _) => todo!(),
-        arrow::datatypes::DataType::Decimal256(_, _) => todo!(),
-        arrow::datatypes::DataType::Map(_, _) => todo!(),
-        arrow::datatypes::DataType::RunEndEncoded(_, _) => todo!(),
     }
 }
 
-pub fn arrow_to_json_schema(fields: &Fields) -> Value {
+pub fn arrow_to_json_schema(fields: &Fields, decimal_encoding: &DecimalEncoding) -> Value {
     let props: HashMap<String, Value> = fields
         .iter()
-        .map(|f| (f.name().clone(), field_to_json_schema(f)))
+        .map(|f| (f.name().clone(), field_to_json_schema(f, decimal_encoding)))
         .collect();
 
     let required: Vec<String> = fields
@@ -236,11 +265,32 @@ pub fn arrow_to_json_schema(fields: &Fields) -> Value {
     }}
 }
 
-pub fn field_to_kafka_json(field: &Field) -> Value {
+pub fn field_to_kafka_json(field: &Field, decimal_encoding: &DecimalEncoding) -> Value {
+    data_type_to_kafka_json(
+        field.name(),
+        field.data_type(),
+        field.is_nullable(),
+        decimal_encoding,
+    )
+}
+
+fn data_type_to_kafka_json(
+    name: &str,
+    data_type: &DataType,
+    nullable: bool,
+    decimal_encoding: &DecimalEncoding,
+) -> Value {
     use arrow::datatypes::DataType::*;
 
-    let typ = match field.data_type() {
-        Null => todo!(),
+    let typ = match data_type {
+        // Kafka Connect has no null type, so use an always-absent optional field
+        Null => {
+            return json! {{
+                "type": "string",
+                "field": name,
+                "optional": true,
+            }}
+        }
         Boolean => "boolean",
         Int8 | UInt8 => "int8",
         Int16 | UInt16 => "int16",
@@ -248,6 +298,10 @@ pub fn field_to_kafka_json(field: &Field) -> Value {
         Int64 | UInt64 => "int64",
         Float16 | Float32 => "float",
         Float64 => "double",
+        Decimal128(_, _) | Decimal256(_, _) => match decimal_encoding {
+            DecimalEncoding::Number => "double",
+            DecimalEncoding::String => "string",
+        },
         Utf8 | LargeUtf8 => "string",
         Binary | FixedSizeBinary(_) | LargeBinary => "bytes",
         Time32(_) | Time64(_) | Timestamp(_, _) => {
@@ -255,57 +309,91 @@ pub fn field_to_kafka_json(field: &Field) -> Value {
             // Kafka connect
             return json! {{
                 "type": "int64",
-                "field": field.name().clone(),
-                "optional": field.is_nullable(),
+                "field": name,
+                "optional": nullable,
                 "name": "org.apache.kafka.connect.data.Timestamp"
             }};
         }
         Date32 | Date64 => {
             return json! {{
                 "type": "int64",
-                "field": field.name().clone(),
-                "optional": field.is_nullable(),
+                "field": name,
+                "optional": nullable,
                 "name": "org.apache.kafka.connect.data.Date"
             }}
         }
         Duration(_) => "int64",
-        Interval(_) => "int64",
+        Interval(_) => "string",
         List(t) | FixedSizeList(t, _) | LargeList(t) => {
             return json! {{
                 "type": "array",
-                "items": field_to_kafka_json(&*t),
-                "field": field.name().clone(),
-                "optional": field.is_nullable(),
+                "items": field_to_kafka_json(t, decimal_encoding),
+                "field": name,
+                "optional": nullable,
             }};
         }
         Struct(s) => {
-            let fields: Vec<_> = s.iter().map(|f| field_to_kafka_json(&*f)).collect();
+            let fields: Vec<_> = s
+                .iter()
+                .map(|f| field_to_kafka_json(f, decimal_encoding))
+                .collect();
+            return json! {{
+                "type": "struct",
+                "fields": fields,
+                "field": name,
+                "optional": nullable,
+            }};
+        }
+        Union(u, _) => {
+            let fields: Vec<_> = u
+                .iter()
+                .map(|(_, f)| {
+                    data_type_to_kafka_json(f.name(), f.data_type(), true, decimal_encoding)
+                })
+                .collect();
             return json! {{
                 "type": "struct",
                 "fields": fields,
-                "field": field.name().clone(),
-                "optional": field.is_nullable(),
+                "field": name,
+                "optional": nullable,
+            }};
+        }
+        Dictionary(_, t) => return data_type_to_kafka_json(name, t, nullable, decimal_encoding),
+        RunEndEncoded(_, values) => {
+            return data_type_to_kafka_json(name, values.data_type(), nullable, decimal_encoding)
+        }
+        Map(entries, _) => {
+            let Struct(kv) = entries.data_type() else {
+                unreachable!("map entries must be a struct");
+            };
+            return json! {{
+                "type": "map",
+                "keys": { "type": "string", "optional": false },
+                "values": field_to_kafka_json(&kv[1], decimal_encoding),
+                "field": name,
+                "optional": nullable,
             }};
         }
-        Union(_, _) => todo!(),
-        Dictionary(_, _) => todo!(),
-        Decimal128(_, _) => todo!(),
-        Decimal256(_, _) => todo!(),
-        Map(_, _) => todo!(),
-        RunEndEncoded(_, _) => todo!(),
     };
 
     json! {{
         "type": typ,
-        "field": field.name().clone(),
-        "optional": field.is_nullable(),
+        "field": name,
+        "optional": nullable,
     }}
 }
 
 // For some reason Kafka uses it's own bespoke almost-but-not-quite JSON schema format
 // https://www.confluent.io/blog/kafka-connect-deep-dive-converters-serialization-explained/#json-schemas
-pub fn arrow_to_kafka_json(name: &str, fields: &Fields) -> Value {
-    let fields: Vec<_> = fields.iter().map(|f| field_to_kafka_json(&*f)).collect();
+pub fn arrow_to_kafka_json(
+    name: &str,
+    fields: &Fields,
+    decimal_encoding: &DecimalEncoding,
+) -> Value {
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|f| field_to_kafka_json(f, decimal_encoding))
+        .collect();
     json! {{
         "type": "struct",
         "name": name,
@@ -0,0 +1,539 @@
+//! The arrow-json row writer only understands a subset of Arrow's types, so before
+//! serializing a batch we rewrite the columns it can't handle into equivalent ones it can.
+
+use arrow::compute::{can_cast_types, cast, take};
+use arrow_array::builder::{ListBuilder, UInt8Builder};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Int16Type, Int32Type, Int64Type, RunEndIndexType};
+use arrow_array::{
+    Array, ArrayRef, FixedSizeListArray, GenericListArray, MapArray, OffsetSizeTrait, RecordBatch,
+    RunArray, StructArray, UInt32Array, UnionArray,
+};
+use arrow_schema::{ArrowError, DataType, Field, FieldRef, Fields, Schema, UnionFields};
+use arroyo_rpc::formats::DecimalEncoding;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Checks that values of the given type can be written as JSON; called while planning so
+/// that unsupported sinks are rejected before the pipeline runs.
+pub fn validate_data_type(data_type: &DataType) -> Result<(), String> {
+    match data_type {
+        DataType::List(f) | DataType::LargeList(f) | DataType::FixedSizeList(f, _) => {
+            validate_data_type(f.data_type())
+        }
+        DataType::Struct(fields) => fields
+            .iter()
+            .try_for_each(|f| validate_data_type(f.data_type())),
+        DataType::Union(fields, _) => fields
+            .iter()
+            .try_for_each(|(_, f)| validate_data_type(f.data_type())),
+        DataType::Dictionary(_, values) => validate_data_type(values),
+        DataType::RunEndEncoded(_, values) => validate_data_type(values.data_type()),
+        DataType::Map(entries, _) => {
+            let DataType::Struct(kv) = entries.data_type() else {
+                return Err(format!("invalid map entries type {}", entries.data_type()));
+            };
+
+            if !can_cast_types(kv[0].data_type(), &DataType::Utf8) {
+                return Err(format!(
+                    "maps with keys of type {} cannot be written as JSON",
+                    kv[0].data_type()
+                ));
+            }
+
+            validate_data_type(kv[1].data_type())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Rewrites the columns of `batch` into types supported by the arrow-json writer, following
+/// the representation described by [`super::arrow_to_json_schema`]. Decimals are rewritten
+/// into strings holding their exact value; see [`JsonShape`] for writing them as numbers.
+pub fn prepare_batch(batch: &RecordBatch) -> Result<RecordBatch, ArrowError> {
+    let (fields, columns): (Vec<_>, Vec<_>) = batch
+        .schema()
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(f, c)| {
+            let c = prepare_array(c)?;
+            Ok((with_data_type(f, c.data_type()), c))
+        })
+        .collect::<Result<Vec<_>, ArrowError>>()?
+        .into_iter()
+        .unzip();
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
+
+fn with_data_type(field: &FieldRef, data_type: &DataType) -> Field {
+    Field::new(field.name(), data_type.clone(), field.is_nullable())
+}
+
+fn prepare_array(array: &ArrayRef) -> Result<ArrayRef, ArrowError> {
+    Ok(match array.data_type() {
+        DataType::Float16 => cast(array, &DataType::Float32)?,
+        // decimals keep their exact text, which `write_json` writes unquoted when they're
+        // encoded as numbers
+        DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => cast(array, &DataType::Utf8)?,
+        DataType::Duration(_) => cast(array, &DataType::Int64)?,
+        DataType::Interval(_) => cast(array, &DataType::Utf8)?,
+        DataType::Binary => binary_to_list(array.as_binary::<i32>().iter()),
+        DataType::LargeBinary => binary_to_list(array.as_binary::<i64>().iter()),
+        DataType::FixedSizeBinary(_) => binary_to_list(array.as_fixed_size_binary().iter()),
+        DataType::Dictionary(_, values) => prepare_array(&cast(array, values)?)?,
+        DataType::RunEndEncoded(run_ends, _) => {
+            let expanded = match run_ends.data_type() {
+                DataType::Int16 => expand_run_array::<Int16Type>(array)?,
+                DataType::Int32 => expand_run_array::<Int32Type>(array)?,
+                DataType::Int64 => expand_run_array::<Int64Type>(array)?,
+                t => {
+                    return Err(ArrowError::InvalidArgumentError(format!(
+                        "invalid run end type {}",
+                        t
+                    )))
+                }
+            };
+            prepare_array(&expanded)?
+        }
+        DataType::List(f) => prepare_list(f, array.as_list::<i32>())?,
+        DataType::LargeList(f) => prepare_list(f, array.as_list::<i64>())?,
+        DataType::FixedSizeList(f, size) => {
+            let list = array.as_fixed_size_list();
+            let values = prepare_array(list.values())?;
+            Arc::new(FixedSizeListArray::try_new(
+                Arc::new(with_data_type(f, values.data_type())),
+                *size,
+                values,
+                list.nulls().cloned(),
+            )?)
+        }
+        DataType::Struct(_) => Arc::new(prepare_struct(array.as_struct())?),
+        DataType::Map(entries, ordered) => {
+            let map = array.as_map();
+            let kv = map.entries();
+            let keys = cast(kv.column(0), &DataType::Utf8)?;
+            let values = prepare_array(kv.column(1))?;
+
+            let kv_fields = Fields::from(vec![
+                Field::new(kv.fields()[0].name(), DataType::Utf8, false),
+                with_data_type(&kv.fields()[1], values.data_type()),
+            ]);
+            let entries_array =
+                StructArray::try_new(kv_fields.clone(), vec![keys, values], kv.nulls().cloned())?;
+
+            Arc::new(MapArray::try_new(
+                Arc::new(Field::new(
+                    entries.name(),
+                    DataType::Struct(kv_fields),
+                    entries.is_nullable(),
+                )),
+                map.offsets().clone(),
+                entries_array,
+                map.nulls().cloned(),
+                *ordered,
+            )?)
+        }
+        DataType::Union(fields, _) => union_to_struct(
+            array
+                .as_any()
+                .downcast_ref::<UnionArray>()
+                .expect("union type does not match array"),
+            fields,
+        )?,
+        _ => array.clone(),
+    })
+}
+
+fn prepare_struct(array: &StructArray) -> Result<StructArray, ArrowError> {
+    let columns = array
+        .columns()
+        .iter()
+        .map(|c| prepare_array(c))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let fields: Fields = array
+        .fields()
+        .iter()
+        .zip(&columns)
+        .map(|(f, c)| with_data_type(f, c.data_type()))
+        .collect();
+
+    StructArray::try_new(fields, columns, array.nulls().cloned())
+}
+
+fn prepare_list<O: OffsetSizeTrait>(
+    field: &FieldRef,
+    list: &GenericListArray<O>,
+) -> Result<ArrayRef, ArrowError> {
+    let values = prepare_array(list.values())?;
+
+    Ok(Arc::new(GenericListArray::<O>::try_new(
+        Arc::new(with_data_type(field, values.data_type())),
+        list.offsets().clone(),
+        values,
+        list.nulls().cloned(),
+    )?))
+}
+
+fn binary_to_list<'a>(values: impl Iterator<Item = Option<&'a [u8]>>) -> ArrayRef {
+    let mut builder = ListBuilder::new(UInt8Builder::new());
+    for value in values {
+        match value {
+            Some(value) => {
+                builder.values().append_slice(value);
+                builder.append(true);
+            }
+            None => builder.append(false),
+        }
+    }
+
+    Arc::new(builder.finish())
+}
+
+fn expand_run_array<R: RunEndIndexType>(array: &ArrayRef) -> Result<ArrayRef, ArrowError> {
+    let run_array = array
+        .as_any()
+        .downcast_ref::<RunArray<R>>()
+        .expect("run end type does not match array");
+
+    let indices: UInt32Array = (0..run_array.len())
+        .map(|i| run_array.get_physical_index(i) as u32)
+        .collect();
+
+    take(run_array.values().as_ref(), &indices, None)
+}
+
+/// Converts a union into a struct with a nullable field per variant, of which only the
+/// variant held by each row is set
+fn union_to_struct(array: &UnionArray, fields: &UnionFields) -> Result<ArrayRef, ArrowError> {
+    let mut struct_fields = vec![];
+    let mut columns = vec![];
+
+    for (type_id, field) in fields.iter() {
+        let indices: UInt32Array = (0..array.len())
+            .map(|i| (array.type_id(i) == type_id).then(|| array.value_offset(i) as u32))
+            .collect();
+
+        let column = prepare_array(&take(array.child(type_id).as_ref(), &indices, None)?)?;
+
+        struct_fields.push(Field::new(field.name(), column.data_type().clone(), true));
+        columns.push(column);
+    }
+
+    Ok(Arc::new(StructArray::try_new(
+        struct_fields.into(),
+        columns,
+        None,
+    )?))
+}
+
+/// Where decimals that are encoded as numbers sit within the JSON for a row. Converting them to
+/// floats would lose precision, so they're carried through as their exact text (see
+/// [`prepare_batch`]) and written unquoted by [`write_json`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonShape {
+    Decimal,
+    Object(HashMap<String, JsonShape>),
+    /// every element of an array, or every value of an object built from a map
+    Elements(Box<JsonShape>),
+    Other,
+}
+
+impl JsonShape {
+    pub fn for_fields(fields: &Fields, decimal_encoding: &DecimalEncoding) -> Self {
+        match decimal_encoding {
+            DecimalEncoding::Number => Self::object(
+                fields
+                    .iter()
+                    .map(|f| (f.name().clone(), Self::for_type(f.data_type()))),
+            ),
+            DecimalEncoding::String => JsonShape::Other,
+        }
+    }
+
+    fn for_type(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => JsonShape::Decimal,
+            DataType::List(f) | DataType::LargeList(f) | DataType::FixedSizeList(f, _) => {
+                Self::elements(Self::for_type(f.data_type()))
+            }
+            DataType::Struct(fields) => Self::object(
+                fields
+                    .iter()
+                    .map(|f| (f.name().clone(), Self::for_type(f.data_type()))),
+            ),
+            DataType::Union(fields, _) => Self::object(
+                fields
+                    .iter()
+                    .map(|(_, f)| (f.name().clone(), Self::for_type(f.data_type()))),
+            ),
+            DataType::Dictionary(_, values) => Self::for_type(values),
+            DataType::RunEndEncoded(_, values) => Self::for_type(values.data_type()),
+            DataType::Map(entries, _) => match entries.data_type() {
+                DataType::Struct(kv) => Self::elements(Self::for_type(kv[1].data_type())),
+                _ => JsonShape::Other,
+            },
+            _ => JsonShape::Other,
+        }
+    }
+
+    /// An object with the given fields, only keeping the ones that contain decimals
+    pub fn object(fields: impl IntoIterator<Item = (String, JsonShape)>) -> Self {
+        let fields: HashMap<_, _> = fields
+            .into_iter()
+            .filter(|(_, shape)| *shape != JsonShape::Other)
+            .collect();
+
+        if fields.is_empty() {
+            JsonShape::Other
+        } else {
+            JsonShape::Object(fields)
+        }
+    }
+
+    fn elements(shape: JsonShape) -> Self {
+        match shape {
+            JsonShape::Other => JsonShape::Other,
+            shape => JsonShape::Elements(Box::new(shape)),
+        }
+    }
+
+    fn field(&self, name: &str) -> &JsonShape {
+        match self {
+            JsonShape::Object(fields) => fields.get(name).unwrap_or(&JsonShape::Other),
+            JsonShape::Elements(shape) => shape,
+            _ => &JsonShape::Other,
+        }
+    }
+}
+
+/// Writes `value` as JSON, with the strings at the decimal positions of `shape` written as
+/// numbers
+pub fn write_json(out: &mut Vec<u8>, value: &Value, shape: &JsonShape) {
+    match (shape, value) {
+        (JsonShape::Other, value) => serde_json::to_writer(out, value).unwrap(),
+        (JsonShape::Decimal, Value::String(decimal)) => out.extend_from_slice(decimal.as_bytes()),
+        (shape, Value::Object(fields)) => {
+            out.push(b'{');
+            for (i, (name, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, name).unwrap();
+                out.push(b':');
+                write_json(out, value, shape.field(name));
+            }
+            out.push(b'}');
+        }
+        (JsonShape::Elements(shape), Value::Array(elements)) => {
+            out.push(b'[');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_json(out, element, shape);
+            }
+            out.push(b']');
+        }
+        (_, value) => serde_json::to_writer(out, value).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::builder::{MapBuilder, StringBuilder, UInt32Builder};
+    use arrow_array::{Decimal128Array, DictionaryArray, Int32Array, StringArray};
+    use arrow_json::writer::record_batches_to_json_rows;
+    use serde_json::json;
+
+    fn to_json(batch: &RecordBatch) -> Vec<serde_json::Value> {
+        let batch = prepare_batch(batch).unwrap();
+        record_batches_to_json_rows(&[&batch])
+            .unwrap()
+            .into_iter()
+            .map(serde_json::Value::Object)
+            .collect()
+    }
+
+    fn to_json_text(batch: &RecordBatch, decimal_encoding: DecimalEncoding) -> Vec<String> {
+        let shape = JsonShape::for_fields(batch.schema().fields(), &decimal_encoding);
+        to_json(batch)
+            .iter()
+            .map(|row| {
+                let mut out = vec![];
+                write_json(&mut out, row, &shape);
+                String::from_utf8(out).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decimals() {
+        let decimals = Decimal128Array::from(vec![Some(12345), None])
+            .with_precision_and_scale(10, 2)
+            .unwrap();
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new(
+                "d",
+                decimals.data_type().clone(),
+                true,
+            )])),
+            vec![Arc::new(decimals)],
+        )
+        .unwrap();
+
+        assert_eq!(
+            to_json_text(&batch, DecimalEncoding::Number),
+            vec![r#"{"d":123.45}"#, "{}"]
+        );
+        assert_eq!(
+            to_json_text(&batch, DecimalEncoding::String),
+            vec![r#"{"d":"123.45"}"#, "{}"]
+        );
+    }
+
+    #[test]
+    fn test_decimals_keep_their_precision() {
+        // more significant digits than fit in a double
+        let decimals = Decimal128Array::from(vec![12345678901234567890123456789_i128, -1])
+            .with_precision_and_scale(38, 10)
+            .unwrap();
+        let list = FixedSizeListArray::try_new(
+            Arc::new(Field::new("item", decimals.data_type().clone(), true)),
+            1,
+            Arc::new(decimals.clone()),
+            None,
+        )
+        .unwrap();
+        let nested = StructArray::try_new(
+            Fields::from(vec![
+                Field::new("amounts", list.data_type().clone(), true),
+                Field::new("label", DataType::Utf8, false),
+            ]),
+            vec![
+                Arc::new(list),
+                Arc::new(StringArray::from(vec!["12.5", "x"])),
+            ],
+            None,
+        )
+        .unwrap();
+
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("d", decimals.data_type().clone(), false),
+                Field::new("nested", nested.data_type().clone(), false),
+            ])),
+            vec![Arc::new(decimals), Arc::new(nested)],
+        )
+        .unwrap();
+
+        // strings that merely look like numbers are still written as strings
+        assert_eq!(
+            to_json_text(&batch, DecimalEncoding::Number),
+            vec![
+                r#"{"d":1234567890123456789.0123456789,"nested":{"amounts":[1234567890123456789.0123456789],"label":"12.5"}}"#,
+                r#"{"d":-0.0000000001,"nested":{"amounts":[-0.0000000001],"label":"x"}}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dictionary_and_map() {
+        let dictionary: DictionaryArray<Int32Type> = vec!["a", "b", "a"].into_iter().collect();
+
+        let mut map = MapBuilder::new(None, UInt32Builder::new(), StringBuilder::new());
+        map.keys().append_value(1);
+        map.values().append_value("x");
+        map.append(true).unwrap();
+        map.append(false).unwrap();
+        map.keys().append_value(2);
+        map.values().append_value("y");
+        map.keys().append_value(3);
+        map.values().append_value("z");
+        map.append(true).unwrap();
+        let map = map.finish();
+
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("dict", dictionary.data_type().clone(), false),
+                Field::new("map", map.data_type().clone(), true),
+            ])),
+            vec![Arc::new(dictionary), Arc::new(map)],
+        )
+        .unwrap();
+
+        assert_eq!(
+            to_json(&batch),
+            vec![
+                json!({"dict": "a", "map": {"1": "x"}}),
+                json!({"dict": "b"}),
+                json!({"dict": "a", "map": {"2": "y", "3": "z"}}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_union() {
+        let union = UnionArray::try_new(
+            &[0, 1],
+            vec![0i8, 1, 0].into(),
+            Some(vec![0i32, 0, 1].into()),
+            vec![
+                (
+                    Field::new("i", DataType::Int32, false),
+                    Arc::new(Int32Array::from(vec![5, 6])) as ArrayRef,
+                ),
+                (
+                    Field::new("s", DataType::Utf8, false),
+                    Arc::new(StringArray::from(vec!["hello"])) as ArrayRef,
+                ),
+            ],
+        )
+        .unwrap();
+
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new(
+                "u",
+                union.data_type().clone(),
+                false,
+            )])),
+            vec![Arc::new(union)],
+        )
+        .unwrap();
+
+        assert_eq!(
+            to_json(&batch),
+            vec![
+                json!({"u": {"i": 5}}),
+                json!({"u": {"s": "hello"}}),
+                json!({"u": {"i": 6}}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate_data_type(&DataType::Decimal128(10, 2)).is_ok());
+
+        let bad_map = DataType::Map(
+            Arc::new(Field::new(
+                "entries",
+                DataType::Struct(Fields::from(vec![
+                    Field::new(
+                        "keys",
+                        DataType::List(Arc::new(Field::new("item", DataType::Int32, true))),
+                        false,
+                    ),
+                    Field::new("values", DataType::Utf8, true),
+                ])),
+                false,
+            )),
+            false,
+        );
+        assert!(validate_data_type(&bad_map).is_err());
+    }
+}
@@ -4,6 +4,7 @@ use serde_json::json;
 
 pub mod avro;
 pub mod json;
+pub mod proto;
 
 pub mod de;
 pub mod ser;
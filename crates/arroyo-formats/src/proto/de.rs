@@ -0,0 +1,150 @@
+use arroyo_rpc::formats::ProtobufFormat;
+use arroyo_types::SourceError;
+use prost_reflect::{DynamicMessage, MessageDescriptor, SerializeOptions};
+use serde_json::Value;
+
+/// Reads a zig-zag encoded varint as used in the Confluent message-index header
+fn read_varint(msg: &mut &[u8]) -> Result<i64, SourceError> {
+    let mut result: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let (b, rest) = msg.split_first().ok_or_else(|| {
+            SourceError::bad_data("unexpected end of message while reading message indexes")
+        })?;
+        *msg = rest;
+        result |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok(((result >> 1) as i64) ^ -((result & 1) as i64));
+        }
+    }
+
+    Err(SourceError::bad_data("invalid varint in message indexes"))
+}
+
+/// Strips the Confluent Schema Registry header (magic byte, schema id and message indexes)
+/// from the message
+fn strip_confluent_header(mut msg: &[u8]) -> Result<&[u8], SourceError> {
+    if msg.len() < 5 || msg[0] != 0 {
+        return Err(SourceError::bad_data(
+            "data was not encoded with schema registry wire format; \
+            magic byte has unexpected value",
+        ));
+    }
+
+    msg = &msg[5..];
+
+    let count = read_varint(&mut msg)?;
+    for _ in 0..count {
+        read_varint(&mut msg)?;
+    }
+
+    Ok(msg)
+}
+
+/// Decodes a protobuf message into its canonical JSON representation
+pub(crate) fn deserialize_proto(
+    format: &ProtobufFormat,
+    descriptor: &MessageDescriptor,
+    msg: &[u8],
+) -> Result<Value, SourceError> {
+    let msg = if format.confluent_schema_registry {
+        strip_confluent_header(msg)?
+    } else {
+        msg
+    };
+
+    let message = DynamicMessage::decode(descriptor.clone(), msg)
+        .map_err(|e| SourceError::bad_data(format!("failed to deserialize protobuf: {}", e)))?;
+
+    message
+        .serialize_with_options(
+            serde_json::value::Serializer,
+            &SerializeOptions::new()
+                .stringify_64_bit_integers(false)
+                .use_proto_field_name(true)
+                .skip_default_fields(false),
+        )
+        .map_err(|e| {
+            SourceError::bad_data(format!("failed to convert protobuf message to JSON: {}", e))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::de::ArrowDeserializer;
+    use crate::proto::schema::tests::test_format;
+    use crate::proto::schema::{get_message_descriptor, protobuf_to_arrow};
+    use arrow_schema::Field;
+    use arroyo_rpc::df::ArroyoSchema;
+    use arroyo_rpc::formats::{BadData, Format};
+    use prost::Message;
+    use prost_reflect::{DynamicMessage, Value};
+    use serde_json::json;
+    use std::time::SystemTime;
+
+    #[tokio::test]
+    async fn test_protobuf_deserialization() {
+        let format = test_format();
+        let descriptor = get_message_descriptor(&format).unwrap();
+
+        let mut item = DynamicMessage::new(
+            descriptor
+                .parent_pool()
+                .get_message_by_name("test.Event.Item")
+                .unwrap(),
+        );
+        item.set_field_by_name("sku", Value::String("abc".to_string()));
+        item.set_field_by_name("quantity", Value::I64(3));
+
+        let mut created_at = DynamicMessage::new(
+            descriptor
+                .parent_pool()
+                .get_message_by_name("google.protobuf.Timestamp")
+                .unwrap(),
+        );
+        created_at.set_field_by_name("seconds", Value::I64(1_700_000_000));
+
+        let mut event = DynamicMessage::new(descriptor.clone());
+        event.set_field_by_name("id", Value::String("event-1".to_string()));
+        event.set_field_by_name("count", Value::I32(5));
+        event.set_field_by_name("items", Value::List(vec![Value::Message(item)]));
+        event.set_field_by_name("created_at", Value::Message(created_at));
+        event.set_field_by_name("device", Value::I64(10));
+
+        let fields: Vec<Field> = protobuf_to_arrow(&descriptor)
+            .unwrap()
+            .fields
+            .iter()
+            .map(|f| (**f).clone())
+            .collect();
+        let schema = ArroyoSchema::from_fields(fields);
+
+        let mut deserializer = ArrowDeserializer::new(
+            Format::Protobuf(format),
+            schema.clone(),
+            None,
+            BadData::Fail {},
+        )
+        .unwrap();
+
+        let mut builders = schema.builders();
+        let errors = deserializer
+            .deserialize_slice(&mut builders, &event.encode_to_vec(), SystemTime::now())
+            .await;
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let batch = deserializer.flush_buffer().unwrap().unwrap();
+        let mut row = arrow_json::writer::record_batches_to_json_rows(&[&batch])
+            .unwrap()
+            .remove(0);
+        row.remove("_timestamp");
+        assert!(!row.remove("created_at").unwrap().is_null());
+
+        assert_eq!(row.get("id").unwrap(), &json!("event-1"));
+        assert_eq!(row.get("count").unwrap(), &json!(5));
+        assert_eq!(
+            row.get("items").unwrap(),
+            &json!([{"sku": "abc", "quantity": 3}])
+        );
+        assert_eq!(row.get("device").unwrap(), &json!(10));
+    }
+}
@@ -0,0 +1,381 @@
+use anyhow::{anyhow, bail};
+use arrow_schema::{DataType, Field, Fields, TimeUnit};
+use arroyo_rpc::formats::ProtobufFormat;
+use arroyo_types::ArroyoExtensionType;
+use prost_reflect::{DescriptorPool, FieldDescriptor, Kind, MessageDescriptor};
+use protox::file::{ChainFileResolver, File, FileResolver, GoogleFileResolver};
+use protox::Compiler;
+use std::sync::Arc;
+
+const TIMESTAMP_MESSAGE: &str = "google.protobuf.Timestamp";
+pub(crate) const DURATION_MESSAGE: &str = "google.protobuf.Duration";
+
+const SCHEMA_FILE: &str = "schema.proto";
+
+/// Resolves the user's schema, which is compiled as if it were a file named `schema.proto`
+struct SchemaResolver<'a> {
+    schema: &'a str,
+}
+
+impl FileResolver for SchemaResolver<'_> {
+    fn open_file(&self, name: &str) -> Result<File, protox::Error> {
+        if name == SCHEMA_FILE {
+            File::from_source(name, self.schema)
+        } else {
+            Err(protox::Error::file_not_found(name))
+        }
+    }
+}
+
+/// Compiles a .proto schema into an encoded FileDescriptorSet; imports of the well-known types
+/// (google/protobuf/*.proto) are resolved from the copies bundled with the compiler
+pub fn schema_file_to_descriptor(schema: &str) -> anyhow::Result<Vec<u8>> {
+    let mut resolver = ChainFileResolver::new();
+    resolver.add(SchemaResolver { schema });
+    resolver.add(GoogleFileResolver::new());
+
+    let mut compiler = Compiler::with_file_resolver(resolver);
+    compiler.include_imports(true);
+    compiler
+        .open_file(SCHEMA_FILE)
+        .map_err(|e| anyhow!("protobuf schema is not valid: {}", e))?;
+
+    Ok(compiler.encode_file_descriptor_set())
+}
+
+pub fn get_pool(encoded: &[u8]) -> anyhow::Result<DescriptorPool> {
+    DescriptorPool::decode(encoded)
+        .map_err(|e| anyhow!("could not decode protobuf descriptor: {}", e))
+}
+
+/// Finds the message to use for the format; if no message name is configured, the first
+/// message defined in the schema is used
+pub fn get_message_descriptor(format: &ProtobufFormat) -> anyhow::Result<MessageDescriptor> {
+    let encoded = format
+        .compiled_schema
+        .as_ref()
+        .ok_or_else(|| anyhow!("protobuf format requires a schema"))?;
+
+    let pool = get_pool(encoded)?;
+
+    match &format.message_name {
+        Some(name) => pool
+            .get_message_by_name(name)
+            .ok_or_else(|| anyhow!("message '{}' not found in protobuf schema", name)),
+        None => pool
+            .files()
+            .filter(|f| !f.package_name().starts_with("google.protobuf"))
+            .last()
+            .and_then(|f| f.messages().next())
+            .ok_or_else(|| anyhow!("no messages defined in protobuf schema")),
+    }
+}
+
+/// Computes the message-index path used by the Confluent Schema Registry wire format to
+/// identify a message within its file
+pub fn message_indexes(descriptor: &MessageDescriptor) -> Vec<i32> {
+    fn find(
+        mut messages: impl Iterator<Item = MessageDescriptor>,
+        target: &MessageDescriptor,
+        path: &mut Vec<i32>,
+    ) -> bool {
+        messages.enumerate().any(|(i, m)| {
+            path.push(i as i32);
+            if m.full_name() == target.full_name() || find(m.child_messages(), target, path) {
+                return true;
+            }
+            path.pop();
+            false
+        })
+    }
+
+    let mut path = vec![];
+    find(descriptor.parent_file().messages(), descriptor, &mut path);
+    path
+}
+
+/// Computes an arrow schema from a protobuf message
+pub fn protobuf_to_arrow(descriptor: &MessageDescriptor) -> anyhow::Result<arrow_schema::Schema> {
+    Ok(arrow_schema::Schema::new(message_fields(descriptor, 0)?))
+}
+
+fn message_fields(descriptor: &MessageDescriptor, depth: usize) -> anyhow::Result<Fields> {
+    if depth > 32 {
+        bail!(
+            "protobuf message '{}' is recursive, which is not supported",
+            descriptor.full_name()
+        );
+    }
+
+    descriptor
+        .fields()
+        .map(|f| field_to_arrow(&f, depth).map(Arc::new))
+        .collect()
+}
+
+fn field_to_arrow(field: &FieldDescriptor, depth: usize) -> anyhow::Result<Field> {
+    if field.is_map() {
+        // maps are represented as json objects
+        return Ok(ArroyoExtensionType::add_metadata(
+            Some(ArroyoExtensionType::JSON),
+            Field::new(field.name(), DataType::Utf8, true),
+        ));
+    }
+
+    let (dt, extension) = kind_to_arrow(&field.kind(), depth)?;
+
+    // fields in a oneof or with explicit presence (messages, proto3 optional, proto2 fields)
+    // may be absent, and so are nullable
+    let nullable = field.supports_presence() || field.containing_oneof().is_some();
+
+    Ok(if field.is_list() {
+        Field::new(
+            field.name(),
+            DataType::List(Arc::new(ArroyoExtensionType::add_metadata(
+                extension,
+                Field::new("item", dt, false),
+            ))),
+            false,
+        )
+    } else {
+        ArroyoExtensionType::add_metadata(extension, Field::new(field.name(), dt, nullable))
+    })
+}
+
+fn kind_to_arrow(
+    kind: &Kind,
+    depth: usize,
+) -> anyhow::Result<(DataType, Option<ArroyoExtensionType>)> {
+    Ok(match kind {
+        Kind::Double => (DataType::Float64, None),
+        Kind::Float => (DataType::Float32, None),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => (DataType::Int32, None),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => (DataType::Int64, None),
+        Kind::Uint32 | Kind::Fixed32 => (DataType::UInt32, None),
+        Kind::Uint64 | Kind::Fixed64 => (DataType::UInt64, None),
+        Kind::Bool => (DataType::Boolean, None),
+        Kind::String | Kind::Enum(_) => (DataType::Utf8, None),
+        Kind::Bytes => (DataType::Binary, None),
+        Kind::Message(m) if m.full_name() == TIMESTAMP_MESSAGE => {
+            (DataType::Timestamp(TimeUnit::Nanosecond, None), None)
+        }
+        Kind::Message(m) if m.full_name() == DURATION_MESSAGE => (DataType::Utf8, None),
+        Kind::Message(m) if m.full_name().starts_with("google.protobuf.") => {
+            // other well-known types (Struct, Any, Value, wrappers) have custom JSON
+            // representations, so we treat them as unstructured json
+            (DataType::Utf8, Some(ArroyoExtensionType::JSON))
+        }
+        Kind::Message(m) => (DataType::Struct(message_fields(m, depth + 1)?), None),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use prost::Message;
+    use prost_types::field_descriptor_proto::{Label, Type};
+    use prost_types::{
+        DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+        OneofDescriptorProto,
+    };
+
+    pub(crate) fn field(name: &str, number: i32, typ: Type, label: Label) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            r#type: Some(typ as i32),
+            label: Some(label as i32),
+            json_name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn message_field(
+        name: &str,
+        number: i32,
+        message: &str,
+        label: Label,
+    ) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            type_name: Some(message.to_string()),
+            ..field(name, number, Type::Message, label)
+        }
+    }
+
+    /// Builds a descriptor equivalent to
+    ///
+    /// ```proto
+    /// syntax = "proto3";
+    /// package test;
+    /// import "google/protobuf/timestamp.proto";
+    ///
+    /// message Event {
+    ///   message Item {
+    ///     string sku = 1;
+    ///     int64 quantity = 2;
+    ///   }
+    ///   string id = 1;
+    ///   int32 count = 2;
+    ///   repeated Item items = 3;
+    ///   google.protobuf.Timestamp created_at = 4;
+    ///   oneof source {
+    ///     string web = 5;
+    ///     int64 device = 6;
+    ///   }
+    /// }
+    /// ```
+    pub(crate) fn test_descriptor() -> Vec<u8> {
+        let timestamp = FileDescriptorProto {
+            name: Some("google/protobuf/timestamp.proto".to_string()),
+            package: Some("google.protobuf".to_string()),
+            message_type: vec![DescriptorProto {
+                name: Some("Timestamp".to_string()),
+                field: vec![
+                    field("seconds", 1, Type::Int64, Label::Optional),
+                    field("nanos", 2, Type::Int32, Label::Optional),
+                ],
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_string()),
+            ..Default::default()
+        };
+
+        let in_oneof = |f: FieldDescriptorProto| FieldDescriptorProto {
+            oneof_index: Some(0),
+            ..f
+        };
+
+        let event = FileDescriptorProto {
+            name: Some("event.proto".to_string()),
+            package: Some("test".to_string()),
+            dependency: vec!["google/protobuf/timestamp.proto".to_string()],
+            message_type: vec![DescriptorProto {
+                name: Some("Event".to_string()),
+                field: vec![
+                    field("id", 1, Type::String, Label::Optional),
+                    field("count", 2, Type::Int32, Label::Optional),
+                    message_field("items", 3, ".test.Event.Item", Label::Repeated),
+                    message_field(
+                        "created_at",
+                        4,
+                        ".google.protobuf.Timestamp",
+                        Label::Optional,
+                    ),
+                    in_oneof(field("web", 5, Type::String, Label::Optional)),
+                    in_oneof(field("device", 6, Type::Int64, Label::Optional)),
+                ],
+                nested_type: vec![DescriptorProto {
+                    name: Some("Item".to_string()),
+                    field: vec![
+                        field("sku", 1, Type::String, Label::Optional),
+                        field("quantity", 2, Type::Int64, Label::Optional),
+                    ],
+                    ..Default::default()
+                }],
+                oneof_decl: vec![OneofDescriptorProto {
+                    name: Some("source".to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_string()),
+            ..Default::default()
+        };
+
+        FileDescriptorSet {
+            file: vec![timestamp, event],
+        }
+        .encode_to_vec()
+    }
+
+    pub(crate) fn test_format() -> ProtobufFormat {
+        ProtobufFormat {
+            into_unstructured_json: false,
+            message_name: None,
+            confluent_schema_registry: false,
+            schema_id: None,
+            compiled_schema: Some(test_descriptor()),
+        }
+    }
+
+    #[test]
+    fn test_protobuf_to_arrow() {
+        let descriptor = get_message_descriptor(&test_format()).unwrap();
+        assert_eq!(descriptor.full_name(), "test.Event");
+
+        let schema = protobuf_to_arrow(&descriptor).unwrap();
+
+        assert_eq!(schema.field(0).data_type(), &DataType::Utf8);
+        assert!(!schema.field(0).is_nullable());
+        assert_eq!(schema.field(1).data_type(), &DataType::Int32);
+
+        let DataType::List(item) = schema.field(2).data_type() else {
+            panic!("repeated field should be a list");
+        };
+        assert_eq!(
+            item.data_type(),
+            &DataType::Struct(Fields::from(vec![
+                Field::new("sku", DataType::Utf8, false),
+                Field::new("quantity", DataType::Int64, false),
+            ]))
+        );
+
+        assert_eq!(
+            schema.field(3).data_type(),
+            &DataType::Timestamp(TimeUnit::Nanosecond, None)
+        );
+        assert!(schema.field(3).is_nullable());
+
+        // oneof members are nullable
+        assert!(schema.field(4).is_nullable());
+        assert!(schema.field(5).is_nullable());
+
+        assert_eq!(message_indexes(&descriptor), vec![0]);
+    }
+
+    #[test]
+    fn test_schema_file_to_descriptor() {
+        let schema = r#"
+            syntax = "proto3";
+            package test;
+            import "google/protobuf/timestamp.proto";
+
+            message Event {
+              message Item {
+                string sku = 1;
+                int64 quantity = 2;
+              }
+              string id = 1;
+              int32 count = 2;
+              repeated Item items = 3;
+              google.protobuf.Timestamp created_at = 4;
+              oneof source {
+                string web = 5;
+                int64 device = 6;
+              }
+            }
+        "#;
+
+        let format = ProtobufFormat {
+            compiled_schema: Some(schema_file_to_descriptor(schema).unwrap()),
+            ..test_format()
+        };
+
+        // the well-known types are included, but the user's message is still the default
+        let descriptor = get_message_descriptor(&format).unwrap();
+        assert_eq!(descriptor.full_name(), "test.Event");
+
+        let expected = get_message_descriptor(&test_format()).unwrap();
+        assert_eq!(
+            protobuf_to_arrow(&descriptor).unwrap(),
+            protobuf_to_arrow(&expected).unwrap()
+        );
+
+        let err = schema_file_to_descriptor("message Event { unknown.Type field = 1; }")
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("protobuf schema is not valid"), "{}", err);
+
+        assert!(schema_file_to_descriptor(r#"import "missing.proto";"#).is_err());
+    }
+}
@@ -0,0 +1,506 @@
+use crate::proto::schema::{message_indexes, protobuf_to_arrow, DURATION_MESSAGE};
+use anyhow::{anyhow, Context};
+use arrow::compute::cast;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Float32Type, Float64Type, Int32Type, Int64Type, TimestampNanosecondType, UInt32Type, UInt64Type,
+};
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_schema::{DataType, TimeUnit};
+use arroyo_rpc::formats::ProtobufFormat;
+use prost::Message;
+use prost_reflect::{DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, Value};
+
+/// Encodes a zig-zag varint, as used in the Confluent message-index header
+fn write_varint(buf: &mut Vec<u8>, v: i64) {
+    let mut v = ((v << 1) ^ (v >> 63)) as u64;
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+fn confluent_header(format: &ProtobufFormat, descriptor: &MessageDescriptor) -> Vec<u8> {
+    let mut buf = vec![0];
+    buf.extend(
+        format
+            .schema_id
+            .expect("must have schema id for confluent schema registry")
+            .to_be_bytes(),
+    );
+
+    let indexes = message_indexes(descriptor);
+    if indexes == [0] {
+        // the common case of the first message is encoded as a single 0
+        buf.push(0);
+    } else {
+        write_varint(&mut buf, indexes.len() as i64);
+        for i in indexes {
+            write_varint(&mut buf, i as i64);
+        }
+    }
+    buf
+}
+
+fn timestamp_message(field: &FieldDescriptor, nanos: i64) -> Value {
+    let Kind::Message(descriptor) = field.kind() else {
+        unreachable!("timestamp field must be a message");
+    };
+    let mut message = DynamicMessage::new(descriptor);
+    message.set_field_by_name("seconds", Value::I64(nanos.div_euclid(1_000_000_000)));
+    message.set_field_by_name("nanos", Value::I32(nanos.rem_euclid(1_000_000_000) as i32));
+    Value::Message(message)
+}
+
+/// Decodes a message from its canonical JSON representation, which is how well-known types
+/// other than Timestamp are represented in arrow
+fn message_from_json(descriptor: MessageDescriptor, s: &str) -> anyhow::Result<Value> {
+    let json = if descriptor.full_name() == DURATION_MESSAGE {
+        // durations are stored as their JSON string (e.g., "1.5s") rather than as JSON
+        serde_json::Value::String(s.to_string())
+    } else {
+        serde_json::from_str(s).with_context(|| {
+            format!(
+                "invalid JSON for protobuf message {}",
+                descriptor.full_name()
+            )
+        })?
+    };
+
+    let name = descriptor.full_name().to_string();
+    Ok(Value::Message(
+        DynamicMessage::deserialize(descriptor, json)
+            .with_context(|| format!("invalid value for protobuf message {}", name))?,
+    ))
+}
+
+/// Decodes a map field from the JSON object that represents it in arrow
+fn map_from_json(field: &FieldDescriptor, s: &str) -> anyhow::Result<Value> {
+    let json: serde_json::Value = serde_json::from_str(s)
+        .with_context(|| format!("invalid JSON for protobuf map field '{}'", field.name()))?;
+
+    let message = DynamicMessage::deserialize(
+        field.containing_message(),
+        serde_json::Value::Object([(field.name().to_string(), json)].into_iter().collect()),
+    )
+    .with_context(|| format!("invalid value for protobuf map field '{}'", field.name()))?;
+
+    Ok(message.get_field(field).into_owned())
+}
+
+/// Converts a single (non-repeated) value from the array into a protobuf value
+fn to_value(
+    field: &FieldDescriptor,
+    array: &dyn Array,
+    idx: usize,
+) -> anyhow::Result<Option<Value>> {
+    if array.is_null(idx) {
+        return Ok(None);
+    }
+
+    Ok(Some(match array.data_type() {
+        DataType::Boolean => Value::Bool(array.as_boolean().value(idx)),
+        DataType::Int32 => Value::I32(array.as_primitive::<Int32Type>().value(idx)),
+        DataType::Int64 => Value::I64(array.as_primitive::<Int64Type>().value(idx)),
+        DataType::UInt32 => Value::U32(array.as_primitive::<UInt32Type>().value(idx)),
+        DataType::UInt64 => Value::U64(array.as_primitive::<UInt64Type>().value(idx)),
+        DataType::Float32 => Value::F32(array.as_primitive::<Float32Type>().value(idx)),
+        DataType::Float64 => Value::F64(array.as_primitive::<Float64Type>().value(idx)),
+        DataType::Binary => Value::Bytes(array.as_binary::<i32>().value(idx).to_vec().into()),
+        DataType::Utf8 => {
+            let s = array.as_string::<i32>().value(idx);
+            match field.kind() {
+                Kind::Enum(e) => Value::EnumNumber(
+                    e.get_value_by_name(s).map(|v| v.number()).ok_or_else(|| {
+                        anyhow!(
+                            "'{}' is not a value of enum {} for field '{}'",
+                            s,
+                            e.full_name(),
+                            field.name()
+                        )
+                    })?,
+                ),
+                Kind::Message(descriptor) => message_from_json(descriptor, s)?,
+                _ => Value::String(s.to_string()),
+            }
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => timestamp_message(
+            field,
+            array.as_primitive::<TimestampNanosecondType>().value(idx),
+        ),
+        DataType::Struct(_) => {
+            let Kind::Message(descriptor) = field.kind() else {
+                unreachable!("struct field must be a message");
+            };
+            let s = array.as_struct();
+            Value::Message(to_message(&descriptor, s.columns(), idx)?)
+        }
+        dt => unreachable!("unexpected type {} for protobuf field", dt),
+    }))
+}
+
+fn to_message(
+    descriptor: &MessageDescriptor,
+    columns: &[ArrayRef],
+    idx: usize,
+) -> anyhow::Result<DynamicMessage> {
+    let mut message = DynamicMessage::new(descriptor.clone());
+
+    for (field, column) in descriptor.fields().zip(columns) {
+        if field.is_list() {
+            let list = column.as_list::<i32>();
+            if list.is_null(idx) {
+                continue;
+            }
+            let values = list.value(idx);
+            let values = (0..values.len())
+                .filter_map(|i| to_value(&field, &values, i).transpose())
+                .collect::<anyhow::Result<_>>()?;
+            message.set_field(&field, Value::List(values));
+        } else if field.is_map() {
+            // maps are represented as json objects
+            if column.is_null(idx) {
+                continue;
+            }
+            let json = column.as_string::<i32>().value(idx);
+            message.set_field(&field, map_from_json(&field, json)?);
+        } else if let Some(value) = to_value(&field, column, idx)? {
+            message.set_field(&field, value);
+        }
+    }
+
+    Ok(message)
+}
+
+/// Serializes a batch into protobuf messages; columns are matched to message fields by name
+/// and cast to the arrow type that corresponds to each field
+pub fn serialize(
+    format: &ProtobufFormat,
+    descriptor: &MessageDescriptor,
+    batch: &RecordBatch,
+) -> anyhow::Result<Vec<Vec<u8>>> {
+    let schema = protobuf_to_arrow(descriptor)?;
+
+    let columns: Vec<ArrayRef> = schema
+        .fields
+        .iter()
+        .map(|f| {
+            Ok(match batch.column_by_name(f.name()) {
+                Some(c) if f.metadata().is_empty() => {
+                    cast(c, f.data_type()).with_context(|| {
+                        format!(
+                            "column '{}' cannot be converted to protobuf type {}",
+                            f.name(),
+                            f.data_type()
+                        )
+                    })?
+                }
+                Some(c) => c.clone(),
+                None => arrow_array::new_null_array(f.data_type(), batch.num_rows()),
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let header = format
+        .confluent_schema_registry
+        .then(|| confluent_header(format, descriptor));
+
+    (0..batch.num_rows())
+        .map(|i| {
+            let message = to_message(descriptor, &columns, i)?;
+            let mut buf = header.clone().unwrap_or_default();
+            message.encode(&mut buf)?;
+            Ok(buf)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::proto::schema::get_message_descriptor;
+    use crate::proto::schema::tests::{field, message_field, test_format};
+    use crate::ser::ArrowSerializer;
+    use arrow_array::{Int64Array, RecordBatch, StringArray, TimestampNanosecondArray};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use arroyo_rpc::formats::{Format, ProtobufFormat};
+    use prost::Message;
+    use prost_reflect::{DynamicMessage, MapKey, Value};
+    use prost_types::field_descriptor_proto::{Label, Type};
+    use prost_types::{
+        DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FileDescriptorProto,
+        FileDescriptorSet, MessageOptions,
+    };
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn well_known_type(
+        name: &str,
+        fields: Vec<prost_types::FieldDescriptorProto>,
+    ) -> FileDescriptorProto {
+        FileDescriptorProto {
+            name: Some(format!("google/protobuf/{}.proto", name.to_lowercase())),
+            package: Some("google.protobuf".to_string()),
+            message_type: vec![DescriptorProto {
+                name: Some(name.to_string()),
+                field: fields,
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// A format for a message equivalent to
+    ///
+    /// ```proto
+    /// message Order {
+    ///   enum Status {
+    ///     PENDING = 0;
+    ///     SHIPPED = 1;
+    ///   }
+    ///   string id = 1;
+    ///   map<string, int64> attributes = 2;
+    ///   Status status = 3;
+    ///   google.protobuf.Duration timeout = 4;
+    ///   google.protobuf.Int64Value quantity = 5;
+    /// }
+    /// ```
+    fn order_format() -> ProtobufFormat {
+        let duration = well_known_type(
+            "Duration",
+            vec![
+                field("seconds", 1, Type::Int64, Label::Optional),
+                field("nanos", 2, Type::Int32, Label::Optional),
+            ],
+        );
+        let wrappers = FileDescriptorProto {
+            name: Some("google/protobuf/wrappers.proto".to_string()),
+            ..well_known_type(
+                "Int64Value",
+                vec![field("value", 1, Type::Int64, Label::Optional)],
+            )
+        };
+
+        let order = FileDescriptorProto {
+            name: Some("order.proto".to_string()),
+            package: Some("test".to_string()),
+            dependency: vec![
+                "google/protobuf/duration.proto".to_string(),
+                "google/protobuf/wrappers.proto".to_string(),
+            ],
+            message_type: vec![DescriptorProto {
+                name: Some("Order".to_string()),
+                field: vec![
+                    field("id", 1, Type::String, Label::Optional),
+                    message_field(
+                        "attributes",
+                        2,
+                        ".test.Order.AttributesEntry",
+                        Label::Repeated,
+                    ),
+                    prost_types::FieldDescriptorProto {
+                        type_name: Some(".test.Order.Status".to_string()),
+                        ..field("status", 3, Type::Enum, Label::Optional)
+                    },
+                    message_field("timeout", 4, ".google.protobuf.Duration", Label::Optional),
+                    message_field(
+                        "quantity",
+                        5,
+                        ".google.protobuf.Int64Value",
+                        Label::Optional,
+                    ),
+                ],
+                nested_type: vec![DescriptorProto {
+                    name: Some("AttributesEntry".to_string()),
+                    field: vec![
+                        field("key", 1, Type::String, Label::Optional),
+                        field("value", 2, Type::Int64, Label::Optional),
+                    ],
+                    options: Some(MessageOptions {
+                        map_entry: Some(true),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                enum_type: vec![EnumDescriptorProto {
+                    name: Some("Status".to_string()),
+                    value: vec![
+                        EnumValueDescriptorProto {
+                            name: Some("PENDING".to_string()),
+                            number: Some(0),
+                            ..Default::default()
+                        },
+                        EnumValueDescriptorProto {
+                            name: Some("SHIPPED".to_string()),
+                            number: Some(1),
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_string()),
+            ..Default::default()
+        };
+
+        ProtobufFormat {
+            compiled_schema: Some(
+                FileDescriptorSet {
+                    file: vec![duration, wrappers, order],
+                }
+                .encode_to_vec(),
+            ),
+            ..test_format()
+        }
+    }
+
+    fn order_batch(status: &str) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("attributes", DataType::Utf8, true),
+            Field::new("status", DataType::Utf8, false),
+            Field::new("timeout", DataType::Utf8, true),
+            Field::new("quantity", DataType::Utf8, true),
+        ]));
+
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["a"])),
+                Arc::new(StringArray::from(vec![Some(r#"{"x": 1, "y": 2}"#)])),
+                Arc::new(StringArray::from(vec![status])),
+                Arc::new(StringArray::from(vec![Some("1.5s")])),
+                Arc::new(StringArray::from(vec![Some("5")])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_protobuf_serialization() {
+        let format = test_format();
+        let descriptor = get_message_descriptor(&format).unwrap();
+        let mut serializer = ArrowSerializer::new(Format::Protobuf(format)).unwrap();
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            // will be cast to the int32 protobuf field
+            Field::new("count", DataType::Int64, false),
+            Field::new("web", DataType::Utf8, true),
+            Field::new(
+                "_timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]));
+
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["a", "b"])),
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec![Some("example.com"), None])),
+                Arc::new(TimestampNanosecondArray::from(vec![0, 1])),
+            ],
+        )
+        .unwrap();
+
+        let messages: Vec<_> = serializer
+            .serialize(&batch)
+            .map(|b| DynamicMessage::decode(descriptor.clone(), &b[..]).unwrap())
+            .collect();
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[0].get_field_by_name("id").unwrap().as_ref(),
+            &Value::String("a".to_string())
+        );
+        assert_eq!(
+            messages[1].get_field_by_name("count").unwrap().as_ref(),
+            &Value::I32(2)
+        );
+        assert!(messages[0].has_field_by_name("web"));
+        assert!(!messages[1].has_field_by_name("web"));
+    }
+
+    #[test]
+    fn test_protobuf_serialization_maps_and_well_known_types() {
+        let format = order_format();
+        let descriptor = get_message_descriptor(&format).unwrap();
+        assert_eq!(descriptor.full_name(), "test.Order");
+
+        let mut serializer = ArrowSerializer::new(Format::Protobuf(format)).unwrap();
+        let messages: Vec<_> = serializer
+            .serialize(&order_batch("SHIPPED"))
+            .map(|b| DynamicMessage::decode(descriptor.clone(), &b[..]).unwrap())
+            .collect();
+        let message = &messages[0];
+
+        let Value::Map(attributes) = message
+            .get_field_by_name("attributes")
+            .unwrap()
+            .into_owned()
+        else {
+            panic!("attributes should be a map");
+        };
+        assert_eq!(
+            attributes,
+            HashMap::from([
+                (MapKey::String("x".to_string()), Value::I64(1)),
+                (MapKey::String("y".to_string()), Value::I64(2)),
+            ])
+        );
+
+        assert_eq!(
+            message.get_field_by_name("status").unwrap().as_ref(),
+            &Value::EnumNumber(1)
+        );
+
+        let Value::Message(timeout) = message.get_field_by_name("timeout").unwrap().into_owned()
+        else {
+            panic!("timeout should be a message");
+        };
+        assert_eq!(
+            timeout.get_field_by_name("seconds").unwrap().as_ref(),
+            &Value::I64(1)
+        );
+        assert_eq!(
+            timeout.get_field_by_name("nanos").unwrap().as_ref(),
+            &Value::I32(500_000_000)
+        );
+
+        let Value::Message(quantity) = message.get_field_by_name("quantity").unwrap().into_owned()
+        else {
+            panic!("quantity should be a message");
+        };
+        assert_eq!(
+            quantity.get_field_by_name("value").unwrap().as_ref(),
+            &Value::I64(5)
+        );
+    }
+
+    #[test]
+    fn test_protobuf_serialization_unknown_enum() {
+        let format = order_format();
+        let descriptor = get_message_descriptor(&format).unwrap();
+
+        let err = super::serialize(&format, &descriptor, &order_batch("LOST")).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("'LOST' is not a value of enum test.Order.Status"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_protobuf_format_requires_schema() {
+        let format = ProtobufFormat {
+            compiled_schema: None,
+            ..test_format()
+        };
+        assert!(ArrowSerializer::new(Format::Protobuf(format)).is_err());
+    }
+}
@@ -1,29 +1,45 @@
 use crate::avro::schema;
-use crate::{avro, json};
+use crate::json::ser::{write_json, JsonShape};
+use crate::{avro, json, proto};
 use arrow_array::cast::AsArray;
-use arrow_array::RecordBatch;
+use arrow_array::types::UInt64Type;
+use arrow_array::{RecordBatch, StructArray};
 use arrow_json::writer::record_batches_to_json_rows;
 use arrow_schema::{DataType, Field};
-use arroyo_rpc::formats::{AvroFormat, Format, JsonFormat, RawStringFormat};
-use arroyo_rpc::TIMESTAMP_FIELD;
-use serde_json::Value;
+use arroyo_rpc::formats::{
+    AvroFormat, Format, JsonFormat, ParquetCompression, ParquetFormat, ProtobufFormat,
+    RawStringFormat,
+};
+use arroyo_rpc::{TIMESTAMP_FIELD, UPDATING_META_FIELD};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use prost_reflect::MessageDescriptor;
+use serde_json::{Map, Value};
 use std::sync::Arc;
 
 pub struct ArrowSerializer {
     kafka_schema: Option<Value>,
     avro_schema: Option<Arc<apache_avro::schema::Schema>>,
+    proto_descriptor: Option<MessageDescriptor>,
     format: Format,
     projection: Vec<usize>,
 }
 
 impl ArrowSerializer {
-    pub fn new(format: Format) -> Self {
-        Self {
+    pub fn new(format: Format) -> anyhow::Result<Self> {
+        let proto_descriptor = match &format {
+            Format::Protobuf(proto) => Some(proto::schema::get_message_descriptor(proto)?),
+            _ => None,
+        };
+
+        Ok(Self {
             kafka_schema: None,
             avro_schema: None,
+            proto_descriptor,
             format,
             projection: vec![],
-        }
+        })
     }
 
     fn projection(schema: &arrow_schema::Schema) -> Vec<usize> {
@@ -31,7 +47,7 @@ impl ArrowSerializer {
             .fields
             .iter()
             .enumerate()
-            .filter(|(_, f)| f.name() != TIMESTAMP_FIELD)
+            .filter(|(_, f)| f.name() != TIMESTAMP_FIELD && f.name() != UPDATING_META_FIELD)
             .map(|(i, _)| i)
             .collect()
     }
@@ -48,12 +64,19 @@ impl ArrowSerializer {
         schema::to_avro("ArroyoAvro", &Self::projected_schema(schema).into())
     }
 
-    pub fn json_schema(schema: &arrow_schema::Schema) -> Value {
-        json::arrow_to_json_schema(&Self::projected_schema(schema).into())
+    pub fn json_schema(schema: &arrow_schema::Schema, format: &JsonFormat) -> Value {
+        json::arrow_to_json_schema(
+            &Self::projected_schema(schema).into(),
+            &format.decimal_encoding,
+        )
     }
 
-    pub fn kafka_schema(schema: &arrow_schema::Schema) -> Value {
-        json::arrow_to_kafka_json("ArroyoJson", &Self::projected_schema(schema).into())
+    pub fn kafka_schema(schema: &arrow_schema::Schema, format: &JsonFormat) -> Value {
+        json::arrow_to_kafka_json(
+            "ArroyoJson",
+            &Self::projected_schema(schema).into(),
+            &format.decimal_encoding,
+        )
     }
 
     pub fn serialize(&mut self, batch: &RecordBatch) -> Box<dyn Iterator<Item = Vec<u8>> + Send> {
@@ -62,21 +85,30 @@ impl ArrowSerializer {
         }
 
         if self.kafka_schema.is_none() {
-            self.kafka_schema = Some(Self::kafka_schema(&batch.schema()));
+            if let Format::Json(json) = &self.format {
+                self.kafka_schema = Some(Self::kafka_schema(&batch.schema(), json));
+            }
         }
 
         if self.avro_schema.is_none() {
             self.avro_schema = Some(Arc::new(Self::avro_schema(&batch.schema())));
         }
 
+        let updating_meta = batch
+            .schema()
+            .index_of(UPDATING_META_FIELD)
+            .ok()
+            .map(|i| batch.column(i).as_struct().clone());
+
         let batch = batch
             .project(&self.projection)
             .expect("batch has wrong number of columns");
 
         match &self.format {
-            Format::Json(json) => self.serialize_json(json, &batch),
+            Format::Json(json) => self.serialize_json(json, &batch, updating_meta.as_ref()),
             Format::Avro(avro) => self.serialize_avro(avro, &batch),
-            Format::Parquet(_) => todo!("parquet"),
+            Format::Protobuf(proto) => self.serialize_proto(proto, &batch),
+            Format::Parquet(parquet) => self.serialize_parquet(parquet, &batch),
             Format::RawString(RawStringFormat {}) => self.serialize_raw_string(&batch),
         }
     }
@@ -85,6 +117,7 @@ impl ArrowSerializer {
         &self,
         json: &JsonFormat,
         batch: &RecordBatch,
+        updating_meta: Option<&StructArray>,
     ) -> Box<dyn Iterator<Item = Vec<u8>> + Send> {
         let header = json.confluent_schema_registry.then(|| {
             if json.include_schema {
@@ -97,9 +130,30 @@ impl ArrowSerializer {
             v
         });
 
-        let rows = record_batches_to_json_rows(&[batch]).unwrap();
+        let shape = JsonShape::for_fields(batch.schema().fields(), &json.decimal_encoding);
+
+        // sink schemas are checked with `validate_data_type` while planning
+        let batch = json::ser::prepare_batch(batch)
+            .expect("batch contains types that cannot be serialized as JSON");
+        let rows = record_batches_to_json_rows(&[&batch]).unwrap();
+        let (rows, shape): (Vec<Value>, _) = if json.debezium {
+            (
+                debezium_rows(rows, updating_meta),
+                JsonShape::object([
+                    ("before".to_string(), shape.clone()),
+                    ("after".to_string(), shape),
+                ]),
+            )
+        } else {
+            (rows.into_iter().map(Value::Object).collect(), shape)
+        };
 
         let include_schema = json.include_schema.then(|| self.kafka_schema.clone());
+        let shape = if include_schema.is_some() {
+            JsonShape::object([("payload".to_string(), shape)])
+        } else {
+            shape
+        };
 
         Box::new(rows.into_iter().map(move |row| {
             let mut buf: Vec<u8> = Vec::with_capacity(128);
@@ -112,14 +166,62 @@ impl ArrowSerializer {
                     "schema": schema,
                     "payload": row
                 }};
-                serde_json::to_writer(&mut buf, &record).unwrap();
+                write_json(&mut buf, &record, &shape);
             } else {
-                serde_json::to_writer(&mut buf, &row).unwrap();
+                write_json(&mut buf, &row, &shape);
             };
 
             buf
         }))
     }
+    fn serialize_proto(
+        &self,
+        format: &ProtobufFormat,
+        batch: &RecordBatch,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + Send> {
+        let descriptor = self
+            .proto_descriptor
+            .as_ref()
+            .expect("must have protobuf descriptor set for protobuf format");
+
+        Box::new(
+            proto::ser::serialize(format, descriptor, batch)
+                .unwrap_or_else(|e| panic!("failed to serialize batch as protobuf: {:?}", e))
+                .into_iter(),
+        )
+    }
+
+    /// Writes the batch as a single, self-contained Parquet file
+    fn serialize_parquet(
+        &self,
+        format: &ParquetFormat,
+        batch: &RecordBatch,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + Send> {
+        if batch.num_rows() == 0 {
+            return Box::new(std::iter::empty());
+        }
+
+        let compression = match format.compression {
+            ParquetCompression::Uncompressed => parquet::basic::Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => parquet::basic::Compression::SNAPPY,
+            ParquetCompression::Gzip => parquet::basic::Compression::GZIP(GzipLevel::default()),
+            ParquetCompression::Zstd => parquet::basic::Compression::ZSTD(ZstdLevel::default()),
+            ParquetCompression::Lz4 => parquet::basic::Compression::LZ4,
+        };
+
+        let props = WriterProperties::builder()
+            .set_compression(compression)
+            .build();
+
+        let mut buf = Vec::with_capacity(batch.get_array_memory_size());
+        let mut writer = ArrowWriter::try_new(&mut buf, batch.schema(), Some(props))
+            .expect("failed to create parquet writer");
+        writer.write(batch).expect("parquet serialization failed");
+        writer.close().expect("failed to finish parquet file");
+
+        Box::new(std::iter::once(buf))
+    }
+
     fn serialize_raw_string(
         &self,
         batch: &RecordBatch,
@@ -192,18 +294,55 @@ impl ArrowSerializer {
     }
 }
 
+/// Wraps rows in Debezium envelopes. Rows produced by updating operators carry a retraction flag
+/// and the id of their key; a retraction immediately followed by an append with the same id is
+/// written as a single update, while any other retraction is a delete. Everything else is a create.
+fn debezium_rows(rows: Vec<Map<String, Value>>, updating_meta: Option<&StructArray>) -> Vec<Value> {
+    let Some(meta) = updating_meta else {
+        return rows
+            .into_iter()
+            .map(|row| json! {{ "before": null, "after": row, "op": "c" }})
+            .collect();
+    };
+
+    let is_retract = meta.column(0).as_boolean();
+    let ids = meta.column(1).as_primitive::<UInt64Type>();
+
+    let mut output = Vec::with_capacity(rows.len());
+    let mut rows = rows.into_iter().enumerate().peekable();
+    while let Some((i, row)) = rows.next() {
+        if !is_retract.value(i) {
+            output.push(json! {{ "before": null, "after": row, "op": "c" }});
+            continue;
+        }
+
+        match rows.next_if(|(j, _)| !is_retract.value(*j) && ids.value(*j) == ids.value(i)) {
+            Some((_, after)) => {
+                output.push(json! {{ "before": row, "after": after, "op": "u" }});
+            }
+            None => {
+                output.push(json! {{ "before": row, "after": null, "op": "d" }});
+            }
+        }
+    }
+
+    output
+}
+
 #[cfg(test)]
 mod tests {
     use crate::ser::ArrowSerializer;
+    use arrow_array::cast::AsArray;
     use arrow_schema::{Schema, TimeUnit};
-    use arroyo_rpc::formats::{Format, RawStringFormat};
+    use arroyo_rpc::formats::{Format, ParquetCompression, ParquetFormat, RawStringFormat};
     use arroyo_types::to_nanos;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
     use std::sync::Arc;
     use std::time::{Duration, SystemTime};
 
     #[test]
     fn test_raw_string() {
-        let mut serializer = ArrowSerializer::new(Format::RawString(RawStringFormat {}));
+        let mut serializer = ArrowSerializer::new(Format::RawString(RawStringFormat {})).unwrap();
 
         let data: Vec<_> = vec!["a", "b", "blah", "whatever"]
             .iter()
@@ -250,7 +389,9 @@ mod tests {
             debezium: false,
             unstructured: false,
             timestamp_format: Default::default(),
-        }));
+            decimal_encoding: Default::default(),
+        }))
+        .unwrap();
 
         let text: Vec<_> = vec!["a", "b", "blah", "whatever"]
             .iter()
@@ -292,4 +433,146 @@ mod tests {
         assert_eq!(iter.next().unwrap(), br#"{"value":"whatever","number":4}"#);
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn test_debezium_updates() {
+        let mut serializer = ArrowSerializer::new(Format::Json(arroyo_rpc::formats::JsonFormat {
+            debezium: true,
+            ..Default::default()
+        }))
+        .unwrap();
+
+        let schema = Arc::new(Schema::new(vec![
+            arrow_schema::Field::new("key", arrow_schema::DataType::Utf8, false),
+            arrow_schema::Field::new("count", arrow_schema::DataType::Int64, false),
+            (*arroyo_rpc::updating_meta_field()).clone(),
+            arrow_schema::Field::new(
+                "_timestamp",
+                arrow_schema::DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]));
+
+        let meta = arrow_array::StructArray::new(
+            arroyo_rpc::updating_meta_fields(),
+            vec![
+                Arc::new(arrow_array::BooleanArray::from(vec![
+                    false, true, false, true,
+                ])),
+                Arc::new(arrow_array::UInt64Array::from(vec![1, 2, 2, 3])),
+            ],
+            None,
+        );
+
+        let batch = arrow_array::RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(arrow_array::StringArray::from(vec!["a", "b", "b", "c"])),
+                Arc::new(arrow_array::Int64Array::from(vec![1, 1, 2, 5])),
+                Arc::new(meta),
+                Arc::new(arrow_array::TimestampNanosecondArray::from(vec![
+                    1, 2, 3, 4,
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let mut iter = serializer.serialize(&batch);
+        assert_eq!(
+            iter.next().unwrap(),
+            br#"{"before":null,"after":{"key":"a","count":1},"op":"c"}"#
+        );
+        assert_eq!(
+            iter.next().unwrap(),
+            br#"{"before":{"key":"b","count":1},"after":{"key":"b","count":2},"op":"u"}"#
+        );
+        assert_eq!(
+            iter.next().unwrap(),
+            br#"{"before":{"key":"c","count":5},"after":null,"op":"d"}"#
+        );
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_debezium_decimals() {
+        let mut serializer = ArrowSerializer::new(Format::Json(arroyo_rpc::formats::JsonFormat {
+            debezium: true,
+            ..Default::default()
+        }))
+        .unwrap();
+
+        let prices = arrow_array::Decimal128Array::from(vec![99999999999999999999999999_i128])
+            .with_precision_and_scale(38, 10)
+            .unwrap();
+        let schema = Arc::new(Schema::new(vec![
+            arrow_schema::Field::new("price", prices.data_type().clone(), false),
+            arrow_schema::Field::new(
+                "_timestamp",
+                arrow_schema::DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]));
+
+        let batch = arrow_array::RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(prices),
+                Arc::new(arrow_array::TimestampNanosecondArray::from(vec![1])),
+            ],
+        )
+        .unwrap();
+
+        let mut iter = serializer.serialize(&batch);
+        assert_eq!(
+            iter.next().unwrap(),
+            br#"{"before":null,"after":{"price":9999999999999999.9999999999},"op":"c"}"#
+        );
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_parquet() {
+        let mut serializer = ArrowSerializer::new(Format::Parquet(ParquetFormat {
+            compression: ParquetCompression::Zstd,
+        }))
+        .unwrap();
+
+        let schema = Arc::new(Schema::new(vec![
+            arrow_schema::Field::new("value", arrow_schema::DataType::Utf8, false),
+            arrow_schema::Field::new(
+                "_timestamp",
+                arrow_schema::DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]));
+
+        let batch = arrow_array::RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(arrow_array::StringArray::from(vec!["a", "b", "c"])),
+                Arc::new(arrow_array::TimestampNanosecondArray::from(vec![1, 2, 3])),
+            ],
+        )
+        .unwrap();
+
+        let files: Vec<_> = serializer.serialize(&batch).collect();
+        assert_eq!(files.len(), 1);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(
+            files.into_iter().next().unwrap(),
+        ))
+        .unwrap();
+        assert!(matches!(
+            reader.metadata().row_group(0).column(0).compression(),
+            parquet::basic::Compression::ZSTD(_)
+        ));
+
+        let batches: Vec<_> = reader.build().unwrap().map(|b| b.unwrap()).collect();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_columns(), 1);
+        assert_eq!(
+            batches[0].column(0).as_string::<i32>(),
+            &arrow_array::StringArray::from(vec!["a", "b", "c"])
+        );
+    }
 }
@@ -2,10 +2,11 @@ use std::collections::HashMap;
 use std::sync::{Arc, OnceLock, RwLock};
 
 use arroyo_types::{
-    TaskInfo, BATCHES_RECV, BATCHES_SENT, BYTES_RECV, BYTES_SENT, DESERIALIZATION_ERRORS,
-    MESSAGES_RECV, MESSAGES_SENT,
+    TaskInfo, BATCHES_RECV, BATCHES_SENT, BUSY_TIME, BYTES_RECV, BYTES_SENT, DEAD_LETTERS,
+    DESERIALIZATION_ERRORS, MESSAGES_RECV, MESSAGES_SENT, TX_QUEUE_REM, TX_QUEUE_SIZE,
 };
 use lazy_static::lazy_static;
+use prometheus::proto::MetricType;
 use prometheus::{
     labels, register_histogram, register_int_counter_vec, register_int_gauge, Histogram,
     HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts,
@@ -85,6 +86,18 @@ lazy_static! {
         &TASK_METRIC_LABELS
     )
     .unwrap();
+    pub static ref DEAD_LETTERS_COUNTER: IntCounterVec = register_int_counter_vec!(
+        DEAD_LETTERS,
+        "Count of invalid messages sent to the dead-letter target",
+        &TASK_METRIC_LABELS
+    )
+    .unwrap();
+    pub static ref BUSY_TIME_COUNTER: IntCounterVec = register_int_counter_vec!(
+        BUSY_TIME,
+        "Microseconds this subtask has spent processing batches",
+        &TASK_METRIC_LABELS
+    )
+    .unwrap();
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
@@ -96,6 +109,8 @@ pub enum TaskCounters {
     BytesReceived,
     BytesSent,
     DeserializationErrors,
+    DeadLetters,
+    BusyTime,
 }
 
 impl TaskCounters {
@@ -108,6 +123,8 @@ impl TaskCounters {
             TaskCounters::BytesReceived => &BYTES_RECEIVED_COUNTER,
             TaskCounters::BytesSent => &BYTES_SENT_COUNTER,
             TaskCounters::DeserializationErrors => &DESERIALIZATION_ERRORS_COUNTER,
+            TaskCounters::DeadLetters => &DEAD_LETTERS_COUNTER,
+            TaskCounters::BusyTime => &BUSY_TIME_COUNTER,
         }
     }
 
@@ -170,3 +187,57 @@ pub fn register_queue_gauge<T>(
         })
         .collect()
 }
+
+// the metrics reported to the controller with each worker heartbeat
+const SNAPSHOT_METRICS: [&str; 7] = [
+    MESSAGES_RECV,
+    MESSAGES_SENT,
+    BYTES_RECV,
+    BYTES_SENT,
+    BUSY_TIME,
+    TX_QUEUE_REM,
+    TX_QUEUE_SIZE,
+];
+
+/// Returns the current values of the task metrics registered in this process, keyed by operator id
+/// and subtask index and then by metric name. Metrics that are reported per output queue are summed
+/// across the subtask's queues.
+pub fn task_metrics_snapshot() -> HashMap<(String, u32), HashMap<String, f64>> {
+    let mut snapshot: HashMap<(String, u32), HashMap<String, f64>> = HashMap::new();
+
+    for family in prometheus::gather() {
+        if !SNAPSHOT_METRICS.contains(&family.get_name()) {
+            continue;
+        }
+
+        for metric in family.get_metric() {
+            let mut operator_id = None;
+            let mut subtask_idx = None;
+            for label in metric.get_label() {
+                match label.get_name() {
+                    "operator_id" => operator_id = Some(label.get_value().to_string()),
+                    "subtask_idx" => subtask_idx = label.get_value().parse().ok(),
+                    _ => {}
+                }
+            }
+
+            let (Some(operator_id), Some(subtask_idx)) = (operator_id, subtask_idx) else {
+                continue;
+            };
+
+            let value = match family.get_field_type() {
+                MetricType::COUNTER => metric.get_counter().get_value(),
+                MetricType::GAUGE => metric.get_gauge().get_value(),
+                _ => continue,
+            };
+
+            *snapshot
+                .entry((operator_id, subtask_idx))
+                .or_default()
+                .entry(family.get_name().to_string())
+                .or_default() += value;
+        }
+    }
+
+    snapshot
+}
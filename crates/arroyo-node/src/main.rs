@@ -8,18 +8,20 @@ use std::{
 
 use anyhow::{anyhow, bail};
 use arroyo_rpc::grpc::{
-    controller_grpc_client::ControllerGrpcClient, node_grpc_server::NodeGrpc,
+    api, controller_grpc_client::ControllerGrpcClient, node_grpc_server::NodeGrpc,
     node_grpc_server::NodeGrpcServer, start_worker_req, GetWorkersReq, GetWorkersResp,
     HeartbeatNodeReq, RegisterNodeReq, StartWorkerReq, StartWorkerResp, StopWorkerReq,
     StopWorkerResp, StopWorkerStatus, WorkerFinishedReq,
 };
 use arroyo_server_common::shutdown::Shutdown;
 use arroyo_types::{
-    grpc_port, ports, to_millis, NodeId, WorkerId, CONTROLLER_ADDR_ENV, JOB_ID_ENV, NODE_ID_ENV,
-    RUN_ID_ENV, TASK_SLOTS_ENV, WORKER_ID_ENV,
+    grpc_port, ports, to_millis, NodeId, WorkerId, ARROYO_PROGRAM_ENV, CONTROLLER_ADDR_ENV,
+    JOB_ID_ENV, NODE_ID_ENV, RUN_ID_ENV, TASK_SLOTS_ENV, WORKER_ID_ENV,
 };
+use base64::{engine::general_purpose, Engine as _};
 use lazy_static::lazy_static;
 use prometheus::{register_gauge, Gauge};
+use prost::Message;
 use rand::Rng;
 use std::os::unix::fs::PermissionsExt;
 use std::process::exit;
@@ -81,6 +83,22 @@ async fn create_file_if_needed(path: &Path, contents: &[u8], mode: Option<u32>)
     panic!("Exhausted attempts to create file");
 }
 
+async fn kill_workers(workers: &Mutex<HashMap<WorkerId, WorkerStatus>>) {
+    let pids: Vec<_> = workers
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|w| w.running)
+        .map(|w| w.pid)
+        .collect();
+
+    for pid in pids {
+        if !signal_process("KILL", pid).await {
+            warn!("failed to kill worker process {}", pid);
+        }
+    }
+}
+
 async fn signal_process(signal: &str, pid: u32) -> bool {
     tokio::process::Command::new("kill")
         .arg("-s")
@@ -122,12 +140,26 @@ impl NodeServer {
             .unwrap();
         tokio::fs::create_dir_all(&dir).await.unwrap();
 
-        let wasm = dir.join("wasm_fns_bg.wasm");
-        create_file_if_needed(&wasm, &header.wasm, None).await;
+        if !header.wasm.is_empty() {
+            let wasm = dir.join("wasm_fns_bg.wasm");
+            create_file_if_needed(&wasm, &header.wasm, None).await;
+        }
 
         // TODO: write the file as bytes are streamed in
 
-        let mut buf = vec![0; (header.binary_size as usize).min(MAX_BIN_SIZE)];
+        // the binary is followed by the UDF dylibs in the stream
+        let binary_size = header.binary_size as usize;
+        let total_size = binary_size + header.udfs.iter().map(|u| u.size as usize).sum::<usize>();
+        if total_size > MAX_BIN_SIZE {
+            bail!(
+                "binary and UDFs for job {} are too large ({} > {} bytes)",
+                header.job_id,
+                total_size,
+                MAX_BIN_SIZE
+            );
+        }
+
+        let mut buf = vec![0; total_size];
         let mut bytes = 0;
         let mut next_part = 0;
         loop {
@@ -145,6 +177,9 @@ impl NodeServer {
             }
             next_part += 1;
 
+            if bytes + data.data.len() > buf.len() {
+                bail!("Received more data than declared in the header");
+            }
             buf[bytes..bytes + data.data.len()].copy_from_slice(&data.data);
             bytes += data.data.len();
 
@@ -153,10 +188,43 @@ impl NodeServer {
             }
         }
 
+        if bytes != total_size {
+            bail!("Expected {} bytes, received {}", total_size, bytes);
+        }
+
         let bin = dir.join("pipeline");
-        create_file_if_needed(&bin, &buf, Some(0o776)).await;
+        create_file_if_needed(&bin, &buf[..binary_size], Some(0o776)).await;
+
+        let mut program = api::ArrowProgram::decode(&header.program[..])
+            .map_err(|e| anyhow!("invalid program for job {}: {:?}", header.job_id, e))?;
+
+        // point the program at our local copies of the UDFs rather than wherever the controller
+        // loaded them from, which may not be reachable from this node
+        let udf_dir = dir.join("udfs");
+        tokio::fs::create_dir_all(&udf_dir).await?;
+        let mut offset = binary_size;
+        for udf in &header.udfs {
+            let data = &buf[offset..offset + udf.size as usize];
+            offset += udf.size as usize;
+
+            let config = program
+                .program_config
+                .as_mut()
+                .and_then(|c| c.udf_dylibs.get_mut(&udf.name))
+                .ok_or_else(|| anyhow!("received dylib for unknown UDF '{}'", udf.name))?;
+
+            let file_name = Path::new(&config.dylib_path)
+                .file_name()
+                .ok_or_else(|| anyhow!("invalid dylib path '{}'", config.dylib_path))?;
+
+            let path = udf_dir.join(file_name);
+            create_file_if_needed(&path, data, None).await;
+            config.dylib_path = format!("file://{}", path.to_string_lossy());
+        }
         drop(buf);
 
+        let program = general_purpose::STANDARD_NO_PAD.encode(program.encode_to_vec());
+
         info!("Starting worker for job {}", header.job_id);
 
         // TODO: Check that we have enough slots to schedule this
@@ -173,12 +241,14 @@ impl NodeServer {
             command.env(env, value);
         }
         let mut child = command
+            .arg("worker")
             .env("RUST_LOG", "info")
             .env(WORKER_ID_ENV, format!("{}", worker_id.0))
             .env(NODE_ID_ENV, format!("{}", node_id.0))
             .env(JOB_ID_ENV, header.job_id.clone())
             .env(TASK_SLOTS_ENV, format!("{}", slots))
             .env(RUN_ID_ENV, format!("{}", header.run_id))
+            .env(ARROYO_PROGRAM_ENV, program)
             .current_dir(&dir)
             .kill_on_drop(true)
             .spawn()
@@ -329,9 +399,11 @@ pub async fn main() {
     let _guard = arroyo_server_common::init_logging(&format!("node-{}", node_id.0));
     let (worker_finished_tx, mut worker_finished_rx) = channel(128);
 
+    let workers = Arc::new(Mutex::new(HashMap::new()));
+
     let server = NodeServer {
         id: node_id,
-        workers: Arc::new(Mutex::new(HashMap::new())),
+        workers: workers.clone(),
         worker_finished_tx,
     };
 
@@ -400,8 +472,12 @@ pub async fn main() {
                             }))
                             .await
                         {
-                            error!("shutting down: controller failed heartbeat with {:?}", e);
-                            return;
+                            // the controller has either lost track of us or is unreachable; in
+                            // both cases it will have rescheduled our workers elsewhere, so we
+                            // stop them and register again from a clean slate
+                            error!("controller failed heartbeat with {:?}; stopping workers and reconnecting", e);
+                            kill_workers(&workers).await;
+                            break;
                         }
                     }
                 }
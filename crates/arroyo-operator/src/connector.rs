@@ -1,9 +1,10 @@
 use crate::operator::OperatorNode;
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
 };
 use arroyo_rpc::OperatorConfig;
+use async_trait::async_trait;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
 use serde_json::value::Value;
@@ -22,6 +23,18 @@ pub struct Connection {
     pub description: String,
 }
 
+/// A connector that can serve point lookups by key, used to implement lookup joins. Values
+/// are returned as raw bytes, and are deserialized by the join operator using the format
+/// of the table.
+#[async_trait]
+pub trait LookupConnector: Send {
+    fn name(&self) -> String;
+
+    /// Looks up the values for each of `keys`, returning a result for each key in the same
+    /// order; missing keys are returned as `None`
+    async fn lookup(&mut self, keys: &[String]) -> anyhow::Result<Vec<Option<Vec<u8>>>>;
+}
+
 pub trait Connector: Send {
     type ProfileT: DeserializeOwned + Serialize;
     type TableT: DeserializeOwned + Serialize;
@@ -106,6 +119,16 @@ pub trait Connector: Send {
         table: Self::TableT,
         config: OperatorConfig,
     ) -> anyhow::Result<OperatorNode>;
+
+    #[allow(unused)]
+    fn make_lookup(
+        &self,
+        profile: Self::ProfileT,
+        table: Self::TableT,
+        config: OperatorConfig,
+    ) -> anyhow::Result<Box<dyn LookupConnector>> {
+        bail!("{} does not support lookup tables", self.name());
+    }
 }
 
 pub trait ErasedConnector: Send {
@@ -172,6 +195,8 @@ pub trait ErasedConnector: Send {
     ) -> anyhow::Result<Connection>;
 
     fn make_operator(&self, config: OperatorConfig) -> anyhow::Result<OperatorNode>;
+
+    fn make_lookup(&self, config: OperatorConfig) -> anyhow::Result<Box<dyn LookupConnector>>;
 }
 
 impl<C: Connector> ErasedConnector for C {
@@ -290,4 +315,15 @@ impl<C: Connector> ErasedConnector for C {
             config,
         )
     }
+
+    fn make_lookup(&self, config: OperatorConfig) -> anyhow::Result<Box<dyn LookupConnector>> {
+        self.make_lookup(
+            self.parse_config(&config.connection).map_err(|e| {
+                anyhow!("invalid profile config for lookup {}: {:?}", self.name(), e)
+            })?,
+            self.parse_table(&config.table)
+                .map_err(|e| anyhow!("invalid table config for lookup {}: {:?}", self.name(), e))?,
+            config,
+        )
+    }
 }
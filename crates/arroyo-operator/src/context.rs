@@ -1,12 +1,18 @@
+use crate::dead_letter::{DeadLetter, DeadLetterWriter, SourcePosition};
 use crate::{server_for_hash_array, RateLimiter};
+use apache_avro::types::Value as AvroValue;
+use apache_avro::AvroResult;
 use arrow::array::{make_builder, Array, ArrayBuilder, PrimitiveArray, RecordBatch};
 use arrow::compute::{partition, sort_to_indices, take};
 use arrow::datatypes::{SchemaRef, UInt64Type};
 use arroyo_formats::de::ArrowDeserializer;
 use arroyo_metrics::{register_queue_gauge, QueueGauges, TaskCounters};
 use arroyo_rpc::df::ArroyoSchema;
-use arroyo_rpc::formats::{BadData, Format, Framing};
-use arroyo_rpc::grpc::{CheckpointMetadata, TableConfig, TaskCheckpointEventType};
+use arroyo_rpc::formats::{BadData, DeadLetterTarget, Format, Framing};
+use arroyo_rpc::grpc::{
+    CheckpointMetadata, OperatorCheckpointMetadata, OperatorMetadata, TableConfig,
+    TaskCheckpointEventType,
+};
 use arroyo_rpc::schema_resolver::SchemaResolver;
 use arroyo_rpc::{get_hasher, CompactionResult, ControlMessage, ControlResp};
 use arroyo_state::tables::table_manager::TableManager;
@@ -257,6 +263,7 @@ pub struct ArrowContext {
     buffered_error: Option<UserError>,
     error_rate_limiter: RateLimiter,
     deserializer: Option<ArrowDeserializer>,
+    pub(crate) dead_letters: Option<DeadLetterWriter>,
     pub table_manager: TableManager,
 }
 
@@ -431,14 +438,30 @@ impl ArrowContext {
     ) -> Self {
         let (watermark, metadata) = if let Some(metadata) = restore_from {
             let (watermark, operator_metadata) = {
-                let metadata = StateBackend::load_operator_metadata(
-                    &task_info.job_id,
-                    &task_info.operator_id,
-                    metadata.epoch,
-                )
-                .await
-                .expect("lookup should succeed")
-                .expect("require metadata");
+                let metadata = if metadata.operator_ids.contains(&task_info.operator_id) {
+                    StateBackend::load_operator_metadata(
+                        &task_info.job_id,
+                        &task_info.operator_id,
+                        metadata.epoch,
+                    )
+                    .await
+                    .expect("lookup should succeed")
+                    .expect("require metadata")
+                } else {
+                    // operators that were added since the savepoint this job was restored from
+                    // start with empty state at the restored epoch
+                    OperatorCheckpointMetadata {
+                        operator_metadata: Some(OperatorMetadata {
+                            job_id: task_info.job_id.clone(),
+                            operator_id: task_info.operator_id.clone(),
+                            epoch: metadata.epoch,
+                            min_watermark: None,
+                            max_watermark: None,
+                            parallelism: task_info.parallelism as u64,
+                        }),
+                        ..Default::default()
+                    }
+                };
                 (
                     metadata
                         .operator_metadata
@@ -509,6 +532,7 @@ impl ArrowContext {
             buffer: out_schema.map(|t| ContextBuffer::new(t.schema)),
             error_rate_limiter: RateLimiter::new(),
             deserializer: None,
+            dead_letters: None,
             buffered_error: None,
             table_manager,
         }
@@ -574,7 +598,8 @@ impl ArrowContext {
                         self.collector.collect(batch).await;
                     }
                     Err(e) => {
-                        self.collect_source_errors(vec![e]).await?;
+                        self.collect_source_errors(vec![e], None, &SourcePosition::default())
+                            .await?;
                     }
                 }
             }
@@ -626,6 +651,21 @@ impl ArrowContext {
             .unwrap();
     }
 
+    /// Reports an error to the user and fails the task, which causes the controller to restart
+    /// the job from its last completed checkpoint
+    pub async fn fail_task(&mut self, error: UserError) {
+        let message = format!("{}: {}", error.name, error.details);
+        self.report_user_error(error).await;
+        self.control_tx
+            .send(ControlResp::TaskFailed {
+                operator_id: self.task_info.operator_id.clone(),
+                task_index: self.task_info.task_index,
+                error: message,
+            })
+            .await
+            .unwrap();
+    }
+
     pub async fn send_checkpoint_event(
         &mut self,
         barrier: CheckpointBarrier,
@@ -658,17 +698,21 @@ impl ArrowContext {
         format: Format,
         framing: Option<Framing>,
         bad_data: Option<BadData>,
-    ) {
+    ) -> Result<(), UserError> {
         if self.deserializer.is_some() {
             panic!("Deserialize already initialized");
         }
 
-        self.deserializer = Some(ArrowDeserializer::new(
-            format,
-            self.out_schema.as_ref().expect("no out schema").clone(),
-            framing,
-            bad_data.unwrap_or_default(),
-        ));
+        self.deserializer = Some(
+            ArrowDeserializer::new(
+                format,
+                self.out_schema.as_ref().expect("no out schema").clone(),
+                framing,
+                bad_data.unwrap_or_default(),
+            )
+            .map_err(|e| UserError::new("Invalid format", e.to_string()))?,
+        );
+        Ok(())
     }
 
     pub fn initialize_deserializer_with_resolver(
@@ -677,20 +721,36 @@ impl ArrowContext {
         framing: Option<Framing>,
         bad_data: Option<BadData>,
         schema_resolver: Arc<dyn SchemaResolver + Sync>,
-    ) {
-        self.deserializer = Some(ArrowDeserializer::with_schema_resolver(
-            format,
-            framing,
-            self.out_schema.as_ref().expect("no out schema").clone(),
-            bad_data.unwrap_or_default(),
-            schema_resolver,
-        ));
+    ) -> Result<(), UserError> {
+        self.deserializer = Some(
+            ArrowDeserializer::with_schema_resolver(
+                format,
+                framing,
+                self.out_schema.as_ref().expect("no out schema").clone(),
+                bad_data.unwrap_or_default(),
+                schema_resolver,
+            )
+            .map_err(|e| UserError::new("Invalid format", e.to_string()))?,
+        );
+        Ok(())
     }
 
     pub async fn deserialize_slice(
         &mut self,
         msg: &[u8],
         time: SystemTime,
+    ) -> Result<(), UserError> {
+        self.deserialize_slice_at(msg, time, &SourcePosition::default())
+            .await
+    }
+
+    /// Deserializes a message read from the given position in the source, which is recorded
+    /// with the message if it is sent to a dead-letter target
+    pub async fn deserialize_slice_at(
+        &mut self,
+        msg: &[u8],
+        time: SystemTime,
+        position: &SourcePosition,
     ) -> Result<(), UserError> {
         let deserializer = self
             .deserializer
@@ -703,22 +763,87 @@ impl ArrowContext {
                 time,
             )
             .await;
-        self.collect_source_errors(errors).await?;
+        self.collect_source_errors(errors, Some(msg), position)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn deserialize_avro_value(
+        &mut self,
+        value: AvroResult<AvroValue>,
+        time: SystemTime,
+        position: &SourcePosition,
+    ) -> Result<(), UserError> {
+        let deserializer = self
+            .deserializer
+            .as_mut()
+            .expect("deserializer not initialized!");
+        let result = deserializer.deserialize_avro_value(
+            &mut self.buffer.as_mut().expect("no out schema").buffer,
+            value,
+            time,
+        );
+        self.collect_source_errors(result.err().into_iter().collect(), None, position)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Writes out any buffered dead letters; called before checkpointing so that dead letters
+    /// are durable before the source positions they came from are
+    pub async fn flush_dead_letters(&mut self) -> Result<(), UserError> {
+        if let Some(dead_letters) = self.dead_letters.as_mut() {
+            dead_letters
+                .flush(&self.task_info)
+                .await
+                .map_err(|e| UserError::new("Failed to write dead letters", e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_dead_letter(
+        &mut self,
+        target: &DeadLetterTarget,
+        letter: DeadLetter,
+    ) -> Result<(), UserError> {
+        if self.dead_letters.is_none() {
+            self.dead_letters = Some(DeadLetterWriter::new(target).await.map_err(|e| {
+                UserError::new("Failed to initialize dead letter target", e.to_string())
+            })?);
+        }
+
+        self.dead_letters
+            .as_mut()
+            .unwrap()
+            .write(&self.task_info, letter)
+            .await
+            .map_err(|e| UserError::new("Failed to write dead letter", e.to_string()))?;
+
+        TaskCounters::DeadLetters.for_task(&self.task_info, |c| c.inc());
 
         Ok(())
     }
 
     /// Handling errors and rate limiting error reporting.
-    /// Considers the `bad_data` option to determine whether to drop or fail on bad data.
-    async fn collect_source_errors(&mut self, errors: Vec<SourceError>) -> Result<(), UserError> {
+    /// Considers the `bad_data` option to determine whether to drop, dead-letter or fail on
+    /// bad data.
+    async fn collect_source_errors(
+        &mut self,
+        errors: Vec<SourceError>,
+        payload: Option<&[u8]>,
+        position: &SourcePosition,
+    ) -> Result<(), UserError> {
         let bad_data = self
             .deserializer
             .as_ref()
             .expect("deserializer not initialized")
-            .bad_data();
+            .bad_data()
+            .clone();
         for error in errors {
             match error {
-                SourceError::BadData { details } => match bad_data {
+                SourceError::BadData { details } => match &bad_data {
                     BadData::Drop {} => {
                         self.error_rate_limiter
                             .rate_limit(|| async {
@@ -736,6 +861,19 @@ impl ArrowContext {
                             .await;
                         TaskCounters::DeserializationErrors.for_task(&self.task_info, |c| c.inc())
                     }
+                    BadData::DeadLetter { target } => {
+                        TaskCounters::DeserializationErrors.for_task(&self.task_info, |c| c.inc());
+                        self.send_dead_letter(
+                            target,
+                            DeadLetter {
+                                payload: payload.map(|p| p.to_vec()),
+                                error: details,
+                                position: position.clone(),
+                                timestamp: SystemTime::now(),
+                            },
+                        )
+                        .await?;
+                    }
                     BadData::Fail {} => {
                         return Err(UserError::new("Deserialization error", details));
                     }
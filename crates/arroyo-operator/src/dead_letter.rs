@@ -0,0 +1,346 @@
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Result};
+use arroyo_rpc::formats::DeadLetterTarget;
+use arroyo_storage::StorageProvider;
+use arroyo_types::{to_millis, TaskInfo};
+use async_trait::async_trait;
+use base64::engine::general_purpose;
+use base64::Engine;
+use serde_json::json;
+use tracing::info;
+
+// files written to a filesystem target are rolled once they reach this size, in addition to
+// at every checkpoint
+const MAX_BUFFERED_BYTES: usize = 8 * 1024 * 1024;
+
+// dead letters are sent to their target in batches of up to this many, as well as at every
+// checkpoint
+const MAX_PENDING_LETTERS: usize = 1000;
+
+/// Position in the source that a message was read from, recorded alongside dead letters
+#[derive(Debug, Clone, Default)]
+pub struct SourcePosition {
+    pub partition: Option<String>,
+    pub offset: Option<String>,
+}
+
+impl SourcePosition {
+    pub fn new(partition: impl ToString, offset: impl ToString) -> Self {
+        Self {
+            partition: Some(partition.to_string()),
+            offset: Some(offset.to_string()),
+        }
+    }
+}
+
+/// A message that could not be deserialized
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub payload: Option<Vec<u8>>,
+    pub error: String,
+    pub position: SourcePosition,
+    pub timestamp: SystemTime,
+}
+
+impl DeadLetter {
+    fn to_json(&self, task_info: &TaskInfo) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "payload": self.payload.as_ref().map(|p| general_purpose::STANDARD.encode(p)),
+            "error": self.error,
+            "job_id": task_info.job_id,
+            "operator_id": task_info.operator_id,
+            "task_index": task_info.task_index,
+            "partition": self.position.partition,
+            "offset": self.position.offset,
+            "timestamp": to_millis(self.timestamp),
+        }))
+        .unwrap()
+    }
+}
+
+/// A destination for dead letters. The filesystem target is implemented here, while targets
+/// that need their own clients, like Kafka, are implemented by the connectors and created
+/// through the factory installed with [`set_dead_letter_sink_factory`].
+#[async_trait]
+pub trait DeadLetterSink: Send {
+    /// Writes a batch of encoded dead letters, returning once all of them have been accepted
+    /// by the target
+    async fn write(&mut self, task_info: &TaskInfo, letters: Vec<Vec<u8>>) -> Result<()>;
+
+    /// Makes all of the letters written so far durable
+    async fn flush(&mut self, task_info: &TaskInfo) -> Result<()>;
+}
+
+/// Creates sinks for the dead-letter targets that aren't implemented in this crate
+#[async_trait]
+pub trait DeadLetterSinkFactory: Send + Sync {
+    async fn create(&self, target: &DeadLetterTarget) -> Result<Box<dyn DeadLetterSink>>;
+}
+
+static SINK_FACTORY: OnceLock<Box<dyn DeadLetterSinkFactory>> = OnceLock::new();
+
+/// Installs the factory used for dead-letter targets that aren't implemented in this crate. Only
+/// the first factory installed is used.
+pub fn set_dead_letter_sink_factory(factory: Box<dyn DeadLetterSinkFactory>) {
+    let _ = SINK_FACTORY.set(factory);
+}
+
+/// Writes dead letters to the target configured with `bad_data = 'dead_letter'`. Each
+/// dead letter is a JSON object holding the base64-encoded original payload, the error and
+/// where in the source the message came from.
+///
+/// Letters are flushed at every checkpoint, so that none are lost once the offsets of the
+/// messages they came from have been committed.
+pub struct DeadLetterWriter {
+    sink: Box<dyn DeadLetterSink>,
+    pending: Vec<Vec<u8>>,
+}
+
+impl DeadLetterWriter {
+    pub async fn new(target: &DeadLetterTarget) -> Result<Self> {
+        let sink: Box<dyn DeadLetterSink> = match target {
+            DeadLetterTarget::Filesystem { path } => {
+                Box::new(FilesystemDeadLetterSink::new(path).await?)
+            }
+            target => {
+                SINK_FACTORY
+                    .get()
+                    .ok_or_else(|| anyhow!("dead letter targets other than the filesystem are not available in this process"))?
+                    .create(target)
+                    .await?
+            }
+        };
+
+        Ok(Self::with_sink(sink))
+    }
+
+    pub fn with_sink(sink: Box<dyn DeadLetterSink>) -> Self {
+        Self {
+            sink,
+            pending: vec![],
+        }
+    }
+
+    pub async fn write(&mut self, task_info: &TaskInfo, letter: DeadLetter) -> Result<()> {
+        self.pending.push(letter.to_json(task_info));
+
+        if self.pending.len() >= MAX_PENDING_LETTERS {
+            self.write_pending(task_info).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn write_pending(&mut self, task_info: &TaskInfo) -> Result<()> {
+        if !self.pending.is_empty() {
+            self.sink
+                .write(task_info, std::mem::take(&mut self.pending))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn flush(&mut self, task_info: &TaskInfo) -> Result<()> {
+        self.write_pending(task_info).await?;
+        self.sink.flush(task_info).await
+    }
+}
+
+/// Writes dead letters as newline-delimited JSON files under a path in object storage
+pub struct FilesystemDeadLetterSink {
+    storage: StorageProvider,
+    buffer: Vec<u8>,
+    files_written: usize,
+}
+
+impl FilesystemDeadLetterSink {
+    pub async fn new(path: &str) -> Result<Self> {
+        Ok(Self {
+            storage: StorageProvider::for_url(path)
+                .await
+                .map_err(|e| anyhow!("invalid dead letter path '{}': {:?}", path, e))?,
+            buffer: vec![],
+            files_written: 0,
+        })
+    }
+}
+
+#[async_trait]
+impl DeadLetterSink for FilesystemDeadLetterSink {
+    async fn write(&mut self, task_info: &TaskInfo, letters: Vec<Vec<u8>>) -> Result<()> {
+        for letter in letters {
+            self.buffer.extend(letter);
+            self.buffer.push(b'\n');
+        }
+
+        if self.buffer.len() >= MAX_BUFFERED_BYTES {
+            self.flush(task_info).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self, task_info: &TaskInfo) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let path = format!(
+            "{}/{}-{}-{}-{}.json",
+            task_info.job_id,
+            task_info.operator_id,
+            task_info.task_index,
+            to_millis(SystemTime::now()),
+            self.files_written
+        );
+
+        let url = self
+            .storage
+            .put(path, std::mem::take(&mut self.buffer))
+            .await
+            .map_err(|e| anyhow!("failed to write dead letters: {:?}", e))?;
+        self.files_written += 1;
+
+        info!("wrote dead letters to {}", url);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use arroyo_types::get_test_task_info;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorded {
+        writes: Vec<Vec<Vec<u8>>>,
+        flushes: usize,
+    }
+
+    struct RecordingSink(Arc<Mutex<Recorded>>);
+
+    #[async_trait]
+    impl DeadLetterSink for RecordingSink {
+        async fn write(&mut self, _: &TaskInfo, letters: Vec<Vec<u8>>) -> Result<()> {
+            self.0.lock().unwrap().writes.push(letters);
+            Ok(())
+        }
+
+        async fn flush(&mut self, _: &TaskInfo) -> Result<()> {
+            self.0.lock().unwrap().flushes += 1;
+            Ok(())
+        }
+    }
+
+    fn letter(offset: usize) -> DeadLetter {
+        DeadLetter {
+            payload: Some(b"not json".to_vec()),
+            error: "invalid JSON".to_string(),
+            position: SourcePosition::new(0, offset),
+            timestamp: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_letters_are_written_in_batches() {
+        let task_info = get_test_task_info();
+        let recorded = Arc::new(Mutex::new(Recorded::default()));
+        let mut writer = DeadLetterWriter::with_sink(Box::new(RecordingSink(recorded.clone())));
+
+        for offset in 0..MAX_PENDING_LETTERS + 10 {
+            writer.write(&task_info, letter(offset)).await.unwrap();
+        }
+
+        {
+            let recorded = recorded.lock().unwrap();
+            assert_eq!(recorded.writes.len(), 1);
+            assert_eq!(recorded.writes[0].len(), MAX_PENDING_LETTERS);
+            assert_eq!(recorded.flushes, 0);
+        }
+
+        writer.flush(&task_info).await.unwrap();
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.writes.len(), 2);
+        assert_eq!(recorded.writes[1].len(), 10);
+        assert_eq!(recorded.flushes, 1);
+
+        let last: serde_json::Value =
+            serde_json::from_slice(recorded.writes[1].last().unwrap()).unwrap();
+        assert_eq!(
+            last,
+            json!({
+                "payload": general_purpose::STANDARD.encode(b"not json"),
+                "error": "invalid JSON",
+                "job_id": task_info.job_id,
+                "operator_id": task_info.operator_id,
+                "task_index": task_info.task_index,
+                "partition": "0",
+                "offset": (MAX_PENDING_LETTERS + 9).to_string(),
+                "timestamp": 0,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filesystem_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let task_info = get_test_task_info();
+
+        let mut writer = DeadLetterWriter::new(&DeadLetterTarget::Filesystem {
+            path: format!("file://{}", dir.path().display()),
+        })
+        .await
+        .unwrap();
+
+        // nothing is written until the letters are flushed
+        for offset in 0..3 {
+            writer.write(&task_info, letter(offset)).await.unwrap();
+        }
+        let job_dir = dir.path().join(&task_info.job_id);
+        assert!(!job_dir.exists());
+
+        writer.flush(&task_info).await.unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(&job_dir)
+            .unwrap()
+            .map(|f| f.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+
+        let offsets: Vec<_> = std::fs::read_to_string(&files[0])
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["offset"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(offsets, vec!["0", "1", "2"]);
+
+        // flushing with nothing buffered doesn't write empty files
+        writer.flush(&task_info).await.unwrap();
+        assert_eq!(std::fs::read_dir(&job_dir).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_other_targets_need_a_factory() {
+        let result = DeadLetterWriter::new(&DeadLetterTarget::Kafka {
+            bootstrap_servers: "localhost:9092".to_string(),
+            topic: "dead-letters".to_string(),
+            client_configs: Default::default(),
+        })
+        .await;
+
+        assert!(result.is_err());
+    }
+}
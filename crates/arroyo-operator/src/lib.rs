@@ -19,6 +19,7 @@ use tokio_stream::Stream;
 
 pub mod connector;
 pub mod context;
+pub mod dead_letter;
 pub mod inq_reader;
 pub mod operator;
 
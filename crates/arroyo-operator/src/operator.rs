@@ -16,7 +16,7 @@ use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::Barrier;
 use tokio_stream::StreamExt;
 use tracing::{debug, error, info, warn, Instrument};
@@ -99,6 +99,10 @@ impl OperatorNode {
 
         let final_message = self.run_behavior(&mut ctx, &mut in_qs, ready).await;
 
+        if let Err(e) = ctx.flush_dead_letters().await {
+            ctx.report_user_error(e).await;
+        }
+
         if let Some(final_message) = final_message {
             ctx.broadcast(ArrowMessage::Signal(final_message)).await;
         }
@@ -119,6 +123,13 @@ impl OperatorNode {
 }
 
 async fn run_checkpoint(checkpoint_barrier: CheckpointBarrier, ctx: &mut ArrowContext) -> bool {
+    if let Err(e) = ctx.flush_dead_letters().await {
+        // the checkpoint can't complete without the dead letters, so it's abandoned and the job
+        // restarts from the previous one
+        ctx.fail_task(e).await;
+        return false;
+    }
+
     let watermark = ctx.watermarks.last_present_watermark();
 
     ctx.table_manager
@@ -224,12 +235,14 @@ async fn operator_run_behavior(
                                 TaskCounters::BatchesReceived.for_task(&ctx.task_info, |c| c.inc());
                                 TaskCounters::MessagesReceived.for_task(&ctx.task_info, |c| c.inc_by(record.num_rows() as u64));
                                 TaskCounters::BytesReceived.for_task(&ctx.task_info, |c| c.inc_by(record.get_array_memory_size() as u64));
+                                let start = Instant::now();
                                 this.process_batch_index(idx, in_partitions, record, ctx)
                                     .instrument(tracing::trace_span!("handle_fn",
                                         name,
                                         operator_id = task_info.operator_id,
                                         subtask_idx = task_info.task_index)
                                 ).await;
+                                TaskCounters::BusyTime.for_task(&ctx.task_info, |c| c.inc_by(start.elapsed().as_micros() as u64));
                             }
                             ArrowMessage::Signal(signal) => {
                                 match this.handle_control_message(idx, &signal, &mut counter, &mut closed, in_partitions, ctx).await {
@@ -486,12 +499,17 @@ pub trait ArrowOperator: Send + 'static {
 #[derive(Default)]
 pub struct Registry {
     udfs: HashMap<String, Arc<ScalarUDF>>,
+    udafs: HashMap<String, Arc<AggregateUDF>>,
 }
 
 impl Registry {
     pub fn add_udf(&mut self, udf: Arc<ScalarUDF>) {
         self.udfs.insert(udf.name().to_string(), udf);
     }
+
+    pub fn add_udaf(&mut self, udaf: Arc<AggregateUDF>) {
+        self.udafs.insert(udaf.name().to_string(), udaf);
+    }
 }
 
 impl FunctionRegistry for Registry {
@@ -507,10 +525,10 @@ impl FunctionRegistry for Registry {
     }
 
     fn udaf(&self, name: &str) -> DFResult<Arc<AggregateUDF>> {
-        Err(DataFusionError::NotImplemented(format!(
-            "udaf {} not implemented",
-            name
-        )))
+        self.udafs
+            .get(name)
+            .cloned()
+            .ok_or_else(|| DataFusionError::Execution(format!("Udaf {} not found", name)))
     }
 
     fn udwf(&self, name: &str) -> DFResult<Arc<WindowUDF>> {
@@ -520,3 +538,76 @@ impl FunctionRegistry for Registry {
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::bail;
+    use arroyo_types::{get_test_task_info, TaskInfo};
+    use tokio::sync::mpsc::channel;
+
+    use crate::dead_letter::{DeadLetterSink, DeadLetterWriter};
+
+    use super::*;
+
+    struct FailingSink;
+
+    #[async_trait]
+    impl DeadLetterSink for FailingSink {
+        async fn write(&mut self, _: &TaskInfo, _: Vec<Vec<u8>>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn flush(&mut self, _: &TaskInfo) -> anyhow::Result<()> {
+            bail!("target unavailable")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_dead_letter_flush_fails_checkpoint() {
+        let (_control_tx, control_rx) = channel(128);
+        let (command_tx, mut command_rx) = channel(128);
+        let (out_tx, mut out_rx) = crate::context::batch_bounded(128);
+
+        let mut ctx = ArrowContext::new(
+            get_test_task_info(),
+            None,
+            control_rx,
+            command_tx,
+            1,
+            vec![],
+            None,
+            None,
+            vec![vec![out_tx]],
+            HashMap::new(),
+        )
+        .await;
+        ctx.dead_letters = Some(DeadLetterWriter::with_sink(Box::new(FailingSink)));
+
+        let barrier = CheckpointBarrier {
+            epoch: 1,
+            min_epoch: 0,
+            timestamp: SystemTime::now(),
+            then_stop: false,
+        };
+        assert!(!run_checkpoint(barrier, &mut ctx).await);
+
+        // the failure is reported and the task is failed, rather than the checkpoint completing
+        let Some(ControlResp::Error { message, .. }) = command_rx.recv().await else {
+            panic!("expected an error to be reported");
+        };
+        assert_eq!(message, "Failed to write dead letters");
+        assert!(matches!(
+            command_rx.recv().await,
+            Some(ControlResp::TaskFailed { .. })
+        ));
+        assert!(command_rx.try_recv().is_err());
+
+        assert!(
+            !matches!(
+                tokio::time::timeout(Duration::from_millis(100), out_rx.recv()).await,
+                Ok(Some(_))
+            ),
+            "the barrier should not be forwarded"
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -43,3 +44,93 @@ pub struct OperatorCheckpointGroup {
     pub bytes: u64,
     pub subtasks: Vec<SubtaskCheckpointGroup>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SavepointPost {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SavepointState {
+    Pending,
+    Ready,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Savepoint {
+    pub id: String,
+    pub name: String,
+    pub job_id: String,
+    pub pipeline_name: String,
+    pub state: SavepointState,
+    pub epoch: Option<u32>,
+    pub operators: HashMap<String, Vec<String>>,
+    pub created_at: u64,
+    pub finish_time: Option<u64>,
+    pub failure_message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StateFile {
+    pub path: String,
+    pub bytes: u64,
+    pub rows: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtaskTableState {
+    pub index: Option<u32>,
+    pub bytes: u64,
+    pub rows: u64,
+    pub files: Vec<StateFile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TableState {
+    pub name: String,
+    pub table_type: String,
+    pub bytes: u64,
+    pub rows: u64,
+    pub subtasks: Vec<SubtaskTableState>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorState {
+    pub operator_id: String,
+    pub tables: Vec<TableState>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointStateSummary {
+    pub job_id: String,
+    pub epoch: u32,
+    pub min_epoch: u32,
+    pub operators: Vec<OperatorState>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StateDumpFormat {
+    #[default]
+    Json,
+    Arrow,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "snake_case")]
+pub struct StateDumpQueryParams {
+    pub format: Option<StateDumpFormat>,
+    pub key: Option<String>,
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+}
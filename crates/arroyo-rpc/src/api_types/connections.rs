@@ -50,6 +50,7 @@ pub struct ConnectionProfilePost {
 pub enum ConnectionType {
     Source,
     Sink,
+    Lookup,
 }
 
 impl Display for ConnectionType {
@@ -57,6 +58,7 @@ impl Display for ConnectionType {
         match self {
             ConnectionType::Source => write!(f, "SOURCE"),
             ConnectionType::Sink => write!(f, "SINK"),
+            ConnectionType::Lookup => write!(f, "LOOKUP"),
         }
     }
 }
@@ -68,6 +70,7 @@ impl TryFrom<String> for ConnectionType {
         match value.to_lowercase().as_str() {
             "source" => Ok(ConnectionType::Source),
             "sink" => Ok(ConnectionType::Sink),
+            "lookup" => Ok(ConnectionType::Lookup),
             _ => Err(format!("Invalid connection type: {}", value)),
         }
     }
@@ -99,11 +102,18 @@ pub struct StructType {
     pub fields: Vec<SourceField>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListType {
+    pub items: Box<SourceField>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum FieldType {
     Primitive(PrimitiveType),
     Struct(StructType),
+    List(ListType),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema, PartialEq, Eq)]
@@ -146,6 +156,7 @@ impl From<SourceField> for Field {
                     .map(|t| t.into())
                     .collect::<Vec<Field>>(),
             )),
+            FieldType::List(l) => DataType::List(Arc::new((*l.items).into())),
         };
 
         Field::new(f.field_name, t, f.nullable)
@@ -188,6 +199,9 @@ impl TryFrom<Field> for SourceField {
 
                 FieldType::Struct(st)
             }
+            DataType::List(item) => FieldType::List(ListType {
+                items: Box::new((**item).clone().try_into()?),
+            }),
             dt => {
                 return Err(format!("Unsupported data type {:?}", dt));
             }
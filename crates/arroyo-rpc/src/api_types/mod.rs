@@ -1,16 +1,20 @@
+use auth::*;
 use checkpoints::*;
 use connections::*;
 use metrics::*;
 use pipelines::*;
+use secrets::*;
 use udfs::*;
 
 use serde::{Deserialize, Serialize};
 use utoipa::{IntoParams, ToSchema};
 
+pub mod auth;
 pub mod checkpoints;
 pub mod connections;
 pub mod metrics;
 pub mod pipelines;
+pub mod secrets;
 pub mod udfs;
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -31,10 +35,13 @@ pub struct PaginatedCollection<T> {
     JobCollection = NonPaginatedCollection<Job>,
     OperatorCheckpointGroupCollection = NonPaginatedCollection<OperatorCheckpointGroup>,
     CheckpointCollection = NonPaginatedCollection<Checkpoint>,
+    SavepointCollection = NonPaginatedCollection<Savepoint>,
     OperatorMetricGroupCollection = NonPaginatedCollection<OperatorMetricGroup>,
     ConnectorCollection = NonPaginatedCollection<Connector>,
     ConnectionProfileCollection = NonPaginatedCollection<ConnectionProfile>,
     GlobalUdfCollection = NonPaginatedCollection<GlobalUdf>,
+    ApiKeyCollection = NonPaginatedCollection<ApiKey>,
+    SecretCollection = NonPaginatedCollection<Secret>,
 )]
 pub struct NonPaginatedCollection<T> {
     pub data: Vec<T>,
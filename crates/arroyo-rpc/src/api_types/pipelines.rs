@@ -1,6 +1,7 @@
 use crate::api_types::udfs::Udf;
 use crate::grpc as grpc_proto;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use utoipa::ToSchema;
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -25,6 +26,10 @@ pub struct PipelinePost {
     pub udfs: Option<Vec<Udf>>,
     pub preview: Option<bool>,
     pub parallelism: u64,
+    pub savepoint_id: Option<String>,
+    pub allow_dropped_state: Option<bool>,
+    pub operator_parallelism: Option<HashMap<String, u64>>,
+    pub restart_strategy: Option<RestartStrategy>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -33,6 +38,68 @@ pub struct PipelinePatch {
     pub parallelism: Option<u64>,
     pub checkpoint_interval_micros: Option<u64>,
     pub stop: Option<StopType>,
+    pub autoscaling: Option<AutoscalingConfig>,
+    pub operator_parallelism: Option<HashMap<String, u64>>,
+    pub restart_strategy: Option<RestartStrategy>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoscalingConfig {
+    pub enabled: bool,
+    pub min_parallelism: u32,
+    pub max_parallelism: u32,
+    pub cooldown_secs: u64,
+}
+
+/// How a pipeline is restarted after it fails
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum RestartStrategy {
+    FixedDelay(FixedDelayRestart),
+    ExponentialBackoff(ExponentialBackoffRestart),
+    FailureRate(FailureRateRestart),
+    Never,
+}
+
+impl Default for RestartStrategy {
+    fn default() -> Self {
+        RestartStrategy::FixedDelay(FixedDelayRestart {
+            delay_millis: 0,
+            max_restarts: 10,
+        })
+    }
+}
+
+/// Waits a fixed delay before each restart, and fails the pipeline after `maxRestarts`
+/// consecutive restarts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FixedDelayRestart {
+    pub delay_millis: u64,
+    pub max_restarts: u32,
+}
+
+/// Multiplies the delay by `multiplier` after each consecutive restart, up to `maxDelayMillis`;
+/// each delay is randomly adjusted by up to `jitter` (a fraction between 0 and 1) of itself
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExponentialBackoffRestart {
+    pub initial_delay_millis: u64,
+    pub max_delay_millis: u64,
+    pub multiplier: f64,
+    pub jitter: f64,
+    pub max_restarts: Option<u32>,
+}
+
+/// Waits a fixed delay before each restart, and fails the pipeline if it fails more than
+/// `maxFailures` times within `windowSecs`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureRateRestart {
+    pub max_failures: u32,
+    pub window_secs: u64,
+    pub delay_millis: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -56,6 +123,8 @@ pub struct Pipeline {
     pub action_in_progress: bool,
     pub graph: PipelineGraph,
     pub preview: bool,
+    pub autoscaling: Option<AutoscalingConfig>,
+    pub restart_strategy: Option<RestartStrategy>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -106,6 +175,7 @@ pub struct Job {
     pub tasks: Option<u64>,
     pub failure_message: Option<String>,
     pub created_at: u64,
+    pub next_retry_time: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretPost {
+    /// the name used to reference the secret in connection configs, as `{{ secret:name }}`
+    pub name: String,
+    pub value: String,
+}
+
+/// A stored secret; its value is never returned by the API
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Secret {
+    pub id: String,
+    pub name: String,
+    pub created_by: String,
+    pub created_at: u64,
+}
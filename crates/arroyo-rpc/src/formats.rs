@@ -1,7 +1,7 @@
 use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 use std::sync::OnceLock;
@@ -30,6 +30,28 @@ impl TryFrom<&str> for TimestampFormat {
     }
 }
 
+#[derive(
+    Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default, Hash, PartialOrd, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DecimalEncoding {
+    #[default]
+    Number,
+    String,
+}
+
+impl TryFrom<&str> for DecimalEncoding {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "number" => Ok(DecimalEncoding::Number),
+            "string" => Ok(DecimalEncoding::String),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(
     Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default, Hash, PartialOrd, ToSchema,
 )]
@@ -52,6 +74,9 @@ pub struct JsonFormat {
 
     #[serde(default)]
     pub timestamp_format: TimestampFormat,
+
+    #[serde(default)]
+    pub decimal_encoding: DecimalEncoding,
 }
 
 impl JsonFormat {
@@ -88,6 +113,13 @@ impl JsonFormat {
                 }
             });
 
+        let decimal_encoding: DecimalEncoding = opts
+            .remove("json.decimal_encoding")
+            .map(|t| t.as_str().try_into())
+            .transpose()
+            .map_err(|_| "json.decimal_encoding".to_string())?
+            .unwrap_or_default();
+
         Ok(Self {
             confluent_schema_registry,
             schema_id: None,
@@ -95,6 +127,7 @@ impl JsonFormat {
             debezium,
             unstructured,
             timestamp_format,
+            decimal_encoding,
         })
     }
 }
@@ -222,15 +255,102 @@ impl AvroFormat {
     }
 }
 
+#[derive(
+    Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default, Hash, PartialOrd, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ParquetCompression {
+    #[default]
+    Uncompressed,
+    Snappy,
+    Gzip,
+    Zstd,
+    Lz4,
+}
+
+impl TryFrom<&str> for ParquetCompression {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "none" | "uncompressed" => Ok(ParquetCompression::Uncompressed),
+            "snappy" => Ok(ParquetCompression::Snappy),
+            "gzip" => Ok(ParquetCompression::Gzip),
+            "zstd" => Ok(ParquetCompression::Zstd),
+            "lz4" => Ok(ParquetCompression::Lz4),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ParquetFormat {
+    /// Compression used when serializing batches into Parquet files; the filesystem sink
+    /// is configured separately with `parquet_compression`
+    #[serde(default)]
+    pub compression: ParquetCompression,
+}
+
+impl ParquetFormat {
+    pub fn from_opts(opts: &mut HashMap<String, String>) -> Result<Self, String> {
+        let compression = opts
+            .remove("parquet.compression")
+            .map(|t| t.as_str().try_into())
+            .transpose()
+            .map_err(|_| "parquet.compression".to_string())?
+            .unwrap_or_default();
+
+        Ok(Self { compression })
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct ParquetFormat {}
+pub struct ProtobufFormat {
+    #[serde(default)]
+    pub into_unstructured_json: bool,
+
+    #[serde(default)]
+    pub message_name: Option<String>,
+
+    #[serde(default)]
+    pub confluent_schema_registry: bool,
+
+    #[serde(default)]
+    #[schema(read_only)]
+    pub schema_id: Option<u32>,
+
+    /// The encoded FileDescriptorSet for the schema, computed when the schema is expanded
+    #[serde(default)]
+    #[schema(read_only, value_type = Vec<u8>)]
+    pub compiled_schema: Option<Vec<u8>>,
+}
+
+impl ProtobufFormat {
+    pub fn from_opts(opts: &mut HashMap<String, String>) -> Result<Self, String> {
+        Ok(Self {
+            into_unstructured_json: opts
+                .remove("protobuf.into_unstructured_json")
+                .filter(|t| t == "true")
+                .is_some(),
+            message_name: opts.remove("protobuf.message_name"),
+            confluent_schema_registry: opts
+                .remove("protobuf.confluent_schema_registry")
+                .filter(|t| t == "true")
+                .is_some(),
+            schema_id: None,
+            compiled_schema: None,
+        })
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Format {
     Json(JsonFormat),
     Avro(AvroFormat),
+    Protobuf(ProtobufFormat),
     Parquet(ParquetFormat),
     RawString(RawStringFormat),
 }
@@ -244,10 +364,10 @@ impl Format {
         Ok(Some(match name.as_str() {
             "json" => Format::Json(JsonFormat::from_opts(false, opts)?),
             "debezium_json" => Format::Json(JsonFormat::from_opts(true, opts)?),
-            "protobuf" => return Err("protobuf is not yet supported".to_string()),
+            "protobuf" => Format::Protobuf(ProtobufFormat::from_opts(opts)?),
             "avro" => Format::Avro(AvroFormat::from_opts(opts)?),
             "raw_string" => Format::RawString(RawStringFormat {}),
-            "parquet" => Format::Parquet(ParquetFormat {}),
+            "parquet" => Format::Parquet(ParquetFormat::from_opts(opts)?),
             f => return Err(format!("Unknown format '{}'", f)),
         }))
     }
@@ -255,7 +375,11 @@ impl Format {
     pub fn is_updating(&self) -> bool {
         match self {
             Format::Json(JsonFormat { debezium: true, .. }) => true,
-            Format::Json(_) | Format::Avro(_) | Format::Parquet(_) | Format::RawString(_) => false,
+            Format::Json(_)
+            | Format::Avro(_)
+            | Format::Protobuf(_)
+            | Format::Parquet(_)
+            | Format::RawString(_) => false,
         }
     }
 }
@@ -265,6 +389,64 @@ impl Format {
 pub enum BadData {
     Fail {},
     Drop {},
+    DeadLetter { target: DeadLetterTarget },
+}
+
+/// Where messages that fail to deserialize are written with `bad_data = 'dead_letter'`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterTarget {
+    Kafka {
+        bootstrap_servers: String,
+        topic: String,
+        /// additional librdkafka configs for the producer, for example to set up SASL or TLS;
+        /// values may reference environment variables like `{{ KAFKA_PASSWORD }}` or secrets
+        #[serde(default)]
+        client_configs: BTreeMap<String, String>,
+    },
+    Filesystem {
+        path: String,
+    },
+}
+
+impl DeadLetterTarget {
+    fn from_opts(opts: &mut HashMap<String, String>) -> Result<Self, String> {
+        let path = opts.remove("bad_data.dead_letter.path");
+        let topic = opts.remove("bad_data.dead_letter.topic");
+
+        match (path, topic) {
+            (Some(path), None) => Ok(DeadLetterTarget::Filesystem { path }),
+            (None, Some(topic)) => {
+                let bootstrap_servers = opts
+                    .remove("bad_data.dead_letter.bootstrap_servers")
+                    .ok_or_else(|| {
+                        "'bad_data.dead_letter.bootstrap_servers' must be set when writing dead letters to Kafka".to_string()
+                    })?;
+
+                let client_configs = opts
+                    .remove("bad_data.dead_letter.client_configs")
+                    .map(|c| {
+                        arroyo_types::string_to_map(&c, '=').ok_or_else(|| {
+                            "invalid 'bad_data.dead_letter.client_configs': expected comma and equals-separated pairs".to_string()
+                        })
+                    })
+                    .transpose()?
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect();
+
+                Ok(DeadLetterTarget::Kafka {
+                    bootstrap_servers,
+                    topic,
+                    client_configs,
+                })
+            }
+            _ => Err(
+                "exactly one of 'bad_data.dead_letter.path' or 'bad_data.dead_letter.topic' must be set"
+                    .to_string(),
+            ),
+        }
+    }
 }
 
 impl Default for BadData {
@@ -282,6 +464,9 @@ impl BadData {
         let method = match method.as_str() {
             "drop" => BadData::Drop {},
             "fail" => BadData::Fail {},
+            "dead_letter" => BadData::DeadLetter {
+                target: DeadLetterTarget::from_opts(opts)?,
+            },
             f => return Err(format!("Unknown invalid data behavior '{}'", f)),
         };
 
@@ -2,6 +2,7 @@ pub mod api_types;
 pub mod formats;
 pub mod public_ids;
 pub mod schema_resolver;
+pub mod secrets;
 pub mod var_str;
 
 use std::collections::HashMap;
@@ -14,7 +15,7 @@ use crate::grpc::{LoadCompactedDataReq, SubtaskCheckpointMetadata};
 use anyhow::Result;
 use arrow::row::{OwnedRow, RowConverter, SortField};
 use arrow_array::{Array, ArrayRef, BooleanArray};
-use arrow_schema::DataType;
+use arrow_schema::{DataType, Field, Fields};
 use arroyo_types::{CheckpointBarrier, HASH_SEEDS};
 use grpc::{StopMode, TableCheckpointMetadata, TaskCheckpointEventType};
 use serde::{Deserialize, Serialize};
@@ -173,6 +174,9 @@ pub struct OperatorConfig {
     pub bad_data: Option<BadData>,
     pub framing: Option<Framing>,
     pub rate_limit: Option<RateLimit>,
+    /// Limits the rate at which a source reads messages across all of its subtasks; set from
+    /// the organization's quotas when the pipeline is created
+    pub quota: Option<RateLimit>,
 }
 
 impl Default for OperatorConfig {
@@ -184,6 +188,7 @@ impl Default for OperatorConfig {
             bad_data: None,
             framing: None,
             rate_limit: None,
+            quota: None,
         }
     }
 }
@@ -197,6 +202,28 @@ pub fn error_chain(e: anyhow::Error) -> String {
 }
 
 pub const TIMESTAMP_FIELD: &str = "_timestamp";
+
+/// Column added to the output of operators that produce updating (changelog) data, like
+/// non-windowed aggregates. It's a struct of `is_retract`, which is true for rows that retract a
+/// previously emitted row, and `id`, which identifies the key the row was produced for; an update
+/// is represented as a retraction followed immediately by an append with the same id.
+pub const UPDATING_META_FIELD: &str = "_updating_meta";
+
+pub fn updating_meta_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("is_retract", DataType::Boolean, false),
+        Field::new("id", DataType::UInt64, false),
+    ])
+}
+
+pub fn updating_meta_field() -> Arc<Field> {
+    Arc::new(Field::new(
+        UPDATING_META_FIELD,
+        DataType::Struct(updating_meta_fields()),
+        false,
+    ))
+}
+
 // need to handle the empty case as a row converter without sort fields emits empty Rows.
 #[derive(Debug)]
 pub enum Converter {
@@ -22,6 +22,8 @@ pub enum IdTypes {
     ConnectionTable,
     ConnectionTablePipeline,
     Udf,
+    Savepoint,
+    Secret,
 }
 
 pub fn generate_id(id_type: IdTypes) -> String {
@@ -38,6 +40,8 @@ pub fn generate_id(id_type: IdTypes) -> String {
         IdTypes::ConnectionTable => "ct",
         IdTypes::ConnectionTablePipeline => "ctp",
         IdTypes::Udf => "udf",
+        IdTypes::Savepoint => "sp",
+        IdTypes::Secret => "sec",
     };
     let id = nanoid!(ID_LENGTH, &ALPHABET);
     format!("{}_{}", prefix, id)
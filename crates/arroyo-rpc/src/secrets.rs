@@ -0,0 +1,141 @@
+use crate::var_str::placeholder_regex;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail};
+use arroyo_types::SECRETS_KEY_ENV;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+// encrypted secrets are stored as a 96-bit nonce followed by the AES-256-GCM ciphertext
+const NONCE_LEN: usize = 12;
+
+// Encrypted secrets that have been sent to this process for use in operator configs. This is only
+// set in workers, which keeps secrets from being resolved anywhere else.
+static SECRETS: RwLock<Option<HashMap<String, Vec<u8>>>> = RwLock::new(None);
+
+fn cipher() -> anyhow::Result<Aes256Gcm> {
+    let key = std::env::var(SECRETS_KEY_ENV)
+        .map_err(|_| anyhow!("{} must be set to use secrets", SECRETS_KEY_ENV))?;
+
+    let key = STANDARD
+        .decode(key.trim())
+        .map_err(|e| anyhow!("{} is not valid base64: {}", SECRETS_KEY_ENV, e))?;
+
+    if key.len() != 32 {
+        bail!(
+            "{} must be a 256-bit key, but it is {} bits",
+            SECRETS_KEY_ENV,
+            key.len() * 8
+        );
+    }
+
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+/// Secret names may only contain letters, numbers, and underscores, so that they can be
+/// referenced as `{{ secret:name }}`
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Encrypts a secret with the master key. The name is authenticated along with the value, so an
+/// encrypted secret can't be moved to a different name.
+pub fn encrypt(name: &str, value: &str) -> anyhow::Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher()?
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: value.as_bytes(),
+                aad: name.as_bytes(),
+            },
+        )
+        .map_err(|_| anyhow!("failed to encrypt secret '{}'", name))?;
+
+    let mut encrypted = nonce.to_vec();
+    encrypted.extend(ciphertext);
+    Ok(encrypted)
+}
+
+pub fn decrypt(name: &str, encrypted: &[u8]) -> anyhow::Result<String> {
+    if encrypted.len() < NONCE_LEN {
+        bail!("encrypted secret '{}' is invalid", name);
+    }
+
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_LEN);
+    let value = cipher()?
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: name.as_bytes(),
+            },
+        )
+        .map_err(|_| {
+            anyhow!(
+                "failed to decrypt secret '{}'; was it encrypted with a different {}?",
+                name,
+                SECRETS_KEY_ENV
+            )
+        })?;
+
+    String::from_utf8(value).map_err(|_| anyhow!("secret '{}' is not valid UTF-8", name))
+}
+
+/// Returns the names of all secrets referenced as `{{ secret:name }}` in the text
+pub fn references(text: &str) -> HashSet<String> {
+    placeholder_regex()
+        .captures_iter(text)
+        .filter(|caps| caps.get(1).is_some())
+        .map(|caps| caps.get(2).unwrap().as_str().to_string())
+        .collect()
+}
+
+/// Makes encrypted secrets available to the operators constructed in this process; they are only
+/// decrypted when they are resolved
+pub fn install(secrets: HashMap<String, Vec<u8>>) {
+    SECRETS
+        .write()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .extend(secrets);
+}
+
+/// Resolves a secret by name, or returns None if secrets have not been installed in this process
+pub(crate) fn resolve(name: &str) -> Option<anyhow::Result<String>> {
+    let secrets = SECRETS.read().unwrap();
+    let secrets = secrets.as_ref()?;
+
+    Some(match secrets.get(name) {
+        Some(encrypted) => decrypt(name, encrypted),
+        None => Err(anyhow!("secret '{}' not found", name)),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn set_test_key() {
+        std::env::set_var(SECRETS_KEY_ENV, STANDARD.encode([7u8; 32]));
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        set_test_key();
+        let encrypted = encrypt("kafka_password", "hunter2").unwrap();
+        assert!(!encrypted.windows(7).any(|w| w == b"hunter2"));
+        assert_eq!(decrypt("kafka_password", &encrypted).unwrap(), "hunter2");
+
+        // the ciphertext is bound to the secret's name
+        assert!(decrypt("other_password", &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_references() {
+        let refs = references("user={{ USER }} password={{ secret:pw }} token={{secret:token}}");
+        assert_eq!(refs, HashSet::from(["pw".to_string(), "token".to_string()]));
+    }
+}
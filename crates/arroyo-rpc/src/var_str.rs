@@ -1,3 +1,4 @@
+use crate::secrets;
 use anyhow::bail;
 use regex::Regex;
 use serde::de::Visitor;
@@ -52,17 +53,31 @@ impl VarStr {
         VarStr { raw_val }
     }
 
-    pub fn sub_env_vars(&self) -> anyhow::Result<String> {
-        // Regex to match patterns like {{ VAR_NAME }}
-        static RE: OnceLock<Regex> = OnceLock::new();
-        let re = RE.get_or_init(|| Regex::new(r"\{\{\s*(\w+)\s*}}").unwrap());
+    /// Whether the value consists only of placeholders, and so contains no literal credentials
+    pub fn only_placeholders(&self) -> bool {
+        placeholder_regex()
+            .replace_all(&self.raw_val, "")
+            .trim()
+            .is_empty()
+    }
 
+    /// Substitutes `{{ VAR_NAME }}` placeholders with environment variables and, in workers,
+    /// `{{ secret:name }}` placeholders with the decrypted secret. Secrets are never resolved
+    /// outside of workers; there, secret placeholders are left as-is.
+    pub fn sub_env_vars(&self) -> anyhow::Result<String> {
         let mut result = self.raw_val.to_string();
 
-        for caps in re.captures_iter(&self.raw_val) {
-            let var_name = caps.get(1).unwrap().as_str();
+        for caps in placeholder_regex().captures_iter(&self.raw_val) {
+            let var_name = caps.get(2).unwrap().as_str();
             let full_match = caps.get(0).unwrap().as_str();
 
+            if caps.get(1).is_some() {
+                if let Some(value) = secrets::resolve(var_name) {
+                    result = result.replace(full_match, &value?);
+                }
+                continue;
+            }
+
             match env::var(var_name) {
                 Ok(value) => {
                     result = result.replace(full_match, &value);
@@ -75,9 +90,16 @@ impl VarStr {
     }
 }
 
+/// Matches placeholders like {{ VAR_NAME }} and {{ secret:name }}
+pub(crate) fn placeholder_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{\{\s*(secret:\s*)?(\w+)\s*}}").unwrap())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_no_placeholders() {
@@ -110,4 +132,24 @@ mod tests {
             expected
         );
     }
+
+    #[test]
+    fn test_secret_placeholders() {
+        crate::secrets::tests::set_test_key();
+        secrets::install(HashMap::from([(
+            "sasl_password".to_string(),
+            secrets::encrypt("sasl_password", "hunter2").unwrap(),
+        )]));
+
+        assert_eq!(
+            VarStr::new("password={{ secret:sasl_password }}".to_string())
+                .sub_env_vars()
+                .unwrap(),
+            "password=hunter2"
+        );
+
+        assert!(VarStr::new("{{ secret:missing }}".to_string())
+            .sub_env_vars()
+            .is_err());
+    }
 }
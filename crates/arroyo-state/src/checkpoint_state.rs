@@ -288,6 +288,7 @@ impl CheckpointState {
                 finish_time: to_micros(operator_state.finish_time.unwrap()),
                 table_checkpoint_metadata,
                 table_configs,
+                allow_dropped_tables: false,
                 operator_metadata: Some(OperatorMetadata {
                     job_id: self.job_id.to_string(),
                     operator_id: c.operator_id,
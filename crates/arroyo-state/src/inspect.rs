@@ -0,0 +1,675 @@
+use crate::parquet::get_storage_provider;
+use crate::tables::global_keyed_map::GLOBAL_KEY_VALUE_SCHEMA;
+use crate::{BackingStore, StateBackend};
+use anyhow::{anyhow, bail, Context, Result};
+use arrow::compute::{cast, filter_record_batch};
+use arrow::ipc::writer::StreamWriter;
+use arrow::json::LineDelimitedWriter;
+use arrow_array::{
+    cast::AsArray, types::TimestampNanosecondType, Array, ArrayRef, BooleanArray, PrimitiveArray,
+    RecordBatch, StringArray,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use arroyo_rpc::api_types::checkpoints::{
+    CheckpointStateSummary, OperatorState, StateDumpFormat, StateFile, SubtaskTableState,
+    TableState,
+};
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::grpc::{
+    ExpiringKeyedTimeTableCheckpointMetadata, ExpiringKeyedTimeTableConfig,
+    GlobalKeyedTableTaskCheckpointMetadata, OperatorCheckpointMetadata, TableEnum,
+};
+use arroyo_storage::StorageProvider;
+use arroyo_types::to_nanos;
+use bincode::config;
+use futures::StreamExt;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{async_reader::ParquetObjectReader, ParquetRecordBatchStreamBuilder};
+use prost::Message;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Restricts the rows returned when reading a table from a checkpoint
+#[derive(Debug, Clone, Default)]
+pub struct StateFilter {
+    /// For global tables, matches string keys or the hex encoding of the key's bytes; for
+    /// expiring time-key tables, matches the key columns cast to strings and joined by commas
+    pub key: Option<String>,
+    /// Inclusive lower bound on row timestamps; only supported for expiring time-key tables
+    pub start_time: Option<SystemTime>,
+    /// Exclusive upper bound on row timestamps; only supported for expiring time-key tables
+    pub end_time: Option<SystemTime>,
+}
+
+fn table_files(table_type: TableEnum, data: &[u8]) -> Result<Vec<String>> {
+    Ok(match table_type {
+        TableEnum::MissingTableType => bail!("missing table type"),
+        TableEnum::GlobalKeyValue => GlobalKeyedTableTaskCheckpointMetadata::decode(data)?.files,
+        TableEnum::ExpiringKeyedTimeTable => {
+            ExpiringKeyedTimeTableCheckpointMetadata::decode(data)?
+                .files
+                .into_iter()
+                .map(|file| file.file)
+                .collect()
+        }
+    })
+}
+
+// table files are named `table-{name}-{subtask_index:03}`, with a `-compacted` suffix if they
+// were produced by compaction
+fn subtask_for_file(path: &str) -> Option<u32> {
+    path.trim_end_matches("-compacted")
+        .rsplit('-')
+        .next()?
+        .parse()
+        .ok()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+async fn load_operator(
+    job_id: &str,
+    epoch: u32,
+    operator_id: &str,
+) -> Result<OperatorCheckpointMetadata> {
+    StateBackend::load_operator_metadata(job_id, operator_id, epoch)
+        .await?
+        .ok_or_else(|| {
+            anyhow!(
+                "no metadata for operator {} in checkpoint {} of job {}",
+                operator_id,
+                epoch,
+                job_id
+            )
+        })
+}
+
+async fn describe_file(storage: &StorageProvider, path: String) -> Result<StateFile> {
+    let object_meta = storage
+        .get_backing_store()
+        .head(&(path.clone().into()))
+        .await?;
+    let bytes = object_meta.size as u64;
+    let object_reader = ParquetObjectReader::new(storage.get_backing_store(), object_meta);
+    let rows = ParquetRecordBatchStreamBuilder::new(object_reader)
+        .await?
+        .metadata()
+        .file_metadata()
+        .num_rows() as u64;
+
+    Ok(StateFile { path, bytes, rows })
+}
+
+/// Lists the operators and tables in a checkpoint, along with the files that make up each table
+/// and their sizes and row counts
+pub async fn describe_checkpoint(job_id: &str, epoch: u32) -> Result<CheckpointStateSummary> {
+    let storage = get_storage_provider().await?;
+    let metadata = StateBackend::load_checkpoint_metadata(job_id, epoch)
+        .await
+        .with_context(|| format!("failed to load checkpoint {} of job {}", epoch, job_id))?;
+
+    let mut operators = vec![];
+    for operator_id in &metadata.operator_ids {
+        let operator_metadata = load_operator(job_id, epoch, operator_id).await?;
+
+        let mut tables = vec![];
+        for (name, table_metadata) in operator_metadata.table_checkpoint_metadata {
+            let mut subtasks: BTreeMap<Option<u32>, SubtaskTableState> = BTreeMap::new();
+            for file in table_files(table_metadata.table_type(), &table_metadata.data)? {
+                let index = subtask_for_file(&file);
+                let file = describe_file(&storage, file).await?;
+                let subtask = subtasks.entry(index).or_insert_with(|| SubtaskTableState {
+                    index,
+                    bytes: 0,
+                    rows: 0,
+                    files: vec![],
+                });
+                subtask.bytes += file.bytes;
+                subtask.rows += file.rows;
+                subtask.files.push(file);
+            }
+
+            tables.push(TableState {
+                name,
+                table_type: table_metadata.table_type().as_str_name().to_string(),
+                bytes: subtasks.values().map(|s| s.bytes).sum(),
+                rows: subtasks.values().map(|s| s.rows).sum(),
+                subtasks: subtasks.into_values().collect(),
+            });
+        }
+        tables.sort_by(|a, b| a.name.cmp(&b.name));
+
+        operators.push(OperatorState {
+            operator_id: operator_id.clone(),
+            tables,
+        });
+    }
+
+    Ok(CheckpointStateSummary {
+        job_id: job_id.to_string(),
+        epoch,
+        min_epoch: metadata.min_epoch,
+        operators,
+    })
+}
+
+/// Reads the rows of a table in a checkpoint that match the filter
+pub async fn read_table(
+    job_id: &str,
+    epoch: u32,
+    operator_id: &str,
+    table: &str,
+    filter: &StateFilter,
+) -> Result<(SchemaRef, Vec<RecordBatch>)> {
+    let storage = get_storage_provider().await?;
+    let operator_metadata = load_operator(job_id, epoch, operator_id).await?;
+
+    let table_metadata = operator_metadata
+        .table_checkpoint_metadata
+        .get(table)
+        .ok_or_else(|| {
+            anyhow!(
+                "operator {} has no table {} in checkpoint {}",
+                operator_id,
+                table,
+                epoch
+            )
+        })?;
+    let files = table_files(table_metadata.table_type(), &table_metadata.data)?;
+
+    match table_metadata.table_type() {
+        TableEnum::MissingTableType => bail!("missing table type for table {}", table),
+        TableEnum::GlobalKeyValue => read_global_table(&storage, files, filter).await,
+        TableEnum::ExpiringKeyedTimeTable => {
+            let config = operator_metadata
+                .table_configs
+                .get(table)
+                .ok_or_else(|| anyhow!("missing config for table {}", table))?;
+            let config = ExpiringKeyedTimeTableConfig::decode(&config.config[..])?;
+            let schema: ArroyoSchema = config
+                .schema
+                .ok_or_else(|| anyhow!("missing schema for table {}", table))?
+                .try_into()?;
+            read_expiring_table(&storage, files, schema, filter).await
+        }
+    }
+}
+
+// keys of global tables are bincode-encoded, so string keys are decoded for comparison and any
+// other key can be matched by the hex encoding of its bytes
+fn global_key_matches(key: &[u8], filter: &str) -> bool {
+    if let Ok((decoded, _)) = bincode::decode_from_slice::<String, _>(key, config::standard()) {
+        if decoded == filter {
+            return true;
+        }
+    }
+
+    hex(key) == filter
+}
+
+async fn read_global_table(
+    storage: &StorageProvider,
+    files: Vec<String>,
+    filter: &StateFilter,
+) -> Result<(SchemaRef, Vec<RecordBatch>)> {
+    if filter.start_time.is_some() || filter.end_time.is_some() {
+        bail!("time filters can only be applied to expiring time-key tables");
+    }
+
+    let mut batches = vec![];
+    for file in files {
+        let contents = storage.get(&file).await?;
+        for batch in ParquetRecordBatchReaderBuilder::try_new(contents)?.build()? {
+            let mut batch = batch?;
+            if let Some(key) = &filter.key {
+                let keys = batch
+                    .column_by_name("key")
+                    .and_then(|c| c.as_binary_opt::<i32>())
+                    .ok_or_else(|| anyhow!("missing key column"))?;
+                let mask: BooleanArray = keys
+                    .iter()
+                    .map(|k| Some(k.map(|k| global_key_matches(k, key)).unwrap_or(false)))
+                    .collect();
+                batch = filter_record_batch(&batch, &mask)?;
+            }
+            if batch.num_rows() > 0 {
+                batches.push(batch);
+            }
+        }
+    }
+
+    Ok((GLOBAL_KEY_VALUE_SCHEMA.clone(), batches))
+}
+
+fn expiring_table_mask(
+    batch: &RecordBatch,
+    schema: &ArroyoSchema,
+    filter: &StateFilter,
+) -> Result<BooleanArray> {
+    let timestamps: &PrimitiveArray<TimestampNanosecondType> = batch
+        .column(schema.timestamp_index)
+        .as_primitive_opt()
+        .ok_or_else(|| anyhow!("failed to find timestamp column"))?;
+    let start = filter.start_time.map(|t| to_nanos(t) as i64);
+    let end = filter.end_time.map(|t| to_nanos(t) as i64);
+
+    let key_columns = match &filter.key {
+        Some(_) => schema
+            .key_indices
+            .as_ref()
+            .filter(|indices| !indices.is_empty())
+            .ok_or_else(|| anyhow!("table has no key columns to filter on"))?
+            .iter()
+            .map(|i| Ok(cast(batch.column(*i), &DataType::Utf8)?))
+            .collect::<Result<Vec<_>>>()?,
+        None => vec![],
+    };
+    let key_columns: Vec<&StringArray> = key_columns.iter().map(|c| c.as_string()).collect();
+
+    Ok((0..batch.num_rows())
+        .map(|row| {
+            let timestamp = timestamps.value(row);
+            let in_range = start.map(|s| timestamp >= s).unwrap_or(true)
+                && end.map(|e| timestamp < e).unwrap_or(true);
+
+            let key_matches = filter
+                .key
+                .as_ref()
+                .map(|key| {
+                    let row_key: Vec<_> = key_columns
+                        .iter()
+                        .map(|c| if c.is_null(row) { "" } else { c.value(row) })
+                        .collect();
+                    row_key.join(",") == *key
+                })
+                .unwrap_or(true);
+
+            Some(in_range && key_matches)
+        })
+        .collect())
+}
+
+async fn read_expiring_table(
+    storage: &StorageProvider,
+    files: Vec<String>,
+    schema: ArroyoSchema,
+    filter: &StateFilter,
+) -> Result<(SchemaRef, Vec<RecordBatch>)> {
+    // drop the _key_hash and _operation columns that are added to the stored data
+    let projection: Vec<_> = (0..schema.schema.fields().len()).collect();
+
+    let mut batches = vec![];
+    for file in files {
+        let object_meta = storage.get_backing_store().head(&(file.into())).await?;
+        let object_reader = ParquetObjectReader::new(storage.get_backing_store(), object_meta);
+        let mut stream = ParquetRecordBatchStreamBuilder::new(object_reader)
+            .await?
+            .build()?;
+
+        while let Some(batch) = stream.next().await {
+            let batch = batch?.project(&projection)?;
+            let mask = expiring_table_mask(&batch, &schema, filter)?;
+            let batch = filter_record_batch(&batch, &mask)?;
+            if batch.num_rows() > 0 {
+                batches.push(batch);
+            }
+        }
+    }
+
+    Ok((schema.schema.clone(), batches))
+}
+
+fn hex_encode_binary(batch: &RecordBatch) -> Result<RecordBatch> {
+    let (fields, columns): (Vec<_>, Vec<_>) = batch
+        .schema()
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| match column.as_binary_opt::<i32>() {
+            Some(binary) => {
+                let encoded: StringArray = binary.iter().map(|v| v.map(hex)).collect();
+                (
+                    Arc::new(Field::new(
+                        field.name(),
+                        DataType::Utf8,
+                        field.is_nullable(),
+                    )),
+                    Arc::new(encoded) as ArrayRef,
+                )
+            }
+            None => (field.clone(), column.clone()),
+        })
+        .unzip();
+
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+/// Encodes rows read from a table, either as newline-delimited JSON (with binary columns, like
+/// the keys and values of global tables, hex-encoded) or as an Arrow IPC stream
+pub fn encode_batches(
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+    format: StateDumpFormat,
+) -> Result<Vec<u8>> {
+    match format {
+        StateDumpFormat::Json => {
+            let mut writer = LineDelimitedWriter::new(vec![]);
+            for batch in batches {
+                writer.write(&hex_encode_binary(batch)?)?;
+            }
+            writer.finish()?;
+            Ok(writer.into_inner())
+        }
+        StateDumpFormat::Arrow => {
+            let mut writer = StreamWriter::try_new(vec![], &schema)?;
+            for batch in batches {
+                writer.write(batch)?;
+            }
+            writer.finish()?;
+            Ok(writer.into_inner()?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parquet::tests::{
+        checkpointing_table_manager, finish_checkpoint, test_job_id, write_checkpoint,
+    };
+    use crate::tables::global_keyed_map::GlobalKeyedView;
+    use crate::{global_table_config, timestamp_table_config};
+    use arrow::ipc::reader::StreamReader;
+    use arrow_array::{Int64Array, TimestampNanosecondArray};
+    use arrow_schema::TimeUnit;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn time(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn events_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("user", DataType::Utf8, false),
+            Field::new("count", DataType::Int64, false),
+            Field::new(
+                "_timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]))
+    }
+
+    fn events(rows: &[(&str, i64, u64)]) -> RecordBatch {
+        RecordBatch::try_new(
+            events_schema(),
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.1))),
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    rows.iter().map(|r| to_nanos(time(r.2)) as i64),
+                )),
+            ],
+        )
+        .unwrap()
+    }
+
+    /// Writes a checkpoint for operator `op` with a global table `k` and an expiring time-key
+    /// table `e`, keyed by user, holding `rows`
+    async fn write_tables(job_id: &str, values: &[(&str, u64)], rows: &[(&str, i64, u64)]) {
+        let mut tables = global_table_config("k", "global table");
+        tables.insert(
+            "e".to_string(),
+            timestamp_table_config(
+                "e",
+                "expiring table",
+                Duration::from_secs(3600),
+                ArroyoSchema::new_keyed(events_schema(), 2, vec![0]),
+            ),
+        );
+        let (mut manager, mut rx) = checkpointing_table_manager(job_id, "op", tables).await;
+
+        let view: &mut GlobalKeyedView<String, u64> =
+            manager.get_global_keyed_state("k").await.unwrap();
+        for (key, value) in values {
+            view.insert(key.to_string(), *value).await;
+        }
+
+        let view = manager
+            .get_expiring_time_key_table("e", None)
+            .await
+            .unwrap();
+        view.insert(time(rows.iter().map(|r| r.2).max().unwrap()), events(rows));
+        view.flush(None).await.unwrap();
+
+        finish_checkpoint(job_id, "op", &mut manager, &mut rx).await;
+    }
+
+    fn filter(key: Option<&str>, start: Option<u64>, end: Option<u64>) -> StateFilter {
+        StateFilter {
+            key: key.map(|k| k.to_string()),
+            start_time: start.map(time),
+            end_time: end.map(time),
+        }
+    }
+
+    async fn read_events(job_id: &str, filter: &StateFilter) -> Vec<(String, i64)> {
+        let (schema, batches) = read_table(job_id, 1, "op", "e", filter).await.unwrap();
+        assert_eq!(schema, events_schema());
+
+        batches
+            .iter()
+            .flat_map(|batch| {
+                let users = batch.column(0).as_string::<i32>();
+                let counts = batch
+                    .column(1)
+                    .as_primitive::<arrow_array::types::Int64Type>();
+                (0..batch.num_rows())
+                    .map(|i| (users.value(i).to_string(), counts.value(i)))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn bincode_hex<T: bincode::Encode>(value: T) -> String {
+        hex(&bincode::encode_to_vec(value, config::standard()).unwrap())
+    }
+
+    #[test]
+    fn test_subtask_for_file() {
+        assert_eq!(
+            subtask_for_file("job/checkpoints/checkpoint-0000001/operator-op/table-k-002"),
+            Some(2)
+        );
+        assert_eq!(
+            subtask_for_file(
+                "job/checkpoints/checkpoint-0000003/operator-op/table-k-010-compacted"
+            ),
+            Some(10)
+        );
+        assert_eq!(subtask_for_file("some-other-file"), None);
+    }
+
+    #[tokio::test]
+    async fn test_describe_checkpoint() {
+        let job_id = test_job_id("inspect-describe");
+        write_tables(
+            &job_id,
+            &[("a", 1), ("b", 2), ("c", 3)],
+            &[("alice", 1, 1), ("bob", 2, 2)],
+        )
+        .await;
+
+        let summary = describe_checkpoint(&job_id, 1).await.unwrap();
+        assert_eq!(summary.job_id, job_id);
+        assert_eq!((summary.epoch, summary.min_epoch), (1, 1));
+        assert_eq!(summary.operators.len(), 1);
+
+        let operator = &summary.operators[0];
+        assert_eq!(operator.operator_id, "op");
+
+        let tables: Vec<_> = operator
+            .tables
+            .iter()
+            .map(|t| (t.name.as_str(), t.table_type.as_str(), t.rows))
+            .collect();
+        assert_eq!(
+            tables,
+            vec![
+                ("e", "ExpiringKeyedTimeTable", 2),
+                ("k", "GlobalKeyValue", 3)
+            ]
+        );
+
+        for table in &operator.tables {
+            assert_eq!(table.subtasks.len(), 1);
+            let subtask = &table.subtasks[0];
+            assert_eq!(subtask.index, Some(0));
+            assert_eq!(subtask.rows, table.rows);
+            assert_eq!(subtask.files.len(), 1);
+            assert!(table.bytes > 0);
+            assert_eq!(subtask.bytes, table.bytes);
+            assert_eq!(subtask.files[0].bytes, table.bytes);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_global_table() {
+        let job_id = test_job_id("inspect-global");
+        write_tables(&job_id, &[("a", 1), ("b", 2)], &[("alice", 1, 1)]).await;
+
+        let read_keys = |filter: StateFilter| {
+            let job_id = job_id.clone();
+            async move {
+                let (schema, batches) = read_table(&job_id, 1, "op", "k", &filter).await?;
+                assert_eq!(schema, GLOBAL_KEY_VALUE_SCHEMA.clone());
+                let mut keys: Vec<_> = batches
+                    .iter()
+                    .flat_map(|b| {
+                        b.column(0)
+                            .as_binary::<i32>()
+                            .iter()
+                            .map(|k| hex(k.unwrap()))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+                keys.sort();
+                anyhow::Ok(keys)
+            }
+        };
+
+        assert_eq!(
+            read_keys(StateFilter::default()).await.unwrap(),
+            vec![bincode_hex("a"), bincode_hex("b")]
+        );
+
+        // string keys can be matched directly or by the hex encoding of their bytes
+        assert_eq!(
+            read_keys(filter(Some("b"), None, None)).await.unwrap(),
+            vec![bincode_hex("b")]
+        );
+        assert_eq!(
+            read_keys(filter(Some(&bincode_hex("a")), None, None))
+                .await
+                .unwrap(),
+            vec![bincode_hex("a")]
+        );
+        assert!(read_keys(filter(Some("c"), None, None))
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(
+            read_keys(filter(None, Some(1), None))
+                .await
+                .unwrap_err()
+                .to_string(),
+            "time filters can only be applied to expiring time-key tables"
+        );
+
+        assert!(
+            read_table(&job_id, 1, "op", "missing", &StateFilter::default())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_expiring_table() {
+        let job_id = test_job_id("inspect-expiring");
+        write_tables(
+            &job_id,
+            &[("a", 1)],
+            &[("alice", 1, 10), ("bob", 2, 20), ("alice", 3, 30)],
+        )
+        .await;
+
+        let mut all = read_events(&job_id, &StateFilter::default()).await;
+        all.sort();
+        assert_eq!(
+            all,
+            vec![
+                ("alice".to_string(), 1),
+                ("alice".to_string(), 3),
+                ("bob".to_string(), 2)
+            ]
+        );
+
+        // the start of the range is inclusive and the end is exclusive
+        assert_eq!(
+            read_events(&job_id, &filter(None, Some(20), Some(30))).await,
+            vec![("bob".to_string(), 2)]
+        );
+        assert_eq!(
+            read_events(&job_id, &filter(None, Some(21), None)).await,
+            vec![("alice".to_string(), 3)]
+        );
+
+        let mut alice = read_events(&job_id, &filter(Some("alice"), None, None)).await;
+        alice.sort();
+        assert_eq!(
+            alice,
+            vec![("alice".to_string(), 1), ("alice".to_string(), 3)]
+        );
+
+        assert_eq!(
+            read_events(&job_id, &filter(Some("alice"), None, Some(30))).await,
+            vec![("alice".to_string(), 1)]
+        );
+        assert!(read_events(&job_id, &filter(Some("carol"), None, None))
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_encode_batches() {
+        let job_id = test_job_id("inspect-encode");
+        write_checkpoint(&job_id, "op", &[("a", 1)]).await;
+        let (schema, batches) = read_table(&job_id, 1, "op", "k", &StateFilter::default())
+            .await
+            .unwrap();
+
+        // binary keys and values are hex-encoded in JSON
+        let json = encode_batches(schema.clone(), &batches, StateDumpFormat::Json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            format!(
+                "{{\"key\":\"{}\",\"value\":\"{}\"}}\n",
+                bincode_hex("a"),
+                bincode_hex(1u64)
+            )
+        );
+
+        let ipc = encode_batches(schema.clone(), &batches, StateDumpFormat::Arrow).unwrap();
+        let reader = StreamReader::try_new(std::io::Cursor::new(ipc), None).unwrap();
+        assert_eq!(reader.schema(), schema);
+        let decoded: Vec<_> = reader.map(|b| b.unwrap()).collect();
+        assert_eq!(decoded, batches);
+    }
+}
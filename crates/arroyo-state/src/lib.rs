@@ -19,6 +19,7 @@ use std::time::{Duration, SystemTime};
 
 pub mod checkpoint_state;
 pub mod committing_state;
+pub mod inspect;
 mod metrics;
 pub mod parquet;
 pub(crate) mod schemas;
@@ -151,6 +152,26 @@ pub trait BackingStore {
         old_min_epoch: u32,
         new_min_epoch: u32,
     ) -> Result<()>;
+
+    /// copies a completed checkpoint into a savepoint that is not subject to checkpoint cleanup,
+    /// returning the names of the tables stored for each operator
+    async fn write_savepoint(
+        job_id: &str,
+        epoch: u32,
+        savepoint_id: &str,
+    ) -> Result<HashMap<String, Vec<String>>>;
+
+    /// loads the checkpoint metadata that a savepoint was taken from
+    async fn load_savepoint_metadata(savepoint_id: &str) -> Result<CheckpointMetadata>;
+
+    /// writes the state for the given operators of a savepoint as a checkpoint of a new job,
+    /// at the same epoch the savepoint was taken at
+    async fn restore_savepoint(
+        savepoint_id: &str,
+        job_id: &str,
+        operator_ids: Vec<String>,
+        allow_dropped_tables: bool,
+    ) -> Result<CheckpointMetadata>;
 }
 
 pub fn hash_key<K: Hash>(key: &K) -> u64 {
@@ -22,7 +22,7 @@ use tracing::{debug, info};
 pub const FULL_KEY_RANGE: RangeInclusive<u64> = 0..=u64::MAX;
 pub const GENERATIONS_TO_COMPACT: u32 = 1; // only compact generation 0 files
 
-async fn get_storage_provider() -> anyhow::Result<StorageProvider> {
+pub(crate) async fn get_storage_provider() -> anyhow::Result<StorageProvider> {
     // TODO: this should be encoded in the config so that the controller doesn't need
     // to be synchronized with the workers
     let storage_url =
@@ -50,6 +50,14 @@ fn operator_path(job_id: &str, epoch: u32, operator: &str) -> String {
     format!("{}/operator-{}", base_path(job_id, epoch), operator)
 }
 
+fn savepoint_path(savepoint_id: &str) -> String {
+    format!("savepoints/{}", savepoint_id)
+}
+
+fn savepoint_operator_path(savepoint_id: &str, operator: &str) -> String {
+    format!("{}/operator-{}", savepoint_path(savepoint_id), operator)
+}
+
 #[async_trait::async_trait]
 impl BackingStore for ParquetBackend {
     fn name() -> &'static str {
@@ -165,6 +173,160 @@ impl BackingStore for ParquetBackend {
         Self::write_checkpoint_metadata(metadata).await?;
         Ok(())
     }
+
+    async fn write_savepoint(
+        job_id: &str,
+        epoch: u32,
+        savepoint_id: &str,
+    ) -> Result<HashMap<String, Vec<String>>> {
+        info!(message = "Writing savepoint", job_id, epoch, savepoint_id);
+
+        let storage_client = get_storage_provider().await?;
+        let metadata = Self::load_checkpoint_metadata(job_id, epoch).await?;
+        let savepoint_file = |file: &str| format!("{}/{}", savepoint_path(savepoint_id), file);
+
+        let mut tables = HashMap::new();
+        for operator_id in &metadata.operator_ids {
+            let Some(mut operator_metadata) =
+                Self::load_operator_metadata(job_id, operator_id, epoch).await?
+            else {
+                bail!(
+                    "missing metadata for operator {} in checkpoint {}",
+                    operator_id,
+                    epoch
+                );
+            };
+
+            let mut files = HashSet::new();
+            for (table_name, table_metadata) in
+                operator_metadata.table_checkpoint_metadata.iter_mut()
+            {
+                let table_config = operator_metadata
+                    .table_configs
+                    .get(table_name)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "missing table config for operator {}, table {}",
+                            operator_id,
+                            table_name
+                        )
+                    })?
+                    .clone();
+                let (table_files, rewritten) = match table_config.table_type() {
+                    grpc::TableEnum::MissingTableType => bail!("should have table type"),
+                    grpc::TableEnum::GlobalKeyValue => (
+                        GlobalKeyedTable::files_to_keep(
+                            table_config.clone(),
+                            table_metadata.clone(),
+                        )?,
+                        GlobalKeyedTable::rewrite_files(
+                            table_config,
+                            table_metadata.clone(),
+                            &savepoint_file,
+                        )?,
+                    ),
+                    grpc::TableEnum::ExpiringKeyedTimeTable => (
+                        ExpiringTimeKeyTable::files_to_keep(
+                            table_config.clone(),
+                            table_metadata.clone(),
+                        )?,
+                        ExpiringTimeKeyTable::rewrite_files(
+                            table_config,
+                            table_metadata.clone(),
+                            &savepoint_file,
+                        )?,
+                    ),
+                };
+                files.extend(table_files);
+                *table_metadata = rewritten;
+            }
+
+            let mut copies: FuturesUnordered<_> = files
+                .iter()
+                .map(|file| storage_client.copy(file.clone(), savepoint_file(file)))
+                .collect();
+            while let Some(result) = copies.next().await {
+                result?;
+            }
+
+            storage_client
+                .put(
+                    metadata_path(&savepoint_operator_path(savepoint_id, operator_id)),
+                    operator_metadata.encode_to_vec(),
+                )
+                .await?;
+
+            tables.insert(
+                operator_id.clone(),
+                operator_metadata
+                    .table_checkpoint_metadata
+                    .into_keys()
+                    .collect(),
+            );
+        }
+
+        // the savepoint metadata is written last, so that its presence means the savepoint is complete
+        storage_client
+            .put(
+                metadata_path(&savepoint_path(savepoint_id)),
+                metadata.encode_to_vec(),
+            )
+            .await?;
+
+        Ok(tables)
+    }
+
+    async fn load_savepoint_metadata(savepoint_id: &str) -> Result<CheckpointMetadata> {
+        let storage_client = get_storage_provider().await?;
+        let data = storage_client
+            .get(&metadata_path(&savepoint_path(savepoint_id)))
+            .await?;
+        Ok(CheckpointMetadata::decode(&data[..])?)
+    }
+
+    async fn restore_savepoint(
+        savepoint_id: &str,
+        job_id: &str,
+        operator_ids: Vec<String>,
+        allow_dropped_tables: bool,
+    ) -> Result<CheckpointMetadata> {
+        let storage_client = get_storage_provider().await?;
+        let mut metadata = Self::load_savepoint_metadata(savepoint_id).await?;
+
+        info!(
+            message = "Restoring savepoint",
+            savepoint_id,
+            job_id,
+            epoch = metadata.epoch
+        );
+
+        for operator_id in &operator_ids {
+            let data = storage_client
+                .get(&metadata_path(&savepoint_operator_path(
+                    savepoint_id,
+                    operator_id,
+                )))
+                .await?;
+            let mut operator_metadata = OperatorCheckpointMetadata::decode(&data[..])?;
+            operator_metadata
+                .operator_metadata
+                .as_mut()
+                .ok_or_else(|| anyhow::anyhow!("missing operator metadata"))?
+                .job_id = job_id.to_string();
+            operator_metadata.allow_dropped_tables = allow_dropped_tables;
+
+            // the table files stay in the savepoint; cleanup only removes files under the job's
+            // own checkpoint directory
+            Self::write_operator_checkpoint_metadata(operator_metadata).await?;
+        }
+
+        metadata.job_id = job_id.to_string();
+        metadata.min_epoch = metadata.epoch;
+        metadata.operator_ids = operator_ids;
+        Self::write_checkpoint_metadata(metadata.clone()).await?;
+
+        Ok(metadata)
+    }
 }
 
 impl ParquetBackend {
@@ -292,7 +454,11 @@ impl ParquetBackend {
                     files
                 })
             {
-                if !paths_to_keep.contains(&file) && !deleted_paths.contains(&file) {
+                // files outside of the job's directory belong to the savepoint it was restored from
+                if !paths_to_keep.contains(&file)
+                    && !deleted_paths.contains(&file)
+                    && file.starts_with(&format!("{}/", job_id))
+                {
                     deleted_paths.insert(file.clone());
                     storage_client.delete_if_present(file).await?;
                 }
@@ -334,3 +500,296 @@ pub fn get_storage_env_vars() -> HashMap<String, String> {
         .filter_map(|&var| env::var(var).ok().map(|v| (var.to_string(), v)))
         .collect()
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::global_table_config;
+    use crate::tables::global_keyed_map::GlobalKeyedView;
+    use crate::tables::table_manager::TableManager;
+    use arroyo_rpc::grpc::{OperatorMetadata, TableConfig, TableEnum};
+    use arroyo_rpc::ControlResp;
+    use arroyo_types::{get_test_task_info, single_item_hash_map, CheckpointBarrier};
+    use tokio::sync::mpsc::{channel, Receiver};
+
+    pub(crate) fn test_job_id(name: &str) -> String {
+        format!("{}-{}", name, rand::random::<u64>())
+    }
+
+    async fn table_manager(
+        job_id: &str,
+        operator_id: &str,
+        tables: HashMap<String, TableConfig>,
+        restore_from: Option<OperatorCheckpointMetadata>,
+    ) -> Result<TableManager> {
+        let mut task_info = get_test_task_info();
+        task_info.job_id = job_id.to_string();
+        task_info.operator_id = operator_id.to_string();
+
+        // the receiver is dropped, so nothing can be checkpointed
+        let (tx, _) = channel(16);
+        TableManager::new(Arc::new(task_info), tables, tx, restore_from).await
+    }
+
+    /// Creates the table manager for the only subtask of an operator, along with the receiver
+    /// that its checkpoints are reported to
+    pub(crate) async fn checkpointing_table_manager(
+        job_id: &str,
+        operator_id: &str,
+        tables: HashMap<String, TableConfig>,
+    ) -> (TableManager, Receiver<ControlResp>) {
+        let mut task_info = get_test_task_info();
+        task_info.job_id = job_id.to_string();
+        task_info.operator_id = operator_id.to_string();
+
+        let (tx, rx) = channel(16);
+        let manager = TableManager::new(Arc::new(task_info), tables, tx, None)
+            .await
+            .unwrap();
+        (manager, rx)
+    }
+
+    /// Checkpoints the tables of `manager` as the first checkpoint of its job, and writes the
+    /// operator and checkpoint metadata that the controller would
+    pub(crate) async fn finish_checkpoint(
+        job_id: &str,
+        operator_id: &str,
+        manager: &mut TableManager,
+        rx: &mut Receiver<ControlResp>,
+    ) -> OperatorCheckpointMetadata {
+        manager
+            .checkpoint(
+                CheckpointBarrier {
+                    epoch: 1,
+                    min_epoch: 0,
+                    timestamp: SystemTime::now(),
+                    then_stop: false,
+                },
+                None,
+            )
+            .await;
+        let completed = loop {
+            if let ControlResp::CheckpointCompleted(c) = rx.recv().await.unwrap() {
+                break c;
+            }
+        };
+
+        let subtask_metadata = completed.subtask_metadata;
+        let table_checkpoint_metadata = subtask_metadata
+            .table_metadata
+            .into_iter()
+            .map(|(name, table_metadata)| {
+                let config = subtask_metadata.table_configs[&name].clone();
+                let subtask_tables = single_item_hash_map(0u32, table_metadata);
+                let merged = match config.table_type() {
+                    TableEnum::GlobalKeyValue => {
+                        GlobalKeyedTable::merge_checkpoint_metadata(config, subtask_tables)
+                    }
+                    TableEnum::ExpiringKeyedTimeTable => {
+                        ExpiringTimeKeyTable::merge_checkpoint_metadata(config, subtask_tables)
+                    }
+                    TableEnum::MissingTableType => unreachable!(),
+                };
+                (name, merged.unwrap().unwrap())
+            })
+            .collect();
+
+        let metadata = OperatorCheckpointMetadata {
+            start_time: 0,
+            finish_time: 0,
+            table_checkpoint_metadata,
+            table_configs: subtask_metadata.table_configs,
+            allow_dropped_tables: false,
+            operator_metadata: Some(OperatorMetadata {
+                job_id: job_id.to_string(),
+                operator_id: operator_id.to_string(),
+                epoch: 1,
+                min_watermark: None,
+                max_watermark: None,
+                parallelism: 1,
+            }),
+        };
+        ParquetBackend::write_operator_checkpoint_metadata(metadata.clone())
+            .await
+            .unwrap();
+        ParquetBackend::write_checkpoint_metadata(CheckpointMetadata {
+            job_id: job_id.to_string(),
+            epoch: 1,
+            min_epoch: 1,
+            start_time: 0,
+            finish_time: 0,
+            operator_ids: vec![operator_id.to_string()],
+        })
+        .await
+        .unwrap();
+
+        metadata
+    }
+
+    /// Writes the first checkpoint of a job with a single operator, whose global keyed table `k`
+    /// holds `values`
+    pub(crate) async fn write_checkpoint(
+        job_id: &str,
+        operator_id: &str,
+        values: &[(&str, u64)],
+    ) -> OperatorCheckpointMetadata {
+        let (mut manager, mut rx) = checkpointing_table_manager(
+            job_id,
+            operator_id,
+            global_table_config("k", "test table"),
+        )
+        .await;
+
+        let view: &mut GlobalKeyedView<String, u64> =
+            manager.get_global_keyed_state("k").await.unwrap();
+        for (key, value) in values {
+            view.insert(key.to_string(), *value).await;
+        }
+
+        finish_checkpoint(job_id, operator_id, &mut manager, &mut rx).await
+    }
+
+    fn table_files(metadata: &OperatorCheckpointMetadata) -> Vec<String> {
+        let table_metadata = metadata.table_checkpoint_metadata["k"].clone();
+        let mut files: Vec<_> =
+            GlobalKeyedTable::files_to_keep(metadata.table_configs["k"].clone(), table_metadata)
+                .unwrap()
+                .into_iter()
+                .collect();
+        files.sort();
+        files
+    }
+
+    #[tokio::test]
+    async fn test_savepoint_round_trip() {
+        let job_id = test_job_id("savepoint-source");
+        let savepoint_id = test_job_id("sp");
+        let checkpoint = write_checkpoint(&job_id, "op", &[("a", 1), ("b", 2)]).await;
+
+        let tables = ParquetBackend::write_savepoint(&job_id, 1, &savepoint_id)
+            .await
+            .unwrap();
+        assert_eq!(tables, single_item_hash_map("op", vec!["k".to_string()]));
+
+        // the savepoint holds its own copies of the table files, so it outlives the job's
+        // checkpoints
+        let storage = get_storage_provider().await.unwrap();
+        let checkpoint_files = table_files(&checkpoint);
+        assert!(!checkpoint_files.is_empty());
+        for file in &checkpoint_files {
+            storage.delete_if_present(file.clone()).await.unwrap();
+        }
+
+        let new_job_id = test_job_id("savepoint-restore");
+        let metadata =
+            ParquetBackend::restore_savepoint(&savepoint_id, &new_job_id, vec!["op".into()], false)
+                .await
+                .unwrap();
+        assert_eq!(metadata.job_id, new_job_id);
+        assert_eq!((metadata.epoch, metadata.min_epoch), (1, 1));
+        assert_eq!(metadata.operator_ids, vec!["op".to_string()]);
+        assert_eq!(
+            ParquetBackend::load_checkpoint_metadata(&new_job_id, 1)
+                .await
+                .unwrap(),
+            metadata
+        );
+
+        let restored = ParquetBackend::load_operator_metadata(&new_job_id, "op", 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            restored.operator_metadata.as_ref().unwrap().job_id,
+            new_job_id
+        );
+        assert_eq!(
+            table_files(&restored),
+            checkpoint_files
+                .iter()
+                .map(|file| format!("{}/{}", savepoint_path(&savepoint_id), file))
+                .collect::<Vec<_>>()
+        );
+
+        let mut manager = table_manager(
+            &new_job_id,
+            "op",
+            global_table_config("k", "test table"),
+            Some(restored),
+        )
+        .await
+        .unwrap();
+        let view: &mut GlobalKeyedView<String, u64> =
+            manager.get_global_keyed_state("k").await.unwrap();
+        assert_eq!(
+            view.get_all(),
+            &HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_savepoint_with_dropped_table() {
+        let job_id = test_job_id("savepoint-source");
+        let savepoint_id = test_job_id("sp");
+        write_checkpoint(&job_id, "op", &[("a", 1)]).await;
+        ParquetBackend::write_savepoint(&job_id, 1, &savepoint_id)
+            .await
+            .unwrap();
+
+        // the operator no longer has table `k`
+        for allow_dropped_tables in [false, true] {
+            let new_job_id = test_job_id("savepoint-restore");
+            ParquetBackend::restore_savepoint(
+                &savepoint_id,
+                &new_job_id,
+                vec!["op".into()],
+                allow_dropped_tables,
+            )
+            .await
+            .unwrap();
+            let restored = ParquetBackend::load_operator_metadata(&new_job_id, "op", 1)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(restored.allow_dropped_tables, allow_dropped_tables);
+
+            let result = table_manager(
+                &new_job_id,
+                "op",
+                global_table_config("other", "test table"),
+                Some(restored),
+            )
+            .await;
+
+            if allow_dropped_tables {
+                assert!(result.is_ok());
+            } else {
+                assert_eq!(
+                    result.err().unwrap().to_string(),
+                    "checkpoint contains state for table k which operator op no longer has"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restore_savepoint_without_dropped_operators() {
+        let job_id = test_job_id("savepoint-source");
+        let savepoint_id = test_job_id("sp");
+        write_checkpoint(&job_id, "op", &[("a", 1)]).await;
+        ParquetBackend::write_savepoint(&job_id, 1, &savepoint_id)
+            .await
+            .unwrap();
+
+        // operators that the controller dropped from the savepoint aren't restored
+        let new_job_id = test_job_id("savepoint-restore");
+        let metadata = ParquetBackend::restore_savepoint(&savepoint_id, &new_job_id, vec![], true)
+            .await
+            .unwrap();
+        assert!(metadata.operator_ids.is_empty());
+        assert!(ParquetBackend::load_operator_metadata(&new_job_id, "op", 1)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -352,6 +352,18 @@ impl Table for ExpiringTimeKeyTable {
             .map(|file: ParquetTimeFile| file.file)
             .collect())
     }
+
+    fn rewrite_files(
+        _config: Self::ConfigMessage,
+        mut checkpoint: Self::TableCheckpointMessage,
+        rewrite: &dyn Fn(&str) -> String,
+    ) -> Result<Self::TableCheckpointMessage> {
+        for file in &mut checkpoint.files {
+            file.file = rewrite(&file.file);
+        }
+        Ok(checkpoint)
+    }
+
     fn apply_compacted_checkpoint(
         &self,
         epoch: u32,
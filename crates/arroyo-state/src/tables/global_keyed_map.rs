@@ -29,7 +29,7 @@ use std::{
 use tokio::sync::mpsc::Sender;
 
 use super::{table_checkpoint_path, CompactionConfig, Table, TableEpochCheckpointer};
-static GLOBAL_KEY_VALUE_SCHEMA: Lazy<Arc<Schema>> = Lazy::new(|| {
+pub(crate) static GLOBAL_KEY_VALUE_SCHEMA: Lazy<Arc<Schema>> = Lazy::new(|| {
     let fields = vec![
         Field::new("key", DataType::Binary, false), // non-nullable BinaryArray for 'key'
         Field::new("value", DataType::Binary, false), // non-nullable BinaryArray for 'value'
@@ -194,6 +194,18 @@ impl Table for GlobalKeyedTable {
     ) -> Result<std::collections::HashSet<String>> {
         Ok(checkpoint.files.into_iter().collect())
     }
+
+    fn rewrite_files(
+        _config: Self::ConfigMessage,
+        mut checkpoint: Self::TableCheckpointMessage,
+        rewrite: &dyn Fn(&str) -> String,
+    ) -> Result<Self::TableCheckpointMessage> {
+        for file in &mut checkpoint.files {
+            *file = rewrite(file);
+        }
+        Ok(checkpoint)
+    }
+
     fn committing_data(
         config: Self::ConfigMessage,
         table_metadata: Self::TableCheckpointMessage,
@@ -129,6 +129,14 @@ pub(crate) trait Table: Send + Sync + 'static + Clone {
         checkpoint: Self::TableCheckpointMessage,
     ) -> Result<HashSet<String>>;
 
+    // produce a copy of the checkpoint metadata with every file path passed through `rewrite`,
+    // used when the files are copied to a new location such as a savepoint.
+    fn rewrite_files(
+        config: Self::ConfigMessage,
+        checkpoint: Self::TableCheckpointMessage,
+        rewrite: &dyn Fn(&str) -> String,
+    ) -> Result<Self::TableCheckpointMessage>;
+
     async fn compact_data(
         config: Self::ConfigMessage,
         compaction_config: &CompactionConfig,
@@ -216,6 +224,14 @@ pub trait ErasedTable: Send + Sync + 'static {
     where
         Self: Sized;
 
+    fn rewrite_files(
+        config: TableConfig,
+        checkpoint: TableCheckpointMetadata,
+        rewrite: &dyn Fn(&str) -> String,
+    ) -> Result<TableCheckpointMetadata>
+    where
+        Self: Sized;
+
     fn as_any(&self) -> &dyn Any;
 
     #[allow(async_fn_in_trait)]
@@ -358,6 +374,25 @@ impl<T: Table + Sized + 'static> ErasedTable for T {
             Self::checked_proto_decode(T::table_type(), checkpoint.data)?,
         )
     }
+
+    fn rewrite_files(
+        config: TableConfig,
+        checkpoint: TableCheckpointMetadata,
+        rewrite: &dyn Fn(&str) -> String,
+    ) -> Result<TableCheckpointMetadata>
+    where
+        Self: Sized,
+    {
+        let result = T::rewrite_files(
+            Self::checked_proto_decode(T::table_type(), config.config)?,
+            Self::checked_proto_decode(T::table_type(), checkpoint.data)?,
+            rewrite,
+        )?;
+        Ok(TableCheckpointMetadata {
+            table_type: T::table_type().into(),
+            data: result.encode_to_vec(),
+        })
+    }
     fn committing_data(
         config: TableConfig,
         table_metadata: &TableCheckpointMetadata,
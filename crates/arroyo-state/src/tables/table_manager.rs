@@ -285,9 +285,21 @@ impl TableManager {
                 epoch = operator_metadata.epoch + 1;
                 min_epoch = operator_metadata.epoch;
                 for (table, table_metadata) in metadata.table_checkpoint_metadata.clone() {
-                    let table_implementation = tables
-                        .get(&table)
-                        .ok_or_else(|| anyhow!("missing table {}", table))?;
+                    let Some(table_implementation) = tables.get(&table) else {
+                        if metadata.allow_dropped_tables {
+                            warn!(
+                                message = "dropping state for table that no longer exists",
+                                operator_id = task_info.operator_id,
+                                table
+                            );
+                            continue;
+                        }
+                        bail!(
+                            "checkpoint contains state for table {} which operator {} no longer has",
+                            table,
+                            task_info.operator_id
+                        );
+                    };
                     if let Some(metadata) =
                         table_implementation.subtask_metadata_from_table(table_metadata)?
                     {
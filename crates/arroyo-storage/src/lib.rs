@@ -11,6 +11,7 @@ use aws::ArroyoCredentialProvider;
 use bytes::Bytes;
 use futures::{Stream, StreamExt};
 use object_store::aws::{AmazonS3ConfigKey, AwsCredential};
+use object_store::azure::{AzureConfigKey, MicrosoftAzureBuilder};
 use object_store::gcp::GoogleCloudStorageBuilder;
 use object_store::multipart::PartId;
 use object_store::path::Path;
@@ -78,10 +79,20 @@ const GCS_PATH: &str =
     r"^https://storage\.googleapis\.com/(?P<bucket>[a-z\d\-_\.]+)(/(?P<key>.+))?$";
 const GCS_URL: &str = r"^[gG][sS]://(?P<bucket>[a-z0-9\-\.]+)(/(?P<key>.+))?$";
 
+// https://ACCOUNT.blob.core.windows.net/CONTAINER/BLOB_NAME
+const AZURE_HTTPS: &str = r"^https://(?P<account>[a-z0-9]+)\.(blob|dfs)\.core\.windows\.net/(?P<container>[a-z0-9\-]+)(/(?P<key>.+))?$";
+// abfss://CONTAINER@ACCOUNT.dfs.core.windows.net/BLOB_NAME
+const ABFS_ACCOUNT_URL: &str = r"^[aA][bB][fF][sS][sS]?://(?P<container>[a-z0-9\-]+)@(?P<account>[a-z0-9]+)\.dfs\.core\.windows\.net(/(?P<key>.+))?$";
+// abfs://CONTAINER/BLOB_NAME
+const ABFS_URL: &str = r"^[aA][bB][fF][sS][sS]?://(?P<container>[a-z0-9\-]+)(/(?P<key>.+))?$";
+// az://CONTAINER/BLOB_NAME
+const AZURE_URL: &str = r"^[aA][zZ]://(?P<container>[a-z0-9\-]+)(/(?P<key>.+))?$";
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
 enum Backend {
     S3,
     GCS,
+    Azure,
     Local,
 }
 
@@ -109,6 +120,16 @@ fn matchers() -> &'static HashMap<Backend, Vec<Regex>> {
             ],
         );
 
+        m.insert(
+            Backend::Azure,
+            vec![
+                Regex::new(AZURE_HTTPS).unwrap(),
+                Regex::new(ABFS_ACCOUNT_URL).unwrap(),
+                Regex::new(ABFS_URL).unwrap(),
+                Regex::new(AZURE_URL).unwrap(),
+            ],
+        );
+
         m.insert(
             Backend::Local,
             vec![
@@ -172,6 +193,14 @@ pub struct GCSConfig {
     key: Option<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AzureConfig {
+    // if not set in the URL, this is taken from the storage options or AZURE_STORAGE_ACCOUNT_NAME
+    account: Option<String>,
+    container: String,
+    key: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LocalConfig {
     pub path: String,
@@ -182,6 +211,7 @@ pub struct LocalConfig {
 pub enum BackendConfig {
     S3(S3Config),
     GCS(GCSConfig),
+    Azure(AzureConfig),
     Local(LocalConfig),
 }
 
@@ -192,6 +222,7 @@ impl BackendConfig {
                 return match k {
                     Backend::S3 => Self::parse_s3(matches),
                     Backend::GCS => Self::parse_gcs(matches),
+                    Backend::Azure => Self::parse_azure(matches),
                     Backend::Local => Self::parse_local(matches, with_key),
                 };
             }
@@ -261,6 +292,23 @@ impl BackendConfig {
         Ok(BackendConfig::GCS(GCSConfig { bucket, key }))
     }
 
+    fn parse_azure(matches: Captures) -> Result<Self, StorageError> {
+        let container = matches
+            .name("container")
+            .expect("container should always be available")
+            .as_str()
+            .to_string();
+
+        let account = matches.name("account").map(|m| m.as_str().to_string());
+        let key = matches.name("key").map(|m| m.as_str().to_string());
+
+        Ok(BackendConfig::Azure(AzureConfig {
+            account,
+            container,
+            key,
+        }))
+    }
+
     fn parse_local(matches: Captures, with_key: bool) -> Result<Self, StorageError> {
         let path = matches
             .name("path")
@@ -291,6 +339,7 @@ impl BackendConfig {
         match self {
             BackendConfig::S3(s3) => s3.key.as_ref(),
             BackendConfig::GCS(gcs) => gcs.key.as_ref(),
+            BackendConfig::Azure(azure) => azure.key.as_ref(),
             BackendConfig::Local(local) => local.key.as_ref(),
         }
     }
@@ -319,6 +368,7 @@ impl StorageProvider {
         match config {
             BackendConfig::S3(config) => Self::construct_s3(config, options).await,
             BackendConfig::GCS(config) => Self::construct_gcs(config),
+            BackendConfig::Azure(config) => Self::construct_azure(config, options),
             BackendConfig::Local(config) => Self::construct_local(config).await,
         }
     }
@@ -336,6 +386,7 @@ impl StorageProvider {
         let provider = match config {
             BackendConfig::S3(config) => Self::construct_s3(config, options).await,
             BackendConfig::GCS(config) => Self::construct_gcs(config),
+            BackendConfig::Azure(config) => Self::construct_azure(config, options),
             BackendConfig::Local(config) => Self::construct_local(config).await,
         }?;
 
@@ -349,6 +400,7 @@ impl StorageProvider {
         let key = match &config {
             BackendConfig::S3(s3) => s3.key.as_ref(),
             BackendConfig::GCS(gcs) => gcs.key.as_ref(),
+            BackendConfig::Azure(azure) => azure.key.as_ref(),
             BackendConfig::Local(local) => local.key.as_ref(),
         }
         .ok_or_else(|| StorageError::NoKeyInUrl)?;
@@ -459,6 +511,78 @@ impl StorageProvider {
         })
     }
 
+    /// Credentials are resolved by object_store: an account key (`azure_storage_account_key`), a
+    /// SAS token (`azure_storage_sas_key`) or service principal set in the storage options or the
+    /// corresponding `AZURE_*` environment variables, falling back to the managed identity of the
+    /// host. Setting `azure_storage_use_emulator` connects to a local Azurite instance instead.
+    fn construct_azure(
+        mut config: AzureConfig,
+        options: HashMap<String, String>,
+    ) -> Result<Self, StorageError> {
+        let mut builder = MicrosoftAzureBuilder::from_env().with_container_name(&config.container);
+        for (key, value) in options {
+            let azure_config_key: AzureConfigKey = key.parse().map_err(|_| {
+                StorageError::CredentialsError(format!("invalid Azure config key: {}", key))
+            })?;
+            builder = builder.with_config(azure_config_key, value);
+        }
+
+        config.account = config
+            .account
+            .or_else(|| builder.get_config_value(&AzureConfigKey::AccountName));
+        if let Some(account) = &config.account {
+            builder = builder.with_account(account);
+        }
+
+        // systems that build their own store (like delta-rs) need the credentials we picked up
+        // from the environment as well as those that were passed in
+        let azure_options: HashMap<String, String> = [
+            AzureConfigKey::AccountName,
+            AzureConfigKey::AccessKey,
+            AzureConfigKey::SasKey,
+            AzureConfigKey::Token,
+            AzureConfigKey::ClientId,
+            AzureConfigKey::ClientSecret,
+            AzureConfigKey::AuthorityId,
+            AzureConfigKey::UseEmulator,
+        ]
+        .into_iter()
+        .filter_map(|k| {
+            builder
+                .get_config_value(&k)
+                .map(|v| (k.as_ref().to_string(), v))
+        })
+        .collect();
+
+        let (mut canonical_url, object_store_base_url) = match &config.account {
+            Some(account) => (
+                format!(
+                    "https://{}.blob.core.windows.net/{}",
+                    account, config.container
+                ),
+                format!(
+                    "abfs://{}@{}.dfs.core.windows.net",
+                    config.container, account
+                ),
+            ),
+            None => (
+                format!("az://{}", config.container),
+                format!("az://{}", config.container),
+            ),
+        };
+        if let Some(key) = &config.key {
+            canonical_url = format!("{}/{}", canonical_url, key);
+        }
+
+        Ok(Self {
+            config: BackendConfig::Azure(config),
+            object_store: Arc::new(builder.build()?),
+            canonical_url,
+            object_store_base_url,
+            storage_options: azure_options,
+        })
+    }
+
     async fn construct_local(config: LocalConfig) -> Result<Self, StorageError> {
         tokio::fs::create_dir_all(&config.path).await.map_err(|e| {
             StorageError::PathError(format!(
@@ -592,6 +716,14 @@ impl StorageProvider {
         Ok(format!("{}/{}", self.canonical_url, path))
     }
 
+    pub async fn copy<P: Into<String>>(&self, from: P, to: P) -> Result<(), StorageError> {
+        let from = self.qualify_path(&from.into().into());
+        let to = self.qualify_path(&to.into().into());
+        retry!(self.object_store.copy(&from, &to).await)?;
+
+        Ok(())
+    }
+
     fn qualify_path(&self, path: &Path) -> Path {
         match self.config.key() {
             Some(prefix) => {
@@ -761,6 +893,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_azure_configs() {
+        assert_eq!(
+            BackendConfig::parse_url(
+                "https://myaccount.blob.core.windows.net/my-container/path/to/blob.parquet",
+                false
+            )
+            .unwrap(),
+            BackendConfig::Azure(crate::AzureConfig {
+                account: Some("myaccount".to_string()),
+                container: "my-container".to_string(),
+                key: Some("path/to/blob.parquet".to_string()),
+            })
+        );
+
+        assert_eq!(
+            BackendConfig::parse_url(
+                "abfss://my-container@myaccount.dfs.core.windows.net/path",
+                false
+            )
+            .unwrap(),
+            BackendConfig::Azure(crate::AzureConfig {
+                account: Some("myaccount".to_string()),
+                container: "my-container".to_string(),
+                key: Some("path".to_string()),
+            })
+        );
+
+        assert_eq!(
+            BackendConfig::parse_url("abfs://my-container/path", false).unwrap(),
+            BackendConfig::Azure(crate::AzureConfig {
+                account: None,
+                container: "my-container".to_string(),
+                key: Some("path".to_string()),
+            })
+        );
+
+        assert_eq!(
+            BackendConfig::parse_url("az://my-container", false).unwrap(),
+            BackendConfig::Azure(crate::AzureConfig {
+                account: None,
+                container: "my-container".to_string(),
+                key: None,
+            })
+        );
+    }
+
     #[test]
     fn test_local_configs() {
         assert_eq!(
@@ -824,4 +1003,27 @@ mod tests {
                 .unwrap()
         );
     }
+
+    #[tokio::test]
+    #[ignore = "requires an Azurite emulator with an `arroyo-testing` container"]
+    async fn test_azurite() {
+        let storage = StorageProvider::for_url_with_options(
+            "az://arroyo-testing/storage-tests",
+            [("azure_storage_use_emulator".to_string(), "true".to_string())]
+                .into_iter()
+                .collect(),
+        )
+        .await
+        .unwrap();
+
+        let now = to_nanos(SystemTime::now());
+        let data = now.to_le_bytes().to_vec();
+        let key = format!("my-test/{}", now);
+
+        storage.put(&key, data.clone()).await.unwrap();
+        assert_eq!(storage.get(&key).await.unwrap(), data.clone());
+        assert!(storage.exists(key.as_str()).await.unwrap());
+
+        storage.delete_if_present(&key).await.unwrap();
+    }
 }
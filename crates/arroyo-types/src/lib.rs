@@ -112,6 +112,24 @@ pub const API_ENDPOINT_ENV: &str = "API_ENDPOINT";
 // The rate parameter (e.g., "15s") used by the API when querying prometheus metrics -- this should
 // be at least 4x the configured scrape interval for your prometheus config
 pub const API_METRICS_RATE_ENV: &str = "API_METRICS_RATE";
+// Where the API and autoscaler read operator metrics from: "prometheus" (the default), or
+// "controller" to use the metrics the controller collects from worker heartbeats
+pub const METRICS_BACKEND_ENV: &str = "METRICS_BACKEND";
+
+// api authentication: "none" (the default, in which all requests are treated as coming from an
+// admin), "api_keys", or "oidc" (which accepts both api keys and JWTs from an OIDC provider)
+pub const AUTH_MODE_ENV: &str = "AUTH_MODE";
+// An api key that is always accepted with the admin role, used to create the first stored keys
+pub const ADMIN_API_KEY_ENV: &str = "ADMIN_API_KEY";
+pub const OIDC_JWKS_URL_ENV: &str = "OIDC_JWKS_URL";
+pub const OIDC_ISSUER_ENV: &str = "OIDC_ISSUER";
+pub const OIDC_AUDIENCE_ENV: &str = "OIDC_AUDIENCE";
+// The (possibly dot-separated) claim that holds a user's role or roles; defaults to "role"
+pub const OIDC_ROLE_CLAIM_ENV: &str = "OIDC_ROLE_CLAIM";
+
+// A base64-encoded 256-bit key used to encrypt secrets at rest; must be set for the api (which
+// encrypts them) and the workers (which decrypt them when constructing operators)
+pub const SECRETS_KEY_ENV: &str = "SECRETS_KEY";
 
 // storage configuration
 pub const S3_ENDPOINT_ENV: &str = "S3_ENDPOINT";
@@ -740,6 +758,8 @@ pub static BATCHES_SENT: &str = "arroyo_worker_batches_sent";
 pub static TX_QUEUE_SIZE: &str = "arroyo_worker_tx_queue_size";
 pub static TX_QUEUE_REM: &str = "arroyo_worker_tx_queue_rem";
 pub static DESERIALIZATION_ERRORS: &str = "arroyo_worker_deserialization_errors";
+pub static DEAD_LETTERS: &str = "arroyo_worker_dead_letters";
+pub static BUSY_TIME: &str = "arroyo_worker_busy_time_micros";
 
 #[derive(Debug, Copy, Clone, Encode, Decode, PartialEq, Eq)]
 pub struct CheckpointBarrier {
@@ -0,0 +1,290 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::Result;
+use arrow::compute::{concat_batches, max, min};
+use arrow_array::{RecordBatch, TimestampNanosecondArray};
+use arroyo_df::physical::{ArroyoPhysicalExtensionCodec, DecodingContext};
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
+use arroyo_rpc::{
+    df::ArroyoSchema,
+    grpc::{api, TableConfig},
+};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{from_nanos, CheckpointBarrier, Watermark};
+use datafusion::execution::context::SessionContext;
+use datafusion_execution::runtime_env::{RuntimeConfig, RuntimeEnv};
+use datafusion_physical_plan::ExecutionPlan;
+use datafusion_proto::{physical_plan::AsExecutionPlan, protobuf::PhysicalPlanNode};
+use futures::StreamExt;
+use prost::Message;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn name(&self) -> &'static str {
+        match self {
+            Side::Left => "left",
+            Side::Right => "right",
+        }
+    }
+
+    fn other(&self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+/// Joins two streams on rows whose event times are within fixed bounds of each other
+/// (lower_bound <= right time - left time <= upper_bound). Each side is buffered until the
+/// watermark passes the point where it could still be matched, and results are emitted as
+/// soon as the second row of a pair arrives.
+pub struct IntervalJoin {
+    lower_bound: i64,
+    upper_bound: i64,
+    left_input_schema: ArroyoSchema,
+    right_input_schema: ArroyoSchema,
+    left_schema: ArroyoSchema,
+    right_schema: ArroyoSchema,
+    left_passer: Arc<RwLock<Option<RecordBatch>>>,
+    right_passer: Arc<RwLock<Option<RecordBatch>>>,
+    join_execution_plan: Arc<dyn ExecutionPlan>,
+}
+
+impl IntervalJoin {
+    fn input_schema(&self, side: Side) -> &ArroyoSchema {
+        match side {
+            Side::Left => &self.left_input_schema,
+            Side::Right => &self.right_input_schema,
+        }
+    }
+
+    fn schema(&self, side: Side) -> &ArroyoSchema {
+        match side {
+            Side::Left => &self.left_schema,
+            Side::Right => &self.right_schema,
+        }
+    }
+
+    /// How long rows from a side need to be kept after the watermark passes them
+    fn retention(&self, side: Side) -> Duration {
+        let nanos = match side {
+            // left rows can be matched by right rows up to upper_bound after them
+            Side::Left => self.upper_bound,
+            // right rows can be matched by left rows up to -lower_bound after them
+            Side::Right => -self.lower_bound,
+        };
+        Duration::from_nanos(nanos.max(0) as u64)
+    }
+
+    async fn process_side(
+        &mut self,
+        side: Side,
+        batch: RecordBatch,
+        ctx: &mut ArrowContext,
+    ) -> Result<()> {
+        let watermark = ctx.last_present_watermark();
+
+        let time_column = batch
+            .column(self.input_schema(side).timestamp_index)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .expect("should have timestamp column");
+        let max_timestamp = max(time_column).expect("should have max timestamp");
+        let min_timestamp = min(time_column).expect("should have min timestamp");
+
+        ctx.table_manager
+            .get_expiring_time_key_table(side.name(), watermark)
+            .await
+            .expect("should have table")
+            .insert(from_nanos(max_timestamp as u128), batch.clone());
+
+        // the earliest time on the other side that any row in this batch can match
+        let other_cutoff = from_nanos(
+            match side {
+                Side::Left => min_timestamp + self.lower_bound,
+                Side::Right => min_timestamp - self.upper_bound,
+            }
+            .max(0) as u128,
+        );
+
+        let other = side.other();
+        let other_input_schema = self.input_schema(other).clone();
+        let other_table = ctx
+            .table_manager
+            .get_expiring_time_key_table(other.name(), watermark)
+            .await
+            .expect("should have table");
+        let other_batches = other_table
+            .all_batches_for_watermark(watermark)
+            .filter(|(max_timestamp, _)| **max_timestamp >= other_cutoff)
+            .flat_map(|(_, batches)| batches.iter())
+            .map(|batch| other_input_schema.unkeyed_batch(batch))
+            .collect::<Result<Vec<_>>>()?;
+
+        if other_batches.is_empty() {
+            return Ok(());
+        }
+
+        let other_batch = concat_batches(&self.schema(other).schema, other_batches.iter())?;
+        let batch = self.input_schema(side).unkeyed_batch(&batch)?;
+
+        match side {
+            Side::Left => self.compute_pair(batch, other_batch, ctx).await,
+            Side::Right => self.compute_pair(other_batch, batch, ctx).await,
+        }
+        Ok(())
+    }
+
+    async fn compute_pair(
+        &mut self,
+        left: RecordBatch,
+        right: RecordBatch,
+        ctx: &mut ArrowContext,
+    ) {
+        {
+            self.right_passer.write().unwrap().replace(right);
+            self.left_passer.write().unwrap().replace(left);
+        }
+        self.join_execution_plan.reset().unwrap();
+        let mut records = self
+            .join_execution_plan
+            .execute(0, SessionContext::new().task_ctx())
+            .expect("successfully computed?");
+        while let Some(batch) = records.next().await {
+            let batch = batch.expect("should be able to compute batch");
+            ctx.collect(batch).await;
+        }
+    }
+
+    async fn flush(&mut self, ctx: &mut ArrowContext) {
+        let watermark = ctx.last_present_watermark();
+        for side in [Side::Left, Side::Right] {
+            ctx.table_manager
+                .get_expiring_time_key_table(side.name(), watermark)
+                .await
+                .expect("should have table")
+                .flush(watermark)
+                .await
+                .expect("should flush");
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for IntervalJoin {
+    fn name(&self) -> String {
+        format!("IntervalJoin")
+    }
+
+    async fn process_batch(&mut self, _record_batch: RecordBatch, _ctx: &mut ArrowContext) {
+        unreachable!();
+    }
+
+    async fn process_batch_index(
+        &mut self,
+        index: usize,
+        total_inputs: usize,
+        record_batch: RecordBatch,
+        ctx: &mut ArrowContext,
+    ) {
+        let side = match index / (total_inputs / 2) {
+            0 => Side::Left,
+            1 => Side::Right,
+            _ => unreachable!(),
+        };
+        self.process_side(side, record_batch, ctx)
+            .await
+            .expect("should process batch");
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        ctx: &mut ArrowContext,
+    ) -> Option<Watermark> {
+        // evict rows that can no longer be matched
+        self.flush(ctx).await;
+        Some(watermark)
+    }
+
+    async fn handle_checkpoint(&mut self, _b: CheckpointBarrier, ctx: &mut ArrowContext) {
+        self.flush(ctx).await;
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        let mut tables = HashMap::new();
+        tables.insert(
+            "left".to_string(),
+            timestamp_table_config(
+                "left",
+                "left join data",
+                self.retention(Side::Left),
+                self.left_input_schema.clone(),
+            ),
+        );
+        tables.insert(
+            "right".to_string(),
+            timestamp_table_config(
+                "right",
+                "right join data",
+                self.retention(Side::Right),
+                self.right_input_schema.clone(),
+            ),
+        );
+        tables
+    }
+}
+
+pub struct IntervalJoinConstructor;
+impl OperatorConstructor for IntervalJoinConstructor {
+    type ConfigT = api::IntervalJoinOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let left_passer = Arc::new(RwLock::new(None));
+        let right_passer = Arc::new(RwLock::new(None));
+
+        let codec = ArroyoPhysicalExtensionCodec {
+            context: DecodingContext::LockedJoinPair {
+                left: left_passer.clone(),
+                right: right_passer.clone(),
+            },
+        };
+        let join_physical_plan_node = PhysicalPlanNode::decode(&mut config.join_plan.as_slice())?;
+        let join_execution_plan = join_physical_plan_node.try_into_physical_plan(
+            registry.as_ref(),
+            &RuntimeEnv::new(RuntimeConfig::new())?,
+            &codec,
+        )?;
+
+        let left_input_schema: ArroyoSchema = config.left_schema.unwrap().try_into()?;
+        let right_input_schema: ArroyoSchema = config.right_schema.unwrap().try_into()?;
+        let left_schema = left_input_schema.schema_without_keys()?;
+        let right_schema = right_input_schema.schema_without_keys()?;
+
+        Ok(OperatorNode::from_operator(Box::new(IntervalJoin {
+            lower_bound: config.lower_bound_micros * 1000,
+            upper_bound: config.upper_bound_micros * 1000,
+            left_input_schema,
+            right_input_schema,
+            left_schema,
+            right_schema,
+            left_passer,
+            right_passer,
+            join_execution_plan,
+        })))
+    }
+}
@@ -0,0 +1,651 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::anyhow;
+use arrow::array::{make_builder, Array, ArrayRef, AsArray, StringArray, UInt32Array};
+use arrow::compute::{cast, concat_batches, take};
+use arrow_array::RecordBatch;
+use arrow_schema::DataType;
+use arroyo_connectors::connectors;
+use arroyo_formats::de::ArrowDeserializer;
+use arroyo_operator::connector::LookupConnector;
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::formats::BadData;
+use arroyo_rpc::grpc::api;
+use arroyo_rpc::OperatorConfig;
+use arroyo_types::{SourceError, UserError};
+use async_trait::async_trait;
+use datafusion_physical_expr::PhysicalExpr;
+use datafusion_proto::physical_plan::from_proto::parse_physical_expr;
+use datafusion_proto::protobuf::PhysicalExprNode;
+use lru::LruCache;
+use prost::Message;
+use tracing::warn;
+
+const MAX_LOOKUP_ATTEMPTS: u32 = 10;
+// used when a cache TTL is configured without a maximum size
+const DEFAULT_CACHE_ENTRIES: usize = 100_000;
+
+/// Where each of the lookup-side columns of the output comes from
+enum LookupColumn {
+    Key,
+    Value(usize),
+}
+
+pub struct LookupJoin {
+    connector: Box<dyn LookupConnector>,
+    key_expression: Arc<dyn PhysicalExpr>,
+    deserializer: ArrowDeserializer,
+    lookup_schema: ArroyoSchema,
+    output_schema: ArroyoSchema,
+    lookup_columns: Vec<LookupColumn>,
+    join_type: api::JoinType,
+    cache: Option<LruCache<String, (Instant, Option<RecordBatch>)>>,
+    ttl: Option<Duration>,
+}
+
+impl LookupJoin {
+    fn cached(&mut self, key: &str) -> Option<Option<RecordBatch>> {
+        let ttl = self.ttl;
+        let cache = self.cache.as_mut()?;
+        let (inserted, value) = cache.get(key)?;
+        if ttl.map(|ttl| inserted.elapsed() <= ttl).unwrap_or(true) {
+            return Some(value.clone());
+        }
+
+        cache.pop(key);
+        None
+    }
+
+    async fn lookup(
+        &mut self,
+        keys: &[String],
+        ctx: &mut ArrowContext,
+    ) -> Result<Vec<Option<Vec<u8>>>, UserError> {
+        let mut attempts = 0;
+        loop {
+            match self.connector.lookup(keys).await {
+                Ok(values) if values.len() == keys.len() => {
+                    return Ok(values);
+                }
+                Ok(values) => {
+                    return Err(UserError::new(
+                        "Lookup failed",
+                        format!(
+                            "{} returned {} values for {} keys",
+                            self.connector.name(),
+                            values.len(),
+                            keys.len()
+                        ),
+                    ));
+                }
+                Err(e) => {
+                    attempts += 1;
+                    if attempts >= MAX_LOOKUP_ATTEMPTS {
+                        return Err(UserError::new(
+                            "Lookup failed",
+                            format!(
+                                "exhausted retries looking up values in {}: {:?}",
+                                self.connector.name(),
+                                e
+                            ),
+                        ));
+                    }
+
+                    ctx.report_error("Lookup failed", format!("{:?}", e)).await;
+                    tokio::time::sleep(Duration::from_millis((50 * (1 << attempts)).min(5_000)))
+                        .await;
+                }
+            }
+        }
+    }
+
+    /// Deserializes a value returned by the connector into a single-row batch, returning None if
+    /// it could not be deserialized and the table's bad data behavior allows it to be skipped
+    async fn deserialize(&mut self, value: &[u8]) -> Result<Option<RecordBatch>, UserError> {
+        let mut builders = self
+            .lookup_schema
+            .schema
+            .fields()
+            .iter()
+            .map(|f| make_builder(f.data_type(), 1))
+            .collect::<Vec<_>>();
+
+        let mut errors = self
+            .deserializer
+            .deserialize_slice(&mut builders, value, SystemTime::now())
+            .await;
+
+        let batch = match self.deserializer.flush_buffer() {
+            Some(Ok(batch)) => Some(batch),
+            Some(Err(e)) => {
+                errors.push(e);
+                None
+            }
+            None => Some(
+                RecordBatch::try_new(
+                    self.lookup_schema.schema.clone(),
+                    builders.into_iter().map(|mut b| b.finish()).collect(),
+                )
+                .unwrap(),
+            ),
+        };
+
+        if let Some(error) = errors.into_iter().next() {
+            match (self.deserializer.bad_data(), &error) {
+                (BadData::Fail {}, _) | (_, SourceError::Other { .. }) => {
+                    return Err(UserError::new(
+                        "Failed to deserialize lookup value",
+                        error.details(),
+                    ));
+                }
+                _ => {
+                    warn!("Dropping invalid lookup value: {}", error.details());
+                    return Ok(None);
+                }
+            }
+        }
+
+        Ok(batch.filter(|b| b.num_rows() > 0).map(|b| b.slice(0, 1)))
+    }
+}
+
+pub struct LookupJoinConstructor;
+
+impl OperatorConstructor for LookupJoinConstructor {
+    type ConfigT = api::LookupJoinOperator;
+
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let join_type = config.join_type();
+        let input_schema: ArroyoSchema = config
+            .input_schema
+            .ok_or_else(|| anyhow!("missing input schema"))?
+            .try_into()?;
+        let lookup_schema: ArroyoSchema = config
+            .lookup_schema
+            .ok_or_else(|| anyhow!("missing lookup schema"))?
+            .try_into()?;
+        let output_schema: ArroyoSchema = config
+            .output_schema
+            .ok_or_else(|| anyhow!("missing output schema"))?
+            .try_into()?;
+
+        let key_expression = PhysicalExprNode::decode(&mut config.key_expression.as_slice())?;
+        let key_expression =
+            parse_physical_expr(&key_expression, registry.as_ref(), &input_schema.schema)?;
+
+        let lookup_columns = output_schema
+            .schema
+            .fields()
+            .iter()
+            .skip(input_schema.schema.fields().len())
+            .map(|f| {
+                if f.name() == &config.key_field {
+                    Ok(LookupColumn::Key)
+                } else {
+                    Ok(LookupColumn::Value(
+                        lookup_schema.schema.index_of(f.name())?,
+                    ))
+                }
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let op = config
+            .connector
+            .ok_or_else(|| anyhow!("missing connector"))?;
+        let operator_config: OperatorConfig = serde_json::from_str(&op.config)
+            .map_err(|e| anyhow!("invalid lookup config for {}: {:?}", op.connector, e))?;
+
+        let deserializer = ArrowDeserializer::new(
+            operator_config
+                .format
+                .clone()
+                .ok_or_else(|| anyhow!("lookup table must have a format"))?,
+            lookup_schema.clone(),
+            operator_config.framing.clone(),
+            operator_config.bad_data.clone().unwrap_or_default(),
+        )?;
+
+        let connector = connectors()
+            .get(op.connector.as_str())
+            .ok_or_else(|| anyhow!("No connector with name '{}'", op.connector))?
+            .make_lookup(operator_config)?;
+
+        let ttl = config.ttl_micros.map(Duration::from_micros);
+        let cache = match (config.max_cache_entries, ttl) {
+            (None, None) => None,
+            (max, _) => Some(LruCache::new(
+                NonZeroUsize::new(max.map(|m| m as usize).unwrap_or(DEFAULT_CACHE_ENTRIES))
+                    .ok_or_else(|| anyhow!("max_cache_entries must be greater than 0"))?,
+            )),
+        };
+
+        Ok(OperatorNode::from_operator(Box::new(LookupJoin {
+            connector,
+            key_expression,
+            deserializer,
+            lookup_schema,
+            output_schema,
+            lookup_columns,
+            join_type,
+            cache,
+            ttl,
+        })))
+    }
+}
+
+#[async_trait]
+impl ArrowOperator for LookupJoin {
+    fn name(&self) -> String {
+        format!("LookupJoin<{}>", self.connector.name())
+    }
+
+    async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        let keys = self
+            .key_expression
+            .evaluate(&batch)
+            .unwrap()
+            .into_array(batch.num_rows())
+            .unwrap();
+        let keys = cast(&keys, &DataType::Utf8).expect("lookup key must be castable to a string");
+        let keys = keys.as_string::<i32>();
+
+        // resolve each distinct key, first from the cache and then from the connector
+        let mut values: HashMap<String, Option<RecordBatch>> = HashMap::new();
+        let mut missing = vec![];
+        for key in keys.iter().flatten() {
+            if values.contains_key(key) {
+                continue;
+            }
+
+            match self.cached(key) {
+                Some(value) => {
+                    values.insert(key.to_string(), value);
+                }
+                None => {
+                    values.insert(key.to_string(), None);
+                    missing.push(key.to_string());
+                }
+            }
+        }
+
+        // a failure fails the task rather than dropping the batch, so that the job restarts from
+        // its last checkpoint and retries the lookups
+        if !missing.is_empty() {
+            let results = match self.lookup(&missing, ctx).await {
+                Ok(results) => results,
+                Err(e) => {
+                    ctx.fail_task(e).await;
+                    return;
+                }
+            };
+
+            for (key, result) in missing.into_iter().zip(results) {
+                let value = match result {
+                    Some(bytes) => match self.deserialize(&bytes).await {
+                        Ok(value) => value,
+                        Err(e) => {
+                            ctx.fail_task(e).await;
+                            return;
+                        }
+                    },
+                    None => None,
+                };
+
+                if let Some(cache) = self.cache.as_mut() {
+                    cache.put(key.clone(), (Instant::now(), value.clone()));
+                }
+                values.insert(key, value);
+            }
+        }
+
+        // gather the found values into a single batch, and compute for each input row the index
+        // of its value in that batch
+        let mut found_keys = vec![];
+        let mut found_values = vec![];
+        let mut indices: HashMap<&str, u32> = HashMap::new();
+        for (key, value) in &values {
+            if let Some(value) = value {
+                indices.insert(key.as_str(), found_values.len() as u32);
+                found_keys.push(key.as_str());
+                found_values.push(value.clone());
+            }
+        }
+
+        let value_indices: Vec<Option<u32>> = keys
+            .iter()
+            .map(|k| k.and_then(|k| indices.get(k).copied()))
+            .collect();
+
+        let (input_columns, value_indices): (Vec<ArrayRef>, UInt32Array) = match self.join_type {
+            api::JoinType::Inner => {
+                let rows: UInt32Array = value_indices
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, v)| v.is_some())
+                    .map(|(i, _)| i as u32)
+                    .collect();
+
+                if rows.is_empty() {
+                    return;
+                }
+
+                (
+                    batch
+                        .columns()
+                        .iter()
+                        .map(|c| take(c, &rows, None).unwrap())
+                        .collect(),
+                    value_indices.into_iter().flatten().map(Some).collect(),
+                )
+            }
+            api::JoinType::Left => (
+                batch.columns().to_vec(),
+                value_indices.into_iter().collect(),
+            ),
+            j => unreachable!("unsupported join type for lookup join {:?}", j),
+        };
+
+        let found_values = concat_batches(&self.lookup_schema.schema, &found_values).unwrap();
+        let found_keys = StringArray::from(found_keys);
+
+        let mut columns = input_columns;
+        for column in &self.lookup_columns {
+            columns.push(match column {
+                LookupColumn::Key => take(&found_keys, &value_indices, None).unwrap(),
+                LookupColumn::Value(i) => {
+                    take(found_values.column(*i), &value_indices, None).unwrap()
+                }
+            });
+        }
+
+        ctx.collect(RecordBatch::try_new(self.output_schema.schema.clone(), columns).unwrap())
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use arrow::array::TimestampNanosecondArray;
+    use arrow_schema::{Field, Schema, TimeUnit};
+    use arroyo_operator::context::{batch_bounded, BatchReceiver};
+    use arroyo_rpc::formats::{Format, JsonFormat};
+    use arroyo_rpc::ControlResp;
+    use arroyo_types::{get_test_task_info, ArrowMessage};
+    use datafusion_physical_expr::expressions::Column;
+    use tokio::sync::mpsc::{channel, Receiver};
+
+    use super::*;
+
+    struct TestLookup {
+        values: HashMap<String, Vec<u8>>,
+        requests: Arc<Mutex<Vec<Vec<String>>>>,
+        extra_value: bool,
+    }
+
+    #[async_trait]
+    impl LookupConnector for TestLookup {
+        fn name(&self) -> String {
+            "TestLookup".to_string()
+        }
+
+        async fn lookup(&mut self, keys: &[String]) -> anyhow::Result<Vec<Option<Vec<u8>>>> {
+            self.requests.lock().unwrap().push(keys.to_vec());
+            let mut values: Vec<_> = keys.iter().map(|k| self.values.get(k).cloned()).collect();
+            if self.extra_value {
+                values.push(None);
+            }
+            Ok(values)
+        }
+    }
+
+    fn timestamp_field() -> Field {
+        Field::new(
+            "_timestamp",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        )
+    }
+
+    fn input_schema() -> ArroyoSchema {
+        ArroyoSchema::new_unkeyed(
+            Arc::new(Schema::new(vec![
+                Field::new("key", DataType::Utf8, true),
+                timestamp_field(),
+            ])),
+            1,
+        )
+    }
+
+    fn output_schema() -> ArroyoSchema {
+        ArroyoSchema::new_unkeyed(
+            Arc::new(Schema::new(vec![
+                Field::new("key", DataType::Utf8, true),
+                timestamp_field(),
+                Field::new("lookup_key", DataType::Utf8, true),
+                Field::new("value", DataType::Utf8, true),
+            ])),
+            1,
+        )
+    }
+
+    fn lookup_join(
+        join_type: api::JoinType,
+        max_cache_entries: Option<usize>,
+        ttl: Option<Duration>,
+    ) -> (LookupJoin, Arc<Mutex<Vec<Vec<String>>>>) {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let connector = TestLookup {
+            values: HashMap::from([
+                ("a".to_string(), br#"{"value": "x"}"#.to_vec()),
+                ("c".to_string(), br#"{"value": "z"}"#.to_vec()),
+                ("bad".to_string(), b"not json".to_vec()),
+            ]),
+            requests: requests.clone(),
+            extra_value: false,
+        };
+
+        let lookup_schema = ArroyoSchema::new_unkeyed(
+            Arc::new(Schema::new(vec![
+                Field::new("value", DataType::Utf8, true),
+                timestamp_field(),
+            ])),
+            1,
+        );
+
+        let cache = (max_cache_entries.is_some() || ttl.is_some()).then(|| {
+            LruCache::new(
+                NonZeroUsize::new(max_cache_entries.unwrap_or(DEFAULT_CACHE_ENTRIES)).unwrap(),
+            )
+        });
+
+        let join = LookupJoin {
+            connector: Box::new(connector),
+            key_expression: Arc::new(Column::new("key", 0)),
+            deserializer: ArrowDeserializer::new(
+                Format::Json(JsonFormat::default()),
+                lookup_schema.clone(),
+                None,
+                BadData::Fail {},
+            )
+            .unwrap(),
+            lookup_schema,
+            output_schema: output_schema(),
+            lookup_columns: vec![LookupColumn::Key, LookupColumn::Value(0)],
+            join_type,
+            cache,
+            ttl,
+        };
+
+        (join, requests)
+    }
+
+    async fn test_context() -> (ArrowContext, BatchReceiver, Receiver<ControlResp>) {
+        let (_, control_rx) = channel(128);
+        let (command_tx, command_rx) = channel(128);
+        let (out_tx, out_rx) = batch_bounded(128);
+
+        let ctx = ArrowContext::new(
+            get_test_task_info(),
+            None,
+            control_rx,
+            command_tx,
+            1,
+            vec![input_schema()],
+            Some(output_schema()),
+            None,
+            vec![vec![out_tx]],
+            HashMap::new(),
+        )
+        .await;
+
+        (ctx, out_rx, command_rx)
+    }
+
+    fn batch(keys: &[Option<&str>]) -> RecordBatch {
+        RecordBatch::try_new(
+            input_schema().schema,
+            vec![
+                Arc::new(StringArray::from(keys.to_vec())),
+                Arc::new(TimestampNanosecondArray::from(vec![0; keys.len()])),
+            ],
+        )
+        .unwrap()
+    }
+
+    async fn next_batch(out_rx: &mut BatchReceiver) -> Option<RecordBatch> {
+        match tokio::time::timeout(Duration::from_millis(100), out_rx.recv()).await {
+            Ok(Some(ArrowMessage::Data(batch))) => Some(batch),
+            Ok(Some(message)) => panic!("unexpected message {:?}", message),
+            _ => None,
+        }
+    }
+
+    fn strings(batch: &RecordBatch, column: usize) -> Vec<Option<&str>> {
+        batch.column(column).as_string::<i32>().iter().collect()
+    }
+
+    #[tokio::test]
+    async fn test_left_join() {
+        let (mut join, requests) = lookup_join(api::JoinType::Left, None, None);
+        let (mut ctx, mut out_rx, _command_rx) = test_context().await;
+
+        join.process_batch(batch(&[Some("a"), Some("b"), None, Some("a")]), &mut ctx)
+            .await;
+
+        // each distinct key is only looked up once
+        assert_eq!(*requests.lock().unwrap(), vec![vec!["a", "b"]]);
+
+        let output = next_batch(&mut out_rx).await.unwrap();
+        assert_eq!(strings(&output, 0), [Some("a"), Some("b"), None, Some("a")]);
+        assert_eq!(strings(&output, 2), [Some("a"), None, None, Some("a")]);
+        assert_eq!(strings(&output, 3), [Some("x"), None, None, Some("x")]);
+    }
+
+    #[tokio::test]
+    async fn test_inner_join() {
+        let (mut join, requests) = lookup_join(api::JoinType::Inner, None, None);
+        let (mut ctx, mut out_rx, _command_rx) = test_context().await;
+
+        join.process_batch(
+            batch(&[Some("a"), Some("b"), None, Some("c"), Some("a")]),
+            &mut ctx,
+        )
+        .await;
+        assert_eq!(*requests.lock().unwrap(), vec![vec!["a", "b", "c"]]);
+
+        let output = next_batch(&mut out_rx).await.unwrap();
+        assert_eq!(strings(&output, 0), [Some("a"), Some("c"), Some("a")]);
+        assert_eq!(strings(&output, 2), [Some("a"), Some("c"), Some("a")]);
+        assert_eq!(strings(&output, 3), [Some("x"), Some("z"), Some("x")]);
+
+        // no rows are emitted when none of the keys are found
+        join.process_batch(batch(&[Some("b"), None]), &mut ctx)
+            .await;
+        assert!(next_batch(&mut out_rx).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cache() {
+        let (mut join, requests) = lookup_join(api::JoinType::Left, Some(2), None);
+        let (mut ctx, mut out_rx, _command_rx) = test_context().await;
+
+        // missing keys are cached as well as found ones
+        for keys in [&["a", "b"][..], &["a", "b"], &["c"], &["a", "b"]] {
+            let keys: Vec<_> = keys.iter().map(|k| Some(*k)).collect();
+            join.process_batch(batch(&keys), &mut ctx).await;
+            assert!(next_batch(&mut out_rx).await.is_some());
+        }
+
+        // inserting c evicts a, the least recently used key
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![vec!["a", "b"], vec!["c"], vec!["a"]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl() {
+        let (mut join, requests) =
+            lookup_join(api::JoinType::Left, None, Some(Duration::from_millis(50)));
+        let (mut ctx, _out_rx, _command_rx) = test_context().await;
+
+        join.process_batch(batch(&[Some("a")]), &mut ctx).await;
+        join.process_batch(batch(&[Some("a")]), &mut ctx).await;
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        join.process_batch(batch(&[Some("a")]), &mut ctx).await;
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    async fn assert_task_failed(command_rx: &mut Receiver<ControlResp>, expected: &str) {
+        let Some(ControlResp::Error { message, .. }) = command_rx.recv().await else {
+            panic!("expected an error to be reported");
+        };
+        assert_eq!(message, expected);
+        assert!(matches!(
+            command_rx.recv().await,
+            Some(ControlResp::TaskFailed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_value_fails_task() {
+        let (mut join, _) = lookup_join(api::JoinType::Left, None, None);
+        let (mut ctx, mut out_rx, mut command_rx) = test_context().await;
+
+        join.process_batch(batch(&[Some("a"), Some("bad")]), &mut ctx)
+            .await;
+
+        assert_task_failed(&mut command_rx, "Failed to deserialize lookup value").await;
+        assert!(next_batch(&mut out_rx).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wrong_number_of_values_fails_task() {
+        let (mut join, _) = lookup_join(api::JoinType::Left, None, None);
+        let requests = Arc::new(Mutex::new(vec![]));
+        join.connector = Box::new(TestLookup {
+            values: HashMap::new(),
+            requests,
+            extra_value: true,
+        });
+        let (mut ctx, mut out_rx, mut command_rx) = test_context().await;
+
+        join.process_batch(batch(&[Some("a")]), &mut ctx).await;
+
+        assert_task_failed(&mut command_rx, "Lookup failed").await;
+        assert!(next_batch(&mut out_rx).await.is_none());
+    }
+}
@@ -22,11 +22,14 @@ use std::sync::Arc;
 use std::sync::RwLock;
 
 pub mod instant_join;
+pub mod interval_join;
 pub mod join_with_expiration;
+pub mod lookup_join;
 pub mod session_aggregating_window;
 pub mod sliding_aggregating_window;
 pub(crate) mod sync;
 pub mod tumbling_aggregating_window;
+pub mod updating_aggregate;
 pub mod window_fn;
 
 pub struct ValueExecutionOperator {
@@ -0,0 +1,594 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, bail, Result};
+use arrow::compute::{cast, filter, take};
+use arrow::row::{RowConverter, SortField};
+use arrow_array::cast::AsArray;
+use arrow_array::types::TimestampNanosecondType;
+use arrow_array::{
+    ArrayRef, BooleanArray, RecordBatch, StructArray, TimestampNanosecondArray, UInt32Array,
+    UInt64Array,
+};
+use arroyo_df::physical::{ArroyoPhysicalExtensionCodec, DecodingContext};
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::grpc::{api, TableConfig};
+use arroyo_rpc::{get_hasher, updating_meta_fields};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{from_nanos, to_nanos, CheckpointBarrier, SignalMessage};
+use datafusion_common::ScalarValue;
+use datafusion_execution::runtime_env::{RuntimeConfig, RuntimeEnv};
+use datafusion_expr::Accumulator;
+use datafusion_physical_expr::{AggregateExpr, PhysicalExpr};
+use datafusion_physical_plan::aggregates::AggregateExec;
+use datafusion_proto::physical_plan::AsExecutionPlan;
+use datafusion_proto::protobuf::PhysicalPlanNode;
+use prost::Message;
+use tracing::info;
+
+// how often updated groups are emitted
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+// how many flushes between checks for expired groups
+const EXPIRATION_TICKS: u64 = 10;
+
+struct Group {
+    key: Vec<ScalarValue>,
+    accumulators: Vec<Box<dyn Accumulator>>,
+    // the row most recently emitted for this group, which will be retracted when it changes
+    emitted: Option<Vec<ScalarValue>>,
+    // the latest event time of the data in this group
+    event_time: SystemTime,
+    // the processing time at which this group was last updated, used for expiration
+    updated_at: SystemTime,
+}
+
+struct OutputRow {
+    is_retract: bool,
+    id: u64,
+    values: Vec<ScalarValue>,
+    timestamp: SystemTime,
+}
+
+pub struct UpdatingAggregatingFunc {
+    group_exprs: Vec<Arc<dyn PhysicalExpr>>,
+    aggregates: Vec<Arc<dyn AggregateExpr>>,
+    filters: Vec<Option<Arc<dyn PhysicalExpr>>>,
+    // the columns of the state schema holding the accumulator state of each aggregate
+    state_ranges: Vec<Range<usize>>,
+    // None if there's no GROUP BY, in which case there's a single group
+    converter: Option<RowConverter>,
+    input_schema: ArroyoSchema,
+    state_schema: ArroyoSchema,
+    output_schema: ArroyoSchema,
+    ttl: Duration,
+    groups: HashMap<Vec<u8>, Group>,
+    // groups that have been updated since they were last emitted
+    dirty: HashSet<Vec<u8>>,
+    // groups that have been updated since the last checkpoint
+    changed: HashSet<Vec<u8>>,
+}
+
+impl UpdatingAggregatingFunc {
+    fn keys(&self, columns: &[ArrayRef], num_rows: usize) -> Result<Vec<Vec<u8>>> {
+        match &self.converter {
+            Some(converter) => Ok(converter
+                .convert_columns(columns)?
+                .iter()
+                .map(|row| row.as_ref().to_vec())
+                .collect()),
+            None => Ok(vec![vec![]; num_rows]),
+        }
+    }
+
+    fn new_accumulators(&self) -> Result<Vec<Box<dyn Accumulator>>> {
+        Ok(self
+            .aggregates
+            .iter()
+            .map(|aggregate| aggregate.create_accumulator())
+            .collect::<datafusion_common::Result<_>>()?)
+    }
+
+    fn update(&mut self, batch: &RecordBatch) -> Result<()> {
+        let num_rows = batch.num_rows();
+        let group_columns = self
+            .group_exprs
+            .iter()
+            .map(|expr| expr.evaluate(batch)?.into_array(num_rows))
+            .collect::<datafusion_common::Result<Vec<_>>>()?;
+        let keys = self.keys(&group_columns, num_rows)?;
+
+        // evaluate the arguments (and FILTER clauses) of the aggregates once for the whole batch
+        let arguments = self
+            .aggregates
+            .iter()
+            .map(|aggregate| {
+                aggregate
+                    .expressions()
+                    .iter()
+                    .map(|expr| expr.evaluate(batch)?.into_array(num_rows))
+                    .collect::<datafusion_common::Result<Vec<_>>>()
+            })
+            .collect::<datafusion_common::Result<Vec<_>>>()?;
+        let filters = self
+            .filters
+            .iter()
+            .map(|expr| {
+                expr.as_ref()
+                    .map(|expr| expr.evaluate(batch)?.into_array(num_rows))
+                    .transpose()
+            })
+            .collect::<datafusion_common::Result<Vec<_>>>()?;
+
+        let timestamps = batch
+            .column(self.input_schema.timestamp_index)
+            .as_primitive::<TimestampNanosecondType>();
+
+        let mut rows_by_key: HashMap<&Vec<u8>, Vec<u32>> = HashMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            rows_by_key.entry(key).or_default().push(i as u32);
+        }
+
+        let now = SystemTime::now();
+        for (key, rows) in rows_by_key {
+            let event_time = rows
+                .iter()
+                .map(|i| timestamps.value(*i as usize))
+                .max()
+                .map(|nanos| from_nanos(nanos as u128))
+                .unwrap();
+            let rows = UInt32Array::from(rows);
+
+            let group = match self.groups.entry(key.clone()) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    let first = rows.value(0) as usize;
+                    let key = group_columns
+                        .iter()
+                        .map(|c| ScalarValue::try_from_array(c, first))
+                        .collect::<datafusion_common::Result<_>>()?;
+                    let accumulators = self
+                        .aggregates
+                        .iter()
+                        .map(|aggregate| aggregate.create_accumulator())
+                        .collect::<datafusion_common::Result<_>>()?;
+
+                    e.insert(Group {
+                        key,
+                        accumulators,
+                        emitted: None,
+                        event_time,
+                        updated_at: now,
+                    })
+                }
+            };
+
+            for ((accumulator, arguments), filter_values) in
+                group.accumulators.iter_mut().zip(&arguments).zip(&filters)
+            {
+                let mut values = arguments
+                    .iter()
+                    .map(|a| take(a, &rows, None))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if let Some(filter_values) = filter_values {
+                    let predicate = take(filter_values, &rows, None)?;
+                    let predicate = predicate.as_boolean();
+                    values = values
+                        .iter()
+                        .map(|v| filter(v, predicate))
+                        .collect::<Result<Vec<_>, _>>()?;
+                }
+
+                accumulator.update_batch(&values)?;
+            }
+
+            group.event_time = group.event_time.max(event_time);
+            group.updated_at = now;
+            self.dirty.insert(key.clone());
+            self.changed.insert(key.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Computes the current value of each group that has been updated since it was last emitted,
+    /// retracting the previously emitted row for the group if there was one
+    fn updates(&mut self) -> Result<Vec<OutputRow>> {
+        let hasher = get_hasher();
+        let mut output = vec![];
+        for key in self.dirty.drain() {
+            let Some(group) = self.groups.get_mut(&key) else {
+                continue;
+            };
+
+            let mut row = group.key.clone();
+            for accumulator in group.accumulators.iter_mut() {
+                row.push(accumulator.evaluate()?);
+            }
+
+            if group.emitted.as_ref() == Some(&row) {
+                continue;
+            }
+
+            let id = hasher.hash_one(&key);
+            if let Some(previous) = group.emitted.replace(row.clone()) {
+                output.push(OutputRow {
+                    is_retract: true,
+                    id,
+                    values: previous,
+                    timestamp: group.event_time,
+                });
+            }
+
+            output.push(OutputRow {
+                is_retract: false,
+                id,
+                values: row,
+                timestamp: group.event_time,
+            });
+        }
+
+        Ok(output)
+    }
+
+    /// Removes the groups that haven't been updated within the TTL, retracting their rows
+    fn expire(&mut self) -> Vec<OutputRow> {
+        let now = SystemTime::now();
+        let ttl = self.ttl;
+        let expired: Vec<_> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| now.duration_since(group.updated_at).unwrap_or_default() > ttl)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let hasher = get_hasher();
+        let mut output = vec![];
+        for key in expired {
+            let group = self.groups.remove(&key).unwrap();
+            self.dirty.remove(&key);
+            self.changed.remove(&key);
+
+            if let Some(emitted) = group.emitted {
+                output.push(OutputRow {
+                    is_retract: true,
+                    id: hasher.hash_one(&key),
+                    values: emitted,
+                    timestamp: group.event_time,
+                });
+            }
+        }
+
+        if !output.is_empty() {
+            info!("expired {} groups from updating aggregate", output.len());
+        }
+
+        output
+    }
+
+    /// Builds a batch from rows of scalars, casting each column to the type in the schema
+    fn columns(rows: &[Vec<ScalarValue>], schema: &ArroyoSchema) -> Result<Vec<ArrayRef>> {
+        (0..rows[0].len())
+            .map(|i| {
+                let array = ScalarValue::iter_to_array(rows.iter().map(|row| row[i].clone()))?;
+                let data_type = schema.schema.field(i).data_type();
+                Ok(if array.data_type() != data_type {
+                    cast(&array, data_type)?
+                } else {
+                    array
+                })
+            })
+            .collect()
+    }
+
+    fn output_batch(&self, rows: Vec<OutputRow>) -> Result<RecordBatch> {
+        let values: Vec<_> = rows.iter().map(|row| row.values.clone()).collect();
+        let mut columns = Self::columns(&values, &self.output_schema)?;
+
+        columns.push(Arc::new(StructArray::try_new(
+            updating_meta_fields(),
+            vec![
+                Arc::new(BooleanArray::from(
+                    rows.iter().map(|row| row.is_retract).collect::<Vec<_>>(),
+                )),
+                Arc::new(UInt64Array::from(
+                    rows.iter().map(|row| row.id).collect::<Vec<_>>(),
+                )),
+            ],
+            None,
+        )?));
+        columns.push(Arc::new(TimestampNanosecondArray::from(
+            rows.iter()
+                .map(|row| to_nanos(row.timestamp) as i64)
+                .collect::<Vec<_>>(),
+        )));
+
+        Ok(RecordBatch::try_new(
+            self.output_schema.schema.clone(),
+            columns,
+        )?)
+    }
+
+    async fn emit(&mut self, rows: Vec<OutputRow>, ctx: &mut ArrowContext) {
+        if rows.is_empty() {
+            return;
+        }
+
+        let batch = self
+            .output_batch(rows)
+            .expect("failed to build updating aggregate output");
+        ctx.collect(batch).await;
+    }
+
+    async fn flush(&mut self, ctx: &mut ArrowContext) {
+        let updates = self
+            .updates()
+            .expect("failed to compute updating aggregate");
+        self.emit(updates, ctx).await;
+    }
+
+    /// A batch of the state of all groups that have changed since the last checkpoint
+    fn state_batch(&mut self) -> Result<Option<(SystemTime, RecordBatch)>> {
+        let mut rows = vec![];
+        let mut max_updated_at = SystemTime::UNIX_EPOCH;
+        for key in self.changed.drain() {
+            let Some(group) = self.groups.get_mut(&key) else {
+                continue;
+            };
+
+            let mut row = group.key.clone();
+            for accumulator in group.accumulators.iter_mut() {
+                row.extend(accumulator.state()?);
+            }
+            row.push(ScalarValue::TimestampNanosecond(
+                Some(to_nanos(group.updated_at) as i64),
+                None,
+            ));
+            max_updated_at = max_updated_at.max(group.updated_at);
+            rows.push(row);
+        }
+
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let columns = Self::columns(&rows, &self.state_schema)?;
+        Ok(Some((
+            max_updated_at,
+            RecordBatch::try_new(self.state_schema.schema.clone(), columns)?,
+        )))
+    }
+
+    fn restore(&mut self, batches: Vec<RecordBatch>) -> Result<()> {
+        let key_count = self.group_exprs.len();
+
+        // groups may have been written in several checkpoints; the latest state wins
+        let mut latest: HashMap<Vec<u8>, (SystemTime, RecordBatch)> = HashMap::new();
+        for batch in batches {
+            let keys = self.keys(&batch.columns()[..key_count], batch.num_rows())?;
+            let updated_at = batch
+                .column(self.state_schema.timestamp_index)
+                .as_primitive::<TimestampNanosecondType>();
+
+            for (i, key) in keys.into_iter().enumerate() {
+                let time = from_nanos(updated_at.value(i) as u128);
+                if latest.get(&key).map(|(t, _)| *t <= time).unwrap_or(true) {
+                    latest.insert(key, (time, batch.slice(i, 1)));
+                }
+            }
+        }
+
+        let now = SystemTime::now();
+        for (key, (updated_at, row)) in latest {
+            if now.duration_since(updated_at).unwrap_or_default() > self.ttl {
+                // this group was already retracted when it expired
+                continue;
+            }
+
+            let mut accumulators = self.new_accumulators()?;
+            for (accumulator, range) in accumulators.iter_mut().zip(&self.state_ranges) {
+                accumulator.merge_batch(&row.columns()[range.clone()])?;
+            }
+
+            let key_values = row.columns()[..key_count]
+                .iter()
+                .map(|c| ScalarValue::try_from_array(c, 0))
+                .collect::<datafusion_common::Result<Vec<_>>>()?;
+
+            // we flush before checkpointing, so the current value is what was last emitted
+            let mut emitted = key_values.clone();
+            for accumulator in accumulators.iter_mut() {
+                emitted.push(accumulator.evaluate()?);
+            }
+
+            self.groups.insert(
+                key,
+                Group {
+                    key: key_values,
+                    accumulators,
+                    emitted: Some(emitted),
+                    event_time: updated_at,
+                    updated_at,
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub struct UpdatingAggregatingConstructor;
+
+impl OperatorConstructor for UpdatingAggregatingConstructor {
+    type ConfigT = api::UpdatingAggregateOperator;
+
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let input_schema: ArroyoSchema = config
+            .input_schema
+            .ok_or_else(|| anyhow!("requires input schema"))?
+            .try_into()?;
+        let state_schema: ArroyoSchema = config
+            .state_schema
+            .ok_or_else(|| anyhow!("requires state schema"))?
+            .try_into()?;
+        let output_schema: ArroyoSchema = config
+            .output_schema
+            .ok_or_else(|| anyhow!("requires output schema"))?
+            .try_into()?;
+
+        // we only use the expressions of the aggregation, so it doesn't need a real input
+        let codec = ArroyoPhysicalExtensionCodec {
+            context: DecodingContext::Planning,
+        };
+        let plan = PhysicalPlanNode::decode(&mut config.aggregate_plan.as_slice())?
+            .try_into_physical_plan(
+                registry.as_ref(),
+                &RuntimeEnv::new(RuntimeConfig::new())?,
+                &codec,
+            )?;
+        let Some(aggregate) = plan.as_any().downcast_ref::<AggregateExec>() else {
+            bail!("updating aggregate plan is not an aggregate");
+        };
+
+        if !aggregate.group_expr().is_single() {
+            bail!("grouping sets are not supported in updating aggregates");
+        }
+
+        let group_exprs: Vec<_> = aggregate
+            .group_expr()
+            .expr()
+            .iter()
+            .map(|(expr, _)| expr.clone())
+            .collect();
+
+        let converter = if group_exprs.is_empty() {
+            None
+        } else {
+            Some(RowConverter::new(
+                group_exprs
+                    .iter()
+                    .map(|expr| Ok(SortField::new(expr.data_type(&input_schema.schema)?)))
+                    .collect::<anyhow::Result<_>>()?,
+            )?)
+        };
+
+        let mut state_ranges = vec![];
+        let mut offset = group_exprs.len();
+        for aggregate in aggregate.aggr_expr() {
+            let count = aggregate.state_fields()?.len();
+            state_ranges.push(offset..offset + count);
+            offset += count;
+        }
+
+        Ok(OperatorNode::from_operator(Box::new(
+            UpdatingAggregatingFunc {
+                group_exprs,
+                aggregates: aggregate.aggr_expr().to_vec(),
+                filters: aggregate.filter_expr().to_vec(),
+                state_ranges,
+                converter,
+                input_schema,
+                state_schema,
+                output_schema,
+                ttl: Duration::from_micros(config.ttl_micros),
+                groups: HashMap::new(),
+                dirty: HashSet::new(),
+                changed: HashSet::new(),
+            },
+        )))
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for UpdatingAggregatingFunc {
+    fn name(&self) -> String {
+        "UpdatingAggregate".to_string()
+    }
+
+    fn tick_interval(&self) -> Option<Duration> {
+        Some(FLUSH_INTERVAL)
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        // state is timestamped with processing time, so it's expired relative to the current time
+        // rather than the watermark
+        let now = Some(SystemTime::now());
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table("u", now)
+            .await
+            .expect("should be able to load table");
+
+        let batches: Vec<_> = table
+            .all_batches_for_watermark(now)
+            .flat_map(|(_, batches)| batches.iter().cloned())
+            .collect();
+
+        self.restore(batches)
+            .expect("failed to restore updating aggregate state");
+    }
+
+    async fn process_batch(&mut self, batch: RecordBatch, _: &mut ArrowContext) {
+        self.update(&batch)
+            .expect("failed to update updating aggregate");
+    }
+
+    async fn handle_tick(&mut self, tick: u64, ctx: &mut ArrowContext) {
+        self.flush(ctx).await;
+
+        if tick % EXPIRATION_TICKS == 0 {
+            let retractions = self.expire();
+            self.emit(retractions, ctx).await;
+        }
+    }
+
+    async fn handle_checkpoint(&mut self, _: CheckpointBarrier, ctx: &mut ArrowContext) {
+        // emit everything before checkpointing, so that on restore the emitted value of each
+        // group can be recomputed from its state
+        self.flush(ctx).await;
+
+        let state = self
+            .state_batch()
+            .expect("failed to compute updating aggregate state");
+
+        let now = Some(SystemTime::now());
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table("u", now)
+            .await
+            .expect("should get table");
+
+        if let Some((updated_at, batch)) = state {
+            table.insert(updated_at, batch);
+        }
+        table.flush(now).await.unwrap();
+    }
+
+    async fn on_close(&mut self, _: &Option<SignalMessage>, ctx: &mut ArrowContext) {
+        self.flush(ctx).await;
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        vec![(
+            "u".to_string(),
+            timestamp_table_config(
+                "u",
+                "updating aggregate state",
+                self.ttl,
+                self.state_schema.clone(),
+            ),
+        )]
+        .into_iter()
+        .collect()
+    }
+}
@@ -1,11 +1,11 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
 use std::sync::{Arc, RwLock};
 use std::{mem, thread};
 
 use std::time::SystemTime;
 
-use arroyo_connectors::connectors;
+use arroyo_connectors::{connectors, register_dead_letter_sinks};
 use arroyo_df::physical::new_registry;
 use arroyo_rpc::df::ArroyoSchema;
 use bincode::{Decode, Encode};
@@ -14,13 +14,17 @@ use futures::StreamExt;
 use tracing::{debug, info, warn};
 
 use crate::arrow::instant_join::InstantJoinConstructor;
+use crate::arrow::interval_join::IntervalJoinConstructor;
 use crate::arrow::join_with_expiration::JoinWithExpirationConstructor;
+use crate::arrow::lookup_join::LookupJoinConstructor;
 use crate::arrow::session_aggregating_window::SessionAggregatingWindowConstructor;
 use crate::arrow::sliding_aggregating_window::SlidingAggregatingWindowConstructor;
 use crate::arrow::tumbling_aggregating_window::TumblingAggregateWindowConstructor;
+use crate::arrow::updating_aggregate::UpdatingAggregatingConstructor;
 use crate::arrow::window_fn::WindowFunctionConstructor;
 use crate::arrow::{KeyExecutionConstructor, ValueExecutionConstructor};
 use crate::network_manager::{NetworkManager, Quad, Senders};
+use crate::operators::async_map::AsyncMapConstructor;
 use crate::operators::watermark_generator::WatermarkGeneratorConstructor;
 use crate::{METRICS_PUSH_INTERVAL, PROMETHEUS_PUSH_GATEWAY};
 use arroyo_datastream::logical::{
@@ -248,6 +252,27 @@ impl Program {
 
         let queue_size = u32_config(QUEUE_SIZE_ENV, DEFAULT_QUEUE_SIZE);
 
+        // join operators tell their sides apart by splitting their inputs in half, so both sides
+        // must have the same parallelism
+        for idx in logical.node_indices() {
+            let join_inputs: HashSet<_> = logical
+                .edges_directed(idx, Direction::Incoming)
+                .filter(|e| {
+                    matches!(
+                        e.weight().edge_type,
+                        LogicalEdgeType::LeftJoin | LogicalEdgeType::RightJoin
+                    )
+                })
+                .map(|e| logical[e.source()].parallelism)
+                .collect();
+            assert!(
+                join_inputs.len() <= 1,
+                "inputs to join {} have different parallelism: {:?}",
+                logical[idx].operator_id,
+                join_inputs
+            );
+        }
+
         for idx in logical.edge_indices() {
             let edge = logical.edge_weight(idx).unwrap();
             let (logical_in_node_idx, logical_out_node_idx) = logical.edge_endpoints(idx).unwrap();
@@ -266,10 +291,9 @@ impl Program {
             assert_ne!(from_nodes.len(), 0, "failed to find to nodes");
 
             match edge.edge_type {
-                LogicalEdgeType::Forward => {
-                    if from_nodes.len() != to_nodes.len() && !from_nodes.is_empty() {
-                        panic!("cannot create a forward connection between nodes of different parallelism");
-                    }
+                // forward edges connect subtasks one-to-one, which is only possible when both sides
+                // have the same parallelism; otherwise they are wired up like a shuffle
+                LogicalEdgeType::Forward if from_nodes.len() == to_nodes.len() => {
                     for (f, t) in from_nodes.iter().zip(&to_nodes) {
                         let (tx, rx) = batch_bounded(queue_size);
                         let edge = PhysicalGraphEdge {
@@ -284,7 +308,8 @@ impl Program {
                         physical.add_edge(*f, *t, edge);
                     }
                 }
-                LogicalEdgeType::Shuffle
+                LogicalEdgeType::Forward
+                | LogicalEdgeType::Shuffle
                 | LogicalEdgeType::LeftJoin
                 | LogicalEdgeType::RightJoin => {
                     for f in &from_nodes {
@@ -469,6 +494,8 @@ impl Engine {
     pub async fn start(mut self, config: StreamConfig) -> (RunningEngine, Receiver<ControlResp>) {
         info!("Starting job {}", self.job_id);
 
+        register_dead_letter_sinks();
+
         let checkpoint_metadata = if let Some(epoch) = config.restore_epoch {
             info!("Restoring checkpoint {} for job {}", epoch, self.job_id);
             Some(
@@ -803,9 +830,13 @@ pub fn construct_operator(
         OperatorName::TumblingWindowAggregate => Box::new(TumblingAggregateWindowConstructor),
         OperatorName::SlidingWindowAggregate => Box::new(SlidingAggregatingWindowConstructor),
         OperatorName::SessionWindowAggregate => Box::new(SessionAggregatingWindowConstructor),
+        OperatorName::UpdatingAggregate => Box::new(UpdatingAggregatingConstructor),
         OperatorName::ExpressionWatermark => Box::new(WatermarkGeneratorConstructor),
         OperatorName::Join => Box::new(JoinWithExpirationConstructor),
         OperatorName::InstantJoin => Box::new(InstantJoinConstructor),
+        OperatorName::IntervalJoin => Box::new(IntervalJoinConstructor),
+        OperatorName::LookupJoin => Box::new(LookupJoinConstructor),
+        OperatorName::AsyncUdf => Box::new(AsyncMapConstructor),
         OperatorName::ConnectorSource | OperatorName::ConnectorSink => {
             let op: api::ConnectorOp = prost::Message::decode(&mut config.as_slice()).unwrap();
             return connectors()
@@ -12,8 +12,8 @@ use arroyo_rpc::grpc::{
     api, CheckpointReq, CheckpointResp, CommitReq, CommitResp, HeartbeatReq, JobFinishedReq,
     JobFinishedResp, LoadCompactedDataReq, LoadCompactedDataRes, RegisterWorkerReq,
     StartExecutionReq, StartExecutionResp, StopExecutionReq, StopExecutionResp,
-    TaskCheckpointCompletedReq, TaskCheckpointEventReq, TaskFailedReq, TaskFinishedReq,
-    TaskStartedReq, WorkerErrorReq, WorkerResources,
+    SubtaskMetricValues, TaskCheckpointCompletedReq, TaskCheckpointEventReq, TaskFailedReq,
+    TaskFinishedReq, TaskStartedReq, WorkerErrorReq, WorkerResources,
 };
 use arroyo_types::{
     default_controller_addr, from_millis, grpc_port, to_micros, CheckpointBarrier, NodeId,
@@ -24,7 +24,7 @@ use rand::random;
 
 use base64::engine::general_purpose;
 use base64::Engine as Base64Engine;
-use datafusion_expr::ScalarUDF;
+use datafusion_expr::{AggregateUDF, ScalarUDF};
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::future::Future;
@@ -43,7 +43,7 @@ pub use ordered_float::OrderedFloat;
 use prost::Message;
 
 use arroyo_datastream::logical::{LogicalGraph, LogicalProgram, ProgramConfig};
-use arroyo_df::physical::{new_registry, UdfDylib};
+use arroyo_df::physical::{new_registry, UdafDylib, UdfDylib};
 use arroyo_server_common::shutdown::ShutdownGuard;
 
 pub mod arrow;
@@ -387,10 +387,20 @@ impl WorkerServer {
                         }
                     }
                     _ = tick.tick() => {
+                        let metrics = arroyo_metrics::task_metrics_snapshot()
+                            .into_iter()
+                            .map(|((operator_id, subtask_index), values)| SubtaskMetricValues {
+                                operator_id,
+                                subtask_index,
+                                values,
+                            })
+                            .collect();
+
                         let result = controller.heartbeat(Request::new(HeartbeatReq {
                             job_id: job_id.clone(),
                             time: to_micros(SystemTime::now()),
                             worker_id: worker_id.0,
+                            metrics,
                         })).await;
                         if let Err(err) = result {
                             error!("heartbeat failed {:?}", err);
@@ -420,9 +430,22 @@ impl WorkerGrpc for WorkerServer {
         }
 
         let req = request.into_inner();
+
+        // secrets must be available before the operators are constructed
+        arroyo_rpc::secrets::install(req.secrets);
+
         let mut registry = new_registry();
 
         for (udf_name, dylib_config) in self.program_config.udf_dylibs.iter() {
+            // async UDFs are loaded by the operators that call them
+            if dylib_config.is_async {
+                continue;
+            }
+            if dylib_config.is_aggregate {
+                let dylib = UdafDylib::init(udf_name, dylib_config).await;
+                registry.add_udaf(Arc::new(AggregateUDF::from(dylib)));
+                continue;
+            }
             let dylib = UdfDylib::init(udf_name, dylib_config).await;
             registry.add_udf(Arc::new(ScalarUDF::from(dylib)));
         }
@@ -1,19 +1,34 @@
-use crate::old::Context;
-use arroyo_macro::{process_fn, StreamNode};
-use arroyo_rpc::grpc::TableDescriptor;
-use arroyo_types::{CheckpointBarrier, Data, Key, UdfContext};
-use arroyo_types::{Message, Record};
+use anyhow::{anyhow, bail};
+use arrow::compute::{cast, concat, concat_batches};
+use arrow::ipc::reader::StreamReader;
+use arrow::ipc::writer::StreamWriter;
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_schema::DataType;
+use arroyo_datastream::logical::DylibUdfConfig;
+use arroyo_df::physical::AsyncUdfDylib;
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::grpc::api::AsyncUdfOperator;
+use arroyo_rpc::grpc::TableConfig;
+use arroyo_state::global_table_config;
+use arroyo_types::{ArrowMessage, CheckpointBarrier, SignalMessage, UserError, Watermark};
 use async_trait::async_trait;
+use datafusion_physical_expr::PhysicalExpr;
+use datafusion_proto::physical_plan::from_proto::parse_physical_expr;
+use datafusion_proto::protobuf::PhysicalExprNode;
 use futures::stream::{FuturesOrdered, FuturesUnordered};
-use futures::StreamExt;
-use std::collections::VecDeque;
+use futures::{FutureExt, StreamExt};
+use prost::Message;
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
 use std::future::Future;
-use std::marker::PhantomData;
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::time::error::Elapsed;
+use std::time::Duration;
+use tokio::sync::Mutex;
 use tracing::{debug, info, warn};
 
-// TODO: port to record batches.
 pub enum FuturesEnum<T>
 where
     T: Future + Send + 'static,
@@ -33,6 +48,18 @@ impl<T> FuturesWrapper<T>
 where
     T: Future + Send + 'static,
 {
+    pub fn new(ordered: bool) -> Self {
+        let futures = if ordered {
+            info!("Using ordered futures");
+            FuturesEnum::Ordered(FuturesOrdered::new())
+        } else {
+            info!("Using unordered futures");
+            FuturesEnum::Unordered(FuturesUnordered::new())
+        };
+
+        Self { futures }
+    }
+
     pub fn push_back(&mut self, f: T) {
         match &mut self.futures {
             FuturesEnum::Ordered(futures) => futures.push_back(f),
@@ -53,6 +80,11 @@ where
             FuturesEnum::Unordered(futures) => futures.len(),
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn is_ordered(&self) -> bool {
         match &self.futures {
             FuturesEnum::Ordered(_) => true,
@@ -61,211 +93,389 @@ where
     }
 }
 
-#[derive(StreamNode)]
-pub struct AsyncMapOperator<
-    InKey: Key,
-    InT: Data,
-    OutT: Data,
-    FutureT: Future<Output = (usize, Result<OutT, Elapsed>)> + Send + 'static,
-    FnT: Fn(usize, InT, Arc<ContextT>) -> FutureT + Send + 'static,
-    ContextT: UdfContext + Send + 'static,
-> {
-    pub name: String,
-
-    pub udf: FnT,
-    pub futures: FuturesWrapper<FutureT>,
-    udf_context: Arc<ContextT>,
-    max_concurrency: u64,
-
-    next_id: usize, // i.e. inputs received so far, should start at 0
-    inputs: VecDeque<Option<Record<InKey, InT>>>,
-    watermarks: VecDeque<(usize, arroyo_types::Watermark)>,
-    _t: PhantomData<(InKey, InT, OutT, ContextT)>,
+type ResultFuture = Pin<Box<dyn Future<Output = (usize, Result<ArrayRef, UserError>)> + Send>>;
+type Results = Vec<(usize, Result<ArrayRef, UserError>)>;
+
+/// Waits for the next call to complete, and returns its result along with those of any other
+/// calls that have already completed
+async fn next_results(futures: Arc<Mutex<FuturesWrapper<ResultFuture>>>) -> Results {
+    let mut futures = futures.lock().await;
+    let mut results = vec![futures
+        .next()
+        .await
+        .expect("should only wait for results when calls are in flight")];
+
+    while let Some(Some(result)) = futures.next().now_or_never() {
+        results.push(result);
+    }
+    results
 }
 
-#[process_fn(in_k = InKey, in_t = InT, out_k = InKey, out_t = OutT, futures = "futures")]
-impl<
-        InKey: Key,
-        InT: Data,
-        OutT: Data,
-        FutureT: Future<Output = (usize, Result<OutT, Elapsed>)> + Send + 'static,
-        FnT: Fn(usize, InT, Arc<ContextT>) -> FutureT + Send + 'static,
-        ContextT: UdfContext + Send + 'static,
-    > AsyncMapOperator<InKey, InT, OutT, FutureT, FnT, ContextT>
-{
-    pub fn new(
-        name: String,
-        udf: FnT,
-        context: ContextT,
-        ordered: bool,
-        max_concurrency: u64,
-    ) -> Self {
-        let futures = if ordered {
-            info!("Using ordered futures");
-            FuturesWrapper {
-                futures: FuturesEnum::Ordered(FuturesOrdered::new()),
-            }
-        } else {
-            info!("Using unordered futures");
-            FuturesWrapper {
-                futures: FuturesEnum::Unordered(FuturesUnordered::new()),
-            }
-        };
+/// Calls an async UDF for each input row, emitting the row with the result appended. Rows whose
+/// results haven't been emitted are written to state on checkpoints, and are sent to the UDF again
+/// on restore.
+pub struct AsyncMapOperator {
+    udf_name: String,
+    udf_config: DylibUdfConfig,
+    udf: Option<Arc<AsyncUdfDylib>>,
+    arg_exprs: Vec<Arc<dyn PhysicalExpr>>,
+    arg_types: Vec<DataType>,
+    input_schema: ArroyoSchema,
+    output_schema: ArroyoSchema,
+    timeout: Duration,
+    retries: u64,
+    max_concurrency: usize,
+    // set once a call has failed and the task has been failed, so that it's only reported once
+    failed: bool,
 
-        Self {
-            name,
-            udf,
-            futures,
-            udf_context: Arc::new(context),
-            max_concurrency,
-            next_id: 0,
-            inputs: VecDeque::new(),
-            watermarks: VecDeque::new(),
-            _t: PhantomData,
-        }
+    futures: Arc<Mutex<FuturesWrapper<ResultFuture>>>,
+    in_flight: usize,
+    // rows are given ids in the order they arrive; this is the id of the first row in `inputs`
+    first_id: usize,
+    // single-row batches for each call, which are set to None once the result has been emitted
+    inputs: VecDeque<Option<RecordBatch>>,
+    // watermarks are held back until all of the rows that arrived before them have been emitted;
+    // each is stored with the id of the first row that arrived after it
+    watermarks: VecDeque<(usize, Watermark)>,
+}
+
+impl AsyncMapOperator {
+    fn next_id(&self) -> usize {
+        self.first_id + self.inputs.len()
     }
 
-    fn name(&self) -> String {
-        self.name.clone()
+    fn evaluate_args(&self, batch: &RecordBatch) -> Vec<ArrayRef> {
+        self.arg_exprs
+            .iter()
+            .zip(&self.arg_types)
+            .map(|(expr, data_type)| {
+                let array = expr
+                    .evaluate(batch)
+                    .unwrap()
+                    .into_array(batch.num_rows())
+                    .unwrap();
+                cast(&array, data_type).expect("async UDF argument has the wrong type")
+            })
+            .collect()
     }
 
-    async fn on_start(&mut self, ctx: &mut Context<InKey, OutT>) {
-        self.udf_context.init().await;
+    fn call(
+        udf: Arc<AsyncUdfDylib>,
+        id: usize,
+        args: Vec<ArrayRef>,
+        timeout: Duration,
+        retries: u64,
+    ) -> ResultFuture {
+        Box::pin(async move {
+            for attempt in 0..=retries {
+                match udf.call(&args, timeout).await {
+                    Ok(Some(result)) => return (id, Ok(result)),
+                    Ok(None) => {
+                        warn!(
+                            "Async UDF {} timed out after {:?} (attempt {} of {})",
+                            udf.name(),
+                            timeout,
+                            attempt + 1,
+                            retries + 1
+                        );
+                    }
+                    Err(_) => {
+                        return (
+                            id,
+                            Err(UserError::new(
+                                "Async UDF failed",
+                                format!("{} dropped a call without returning a result", udf.name()),
+                            )),
+                        );
+                    }
+                }
+            }
 
-        let gs = ctx
-            .state
-            .get_global_keyed_state::<(usize, usize), Record<InKey, InT>>('a')
-            .await;
+            (
+                id,
+                Err(UserError::new(
+                    "Async UDF timed out",
+                    format!(
+                        "{} did not return a result within {:?} after {} attempts",
+                        udf.name(),
+                        timeout,
+                        retries + 1
+                    ),
+                )),
+            )
+        })
+    }
 
-        gs.get_key_values()
-            .into_iter()
-            .filter(|(k, _)| {
-                let (task_index, _) = k;
-                task_index % ctx.task_info.parallelism == ctx.task_info.task_index
-            })
-            .for_each(|(_, v)| {
-                self.inputs.insert(self.next_id, Some(v.clone()));
-                self.futures.push_back((self.udf)(
-                    self.next_id,
-                    v.value.clone(),
-                    self.udf_context.clone(),
-                ));
-                self.next_id += 1;
-            });
+    async fn send(&mut self, row: RecordBatch, args: Vec<ArrayRef>) {
+        let udf = self
+            .udf
+            .clone()
+            .expect("async UDF should be loaded on start");
+        let future = Self::call(udf, self.next_id(), args, self.timeout, self.retries);
+        self.futures.lock().await.push_back(future);
+        self.inputs.push_back(Some(row));
+        self.in_flight += 1;
     }
 
-    async fn process_element(
-        &mut self,
-        record: &Record<InKey, InT>,
-        _ctx: &mut Context<InKey, OutT>,
-    ) {
-        self.inputs.push_back(Some(record.clone()));
-
-        self.futures.push_back((self.udf)(
-            self.next_id,
-            record.value.clone(),
-            self.udf_context.clone(),
-        ));
-        self.next_id += 1;
+    async fn send_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        let args = self.evaluate_args(&batch);
+        for i in 0..batch.num_rows() {
+            while self.in_flight >= self.max_concurrency {
+                let results = next_results(self.futures.clone()).await;
+                self.handle_results(results, ctx).await;
+            }
+
+            self.send(
+                batch.slice(i, 1),
+                args.iter().map(|arg| arg.slice(i, 1)).collect(),
+            )
+            .await;
+        }
     }
 
-    async fn on_collect(&mut self, id: usize, ctx: &mut Context<InKey, OutT>) {
-        // mark the input as collected by setting it to None,
-        // then pop all the Nones from the front of the queue
-        let index = self.inputs.len() - (self.next_id - id);
-        self.inputs[index] = None;
+    /// Emits the rows for completed calls, along with any watermarks that were waiting on them
+    async fn handle_results(&mut self, results: Results, ctx: &mut ArrowContext) {
+        self.in_flight -= results.len();
+
+        let mut rows = Vec::with_capacity(results.len());
+        let mut values = Vec::with_capacity(results.len());
+        for (id, value) in results {
+            let value = match value {
+                Ok(value) => value,
+                Err(error) => {
+                    // the row is left in flight, so that it's retried when the job restarts from
+                    // its last checkpoint
+                    if !self.failed {
+                        self.failed = true;
+                        ctx.fail_task(error).await;
+                    }
+                    continue;
+                }
+            };
+
+            rows.push(
+                self.inputs[id - self.first_id]
+                    .take()
+                    .expect("received a result for a row that was already emitted"),
+            );
+            values.push(value);
+        }
+
+        if rows.is_empty() {
+            return;
+        }
+
+        let mut columns = concat_batches(&self.input_schema.schema, &rows)
+            .unwrap()
+            .columns()
+            .to_vec();
+        columns.push(concat(&values.iter().map(|v| v.as_ref()).collect::<Vec<_>>()).unwrap());
+
+        ctx.collect(RecordBatch::try_new(self.output_schema.schema.clone(), columns).unwrap())
+            .await;
+
         while let Some(None) = self.inputs.front() {
             self.inputs.pop_front();
-            if let Some((index, _watermark)) = self.watermarks.front() {
-                // if index is 3, then that means that the watermark came in after 3 elements.
-                // if we've read in 9 more, then next_id would be 12, and 12 - 3 = 9
-                if *index + self.inputs.len() == self.next_id {
-                    let (_index, watermark) = self.watermarks.pop_front().unwrap();
-                    ctx.broadcast(arroyo_types::Message::Watermark(watermark))
-                        .await;
-                }
+            self.first_id += 1;
+        }
+
+        while let Some((id, _)) = self.watermarks.front() {
+            if *id > self.first_id {
+                break;
             }
+            let (_, watermark) = self.watermarks.pop_front().unwrap();
+            ctx.broadcast(ArrowMessage::Signal(SignalMessage::Watermark(watermark)))
+                .await;
         }
     }
+}
 
-    async fn handle_checkpoint(&mut self, _: &CheckpointBarrier, ctx: &mut Context<InKey, OutT>) {
-        let mut gs = ctx
-            .state
-            .get_global_keyed_state::<(usize, usize), Record<InKey, InT>>('a')
-            .await;
+pub struct AsyncMapConstructor;
+
+impl OperatorConstructor for AsyncMapConstructor {
+    type ConfigT = AsyncUdfOperator;
+
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let input_schema: ArroyoSchema = config
+            .input_schema
+            .ok_or_else(|| anyhow!("missing input schema"))?
+            .try_into()?;
+        let output_schema: ArroyoSchema = config
+            .output_schema
+            .ok_or_else(|| anyhow!("missing output schema"))?
+            .try_into()?;
+        let udf_config: DylibUdfConfig = config
+            .udf
+            .ok_or_else(|| anyhow!("missing async UDF config"))?
+            .into();
+
+        let arg_types = udf_config
+            .arg_types
+            .iter()
+            .map(DataType::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let arg_exprs = config
+            .arg_exprs
+            .iter()
+            .map(|expr| {
+                let expr = PhysicalExprNode::decode(&mut expr.as_slice())?;
+                Ok(parse_physical_expr(
+                    &expr,
+                    registry.as_ref(),
+                    &input_schema.schema,
+                )?)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
 
-        for (i, record) in self.inputs.iter().filter_map(|x| x.clone()).enumerate() {
-            let key = (ctx.task_info.task_index, i);
-            gs.insert(key, record).await;
+        if config.max_concurrency == 0 {
+            bail!(
+                "max_concurrency for async UDF {} must be greater than 0",
+                config.udf_name
+            );
         }
+
+        Ok(OperatorNode::from_operator(Box::new(AsyncMapOperator {
+            udf_name: config.udf_name,
+            udf_config,
+            udf: None,
+            arg_exprs,
+            arg_types,
+            input_schema,
+            output_schema,
+            timeout: Duration::from_micros(config.timeout_micros),
+            retries: config.retries,
+            max_concurrency: config.max_concurrency as usize,
+            failed: false,
+            futures: Arc::new(Mutex::new(FuturesWrapper::new(config.ordered))),
+            in_flight: 0,
+            first_id: 0,
+            inputs: VecDeque::new(),
+            watermarks: VecDeque::new(),
+        })))
     }
+}
 
-    async fn handle_watermark(
-        &mut self,
-        watermark: arroyo_types::Watermark,
-        _ctx: &mut Context<InKey, OutT>,
-    ) {
-        self.watermarks.push_back((self.next_id, watermark));
+#[async_trait]
+impl ArrowOperator for AsyncMapOperator {
+    fn name(&self) -> String {
+        format!("AsyncUdf<{}>", self.udf_name)
     }
 
-    fn tables(&self) -> Vec<TableDescriptor> {
-        vec![arroyo_state::global_table("a", "AsyncMapOperator state")]
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        global_table_config("a", "async UDF state")
     }
 
-    async fn on_close(
-        &mut self,
-        ctx: &mut Context<InKey, OutT>,
-        final_message: &Option<Message<InKey, OutT>>,
-    ) {
-        if let Some(Message::EndOfData) = final_message {
-            debug!(
-                "AsyncMapOperator end of data with {} futures",
-                self.futures.len()
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        self.udf = Some(Arc::new(
+            AsyncUdfDylib::init(&self.udf_name, &self.udf_config).await,
+        ));
+
+        let gs = ctx
+            .table_manager
+            .get_global_keyed_state::<usize, Vec<u8>>("a")
+            .await
+            .expect("should have async UDF state table");
+
+        let restored: Vec<RecordBatch> = gs
+            .get_all()
+            .iter()
+            .filter(|(task_index, _)| {
+                **task_index % ctx.task_info.parallelism == ctx.task_info.task_index
+            })
+            .flat_map(|(_, data)| {
+                StreamReader::try_new(data.as_slice(), None)
+                    .expect("invalid async UDF state")
+                    .map(|batch| batch.expect("invalid async UDF state"))
+            })
+            .collect();
+
+        for batch in restored.into_iter().filter(|b| b.num_rows() > 0) {
+            info!(
+                "Retrying {} in-flight calls to {} from checkpoint",
+                batch.num_rows(),
+                self.udf_name
             );
-            while let Some((id, result)) = self.futures.next().await {
-                self.handle_future(id, result, ctx).await;
+            let args = self.evaluate_args(&batch);
+            for i in 0..batch.num_rows() {
+                self.send(
+                    batch.slice(i, 1),
+                    args.iter().map(|arg| arg.slice(i, 1)).collect(),
+                )
+                .await;
             }
         }
-        self.udf_context.close().await;
     }
 
-    async fn handle_future(
+    async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        self.send_batch(batch, ctx).await;
+    }
+
+    fn future_to_poll(
         &mut self,
-        id: usize,
-        result: Result<OutT, Elapsed>,
-        ctx: &mut Context<InKey, OutT>,
-    ) {
-        match result {
-            Ok(value) => {
-                let index = self.inputs.len() - (self.next_id - id);
-                let input = self.inputs[index].clone().unwrap();
-                ctx.collector
-                    .collect(Record {
-                        timestamp: input.timestamp,
-                        key: input.key.clone(),
-                        value,
-                    })
-                    .await;
-                self.on_collect(id, ctx).await;
-            }
-            Err(_e) => {
-                if self.futures.is_ordered() {
-                    unimplemented!(
-                        "Ordered Async UDF timed out, currently panic to preserve ordering"
-                    );
-                }
-                warn!("Unordered Async UDF timed out, retrying");
-                self.futures.push_back((self.udf)(
-                    id,
-                    self.inputs[id].clone().unwrap().value.clone(),
-                    self.udf_context.clone(),
-                ));
-            }
+    ) -> Option<Pin<Box<dyn Future<Output = Box<dyn Any + Send>> + Send>>> {
+        if self.in_flight == 0 {
+            return None;
         }
+
+        let futures = self.futures.clone();
+        Some(Box::pin(async move {
+            Box::new(next_results(futures).await) as Box<dyn Any + Send>
+        }))
+    }
+
+    async fn handle_future_result(&mut self, result: Box<dyn Any + Send>, ctx: &mut ArrowContext) {
+        let results: Box<Results> = result.downcast().expect("invalid data in future");
+        self.handle_results(*results, ctx).await;
     }
-}
 
-pub struct EmptyContext {}
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        _: &mut ArrowContext,
+    ) -> Option<Watermark> {
+        if self.inputs.is_empty() {
+            return Some(watermark);
+        }
 
-#[async_trait]
-impl UdfContext for EmptyContext {}
+        self.watermarks.push_back((self.next_id(), watermark));
+        None
+    }
+
+    async fn handle_checkpoint(&mut self, _: CheckpointBarrier, ctx: &mut ArrowContext) {
+        let rows: Vec<_> = self.inputs.iter().flatten().cloned().collect();
+        let batch = concat_batches(&self.input_schema.schema, &rows).unwrap();
+
+        let mut data = vec![];
+        {
+            let mut writer = StreamWriter::try_new(&mut data, &self.input_schema.schema).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let gs = ctx
+            .table_manager
+            .get_global_keyed_state::<usize, Vec<u8>>("a")
+            .await
+            .expect("should have async UDF state table");
+
+        gs.insert(ctx.task_info.task_index, data).await;
+    }
+
+    async fn on_close(&mut self, final_message: &Option<SignalMessage>, ctx: &mut ArrowContext) {
+        if let Some(SignalMessage::EndOfData) = final_message {
+            debug!(
+                "AsyncMapOperator end of data with {} calls in flight",
+                self.in_flight
+            );
+            while self.in_flight > 0 {
+                let results = next_results(self.futures.clone()).await;
+                self.handle_results(results, ctx).await;
+            }
+        }
+    }
+}
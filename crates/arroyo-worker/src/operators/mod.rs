@@ -1,2 +1,2 @@
-//pub mod async_map;
+pub mod async_map;
 pub mod watermark_generator;
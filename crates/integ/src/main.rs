@@ -181,6 +181,10 @@ pub async fn main() {
                 source_name
             ),
             udfs: None,
+            savepoint_id: None,
+            allow_dropped_state: None,
+            operator_parallelism: None,
+            restart_strategy: None,
         },
     )
     .await
@@ -221,6 +225,9 @@ pub async fn main() {
             checkpoint_interval_micros: None,
             parallelism: None,
             stop: Some(Some(StopType::Checkpoint)),
+            autoscaling: None,
+            operator_parallelism: None,
+            restart_strategy: None,
         },
     )
     .await
@@ -228,6 +228,10 @@ export interface components {
       fail: Record<string, never>;
     }, {
       drop: Record<string, never>;
+    }, {
+      dead_letter: {
+        target: components["schemas"]["DeadLetterTarget"];
+      };
     }]>;
     Checkpoint: {
       backend: string;
@@ -305,7 +309,7 @@ export interface components {
       schema?: components["schemas"]["ConnectionSchema"] | null;
     };
     /** @enum {string} */
-    ConnectionType: "source" | "sink";
+    ConnectionType: "source" | "sink" | "lookup";
     Connector: {
       connectionConfig?: string | null;
       customSchemas: boolean;
@@ -323,6 +327,26 @@ export interface components {
     ConnectorCollection: {
       data: (components["schemas"]["Connector"])[];
     };
+    /** @description Where messages that fail to deserialize are written with `bad_data = 'dead_letter'` */
+    DeadLetterTarget: OneOf<[{
+      kafka: {
+        bootstrap_servers: string;
+        /**
+         * @description additional librdkafka configs for the producer, for example to set up SASL or TLS;
+         * values may reference environment variables like `{{ KAFKA_PASSWORD }}` or secrets
+         */
+        client_configs?: {
+          [key: string]: string;
+        };
+        topic: string;
+      };
+    }, {
+      filesystem: {
+        path: string;
+      };
+    }]>;
+    /** @enum {string} */
+    DecimalEncoding: "number" | "string";
     FieldType: OneOf<[{
       primitive: components["schemas"]["PrimitiveType"];
     }, {
@@ -396,6 +420,7 @@ export interface components {
     JsonFormat: {
       confluentSchemaRegistry?: boolean;
       debezium?: boolean;
+      decimalEncoding?: components["schemas"]["DecimalEncoding"];
       includeSchema?: boolean;
       /** Format: int32 */
       schemaId?: number | null;
@@ -445,7 +470,11 @@ export interface components {
       limit?: number | null;
       starting_after?: string | null;
     };
-    ParquetFormat: Record<string, never>;
+    /** @enum {string} */
+    ParquetCompression: "uncompressed" | "snappy" | "gzip" | "zstd" | "lz4";
+    ParquetFormat: {
+      compression?: components["schemas"]["ParquetCompression"];
+    };
     Pipeline: {
       action?: components["schemas"]["StopType"] | null;
       actionInProgress: boolean;
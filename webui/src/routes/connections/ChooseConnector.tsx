@@ -27,7 +27,13 @@ export function ChooseConnector() {
     return (
       <Card key={c.name} size={'md'} maxW={400}>
         <Text fontSize={'12px'} p={2} w={'100%'} bgColor={'gray.600'} color={'blue.100'}>
-          {c.source && c.sink ? 'source / sink' : c.source ? 'source' : 'sink'}
+          {c.source && c.sink
+            ? 'source / sink'
+            : c.source
+            ? 'source'
+            : c.sink
+            ? 'sink'
+            : 'lookup'}
         </Text>
 
         <CardHeader>
@@ -16,6 +16,7 @@ const CatalogTab: React.FC = () => {
 
   const sources = connectionTables.filter(s => s.tableType == 'source');
   const sinks = connectionTables.filter(s => s.tableType == 'sink');
+  const lookups = connectionTables.filter(s => s.tableType == 'lookup');
 
   // Since we only fetch the first page of connection tables,
   // display a warning if there are too many to be shown.
@@ -60,6 +61,7 @@ const CatalogTab: React.FC = () => {
       {catalogTruncatedWarning}
       {catalogType('Source', sources)}
       {catalogType('Sink', sinks)}
+      {catalogType('Lookup', lookups)}
 
       <Spacer />
       <Box p={4} borderTop={'1px solid'} borderColor={'gray.500'}>